@@ -0,0 +1,32 @@
+import test from 'ava'
+import { PostgresInstance } from '../index.js'
+
+test.serial('connections opened by pg-embedded carry an application_name tag', async (t) => {
+  const pg = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+
+  try {
+    await pg.start()
+    const result = await pg.executeSql('SHOW application_name;', { tuplesOnly: true })
+    t.is(result.stdout.trim(), `pg-embedded/${pg.instanceId}`)
+  } finally {
+    await pg.cleanup()
+  }
+})
+
+test.serial('the application_name tag is configurable and can be disabled', async (t) => {
+  const tagged = new PostgresInstance({ username: 'postgres', password: 'password', port: 0, applicationName: 'fixtures' })
+  const untagged = new PostgresInstance({ username: 'postgres', password: 'password', port: 0, applicationName: '' })
+
+  try {
+    await tagged.start()
+    await untagged.start()
+    const custom = await tagged.executeSql('SHOW application_name;', { tuplesOnly: true })
+    t.is(custom.stdout.trim(), 'fixtures')
+    // psql reports itself when no application_name is set
+    const none = await untagged.executeSql('SHOW application_name;', { tuplesOnly: true })
+    t.is(none.stdout.trim(), 'psql')
+  } finally {
+    await untagged.cleanup()
+    await tagged.cleanup()
+  }
+})
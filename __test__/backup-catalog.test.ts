@@ -0,0 +1,48 @@
+import test from 'ava'
+import fs from 'node:fs/promises'
+import os from 'node:os'
+import path from 'node:path'
+import { PgDumpFormat, PostgresInstance } from '../index.js'
+
+test.serial('dumps are recorded in the backup catalog and pruned by policy', async (t) => {
+  const instance = new PostgresInstance({ databaseName: 'catalog_app', password: 'password', port: 0 })
+  const dir = await fs.mkdtemp(path.join(os.tmpdir(), 'pg-embedded-catalog-'))
+  const first = path.join(dir, 'first.dump')
+  const second = path.join(dir, 'second.dump')
+
+  try {
+    await instance.start()
+    t.deepEqual(instance.listBackups(), [])
+
+    await instance.createDump({ file: first, format: PgDumpFormat.Custom })
+    // Dry runs and dumps to stdout are not recorded
+    await instance.createDump({ file: path.join(dir, 'dry.dump'), tool: { dryRun: true } })
+    await instance.createDump({ format: PgDumpFormat.Plain })
+    await instance.createDump({ file: second, format: PgDumpFormat.Custom })
+
+    const backups = instance.listBackups()
+    t.deepEqual(
+      backups.map((backup) => backup.path),
+      [second, first],
+    )
+    t.like(backups[0], { kind: 'dump', database: 'catalog_app', dataDir: instance.dataDir })
+    t.true(backups[0].sizeBytes > 0)
+    t.regex(backups[0].lsn ?? '', /^[0-9A-F]+\/[0-9A-F]+$/)
+
+    const removed = instance.pruneBackups({ keepLast: 1 })
+    t.deepEqual(
+      removed.map((backup) => backup.path),
+      [first],
+    )
+    await t.throwsAsync(() => fs.access(first))
+    await fs.access(second)
+
+    // A backup deleted by other means is dropped from the catalog
+    await fs.rm(second)
+    t.is(instance.pruneBackups().length, 1)
+    t.deepEqual(instance.listBackups(), [])
+  } finally {
+    await instance.cleanup()
+    await fs.rm(dir, { recursive: true, force: true })
+  }
+})
@@ -0,0 +1,49 @@
+import test from 'ava'
+import { PostgresInstance } from '../index.js'
+
+const count = async (instance: PostgresInstance, table: string) => {
+  const result = await instance.executeSql(`SELECT count(*) FROM ${table}`, { tuplesOnly: true, noAlign: true })
+  return result.stdout.trim()
+}
+
+test.serial('beginTransaction() changes are visible to others only after commit()', async (t) => {
+  const instance = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+
+  try {
+    await instance.start()
+    await instance.executeSql('CREATE TABLE users (id int PRIMARY KEY, name text)', {})
+
+    const tx = await instance.beginTransaction()
+    await tx.query('INSERT INTO users VALUES ($1, $2)', [1, 'alice'])
+    const inside = await tx.query('SELECT count(*) FROM users')
+    t.deepEqual(inside.rows, [['1']])
+    t.is(await count(instance, 'users'), '0')
+
+    await tx.commit()
+    t.is(await count(instance, 'users'), '1')
+
+    // Ending a transaction twice does nothing; querying an ended one fails
+    await tx.commit()
+    await tx.rollback()
+    await t.throwsAsync(() => tx.query('SELECT 1'), { message: /already ended/ })
+  } finally {
+    await instance.cleanup()
+  }
+})
+
+test.serial('beginTransaction() changes are discarded by rollback()', async (t) => {
+  const instance = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+
+  try {
+    await instance.start()
+    await instance.executeSql('CREATE TABLE events (id int)', {})
+
+    const tx = await instance.beginTransaction()
+    await tx.query('INSERT INTO events VALUES (1), (2)')
+    await tx.rollback()
+
+    t.is(await count(instance, 'events'), '0')
+  } finally {
+    await instance.cleanup()
+  }
+})
@@ -0,0 +1,88 @@
+import test from 'ava'
+import { mkdtempSync, readFileSync, rmSync, writeFileSync } from 'node:fs'
+import { tmpdir } from 'node:os'
+import { join } from 'node:path'
+import { PostgresInstance, runBenchmarkSuite } from '../index.js'
+
+test.serial('runPgbench initializes the tables and reports throughput', async (t) => {
+  const pg = new PostgresInstance({ port: 0 })
+
+  try {
+    await pg.start()
+    const result = await pg.runPgbench({ initialize: true, clients: 2, transactions: 20 })
+    t.is(result.transactions, 40)
+    t.true(result.tps > 0)
+    t.true((result.latencyAvgMs ?? 0) > 0)
+
+    const custom = await pg.runPgbench({ script: 'SELECT 1;', transactions: 5 })
+    t.is(custom.transactions, 5)
+
+    await t.throwsAsync(() => pg.runPgbench({ transactions: 5, durationSeconds: 1 }), {
+      message: /either a number of transactions or a duration/,
+    })
+  } finally {
+    await pg.cleanup()
+  }
+})
+
+test.serial('runBenchmarkSuite stores results and flags regressions against a baseline', async (t) => {
+  const pg = new PostgresInstance({ port: 0 })
+  const dir = mkdtempSync(join(tmpdir(), 'pg-embedded-bench-'))
+
+  try {
+    await pg.start()
+    const outputFile = join(dir, 'current.json')
+    const report = await runBenchmarkSuite(pg, {
+      workloads: [
+        { name: 'tpcb', initialize: true, transactions: 20 },
+        { name: 'select', script: 'SELECT 1;', transactions: 20 },
+      ],
+      outputFile,
+    })
+    t.true(report.passed)
+    t.deepEqual(
+      report.workloads.map((workload) => workload.name),
+      ['tpcb', 'select'],
+    )
+    t.is(report.environment.platform, process.platform)
+    const stored = JSON.parse(readFileSync(outputFile, 'utf8'))
+    t.is(stored.workloads.length, 2)
+
+    // A baseline ten times faster than anything measured makes every workload regress
+    const baselineFile = join(dir, 'baseline.json')
+    stored.workloads = stored.workloads.map((workload: { tps: number }) => ({
+      ...workload,
+      tps: workload.tps * 10,
+    }))
+    writeFileSync(baselineFile, JSON.stringify(stored))
+    const compared = await runBenchmarkSuite(pg, {
+      workloads: [{ name: 'select', script: 'SELECT 1;', transactions: 20 }],
+      baselineFile,
+    })
+    t.false(compared.passed)
+    t.true(compared.comparisons[0].regressed)
+    t.true(compared.comparisons[0].tpsChangePercent < -10)
+
+    await t.throwsAsync(
+      () =>
+        runBenchmarkSuite(pg, {
+          workloads: [{ name: 'select', script: 'SELECT 1;', transactions: 20 }],
+          baselineFile,
+          failOnRegression: true,
+        }),
+      { message: /Benchmark regressions/ },
+    )
+
+    // Updating the baseline in place still compares against the previous run
+    const updated = await runBenchmarkSuite(pg, {
+      workloads: [{ name: 'select', script: 'SELECT 1;', transactions: 20 }],
+      baselineFile,
+      outputFile: baselineFile,
+    })
+    t.false(updated.passed)
+    t.is(JSON.parse(readFileSync(baselineFile, 'utf8')).workloads[0].tps, updated.workloads[0].tps)
+  } finally {
+    await pg.cleanup()
+    rmSync(dir, { recursive: true, force: true })
+  }
+})
@@ -0,0 +1,36 @@
+import test from 'ava'
+import { type DdlCommand, PostgresInstance } from '../index.js'
+
+test.serial('captureDdl() reports the DDL commands run in the database', async (t) => {
+  const instance = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+
+  try {
+    await instance.start()
+    const commands: DdlCommand[] = []
+    const capture = await instance.captureDdl(undefined, (command) => commands.push(command))
+
+    await instance.executeSql('CREATE TABLE users (id int, name text)', {})
+    await instance.executeSql('CREATE INDEX users_name_idx ON users (name); DROP TABLE users;', {})
+    await capture.stop()
+    await new Promise((resolve) => setImmediate(resolve))
+
+    t.deepEqual(
+      commands.map(({ commandTag, objectIdentity }) => [commandTag, objectIdentity]),
+      [
+        ['CREATE TABLE', 'public.users'],
+        ['CREATE INDEX', 'public.users_name_idx'],
+        ['DROP TABLE', 'public.users'],
+      ],
+    )
+    t.regex(commands[0].query, /CREATE TABLE users/)
+
+    // The triggers and the log table are gone
+    const triggers = await instance.executeSql("SELECT count(*) FROM pg_event_trigger WHERE evtname LIKE 'pg_embedded%'", {
+      tuplesOnly: true,
+      noAlign: true,
+    })
+    t.is(triggers.stdout.trim(), '0')
+  } finally {
+    await instance.cleanup()
+  }
+})
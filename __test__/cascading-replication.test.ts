@@ -0,0 +1,52 @@
+import test from 'ava'
+import { compareLsn, PostgresInstance, ServerRole } from '../index.js'
+
+test.serial('createReplica() on a standby builds a cascading replication tree', async (t) => {
+  const primary = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+  let tier1: PostgresInstance | undefined
+  let tier2: PostgresInstance | undefined
+
+  try {
+    await primary.start()
+    tier1 = await primary.createReplica({ name: 'tier1', slotName: 'tier1' })
+    tier2 = await tier1.createReplica({ name: 'tier2', slotName: 'tier2' })
+    t.is(await tier1.getRole(), ServerRole.Standby)
+    t.is(await tier2.getRole(), ServerRole.Standby)
+    t.is(tier2.getConfigFileValue('primary_slot_name'), 'tier2')
+
+    await primary.createDatabase('cascaded')
+    const lsn = await primary.getCurrentLsn()
+    for (let attempt = 0; attempt < 100; attempt++) {
+      if (compareLsn(await tier2.getCurrentLsn(), lsn) >= 0) break
+      await new Promise((resolve) => setTimeout(resolve, 100))
+    }
+    t.true(await tier2.databaseExists('cascaded'))
+
+    // Each tier streams from the one above it through its own slot
+    const slots = await tier1.executeSql(
+      'SELECT slot_name, active FROM pg_replication_slots',
+      { tuplesOnly: true, noAlign: true, fieldSeparator: ',' },
+    )
+    t.is(slots.stdout.trim(), 'tier2,t')
+
+    // Cleaning up a replica drops its slot, so the upstream stops keeping WAL for it
+    await tier2.cleanup()
+    const remaining = await tier1.executeSql('SELECT count(*) FROM pg_replication_slots', { tuplesOnly: true })
+    t.is(remaining.stdout.trim(), '0')
+  } finally {
+    await tier2?.cleanup()
+    await tier1?.cleanup()
+    await primary.cleanup()
+  }
+})
+
+test.serial('createReplica() rejects an invalid slotName', async (t) => {
+  const primary = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+
+  try {
+    await primary.start()
+    await t.throwsAsync(() => primary.createReplica({ slotName: 'Tier-1' }), { message: /slotName/ })
+  } finally {
+    await primary.cleanup()
+  }
+})
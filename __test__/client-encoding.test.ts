@@ -0,0 +1,20 @@
+import test from 'ava'
+import { ByteaOutput, PostgresInstance } from '../index.js'
+
+test.serial('clientEncoding and byteaOutput apply to the psql session', async (t) => {
+  const pg = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+
+  try {
+    await pg.start()
+
+    const encoding = await pg.executeSql('SHOW client_encoding;', { tuplesOnly: true, clientEncoding: 'LATIN1' })
+    t.is(encoding.stdout.trim(), 'LATIN1')
+
+    const hex = await pg.executeSql("SELECT 'abc'::bytea;", { tuplesOnly: true, byteaOutput: ByteaOutput.Hex })
+    t.is(hex.stdout.trim(), '\\x616263')
+    const escape = await pg.executeSql("SELECT 'abc'::bytea;", { tuplesOnly: true, byteaOutput: ByteaOutput.Escape })
+    t.is(escape.stdout.trim(), 'abc')
+  } finally {
+    await pg.cleanup()
+  }
+})
@@ -0,0 +1,69 @@
+import test from 'ava'
+import fs from 'node:fs/promises'
+import path from 'node:path'
+import { PgBasebackupTool, PgBasebackupWalMethod, PostgresInstance, applyConfigToCluster, compareLsn } from '../index.js'
+
+test.serial('applyConfigToCluster() reloads settings and restarts standby then primary', async (t) => {
+  const primary = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+  const standbyDir = path.resolve(`data/cluster-config-standby-${Date.now()}`)
+  let standby: PostgresInstance | undefined
+
+  try {
+    await primary.start()
+    const info = primary.connectionInfo
+    const backup = await new PgBasebackupTool({
+      connection: { host: info.host, port: info.port, username: info.username, password: info.password },
+      programDir: path.join(primary.programDir, 'bin'),
+      config: { pgdata: standbyDir, walMethod: PgBasebackupWalMethod.Stream },
+    }).execute()
+    t.is(backup.exitCode, 0)
+    await fs.writeFile(path.join(standbyDir, 'standby.signal'), '')
+    await fs.appendFile(
+      path.join(standbyDir, 'postgresql.auto.conf'),
+      `primary_conninfo = '${info.connectionString}'\n`,
+    )
+    standby = new PostgresInstance({ username: 'postgres', password: 'password', port: 0, dataDir: standbyDir })
+    await standby.start(false)
+
+    const pending = await applyConfigToCluster([primary, standby], { work_mem: '12MB', max_connections: '150' })
+    t.deepEqual(pending, ['max_connections'])
+    for (const instance of [primary, standby]) {
+      const workMem = await instance.executeSql('SHOW work_mem;', { tuplesOnly: true })
+      t.is(workMem.stdout.trim(), '12MB')
+    }
+
+    await applyConfigToCluster([standby, primary], { max_connections: '150' }, { rollingRestart: true })
+    for (const instance of [primary, standby]) {
+      const maxConnections = await instance.executeSql('SHOW max_connections;', { tuplesOnly: true })
+      t.is(maxConnections.stdout.trim(), '150')
+    }
+
+    await primary.executeSql('CREATE TABLE after_restart (id int); INSERT INTO after_restart VALUES (1);', {})
+    const target = await primary.getCurrentLsn()
+    const deadline = Date.now() + 30_000
+    while (compareLsn(await standby.getCurrentLsn(), target) < 0 && Date.now() < deadline) {
+      await new Promise((resolve) => setTimeout(resolve, 100))
+    }
+    const replicated = await standby.executeSql('SELECT count(*) FROM after_restart;', { tuplesOnly: true })
+    t.is(replicated.stdout.trim(), '1')
+  } finally {
+    await standby?.cleanup()
+    await primary.cleanup()
+    await fs.rm(standbyDir, { recursive: true, force: true })
+  }
+})
+
+test.serial('applyConfigToCluster() requires exactly one primary', async (t) => {
+  const first = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+  const second = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+  try {
+    await first.start()
+    await second.start()
+    await t.throwsAsync(() => applyConfigToCluster([first, second], { work_mem: '8MB' }), {
+      message: /exactly one primary/,
+    })
+  } finally {
+    await second.cleanup()
+    await first.cleanup()
+  }
+})
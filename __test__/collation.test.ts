@@ -0,0 +1,36 @@
+import test from 'ava'
+import { PostgresInstance } from '../index.js'
+
+test.serial('icuLocale initializes the cluster with the ICU locale provider', async (t) => {
+  const pg = new PostgresInstance({ port: 0, icuLocale: 'en-US' })
+
+  try {
+    await pg.start()
+    const provider = await pg.executeSql(
+      'SELECT datlocprovider, datlocale FROM pg_database WHERE datname = current_database();',
+      { tuplesOnly: true, csv: true },
+    )
+    t.is(provider.stdout.trim(), 'i,en-US')
+    t.deepEqual(await pg.checkCollationVersionMismatch(), [])
+  } finally {
+    await pg.cleanup()
+  }
+})
+
+test.serial('checkCollationVersionMismatch() reports outdated collation versions', async (t) => {
+  const pg = new PostgresInstance({ port: 0, icuLocale: 'en-US' })
+
+  try {
+    await pg.start()
+    await pg.executeSql("UPDATE pg_collation SET collversion = '0.1' WHERE collname = 'en-x-icu';", {})
+
+    const mismatches = await pg.checkCollationVersionMismatch()
+    t.is(mismatches.length, 1)
+    t.is(mismatches[0].collation, 'en-x-icu')
+    t.false(mismatches[0].databaseDefault)
+    t.is(mismatches[0].recordedVersion, '0.1')
+    t.truthy(mismatches[0].actualVersion)
+  } finally {
+    await pg.cleanup()
+  }
+})
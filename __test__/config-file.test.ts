@@ -0,0 +1,34 @@
+import test from 'ava'
+import fs from 'node:fs/promises'
+import path from 'node:path'
+import { PostgresInstance } from '../index.js'
+
+test.serial('setConfigFileValue() writes settings idempotently to conf.d', async (t) => {
+  const dataDir = path.resolve(`data/config-file-${Date.now()}`)
+  const pg = new PostgresInstance({ port: 0, dataDir })
+
+  try {
+    t.throws(() => pg.getConfigFileValue('work_mem'), { message: /not initialized/ })
+    await pg.setup()
+
+    pg.setConfigFileValue('work_mem', '12MB')
+    pg.setConfigFileValue('work_mem', '12MB')
+    t.is(pg.getConfigFileValue('work_mem'), '12MB')
+
+    const mainConf = await fs.readFile(path.join(dataDir, 'postgresql.conf'), 'utf8')
+    t.is(mainConf.match(/^include_dir = 'conf\.d'$/gm)?.length, 1)
+    const managed = await fs.readFile(path.join(dataDir, 'conf.d', 'pg-embedded.conf'), 'utf8')
+    t.is(managed, "work_mem = '12MB'\n")
+
+    await pg.start(false)
+    const workMem = await pg.executeSql('SHOW work_mem;', { tuplesOnly: true })
+    t.is(workMem.stdout.trim(), '12MB')
+
+    pg.setConfigFileValue('work_mem', null)
+    t.not(pg.getConfigFileValue('work_mem'), '12MB')
+    t.throws(() => pg.setConfigFileValue('work_mem = 1', 'x'), { message: /Invalid setting name/ })
+  } finally {
+    await pg.cleanup()
+    await fs.rm(dataDir, { recursive: true, force: true })
+  }
+})
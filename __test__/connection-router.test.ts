@@ -0,0 +1,56 @@
+import test from 'ava'
+import fs from 'node:fs/promises'
+import path from 'node:path'
+import { ConnectionRouter, PgBasebackupTool, PgBasebackupWalMethod, PostgresInstance } from '../index.js'
+
+test.serial('ConnectionRouter sends writes to the primary and reads to healthy replicas', async (t) => {
+  const primary = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+  const standbyDirs = [0, 1].map((i) => path.resolve(`data/router-standby-${i}-${Date.now()}`))
+  const standbys: PostgresInstance[] = []
+
+  try {
+    await primary.start()
+    const info = primary.connectionInfo
+    for (const standbyDir of standbyDirs) {
+      const backup = await new PgBasebackupTool({
+        connection: { host: info.host, port: info.port, username: info.username, password: info.password },
+        programDir: path.join(primary.programDir, 'bin'),
+        config: { pgdata: standbyDir, walMethod: PgBasebackupWalMethod.Stream },
+      }).execute()
+      t.is(backup.exitCode, 0)
+      await fs.writeFile(path.join(standbyDir, 'standby.signal'), '')
+      await fs.appendFile(
+        path.join(standbyDir, 'postgresql.auto.conf'),
+        `primary_conninfo = '${info.connectionString}'\n`,
+      )
+      const standby = new PostgresInstance({ username: 'postgres', password: 'password', port: 0, dataDir: standbyDir })
+      standbys.push(standby)
+      await standby.start(false)
+    }
+
+    const [first, second] = standbys.map((standby) => standby.connectionInfo.connectionString)
+    const router = new ConnectionRouter({ primary: info.connectionString, replicas: [first, second] })
+    t.is(router.writeUri(), info.connectionString)
+    t.deepEqual([await router.readUri(), await router.readUri(), await router.readUri()], [first, second, first])
+
+    router.markUnhealthy(first)
+    t.deepEqual(router.getHealthyReplicas(), [second])
+    t.is(await router.readUri(), second)
+    t.is(await router.readUri(), second)
+
+    await standbys[1].stop()
+    t.is(await router.readUri(), info.connectionString)
+
+    router.markHealthy(first)
+    t.is(await router.readUri(), first)
+    t.throws(() => router.markUnhealthy(info.connectionString), { message: /not a replica/ })
+  } finally {
+    for (const standby of standbys) {
+      await standby.cleanup()
+    }
+    await primary.cleanup()
+    for (const standbyDir of standbyDirs) {
+      await fs.rm(standbyDir, { recursive: true, force: true })
+    }
+  }
+})
@@ -1,5 +1,5 @@
 import test from 'ava'
-import { PostgresInstance, InstanceState } from '../index.js'
+import { computeConfigHash, PostgresInstance, InstanceState } from '../index.js'
 
 test('PostgresInstance can be created with connection settings', (t) => {
   const instance = new PostgresInstance({
@@ -54,3 +54,36 @@ test('Connection info throws error when instance is stopped', (t) => {
   t.truthy(error)
   t.true(error.message.includes('not running'))
 })
+
+test('connectionCacheTtlSeconds is part of the config hash', (t) => {
+  const cached = new PostgresInstance({ port: 5434, username: 'postgres', password: 'postgres' })
+  const uncached = new PostgresInstance({
+    port: 5434,
+    username: 'postgres',
+    password: 'postgres',
+    connectionCacheTtlSeconds: 0,
+  })
+
+  t.not(cached.getConfigHash(), uncached.getConfigHash())
+})
+
+test.serial('connectionCacheTtlSeconds: 0 disables the connection info cache', async (t) => {
+  const pg = new PostgresInstance({ port: 0, connectionCacheTtlSeconds: 0 })
+
+  try {
+    await pg.start()
+    t.truthy(pg.connectionInfo.connectionString)
+    t.false(pg.isConnectionCacheValid())
+  } finally {
+    await pg.cleanup()
+  }
+})
+
+test('computeConfigHash() matches getConfigHash() and covers data directories', (t) => {
+  const settings = { port: 5435, username: 'postgres', password: 'postgres', version: '>=16' }
+  const instance = new PostgresInstance(settings)
+
+  t.is(computeConfigHash(settings), instance.getConfigHash())
+  t.not(computeConfigHash({ ...settings, dataDir: './data/hash-a' }), computeConfigHash({ ...settings, dataDir: './data/hash-b' }))
+  t.not(computeConfigHash({ ...settings, serverConfig: { fsync: 'off' } }), computeConfigHash(settings))
+})
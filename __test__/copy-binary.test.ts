@@ -0,0 +1,58 @@
+import test from 'ava'
+import { PostgresInstance } from '../index.js'
+
+test.serial('copyInBinary() and copyOutBinary() move rows in the binary COPY format', async (t) => {
+  const pg = new PostgresInstance({ port: 0 })
+
+  try {
+    await pg.start()
+    const client = await pg.connect()
+    try {
+      await client.query(
+        'CREATE TABLE events (id int, big bigint, active boolean, score float8, name text, data jsonb, key uuid, blob bytea, total numeric)',
+      )
+      const rows = Array.from({ length: 5000 }, (_, i) => [
+        i,
+        '9007199254740993',
+        i % 2 === 0,
+        i / 4,
+        i === 0 ? null : `event ${i}`,
+        JSON.stringify({ i }),
+        'a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11',
+        '\\x00ff',
+      ])
+      const columns = ['id', 'big', 'active', 'score', 'name', 'data', 'key', 'blob']
+
+      // numeric has no binary encoder, so it must be left out
+      await t.throwsAsync(client.copyInBinary('events', rows), { message: /numeric/ })
+      t.is(await client.copyInBinary('events', rows, columns), 5000)
+
+      const check = await client.query('SELECT count(*)::int AS count, sum(id)::int AS sum FROM events')
+      t.deepEqual(check.rows, [{ count: 5000, sum: (4999 * 5000) / 2 }])
+
+      const copied = await client.copyOutBinary('public.events', columns)
+      t.deepEqual(copied.columns, columns)
+      t.is(copied.rowCount, 5000)
+      t.deepEqual(copied.rows[1], {
+        id: 1,
+        big: '9007199254740993',
+        active: false,
+        score: 0.25,
+        name: 'event 1',
+        data: '{"i": 1}',
+        key: 'a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11',
+        blob: '\\x00ff',
+      })
+      t.is(copied.rows[0].name, null)
+
+      // A bad value is reported before anything is copied
+      await t.throwsAsync(client.copyInBinary('events', [['not a number']], ['id']), { message: /"id"/ })
+      const after = await client.query('SELECT count(*)::int AS count FROM events')
+      t.is(after.rows[0].count, 5000)
+    } finally {
+      await client.close()
+    }
+  } finally {
+    await pg.cleanup()
+  }
+})
@@ -0,0 +1,44 @@
+import test from 'ava'
+import { Readable } from 'node:stream'
+import { PostgresInstance } from '../index.js'
+
+const count = async (instance: PostgresInstance, table: string) => {
+  const result = await instance.executeSql(`SELECT count(*) FROM ${table}`, { tuplesOnly: true, noAlign: true })
+  return Number(result.stdout.trim())
+}
+
+test.serial('copyInto() loads strings, buffers and streams with COPY', async (t) => {
+  const instance = new PostgresInstance({ password: 'password', port: 0 })
+
+  try {
+    await instance.start()
+    await instance.executeSql('CREATE TABLE events (id int PRIMARY KEY, name text)', {})
+
+    t.is(await instance.copyInto('events', 'id,name\n1,first\n2,\n', { header: true }), 2)
+    t.is(await instance.copyInto('events', Buffer.from('3\tthird\n'), { format: 'text' }), 1)
+
+    // Chunks of a stream need not end at row boundaries
+    const rows = Array.from({ length: 10000 }, (_, i) => `${i + 100},event ${i}\n`).join('')
+    const chunks = rows.match(/[\s\S]{1,4096}/g) ?? []
+    t.is(await instance.copyInto('public.events', Readable.from(chunks)), 10000)
+    t.is(await count(instance, 'events'), 10003)
+
+    const nulls = await instance.executeSql('SELECT count(*) FROM events WHERE name IS NULL', {
+      tuplesOnly: true,
+      noAlign: true,
+    })
+    t.is(nulls.stdout.trim(), '1')
+
+    // Invalid data loads nothing
+    await t.throwsAsync(() => instance.copyInto('events', '4,ok\nfive,bad\n'), { message: /five/ })
+    await t.throwsAsync(() => instance.copyInto('missing_table', '1,x\n'), { message: /missing_table/ })
+    t.is(await count(instance, 'events'), 10003)
+
+    const writer = await instance.openCopyWriter('events', { columns: ['name', 'id'] })
+    await writer.write('aborted,20000\n')
+    await writer.abort()
+    t.is(await count(instance, 'events'), 10003)
+  } finally {
+    await instance.cleanup()
+  }
+})
@@ -0,0 +1,49 @@
+import test from 'ava'
+import fs from 'node:fs/promises'
+import os from 'node:os'
+import path from 'node:path'
+import { PassThrough } from 'node:stream'
+import { PostgresInstance } from '../index.js'
+
+test.serial('copyOut() exports tables and queries with COPY', async (t) => {
+  const instance = new PostgresInstance({ password: 'password', port: 0 })
+  const dir = await fs.mkdtemp(path.join(os.tmpdir(), 'pg-embedded-copy-out-'))
+
+  try {
+    await instance.start()
+    await instance.executeSql(
+      "CREATE TABLE results (id int PRIMARY KEY, name text, status text); INSERT INTO results VALUES (1, 'first', 'passed'), (2, NULL, 'failed')",
+      {},
+    )
+
+    t.is((await instance.copyOut('results', { header: true })).toString(), 'id,name,status\n1,first,passed\n2,,failed\n')
+    t.is((await instance.copyOut('public.results', { format: 'text', columns: ['name', 'id'] })).toString(), 'first\t1\n\\N\t2\n')
+    t.is((await instance.copyOut("SELECT id FROM results WHERE status = 'failed';")).toString(), '2\n')
+
+    await t.throwsAsync(() => instance.copyOut('missing_table'), { message: /missing_table/ })
+    await t.throwsAsync(() => instance.copyOut('SELECT id FROM results', { columns: ['id'] }), { message: /columns/ })
+
+    // Large exports arrive in several chunks
+    await instance.executeSql("INSERT INTO results SELECT i, 'row ' || i, 'passed' FROM generate_series(3, 20000) i", {})
+    const file = path.join(dir, 'results.csv')
+    const written = await instance.copyOutToFile('results', file)
+    const contents = await fs.readFile(file, 'utf8')
+    t.is(written, Buffer.byteLength(contents))
+    t.is(contents.split('\n').length, 20001)
+
+    const stream = new PassThrough()
+    const chunks: Buffer[] = []
+    stream.on('data', (chunk: Buffer) => chunks.push(chunk))
+    t.is(await instance.copyOutToStream('results', stream), written)
+    t.is(Buffer.concat(chunks).toString(), contents)
+
+    // A reader closed early ends the export
+    const reader = await instance.openCopyReader('results')
+    t.truthy(await reader.read())
+    await reader.close()
+    t.is((await instance.copyOut('SELECT count(*) FROM results')).toString(), '20000\n')
+  } finally {
+    await instance.cleanup()
+    await fs.rm(dir, { recursive: true, force: true })
+  }
+})
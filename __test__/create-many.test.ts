@@ -0,0 +1,48 @@
+import test from 'ava'
+import { PassThrough } from 'node:stream'
+import { PostgresInstance } from '../index.js'
+
+test.serial('createMany() starts instances with distinct ports and names', async (t) => {
+  const signalListeners = process.listenerCount('SIGINT')
+  const instances = await PostgresInstance.createMany(3, { port: 0, name: 'create-many' })
+
+  try {
+    t.is(instances.length, 3)
+    t.deepEqual(
+      instances.map((instance) => instance.name),
+      ['create-many-0', 'create-many-1', 'create-many-2'],
+    )
+    t.true(instances.every((instance) => instance.isHealthy()))
+
+    const ports = instances.map((instance) => instance.getPort())
+    t.is(new Set(ports).size, 3)
+    t.is(new Set(instances.map((instance) => instance.dataDir)).size, 3)
+    t.is(new Set(instances.map((instance) => instance.programDir)).size, 1)
+    // All instances share one interrupt handler
+    t.true(process.listenerCount('SIGINT') <= signalListeners + 1)
+
+    await instances[1].createDatabase('only_on_one')
+    t.true(await instances[1].databaseExists('only_on_one'))
+    t.false(await instances[2].databaseExists('only_on_one'))
+
+    // The JavaScript-only helpers are available on every instance
+    t.true(instances.every((instance) => instance instanceof PostgresInstance))
+    await instances[0].executeSql('CREATE TABLE items (id int)', {})
+    t.is(await instances[0].copyInto('items', ['1\n', '2\n']), 2)
+    const stream = new PassThrough()
+    const chunks: Buffer[] = []
+    stream.on('data', (chunk: Buffer) => chunks.push(chunk))
+    await instances[0].copyOutToStream('items', stream)
+    t.is(Buffer.concat(chunks).toString(), '1\n2\n')
+  } finally {
+    await Promise.all(instances.map((instance) => instance.cleanup()))
+  }
+})
+
+test('createMany() rejects a count of 0', async (t) => {
+  await t.throwsAsync(() => PostgresInstance.createMany(0, { port: 0 }), { message: /at least 1/ })
+})
+
+test('createMany() rejects ports beyond 65535', async (t) => {
+  await t.throwsAsync(() => PostgresInstance.createMany(3, { port: 65534 }), { message: /exceed 65535/ })
+})
@@ -0,0 +1,29 @@
+import test from 'ava'
+import { PostgresInstance } from '../index.js'
+
+test.serial('configured databaseName is created on start and used by default', async (t) => {
+  const pg = new PostgresInstance({
+    databaseName: 'app_db',
+    username: 'postgres',
+    password: 'password',
+    port: 0,
+  })
+
+  try {
+    await pg.start()
+
+    t.true(await pg.databaseExists('app_db'))
+    t.is(pg.connectionInfo.databaseName, 'app_db')
+    t.true(pg.connectionInfo.connectionString.endsWith('/app_db'))
+
+    const result = await pg.executeSql('SELECT current_database();', { tuplesOnly: true })
+    t.is(result.stdout.trim(), 'app_db')
+
+    // Restarting an existing cluster must not fail on the already created database
+    await pg.stop()
+    await pg.start()
+    t.true(await pg.databaseExists('app_db'))
+  } finally {
+    await pg.cleanup()
+  }
+})
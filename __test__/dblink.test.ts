@@ -0,0 +1,33 @@
+import test from 'ava'
+import { PostgresInstance } from '../index.js'
+
+test.serial('dblinkQuery() returns rows of another database with column names', async (t) => {
+  const instance = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+
+  try {
+    await instance.start()
+    await instance.createDatabase('reporting')
+    await instance.executeSql(
+      "CREATE TABLE customers (id int, name text); INSERT INTO customers VALUES (1, 'alice'), (2, NULL);",
+      {},
+      'reporting',
+    )
+
+    const result = await instance.dblinkQuery('reporting', 'SELECT id, name FROM customers ORDER BY id;')
+    t.deepEqual(result.columns, ['id', 'name'])
+    t.deepEqual(result.rows, [
+      ['1', 'alice'],
+      ['2', null],
+    ])
+    t.is(result.rowCount, 2)
+
+    const empty = await instance.dblinkQuery('reporting', 'SELECT id FROM customers WHERE false')
+    t.deepEqual(empty.rows, [])
+
+    await t.throwsAsync(() => instance.dblinkQuery('reporting', 'SELECT * FROM missing_table'), {
+      message: /missing_table/,
+    })
+  } finally {
+    await instance.cleanup()
+  }
+})
@@ -0,0 +1,52 @@
+import test from 'ava'
+import fs from 'node:fs/promises'
+import path from 'node:path'
+import { compareLsn, PostgresInstance, ServerRole } from '../index.js'
+
+test.serial('createReplica() with recoveryMinApplyDelay keeps dropped data on the standby', async (t) => {
+  const primary = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+  const replicaDir = path.resolve(`data/delayed-replica-${Date.now()}`)
+  let replica: PostgresInstance | undefined
+
+  try {
+    await primary.start()
+    await primary.createDatabase('orders_db')
+
+    replica = await primary.createReplica({ dataDir: replicaDir, recoveryMinApplyDelay: '1h' })
+    t.is(await replica.getRole(), ServerRole.Standby)
+    t.is(replica.getConfigFileValue('recovery_min_apply_delay'), '1h')
+    t.true(await replica.databaseExists('orders_db'))
+
+    await primary.dropDatabase('orders_db')
+    const lsn = await primary.getCurrentLsn()
+    // Wait until the drop has been received, then check it was not applied
+    for (let attempt = 0; attempt < 50; attempt++) {
+      const status = await replica.getRecoveryStatus()
+      if (status.receiveLsn && compareLsn(status.receiveLsn, lsn) >= 0) break
+      await new Promise((resolve) => setTimeout(resolve, 100))
+    }
+    t.false(await primary.databaseExists('orders_db'))
+    t.true(await replica.databaseExists('orders_db'))
+
+    await replica.pauseReplay()
+    t.true((await replica.getRecoveryStatus()).replayPaused)
+    t.true(await replica.databaseExists('orders_db'))
+  } finally {
+    await replica?.cleanup()
+    await primary.cleanup()
+    await fs.rm(replicaDir, { recursive: true, force: true })
+  }
+})
+
+test.serial('createReplica() rejects an invalid recoveryMinApplyDelay', async (t) => {
+  const primary = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+
+  try {
+    await primary.start()
+    await t.throwsAsync(() => primary.createReplica({ recoveryMinApplyDelay: '5 minutes' }), {
+      message: /recoveryMinApplyDelay/,
+    })
+  } finally {
+    await primary.cleanup()
+  }
+})
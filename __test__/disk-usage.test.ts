@@ -0,0 +1,43 @@
+import test from 'ava'
+import { PostgresInstance, type DiskUsageWarning } from '../index.js'
+
+const sleep = (ms: number) => new Promise((resolve) => setTimeout(resolve, ms))
+
+test.serial('getDiskUsage() reports the data directory, WAL and volume sizes', async (t) => {
+  const pg = new PostgresInstance({ port: 0 })
+
+  try {
+    await pg.start()
+    const usage = await pg.getDiskUsage()
+    t.true(usage.walBytes > 0)
+    t.true(usage.dataDirBytes > usage.walBytes)
+    t.is(usage.walArchiveBytes, undefined)
+    if (process.platform !== 'win32') {
+      t.true(usage.freeBytes! > 0)
+      t.true(usage.totalBytes! >= usage.freeBytes!)
+    }
+  } finally {
+    await pg.cleanup()
+  }
+})
+
+test.serial('watchDiskUsage() warns once when the WAL exceeds its threshold', async (t) => {
+  const pg = new PostgresInstance({ port: 0 })
+  const warnings: DiskUsageWarning[] = []
+
+  try {
+    await pg.start()
+    pg.watchDiskUsage({ intervalMs: 100, maxWalBytes: 1 }, (warning) => warnings.push(warning))
+    t.true(pg.isDiskUsageWatchRunning())
+    await sleep(600)
+
+    t.is(warnings.length, 1)
+    t.is(warnings[0].threshold, 'maxWalBytes')
+    t.true(warnings[0].usage.walBytes > 1)
+
+    pg.stopDiskUsageWatch()
+    t.false(pg.isDiskUsageWatchRunning())
+  } finally {
+    await pg.cleanup()
+  }
+})
@@ -0,0 +1,44 @@
+import test from 'ava'
+import path from 'node:path'
+import { PgDumpTool, PsqlTool } from '../index.js'
+
+const missingBin = path.resolve('data/missing-postgres/bin')
+
+test('a dry run returns the composed command without running it', async (t) => {
+  const psql = new PsqlTool({
+    connection: { host: 'localhost', port: 5432, username: 'postgres', database: 'dbname=app password=secret' },
+    programDir: missingBin,
+    config: { tool: { dryRun: true } },
+  })
+
+  const result = await psql.executeCommand('SELECT 1;')
+  t.is(result.exitCode, 0)
+  t.is(result.stdout, '')
+  t.is(path.basename(result.command[0]), 'psql')
+  t.true(result.command.includes('SELECT 1;'))
+  t.false(result.command.some((arg) => arg.includes('secret')))
+  t.true(result.rawCommand!.some((arg) => arg.includes('password=secret')))
+})
+
+test('a dry run of pg_dump maps the options to arguments', async (t) => {
+  const pgDump = new PgDumpTool({
+    connection: { host: 'localhost', port: 5432, username: 'postgres', password: 'secret', database: 'app', sslMode: 'require' },
+    programDir: missingBin,
+    config: { tool: { dryRun: true }, schemaOnly: true },
+  })
+
+  const result = await pgDump.execute()
+  t.true(result.rawCommand!.includes('--schema-only'))
+  // Credentials and libpq settings go through the environment, listed by name only
+  t.deepEqual(result.environment, ['PGPASSWORD', 'PGSSLMODE'])
+  t.deepEqual(result.rawCommand!.slice(1), result.command.slice(1))
+})
+
+test('without dryRun the command runs and a missing program is reported', async (t) => {
+  const psql = new PsqlTool({
+    connection: { host: 'localhost', port: 5432, username: 'postgres' },
+    programDir: missingBin,
+    config: {},
+  })
+  await t.throwsAsync(() => psql.executeCommand('SELECT 1;'), { message: /psql not found at/ })
+})
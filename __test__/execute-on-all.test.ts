@@ -0,0 +1,32 @@
+import test from 'ava'
+import { PostgresInstance } from '../index.js'
+
+test.serial('executeSqlOnAllDatabases() runs SQL on every database except excluded ones', async (t) => {
+  const pg = new PostgresInstance({ databaseName: 'app', username: 'postgres', password: 'password', port: 0 })
+
+  try {
+    await pg.start()
+    await pg.createDatabase('reports')
+    await pg.createDatabase('scratch')
+
+    const results = await pg.executeSqlOnAllDatabases('CREATE EXTENSION IF NOT EXISTS pg_trgm;', {
+      exclude: ['scratch'],
+    })
+    t.deepEqual(
+      results.map(({ databaseName, result }) => [databaseName, result.exitCode]),
+      [
+        ['app', 0],
+        ['postgres', 0],
+        ['reports', 0],
+      ],
+    )
+
+    const installed = await pg.executeSql("SELECT count(*) FROM pg_extension WHERE extname = 'pg_trgm';", { tuplesOnly: true }, 'scratch')
+    t.is(installed.stdout.trim(), '0')
+
+    const failing = await pg.executeSqlOnAllDatabases('SELECT * FROM only_in_app;', { psql: { tuplesOnly: true } })
+    t.true(failing.every(({ result }) => result.exitCode !== 0))
+  } finally {
+    await pg.cleanup()
+  }
+})
@@ -0,0 +1,35 @@
+import path from 'node:path'
+import test from 'ava'
+import { executeTool, PostgresInstance } from '../index.js'
+
+test.serial('executeTool runs programs without a dedicated wrapper', async (t) => {
+  const pg = new PostgresInstance({ port: 0, password: 'tool-secret' })
+
+  try {
+    await pg.start()
+    const programDir = path.join(pg.programDir, 'bin')
+
+    const control = await executeTool('pg_controldata', { programDir, args: [pg.dataDir] })
+    t.is(control.exitCode, 0)
+    t.true(control.stdout.includes('Database cluster state'))
+
+    const lines: string[] = []
+    const ready = await executeTool(
+      'pg_isready',
+      { programDir, connection: { ...pg.connectionInfo, database: 'postgres' } },
+      (line) => lines.push(line),
+    )
+    t.is(ready.exitCode, 0)
+    t.deepEqual(ready.environment, ['PGDATABASE', 'PGHOST', 'PGPASSWORD', 'PGPORT', 'PGUSER'])
+
+    const dryRun = await executeTool('oid2name', { programDir, args: ['--quiet'], tool: { dryRun: true } })
+    t.is(path.basename(dryRun.command[0], '.exe'), 'oid2name')
+    t.deepEqual(dryRun.command.slice(1), ['--quiet'])
+  } finally {
+    await pg.cleanup()
+  }
+})
+
+test('executeTool only accepts plain program names', async (t) => {
+  await t.throwsAsync(executeTool('../bin/psql', { programDir: '/tmp' }), { message: /Invalid tool name/ })
+})
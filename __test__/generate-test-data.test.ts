@@ -0,0 +1,53 @@
+import test from 'ava'
+import { PostgresInstance } from '../index.js'
+
+test.serial('generateTestData() fills a table based on its column types', async (t) => {
+  const pg = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+
+  try {
+    await pg.start()
+    await pg.executeSql(
+      `CREATE TABLE events (
+        id serial PRIMARY KEY,
+        code int UNIQUE NOT NULL,
+        ref uuid NOT NULL,
+        title varchar(40) NOT NULL,
+        score numeric(5, 2),
+        active boolean,
+        created_at timestamptz NOT NULL,
+        payload jsonb,
+        shape polygon
+      );`,
+      {},
+    )
+
+    t.is(await pg.generateTestData('events', 1000), 1000)
+    t.is(await pg.generateTestData('public.events', 500, { seed: 0.25 }), 500)
+
+    const stats = await pg.executeSql(
+      'SELECT count(*), count(DISTINCT code), max(code), count(shape), bool_and(length(title) <= 40) FROM events;',
+      { tuplesOnly: true, noAlign: true, fieldSeparator: ',' },
+    )
+    t.is(stats.stdout.trim(), '1500,1500,1500,0,t')
+
+    await t.throwsAsync(() => pg.generateTestData('missing', 10), { message: /does not exist/ })
+  } finally {
+    await pg.cleanup()
+  }
+})
+
+test.serial('generateTestData() repeats values for the same seed', async (t) => {
+  const pg = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+
+  try {
+    await pg.start()
+    await pg.executeSql('CREATE TABLE a (n int, s text); CREATE TABLE b (n int, s text);', {})
+    await pg.generateTestData('a', 100, { seed: 0.5 })
+    await pg.generateTestData('b', 100, { seed: 0.5 })
+
+    const diff = await pg.executeSql('SELECT count(*) FROM (TABLE a EXCEPT ALL TABLE b) d;', { tuplesOnly: true })
+    t.is(diff.stdout.trim(), '0')
+  } finally {
+    await pg.cleanup()
+  }
+})
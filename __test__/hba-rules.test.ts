@@ -0,0 +1,67 @@
+import test from 'ava'
+import fs from 'node:fs/promises'
+import os from 'node:os'
+import path from 'node:path'
+import { PostgresInstance, PsqlTool } from '../index.js'
+
+test.serial('addHbaRule() and removeHbaRule() change authentication on a running instance', async (t) => {
+  const pg = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+
+  try {
+    await pg.start()
+    await pg.executeSql("CREATE ROLE mallory LOGIN PASSWORD 'secret';", {})
+    const info = pg.connectionInfo
+    const connectAsMallory = () =>
+      new PsqlTool({
+        connection: { host: '127.0.0.1', port: info.port, username: 'mallory', password: 'secret', database: 'postgres' },
+        programDir: path.join(pg.programDir, 'bin'),
+        config: { tuplesOnly: true },
+      }).executeCommand('SELECT current_user;')
+
+    t.is((await connectAsMallory()).exitCode, 0)
+
+    const rule = {
+      connectionType: 'host',
+      database: 'all',
+      user: 'mallory',
+      address: '127.0.0.1/32',
+      method: 'reject',
+    }
+    const before = pg.listHbaRules().length
+    await pg.addHbaRule(rule)
+    await pg.addHbaRule(rule)
+    const rules = pg.listHbaRules()
+    t.is(rules.length, before + 1)
+    t.like(rules[0], rule)
+
+    const rejected = await connectAsMallory()
+    t.not(rejected.exitCode, 0)
+    t.regex(rejected.stderr, /rejects connection/)
+
+    t.is(await pg.removeHbaRule({ user: 'mallory' }), 1)
+    t.is((await connectAsMallory()).exitCode, 0)
+
+    await t.throwsAsync(() => pg.addHbaRule({ ...rule, connectionType: 'local' }), {
+      message: /local rules do not take an address/,
+    })
+  } finally {
+    await pg.cleanup()
+  }
+})
+
+test.serial('a restart keeps the method of rules added to a persistent trust instance', async (t) => {
+  const dataDir = path.join(os.tmpdir(), `pg-embedded-hba-${Date.now()}`)
+  const pg = new PostgresInstance({ username: 'postgres', password: '', port: 0, persistent: true, dataDir })
+
+  try {
+    await pg.start()
+    const rule = { connectionType: 'host', database: 'all', user: 'mallory', address: '10.0.0.0/8', method: 'reject' }
+    await pg.addHbaRule(rule)
+    await pg.stop()
+    await pg.start()
+    t.like(pg.listHbaRules()[0], rule)
+  } finally {
+    await pg.cleanup()
+    await fs.rm(dataDir, { recursive: true, force: true })
+  }
+})
@@ -0,0 +1,31 @@
+import test from 'ava'
+import fs from 'node:fs/promises'
+import path from 'node:path'
+import { FailurePhase, InstanceState, PostgresInstance } from '../index.js'
+
+test.serial('probeHealth probes the server and can reconcile the state', async (t) => {
+  const instance = new PostgresInstance({ port: 0 })
+
+  try {
+    await instance.start()
+    t.true(instance.isHealthy())
+    t.true(await instance.probeHealth())
+
+    const pidFile = await fs.readFile(path.join(instance.dataDir, 'postmaster.pid'), 'utf8')
+    process.kill(Number(pidFile.split('\n')[0]), 'SIGKILL')
+    await new Promise((resolve) => setTimeout(resolve, 500))
+
+    t.true(instance.isHealthy())
+    t.false(await instance.probeHealth())
+    t.is(instance.state, InstanceState.Running)
+
+    t.false(await instance.probeHealth(true))
+    t.false(instance.isHealthy())
+    t.is(instance.state, InstanceState.Failed)
+    const failure = instance.getLastError()
+    t.is(failure?.phase, FailurePhase.Runtime)
+    t.regex(failure?.message ?? '', /no longer accepts connections/)
+  } finally {
+    await instance.cleanup()
+  }
+})
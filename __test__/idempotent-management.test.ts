@@ -0,0 +1,37 @@
+import test from 'ava'
+import { PostgresInstance } from '../index.js'
+
+test.serial('IfNotExists and IfExists flavors can be re-run without errors', async (t) => {
+  const instance = new PostgresInstance({ port: 0 })
+
+  try {
+    await instance.start()
+
+    t.true(await instance.createDatabaseIfNotExists('rerun_app'))
+    t.false(await instance.createDatabaseIfNotExists('rerun_app'))
+    t.true(await instance.databaseExists('rerun_app'))
+
+    await instance.createSchema('rerun_app', 'tenant_acme', undefined, true)
+    await instance.createSchema('rerun_app', 'tenant_acme', undefined, true)
+    t.deepEqual(
+      (await instance.listSchemas('rerun_app')).map((schema) => schema.name),
+      ['public', 'tenant_acme'],
+    )
+
+    await instance.createExtension('rerun_app', 'pgcrypto', undefined, true)
+    await instance.createExtension('rerun_app', 'pgcrypto', undefined, true)
+    await t.throwsAsync(() => instance.createExtension('rerun_app', 'pgcrypto'), { message: /already exists/ })
+    await instance.dropExtension('rerun_app', 'pgcrypto', false, true)
+    await instance.dropExtension('rerun_app', 'pgcrypto', false, true)
+    await t.throwsAsync(() => instance.dropExtension('rerun_app', 'pgcrypto'), { message: /does not exist/ })
+
+    await instance.dropSchema('rerun_app', 'tenant_acme', false, true)
+    await instance.dropSchema('rerun_app', 'tenant_acme', false, true)
+
+    t.true(await instance.dropDatabaseIfExists('rerun_app'))
+    t.false(await instance.dropDatabaseIfExists('rerun_app'))
+    t.false(await instance.databaseExists('rerun_app'))
+  } finally {
+    await instance.cleanup()
+  }
+})
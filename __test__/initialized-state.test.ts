@@ -0,0 +1,24 @@
+import test from 'ava'
+import { InstanceState, PostgresInstance } from '../index.js'
+
+test.serial('setup() leaves the instance Initialized until it is started', async (t) => {
+  const pg = new PostgresInstance({ port: 0, dataDir: `data/initialized-${Date.now()}` })
+
+  try {
+    t.is(pg.state, InstanceState.Stopped)
+    t.false(pg.isInitialized())
+
+    await pg.setup()
+    t.is(pg.state, InstanceState.Initialized)
+    t.true(pg.isInitialized())
+
+    await pg.start(false)
+    t.is(pg.state, InstanceState.Running)
+
+    await pg.stop()
+    t.is(pg.state, InstanceState.Stopped)
+    t.true(pg.isInitialized())
+  } finally {
+    await pg.cleanup()
+  }
+})
@@ -0,0 +1,49 @@
+import test from 'ava'
+import path from 'node:path'
+import { findInstances, PostgresInstance } from '../index.js'
+
+test.serial('findInstances() locates instances by label', async (t) => {
+  const orders = new PostgresInstance({ port: 0, labels: { service: 'orders', suite: 'labels-test' } })
+  const billing = new PostgresInstance({ port: 0, labels: { service: 'billing', suite: 'labels-test' } })
+
+  try {
+    t.deepEqual(orders.labels, { service: 'orders', suite: 'labels-test' })
+
+    const suite = findInstances({ label: { suite: 'labels-test' } })
+    t.deepEqual(
+      suite.map((instance) => instance.instanceId),
+      [orders.instanceId, billing.instanceId],
+    )
+
+    const found = findInstances({ label: { service: 'orders', suite: 'labels-test' } })
+    t.is(found.length, 1)
+    t.is(found[0].instanceId, orders.instanceId)
+    t.is(found[0].state, orders.state)
+
+    await orders.start()
+    const [running] = findInstances({ label: { service: 'orders', suite: 'labels-test' } })
+    t.is(running.port, orders.connectionInfo.port)
+  } finally {
+    await orders.cleanup()
+    await billing.cleanup()
+  }
+
+  t.is(findInstances({ label: { suite: 'labels-test' } }).length, 0)
+})
+
+test.serial('named instances use their name in the registry and data directory', async (t) => {
+  const pg = new PostgresInstance({ name: 'orders-db', port: 0 })
+
+  try {
+    t.is(pg.name, 'orders-db')
+    const [found] = findInstances({ name: 'orders-db' })
+    t.is(found.instanceId, pg.instanceId)
+    t.true(path.basename(found.dataDir).startsWith('pg-embedded-orders-db-'))
+  } finally {
+    await pg.cleanup()
+  }
+})
+
+test('invalid instance names are rejected', (t) => {
+  t.throws(() => new PostgresInstance({ name: 'orders/db' }), { message: /Instance name/ })
+})
@@ -0,0 +1,35 @@
+import test from 'ava'
+import fs from 'node:fs/promises'
+import os from 'node:os'
+import path from 'node:path'
+import { PgDumpFormat, PostgresInstance } from '../index.js'
+
+test.serial('large objects survive a dump and restore', async (t) => {
+  const source = new PostgresInstance({ databaseName: 'lo_source', password: 'password', port: 0 })
+  const target = new PostgresInstance({ databaseName: 'lo_target', password: 'password', port: 0 })
+  const dumpFile = path.join(os.tmpdir(), `pg-embedded-lo-${Date.now()}.dump`)
+  const data = Buffer.from([0, 1, 2, 255, 254, 39, 92, 10])
+
+  try {
+    await source.start()
+    await target.start()
+
+    const oid = await source.putLargeObject(data)
+    t.deepEqual(await source.getLargeObject(oid), data)
+
+    const withoutBlobs = await source.createDump({ format: PgDumpFormat.Plain, noBlobs: true })
+    t.false(withoutBlobs.stdout.includes('lo_create'))
+
+    const dump = await source.createDump({ file: dumpFile, format: PgDumpFormat.Custom, blobs: true })
+    t.is(dump.exitCode, 0)
+    const restore = await target.createRestore({ file: dumpFile })
+    t.is(restore.exitCode, 0)
+    t.deepEqual(await target.getLargeObject(oid), data)
+
+    await t.throwsAsync(() => source.getLargeObject(4294967000))
+  } finally {
+    await source.cleanup()
+    await target.cleanup()
+    await fs.rm(dumpFile, { force: true })
+  }
+})
@@ -0,0 +1,23 @@
+import test from 'ava'
+import { FailurePhase, InstanceState, PostgresInstance } from '../index.js'
+
+test.serial('a failed start moves the instance to Failed and keeps the cause', async (t) => {
+  const pg = new PostgresInstance({
+    port: 0,
+    serverConfig: { shared_buffers: 'not-a-size' },
+  })
+
+  try {
+    t.is(pg.getLastError(), null)
+    await t.throwsAsync(() => pg.start())
+
+    t.is(pg.state, InstanceState.Failed)
+    const failure = pg.getLastError()
+    t.truthy(failure)
+    t.is(failure!.phase, FailurePhase.Start)
+    t.truthy(failure!.message)
+    t.true(failure!.stderr?.includes('shared_buffers') ?? false)
+  } finally {
+    await pg.cleanup()
+  }
+})
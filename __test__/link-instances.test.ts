@@ -0,0 +1,33 @@
+import test from 'ava'
+import { PostgresInstance } from '../index.js'
+
+test.serial('linkInstances() imports the tables of another instance as foreign tables', async (t) => {
+  const orders = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+  const billing = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+
+  try {
+    await orders.start()
+    await billing.start()
+    await orders.executeSql('CREATE TABLE orders (id int, total numeric); INSERT INTO orders VALUES (1, 9.5), (2, 20);', {})
+
+    const tables = await billing.linkInstances(undefined, orders, { serverName: 'orders' })
+    t.deepEqual(tables, ['orders_public.orders'])
+
+    const sum = await billing.executeSql('SELECT sum(total) FROM orders_public.orders', {
+      tuplesOnly: true,
+      noAlign: true,
+    })
+    t.is(sum.stdout.trim(), '29.5')
+
+    // The server name is taken now, and the failed link leaves no schema behind
+    await t.throwsAsync(() => billing.linkInstances(undefined, orders, { serverName: 'orders', schemas: ['audit'] }))
+    const schemas = await billing.executeSql("SELECT count(*) FROM pg_namespace WHERE nspname = 'orders_audit'", {
+      tuplesOnly: true,
+      noAlign: true,
+    })
+    t.is(schemas.stdout.trim(), '0')
+  } finally {
+    await billing.cleanup()
+    await orders.cleanup()
+  }
+})
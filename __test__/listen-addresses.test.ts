@@ -0,0 +1,48 @@
+import test from 'ava'
+import { PostgresInstance } from '../index.js'
+
+test.serial('listenAddresses is passed to the server', async (t) => {
+  const pg = new PostgresInstance({
+    username: 'postgres',
+    password: 'password',
+    port: 0,
+    listenAddresses: '127.0.0.1',
+  })
+
+  try {
+    await pg.start()
+    const result = await pg.executeSql('SHOW listen_addresses;', { tuplesOnly: true })
+    t.is(result.stdout.trim(), '127.0.0.1')
+  } finally {
+    await pg.cleanup()
+  }
+})
+
+test.serial('exposeExternally listens on all interfaces and admits remote clients', async (t) => {
+  const pg = new PostgresInstance({
+    username: 'postgres',
+    password: 'password',
+    port: 0,
+    exposeExternally: true,
+  })
+
+  try {
+    await pg.start()
+    const listen = await pg.executeSql('SHOW listen_addresses;', { tuplesOnly: true })
+    t.is(listen.stdout.trim(), '0.0.0.0,::')
+
+    const rules = await pg.executeSql(
+      "SELECT address FROM pg_hba_file_rules WHERE address IN ('0.0.0.0', '::') ORDER BY address;",
+      { tuplesOnly: true, noAlign: true },
+    )
+    t.deepEqual(rules.stdout.trim().split('\n'), ['0.0.0.0', '::'])
+  } finally {
+    await pg.cleanup()
+  }
+})
+
+test('exposeExternally is rejected with trust authentication', (t) => {
+  t.throws(() => new PostgresInstance({ password: null, port: 0, exposeExternally: true }), {
+    message: /exposeExternally requires a password/,
+  })
+})
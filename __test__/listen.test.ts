@@ -0,0 +1,49 @@
+import test from 'ava'
+import { type Notification, PostgresInstance } from '../index.js'
+
+const waitFor = async (condition: () => boolean, timeoutMs = 5000) => {
+  const deadline = Date.now() + timeoutMs
+  while (!condition()) {
+    if (Date.now() > deadline) {
+      throw new Error('Timed out waiting for notifications')
+    }
+    await new Promise((resolve) => setTimeout(resolve, 20))
+  }
+}
+
+test.serial('listen() passes NOTIFY payloads to the callbacks of the channel', async (t) => {
+  const instance = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+
+  try {
+    await instance.start()
+    const orders: Notification[] = []
+    const audit: string[] = []
+    await instance.listen('orders', (notification) => orders.push(notification))
+    await instance.listen('orders', (notification) => audit.push(notification.payload))
+    await instance.listen('Mixed Case', (notification) => audit.push(notification.channel))
+
+    await instance.executeSql("NOTIFY orders, 'first'; SELECT pg_notify('orders', 'second');", {})
+    await instance.executeSql('NOTIFY "Mixed Case"; NOTIFY unrelated;', {})
+    // Notifications are only sent once their transaction commits
+    await instance.executeSql("BEGIN; NOTIFY orders, 'rolled back'; ROLLBACK;", {})
+    await waitFor(() => orders.length === 2 && audit.length === 3)
+
+    t.deepEqual(
+      orders.map(({ channel, payload }) => [channel, payload]),
+      [
+        ['orders', 'first'],
+        ['orders', 'second'],
+      ],
+    )
+    t.true(orders[0].processId > 0)
+    t.deepEqual(audit.sort(), ['Mixed Case', 'first', 'second'])
+
+    t.true(await instance.unlisten('orders'))
+    t.false(await instance.unlisten('orders'))
+    await instance.executeSql("NOTIFY orders, 'third'; NOTIFY \"Mixed Case\", 'still listening';", {})
+    await waitFor(() => audit.length === 4)
+    t.is(orders.length, 2)
+  } finally {
+    await instance.cleanup()
+  }
+})
@@ -0,0 +1,37 @@
+import test from 'ava'
+import { PostgresInstance } from '../index.js'
+
+test.serial('getLockWaits() reports a query blocked by an open transaction', async (t) => {
+  const instance = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+
+  try {
+    await instance.start()
+    await instance.executeSql('CREATE TABLE accounts (id int)', {})
+    t.deepEqual(await instance.getLockWaits(), [])
+
+    let blocked: Promise<unknown> | undefined
+    await instance.runInRollbackTransaction(async (tx) => {
+      await tx.query('LOCK TABLE accounts IN ACCESS EXCLUSIVE MODE')
+      const holder = Number((await tx.query('SELECT pg_backend_pid()')).rows[0][0])
+      blocked = instance.executeSql('SELECT count(*) FROM accounts', {})
+
+      let waits = await instance.getLockWaits()
+      for (let attempt = 0; waits.length === 0 && attempt < 50; attempt++) {
+        await new Promise((resolve) => setTimeout(resolve, 100))
+        waits = await instance.getLockWaits()
+      }
+      t.is(waits.length, 1)
+      t.is(waits[0].blockingPid, holder)
+      t.regex(waits[0].blockedQuery, /count\(\*\) FROM accounts/)
+      t.is(waits[0].blockingState, 'idle in transaction')
+      t.is(waits[0].lockType, 'relation')
+      t.is(waits[0].mode, 'AccessShareLock')
+      t.deepEqual(waits[0].relations, ['accounts'])
+    })
+
+    await blocked
+    t.deepEqual(await instance.getLockWaits(), [])
+  } finally {
+    await instance.cleanup()
+  }
+})
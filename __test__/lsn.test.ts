@@ -0,0 +1,27 @@
+import test from 'ava'
+import { compareLsn, lsnDiffBytes, PostgresInstance } from '../index.js'
+
+test('compareLsn() and lsnDiffBytes() compare WAL positions', (t) => {
+  t.is(compareLsn('0/3000060', '0/3000060'), 0)
+  t.is(compareLsn('0/3000060', '1/0'), -1)
+  t.is(compareLsn('1/0', '0/FFFFFFFF'), 1)
+  t.is(lsnDiffBytes('1/0', '0/FFFFFF00'), 256)
+  t.is(lsnDiffBytes('0/FFFFFF00', '1/0'), -256)
+  t.throws(() => compareLsn('3000060', '0/0'), { message: /Invalid LSN/ })
+})
+
+test.serial('getCurrentTimeline() reports the timeline of a fresh cluster', async (t) => {
+  const pg = new PostgresInstance({ port: 0 })
+
+  try {
+    await pg.start()
+    t.is(await pg.getCurrentTimeline(), 1)
+
+    const before = await pg.executeSql('SELECT pg_current_wal_lsn();', { tuplesOnly: true })
+    await pg.executeSql('CREATE TABLE lsn_test AS SELECT generate_series(1, 1000) AS id;', {})
+    const after = await pg.executeSql('SELECT pg_current_wal_lsn();', { tuplesOnly: true })
+    t.true(lsnDiffBytes(after.stdout.trim(), before.stdout.trim()) > 0)
+  } finally {
+    await pg.cleanup()
+  }
+})
@@ -0,0 +1,39 @@
+import test from 'ava'
+import { PostgresInstance } from '../index.js'
+
+const sleep = (ms: number) => new Promise((resolve) => setTimeout(resolve, ms))
+
+test.serial('the metrics sampler records resource usage in a bounded history', async (t) => {
+  const pg = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+
+  try {
+    await pg.start()
+    t.deepEqual(pg.getMetricsHistory(), [])
+
+    pg.startMetricsSampler({ intervalMs: 100, capacity: 5 })
+    t.true(pg.isMetricsSamplerRunning())
+    await sleep(1500)
+
+    const history = pg.getMetricsHistory()
+    t.is(history.length, 5)
+    t.true(history.every((sample, i) => i === 0 || sample.timestamp > history[i - 1].timestamp))
+    const latest = history[history.length - 1]
+    t.true(latest.connections! >= 0)
+    if (process.platform !== 'win32') {
+      t.true(latest.rssBytes! > 0)
+      t.true(latest.processCount! > 1)
+      t.true(latest.cpuSeconds! >= 0)
+    }
+
+    pg.stopMetricsSampler()
+    t.false(pg.isMetricsSamplerRunning())
+    const stopped = pg.getMetricsHistory().map((sample) => sample.timestamp)
+    await sleep(300)
+    t.deepEqual(
+      pg.getMetricsHistory().map((sample) => sample.timestamp),
+      stopped,
+    )
+  } finally {
+    await pg.cleanup()
+  }
+})
@@ -0,0 +1,64 @@
+import test from 'ava'
+import { PostgresInstance } from '../index.js'
+
+test.serial('a client reuses its connection and returns typed rows', async (t) => {
+  const pg = new PostgresInstance({ port: 0 })
+
+  try {
+    await pg.start()
+    const client = await pg.connect()
+    try {
+      await client.query('CREATE TABLE users (id int, active boolean, balance numeric, note text)')
+      const inserted = await client.query(
+        "INSERT INTO users VALUES (1, true, 10.50, 'first'), (2, false, 0, NULL)",
+      )
+      t.is(inserted.rowCount, 2)
+
+      const result = await client.query('SELECT id, active, balance, note, pg_backend_pid() AS pid FROM users ORDER BY id')
+      t.deepEqual(result.columns, ['id', 'active', 'balance', 'note', 'pid'])
+      t.is(result.rowCount, 2)
+      t.like(result.rows[0], { id: 1, active: true, balance: '10.50', note: 'first' })
+      t.is(result.rows[1].note, null)
+
+      // Session state survives between queries on the same client
+      await client.query("SET application_name = 'reused'")
+      const session = await client.query("SELECT current_setting('application_name') AS name, pg_backend_pid() AS pid")
+      t.is(session.rows[0].name, 'reused')
+      t.is(session.rows[0].pid, result.rows[0].pid)
+
+      await t.throwsAsync(client.query('SELECT * FROM missing_table'), { message: /missing_table/ })
+    } finally {
+      await client.close()
+    }
+    await t.throwsAsync(client.query('SELECT 1'), { message: /closed/ })
+  } finally {
+    await pg.cleanup()
+  }
+})
+
+test.serial('a client applies the session options it is given', async (t) => {
+  const pg = new PostgresInstance({ port: 0 })
+
+  try {
+    await pg.start()
+    const client = await pg.connect(undefined, {
+      statementTimeoutMs: 5000,
+      lockTimeoutMs: 250,
+      timezone: 'Asia/Tokyo',
+      datestyle: 'ISO, DMY',
+    })
+    try {
+      const result = await client.query(
+        "SELECT current_setting('statement_timeout') AS statement, current_setting('lock_timeout') AS lock, current_setting('TimeZone') AS zone, current_setting('DateStyle') AS style",
+      )
+      t.deepEqual(result.rows[0], { statement: '5s', lock: '250ms', zone: 'Asia/Tokyo', style: 'ISO, DMY' })
+    } finally {
+      await client.close()
+    }
+
+    await t.throwsAsync(pg.connect(undefined, { timezone: 'Mars/Base' }), { message: /TimeZone/ })
+    await t.throwsAsync(pg.connect(undefined, { clientEncoding: 'LATIN1' }), { message: /clientEncoding/ })
+  } finally {
+    await pg.cleanup()
+  }
+})
@@ -0,0 +1,27 @@
+import test from 'ava'
+import { PasswordEncryption, PostgresInstance } from '../index.js'
+
+test.serial('passwordEncryption: Md5 stores md5 hashes for the superuser and new roles', async (t) => {
+  const pg = new PostgresInstance({
+    username: 'postgres',
+    password: 'password',
+    port: 0,
+    passwordEncryption: PasswordEncryption.Md5,
+  })
+
+  try {
+    await pg.start()
+
+    const setting = await pg.executeSql('SHOW password_encryption;', { tuplesOnly: true })
+    t.is(setting.stdout.trim(), 'md5')
+
+    await pg.executeSql("CREATE ROLE legacy LOGIN PASSWORD 'legacy';", {})
+    const hashes = await pg.executeSql(
+      "SELECT rolname || ':' || left(rolpassword, 3) FROM pg_authid WHERE rolname IN ('postgres', 'legacy') ORDER BY rolname;",
+      { tuplesOnly: true, noAlign: true },
+    )
+    t.deepEqual(hashes.stdout.trim().split('\n'), ['legacy:md5', 'postgres:md5'])
+  } finally {
+    await pg.cleanup()
+  }
+})
@@ -0,0 +1,32 @@
+import test from 'ava'
+import fs from 'node:fs/promises'
+import path from 'node:path'
+import { archiveCommand, PostgresInstance } from '../index.js'
+
+test('archiveCommand() accepts extended-length and UNC paths on Windows', (t) => {
+  if (process.platform !== 'win32') {
+    t.is(archiveCommand('/tmp//wal/'), "test ! -f '/tmp/wal/%f' && cp %p '/tmp/wal/%f'")
+    return
+  }
+  t.is(archiveCommand('\\\\?\\C:\\pg wal'), 'if not exist "C:\\pg wal\\%f" copy "%p" "C:\\pg wal\\%f"')
+  t.is(
+    archiveCommand('\\\\?\\UNC\\backup\\wal'),
+    'if not exist "\\\\backup\\wal\\%f" copy "%p" "\\\\backup\\wal\\%f"',
+  )
+})
+
+test.serial('data directories with spaces and trailing separators are normalized', async (t) => {
+  const root = path.resolve(`data/path with spaces-${Date.now()}`)
+  const dataDir = `${root}/cluster data//`
+  const pg = new PostgresInstance({ port: 0, dataDir, walArchiveDir: path.join(root, 'wal archive') })
+
+  try {
+    await pg.start()
+    t.is(pg.dataDir, path.join(root, 'cluster data'))
+    const result = await pg.executeSql('SHOW data_directory;', { tuplesOnly: true, noAlign: true })
+    t.is(path.resolve(result.stdout.trim()), path.join(root, 'cluster data'))
+  } finally {
+    await pg.cleanup()
+    await fs.rm(root, { recursive: true, force: true })
+  }
+})
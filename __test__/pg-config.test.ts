@@ -0,0 +1,27 @@
+import path from 'node:path'
+import test from 'ava'
+import { PgConfigTool, PostgresInstance } from '../index.js'
+
+test.serial('getBuildConfig reports the build configuration of the installation', async (t) => {
+  const pg = new PostgresInstance({ port: 0 })
+
+  try {
+    await pg.setup()
+    const config = await pg.getBuildConfig()
+    t.regex(config.version, /^PostgreSQL \d+/)
+    t.true(config.version.includes(pg.getPostgreSqlVersion().split('.')[0]))
+    t.not(config.bindir, '')
+    t.not(config.sharedir, '')
+    t.not(config.pkglibdir, '')
+    t.true(Array.isArray(config.configure))
+    t.is(config.values.VERSION, config.version)
+
+    const tool = new PgConfigTool({ programDir: path.join(pg.programDir, 'bin') })
+    const result = await tool.execute()
+    t.is(result.exitCode, 0)
+    t.true(result.stdout.includes('BINDIR = '))
+    t.deepEqual(await tool.getBuildConfig(), config)
+  } finally {
+    await pg.cleanup()
+  }
+})
@@ -1,7 +1,15 @@
 import anyTest, { type TestFn } from 'ava'
+import fs from 'node:fs'
 import path from 'node:path'
 import { fileURLToPath } from 'node:url'
-import { PgDumpTool, PostgresInstance, PgDumpFormat } from '../index.js'
+import {
+  PgDumpTool,
+  PostgresInstance,
+  PgDumpFormat,
+  VerboseAction,
+  type DumpProgress,
+  type VerboseEvent,
+} from '../index.js'
 
 const __dirname = path.dirname(fileURLToPath(import.meta.url))
 const test = anyTest as TestFn<{ pg: PostgresInstance; pgDump: PgDumpTool }>
@@ -13,11 +21,9 @@ test.before(async (t) => {
     password: 'password',
     port: 0, // Auto-assign available port to avoid conflicts
   })
+  // The configured databaseName is created on first start
   await pg.start()
 
-  // Create the test database that we want to dump
-  await pg.createDatabase('test_db')
-
   // Create some test data in the database
   const { PsqlTool } = await import('../index.js')
   const psql = new PsqlTool({
@@ -203,3 +209,69 @@ test('should use if_exists with clean option to avoid errors on non-existent obj
   t.true(result.stdout.includes('DROP TABLE'), "Expected 'DROP TABLE' statement in the dump")
   t.true(result.stdout.includes('DROP SCHEMA'), "Expected 'DROP SCHEMA' statement for test_schema in the dump")
 })
+
+test('should report verbose output as structured events', async (t) => {
+  const dumpTool = new PgDumpTool({
+    connection: {
+      host: t.context.pg.connectionInfo.host,
+      port: t.context.pg.connectionInfo.port,
+      username: t.context.pg.connectionInfo.username,
+      password: t.context.pg.connectionInfo.password,
+      database: 'test_db',
+    },
+    programDir: path.join(t.context.pg.programDir, 'bin'),
+    config: {},
+  })
+
+  const events: VerboseEvent[] = []
+  const result = await dumpTool.executeWithEvents((event) => {
+    events.push(event)
+  })
+  // Let pending callbacks reach the JS thread
+  await new Promise((resolve) => setImmediate(resolve))
+
+  t.is(result.exitCode, 0, result.stderr)
+  t.true(result.stdout.includes('CREATE TABLE test_schema.test_table'))
+  t.true(events.every((event) => event.tool === 'pg_dump'))
+  t.true(events.some((event) => event.action === VerboseAction.Read))
+  const data = events.find((event) => event.action === VerboseAction.Data)
+  t.is(data?.objectType, 'TABLE DATA')
+  t.is(data?.schema, 'test_schema')
+  t.is(data?.name, 'test_table')
+})
+
+test('should report progress while dumping to a directory', async (t) => {
+  const dumpDir = path.resolve(__dirname, 'assets', 'dump_progress')
+  const dumpTool = new PgDumpTool({
+    connection: {
+      host: t.context.pg.connectionInfo.host,
+      port: t.context.pg.connectionInfo.port,
+      username: t.context.pg.connectionInfo.username,
+      password: t.context.pg.connectionInfo.password,
+      database: 'test_db',
+    },
+    programDir: path.join(t.context.pg.programDir, 'bin'),
+    config: {
+      file: dumpDir,
+      format: PgDumpFormat.Directory,
+      jobs: 2,
+    },
+  })
+
+  const updates: DumpProgress[] = []
+  try {
+    const result = await dumpTool.executeWithProgress((progress) => {
+      updates.push(progress)
+    })
+    // Let pending callbacks reach the JS thread
+    await new Promise((resolve) => setImmediate(resolve))
+
+    t.is(result.exitCode, 0, result.stderr)
+    const last = updates[updates.length - 1]
+    t.is(last.total, 1)
+    t.is(last.completed, last.total)
+    t.true(updates.some((update) => update.currentTable === 'test_schema.test_table'))
+  } finally {
+    fs.rmSync(dumpDir, { recursive: true, force: true })
+  }
+})
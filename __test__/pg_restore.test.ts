@@ -1,7 +1,17 @@
 import test from 'ava'
 import path from 'node:path'
 import { fileURLToPath } from 'node:url'
-import { PgDumpTool, PostgresInstance, PgRestoreTool, PsqlTool, PgDumpFormat, PgRestoreFormat } from '../index.js'
+import {
+  PgDumpTool,
+  PostgresInstance,
+  PgRestoreTool,
+  PsqlTool,
+  PgDumpFormat,
+  PgRestoreFormat,
+  StreamCompression,
+  VerboseAction,
+  type VerboseEvent,
+} from '../index.js'
 import fs from 'fs'
 
 const __filename = fileURLToPath(import.meta.url)
@@ -198,3 +208,130 @@ test('should restore data only', async (t) => {
   // Clean up
   await pg.dropDatabase(restoreDbName)
 })
+
+test('should restore a compressed and encrypted dump', async (t) => {
+  const restoreDbName = `${dbName}_encrypted`
+  const encryptedDumpPath = path.join(__dirname, 'test_restore_dump.enc')
+  await pg.createDatabase(restoreDbName)
+
+  const programDir = path.join(pg.programDir, 'bin')
+  const sourceConnectionConfig = {
+    host: pg.connectionInfo.host,
+    port: pg.connectionInfo.port,
+    username: pg.connectionInfo.username,
+    password: pg.connectionInfo.password,
+    database: dbName,
+  }
+  const restoreConnectionConfig = { ...sourceConnectionConfig, database: restoreDbName }
+
+  try {
+    const pgDump = new PgDumpTool({
+      connection: sourceConnectionConfig,
+      programDir,
+      config: {
+        file: encryptedDumpPath,
+        format: PgDumpFormat.Custom,
+        outputCompression: StreamCompression.Zstd,
+        encryptionPassphrase: 'fixture-secret',
+      },
+    })
+    const dumpResult = await pgDump.execute()
+    t.is(dumpResult.exitCode, 0, dumpResult.stderr)
+    t.true(fs.readFileSync(encryptedDumpPath).subarray(0, 21).toString() === 'age-encryption.org/v1')
+
+    const pgRestore = new PgRestoreTool({
+      connection: restoreConnectionConfig,
+      programDir,
+      config: {
+        file: encryptedDumpPath,
+        noOwner: true,
+        inputCompression: StreamCompression.Zstd,
+        encryptionPassphrase: 'fixture-secret',
+      },
+    })
+    const restoreResult = await pgRestore.execute()
+    t.is(restoreResult.exitCode, 0, restoreResult.stderr)
+
+    const psql = new PsqlTool({ connection: restoreConnectionConfig, programDir, config: {} })
+    const { stdout } = await psql.executeCommand('SELECT * FROM test_table;')
+    t.true(stdout.includes('test1'))
+  } finally {
+    await pg.dropDatabase(restoreDbName)
+    fs.rmSync(encryptedDumpPath, { force: true })
+  }
+})
+
+test('should report progress while restoring', async (t) => {
+  const restoreDbName = `${dbName}_progress`
+  await pg.createDatabase(restoreDbName)
+
+  const pgRestore = new PgRestoreTool({
+    connection: {
+      host: pg.connectionInfo.host,
+      port: pg.connectionInfo.port,
+      username: pg.connectionInfo.username,
+      password: pg.connectionInfo.password,
+      database: restoreDbName,
+    },
+    programDir: path.join(pg.programDir, 'bin'),
+    config: {
+      file: dumpFilePath,
+      format: PgRestoreFormat.Custom,
+      noOwner: true,
+    },
+  })
+
+  const updates: Array<{ completed: number; total: number; currentTable?: string | null }> = []
+  const result = await pgRestore.executeWithProgress((progress) => {
+    updates.push(progress)
+  })
+  // Let pending callbacks reach the JS thread
+  await new Promise((resolve) => setImmediate(resolve))
+
+  t.is(result.exitCode, 0, result.stderr)
+  t.true(updates.length > 1)
+  const last = updates[updates.length - 1]
+  t.true(last.total > 0)
+  t.is(last.completed, last.total)
+  t.true(updates.some((update) => update.currentTable?.includes('test_table') ?? false))
+
+  await pg.dropDatabase(restoreDbName)
+})
+
+test('should report verbose output as structured events while restoring', async (t) => {
+  const restoreDbName = `${dbName}_events`
+  await pg.createDatabase(restoreDbName)
+
+  const pgRestore = new PgRestoreTool({
+    connection: {
+      host: pg.connectionInfo.host,
+      port: pg.connectionInfo.port,
+      username: pg.connectionInfo.username,
+      password: pg.connectionInfo.password,
+      database: restoreDbName,
+    },
+    programDir: path.join(pg.programDir, 'bin'),
+    config: {
+      file: dumpFilePath,
+      format: PgRestoreFormat.Custom,
+      noOwner: true,
+    },
+  })
+
+  try {
+    const events: VerboseEvent[] = []
+    const result = await pgRestore.executeWithEvents((event) => {
+      events.push(event)
+    })
+    // Let pending callbacks reach the JS thread
+    await new Promise((resolve) => setImmediate(resolve))
+
+    t.is(result.exitCode, 0, result.stderr)
+    const created = events.find((event) => event.action === VerboseAction.Create && event.objectType === 'TABLE')
+    t.is(created?.schema, 'public')
+    t.is(created?.name, 'test_table')
+    t.true(events.some((event) => event.action === VerboseAction.Data && event.name === 'test_table'))
+  } finally {
+    await pg.dropDatabase(restoreDbName)
+  }
+})
@@ -0,0 +1,39 @@
+import test from 'ava'
+import { PostgresInstance, comparePlans } from '../index.js'
+
+test.serial('comparePlans reports queries whose plans change after adding an index', async (t) => {
+  const pg = new PostgresInstance({ port: 0 })
+
+  try {
+    await pg.start()
+    await pg.createDatabase('plans')
+    await pg.executeSql(
+      `CREATE TABLE users (id int PRIMARY KEY, email text);
+       INSERT INTO users SELECT g, 'user' || g || '@example.com' FROM generate_series(1, 10000) g;
+       ANALYZE users;`,
+      {},
+      'plans',
+    )
+    const queries = ["SELECT * FROM users WHERE email = 'user42@example.com';", 'SELECT count(*) FROM users']
+
+    const before = await pg.snapshotPlans('plans', queries)
+    t.is(before.plans.length, 2)
+    t.true(before.plans[0].nodes.some((node) => node.nodeType === 'Seq Scan' && node.relationName === 'users'))
+    t.true(before.plans[0].totalCost > 0)
+    t.deepEqual(comparePlans(before, before), [])
+
+    await pg.executeSql('CREATE INDEX users_email_idx ON users (email); ANALYZE users;', {}, 'plans')
+    const after = await pg.snapshotPlans('plans', queries)
+    const differences = comparePlans(before, after)
+    t.is(differences.length, 1)
+    t.is(differences[0].kind, 'changed')
+    t.is(differences[0].query, queries[0])
+    t.true(differences[0].changes.some((line) => line.startsWith('+ ') && line.includes('users_email_idx')))
+
+    await t.throwsAsync(() => pg.snapshotPlans('plans', ['SELECT * FROM missing_table']), {
+      message: /Failed to plan query/,
+    })
+  } finally {
+    await pg.cleanup()
+  }
+})
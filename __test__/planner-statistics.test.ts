@@ -0,0 +1,49 @@
+import test from 'ava'
+import { PostgresInstance } from '../index.js'
+
+const schema = 'CREATE TABLE orders (id int PRIMARY KEY, status text, amount int);'
+
+async function columnStats(pg: PostgresInstance, databaseName: string) {
+  const [stats] = await pg.executeSqlBatch(
+    `SELECT attname, null_frac::text, n_distinct::text, most_common_vals::text
+     FROM pg_stats WHERE tablename = 'orders' ORDER BY attname;`,
+    {},
+    databaseName,
+  )
+  return stats.result!.rows
+}
+
+test.serial('exported planner statistics can be imported into a database with the same schema', async (t) => {
+  const pg = new PostgresInstance({ port: 0, serverConfig: { autovacuum: 'off' } })
+
+  try {
+    await pg.start()
+    await pg.createDatabase('source')
+    await pg.createDatabase('target')
+    await pg.executeSql(
+      `${schema}
+       INSERT INTO orders SELECT g, CASE WHEN g % 10 = 0 THEN 'open' ELSE 'done' END, g % 7
+       FROM generate_series(1, 5000) g;`,
+      {},
+      'source',
+    )
+    await pg.executeSql(schema, {}, 'target')
+
+    await pg.analyzeWithStatisticsTarget('source', 50, ['orders'])
+    await t.throwsAsync(() => pg.analyzeWithStatisticsTarget('source', 0), { message: /out of range/ })
+
+    const script = await pg.exportStatistics('source')
+    t.true(script.startsWith('-- Planner statistics of database source'))
+    t.true(script.includes('pg_restore_relation_stats'))
+    t.true(script.includes('pg_restore_attribute_stats'))
+
+    const restored = await pg.importStatistics('target', script)
+    t.true(restored >= 4)
+    t.deepEqual(await columnStats(pg, 'target'), await columnStats(pg, 'source'))
+
+    const [tuples] = await pg.executeSqlBatch("SELECT reltuples::text FROM pg_class WHERE relname = 'orders';", {}, 'target')
+    t.is(tuples.result!.rows[0][0], '5000')
+  } finally {
+    await pg.cleanup()
+  }
+})
@@ -0,0 +1,24 @@
+import test from 'ava'
+import { PostgresInstance } from '../index.js'
+
+test.serial('getPort and connectionInfo report the port resolved for port 0', async (t) => {
+  const instance = new PostgresInstance({ port: 0 })
+  const resolved: number[] = []
+  instance.onPortResolved((port) => resolved.push(port))
+  const configHash = instance.getConfigHash()
+
+  try {
+    t.is(instance.getPort(), 0)
+    await instance.start()
+    await new Promise((resolve) => setTimeout(resolve, 50))
+
+    const port = instance.getPort()
+    t.not(port, 0)
+    t.deepEqual(resolved, [port])
+    t.is(instance.connectionInfo.port, port)
+    t.true(instance.connectionInfo.connectionString.includes(`:${port}/`))
+    t.is(instance.getConfigHash(), configHash)
+  } finally {
+    await instance.cleanup()
+  }
+})
@@ -0,0 +1,49 @@
+import test from 'ava'
+import fs from 'node:fs/promises'
+import os from 'node:os'
+import path from 'node:path'
+import { PostgresInstance } from '../index.js'
+
+const lastAnalyzed = "SELECT last_analyze IS NOT NULL, last_vacuum IS NOT NULL FROM pg_stat_user_tables WHERE relname = 'items';"
+
+test.serial('postLoadOptimize() analyzes and optionally vacuums loaded tables', async (t) => {
+  const pg = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+
+  try {
+    await pg.start()
+    await pg.executeSql('CREATE TABLE items AS SELECT g AS id FROM generate_series(1, 1000) g;', {})
+
+    await pg.postLoadOptimize()
+    const analyzed = await pg.executeSql(lastAnalyzed, { tuplesOnly: true, fieldSeparator: ',', noAlign: true })
+    t.is(analyzed.stdout.trim(), 't,f')
+
+    await pg.postLoadOptimize(undefined, { vacuum: true })
+    const vacuumed = await pg.executeSql(lastAnalyzed, { tuplesOnly: true, fieldSeparator: ',', noAlign: true })
+    t.is(vacuumed.stdout.trim(), 't,t')
+  } finally {
+    await pg.cleanup()
+  }
+})
+
+test.serial('profiles run postLoadOptimize() after their seed scripts', async (t) => {
+  const seedDir = await fs.mkdtemp(path.join(os.tmpdir(), 'pg-embedded-post-load-'))
+  await fs.writeFile(path.join(seedDir, 'items.sql'), 'CREATE TABLE items AS SELECT g AS id FROM generate_series(1, 1000) g;')
+
+  const pg = PostgresInstance.fromProfile({
+    name: 'post-load',
+    settings: { username: 'postgres', password: 'password', port: 0 },
+    seedDir,
+    postLoadOptimize: {},
+  })
+
+  try {
+    await pg.start()
+    const stats = await pg.executeSql("SELECT reltuples::int FROM pg_class WHERE relname = 'items';", {
+      tuplesOnly: true,
+    })
+    t.is(stats.stdout.trim(), '1000')
+  } finally {
+    await pg.cleanup()
+    await fs.rm(seedDir, { recursive: true, force: true })
+  }
+})
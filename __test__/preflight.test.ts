@@ -0,0 +1,31 @@
+import test from 'ava'
+import { PostgresInstance, PreflightSeverity } from '../index.js'
+
+test('preflight() reports the checks without setting up the instance', async (t) => {
+  const pg = new PostgresInstance({ port: 0 })
+
+  const report = await pg.preflight()
+  t.is(report.passed, !report.findings.some((finding) => finding.severity === PreflightSeverity.Error))
+  for (const finding of report.findings) {
+    t.true(['openFiles', 'sharedMemory', 'semaphores', 'locale'].includes(finding.check))
+    t.true(finding.message.length > 0)
+  }
+  if (process.platform !== 'win32') {
+    t.true(report.findings.some((finding) => finding.check === 'openFiles'))
+  }
+})
+
+test('preflight() flags a locale in serverEnv that is not installed', async (t) => {
+  if (process.platform === 'win32') {
+    t.pass('locales are only checked on Unix')
+    return
+  }
+  const pg = new PostgresInstance({ port: 0, serverEnv: { LC_ALL: 'xx_XX.UTF-8' } })
+
+  const report = await pg.preflight()
+  const locale = report.findings.find((finding) => finding.check === 'locale')
+  t.false(report.passed)
+  t.is(locale?.severity, PreflightSeverity.Error)
+  t.regex(locale?.message ?? '', /LC_ALL=xx_XX\.UTF-8/)
+  t.truthy(locale?.remedy)
+})
@@ -0,0 +1,43 @@
+import test from 'ava'
+import { PostgresInstance } from '../index.js'
+
+test.serial('prepared transactions can be listed, committed and rolled back', async (t) => {
+  const instance = new PostgresInstance({
+    username: 'postgres',
+    password: 'password',
+    port: 0,
+    maxPreparedTransactions: 5,
+  })
+
+  try {
+    await instance.start()
+    await instance.createDatabase('payments_db')
+    await instance.executeSql('CREATE TABLE payments (id int)', {}, 'payments_db')
+    await instance.executeSql(
+      "BEGIN; INSERT INTO payments VALUES (1); PREPARE TRANSACTION 'pay-1';" +
+        "BEGIN; INSERT INTO payments VALUES (2); PREPARE TRANSACTION 'pay-2';",
+      {},
+      'payments_db',
+    )
+
+    const prepared = await instance.listPreparedTransactions()
+    t.deepEqual(
+      prepared.map(({ gid, owner, database }) => [gid, owner, database]),
+      [
+        ['pay-1', 'postgres', 'payments_db'],
+        ['pay-2', 'postgres', 'payments_db'],
+      ],
+    )
+
+    await instance.commitPrepared('pay-1')
+    await instance.rollbackPrepared('pay-2')
+    t.deepEqual(await instance.listPreparedTransactions(), [])
+
+    const rows = await instance.executeSql('SELECT id FROM payments', { tuplesOnly: true, noAlign: true }, 'payments_db')
+    t.is(rows.stdout.trim(), '1')
+
+    await t.throwsAsync(() => instance.commitPrepared('pay-1'), { message: /No prepared transaction/ })
+  } finally {
+    await instance.cleanup()
+  }
+})
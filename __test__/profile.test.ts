@@ -0,0 +1,44 @@
+import test from 'ava'
+import fs from 'node:fs/promises'
+import os from 'node:os'
+import path from 'node:path'
+import { PostgresInstance } from '../index.js'
+
+test.serial('fromProfile() applies server config, extensions, migrations and seeds', async (t) => {
+  const root = await fs.mkdtemp(path.join(os.tmpdir(), 'pg-embedded-profile-'))
+  const migrationsDir = path.join(root, 'migrations')
+  const seedDir = path.join(root, 'seeds')
+  await fs.mkdir(migrationsDir)
+  await fs.mkdir(seedDir)
+  await fs.writeFile(path.join(migrationsDir, '001_users.sql'), 'CREATE TABLE users (id INT PRIMARY KEY, name TEXT);')
+  await fs.writeFile(path.join(migrationsDir, '002_email.sql'), 'ALTER TABLE users ADD COLUMN email TEXT;')
+  await fs.writeFile(path.join(seedDir, 'users.sql'), "INSERT INTO users VALUES (1, 'alice', 'alice@example.com');")
+
+  const pg = PostgresInstance.fromProfile({
+    name: 'app',
+    settings: { databaseName: 'app_db', username: 'postgres', password: 'password', port: 0 },
+    serverConfig: { work_mem: '8MB' },
+    extensions: ['pg_trgm'],
+    migrationsDir,
+    seedDir,
+  })
+
+  try {
+    t.is(pg.profileName, 'app')
+    await pg.start()
+
+    const workMem = await pg.executeSql('SHOW work_mem;', { tuplesOnly: true })
+    t.is(workMem.stdout.trim(), '8MB')
+
+    const extension = await pg.executeSql("SELECT extname FROM pg_extension WHERE extname = 'pg_trgm';", {
+      tuplesOnly: true,
+    })
+    t.is(extension.stdout.trim(), 'pg_trgm')
+
+    const users = await pg.executeSql('SELECT email FROM users WHERE id = 1;', { tuplesOnly: true })
+    t.is(users.stdout.trim(), 'alice@example.com')
+  } finally {
+    await pg.cleanup()
+    await fs.rm(root, { recursive: true, force: true })
+  }
+})
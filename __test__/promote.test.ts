@@ -0,0 +1,48 @@
+import test from 'ava'
+import fs from 'node:fs/promises'
+import path from 'node:path'
+import { PgBasebackupTool, PgBasebackupWalMethod, PostgresInstance, ServerRole } from '../index.js'
+
+test.serial('promote() waits until the standby has left recovery', async (t) => {
+  const primary = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+  const standbyDir = path.resolve(`data/promote-standby-${Date.now()}`)
+  let standby: PostgresInstance | undefined
+
+  try {
+    await primary.start()
+    const info = primary.connectionInfo
+    const backup = await new PgBasebackupTool({
+      connection: { host: info.host, port: info.port, username: info.username, password: info.password },
+      programDir: path.join(primary.programDir, 'bin'),
+      config: { pgdata: standbyDir, walMethod: PgBasebackupWalMethod.Stream },
+    }).execute()
+    t.is(backup.exitCode, 0)
+    await fs.writeFile(path.join(standbyDir, 'standby.signal'), '')
+    await fs.appendFile(
+      path.join(standbyDir, 'postgresql.auto.conf'),
+      `primary_conninfo = '${info.connectionString}'\n`,
+    )
+
+    standby = new PostgresInstance({ username: 'postgres', password: 'password', port: 0, dataDir: standbyDir })
+    await standby.start(false)
+    t.is(await standby.getRole(), ServerRole.Standby)
+    t.is(await primary.getRole(), ServerRole.Primary)
+
+    await standby.pauseReplay()
+    const paused = await standby.getRecoveryStatus()
+    t.true(paused.replayPaused)
+    t.truthy(paused.replayLsn)
+    await standby.resumeReplay()
+    t.false((await standby.getRecoveryStatus()).replayPaused)
+
+    const timeline = await standby.promote()
+    t.is(timeline, 2)
+    t.is(await standby.getRole(), ServerRole.Primary)
+
+    await t.throwsAsync(() => primary.promote(), { message: /not a standby/ })
+  } finally {
+    await standby?.cleanup()
+    await primary.cleanup()
+    await fs.rm(standbyDir, { recursive: true, force: true })
+  }
+})
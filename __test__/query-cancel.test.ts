@@ -0,0 +1,58 @@
+import anyTest, { type TestFn } from 'ava'
+import { PostgresInstance } from '../index.js'
+
+const test = anyTest as TestFn<{ pg: PostgresInstance }>
+
+test.before(async (t) => {
+  const pg = new PostgresInstance({
+    databaseName: 'cancel_db',
+    username: 'postgres',
+    password: 'password',
+    port: 0,
+  })
+  await pg.start()
+  t.context.pg = pg
+})
+
+test.after.always(async (t) => {
+  await t.context.pg.cleanup()
+})
+
+async function waitForSleepingBackend(pg: PostgresInstance): Promise<number> {
+  for (let attempt = 0; attempt < 50; attempt++) {
+    const result = await pg.executeSql(
+      "SELECT pid FROM pg_stat_activity WHERE query LIKE 'SELECT pg_sleep(30)%' AND state = 'active';",
+      { tuplesOnly: true, noAlign: true },
+    )
+    const pid = parseInt(result.stdout.trim(), 10)
+    if (!Number.isNaN(pid)) {
+      return pid
+    }
+    await new Promise((resolve) => setTimeout(resolve, 100))
+  }
+  throw new Error('sleeping backend did not show up in pg_stat_activity')
+}
+
+test.serial('cancelQuery() cancels the statement of a single backend', async (t) => {
+  const { pg } = t.context
+  const running = pg.executeSql('SELECT pg_sleep(30);', {})
+  const pid = await waitForSleepingBackend(pg)
+
+  t.true(await pg.cancelQuery(pid))
+
+  const result = await running
+  t.not(result.exitCode, 0)
+  t.true(result.stderr.includes('canceling statement due to user request'))
+})
+
+test.serial('cancelAllQueries() cancels active queries in a database', async (t) => {
+  const { pg } = t.context
+  const running = pg.executeSql('SELECT pg_sleep(30);', {})
+  await waitForSleepingBackend(pg)
+
+  t.is(await pg.cancelAllQueries('cancel_db'), 1)
+
+  const result = await running
+  t.not(result.exitCode, 0)
+  t.is(await pg.cancelAllQueries('cancel_db'), 0)
+})
@@ -0,0 +1,43 @@
+import test from 'ava'
+import { PostgresInstance, type QueryLogEvent } from '../index.js'
+
+const settle = () => new Promise((resolve) => setTimeout(resolve, 50))
+
+test.serial('setQueryLogHook reports native queries with their duration and outcome', async (t) => {
+  const instance = new PostgresInstance({ port: 0 })
+  try {
+    await instance.start()
+    const events: QueryLogEvent[] = []
+    instance.setQueryLogHook((event) => events.push(event))
+
+    await instance.executeSqlBatch('CREATE TABLE logged (id int);\nINSERT INTO logged VALUES (1), (2);')
+    await instance.runInRollbackTransaction(async (tx) => {
+      await tx.query('SELECT * FROM logged')
+      await t.throwsAsync(() => tx.query('SELECT * FROM missing'))
+    })
+    await settle()
+
+    t.deepEqual(
+      events.map((event) => event.sql),
+      ['CREATE TABLE logged (id int)', 'INSERT INTO logged VALUES (1), (2)', 'SELECT * FROM logged', 'SELECT * FROM missing'],
+    )
+    t.is(events[1].rowCount, 2)
+    t.true(events.every((event) => event.durationMs >= 0))
+    t.regex(events[3].error ?? '', /does not exist/)
+
+    events.length = 0
+    instance.setQueryLogHook((event) => events.push(event), { slowQueryMs: 60_000 })
+    await instance.executeSqlBatch('SELECT 1;\nSELECT 1/0;', { continueOnError: true })
+    await settle()
+    t.is(events.length, 1)
+    t.regex(events[0].error ?? '', /division by zero/)
+
+    instance.clearQueryLogHook()
+    events.length = 0
+    await instance.executeSqlBatch('SELECT 1')
+    await settle()
+    t.is(events.length, 0)
+  } finally {
+    await instance.cleanup()
+  }
+})
@@ -0,0 +1,56 @@
+import test from 'ava'
+import { PostgresInstance } from '../index.js'
+
+test.serial('query parameters are bound as values, never as SQL', async (t) => {
+  const pg = new PostgresInstance({ port: 0 })
+
+  try {
+    await pg.start()
+    const client = await pg.connect()
+    try {
+      await client.query('CREATE TABLE users (id int, name text, active boolean)')
+      for (const [id, name, active] of [
+        [1, "O'Reilly", true],
+        [2, "'; DROP TABLE users; --", false],
+        [3, null, null],
+      ] as const) {
+        await client.query('INSERT INTO users VALUES ($1, $2, $3)', [id, name, active])
+      }
+
+      const byId = await client.query('SELECT name FROM users WHERE id = $1', [1])
+      t.deepEqual(byId.rows, [{ name: "O'Reilly" }])
+
+      const byName = await client.query('SELECT id FROM users WHERE name = $1', ["'; DROP TABLE users; --"])
+      t.deepEqual(byName.rows, [{ id: 2 }])
+
+      const nulls = await client.query('SELECT id FROM users WHERE name IS NULL AND active IS NOT DISTINCT FROM $1', [null])
+      t.deepEqual(nulls.rows, [{ id: 3 }])
+
+      // `$1` inside a literal is not a parameter reference
+      const literal = await client.query("SELECT '$1' AS text, $1::int + 1 AS next", [41])
+      t.deepEqual(literal.rows, [{ text: '$1', next: 42 }])
+
+      await t.throwsAsync(client.query('SELECT $1, $2', ['only one']), { message: /\$2/ })
+
+      // Numbers and booleans keep their type
+      const typed = await client.query('SELECT $1 AS answer, $2 AS ratio, $3 AS flag', [42, 0.5, true])
+      t.deepEqual(typed.rows, [{ answer: 42, ratio: 0.5, flag: true }])
+
+      await t.throwsAsync(client.query('SELECT $1; SELECT 2', [1]), { message: /single SQL statement/ })
+
+      // Strings take the type the SQL expects, converted by the server
+      const resolved = await client.query(
+        'SELECT $1 + 1 AS next, $2::date AS day, $3 = ANY($4) AS found',
+        ['41', '2024-02-29', 'b', '{a,b}'],
+      )
+      t.deepEqual(resolved.rows, [{ next: 42, day: '2024-02-29', found: true }])
+
+      const structured = await pg.executeSqlStructured('SELECT count(*) AS count FROM users WHERE id > $1', undefined, [1])
+      t.deepEqual(structured.rows, [{ count: '2' }])
+    } finally {
+      await client.close()
+    }
+  } finally {
+    await pg.cleanup()
+  }
+})
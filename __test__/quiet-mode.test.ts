@@ -0,0 +1,38 @@
+import test from 'ava'
+import { spawnSync } from 'node:child_process'
+import { fileURLToPath } from 'node:url'
+import { isQuietMode, PostgresInstance, setQuietMode } from '../index.js'
+
+const indexPath = fileURLToPath(new URL('../index.js', import.meta.url))
+
+test.serial('setQuietMode() toggles quiet mode', (t) => {
+  t.false(isQuietMode())
+  setQuietMode(true)
+  t.true(isQuietMode())
+  setQuietMode(false)
+  t.false(isQuietMode())
+})
+
+test.serial('quiet: true keeps quiet mode on only until the instance is cleaned up', async (t) => {
+  const instance = new PostgresInstance({ port: 0, quiet: true })
+  t.true(isQuietMode())
+  await instance.cleanup()
+  t.false(isQuietMode())
+})
+
+test.serial('an instance with quiet: true prints nothing on stdout or stderr', (t) => {
+  const script = `
+    const { initLogger, LogLevel, PostgresInstance } = await import(${JSON.stringify(indexPath)})
+    initLogger(LogLevel.Trace)
+    const instance = new PostgresInstance({ port: 0, quiet: true })
+    await instance.start()
+    await instance.executeSql('SELECT 1', {})
+    await instance.stop()
+    await instance.cleanup()
+  `
+  const child = spawnSync(process.execPath, ['--input-type=module', '-e', script], { encoding: 'utf8' })
+
+  t.is(child.status, 0, child.stderr)
+  t.is(child.stdout, '')
+  t.is(child.stderr, '')
+})
@@ -0,0 +1,39 @@
+import test from 'ava'
+import { PostgresInstance, verifyReadYourWrites } from '../index.js'
+
+test.serial('verifyReadYourWrites() reports the lag until a replica sees a write', async (t) => {
+  const primary = new PostgresInstance({ port: 0 })
+  let replica: PostgresInstance | undefined
+
+  try {
+    await primary.start()
+    replica = await primary.createReplica()
+
+    const first = await verifyReadYourWrites(primary, replica, { table: 'ryw_markers' })
+    t.true(first.lagMs >= 0)
+    const second = await verifyReadYourWrites(primary, replica, { table: 'ryw_markers' })
+    t.not(second.marker, first.marker)
+
+    const markers = await primary.executeSql('SELECT count(*) FROM ryw_markers;', { tuplesOnly: true })
+    t.is(markers.stdout.trim(), '2')
+  } finally {
+    await replica?.cleanup()
+    await primary.cleanup()
+  }
+})
+
+test.serial('verifyReadYourWrites() fails when the write never reaches the instance', async (t) => {
+  const primary = new PostgresInstance({ port: 0 })
+  const unrelated = new PostgresInstance({ port: 0 })
+
+  try {
+    await primary.start()
+    await unrelated.start()
+    await t.throwsAsync(() => verifyReadYourWrites(primary, unrelated, { timeoutMs: 300 }), {
+      message: /did not see marker/,
+    })
+  } finally {
+    await unrelated.cleanup()
+    await primary.cleanup()
+  }
+})
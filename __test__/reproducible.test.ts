@@ -0,0 +1,73 @@
+import test from 'ava'
+import fs from 'node:fs/promises'
+import os from 'node:os'
+import path from 'node:path'
+import { PostgresInstance } from '../index.js'
+
+const count = async (pg: PostgresInstance, sql: string, databaseName?: string) => {
+  const result = await pg.executeSql(sql, { tuplesOnly: true }, databaseName)
+  return result.stdout.trim()
+}
+
+const randomValues = async (pg: PostgresInstance, databaseName?: string) => {
+  const result = await pg.executeSql('SELECT round(random() * 1000000) FROM generate_series(1, 3);', { tuplesOnly: true }, databaseName)
+  return result.stdout.trim()
+}
+
+test.serial('the reproducible preset fixes output settings and seeds every session', async (t) => {
+  const pg = new PostgresInstance({ port: 0, reproducible: true, databaseName: 'golden' })
+
+  try {
+    await pg.start()
+    const settings = await pg.executeSql(
+      "SELECT current_setting('TimeZone'), current_setting('DateStyle'), current_setting('lc_messages');",
+      { tuplesOnly: true },
+    )
+    t.is(settings.stdout.trim(), 'UTC | ISO, MDY | C')
+
+    // Every session starts from the same seed, in existing and newly created databases
+    const first = await randomValues(pg, 'golden')
+    t.is(await randomValues(pg, 'golden'), first)
+    await pg.createDatabase('later')
+    t.is(await randomValues(pg, 'later'), first)
+  } finally {
+    await pg.cleanup()
+  }
+})
+
+test.serial('explicit settings override the reproducible preset', async (t) => {
+  const pg = new PostgresInstance({ port: 0, reproducible: true, timezone: 'Europe/Amsterdam' })
+
+  try {
+    await pg.start()
+    const timezone = await pg.executeSql('SHOW timezone;', { tuplesOnly: true })
+    t.is(timezone.stdout.trim(), 'Europe/Amsterdam')
+  } finally {
+    await pg.cleanup()
+  }
+})
+
+test.serial('the session seed lives in its own schema and is removed when a persistent instance stops', async (t) => {
+  const dataDir = path.join(os.tmpdir(), `pg-embedded-reproducible-${Date.now()}`)
+  const pg = new PostgresInstance({ port: 0, reproducible: true, persistent: true, dataDir })
+  let plain: PostgresInstance | undefined
+
+  try {
+    await pg.start()
+    t.is(await count(pg, "SELECT count(*) FROM pg_proc WHERE pronamespace = '_pg_embedded'::regnamespace;"), '1')
+    t.is(await count(pg, "SELECT count(*) FROM pg_proc WHERE pronamespace = 'public'::regnamespace;"), '0')
+    await pg.createDatabase('later')
+    await pg.stop()
+
+    plain = new PostgresInstance({ port: 0, persistent: true, dataDir })
+    await plain.start()
+    for (const database of ['template1', 'later']) {
+      t.is(await count(plain, 'SELECT count(*) FROM pg_event_trigger;', database), '0')
+      t.is(await count(plain, "SELECT count(*) FROM pg_namespace WHERE nspname = '_pg_embedded';", database), '0')
+    }
+  } finally {
+    await plain?.cleanup()
+    await pg.cleanup()
+    await fs.rm(dataDir, { recursive: true, force: true })
+  }
+})
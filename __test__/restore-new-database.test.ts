@@ -0,0 +1,59 @@
+import test from 'ava'
+import fs from 'node:fs/promises'
+import path from 'node:path'
+import { PgDumpFormat, PostgresInstance } from '../index.js'
+
+test.serial('restoreIntoNewDatabase() creates the database and verifies the restored objects', async (t) => {
+  const pg = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+  const archive = path.resolve(`data/restore-new-${Date.now()}.dump`)
+
+  try {
+    await pg.start()
+    await fs.mkdir(path.dirname(archive), { recursive: true })
+    await pg.executeSql(
+      `CREATE TABLE orders (id serial PRIMARY KEY, total numeric);
+       INSERT INTO orders (total) VALUES (10), (20);
+       CREATE VIEW big_orders AS SELECT * FROM orders WHERE total > 15;`,
+      {},
+    )
+    const dump = await pg.createDump({ file: archive, format: PgDumpFormat.Custom })
+    t.is(dump.exitCode, 0, dump.stderr)
+
+    const restored = await pg.restoreIntoNewDatabase(archive, 'orders_copy')
+    t.is(restored.databaseName, 'orders_copy')
+    t.is(restored.result.exitCode, 0)
+    t.deepEqual(
+      restored.objectCounts.map(({ objectType, expected, actual }) => [objectType, expected, actual]),
+      [
+        ['TABLE', 1, 1],
+        ['VIEW', 1, 1],
+        ['MATERIALIZED VIEW', 0, 0],
+        ['SEQUENCE', 1, 1],
+      ],
+    )
+    const rows = await pg.executeSql('SELECT count(*) FROM big_orders', { tuplesOnly: true }, 'orders_copy')
+    t.is(rows.stdout.trim(), '1')
+
+    await t.throwsAsync(() => pg.restoreIntoNewDatabase(archive, 'orders_copy'), { message: /already exists/ })
+  } finally {
+    await pg.cleanup()
+    await fs.rm(archive, { force: true })
+  }
+})
+
+test.serial('restoreIntoNewDatabase() drops the database when the restore fails', async (t) => {
+  const pg = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+  const archive = path.resolve(`data/restore-invalid-${Date.now()}.dump`)
+
+  try {
+    await pg.start()
+    await fs.mkdir(path.dirname(archive), { recursive: true })
+    await fs.writeFile(archive, 'not an archive')
+
+    await t.throwsAsync(() => pg.restoreIntoNewDatabase(archive, 'broken'), { message: /Failed to restore/ })
+    t.false(await pg.databaseExists('broken'))
+  } finally {
+    await pg.cleanup()
+    await fs.rm(archive, { force: true })
+  }
+})
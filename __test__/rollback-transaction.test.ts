@@ -0,0 +1,87 @@
+import test from 'ava'
+import { PostgresInstance, type Transaction } from '../index.js'
+
+test.serial('runInRollbackTransaction() rolls back everything the callback wrote', async (t) => {
+  const instance = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+
+  try {
+    await instance.start()
+    await instance.executeSql('CREATE TABLE users (id int PRIMARY KEY, name text)', {})
+
+    await instance.runInRollbackTransaction(async (tx) => {
+      const inserted = await tx.query("INSERT INTO users VALUES (1, 'alice'), (2, NULL)")
+      t.is(inserted.rowCount, 2)
+
+      // A failing query only undoes itself
+      await t.throwsAsync(() => tx.query("INSERT INTO users VALUES (1, 'duplicate')"), {
+        message: /duplicate key/,
+      })
+
+      const result = await tx.query('SELECT id, name FROM users ORDER BY id')
+      t.deepEqual(result.columns, ['id', 'name'])
+      t.deepEqual(result.rows, [
+        ['1', 'alice'],
+        ['2', null],
+      ])
+    })
+
+    const count = await instance.executeSql('SELECT count(*) FROM users', { tuplesOnly: true, noAlign: true })
+    t.is(count.stdout.trim(), '0')
+  } finally {
+    await instance.cleanup()
+  }
+})
+
+test.serial('runInRollbackTransaction() rolls back when the callback throws', async (t) => {
+  const instance = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+
+  try {
+    await instance.start()
+    await instance.executeSql('CREATE TABLE events (id int)', {})
+
+    let handle: Transaction | undefined
+    await t.throwsAsync(
+      () =>
+        instance.runInRollbackTransaction(async (tx) => {
+          handle = tx
+          await tx.query('INSERT INTO events VALUES (1)')
+          throw new Error('test failed')
+        }),
+      { message: /test failed/ },
+    )
+    await t.throwsAsync(() => handle!.query('SELECT 1'), { message: /already ended/ })
+
+    const count = await instance.executeSql('SELECT count(*) FROM events', { tuplesOnly: true, noAlign: true })
+    t.is(count.stdout.trim(), '0')
+  } finally {
+    await instance.cleanup()
+  }
+})
+
+test.serial('runInRollbackTransaction() refuses to commit', async (t) => {
+  const instance = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+
+  try {
+    await instance.start()
+    await instance.executeSql('CREATE TABLE orders (id int)', {})
+
+    await instance.runInRollbackTransaction(async (tx) => {
+      await tx.query('INSERT INTO orders VALUES (1)')
+      await t.throwsAsync(() => tx.commit(), { message: /cannot be committed/ })
+    })
+    const count = await instance.executeSql('SELECT count(*) FROM orders', { tuplesOnly: true, noAlign: true })
+    t.is(count.stdout.trim(), '0')
+
+    // A COMMIT run as SQL cannot be prevented, but is reported
+    await t.throwsAsync(
+      () =>
+        instance.runInRollbackTransaction(async (tx) => {
+          await tx.query('INSERT INTO orders VALUES (2)')
+          await tx.query('COMMIT').catch(() => {})
+        }),
+      { message: /ended by SQL run through it/ },
+    )
+  } finally {
+    await instance.cleanup()
+  }
+})
@@ -0,0 +1,38 @@
+import test from 'ava'
+import { PostgresInstance } from '../index.js'
+
+test.serial('undoLastDestructiveOp() recreates a database dropped in safe mode', async (t) => {
+  const instance = new PostgresInstance({ port: 0, safeMode: true })
+
+  try {
+    await instance.start()
+    await instance.createDatabase('safe_app')
+    await instance.executeSql("CREATE TABLE notes (body text); INSERT INTO notes VALUES ('kept');", {}, 'safe_app')
+
+    await instance.dropDatabase('safe_app')
+    t.false(await instance.databaseExists('safe_app'))
+
+    const undone = await instance.undoLastDestructiveOp()
+    t.is(undone.operation, 'dropDatabase')
+    t.is(undone.databaseName, 'safe_app')
+    const notes = await instance.executeSql('SELECT body FROM notes;', { tuplesOnly: true }, 'safe_app')
+    t.is(notes.stdout.trim(), 'kept')
+
+    await t.throwsAsync(() => instance.undoLastDestructiveOp(), { message: /no destructive operation/ })
+  } finally {
+    await instance.cleanup()
+  }
+})
+
+test.serial('without safe mode nothing is recorded', async (t) => {
+  const instance = new PostgresInstance({ port: 0 })
+
+  try {
+    await instance.start()
+    await instance.createDatabase('unsafe_app')
+    t.true(await instance.dropDatabaseIfExists('unsafe_app'))
+    await t.throwsAsync(() => instance.undoLastDestructiveOp(), { message: /no destructive operation/ })
+  } finally {
+    await instance.cleanup()
+  }
+})
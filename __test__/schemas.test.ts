@@ -0,0 +1,32 @@
+import test from 'ava'
+import { PostgresInstance } from '../index.js'
+
+test.serial('createSchema(), listSchemas() and dropSchema() manage tenant schemas', async (t) => {
+  const instance = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+
+  try {
+    await instance.start()
+    await instance.executeSql('CREATE ROLE acme_app', {})
+
+    await instance.createSchema(undefined, 'tenant_acme', 'acme_app')
+    await instance.createSchema(undefined, 'Tenant Beta')
+    t.deepEqual(await instance.listSchemas(), [
+      { name: 'Tenant Beta', owner: 'postgres' },
+      { name: 'public', owner: 'pg_database_owner' },
+      { name: 'tenant_acme', owner: 'acme_app' },
+    ])
+
+    await t.throwsAsync(() => instance.createSchema(undefined, 'tenant_acme'), { message: /already exists/ })
+
+    await instance.executeSql('CREATE TABLE tenant_acme.users (id int)', {})
+    await t.throwsAsync(() => instance.dropSchema(undefined, 'tenant_acme'), { message: /depend/ })
+    await instance.dropSchema(undefined, 'tenant_acme', true)
+    await instance.dropSchema(undefined, 'Tenant Beta')
+    t.deepEqual(
+      (await instance.listSchemas()).map((schema) => schema.name),
+      ['public'],
+    )
+  } finally {
+    await instance.cleanup()
+  }
+})
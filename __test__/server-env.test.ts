@@ -0,0 +1,29 @@
+import test from 'ava'
+import { PostgresInstance } from '../index.js'
+
+test.serial('serverEnv is passed to the server process', async (t) => {
+  const pg = new PostgresInstance({ port: 0, serverEnv: { PG_EMBEDDED_TEST_ENV: 'from-settings' } })
+
+  try {
+    t.deepEqual(pg.serverEnv, { PG_EMBEDDED_TEST_ENV: 'from-settings' })
+    await pg.start()
+    t.not(pg.port, 0)
+
+    if (process.platform === 'linux') {
+      // Backends inherit the environment of the postmaster
+      const result = await pg.executeSql(
+        "SELECT convert_from(pg_read_binary_file('/proc/' || pg_backend_pid() || '/environ'), 'SQL_ASCII');",
+        { tuplesOnly: true },
+      )
+      t.true(result.stdout.includes('PG_EMBEDDED_TEST_ENV=from-settings'))
+    }
+  } finally {
+    await pg.cleanup()
+  }
+})
+
+test('invalid serverEnv names are rejected', (t) => {
+  t.throws(() => new PostgresInstance({ serverEnv: { 'A=B': 'x' } }), {
+    message: /Invalid server environment variable name/,
+  })
+})
@@ -0,0 +1,39 @@
+import test from 'ava'
+import { PostgresInstance, ShardSet } from '../index.js'
+
+test.serial('ShardSet routes rows to the shard owning their key', async (t) => {
+  const instances = await PostgresInstance.createMany(2, { port: 0 })
+
+  try {
+    const shards = new ShardSet({
+      shards: instances.map((instance) => instance.connectionInfo.connectionString),
+    })
+    t.is(shards.shardCount, 2)
+
+    await shards.applySchema('CREATE TABLE users (id text PRIMARY KEY, name text);')
+    const rows = Array.from({ length: 20 }, (_, i) => ({ id: `user-${i}`, name: `User ${i}` }))
+    const counts = await shards.insertRows('users', 'id', rows)
+    t.is(counts.length, 2)
+    t.is(counts[0] + counts[1], 20)
+    t.true(counts.every((count) => count > 0))
+
+    for (const row of rows.slice(0, 5)) {
+      const owner = shards.shardFor(row.id)
+      t.is(shards.connectionStringFor(row.id), instances[owner].connectionInfo.connectionString)
+      const result = await shards.executeForKey(row.id, `SELECT name FROM users WHERE id = '${row.id}'`)
+      t.deepEqual(result.rows, [[row.name]])
+    }
+  } finally {
+    await Promise.all(instances.map((instance) => instance.cleanup()))
+  }
+})
+
+test('ShardSet requires at least one shard and a key for every row', async (t) => {
+  t.throws(() => new ShardSet({ shards: [] }), { message: /at least one shard/ })
+
+  const shards = new ShardSet({ shards: ['postgresql://postgres@localhost:1/postgres'] })
+  t.is(shards.shardFor('anything'), 0)
+  await t.throwsAsync(() => shards.insertRows('users', 'id', [{ name: 'no key' }]), {
+    message: /key column 'id'/,
+  })
+})
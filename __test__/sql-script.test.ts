@@ -0,0 +1,55 @@
+import test from 'ava'
+import { PostgresInstance, SqlStatementKind, splitSqlScript } from '../index.js'
+
+const script = `
+-- schema
+CREATE TABLE notes (id int PRIMARY KEY, body text);
+CREATE FUNCTION note_count() RETURNS bigint AS $$
+BEGIN
+  RETURN (SELECT count(*) FROM notes);
+END;
+$$ LANGUAGE plpgsql;
+COPY notes (id, body) FROM stdin;
+1\tfirst; with a semicolon
+2\t'quoted'
+\\.
+INSERT INTO notes VALUES (3, 'third;');
+`
+
+test('splitSqlScript keeps function bodies, strings and COPY data together', (t) => {
+  const statements = splitSqlScript(script)
+  t.is(statements.length, 4)
+  t.true(statements[1].sql.endsWith('LANGUAGE plpgsql'))
+  t.is(statements[2].kind, SqlStatementKind.CopyFromStdin)
+  t.is(statements[2].copyData, "1\tfirst; with a semicolon\n2\t'quoted'\n")
+  t.is(statements[3].line, 13)
+  t.is(splitSqlScript('\\connect app\nSELECT 1')[0].kind, SqlStatementKind.MetaCommand)
+})
+
+test.serial('executeSqlBatch runs the statements of a script', async (t) => {
+  const instance = new PostgresInstance({ port: 0 })
+  try {
+    await instance.start()
+    const results = await instance.executeSqlBatch(script, { transaction: true })
+    t.is(results.length, 4)
+    t.is(results[2].result?.rowCount, 2)
+
+    const count = await instance.executeSqlBatch('SELECT note_count()')
+    t.deepEqual(count[0].result?.rows, [['3']])
+
+    const error = await t.throwsAsync(() =>
+      instance.executeSqlBatch("INSERT INTO notes VALUES (4, 'x');\nINSERT INTO notes VALUES (1, 'dup');", {
+        transaction: true,
+      }),
+    )
+    t.regex(error!.message, /Statement at line 2 failed/)
+    const afterRollback = await instance.executeSqlBatch('SELECT count(*) FROM notes')
+    t.deepEqual(afterRollback[0].result?.rows, [['3']])
+
+    const continued = await instance.executeSqlBatch('SELECT 1/0;\nSELECT 2;', { continueOnError: true })
+    t.regex(continued[0].error ?? '', /division by zero/)
+    t.deepEqual(continued[1].result?.rows, [['2']])
+  } finally {
+    await instance.cleanup()
+  }
+})
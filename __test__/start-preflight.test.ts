@@ -0,0 +1,25 @@
+import test from 'ava'
+import { FailurePhase, InstanceState, PostgresInstance } from '../index.js'
+
+test.serial('start(false) rejects an uninitialized data directory', async (t) => {
+  const pg = new PostgresInstance({ port: 0, dataDir: `data/preflight-${Date.now()}` })
+
+  try {
+    await t.throwsAsync(() => pg.start(false), { message: /not initialized - call setup\(\)/ })
+    t.is(pg.state, InstanceState.Failed)
+    t.is(pg.getLastError()?.phase, FailurePhase.Setup)
+  } finally {
+    await pg.cleanup()
+  }
+})
+
+test.serial('start(false) runs setup when autoSetup is enabled', async (t) => {
+  const pg = new PostgresInstance({ port: 0, dataDir: `data/preflight-auto-${Date.now()}`, autoSetup: true })
+
+  try {
+    await pg.start(false)
+    t.is(pg.state, InstanceState.Running)
+  } finally {
+    await pg.cleanup()
+  }
+})
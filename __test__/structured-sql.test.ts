@@ -0,0 +1,28 @@
+import test from 'ava'
+import { PostgresInstance } from '../index.js'
+
+test.serial('executeSqlStructured returns rows keyed by column name', async (t) => {
+  const pg = new PostgresInstance({ port: 0 })
+
+  try {
+    await pg.start()
+    await pg.executeSql(
+      "CREATE TABLE users (id int, email text); INSERT INTO users VALUES (1, 'a@example.com'), (2, NULL);",
+      {},
+    )
+
+    const result = await pg.executeSqlStructured('SELECT id, email FROM users ORDER BY id')
+    t.deepEqual(result.columns, ['id', 'email'])
+    t.is(result.rowCount, 2)
+    t.deepEqual(result.rows[0], { id: '1', email: 'a@example.com' })
+    t.is(result.rows[1].email, null)
+
+    const updated = await pg.executeSqlStructured("UPDATE users SET email = 'b@example.com' WHERE id = 2")
+    t.is(updated.rowCount, 1)
+    t.deepEqual(updated.rows, [])
+
+    await t.throwsAsync(pg.executeSqlStructured('SELECT * FROM missing_table'), { message: /missing_table/ })
+  } finally {
+    await pg.cleanup()
+  }
+})
@@ -0,0 +1,64 @@
+import test from 'ava'
+import { PostgresInstance } from '../index.js'
+
+test.serial('suggestIndexes reports sequential-scan-heavy tables and their filtered columns', async (t) => {
+  const pg = new PostgresInstance({
+    port: 0,
+    serverConfig: { shared_preload_libraries: 'pg_stat_statements' },
+  })
+
+  try {
+    await pg.start()
+    await pg.createDatabase('advisor')
+    await pg.executeSql(
+      `CREATE EXTENSION pg_stat_statements;
+       CREATE TABLE users (id int PRIMARY KEY, email text, country text);
+       INSERT INTO users SELECT g, 'user' || g || '@example.com', 'NL' FROM generate_series(1, 2000) g;
+       ANALYZE users;`,
+      {},
+      'advisor',
+    )
+    for (let i = 0; i < 12; i++) {
+      await pg.executeSql(`SELECT * FROM users WHERE email = 'user${i}@example.com';`, {}, 'advisor')
+    }
+
+    const advice = await pg.suggestIndexes('advisor')
+    t.true(advice.statementsAvailable)
+    const users = advice.suggestions.find((suggestion) => suggestion.tableName === 'users')
+    t.truthy(users)
+    t.true(users!.seqScans >= 12)
+    t.deepEqual(users!.columns, ['email'])
+    t.is(users!.createIndexSql, 'CREATE INDEX ON "public"."users" ("email")')
+    t.true(advice.frequentPredicates.some((predicate) => predicate.columns.includes('email')))
+
+    const strict = await pg.suggestIndexes('advisor', { minRows: 1_000_000 })
+    t.deepEqual(strict.suggestions, [])
+  } finally {
+    await pg.cleanup()
+  }
+})
+
+test.serial('suggestIndexes works without pg_stat_statements', async (t) => {
+  const pg = new PostgresInstance({ port: 0 })
+
+  try {
+    await pg.start()
+    await pg.executeSql(
+      `CREATE TABLE events (id int, kind text);
+       INSERT INTO events SELECT g, 'click' FROM generate_series(1, 20) g;`,
+      {},
+    )
+    for (let i = 0; i < 3; i++) {
+      await pg.executeSql("SELECT count(*) FROM events WHERE kind = 'click';", {})
+    }
+
+    const advice = await pg.suggestIndexes('postgres', { minRows: 10, minSeqScans: 3 })
+    t.false(advice.statementsAvailable)
+    const events = advice.suggestions.find((suggestion) => suggestion.tableName === 'events')
+    t.truthy(events)
+    t.deepEqual(events!.columns, [])
+    t.is(events!.createIndexSql, undefined)
+  } finally {
+    await pg.cleanup()
+  }
+})
@@ -0,0 +1,58 @@
+import test from 'ava'
+import path from 'node:path'
+import { type ConnectionInfo, PostgresInstance, compareData } from '../index.js'
+
+const connection = ({ host, port, username, password, databaseName }: ConnectionInfo) => ({
+  host,
+  port,
+  username,
+  password,
+  database: databaseName,
+})
+
+test.serial('tableChecksums() and compareData() detect equal and differing tables', async (t) => {
+  const a = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+  const b = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+
+  try {
+    await a.start()
+    await b.start()
+    await a.executeSql(
+      "CREATE TABLE users (id int, name text); INSERT INTO users VALUES (1, 'alice'), (2, 'bob');" +
+        'CREATE TABLE orders (id int); INSERT INTO orders VALUES (1);',
+      {},
+    )
+    // Same users in a different physical order, different orders
+    await b.executeSql(
+      "CREATE TABLE users (id int, name text); INSERT INTO users VALUES (2, 'bob'), (1, 'alice');" +
+        'CREATE TABLE orders (id int); INSERT INTO orders VALUES (2);',
+      {},
+    )
+
+    const checksums = await a.tableChecksums()
+    t.deepEqual(
+      checksums.map(({ table, rowCount }) => [table, rowCount]),
+      [
+        ['public.orders', 1],
+        ['public.users', 2],
+      ],
+    )
+    t.deepEqual(await b.tableChecksums(undefined, ['users']), [checksums[1]].map((c) => ({ ...c, table: 'users' })))
+
+    const programDir = path.join(a.programDir, 'bin')
+    const results = await compareData(connection(a.connectionInfo), connection(b.connectionInfo), ['users', 'orders', 'missing'], programDir)
+    t.deepEqual(
+      results.map(({ table, matches }) => [table, matches]),
+      [
+        ['users', true],
+        ['orders', false],
+        ['missing', false],
+      ],
+    )
+    t.false(results[2].a.exists)
+    t.is(results[2].a.checksum, undefined)
+  } finally {
+    await b.cleanup()
+    await a.cleanup()
+  }
+})
@@ -0,0 +1,35 @@
+import test from 'ava'
+import fs from 'node:fs/promises'
+import os from 'node:os'
+import path from 'node:path'
+import { findInstances, PostgresInstance } from '../index.js'
+
+test.serial('temporary data directories are created under tempDirParent with tempDirPrefix', async (t) => {
+  const parent = path.resolve(`data/.cache-${Date.now()}`)
+  const pg = new PostgresInstance({ port: 0, tempDirParent: parent, tempDirPrefix: 'app-test' })
+
+  try {
+    await pg.start()
+    t.is(path.dirname(pg.dataDir), parent)
+    t.regex(path.basename(pg.dataDir), /^app-test-[0-9a-f]{12}$/)
+  } finally {
+    await pg.cleanup()
+    await fs.rm(parent, { recursive: true, force: true })
+  }
+})
+
+test.serial('named instances combine tempDirPrefix with the instance name', async (t) => {
+  const pg = new PostgresInstance({ name: 'temp-dir-test', tempDirPrefix: 'app-test' })
+
+  try {
+    const [found] = findInstances({ name: 'temp-dir-test' })
+    t.is(path.dirname(found.dataDir), os.tmpdir())
+    t.regex(path.basename(found.dataDir), /^app-test-temp-dir-test-[0-9a-f]{12}$/)
+  } finally {
+    await pg.cleanup()
+  }
+})
+
+test('invalid temporary directory prefixes are rejected', (t) => {
+  t.throws(() => new PostgresInstance({ tempDirPrefix: 'app/test' }), { message: /tempDirPrefix/ })
+})
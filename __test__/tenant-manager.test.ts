@@ -0,0 +1,67 @@
+import test from 'ava'
+import { PostgresInstance, TenantStrategy } from '../index.js'
+
+const count = async (instance: PostgresInstance, sql: string, database?: string) =>
+  (await instance.executeSql(sql, { tuplesOnly: true, noAlign: true }, database)).stdout.trim()
+
+test.serial('TenantManager provisions schema tenants from a template schema', async (t) => {
+  const instance = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+
+  try {
+    await instance.start()
+    await instance.executeSql(
+      "CREATE SCHEMA template; CREATE TABLE template.plans (name text); INSERT INTO template.plans VALUES ('free');",
+      {},
+    )
+    const tenants = instance.createTenantManager({ strategy: TenantStrategy.Schema, template: 'template' })
+
+    const acme = await tenants.provision('acme')
+    t.is(acme.schema, 'tenant_acme')
+    t.regex(acme.connectionString, /options=-csearch_path%3Dtenant_acme$/)
+    await tenants.provision('globex')
+    await instance.executeSql("INSERT INTO tenant_acme.plans VALUES ('pro')", {})
+
+    t.is(await count(instance, 'SELECT count(*) FROM tenant_acme.plans'), '2')
+    t.is(await count(instance, 'SELECT count(*) FROM tenant_globex.plans'), '1')
+    t.is(await count(instance, 'SELECT count(*) FROM template.plans'), '1')
+    t.deepEqual(
+      (await tenants.list()).map((tenant) => tenant.id),
+      ['acme', 'globex'],
+    )
+    await t.throwsAsync(() => tenants.provision('acme'), { message: /already exists/ })
+
+    await tenants.teardown('acme')
+    t.deepEqual(await tenants.teardownAll(), ['globex'])
+    t.deepEqual(
+      (await instance.listSchemas()).map((schema) => schema.name),
+      ['public', 'template'],
+    )
+  } finally {
+    await instance.cleanup()
+  }
+})
+
+test.serial('TenantManager provisions database tenants from a template database', async (t) => {
+  const instance = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+
+  try {
+    await instance.start()
+    await instance.createDatabase('app_template')
+    await instance.executeSql('CREATE TABLE users (id int); INSERT INTO users VALUES (1);', {}, 'app_template')
+    const tenants = instance.createTenantManager({
+      strategy: TenantStrategy.Database,
+      template: 'app_template',
+      prefix: 'app_',
+    })
+
+    const acme = await tenants.provision('acme')
+    t.is(acme.databaseName, 'app_acme')
+    t.is(acme.schema, undefined)
+    t.is(await count(instance, 'SELECT count(*) FROM users', 'app_acme'), '1')
+
+    await tenants.teardownAll()
+    t.false(await instance.databaseExists('app_acme'))
+  } finally {
+    await instance.cleanup()
+  }
+})
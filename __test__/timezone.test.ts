@@ -0,0 +1,42 @@
+import test from 'ava'
+import { PostgresInstance } from '../index.js'
+
+test.serial('timezone and datestyle set server defaults and can be overridden per session', async (t) => {
+  const pg = new PostgresInstance({
+    databaseName: 'timezone_db',
+    username: 'postgres',
+    password: 'password',
+    port: 0,
+    timezone: 'America/New_York',
+    datestyle: 'SQL, DMY',
+  })
+
+  try {
+    await pg.start()
+
+    const timezone = await pg.executeSql('SHOW timezone;', { tuplesOnly: true })
+    t.is(timezone.stdout.trim(), 'America/New_York')
+    const datestyle = await pg.executeSql('SHOW datestyle;', { tuplesOnly: true })
+    t.is(datestyle.stdout.trim(), 'SQL, DMY')
+
+    const session = await pg.executeSql('SHOW timezone; SHOW datestyle;', {
+      tuplesOnly: true,
+      timezone: 'Asia/Tokyo',
+      datestyle: 'ISO, MDY',
+    })
+    t.deepEqual(
+      session.stdout
+        .split('\n')
+        .map((line) => line.trim())
+        .filter(Boolean),
+      ['Asia/Tokyo', 'ISO, MDY'],
+    )
+  } finally {
+    await pg.cleanup()
+  }
+})
+
+test('invalid timezone and datestyle values are rejected', (t) => {
+  t.throws(() => new PostgresInstance({ timezone: "UTC'; DROP" }))
+  t.throws(() => new PostgresInstance({ datestyle: 'ISO,' }))
+})
@@ -0,0 +1,23 @@
+import test from 'ava'
+import path from 'node:path'
+import { PgRestoreTool, PsqlTool } from '../index.js'
+
+const missingDir = path.resolve('data/missing-postgres/bin')
+
+test('tools report the expected path of a missing executable', async (t) => {
+  const pgRestore = new PgRestoreTool({
+    connection: { host: 'localhost', port: 5432 },
+    programDir: missingDir,
+    config: { file: 'dump.backup' },
+  })
+
+  const error = await t.throwsAsync(() => pgRestore.execute())
+  t.true(error!.message.includes(`pg_restore not found at '${path.join(missingDir, 'pg_restore')}`))
+  t.regex(error!.message, /programDir option of the tool, or the installationDir and version settings/)
+})
+
+test('psql reports a missing executable before connecting', async (t) => {
+  const psql = new PsqlTool({ connection: { host: 'localhost', port: 5432 }, programDir: missingDir, config: {} })
+
+  await t.throwsAsync(() => psql.executeCommand('SELECT 1'), { message: /psql not found at/ })
+})
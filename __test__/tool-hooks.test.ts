@@ -0,0 +1,40 @@
+import test from 'ava'
+import { PostgresInstance, setAfterExecuteHook, setBeforeExecuteHook, ToolCommand, ToolExecution } from '../index.js'
+
+test.serial('tool hooks see every command and can wrap it', async (t) => {
+  const pg = new PostgresInstance({ port: 0, password: 'hook-secret' })
+  const before: ToolCommand[] = []
+  const after: ToolExecution[] = []
+
+  try {
+    await pg.start()
+    setBeforeExecuteHook((command) => {
+      before.push(command)
+      // `env` runs the command it is given, so the result stays the same
+      return process.platform === 'win32' ? undefined : ['env']
+    })
+    setAfterExecuteHook((execution) => {
+      after.push(execution)
+    })
+
+    const result = await pg.executeSql('SELECT 1;', { tuplesOnly: true })
+    t.is(result.stdout.trim(), '1')
+    // The after hook is called asynchronously
+    await new Promise((resolve) => setTimeout(resolve, 100))
+
+    t.is(before.length, 1)
+    t.is(before[0].tool, 'psql')
+    t.false(before[0].command.join(' ').includes('hook-secret'))
+    t.is(after.length, 1)
+    t.is(after[0].tool, 'psql')
+    t.is(after[0].exitCode, 0)
+    t.true(after[0].durationMs >= 0)
+    if (process.platform !== 'win32') {
+      t.is(after[0].command[0], 'env')
+    }
+  } finally {
+    setBeforeExecuteHook(null)
+    setAfterExecuteHook(null)
+    await pg.cleanup()
+  }
+})
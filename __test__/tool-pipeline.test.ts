@@ -0,0 +1,55 @@
+import test from 'ava'
+import fs from 'node:fs/promises'
+import path from 'node:path'
+import { PostgresInstance, runToolPipeline } from '../index.js'
+
+test('runToolPipeline validates its stages', async (t) => {
+  await t.throwsAsync(() => runToolPipeline([]), { message: /at least one stage/ })
+  await t.throwsAsync(() => runToolPipeline([{ filter: { replace: [{ from: 'a', to: 'b' }] } }]), {
+    message: /first stage of a pipeline must run a tool/,
+  })
+  await t.throwsAsync(() => runToolPipeline([{ tool: 'psql', filter: {} }]), {
+    message: /stage 1 must set exactly one of tool and filter/,
+  })
+  await t.throwsAsync(
+    () => runToolPipeline([{ tool: 'pg_dump', programDir: path.resolve('data/missing-postgres/bin') }]),
+    { message: /pg_dump not found at/ },
+  )
+})
+
+test.serial('pipeDatabaseTo streams a filtered dump into another instance', async (t) => {
+  const source = new PostgresInstance({ port: 0, dataDir: './data/test-pipeline-source' })
+  const target = new PostgresInstance({ port: 0, dataDir: './data/test-pipeline-target' })
+  try {
+    await source.start()
+    await target.start()
+    await source.executeSql(
+      "CREATE TABLE legacy_items (id int PRIMARY KEY, label text); INSERT INTO legacy_items VALUES (1, 'one'), (2, 'two');",
+      {},
+    )
+
+    const options = { filter: { replace: [{ from: 'legacy_items', to: 'items' }] } }
+    const result = await source.pipeDatabaseTo(target, options)
+    t.true(result.success, JSON.stringify(result.stages))
+    t.deepEqual(
+      result.stages.map((stage) => stage.name),
+      ['pg_dump', 'filter', 'psql'],
+    )
+
+    const copied = await target.executeSql('SELECT label FROM items ORDER BY id', {
+      tuplesOnly: true,
+      noAlign: true,
+    })
+    t.is(copied.stdout.trim(), 'one\ntwo')
+
+    const failed = await source.pipeDatabaseTo(target, options)
+    t.false(failed.success)
+    t.is(failed.failedStage, 'psql')
+    t.regex(failed.stages[2].stderr, /already exists/)
+  } finally {
+    await source.cleanup()
+    await target.cleanup()
+    await fs.rm('./data/test-pipeline-source', { recursive: true, force: true })
+    await fs.rm('./data/test-pipeline-target', { recursive: true, force: true })
+  }
+})
@@ -0,0 +1,29 @@
+import test from 'ava'
+import fs from 'node:fs/promises'
+import path from 'node:path'
+import { PgDumpTool } from '../index.js'
+
+test('PgDumpTool passes large object options under their old names to pg_dump before 16', async (t) => {
+  if (process.platform === 'win32') {
+    t.pass('fake pg_dump script requires a POSIX shell')
+    return
+  }
+  const programDir = path.resolve(`data/old-pg-dump-${Date.now()}`)
+  await fs.mkdir(programDir, { recursive: true })
+  const script = path.join(programDir, 'pg_dump')
+  await fs.writeFile(script, '#!/bin/sh\necho "pg_dump (PostgreSQL) 15.8"\n', { mode: 0o755 })
+
+  try {
+    const tool = new PgDumpTool({
+      connection: { host: 'localhost', port: 5432, username: 'postgres' },
+      programDir,
+      config: { blobs: true, noBlobs: true, tool: { dryRun: true } },
+    })
+    const result = await tool.execute()
+    t.true(result.command.includes('--blobs'))
+    t.true(result.command.includes('--no-blobs'))
+    t.false(result.command.includes('--large-objects'))
+  } finally {
+    await fs.rm(programDir, { recursive: true, force: true })
+  }
+})
@@ -0,0 +1,58 @@
+import test from 'ava'
+import { PostgresInstance } from '../index.js'
+
+const schema =
+  'CREATE TABLE customers (id serial PRIMARY KEY);' +
+  'CREATE TABLE orders (id serial PRIMARY KEY, customer_id int REFERENCES customers);' +
+  'CREATE TABLE order_items (order_id int REFERENCES orders, sku text);' +
+  'CREATE TABLE products (id serial PRIMARY KEY);' +
+  'INSERT INTO customers DEFAULT VALUES; INSERT INTO orders (customer_id) VALUES (1);' +
+  "INSERT INTO order_items VALUES (1, 'A-1'); INSERT INTO products DEFAULT VALUES;"
+
+const rowCounts = async (instance: PostgresInstance) =>
+  Object.fromEntries((await instance.tableChecksums()).map(({ table, rowCount }) => [table, rowCount]))
+
+test.serial('truncateTables() empties all tables regardless of foreign key order', async (t) => {
+  const instance = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+
+  try {
+    await instance.start()
+    await instance.executeSql(schema, {})
+
+    const truncated = await instance.truncateTables(undefined, undefined, { restartIdentity: true })
+    t.deepEqual([...truncated].sort(), ['customers', 'order_items', 'orders', 'products'])
+    t.deepEqual(await rowCounts(instance), {
+      'public.customers': 0,
+      'public.order_items': 0,
+      'public.orders': 0,
+      'public.products': 0,
+    })
+
+    const result = await instance.executeSql('INSERT INTO customers DEFAULT VALUES RETURNING id', { tuplesOnly: true, noAlign: true })
+    t.is(result.stdout.trim().split('\n')[0], '1')
+  } finally {
+    await instance.cleanup()
+  }
+})
+
+test.serial('truncateTables() requires cascade for referencing tables', async (t) => {
+  const instance = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+
+  try {
+    await instance.start()
+    await instance.executeSql(schema, {})
+
+    await t.throwsAsync(() => instance.truncateTables(undefined, ['customers']), {
+      message: /order_items, orders.*cascade/,
+    })
+    await t.throwsAsync(() => instance.truncateTables(undefined, ['missing']), {
+      message: /do not exist: missing/,
+    })
+
+    const truncated = await instance.truncateTables(undefined, ['customers'], { cascade: true })
+    t.deepEqual(truncated, ['customers', 'order_items', 'orders'])
+    t.is((await rowCounts(instance))['public.products'], 1)
+  } finally {
+    await instance.cleanup()
+  }
+})
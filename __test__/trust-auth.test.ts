@@ -0,0 +1,34 @@
+import test from 'ava'
+import path from 'node:path'
+import { PostgresInstance, PsqlTool } from '../index.js'
+
+test.serial('password: null runs the instance with trust authentication', async (t) => {
+  const pg = new PostgresInstance({
+    username: 'postgres',
+    password: null,
+    port: 0,
+  })
+
+  try {
+    await pg.start()
+
+    const info = pg.connectionInfo
+    t.is(info.password, '')
+    t.is(info.connectionString, `postgresql://postgres@localhost:${info.port}/postgres`)
+    t.false(info.jdbcUrl().includes('password='))
+
+    const result = await pg.executeSql('SELECT 1;', { tuplesOnly: true })
+    t.is(result.stdout.trim(), '1')
+
+    const psql = new PsqlTool({
+      connection: { host: info.host, port: info.port, username: 'postgres', database: 'postgres' },
+      programDir: path.join(pg.programDir, 'bin'),
+      config: { tuplesOnly: true },
+    })
+    const auth = await psql.executeCommand('SELECT current_user;')
+    t.is(auth.exitCode, 0)
+    t.is(auth.stdout.trim(), 'postgres')
+  } finally {
+    await pg.cleanup()
+  }
+})
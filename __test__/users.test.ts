@@ -0,0 +1,44 @@
+import test from 'ava'
+import { PostgresInstance } from '../index.js'
+
+test.serial('users can be created, listed, altered and dropped', async (t) => {
+  const pg = new PostgresInstance({ port: 0 })
+
+  try {
+    await pg.start()
+    await pg.createUser({ name: 'readers', login: false })
+    await pg.createUser({ name: 'app', password: "it's secret", createdb: true })
+    await pg.executeSql('GRANT readers TO app;', {})
+
+    const users = await pg.listUsers()
+    const app = users.find((user) => user.name === 'app')
+    t.deepEqual(app, {
+      name: 'app',
+      superuser: false,
+      createdb: true,
+      createrole: false,
+      login: true,
+      memberOf: ['readers'],
+    })
+    t.false(users.find((user) => user.name === 'readers')!.login)
+    t.false(users.some((user) => user.name.startsWith('pg_')))
+
+    await pg.alterUserPassword('app', 'rotated')
+    const password = await pg.executeSql("SELECT rolpassword IS NOT NULL FROM pg_authid WHERE rolname = 'app';", {
+      tuplesOnly: true,
+    })
+    t.is(password.stdout.trim(), 't')
+    await pg.alterUserPassword('app', null)
+    const removed = await pg.executeSql("SELECT rolpassword IS NULL FROM pg_authid WHERE rolname = 'app';", {
+      tuplesOnly: true,
+    })
+    t.is(removed.stdout.trim(), 't')
+
+    await t.throwsAsync(pg.createUser({ name: 'app' }))
+    await pg.dropUser('app')
+    t.false((await pg.listUsers()).some((user) => user.name === 'app'))
+    await t.throwsAsync(pg.createUser({ name: '' }), { message: /User name cannot be empty/ })
+  } finally {
+    await pg.cleanup()
+  }
+})
@@ -0,0 +1,40 @@
+import test from 'ava'
+import { validateConnectionConfig } from '../index.js'
+
+test.serial('validateConnectionConfig() fills defaults from PG* variables and reports them', (t) => {
+  const saved = { PGPORT: process.env.PGPORT, PGUSER: process.env.PGUSER, PGHOST: process.env.PGHOST }
+  process.env.PGPORT = '6001'
+  process.env.PGUSER = 'ci'
+  delete process.env.PGHOST
+
+  try {
+    const { config, defaults } = validateConnectionConfig({ database: 'app' })
+    t.is(config.port, 6001)
+    t.is(config.username, 'ci')
+    t.is(config.database, 'app')
+    t.is(config.host, process.platform === 'win32' ? '127.0.0.1' : 'localhost')
+    t.deepEqual(
+      defaults.filter(({ field }) => ['host', 'port', 'username'].includes(field)),
+      [
+        { field: 'username', source: 'PGUSER' },
+        { field: 'port', source: 'PGPORT' },
+        { field: 'host', source: 'default' },
+      ],
+    )
+  } finally {
+    for (const [key, value] of Object.entries(saved)) {
+      if (value === undefined) {
+        delete process.env[key]
+      } else {
+        process.env[key] = value
+      }
+    }
+  }
+})
+
+test('validateConnectionConfig() rejects invalid values', (t) => {
+  t.throws(() => validateConnectionConfig({ host: 'localhost', port: 0 }), { message: /port 0/ })
+  t.throws(() => validateConnectionConfig({ host: 'localhost', port: 5432, sslMode: 'required' }), {
+    message: /sslMode/,
+  })
+})
@@ -0,0 +1,56 @@
+import test from 'ava'
+import fs from 'node:fs/promises'
+import path from 'node:path'
+import { PgDumpFormat, PostgresInstance } from '../index.js'
+
+test.serial('verifyBackup() restores the archive in a throwaway instance and runs the checks', async (t) => {
+  const pg = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+  const archive = path.resolve(`data/verify-backup-${Date.now()}.dump`)
+
+  try {
+    await pg.start()
+    await fs.mkdir(path.dirname(archive), { recursive: true })
+    await pg.executeSql(
+      `CREATE TABLE users (id serial PRIMARY KEY, email text);
+       INSERT INTO users (email) VALUES ('a@example.com'), ('b@example.com');`,
+      {},
+    )
+    const dump = await pg.createDump({ file: archive, format: PgDumpFormat.Custom })
+    t.is(dump.exitCode, 0, dump.stderr)
+    // Later changes are not in the backup
+    await pg.executeSql('DROP TABLE users', {})
+
+    const report = await pg.verifyBackup(archive, {
+      checks: [
+        'SELECT count(*) FROM users',
+        'SELECT count(*) > 2 FROM users',
+        'SELECT * FROM missing_table',
+        'SELECT id FROM users WHERE false',
+      ],
+    })
+    t.true(report.restored)
+    t.false(report.passed)
+    t.true(report.objectCounts.every(({ expected, actual }) => expected === actual))
+    t.like(report.checks[0], { passed: true, value: '2' })
+    t.like(report.checks[1], { passed: false, value: 'f' })
+    t.false(report.checks[2].passed)
+    t.regex(report.checks[2].error ?? '', /missing_table/)
+    // A check returning no rows fails
+    t.false(report.checks[3].passed)
+    t.falsy(report.checks[3].value)
+    t.falsy(report.checks[3].error)
+    t.true(report.durationMs > 0)
+
+    t.true((await pg.verifyBackup(archive, { checks: ['SELECT count(*) = 2 FROM users'] })).passed)
+
+    await fs.writeFile(archive, 'not an archive')
+    const broken = await pg.verifyBackup(archive, { checks: ['SELECT 1'] })
+    t.false(broken.passed)
+    t.false(broken.restored)
+    t.truthy(broken.error)
+    t.deepEqual(broken.checks, [])
+  } finally {
+    await pg.cleanup()
+    await fs.rm(archive, { force: true })
+  }
+})
@@ -0,0 +1,50 @@
+import test from 'ava'
+import fs from 'node:fs/promises'
+import path from 'node:path'
+import { getPostgreSqlVersion, PostgresInstance } from '../index.js'
+
+const major = getPostgreSqlVersion().split('.')[0]
+
+test.serial('a major version requirement resolves to an exact version recorded in the data directory', async (t) => {
+  const dataDir = path.resolve(`data/version-pinning-${Date.now()}`)
+  const instance = new PostgresInstance({ version: major, port: 0, dataDir })
+
+  try {
+    await instance.start()
+    const resolved = instance.getPostgreSqlVersion()
+    t.regex(resolved, new RegExp(`^${major}\\.\\d+\\.\\d+$`))
+
+    const metadata = await fs.readFile(path.join(dataDir, 'pg-embedded.meta'), 'utf8')
+    t.true(metadata.includes(`postgresql_version = '${resolved}'`))
+
+    await instance.stop()
+    const reopened = new PostgresInstance({ version: major, port: 0, dataDir })
+    t.is(reopened.getPostgreSqlVersion(), resolved)
+    await reopened.cleanup()
+  } finally {
+    await instance.cleanup()
+    await fs.rm(dataDir, { recursive: true, force: true })
+  }
+})
+
+test.serial('start() reports a data directory of an older major version', async (t) => {
+  const dataDir = path.resolve(`data/version-mismatch-${Date.now()}`)
+  const otherMajor = String(Number(major) - 1)
+  await fs.mkdir(dataDir, { recursive: true })
+  await fs.writeFile(path.join(dataDir, 'PG_VERSION'), `${otherMajor}\n`)
+  await fs.writeFile(path.join(dataDir, 'postgresql.conf'), '')
+  const instance = new PostgresInstance({ version: major, port: 0, dataDir })
+
+  try {
+    const error = await t.throwsAsync(() => instance.start())
+    t.regex(error!.message, /^Data directory version mismatch:/)
+    t.regex(
+      error!.message,
+      new RegExp(`PostgreSQL ${otherMajor} cluster, but PostgreSQL ${major}\\.\\d+\\.\\d+ is selected`),
+    )
+    t.regex(error!.message, new RegExp(`set version to '${otherMajor}'.*restoring the dump into a new dataDir`))
+  } finally {
+    await instance.cleanup()
+    await fs.rm(dataDir, { recursive: true, force: true })
+  }
+})
@@ -0,0 +1,38 @@
+import test from 'ava'
+import fs from 'node:fs/promises'
+import path from 'node:path'
+import { archiveCommand, PostgresInstance, restoreCommand } from '../index.js'
+
+test('archiveCommand() and restoreCommand() quote the archive directory', (t) => {
+  if (process.platform === 'win32') {
+    t.is(archiveCommand('C:/wal'), 'if not exist "C:\\wal\\%f" copy "%p" "C:\\wal\\%f"')
+    t.is(restoreCommand('C:/wal'), 'copy "C:\\wal\\%f" "%p"')
+  } else {
+    t.is(archiveCommand('/tmp/my wal'), "test ! -f '/tmp/my wal/%f' && cp %p '/tmp/my wal/%f'")
+    t.is(restoreCommand('/tmp/my wal'), "cp '/tmp/my wal/%f' %p")
+  }
+})
+
+test.serial('walArchiveDir archives completed WAL segments', async (t) => {
+  const walArchiveDir = path.resolve(`data/wal archive-${Date.now()}`)
+  const pg = new PostgresInstance({ port: 0, walArchiveDir })
+
+  try {
+    await pg.start()
+    const mode = await pg.executeSql('SHOW archive_mode;', { tuplesOnly: true })
+    t.is(mode.stdout.trim(), 'on')
+
+    await pg.executeSql('CREATE TABLE archived AS SELECT generate_series(1, 100) AS id;', {})
+    await pg.executeSql('SELECT pg_switch_wal();', {})
+
+    let segments: string[] = []
+    for (let attempt = 0; attempt < 50 && segments.length === 0; attempt++) {
+      await new Promise((resolve) => setTimeout(resolve, 100))
+      segments = await fs.readdir(walArchiveDir)
+    }
+    t.true(segments.length > 0)
+  } finally {
+    await pg.cleanup()
+    await fs.rm(walArchiveDir, { recursive: true, force: true })
+  }
+})
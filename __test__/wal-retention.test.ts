@@ -0,0 +1,27 @@
+import test from 'ava'
+import { PostgresInstance } from '../index.js'
+
+test.serial('WAL retention helpers control wal_keep_size and segment switches', async (t) => {
+  const pg = new PostgresInstance({ port: 0 })
+
+  try {
+    await pg.start()
+    await pg.setWalKeepSize('256MB')
+    const keep = await pg.executeSql('SHOW wal_keep_size;', { tuplesOnly: true })
+    t.is(keep.stdout.trim(), '256MB')
+    await t.throwsAsync(() => pg.setWalKeepSize('lots'), { message: /Invalid WAL keep size/ })
+
+    const before = (await pg.listWalFiles()).filter((file) => file.isSegment)
+    t.true(before.length > 0)
+    t.true(before.every((file) => file.sizeBytes > 0 && file.modified > 0))
+
+    await pg.executeSql('CREATE TABLE wal_filler AS SELECT generate_series(1, 1000) AS id;', {})
+    const switchedAt = await pg.forceCheckpointAndSwitchWal()
+    t.regex(switchedAt, /^[0-9A-F]+\/[0-9A-F]+$/)
+
+    const after = (await pg.listWalFiles()).filter((file) => file.isSegment)
+    t.true(after[after.length - 1].name > before[before.length - 1].name)
+  } finally {
+    await pg.cleanup()
+  }
+})
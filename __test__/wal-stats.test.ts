@@ -0,0 +1,37 @@
+import test from 'ava'
+import { PostgresInstance } from '../index.js'
+
+test.serial('getWalStats() reports WAL generated by writes', async (t) => {
+  const instance = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+
+  try {
+    await instance.start()
+    const before = await instance.getWalStats()
+    await instance.executeSql('CREATE TABLE items AS SELECT g AS id FROM generate_series(1, 10000) AS g', {})
+    const after = await instance.getWalStats()
+
+    t.true(after.records > before.records)
+    t.true(after.bytes > before.bytes)
+    t.is(typeof after.statsReset, 'number')
+  } finally {
+    await instance.cleanup()
+  }
+})
+
+test.serial('getCheckpointStats() counts requested checkpoints', async (t) => {
+  const instance = new PostgresInstance({ username: 'postgres', password: 'password', port: 0 })
+
+  try {
+    await instance.start()
+    const before = await instance.getCheckpointStats()
+    await instance.executeSql('CREATE TABLE items AS SELECT g AS id FROM generate_series(1, 1000) AS g', {})
+    await instance.executeSql('CHECKPOINT', {})
+    const after = await instance.getCheckpointStats()
+
+    t.true(after.requested > before.requested)
+    t.true(after.buffersWritten >= before.buffersWritten)
+    t.is(typeof after.writeTimeMs, 'number')
+  } finally {
+    await instance.cleanup()
+  }
+})
@@ -508,8 +508,14 @@ if (!nativeBinding) {
 }
 
 module.exports = nativeBinding
+module.exports.Client = nativeBinding.Client
 module.exports.ConnectionInfo = nativeBinding.ConnectionInfo
+module.exports.ConnectionRouter = nativeBinding.ConnectionRouter
+module.exports.CopyReader = nativeBinding.CopyReader
+module.exports.CopyWriter = nativeBinding.CopyWriter
+module.exports.DdlCapture = nativeBinding.DdlCapture
 module.exports.PgBasebackupTool = nativeBinding.PgBasebackupTool
+module.exports.PgConfigTool = nativeBinding.PgConfigTool
 module.exports.PgDumpallTool = nativeBinding.PgDumpallTool
 module.exports.PgDumpTool = nativeBinding.PgDumpTool
 module.exports.PgIsReadyTool = nativeBinding.PgIsReadyTool
@@ -517,20 +523,50 @@ module.exports.PgRestoreTool = nativeBinding.PgRestoreTool
 module.exports.PgRewindTool = nativeBinding.PgRewindTool
 module.exports.PostgresInstance = nativeBinding.PostgresInstance
 module.exports.PsqlTool = nativeBinding.PsqlTool
+module.exports.ShardSet = nativeBinding.ShardSet
+module.exports.TenantManager = nativeBinding.TenantManager
+module.exports.Transaction = nativeBinding.Transaction
+module.exports.archiveCommand = nativeBinding.archiveCommand
+module.exports.ByteaOutput = nativeBinding.ByteaOutput
+module.exports.compareData = nativeBinding.compareData
+module.exports.compareLsn = nativeBinding.compareLsn
+module.exports.comparePlans = nativeBinding.comparePlans
+module.exports.computeConfigHash = nativeBinding.computeConfigHash
+module.exports.executeTool = nativeBinding.executeTool
+module.exports.FailurePhase = nativeBinding.FailurePhase
+module.exports.findInstances = nativeBinding.findInstances
 module.exports.getPackageVersion = nativeBinding.getPackageVersion
 module.exports.getPostgreSqlVersion = nativeBinding.getPostgreSqlVersion
 module.exports.getVersionInfo = nativeBinding.getVersionInfo
 module.exports.initLogger = nativeBinding.initLogger
 module.exports.InstanceState = nativeBinding.InstanceState
+module.exports.isQuietMode = nativeBinding.isQuietMode
 module.exports.logDebug = nativeBinding.logDebug
 module.exports.logError = nativeBinding.logError
 module.exports.logInfo = nativeBinding.logInfo
 module.exports.LogLevel = nativeBinding.LogLevel
 module.exports.logTrace = nativeBinding.logTrace
 module.exports.logWarn = nativeBinding.logWarn
+module.exports.lsnDiffBytes = nativeBinding.lsnDiffBytes
+module.exports.parseConnectionString = nativeBinding.parseConnectionString
+module.exports.PasswordEncryption = nativeBinding.PasswordEncryption
 module.exports.PgBasebackupCheckpoint = nativeBinding.PgBasebackupCheckpoint
 module.exports.PgBasebackupFormat = nativeBinding.PgBasebackupFormat
 module.exports.PgBasebackupWalMethod = nativeBinding.PgBasebackupWalMethod
 module.exports.PgDumpFormat = nativeBinding.PgDumpFormat
 module.exports.PgRestoreFormat = nativeBinding.PgRestoreFormat
 module.exports.PostgresError = nativeBinding.PostgresError
+module.exports.PreflightSeverity = nativeBinding.PreflightSeverity
+module.exports.restoreCommand = nativeBinding.restoreCommand
+module.exports.runToolPipeline = nativeBinding.runToolPipeline
+module.exports.ServerRole = nativeBinding.ServerRole
+module.exports.setAfterExecuteHook = nativeBinding.setAfterExecuteHook
+module.exports.setBeforeExecuteHook = nativeBinding.setBeforeExecuteHook
+module.exports.setCredentialRedaction = nativeBinding.setCredentialRedaction
+module.exports.setQuietMode = nativeBinding.setQuietMode
+module.exports.splitSqlScript = nativeBinding.splitSqlScript
+module.exports.SqlStatementKind = nativeBinding.SqlStatementKind
+module.exports.StreamCompression = nativeBinding.StreamCompression
+module.exports.TenantStrategy = nativeBinding.TenantStrategy
+module.exports.validateConnectionConfig = nativeBinding.validateConnectionConfig
+module.exports.VerboseAction = nativeBinding.VerboseAction
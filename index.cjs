@@ -1,19 +1,279 @@
-const { PostgresInstance: Postgres } = require('./binding.cjs')
+const { once } = require('node:events')
+const { readFile, writeFile } = require('node:fs/promises')
+const os = require('node:os')
+const { PostgresInstance: Postgres, ServerRole, compareLsn, getVersionInfo } = require('./binding.cjs')
 
-class PostgresInstance extends Postgres {
-  constructor(settings) {
-    super(settings)
+// The native settings cannot tell `password: null` apart from a missing password,
+// so an explicit null is passed on as the empty password that selects trust mode
+function normalizeSettings(settings) {
+  if (settings && settings.password === null) {
+    return { ...settings, password: '' }
+  }
+  return settings
+}
+
+// Instances that are cleaned up when the process is interrupted; one pair of signal
+// listeners serves all of them, so creating many instances adds no listeners
+const liveInstances = new Set()
+
+function cleanupLiveInstances() {
+  return Promise.allSettled([...liveInstances].map((instance) => instance.cleanup()))
+}
+
+function registerCleanup(instance) {
+  if (liveInstances.size === 0) {
     // catch Ctrl+C
-    process.on('SIGINT', async () => {
-      await this.cleanup()
-    });
+    process.on('SIGINT', cleanupLiveInstances)
     // catch kill command
-    process.on('SIGTERM', async () => {
-      await this.cleanup()
-    });
+    process.on('SIGTERM', cleanupLiveInstances)
+  }
+  liveInstances.add(instance)
+}
+
+function unregisterCleanup(instance) {
+  liveInstances.delete(instance)
+  if (liveInstances.size === 0) {
+    process.off('SIGINT', cleanupLiveInstances)
+    process.off('SIGTERM', cleanupLiveInstances)
+  }
+}
+
+// Instances created by the native code lack the methods defined in JavaScript below, so
+// they are given the prototype of the JavaScript class
+function adopt(instance) {
+  Object.setPrototypeOf(instance, PostgresInstance.prototype)
+  registerCleanup(instance)
+  return instance
+}
+
+class PostgresInstance extends Postgres {
+  constructor(settings) {
+    super(normalizeSettings(settings))
+    registerCleanup(this)
+  }
+
+  static fromProfile(profile) {
+    return adopt(Postgres.fromProfile({ ...profile, settings: normalizeSettings(profile.settings) }))
+  }
+
+  static async createMany(count, settings) {
+    const instances = await Postgres.createMany(count, normalizeSettings(settings))
+    return instances.map(adopt)
+  }
+
+  async createReplica(options) {
+    return adopt(await super.createReplica(options))
+  }
+
+  async cleanup() {
+    unregisterCleanup(this)
+    return super.cleanup()
+  }
+
+  // Buffers and strings are sent in one piece; streams and other iterables of chunks
+  // are written chunk by chunk, so a large file is never held in memory
+  async copyInto(table, data, options, databaseName) {
+    if (typeof data === 'string' || Buffer.isBuffer(data)) {
+      return super.copyInto(table, data, options, databaseName)
+    }
+    if (data instanceof Uint8Array) {
+      return super.copyInto(table, Buffer.from(data), options, databaseName)
+    }
+    const writer = await this.openCopyWriter(table, options, databaseName)
+    try {
+      for await (const chunk of data) {
+        await writer.write(typeof chunk === 'string' || Buffer.isBuffer(chunk) ? chunk : Buffer.from(chunk))
+      }
+    } catch (error) {
+      await writer.abort(String(error?.message ?? error))
+      throw error
+    }
+    return writer.finish()
+  }
+
+  // Chunks are written as they arrive, waiting for the destination to drain when its
+  // buffer is full, so a slow destination holds the export back
+  async copyOutToStream(source, destination, options, databaseName) {
+    const reader = await this.openCopyReader(source, options, databaseName)
+    let written = 0
+    try {
+      for (let chunk = await reader.read(); chunk; chunk = await reader.read()) {
+        written += chunk.length
+        if (!destination.write(chunk)) {
+          await once(destination, 'drain')
+        }
+      }
+    } finally {
+      await reader.close()
+    }
+    return written
+  }
+}
+
+const sleep = (ms) => new Promise((resolve) => setTimeout(resolve, ms))
+
+// Wait until the standby has replayed the primary's current WAL position
+async function waitForCatchUp(primary, standby, timeoutSeconds) {
+  const target = await primary.getCurrentLsn()
+  const deadline = Date.now() + timeoutSeconds * 1000
+  while (compareLsn(await standby.getCurrentLsn(), target) < 0) {
+    if (Date.now() > deadline) {
+      throw new Error(`Standby ${standby.instanceId} did not catch up to ${target} within ${timeoutSeconds} seconds`)
+    }
+    await sleep(100)
+  }
+}
+
+async function applyConfigToCluster(instances, config, options = {}) {
+  const { rollingRestart = false, catchUpTimeoutSeconds = 60 } = options
+  const roles = await Promise.all(instances.map((instance) => instance.getRole()))
+  const primaries = instances.filter((_, i) => roles[i] === ServerRole.Primary)
+  if (primaries.length !== 1) {
+    throw new Error(`Expected exactly one primary, found ${primaries.length}`)
+  }
+  const [primary] = primaries
+  const standbys = instances.filter((_, i) => roles[i] === ServerRole.Standby)
+
+  // Standbys first, so they never run with a lower setting than the primary
+  const pending = new Set()
+  for (const instance of [...standbys, primary]) {
+    for (const name of await instance.applyServerConfig(config)) {
+      pending.add(name)
+    }
+  }
+  if (!rollingRestart) {
+    return [...pending].sort()
+  }
+
+  for (const standby of standbys) {
+    await standby.stop()
+    await standby.start(false)
+    await waitForCatchUp(primary, standby, catchUpTimeoutSeconds)
+  }
+  await primary.stop()
+  await primary.start(false)
+  for (const standby of standbys) {
+    await waitForCatchUp(primary, standby, catchUpTimeoutSeconds)
+  }
+  return []
+}
+
+const quoteIdent = (name) => `"${name.replace(/"/g, '""')}"`
+
+// A failed statement of a batch run with continueOnError
+const batchError = (results) => results.map((result) => result.error).find(Boolean)
+
+async function verifyReadYourWrites(primary, replica, options = {}) {
+  const { table = 'pg_embedded_read_your_writes', timeoutMs = 10000, pollIntervalMs = 10, databaseName } = options
+  const target = quoteIdent(table)
+  const marker = `${Date.now()}-${Math.random().toString(36).slice(2)}`
+  await primary.executeSqlBatch(
+    `CREATE TABLE IF NOT EXISTS ${target} (marker text PRIMARY KEY, written_at timestamptz NOT NULL DEFAULT now());
+     INSERT INTO ${target} (marker) VALUES ('${marker}');`,
+    {},
+    databaseName,
+  )
+
+  // The table itself may not have replicated yet, so failing reads are retried too
+  const written = Date.now()
+  const deadline = written + timeoutMs
+  for (;;) {
+    const results = await replica.executeSqlBatch(
+      `SELECT 1 FROM ${target} WHERE marker = '${marker}';`,
+      { continueOnError: true },
+      databaseName,
+    )
+    const lastError = batchError(results)
+    if (!lastError && results[0].result.rows.length > 0) {
+      return { marker, lagMs: Date.now() - written }
+    }
+    if (Date.now() > deadline) {
+      const reason = lastError ? `: ${lastError}` : ''
+      throw new Error(`Replica ${replica.instanceId} did not see marker ${marker} within ${timeoutMs} ms${reason}`)
+    }
+    await sleep(pollIntervalMs)
+  }
+}
+
+const percentChange = (value, baseline) => ((value - baseline) / baseline) * 100
+
+async function benchmarkEnvironment(instance) {
+  const versions = getVersionInfo()
+  const [serverVersion] = await instance.executeSqlBatch('SHOW server_version;', {})
+  const cpus = os.cpus()
+  return {
+    timestamp: new Date().toISOString(),
+    packageVersion: versions.packageVersion,
+    postgresqlVersion: serverVersion.result.rows[0][0],
+    platform: process.platform,
+    arch: process.arch,
+    nodeVersion: process.version,
+    cpuModel: cpus[0]?.model ?? null,
+    cpuCount: cpus.length,
+    totalMemoryBytes: os.totalmem(),
+  }
+}
+
+// Compare each workload with the baseline workload of the same name; a workload
+// regresses when its throughput drops or its latency grows by more than the threshold
+function compareWithBaseline(workloads, baseline, thresholdPercent) {
+  const previous = new Map(baseline.workloads.map((workload) => [workload.name, workload]))
+  return workloads
+    .filter((workload) => previous.has(workload.name))
+    .map((workload) => {
+      const base = previous.get(workload.name)
+      const tpsChangePercent = percentChange(workload.tps, base.tps)
+      const latencyChangePercent =
+        workload.latencyAvgMs != null && base.latencyAvgMs != null
+          ? percentChange(workload.latencyAvgMs, base.latencyAvgMs)
+          : null
+      return {
+        name: workload.name,
+        baselineTps: base.tps,
+        tps: workload.tps,
+        tpsChangePercent,
+        latencyChangePercent,
+        regressed: tpsChangePercent < -thresholdPercent || (latencyChangePercent ?? 0) > thresholdPercent,
+      }
+    })
+}
+
+async function runBenchmarkSuite(instance, config) {
+  const { workloads = [], outputFile, baselineFile, thresholdPercent = 10, failOnRegression = false } = config
+  if (workloads.length === 0) {
+    throw new Error('A benchmark suite needs at least one workload')
+  }
+  const names = workloads.map((workload) => workload.name)
+  const duplicate = names.find((name, i) => names.indexOf(name) !== i)
+  if (duplicate !== undefined) {
+    throw new Error(`Duplicate benchmark workload name '${duplicate}'`)
+  }
+
+  // Read before the output is written, which may replace the baseline with this run
+  const baseline = baselineFile ? JSON.parse(await readFile(baselineFile, 'utf8')) : undefined
+
+  const results = []
+  for (const { name, ...options } of workloads) {
+    const { output, ...summary } = await instance.runPgbench(options)
+    results.push({ name, ...summary })
+  }
+  const report = { environment: await benchmarkEnvironment(instance), workloads: results }
+  if (outputFile) {
+    await writeFile(outputFile, `${JSON.stringify(report, null, 2)}\n`)
+  }
+
+  const comparisons = baseline ? compareWithBaseline(results, baseline, thresholdPercent) : []
+  const regressions = comparisons.filter((comparison) => comparison.regressed)
+  if (failOnRegression && regressions.length > 0) {
+    const list = regressions.map((r) => `${r.name} (${r.tpsChangePercent.toFixed(1)}% tps)`).join(', ')
+    throw new Error(`Benchmark regressions beyond ${thresholdPercent}%: ${list}`)
   }
+  return { ...report, comparisons, passed: regressions.length === 0 }
 }
 
 module.exports = Object.assign(require('./binding.cjs'), {
-  PostgresInstance
+  PostgresInstance,
+  applyConfigToCluster,
+  verifyReadYourWrites,
+  runBenchmarkSuite
 });
\ No newline at end of file
@@ -1 +1,222 @@
-export * from "./binding.js"
\ No newline at end of file
+export * from "./binding.js"
+
+import type { Writable } from "node:stream"
+import type { CopyIntoOptions, CopyOutOptions, PgbenchOptions, PgbenchResult, PostgresInstance } from "./binding.js"
+
+/** Options for `applyConfigToCluster()` */
+export interface ApplyConfigToClusterOptions {
+  /** Restart the standbys and then the primary so settings that need a restart take effect (default: false) */
+  rollingRestart?: boolean
+  /** How long to wait for a standby to catch up with the primary after each restart (default: 60) */
+  catchUpTimeoutSeconds?: number
+}
+
+/**
+ * Apply server settings to a primary and its standbys
+ *
+ * The settings are written to every instance's configuration files and reloaded, standbys
+ * first. With `rollingRestart` the standbys are then restarted one at a time, each waiting
+ * until it has caught up with the primary, and the primary is restarted last.
+ *
+ * @param instances - The primary and its standbys, all running
+ * @param config - Settings to apply, e.g. `{ max_connections: '200' }`
+ * @param options - Rolling restart options
+ * @returns Promise that resolves to the names of the settings that still need a restart
+ * (always empty after a rolling restart)
+ * @throws Error if the instances do not include exactly one primary or a standby does not catch up in time
+ *
+ * @example
+ * ```typescript
+ * await applyConfigToCluster([primary, standby], { max_connections: '200' }, { rollingRestart: true });
+ * ```
+ */
+export declare function applyConfigToCluster(
+  instances: PostgresInstance[],
+  config: Record<string, string>,
+  options?: ApplyConfigToClusterOptions,
+): Promise<string[]>
+
+/** Options for `verifyReadYourWrites()` */
+export interface VerifyReadYourWritesOptions {
+  /** Table the marker rows are written to, created on the primary if missing (default: "pg_embedded_read_your_writes") */
+  table?: string
+  /** How long to wait for the marker to appear on the replica, in milliseconds (default: 10000) */
+  timeoutMs?: number
+  /** Delay between reads on the replica, in milliseconds (default: 10) */
+  pollIntervalMs?: number
+  /** Database the marker is written to and read from (default: the instances' databaseName) */
+  databaseName?: string
+}
+
+/** Outcome of `verifyReadYourWrites()` */
+export interface ReadYourWritesResult {
+  /** The marker value written on the primary */
+  marker: string
+  /** Time from the committed write on the primary until the replica returned it, in milliseconds */
+  lagMs: number
+}
+
+/**
+ * Check that a write on the primary becomes readable on a replica
+ *
+ * A marker row is committed on the primary, then the replica is polled until it returns
+ * the row. Reads that fail, e.g. because the marker table has not replicated yet, are
+ * retried until the timeout.
+ *
+ * @param primary - The running primary
+ * @param replica - A running replica of the primary
+ * @param options - Marker table, timeout and polling interval
+ * @returns Promise that resolves to the marker and the observed replication lag
+ * @throws Error if the replica does not return the marker within the timeout
+ *
+ * @example
+ * ```typescript
+ * const replica = await primary.createReplica();
+ * const { lagMs } = await verifyReadYourWrites(primary, replica, { table: 'ryw_markers' });
+ * t.true(lagMs < 1000);
+ * ```
+ */
+export declare function verifyReadYourWrites(
+  primary: PostgresInstance,
+  replica: PostgresInstance,
+  options?: VerifyReadYourWritesOptions,
+): Promise<ReadYourWritesResult>
+
+/** A named pgbench workload of a benchmark suite */
+export interface BenchmarkWorkload extends PgbenchOptions {
+  /** Name identifying the workload in results and baselines */
+  name: string
+}
+
+/** Options for `runBenchmarkSuite()` */
+export interface BenchmarkSuiteConfig {
+  /** Workloads to run, in order */
+  workloads: BenchmarkWorkload[]
+  /** File the results are written to as JSON, usable as a later baseline */
+  outputFile?: string
+  /** Results of an earlier run, as written to `outputFile`, to compare against */
+  baselineFile?: string
+  /** Allowed drop in throughput or growth in latency, in percent (default: 10) */
+  thresholdPercent?: number
+  /** Throw when a workload regressed beyond the threshold (default: false) */
+  failOnRegression?: boolean
+}
+
+/** Machine and versions a benchmark suite ran on */
+export interface BenchmarkEnvironment {
+  /** Start of the run, as an ISO 8601 timestamp */
+  timestamp: string
+  packageVersion: string
+  /** Version reported by the server, e.g. "18.0" */
+  postgresqlVersion: string
+  platform: string
+  arch: string
+  nodeVersion: string
+  cpuModel: string | null
+  cpuCount: number
+  totalMemoryBytes: number
+}
+
+/** Result of one workload of a benchmark suite */
+export interface BenchmarkWorkloadResult extends Omit<PgbenchResult, "output"> {
+  name: string
+}
+
+/** Comparison of a workload with the baseline workload of the same name */
+export interface BenchmarkComparison {
+  name: string
+  baselineTps: number
+  tps: number
+  /** Change in throughput, in percent; negative is slower */
+  tpsChangePercent: number
+  /** Change in average latency, in percent; positive is slower (null if either run has no latency) */
+  latencyChangePercent: number | null
+  /** Whether the change exceeds the threshold */
+  regressed: boolean
+}
+
+/** Outcome of `runBenchmarkSuite()` */
+export interface BenchmarkSuiteReport {
+  environment: BenchmarkEnvironment
+  workloads: BenchmarkWorkloadResult[]
+  /** Comparisons with the baseline, for workloads the baseline contains */
+  comparisons: BenchmarkComparison[]
+  /** False if any workload regressed beyond the threshold */
+  passed: boolean
+}
+
+/**
+ * Run named pgbench workloads and compare them with a stored baseline
+ *
+ * Each workload runs with `runPgbench()`. The results and the environment they were
+ * measured in can be written to a JSON file, which a later run reads as its baseline.
+ * A workload regresses when its throughput drops or its average latency grows by more
+ * than the threshold, so CI can fail on database performance regressions.
+ *
+ * @param instance - The running instance to benchmark
+ * @param config - Workloads, output and baseline files, and the regression threshold
+ * @returns Promise that resolves to the results, the comparisons and whether the suite passed
+ * @throws Error if a workload fails, names are not unique, or `failOnRegression` is set and a workload regressed
+ *
+ * @example
+ * ```typescript
+ * const report = await runBenchmarkSuite(instance, {
+ *   workloads: [
+ *     { name: 'tpcb', initialize: true, clients: 4, durationSeconds: 10 },
+ *     { name: 'reads', builtin: 'select-only', clients: 8, durationSeconds: 10 },
+ *   ],
+ *   outputFile: 'bench/current.json',
+ *   baselineFile: 'bench/baseline.json',
+ *   thresholdPercent: 15,
+ * });
+ * if (!report.passed) process.exitCode = 1;
+ * ```
+ */
+export declare function runBenchmarkSuite(
+  instance: PostgresInstance,
+  config: BenchmarkSuiteConfig,
+): Promise<BenchmarkSuiteReport>
+
+declare module "./binding.js" {
+  interface PostgresInstance {
+    /**
+     * Loads a stream of data into a table with `COPY ... FROM STDIN`
+     *
+     * Accepts a Node stream or any other (async) iterable of chunks, e.g.
+     * `fs.createReadStream('events.csv')`; the chunks are written as they are read, so a
+     * large file is never held in memory. Chunks do not have to end at row boundaries.
+     *
+     * @param table - Name of the table, optionally schema-qualified
+     * @param data - Stream of chunks of the data
+     * @param options - Format, columns, header, delimiter and NULL string of the data
+     * @param databaseName - Optional database to load into (defaults to the configured databaseName)
+     * @returns Promise that resolves to the number of rows loaded
+     * @throws Error if the table does not exist, the data is invalid or the stream fails; no row is loaded then
+     */
+    copyInto(
+      table: string,
+      data: AsyncIterable<Buffer | Uint8Array | string> | Iterable<Buffer | Uint8Array | string>,
+      options?: CopyIntoOptions | undefined | null,
+      databaseName?: string | undefined | null,
+    ): Promise<number>
+    /**
+     * Exports a table or the result of a query with `COPY ... TO STDOUT` into a stream
+     *
+     * The chunks are written as they arrive, waiting for the stream to drain when its
+     * buffer is full; the stream is not ended afterwards.
+     *
+     * @param source - Name of a table, optionally schema-qualified, or a query starting with SELECT, WITH, VALUES or TABLE
+     * @param destination - Writable stream, e.g. `fs.createWriteStream('results.csv')`
+     * @param options - Format, columns, header, delimiter and NULL string of the data
+     * @param databaseName - Optional database to export from (defaults to the configured databaseName)
+     * @returns Promise that resolves to the number of bytes written
+     * @throws Error if the table does not exist, the query fails or the stream fails
+     */
+    copyOutToStream(
+      source: string,
+      destination: Writable,
+      options?: CopyOutOptions | undefined | null,
+      databaseName?: string | undefined | null,
+    ): Promise<number>
+  }
+}
@@ -0,0 +1,387 @@
+//! Missing index report from table scan statistics and pg_stat_statements
+
+use crate::sql::{quote_ident, quote_literal};
+use napi_derive::napi;
+use std::collections::{HashMap, HashSet};
+
+/// Default minimum number of sequential scans for a table to be reported
+const DEFAULT_MIN_SEQ_SCANS: u32 = 10;
+/// Default minimum number of live rows for a table to be reported
+const DEFAULT_MIN_ROWS: u32 = 1000;
+/// Default number of most frequent pg_stat_statements entries examined
+const DEFAULT_MAX_STATEMENTS: u32 = 100;
+
+/// Whether pg_stat_statements is installed in the current database
+pub(crate) const STATEMENTS_INSTALLED_SQL: &str =
+  "SELECT count(*) FROM pg_extension WHERE extname = 'pg_stat_statements'";
+
+/// Columns of user tables that are not the leading column of any index
+pub(crate) const UNINDEXED_COLUMNS_SQL: &str = "SELECT n.nspname, c.relname, a.attname \
+   FROM pg_attribute a \
+   JOIN pg_class c ON c.oid = a.attrelid \
+   JOIN pg_namespace n ON n.oid = c.relnamespace \
+   WHERE c.relkind IN ('r', 'p') AND a.attnum > 0 AND NOT a.attisdropped \
+   AND n.nspname NOT IN ('pg_catalog', 'information_schema') \
+   AND NOT EXISTS (SELECT 1 FROM pg_index i WHERE i.indrelid = c.oid AND i.indkey[0] = a.attnum)";
+
+/// Options for `suggestIndexes()`
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct SuggestIndexesOptions {
+  /// Only report tables with at least this many sequential scans (default: 10)
+  pub min_seq_scans: Option<u32>,
+  /// Only report tables with at least this many live rows (default: 1000)
+  pub min_rows: Option<u32>,
+  /// Number of most frequently called statements to examine (default: 100)
+  pub max_statements: Option<u32>,
+}
+
+/// A frequently called statement with a WHERE clause, from pg_stat_statements
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct FrequentPredicate {
+  /// Normalized statement text, with constants replaced by parameters
+  pub query: String,
+  /// Number of times the statement was executed
+  pub calls: i64,
+  /// Mean execution time in milliseconds
+  pub mean_time_ms: f64,
+  /// Columns compared in the statement's WHERE clauses
+  pub columns: Vec<String>,
+}
+
+/// A table that is read mostly by sequential scans
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct IndexSuggestion {
+  /// Schema of the table
+  pub schema_name: String,
+  /// Name of the table
+  pub table_name: String,
+  /// Number of sequential scans
+  pub seq_scans: i64,
+  /// Number of rows read by sequential scans
+  pub seq_rows_read: i64,
+  /// Number of index scans
+  pub index_scans: i64,
+  /// Estimated number of live rows
+  pub live_rows: i64,
+  /// Columns filtered on by frequent statements that lead no index, most called first
+  /// (empty without pg_stat_statements)
+  pub columns: Vec<String>,
+  /// Statement creating an index on the first of `columns`, if any
+  pub create_index_sql: Option<String>,
+  /// Why the table is reported
+  pub reason: String,
+}
+
+/// Report of `suggestIndexes()`
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct IndexAdvice {
+  /// Database the report is for
+  pub database_name: String,
+  /// Whether pg_stat_statements was available, so predicates could be analyzed
+  pub statements_available: bool,
+  /// Tables read mostly by sequential scans, most rows read first
+  pub suggestions: Vec<IndexSuggestion>,
+  /// Frequent statements with a WHERE clause, most called first
+  pub frequent_predicates: Vec<FrequentPredicate>,
+}
+
+/// Tables of pg_stat_user_tables whose sequential scans reach the thresholds and outnumber
+/// their index scans
+pub(crate) fn seq_scan_tables_sql(options: &SuggestIndexesOptions) -> String {
+  format!(
+    "SELECT schemaname, relname, seq_scan, seq_tup_read, coalesce(idx_scan, 0), n_live_tup \
+     FROM pg_stat_user_tables \
+     WHERE seq_scan >= {} AND n_live_tup >= {} AND seq_scan > coalesce(idx_scan, 0) \
+     ORDER BY seq_tup_read DESC",
+    options.min_seq_scans.unwrap_or(DEFAULT_MIN_SEQ_SCANS),
+    options.min_rows.unwrap_or(DEFAULT_MIN_ROWS)
+  )
+}
+
+/// The most frequently called statements of the current database with a WHERE clause
+pub(crate) fn frequent_statements_sql(options: &SuggestIndexesOptions) -> String {
+  format!(
+    "SELECT query, calls, mean_exec_time FROM pg_stat_statements \
+     WHERE dbid = (SELECT oid FROM pg_database WHERE datname = current_database()) \
+     AND query ~* {} ORDER BY calls DESC LIMIT {}",
+    quote_literal("\\mwhere\\M"),
+    options.max_statements.unwrap_or(DEFAULT_MAX_STATEMENTS)
+  )
+}
+
+/// Parse a row of `seq_scan_tables_sql`, without columns yet
+pub(crate) fn parse_seq_scan_table(row: &[String]) -> Option<IndexSuggestion> {
+  let [schema_name, table_name, seq_scans, seq_rows_read, index_scans, live_rows] = row else {
+    return None;
+  };
+  let (seq_scans, seq_rows_read, index_scans) = (
+    seq_scans.parse().ok()?,
+    seq_rows_read.parse().ok()?,
+    index_scans.parse().ok()?,
+  );
+  Some(IndexSuggestion {
+    schema_name: schema_name.clone(),
+    table_name: table_name.clone(),
+    seq_scans,
+    seq_rows_read,
+    index_scans,
+    live_rows: live_rows.parse().ok()?,
+    columns: Vec::new(),
+    create_index_sql: None,
+    reason: format!(
+      "{seq_scans} sequential scans read {seq_rows_read} rows, against {index_scans} index scans"
+    ),
+  })
+}
+
+/// Parse a row of `frequent_statements_sql`
+pub(crate) fn parse_frequent_statement(row: &[String]) -> Option<FrequentPredicate> {
+  let [query, calls, mean_time_ms] = row else {
+    return None;
+  };
+  Some(FrequentPredicate {
+    columns: predicate_columns(query),
+    query: query.clone(),
+    calls: calls.parse().ok()?,
+    mean_time_ms: mean_time_ms.parse().ok()?,
+  })
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+  /// An identifier or keyword, lower-cased unless it was quoted (the flag); the parts
+  /// of a qualified name are separate words
+  Word(String, bool),
+  /// A comparison operator
+  Comparison,
+  /// Anything else: literals, parameters, punctuation
+  Other,
+}
+
+/// Split SQL into the tokens that matter for finding compared columns
+fn tokenize(sql: &str) -> Vec<Token> {
+  let mut tokens = Vec::new();
+  let mut chars = sql.chars().peekable();
+  while let Some(c) = chars.next() {
+    match c {
+      c if c.is_whitespace() => {}
+      '\'' => {
+        // String literal; a doubled quote continues it
+        while let Some(c) = chars.next() {
+          if c == '\'' && chars.next_if_eq(&'\'').is_none() {
+            break;
+          }
+        }
+        tokens.push(Token::Other);
+      }
+      '"' => {
+        let mut name = String::new();
+        while let Some(c) = chars.next() {
+          if c == '"' && chars.next_if_eq(&'"').is_none() {
+            break;
+          }
+          name.push(c);
+        }
+        tokens.push(Token::Word(name, true));
+      }
+      c if c.is_alphabetic() || c == '_' => {
+        let mut word = c.to_string();
+        while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_' || *c == '$') {
+          word.push(c);
+        }
+        tokens.push(Token::Word(word.to_lowercase(), false));
+      }
+      '<' | '>' | '=' | '!' => {
+        while chars.next_if(|c| matches!(c, '<' | '>' | '=')).is_some() {}
+        tokens.push(Token::Comparison);
+      }
+      '.' => {}
+      _ => tokens.push(Token::Other),
+    }
+  }
+  tokens
+}
+
+/// Keywords that end a WHERE clause
+const CLAUSE_END: &[&str] = &[
+  "group",
+  "order",
+  "limit",
+  "offset",
+  "having",
+  "window",
+  "union",
+  "returning",
+  "for",
+];
+/// Keywords that compare the word before them
+const COMPARISON_KEYWORDS: &[&str] = &["in", "like", "ilike", "between", "is", "similar"];
+/// Keywords that are never column names in a predicate
+const NOT_COLUMNS: &[&str] = &["and", "or", "not", "where", "exists", "any", "all", "null"];
+
+/// Columns compared with an operator or IN, LIKE, BETWEEN, IS in the WHERE clauses of `sql`
+pub(crate) fn predicate_columns(sql: &str) -> Vec<String> {
+  let tokens = tokenize(sql);
+  let mut columns = Vec::new();
+  let mut in_where = false;
+  for (index, token) in tokens.iter().enumerate() {
+    let Token::Word(word, quoted) = token else {
+      continue;
+    };
+    if !quoted {
+      if word == "where" {
+        in_where = true;
+        continue;
+      }
+      if CLAUSE_END.contains(&word.as_str()) {
+        in_where = false;
+        continue;
+      }
+      if NOT_COLUMNS.contains(&word.as_str()) || COMPARISON_KEYWORDS.contains(&word.as_str()) {
+        continue;
+      }
+    }
+    if !in_where {
+      continue;
+    }
+    // The qualifier of "t.col" is followed by a word, so only the column matches
+    let compared = match tokens.get(index + 1) {
+      Some(Token::Comparison) => true,
+      Some(Token::Word(next, false)) => {
+        COMPARISON_KEYWORDS.contains(&next.as_str())
+          || (next == "not"
+            && matches!(tokens.get(index + 2), Some(Token::Word(after, false))
+              if COMPARISON_KEYWORDS.contains(&after.as_str())))
+      }
+      _ => false,
+    };
+    if compared && !columns.contains(word) {
+      columns.push(word.clone());
+    }
+  }
+  columns
+}
+
+/// Whether `query` mentions `table` as a whole word
+fn mentions_table(query: &str, table: &str) -> bool {
+  let query = query.to_lowercase();
+  let table = table.to_lowercase();
+  query.match_indices(&table).any(|(start, _)| {
+    let before = query[..start].chars().next_back();
+    let after = query[start + table.len()..].chars().next();
+    let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+    !is_word(before) && !is_word(after)
+  })
+}
+
+/// Fill in the columns of each suggestion from the frequent predicates that mention its
+/// table, keeping the columns that lead no index
+pub(crate) fn attach_columns(
+  suggestions: &mut [IndexSuggestion],
+  predicates: &[FrequentPredicate],
+  unindexed_columns: &[Vec<String>],
+) {
+  let unindexed: HashSet<(&str, &str, &str)> = unindexed_columns
+    .iter()
+    .filter_map(|row| match row.as_slice() {
+      [schema, table, column] => Some((schema.as_str(), table.as_str(), column.as_str())),
+      _ => None,
+    })
+    .collect();
+  for suggestion in suggestions.iter_mut() {
+    let mut calls: HashMap<String, i64> = HashMap::new();
+    for predicate in predicates
+      .iter()
+      .filter(|predicate| mentions_table(&predicate.query, &suggestion.table_name))
+    {
+      for column in &predicate.columns {
+        let key = (
+          suggestion.schema_name.as_str(),
+          suggestion.table_name.as_str(),
+          column.as_str(),
+        );
+        if unindexed.contains(&key) {
+          *calls.entry(column.clone()).or_default() += predicate.calls;
+        }
+      }
+    }
+    let mut columns: Vec<(String, i64)> = calls.into_iter().collect();
+    columns.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    suggestion.columns = columns.into_iter().map(|(column, _)| column).collect();
+    suggestion.create_index_sql = suggestion.columns.first().map(|column| {
+      format!(
+        "CREATE INDEX ON {}.{} ({})",
+        quote_ident(&suggestion.schema_name),
+        quote_ident(&suggestion.table_name),
+        quote_ident(column)
+      )
+    });
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_predicate_columns() {
+    assert_eq!(
+      predicate_columns("SELECT * FROM users WHERE email = $1 AND deleted_at IS NULL"),
+      ["email", "deleted_at"]
+    );
+    assert_eq!(
+      predicate_columns(
+        "SELECT o.id FROM orders o JOIN users u ON u.id = o.user_id \
+         WHERE o.status IN ($1, $2) AND u.\"Region\" NOT LIKE $3 ORDER BY o.created_at > $4"
+      ),
+      ["status", "Region"]
+    );
+    assert_eq!(
+      predicate_columns("UPDATE t SET note = 'where x = 1' WHERE id >= $1"),
+      ["id"]
+    );
+    assert!(predicate_columns("SELECT count(*) FROM users").is_empty());
+  }
+
+  #[test]
+  fn test_mentions_table() {
+    assert!(mentions_table("SELECT * FROM users WHERE id = $1", "users"));
+    assert!(mentions_table("select * from public.Users", "users"));
+    assert!(!mentions_table("SELECT * FROM users_archive", "users"));
+  }
+
+  #[test]
+  fn test_attach_columns() {
+    let row = |values: &[&str]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+    let mut suggestions =
+      vec![parse_seq_scan_table(&row(&["public", "users", "50", "500000", "0", "10000"])).unwrap()];
+    let predicates = vec![
+      parse_frequent_statement(&row(&["SELECT * FROM users WHERE email = $1", "40", "1.5"]))
+        .unwrap(),
+      parse_frequent_statement(&row(&[
+        "SELECT * FROM users WHERE id = $1 OR country = $2",
+        "90",
+        "0.2",
+      ]))
+      .unwrap(),
+    ];
+    let unindexed = vec![
+      row(&["public", "users", "email"]),
+      row(&["public", "users", "country"]),
+    ];
+    attach_columns(&mut suggestions, &predicates, &unindexed);
+    assert_eq!(suggestions[0].columns, ["country", "email"]);
+    assert_eq!(
+      suggestions[0].create_index_sql.as_deref(),
+      Some("CREATE INDEX ON \"public\".\"users\" (\"country\")")
+    );
+    assert_eq!(
+      suggestions[0].reason,
+      "50 sequential scans read 500000 rows, against 0 index scans"
+    );
+  }
+}
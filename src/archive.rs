@@ -0,0 +1,114 @@
+//! Platform-aware archive_command and restore_command generation for WAL archiving
+
+use crate::paths::{native_path, native_path_for};
+use napi_derive::napi;
+use std::path::Path;
+
+/// Build an `archive_command` that copies completed WAL segments into `archive_dir`
+///
+/// Uses `cp` on Unix and `copy` on Windows. Segments that already exist in the archive
+/// are not overwritten, so the command fails instead of silently replacing a segment.
+///
+/// @param archive_dir - Directory the WAL segments are copied to
+/// @returns The command, ready to be used as the `archive_command` server setting
+///
+/// @example
+/// ```typescript
+/// const pg = new PostgresInstance({
+///   serverConfig: { archive_mode: 'on', archive_command: archiveCommand('/var/lib/wal') },
+/// });
+/// ```
+#[napi]
+pub fn archive_command(archive_dir: String) -> String {
+  archive_command_for(&archive_dir, cfg!(windows))
+}
+
+/// Build a `restore_command` that reads WAL segments back from `archive_dir`
+///
+/// @param archive_dir - Directory the WAL segments were archived to
+/// @returns The command, ready to be used as the `restore_command` server setting
+#[napi]
+pub fn restore_command(archive_dir: String) -> String {
+  restore_command_for(&archive_dir, cfg!(windows))
+}
+
+pub(crate) fn archive_command_for(archive_dir: &str, windows: bool) -> String {
+  let target = segment_path(archive_dir, windows);
+  if windows {
+    format!("if not exist {target} copy \"%p\" {target}")
+  } else {
+    format!("test ! -f {target} && cp %p {target}")
+  }
+}
+
+pub(crate) fn restore_command_for(archive_dir: &str, windows: bool) -> String {
+  let source = segment_path(archive_dir, windows);
+  if windows {
+    format!("copy {source} \"%p\"")
+  } else {
+    format!("cp {source} %p")
+  }
+}
+
+/// Quoted path of the `%f` segment inside `archive_dir`, as understood by the platform's shell
+fn segment_path(archive_dir: &str, windows: bool) -> String {
+  // "%" introduces a placeholder in archive_command and restore_command
+  let dir = native_path_for(archive_dir, windows).replace('%', "%%");
+  if windows {
+    format!("\"{}\\%f\"", dir.trim_end_matches('\\'))
+  } else {
+    let dir = dir.trim_end_matches('/');
+    format!("'{}/%f'", dir.replace('\'', "'\\''"))
+  }
+}
+
+/// Absolute form of `archive_dir`; the server runs archive commands inside its data directory,
+/// so relative paths would point somewhere else
+pub(crate) fn absolute_archive_dir(archive_dir: &Path) -> std::io::Result<String> {
+  Ok(
+    native_path(std::path::absolute(archive_dir)?)
+      .to_string_lossy()
+      .to_string(),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_unix_commands() {
+    assert_eq!(
+      archive_command_for("/var/lib/wal", false),
+      "test ! -f '/var/lib/wal/%f' && cp %p '/var/lib/wal/%f'"
+    );
+    assert_eq!(
+      restore_command_for("/var/lib/wal/", false),
+      "cp '/var/lib/wal/%f' %p"
+    );
+    assert_eq!(
+      restore_command_for("/tmp/it's 100%", false),
+      "cp '/tmp/it'\\''s 100%%/%f' %p"
+    );
+  }
+
+  #[test]
+  fn test_windows_commands() {
+    assert_eq!(
+      archive_command_for("C:/pg/wal", true),
+      "if not exist \"C:\\pg\\wal\\%f\" copy \"%p\" \"C:\\pg\\wal\\%f\""
+    );
+    assert_eq!(
+      restore_command_for("C:\\pg\\wal\\", true),
+      "copy \"C:\\pg\\wal\\%f\" \"%p\""
+    );
+    assert_eq!(
+      restore_command_for("\\\\?\\C:\\Program Files\\pg wal", true),
+      "copy \"C:\\Program Files\\pg wal\\%f\" \"%p\""
+    );
+    assert_eq!(
+      archive_command_for("\\\\?\\UNC\\backup\\wal", true),
+      "if not exist \"\\\\backup\\wal\\%f\" copy \"%p\" \"\\\\backup\\wal\\%f\""
+    );
+  }
+}
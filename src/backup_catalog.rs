@@ -0,0 +1,364 @@
+//! A catalog of the backups made through pg-embedded, for `listBackups()` and
+//! `pruneBackups()`
+//!
+//! The catalog is a JSON file in the installation directory, shared by every instance
+//! using that installation; each entry records the data directory of the instance it was
+//! made from. Changes are made under a lock file, so concurrent instances do not lose
+//! each other's entries, and written through a temporary file, so a crash never leaves it
+//! truncated.
+
+use crate::error::{PgEmbedError, Result};
+use crate::paths::extended_path;
+use napi_derive::napi;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// File in the installation directory holding the catalog
+const CATALOG_FILE: &str = "backup-catalog.json";
+
+/// A backup recorded in the catalog
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupEntry {
+  /// Unique ID of the backup
+  pub id: String,
+  /// "dump", "dumpall" or "basebackup"
+  pub kind: String,
+  /// Path of the dump file or base backup directory
+  pub path: String,
+  /// Size of the backup when it was made, in bytes
+  pub size_bytes: f64,
+  /// Database that was dumped; not set for cluster-wide backups
+  pub database: Option<String>,
+  /// WAL position of the server when the backup started
+  pub lsn: Option<String>,
+  /// Time the backup finished, in milliseconds since the Unix epoch
+  pub created_at: f64,
+  /// Data directory of the instance the backup was made from
+  pub data_dir: String,
+}
+
+/// Which backups `pruneBackups()` keeps; a backup is removed when any rule removes it
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct BackupPrunePolicy {
+  /// Keep only this many of the newest backups of each kind
+  pub keep_last: Option<u32>,
+  /// Remove backups older than this many milliseconds
+  pub max_age_ms: Option<f64>,
+  /// Only consider backups of this kind ("dump", "dumpall" or "basebackup")
+  pub kind: Option<String>,
+  /// Delete the files of removed backups as well (default: true)
+  pub delete_files: Option<bool>,
+}
+
+/// Path of the catalog of the installation in `installation_dir`
+pub(crate) fn catalog_path(installation_dir: &Path) -> PathBuf {
+  installation_dir.join(CATALOG_FILE)
+}
+
+/// Milliseconds since the Unix epoch
+pub(crate) fn now_ms() -> f64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|elapsed| elapsed.as_secs_f64() * 1000.0)
+    .unwrap_or_default()
+}
+
+/// Size of a file, or of the files below a directory, in bytes
+pub(crate) fn backup_size(path: &Path) -> u64 {
+  match fs::symlink_metadata(path) {
+    Ok(metadata) if metadata.is_dir() => fs::read_dir(path)
+      .map(|entries| {
+        entries
+          .flatten()
+          .map(|entry| backup_size(&entry.path()))
+          .sum()
+      })
+      .unwrap_or_default(),
+    Ok(metadata) => metadata.len(),
+    Err(_) => 0,
+  }
+}
+
+/// The entries of the catalog at `path`; a missing catalog is empty
+pub(crate) fn load_catalog(path: &Path) -> Result<Vec<BackupEntry>> {
+  let contents = match fs::read_to_string(extended_path(path)) {
+    Ok(contents) => contents,
+    Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+    Err(e) => {
+      return Err(PgEmbedError::InternalError(format!(
+        "Failed to read the backup catalog {}: {e}",
+        path.display()
+      )))
+    }
+  };
+  serde_json::from_str(&contents).map_err(|e| {
+    PgEmbedError::InternalError(format!(
+      "Backup catalog {} is not valid: {e}",
+      path.display()
+    ))
+  })
+}
+
+/// Lock the catalog at `path` for a change; the lock is released when the file is dropped
+fn lock_catalog(path: &Path) -> Result<File> {
+  let lock_path = path.with_extension("json.lock");
+  File::create(extended_path(&lock_path))
+    .and_then(|file| file.lock().map(|()| file))
+    .map_err(|e| {
+      PgEmbedError::InternalError(format!(
+        "Failed to lock the backup catalog {}: {e}",
+        path.display()
+      ))
+    })
+}
+
+/// Write `entries` to the catalog at `path`, which the caller has locked
+fn save_catalog(path: &Path, entries: &[BackupEntry]) -> Result<()> {
+  static TEMP_FILES: AtomicU64 = AtomicU64::new(0);
+  let contents = serde_json::to_string_pretty(entries).map_err(|e| {
+    PgEmbedError::InternalError(format!("Failed to encode the backup catalog: {e}"))
+  })?;
+  let temp = path.with_extension(format!(
+    "json.{}-{}.tmp",
+    std::process::id(),
+    TEMP_FILES.fetch_add(1, Ordering::Relaxed)
+  ));
+  fs::write(extended_path(&temp), contents)
+    .and_then(|()| fs::rename(extended_path(&temp), extended_path(path)))
+    .map_err(|e| {
+      PgEmbedError::InternalError(format!(
+        "Failed to write the backup catalog {}: {e}",
+        path.display()
+      ))
+    })
+}
+
+/// Add `entry` to the catalog at `path`
+pub(crate) fn record_backup(path: &Path, entry: BackupEntry) -> Result<()> {
+  let _lock = lock_catalog(path)?;
+  let mut entries = load_catalog(path)?;
+  entries.push(entry);
+  save_catalog(path, &entries)
+}
+
+/// The backups of the instance in `data_dir`, newest first
+pub(crate) fn list_backups(path: &Path, data_dir: &str) -> Result<Vec<BackupEntry>> {
+  let mut entries: Vec<BackupEntry> = load_catalog(path)?
+    .into_iter()
+    .filter(|entry| entry.data_dir == data_dir)
+    .collect();
+  entries.sort_by(|a, b| b.created_at.total_cmp(&a.created_at));
+  Ok(entries)
+}
+
+/// The backups of `entries` that `policy` removes at time `now`
+///
+/// Backups whose files no longer exist are always removed.
+pub(crate) fn backups_to_prune(
+  entries: &[BackupEntry],
+  policy: &BackupPrunePolicy,
+  now: f64,
+  exists: impl Fn(&str) -> bool,
+) -> Vec<BackupEntry> {
+  let mut newest_first: Vec<&BackupEntry> = entries
+    .iter()
+    .filter(|entry| policy.kind.as_ref().is_none_or(|kind| *kind == entry.kind))
+    .collect();
+  newest_first.sort_by(|a, b| b.created_at.total_cmp(&a.created_at));
+  let mut kept_per_kind: Vec<(&str, u32)> = Vec::new();
+  newest_first
+    .into_iter()
+    .filter(|entry| {
+      let position = match kept_per_kind
+        .iter_mut()
+        .find(|(kind, _)| *kind == entry.kind)
+      {
+        Some((_, count)) => {
+          *count += 1;
+          *count
+        }
+        None => {
+          kept_per_kind.push((entry.kind.as_str(), 1));
+          1
+        }
+      };
+      let too_many = policy.keep_last.is_some_and(|keep| position > keep);
+      let too_old = policy
+        .max_age_ms
+        .is_some_and(|max_age| now - entry.created_at > max_age);
+      too_many || too_old || !exists(&entry.path)
+    })
+    .cloned()
+    .collect()
+}
+
+/// Remove the backups of the instance in `data_dir` that `policy` removes from the catalog
+/// at `path`, deleting their files unless the policy keeps them
+pub(crate) fn prune_backups(
+  path: &Path,
+  data_dir: &str,
+  policy: &BackupPrunePolicy,
+) -> Result<Vec<BackupEntry>> {
+  let _lock = lock_catalog(path)?;
+  let entries = load_catalog(path)?;
+  let own: Vec<BackupEntry> = entries
+    .iter()
+    .filter(|entry| entry.data_dir == data_dir)
+    .cloned()
+    .collect();
+  let removed = backups_to_prune(&own, policy, now_ms(), |path| {
+    extended_path(Path::new(path)).exists()
+  });
+  if removed.is_empty() {
+    return Ok(removed);
+  }
+  if policy.delete_files.unwrap_or(true) {
+    for entry in &removed {
+      let file = extended_path(Path::new(&entry.path));
+      let deleted = if file.is_dir() {
+        fs::remove_dir_all(&file)
+      } else {
+        fs::remove_file(&file)
+      };
+      match deleted {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => {
+          return Err(PgEmbedError::InternalError(format!(
+            "Failed to delete backup {}: {e}",
+            entry.path
+          )))
+        }
+      }
+    }
+  }
+  let remaining: Vec<BackupEntry> = entries
+    .into_iter()
+    .filter(|entry| !removed.iter().any(|removed| removed.id == entry.id))
+    .collect();
+  save_catalog(path, &remaining)?;
+  Ok(removed)
+}
+
+/// Remove the entries of the instance in `data_dir` from the catalog at `path`, keeping
+/// their files; for temporary instances, whose data directory is deleted on cleanup
+pub(crate) fn forget_backups(path: &Path, data_dir: &str) -> Result<()> {
+  let _lock = lock_catalog(path)?;
+  let entries = load_catalog(path)?;
+  let count = entries.len();
+  let remaining: Vec<BackupEntry> = entries
+    .into_iter()
+    .filter(|entry| entry.data_dir != data_dir)
+    .collect();
+  if remaining.len() == count {
+    return Ok(());
+  }
+  save_catalog(path, &remaining)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn entry(id: &str, kind: &str, created_at: f64) -> BackupEntry {
+    BackupEntry {
+      id: id.to_string(),
+      kind: kind.to_string(),
+      path: format!("/backups/{id}"),
+      size_bytes: 1024.0,
+      database: None,
+      lsn: Some("0/3000060".to_string()),
+      created_at,
+      data_dir: "/data".to_string(),
+    }
+  }
+
+  fn ids(entries: &[BackupEntry]) -> Vec<&str> {
+    entries.iter().map(|entry| entry.id.as_str()).collect()
+  }
+
+  #[test]
+  fn test_backups_to_prune() {
+    let entries = [
+      entry("d1", "dump", 1000.0),
+      entry("d2", "dump", 2000.0),
+      entry("d3", "dump", 3000.0),
+      entry("b1", "basebackup", 1500.0),
+    ];
+    let keep_last = BackupPrunePolicy {
+      keep_last: Some(1),
+      ..Default::default()
+    };
+    assert_eq!(
+      ids(&backups_to_prune(&entries, &keep_last, 4000.0, |_| true)),
+      ["d2", "d1"]
+    );
+
+    let dumps_by_age = BackupPrunePolicy {
+      max_age_ms: Some(2500.0),
+      kind: Some("dump".to_string()),
+      ..Default::default()
+    };
+    assert_eq!(
+      ids(&backups_to_prune(&entries, &dumps_by_age, 4000.0, |_| true)),
+      ["d1"]
+    );
+
+    let missing = backups_to_prune(&entries, &BackupPrunePolicy::default(), 4000.0, |path| {
+      path != "/backups/b1"
+    });
+    assert_eq!(ids(&missing), ["b1"]);
+  }
+
+  #[test]
+  fn test_concurrent_records() {
+    let dir = std::env::temp_dir().join(format!(
+      "pg-embedded-catalog-concurrent-{}",
+      std::process::id()
+    ));
+    fs::create_dir_all(&dir).unwrap();
+    let path = catalog_path(&dir);
+    let _ = fs::remove_file(&path);
+
+    let writers: Vec<_> = (0..8)
+      .map(|i| {
+        let path = path.clone();
+        std::thread::spawn(move || record_backup(&path, entry(&format!("b{i}"), "dump", 0.0)))
+      })
+      .collect();
+    for writer in writers {
+      writer.join().unwrap().unwrap();
+    }
+    assert_eq!(list_backups(&path, "/data").unwrap().len(), 8);
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn test_catalog_round_trip() {
+    let dir = std::env::temp_dir().join(format!("pg-embedded-catalog-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = catalog_path(&dir);
+    let _ = fs::remove_file(&path);
+
+    assert!(list_backups(&path, "/data").unwrap().is_empty());
+    record_backup(&path, entry("old", "dump", 1000.0)).unwrap();
+    record_backup(&path, entry("new", "basebackup", 2000.0)).unwrap();
+    let mut other = entry("other", "dump", 3000.0);
+    other.data_dir = "/elsewhere".to_string();
+    record_backup(&path, other).unwrap();
+
+    assert_eq!(ids(&list_backups(&path, "/data").unwrap()), ["new", "old"]);
+    assert!(fs::read_to_string(&path).unwrap().contains("\"sizeBytes\""));
+
+    forget_backups(&path, "/data").unwrap();
+    assert!(list_backups(&path, "/data").unwrap().is_empty());
+    assert_eq!(ids(&list_backups(&path, "/elsewhere").unwrap()), ["other"]);
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
@@ -0,0 +1,99 @@
+//! Validation of backups restored into a throwaway instance, for `verifyBackup()`
+
+use crate::client::run_query_once;
+use crate::tools::common::ConnectionConfig;
+use crate::tools::PgRestoreFormat;
+use crate::types::RestoredObjectCount;
+use napi_derive::napi;
+
+/// Options for `verifyBackup()`
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct VerifyBackupOptions {
+  /// Queries run against the restored database, in order; a check fails when it errors,
+  /// returns no rows or its first value is false or NULL, e.g. `SELECT count(*) > 0 FROM users`
+  pub checks: Option<Vec<String>>,
+  /// Database the archive is restored into (default: "verify_backup")
+  pub database_name: Option<String>,
+  /// Archive format (detected by pg_restore when omitted)
+  pub format: Option<PgRestoreFormat>,
+  /// Number of parallel restore jobs
+  pub jobs: Option<u32>,
+}
+
+/// Outcome of one check of `verifyBackup()`
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct BackupCheckResult {
+  /// The query that was run
+  pub sql: String,
+  /// Whether the query succeeded and returned a first value that was neither false nor NULL
+  pub passed: bool,
+  /// First value returned, in its text form; null if the query returned no rows or NULL
+  pub value: Option<String>,
+  /// Error the query failed with
+  pub error: Option<String>,
+}
+
+/// Report of `verifyBackup()`
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct BackupVerification {
+  /// Whether the archive was restored and every check passed
+  pub passed: bool,
+  /// Whether the archive was restored and its object counts matched
+  pub restored: bool,
+  /// Why the restore failed
+  pub error: Option<String>,
+  /// Object counts compared after the restore, empty if the restore failed
+  pub object_counts: Vec<RestoredObjectCount>,
+  /// Outcome of every check, in order; empty if the restore failed
+  pub checks: Vec<BackupCheckResult>,
+  /// Time the verification took, including the start of the throwaway instance
+  pub duration_ms: f64,
+}
+
+/// Whether a check returning `value` as its first value passes; a check that returned no
+/// rows has no value and fails, as one returning NULL does
+fn check_value_passes(value: Option<&str>) -> bool {
+  !matches!(value, None | Some("f") | Some("false"))
+}
+
+/// Run the check `sql` on a new connection to `config`
+pub(crate) async fn run_check(config: &ConnectionConfig, sql: &str) -> BackupCheckResult {
+  match run_query_once(config, sql, &[], None).await {
+    Ok(result) => {
+      let value = result
+        .rows
+        .first()
+        .and_then(|row| row.first().cloned().flatten());
+      BackupCheckResult {
+        sql: sql.to_string(),
+        passed: check_value_passes(value.as_deref()),
+        value,
+        error: None,
+      }
+    }
+    Err(e) => BackupCheckResult {
+      sql: sql.to_string(),
+      passed: false,
+      value: None,
+      error: Some(e.to_string()),
+    },
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_check_value_passes() {
+    assert!(check_value_passes(Some("t")));
+    assert!(check_value_passes(Some("42")));
+    assert!(!check_value_passes(Some("f")));
+    assert!(!check_value_passes(Some("false")));
+    // NULL, or no rows at all
+    assert!(!check_value_passes(None));
+  }
+}
@@ -0,0 +1,250 @@
+//! pgbench runs and parsing of their summary output
+
+use crate::error::{PgEmbedError, Result};
+use crate::paths::native_path;
+use crate::tools::common::{compose, ConnectionConfig};
+use napi_derive::napi;
+use postgresql_commands::pgbench::PgBenchBuilder;
+use std::path::Path;
+use std::process::Command;
+
+/// Options for `runPgbench()`
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct PgbenchOptions {
+  /// Built-in script: "tpcb-like", "simple-update" or "select-only" (default: "tpcb-like")
+  pub builtin: Option<String>,
+  /// Custom SQL script to run instead of a built-in one, in pgbench script syntax
+  pub script: Option<String>,
+  /// Create the pgbench tables before the run, replacing existing ones (default: false)
+  pub initialize: Option<bool>,
+  /// Scale factor of the tables created by `initialize` (default: 1)
+  pub scale: Option<u32>,
+  /// Number of concurrent clients (default: 1)
+  pub clients: Option<u32>,
+  /// Number of worker threads (default: 1)
+  pub jobs: Option<u32>,
+  /// Number of transactions each client runs (default: 10 when no duration is given)
+  pub transactions: Option<u32>,
+  /// Run for this many seconds instead of a number of transactions
+  pub duration_seconds: Option<u32>,
+  /// Database to run against (default: the instance's databaseName)
+  pub database_name: Option<String>,
+}
+
+/// Summary of a pgbench run
+#[napi(object)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PgbenchResult {
+  /// Transactions per second, without the initial connection time
+  pub tps: f64,
+  /// Average transaction latency in milliseconds
+  pub latency_avg_ms: Option<f64>,
+  /// Standard deviation of the transaction latency in milliseconds
+  pub latency_stddev_ms: Option<f64>,
+  /// Number of transactions processed
+  pub transactions: i64,
+  /// Number of failed transactions (reported by PostgreSQL 15 and later)
+  pub failed_transactions: Option<i64>,
+  /// The summary pgbench printed
+  pub output: String,
+}
+
+/// Check that the options select one script and one run length
+pub(crate) fn validate_pgbench_options(options: &PgbenchOptions) -> Result<()> {
+  if options.builtin.is_some() && options.script.is_some() {
+    return Err(PgEmbedError::ConfigurationError(
+      "Specify either a builtin or a custom script, not both".to_string(),
+    ));
+  }
+  if options.transactions.is_some() && options.duration_seconds.is_some() {
+    return Err(PgEmbedError::ConfigurationError(
+      "Specify either a number of transactions or a duration, not both".to_string(),
+    ));
+  }
+  Ok(())
+}
+
+/// Base pgbench command connecting to `connection`
+fn base_command(program_dir: &str, connection: &ConnectionConfig) -> PgBenchBuilder {
+  let mut builder = PgBenchBuilder::new().program_dir(native_path(program_dir));
+  if let Some(host) = &connection.host {
+    builder = builder.host(host);
+  }
+  if let Some(port) = connection.port {
+    builder = builder.port(port);
+  }
+  if let Some(username) = &connection.username {
+    builder = builder.username(username);
+  }
+  builder
+}
+
+/// Finish a pgbench command: connection environment and the database argument
+fn finish_command(builder: PgBenchBuilder, connection: &ConnectionConfig) -> Command {
+  let mut command = compose(builder, connection);
+  if let Some(password) = connection.password() {
+    command.env("PGPASSWORD", password);
+  }
+  if let Some(database) = &connection.database {
+    command.arg(database);
+  }
+  command
+}
+
+/// `pgbench -i` creating the pgbench tables
+pub(crate) fn init_command(
+  program_dir: &str,
+  connection: &ConnectionConfig,
+  options: &PgbenchOptions,
+) -> Command {
+  let builder = base_command(program_dir, connection)
+    .initialize()
+    .quiet()
+    .scale(options.scale.unwrap_or(1) as usize);
+  finish_command(builder, connection)
+}
+
+/// pgbench command running the benchmark, with a custom script read from `script_file`
+pub(crate) fn bench_command(
+  program_dir: &str,
+  connection: &ConnectionConfig,
+  options: &PgbenchOptions,
+  script_file: Option<&Path>,
+) -> Command {
+  let mut builder = base_command(program_dir, connection)
+    .client(options.clients.unwrap_or(1) as usize)
+    .jobs(options.jobs.unwrap_or(1) as usize);
+  builder = match script_file {
+    Some(file) => builder.file(file),
+    None => builder.builtin(options.builtin.as_deref().unwrap_or("tpcb-like")),
+  };
+  builder = match options.duration_seconds {
+    Some(seconds) => builder.time(seconds as usize),
+    None => builder.transactions(options.transactions.unwrap_or(10) as usize),
+  };
+  if script_file.is_some() {
+    // A custom script may not use the pgbench tables, which vacuum would expect
+    builder = builder.no_vacuum_bench();
+  }
+  finish_command(builder, connection)
+}
+
+/// The number at the start of `value`, e.g. "812.3" of "812.3 (without initial connection time)"
+fn leading_number<T: std::str::FromStr>(value: &str) -> Option<T> {
+  value.split_whitespace().next()?.parse().ok()
+}
+
+/// Parse the summary pgbench prints after a run
+pub(crate) fn parse_pgbench_output(output: &str) -> Option<PgbenchResult> {
+  let mut result = PgbenchResult {
+    output: output.trim().to_string(),
+    ..Default::default()
+  };
+  let mut tps = None;
+  let mut transactions = None;
+  for line in output.lines() {
+    if let Some(value) = line.strip_prefix("number of transactions actually processed:") {
+      // "1000/1000" for a number of transactions, "1000" for a duration
+      transactions = value.trim().split('/').next().and_then(leading_number);
+    } else if let Some(value) = line.strip_prefix("number of failed transactions:") {
+      result.failed_transactions = leading_number(value);
+    } else if let Some(value) = line.strip_prefix("latency average =") {
+      result.latency_avg_ms = leading_number(value);
+    } else if let Some(value) = line.strip_prefix("latency stddev =") {
+      result.latency_stddev_ms = leading_number(value);
+    } else if let Some(value) = line.strip_prefix("tps =") {
+      // Servers before 14 print the rate including connection time first
+      if tps.is_none() || !value.contains("including") {
+        tps = leading_number(value);
+      }
+    }
+  }
+  result.tps = tps?;
+  result.transactions = transactions?;
+  Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_pgbench_output() {
+    let output = "transaction type: <builtin: TPC-B (sort of)>\n\
+                  scaling factor: 1\n\
+                  query mode: simple\n\
+                  number of clients: 4\n\
+                  number of threads: 2\n\
+                  number of transactions per client: 250\n\
+                  number of transactions actually processed: 1000/1000\n\
+                  number of failed transactions: 0 (0.000%)\n\
+                  latency average = 3.412 ms\n\
+                  latency stddev = 1.020 ms\n\
+                  initial connection time = 5.123 ms\n\
+                  tps = 1172.286 (without initial connection time)\n";
+    let result = parse_pgbench_output(output).unwrap();
+    assert_eq!(result.tps, 1172.286);
+    assert_eq!(result.transactions, 1000);
+    assert_eq!(result.failed_transactions, Some(0));
+    assert_eq!(result.latency_avg_ms, Some(3.412));
+    assert_eq!(result.latency_stddev_ms, Some(1.02));
+
+    let old = "number of transactions actually processed: 5210\n\
+               latency average = 1.920 ms\n\
+               tps = 520.1 (including connections establishing)\n\
+               tps = 523.4 (excluding connections establishing)\n";
+    let result = parse_pgbench_output(old).unwrap();
+    assert_eq!(result.tps, 523.4);
+    assert_eq!(result.transactions, 5210);
+    assert_eq!(result.failed_transactions, None);
+
+    assert_eq!(
+      parse_pgbench_output("pgbench: error: connection failed"),
+      None
+    );
+  }
+
+  #[test]
+  fn test_validate_pgbench_options() {
+    assert!(validate_pgbench_options(&PgbenchOptions::default()).is_ok());
+    assert!(validate_pgbench_options(&PgbenchOptions {
+      builtin: Some("select-only".to_string()),
+      script: Some("SELECT 1;".to_string()),
+      ..Default::default()
+    })
+    .is_err());
+    assert!(validate_pgbench_options(&PgbenchOptions {
+      transactions: Some(100),
+      duration_seconds: Some(10),
+      ..Default::default()
+    })
+    .is_err());
+  }
+
+  #[test]
+  fn test_bench_command_args() {
+    let connection = ConnectionConfig {
+      host: Some("localhost".to_string()),
+      port: Some(5432),
+      username: Some("postgres".to_string()),
+      database: Some("app".to_string()),
+      ..Default::default()
+    };
+    let options = PgbenchOptions {
+      builtin: Some("select-only".to_string()),
+      clients: Some(4),
+      duration_seconds: Some(5),
+      ..Default::default()
+    };
+    let command = bench_command("/pg/bin", &connection, &options, None);
+    let args: Vec<String> = command
+      .get_args()
+      .map(|arg| arg.to_string_lossy().to_string())
+      .collect();
+    assert!(args.contains(&"--builtin".to_string()));
+    assert!(args.contains(&"select-only".to_string()));
+    assert!(args.contains(&"--time".to_string()));
+    assert_eq!(args.last().map(String::as_str), Some("app"));
+  }
+}
@@ -0,0 +1,562 @@
+//! Text forms of values in their binary wire form
+//!
+//! A query with parameters runs over the extended protocol, where the server sends every
+//! column in binary form. `query()` returns the same text forms as a query without
+//! parameters, so the binary forms of the common types are converted back here; other
+//! types must be cast to text in the SQL.
+
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+/// Microseconds in a day
+const DAY_MICROS: i64 = 86_400_000_000;
+
+/// Days from 1970-01-01, the epoch of `civil_date()`, to 2000-01-01, the epoch of the
+/// server's dates and timestamps
+const SERVER_EPOCH_DAYS: i64 = 10_957;
+
+/// The text form of the value `field` of the type with `oid`, `None` for types without
+/// a conversion here
+pub(crate) fn binary_text(oid: u32, field: &[u8]) -> Option<String> {
+  let bytes = |length: usize| field.get(..length).filter(|_| field.len() == length);
+  let i16_at = || bytes(2).map(|b| i16::from_be_bytes([b[0], b[1]]));
+  let i32_at = || bytes(4).map(|b| i32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+  let u32_at = || bytes(4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+  let i64_at = || bytes(8).map(|b| i64::from_be_bytes(b.try_into().unwrap_or_default()));
+  Some(match oid {
+    16 => if bytes(1)?[0] != 0 { "t" } else { "f" }.to_string(),
+    17 => format!("\\x{}", hex(field)),
+    18 => String::from_utf8_lossy(field).into_owned(),
+    19 | 25 | 114 | 142 | 705 | 1042 | 1043 => String::from_utf8_lossy(field).into_owned(),
+    20 => i64_at()?.to_string(),
+    21 => i16_at()?.to_string(),
+    23 => i32_at()?.to_string(),
+    26 | 28 | 29 => u32_at()?.to_string(),
+    650 | 869 => inet_text(field, oid == 650)?,
+    700 => float_text(
+      f32::from_be_bytes(bytes(4)?.try_into().unwrap_or_default()),
+      6,
+    ),
+    701 => float_text(
+      f64::from_be_bytes(bytes(8)?.try_into().unwrap_or_default()),
+      15,
+    ),
+    1082 => date_text(i32_at()?),
+    1083 => time_text(i64_at()?),
+    1114 => timestamp_text(i64_at()?, ""),
+    1184 => timestamp_text(i64_at()?, "+00"),
+    1186 => {
+      let field = bytes(16)?;
+      interval_text(
+        i64::from_be_bytes(field[..8].try_into().unwrap_or_default()),
+        i32::from_be_bytes(field[8..12].try_into().unwrap_or_default()),
+        i32::from_be_bytes(field[12..].try_into().unwrap_or_default()),
+      )
+    }
+    1266 => {
+      let field = bytes(12)?;
+      let zone = i32::from_be_bytes(field[8..].try_into().unwrap_or_default());
+      format!(
+        "{}{}",
+        time_text(i64::from_be_bytes(
+          field[..8].try_into().unwrap_or_default()
+        )),
+        offset_text(-zone)
+      )
+    }
+    1700 => numeric_text(field)?,
+    2950 => {
+      let digits = hex(bytes(16)?);
+      format!(
+        "{}-{}-{}-{}-{}",
+        &digits[..8],
+        &digits[8..12],
+        &digits[12..16],
+        &digits[16..20],
+        &digits[20..]
+      )
+    }
+    3802 => String::from_utf8_lossy(field.get(1..)?).into_owned(),
+    _ => return None,
+  })
+}
+
+/// Lowercase hexadecimal digits of `bytes`
+fn hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// The text form of a float with `digits` significant digits before switching to
+/// exponent notation, as the server writes it
+fn float_text<T>(value: T, digits: i32) -> String
+where
+  T: Copy + Into<f64> + std::fmt::Display + std::fmt::LowerExp,
+{
+  let float: f64 = value.into();
+  if float.is_nan() {
+    return "NaN".to_string();
+  }
+  if float.is_infinite() {
+    return if float > 0.0 { "Infinity" } else { "-Infinity" }.to_string();
+  }
+  let scientific = format!("{value:e}");
+  let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+  let exponent: i32 = exponent.parse().unwrap_or_default();
+  if float != 0.0 && (exponent < -4 || exponent >= digits) {
+    let sign = if exponent < 0 { '-' } else { '+' };
+    format!("{mantissa}e{sign}{:02}", exponent.abs())
+  } else {
+    value.to_string()
+  }
+}
+
+/// Year, month and day of the proleptic Gregorian calendar `days` after 1970-01-01
+fn civil_date(days: i64) -> (i64, u32, u32) {
+  let days = days + 719_468;
+  let era = days.div_euclid(146_097);
+  let day_of_era = days.rem_euclid(146_097);
+  let year_of_era =
+    (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+  let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let month_index = (5 * day_of_year + 2) / 153;
+  let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+  let month = if month_index < 10 {
+    month_index + 3
+  } else {
+    month_index - 9
+  } as u32;
+  let year = year_of_era + era * 400 + i64::from(month <= 2);
+  (year, month, day)
+}
+
+/// The text form of the date `days` after 2000-01-01, with the BC suffix split off
+fn date_parts(days: i64) -> (String, &'static str) {
+  let (year, month, day) = civil_date(days + SERVER_EPOCH_DAYS);
+  if year > 0 {
+    (format!("{year:04}-{month:02}-{day:02}"), "")
+  } else {
+    (format!("{:04}-{month:02}-{day:02}", 1 - year), " BC")
+  }
+}
+
+/// The text form of a date, `days` after 2000-01-01
+fn date_text(days: i32) -> String {
+  match days {
+    i32::MAX => "infinity".to_string(),
+    i32::MIN => "-infinity".to_string(),
+    _ => {
+      let (date, era) = date_parts(days.into());
+      format!("{date}{era}")
+    }
+  }
+}
+
+/// Seconds with their fraction, without trailing zeros
+fn seconds_text(seconds: i64, micros: i64) -> String {
+  if micros == 0 {
+    format!("{seconds:02}")
+  } else {
+    format!("{seconds:02}.{micros:06}")
+      .trim_end_matches('0')
+      .to_string()
+  }
+}
+
+/// The text form of a time of day, `micros` after midnight
+fn time_text(micros: i64) -> String {
+  let seconds = micros / 1_000_000;
+  format!(
+    "{:02}:{:02}:{}",
+    seconds / 3600,
+    seconds / 60 % 60,
+    seconds_text(seconds % 60, micros % 1_000_000)
+  )
+}
+
+/// The text form of a UTC offset of `seconds` east of Greenwich
+fn offset_text(seconds: i32) -> String {
+  let sign = if seconds < 0 { '-' } else { '+' };
+  let seconds = seconds.unsigned_abs();
+  let mut text = format!("{sign}{:02}", seconds / 3600);
+  if !seconds.is_multiple_of(3600) {
+    text.push_str(&format!(":{:02}", seconds / 60 % 60));
+  }
+  if !seconds.is_multiple_of(60) {
+    text.push_str(&format!(":{:02}", seconds % 60));
+  }
+  text
+}
+
+/// The text form of a timestamp, `micros` after 2000-01-01 00:00, followed by `zone`
+///
+/// A timestamp with time zone is given in UTC, whatever the `TimeZone` of the session.
+fn timestamp_text(micros: i64, zone: &str) -> String {
+  match micros {
+    i64::MAX => "infinity".to_string(),
+    i64::MIN => "-infinity".to_string(),
+    _ => {
+      let (date, era) = date_parts(micros.div_euclid(DAY_MICROS));
+      format!(
+        "{date} {}{zone}{era}",
+        time_text(micros.rem_euclid(DAY_MICROS))
+      )
+    }
+  }
+}
+
+/// The text form of an interval, in the default `postgres` interval style
+fn interval_text(micros: i64, days: i32, months: i32) -> String {
+  let mut text = String::new();
+  let mut negative_before = false;
+  for (value, unit) in [(months / 12, "year"), (months % 12, "mon"), (days, "day")] {
+    if value == 0 {
+      continue;
+    }
+    if !text.is_empty() {
+      text.push(' ');
+    }
+    let sign = if negative_before && value > 0 {
+      "+"
+    } else {
+      ""
+    };
+    let plural = if value == 1 { "" } else { "s" };
+    text.push_str(&format!("{sign}{value} {unit}{plural}"));
+    negative_before = value < 0;
+  }
+  if text.is_empty() || micros != 0 {
+    if !text.is_empty() {
+      text.push(' ');
+    }
+    let sign = if micros < 0 {
+      "-"
+    } else if negative_before {
+      "+"
+    } else {
+      ""
+    };
+    let micros = micros.unsigned_abs();
+    let seconds = micros / 1_000_000;
+    text.push_str(&format!(
+      "{sign}{:02}:{:02}:{}",
+      seconds / 3600,
+      seconds / 60 % 60,
+      seconds_text((seconds % 60) as i64, (micros % 1_000_000) as i64)
+    ));
+  }
+  text
+}
+
+/// The text form of an inet or cidr value
+fn inet_text(field: &[u8], cidr: bool) -> Option<String> {
+  let [family, bits, _, length, address @ ..] = field else {
+    return None;
+  };
+  let (address, full) = match (family, address.len()) {
+    (2, 4) if *length == 4 => (
+      Ipv4Addr::from(<[u8; 4]>::try_from(address).ok()?).to_string(),
+      32,
+    ),
+    (3, 16) if *length == 16 => (
+      Ipv6Addr::from(<[u8; 16]>::try_from(address).ok()?).to_string(),
+      128,
+    ),
+    _ => return None,
+  };
+  Some(if cidr || *bits != full {
+    format!("{address}/{bits}")
+  } else {
+    address
+  })
+}
+
+/// The text form of a numeric value
+fn numeric_text(field: &[u8]) -> Option<String> {
+  let header = |index: usize| {
+    field
+      .get(index * 2..index * 2 + 2)
+      .map(|b| u16::from_be_bytes([b[0], b[1]]))
+  };
+  let count = usize::from(header(0)?);
+  let weight = header(1)? as i16;
+  let sign = header(2)?;
+  let scale = usize::from(header(3)?);
+  match sign {
+    0xC000 => return Some("NaN".to_string()),
+    0xD000 => return Some("Infinity".to_string()),
+    0xF000 => return Some("-Infinity".to_string()),
+    _ => {}
+  }
+  let digits = (0..count)
+    .map(|index| header(4 + index))
+    .collect::<Option<Vec<_>>>()?;
+  let digit = |index: i32| {
+    usize::try_from(index)
+      .ok()
+      .and_then(|index| digits.get(index).copied())
+      .unwrap_or_default()
+  };
+  let mut text = String::new();
+  if sign == 0x4000 {
+    text.push('-');
+  }
+  if weight < 0 {
+    text.push('0');
+  } else {
+    text.push_str(&digit(0).to_string());
+    for index in 1..=i32::from(weight) {
+      text.push_str(&format!("{:04}", digit(index)));
+    }
+  }
+  if scale > 0 {
+    let mut fraction = String::new();
+    let mut index = i32::from(weight) + 1;
+    while fraction.len() < scale {
+      fraction.push_str(&format!("{:04}", digit(index)));
+      index += 1;
+    }
+    fraction.truncate(scale);
+    text.push('.');
+    text.push_str(&fraction);
+  }
+  Some(text)
+}
+
+/// The text form of the array `field`, `None` if its elements have no conversion here
+pub(crate) fn binary_array_text(field: &[u8]) -> Option<String> {
+  let word = |index: usize| {
+    field
+      .get(index * 4..index * 4 + 4)
+      .map(|b| i32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+  };
+  let (dimensions, element) = (usize::try_from(word(0)?).ok()?, word(2)?);
+  if dimensions == 0 {
+    return Some("{}".to_string());
+  }
+  let mut bounds = Vec::with_capacity(dimensions);
+  for dimension in 0..dimensions {
+    let size = usize::try_from(word(3 + dimension * 2)?).ok()?;
+    bounds.push((size, word(4 + dimension * 2)?));
+  }
+  let mut rest = field.get((3 + dimensions * 2) * 4..)?;
+  let total: usize = bounds.iter().map(|(size, _)| size).product();
+  let mut elements = Vec::with_capacity(total);
+  for _ in 0..total {
+    let (length, tail) = rest.split_first_chunk::<4>()?;
+    let Ok(length) = usize::try_from(i32::from_be_bytes(*length)) else {
+      elements.push("NULL".to_string());
+      rest = tail;
+      continue;
+    };
+    elements.push(array_element(&binary_text(
+      element as u32,
+      tail.get(..length)?,
+    )?));
+    rest = &tail[length..];
+  }
+
+  let mut text = String::new();
+  if bounds.iter().any(|(_, lower)| *lower != 1) {
+    for (size, lower) in &bounds {
+      let upper = i64::from(*lower) + *size as i64 - 1;
+      text.push_str(&format!("[{lower}:{upper}]"));
+    }
+    text.push('=');
+  }
+  let mut elements = elements.into_iter();
+  nest_elements(&mut text, &mut elements, &bounds);
+  Some(text)
+}
+
+/// An array element, quoted where the array syntax requires it
+fn array_element(text: &str) -> String {
+  let quote = text.is_empty()
+    || text.eq_ignore_ascii_case("NULL")
+    || text
+      .chars()
+      .any(|c| matches!(c, '{' | '}' | ',' | '"' | '\\') || c.is_ascii_whitespace());
+  if quote {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+  } else {
+    text.to_string()
+  }
+}
+
+/// Write the elements of the dimensions `bounds` into `text`, nested in braces
+fn nest_elements(
+  text: &mut String,
+  elements: &mut impl Iterator<Item = String>,
+  bounds: &[(usize, i32)],
+) {
+  text.push('{');
+  let Some(((size, _), inner)) = bounds.split_first() else {
+    return;
+  };
+  for index in 0..*size {
+    if index > 0 {
+      text.push(',');
+    }
+    if inner.is_empty() {
+      text.push_str(&elements.next().unwrap_or_default());
+    } else {
+      nest_elements(text, elements, inner);
+    }
+  }
+  text.push('}');
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_scalar_types() {
+    assert_eq!(binary_text(16, &[1]).unwrap(), "t");
+    assert_eq!(binary_text(23, &(-42i32).to_be_bytes()).unwrap(), "-42");
+    assert_eq!(
+      binary_text(20, &9_007_199_254_740_993i64.to_be_bytes()).unwrap(),
+      "9007199254740993"
+    );
+    assert_eq!(binary_text(701, &0.5f64.to_be_bytes()).unwrap(), "0.5");
+    assert_eq!(binary_text(701, &1e20f64.to_be_bytes()).unwrap(), "1e+20");
+    assert_eq!(
+      binary_text(701, &1.5e-5f64.to_be_bytes()).unwrap(),
+      "1.5e-05"
+    );
+    assert_eq!(binary_text(700, &0.1f32.to_be_bytes()).unwrap(), "0.1");
+    assert_eq!(binary_text(25, b"caf\xc3\xa9").unwrap(), "café");
+    assert_eq!(binary_text(17, &[1, 0xab]).unwrap(), "\\x01ab");
+    assert_eq!(binary_text(3802, b"\x01{\"a\": 1}").unwrap(), "{\"a\": 1}");
+    assert_eq!(
+      binary_text(2950, &[0x12; 16]).unwrap(),
+      "12121212-1212-1212-1212-121212121212"
+    );
+    assert!(binary_text(23, &[0, 1]).is_none());
+    assert!(binary_text(2205, &[0, 0, 4, 0]).is_none());
+  }
+
+  #[test]
+  fn test_date_and_time_types() {
+    assert_eq!(
+      binary_text(1082, &0i32.to_be_bytes()).unwrap(),
+      "2000-01-01"
+    );
+    assert_eq!(
+      binary_text(1082, &9190i32.to_be_bytes()).unwrap(),
+      "2025-02-28"
+    );
+    assert_eq!(
+      binary_text(1082, &(-730_120i32).to_be_bytes()).unwrap(),
+      "0001-12-31 BC"
+    );
+    assert_eq!(
+      binary_text(1082, &i32::MAX.to_be_bytes()).unwrap(),
+      "infinity"
+    );
+    let micros: i64 = 9190 * DAY_MICROS + 13 * 3_600_000_000 + 5 * 60_000_000 + 7_250_000;
+    assert_eq!(
+      binary_text(1114, &micros.to_be_bytes()).unwrap(),
+      "2025-02-28 13:05:07.25"
+    );
+    assert_eq!(
+      binary_text(1184, &(-DAY_MICROS).to_be_bytes()).unwrap(),
+      "1999-12-31 00:00:00+00"
+    );
+    assert_eq!(
+      binary_text(1083, &3_723_000_000i64.to_be_bytes()).unwrap(),
+      "01:02:03"
+    );
+    let mut timetz = 3_723_000_000i64.to_be_bytes().to_vec();
+    timetz.extend_from_slice(&(-19_800i32).to_be_bytes());
+    assert_eq!(binary_text(1266, &timetz).unwrap(), "01:02:03+05:30");
+  }
+
+  #[test]
+  fn test_interval() {
+    assert_eq!(interval_text(0, 0, 0), "00:00:00");
+    assert_eq!(
+      interval_text(3_723_500_000, 3, 14),
+      "1 year 2 mons 3 days 01:02:03.5"
+    );
+    assert_eq!(interval_text(7_200_000_000, -1, 0), "-1 days +02:00:00");
+    assert_eq!(interval_text(-60_000_000, 0, 1), "1 mon -00:01:00");
+  }
+
+  #[test]
+  fn test_numeric() {
+    let numeric = |weight: i16, sign: u16, scale: u16, digits: &[u16]| {
+      let mut field = Vec::new();
+      for word in [digits.len() as u16, weight as u16, sign, scale]
+        .into_iter()
+        .chain(digits.iter().copied())
+      {
+        field.extend_from_slice(&word.to_be_bytes());
+      }
+      binary_text(1700, &field).unwrap()
+    };
+    assert_eq!(numeric(1, 0, 2, &[12, 3456, 7800]), "123456.78");
+    assert_eq!(numeric(-1, 0x4000, 5, &[50]), "-0.00500");
+    assert_eq!(numeric(2, 0, 0, &[1]), "100000000");
+    assert_eq!(numeric(0, 0, 0, &[]), "0");
+    assert_eq!(numeric(0, 0xC000, 0, &[]), "NaN");
+  }
+
+  #[test]
+  fn test_inet() {
+    assert_eq!(
+      binary_text(869, &[2, 32, 0, 4, 10, 0, 0, 1]).unwrap(),
+      "10.0.0.1"
+    );
+    assert_eq!(
+      binary_text(650, &[2, 8, 1, 4, 10, 0, 0, 0]).unwrap(),
+      "10.0.0.0/8"
+    );
+    let mut v6 = vec![3, 128, 0, 16];
+    v6.extend_from_slice(&Ipv6Addr::LOCALHOST.octets());
+    assert_eq!(binary_text(869, &v6).unwrap(), "::1");
+  }
+
+  #[test]
+  fn test_arrays() {
+    let array = |element: u32, bounds: &[(i32, i32)], values: &[Option<&[u8]>]| {
+      let mut field = Vec::new();
+      field.extend_from_slice(&(bounds.len() as i32).to_be_bytes());
+      field.extend_from_slice(&0i32.to_be_bytes());
+      field.extend_from_slice(&element.to_be_bytes());
+      for (size, lower) in bounds {
+        field.extend_from_slice(&size.to_be_bytes());
+        field.extend_from_slice(&lower.to_be_bytes());
+      }
+      for value in values {
+        match value {
+          Some(value) => {
+            field.extend_from_slice(&(value.len() as i32).to_be_bytes());
+            field.extend_from_slice(value);
+          }
+          None => field.extend_from_slice(&(-1i32).to_be_bytes()),
+        }
+      }
+      binary_array_text(&field)
+    };
+    let one = 1i32.to_be_bytes();
+    let two = 2i32.to_be_bytes();
+    assert_eq!(
+      array(23, &[(3, 1)], &[Some(&one), None, Some(&two)]).unwrap(),
+      "{1,NULL,2}"
+    );
+    assert_eq!(
+      array(23, &[(2, 1), (1, 1)], &[Some(&one), Some(&two)]).unwrap(),
+      "{{1},{2}}"
+    );
+    assert_eq!(
+      array(
+        25,
+        &[(3, 0)],
+        &[Some(b"a b"), Some(b"\"q\""), Some(b"null")]
+      )
+      .unwrap(),
+      "[0:2]={\"a b\",\"\\\"q\\\"\",\"null\"}"
+    );
+    assert_eq!(array(23, &[], &[]).unwrap(), "{}");
+    assert!(array(2205, &[(1, 1)], &[Some(&one)]).is_none());
+  }
+}
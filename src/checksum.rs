@@ -0,0 +1,207 @@
+//! Row counts and content checksums of tables, for verifying copies of data
+
+use crate::error::{PgEmbedError, Result};
+use crate::sql::quote_literal;
+use crate::tools::{common::ConnectionConfig, psql::parse_csv, PsqlConfig, PsqlTool};
+use napi_derive::napi;
+
+/// Row count and content checksum of a table
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct TableChecksum {
+  /// Table name as passed in, or schema-qualified when all tables were listed
+  pub table: String,
+  /// Whether the table exists
+  pub exists: bool,
+  /// Number of rows, 0 if the table does not exist
+  pub row_count: i64,
+  /// md5 hash of the rows in a fixed order, or null if the table does not exist
+  pub checksum: Option<String>,
+}
+
+/// Result of comparing a table between two databases with `compareData()`
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct TableComparison {
+  /// Table name as passed in
+  pub table: String,
+  /// Whether the table exists in both databases with the same rows
+  pub matches: bool,
+  /// Checksum of the table in the first database
+  pub a: TableChecksum,
+  /// Checksum of the table in the second database
+  pub b: TableChecksum,
+}
+
+/// Compare the contents of tables in two databases
+///
+/// Every table is reduced to its row count and an md5 hash of its rows sorted by their
+/// text form, so the physical row order does not matter. Tables missing from one of the
+/// databases are reported as mismatches.
+///
+/// @param connA - Connection to the first database
+/// @param connB - Connection to the second database
+/// @param tables - Tables to compare, optionally schema-qualified (e.g. `public.orders`)
+/// @param programDir - Directory containing the psql binary
+/// @returns One comparison per table, in the given order
+/// @throws Error if either database cannot be queried
+///
+/// @example
+/// ```typescript
+/// const connection = ({ host, port, username, password, databaseName }: ConnectionInfo) =>
+///   ({ host, port, username, password, database: databaseName });
+/// const results = await compareData(
+///   connection(source.connectionInfo),
+///   connection(restored.connectionInfo),
+///   ['users', 'orders'],
+///   path.join(source.programDir, 'bin'),
+/// );
+/// const mismatches = results.filter((result) => !result.matches);
+/// ```
+#[napi]
+pub async fn compare_data(
+  conn_a: ConnectionConfig,
+  conn_b: ConnectionConfig,
+  tables: Vec<String>,
+  program_dir: String,
+) -> napi::Result<Vec<TableComparison>> {
+  let a = table_checksums(conn_a, program_dir.clone(), Some(tables.clone())).await?;
+  let b = table_checksums(conn_b, program_dir, Some(tables.clone())).await?;
+  Ok(
+    tables
+      .into_iter()
+      .zip(a.into_iter().zip(b))
+      .map(|(table, (a, b))| TableComparison {
+        table,
+        matches: a.exists && b.exists && a.row_count == b.row_count && a.checksum == b.checksum,
+        a,
+        b,
+      })
+      .collect(),
+  )
+}
+
+/// Compute the checksums of `tables`, or of all user tables when no tables are given
+pub(crate) async fn table_checksums(
+  connection: ConnectionConfig,
+  program_dir: String,
+  tables: Option<Vec<String>>,
+) -> Result<Vec<TableChecksum>> {
+  let tool = PsqlTool::from_connection(
+    connection,
+    program_dir,
+    PsqlConfig {
+      csv: Some(true),
+      tuples_only: Some(true),
+      no_psqlrc: Some(true),
+      variable: Some(("ON_ERROR_STOP".to_string(), "1".to_string())),
+      ..Default::default()
+    },
+  );
+  let query = |sql: String| {
+    let tool = &tool;
+    async move {
+      let result = tool.execute_command(sql).await?;
+      if result.exit_code != 0 {
+        return Err(PgEmbedError::DatabaseError(
+          result.stderr.trim().to_string(),
+        ));
+      }
+      Ok(parse_csv(&result.stdout))
+    }
+  };
+
+  // Pairs of requested name and the quoted relation name, empty for missing tables
+  let relations = query(resolve_tables_sql(tables.as_deref())).await?;
+  let existing: Vec<&str> = relations
+    .iter()
+    .filter_map(|row| row.get(1))
+    .map(String::as_str)
+    .filter(|relation| !relation.is_empty())
+    .collect();
+  let checksums = if existing.is_empty() {
+    Vec::new()
+  } else {
+    query(checksums_sql(&existing)).await?
+  };
+
+  Ok(
+    relations
+      .iter()
+      .map(|row| {
+        let table = row.first().cloned().unwrap_or_default();
+        let relation = row.get(1).map(String::as_str).unwrap_or_default();
+        let checksum = checksums.iter().find(|checksum| {
+          !relation.is_empty() && checksum.first().map(String::as_str) == Some(relation)
+        });
+        TableChecksum {
+          table,
+          exists: checksum.is_some(),
+          row_count: checksum
+            .and_then(|checksum| checksum.get(1))
+            .and_then(|count| count.parse().ok())
+            .unwrap_or(0),
+          checksum: checksum.and_then(|checksum| checksum.get(2)).cloned(),
+        }
+      })
+      .collect(),
+  )
+}
+
+/// Query returning the requested table names with their quoted relation names
+fn resolve_tables_sql(tables: Option<&[String]>) -> String {
+  match tables {
+    Some(tables) => {
+      let names: Vec<String> = tables.iter().map(|table| quote_literal(table)).collect();
+      format!(
+        "SELECT name, to_regclass(name)::text FROM unnest(ARRAY[{}]::text[]) \
+         WITH ORDINALITY AS t(name, n) ORDER BY n",
+        names.join(", ")
+      )
+    }
+    None => "SELECT format('%I.%I', n.nspname, c.relname), c.oid::regclass::text \
+             FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace \
+             WHERE c.relkind = 'r' AND n.nspname NOT IN ('pg_catalog', 'information_schema') \
+             AND n.nspname NOT LIKE 'pg_toast%' ORDER BY 1"
+      .to_string(),
+  }
+}
+
+/// Query returning relation name, row count and checksum for each relation
+fn checksums_sql(relations: &[&str]) -> String {
+  relations
+    .iter()
+    .map(|relation| {
+      // Relation names come from regclass output and are already quoted; ROW(r.*) cannot
+      // be mistaken for a column named r
+      format!(
+        "SELECT {}, count(*), md5(coalesce(string_agg(ROW(r.*)::text, E'\\n' ORDER BY ROW(r.*)::text COLLATE \"C\"), '')) \
+         FROM {relation} AS r",
+        quote_literal(relation)
+      )
+    })
+    .collect::<Vec<_>>()
+    .join(" UNION ALL ")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_checksums_sql() {
+    assert_eq!(
+      checksums_sql(&["users", "\"Order\""]),
+      "SELECT 'users', count(*), md5(coalesce(string_agg(ROW(r.*)::text, E'\\n' ORDER BY ROW(r.*)::text COLLATE \"C\"), '')) \
+       FROM users AS r UNION ALL \
+       SELECT '\"Order\"', count(*), md5(coalesce(string_agg(ROW(r.*)::text, E'\\n' ORDER BY ROW(r.*)::text COLLATE \"C\"), '')) \
+       FROM \"Order\" AS r"
+    );
+  }
+
+  #[test]
+  fn test_resolve_tables_sql_quotes_names() {
+    let sql = resolve_tables_sql(Some(&["public.users".to_string(), "o'brien".to_string()]));
+    assert!(sql.contains("ARRAY['public.users', 'o''brien']::text[]"));
+  }
+}
@@ -0,0 +1,1304 @@
+//! Persistent native connections to a running instance
+//!
+//! psql starts a new session for every call, so work that must share a session, such as
+//! a transaction spanning several queries, runs on these connections instead.
+
+use crate::binary_text::{binary_array_text, binary_text};
+use crate::conninfo::{apply_service, passfile_password};
+use crate::copy_binary::{
+  copy_columns, copy_columns_sql, decode_rows, encode_header, encode_row, encode_trailer,
+  CopyColumn,
+};
+use crate::error::{database_error, PgEmbedError, Result};
+use crate::query_log::QueryLogger;
+use crate::script::{
+  split_script, ExecuteSqlBatchOptions, SqlStatement, SqlStatementKind, SqlStatementResult,
+};
+use crate::sql::{quote_ident, quote_literal};
+use crate::tools::common::ConnectionConfig;
+use crate::tools::psql::ByteaOutput;
+use futures_util::stream::BoxStream;
+use futures_util::TryStreamExt;
+use napi::bindgen_prelude::Either3;
+use napi_derive::napi;
+use sqlx::encode::{Encode, IsNull};
+use sqlx::error::BoxDynError;
+use sqlx::postgres::types::Oid;
+use sqlx::postgres::{
+  PgArgumentBuffer, PgConnectOptions, PgConnection, PgQueryResult, PgRow, PgTypeInfo, PgTypeKind,
+  PgValueFormat, Postgres,
+};
+use sqlx::{
+  AssertSqlSafe, Column, Connection, Either, Executor, Row, SqlSafeStr, Statement, Type, TypeInfo,
+  ValueRef,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// Savepoint each query of a `Transaction` runs under
+const QUERY_SAVEPOINT: &str = "pg_embedded_query";
+
+/// Result of a query on a native connection
+#[napi(object)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QueryResult {
+  /// Names of the returned columns (empty when no rows were returned)
+  pub columns: Vec<String>,
+  /// Returned rows, with each value in its text form and NULL as null
+  pub rows: Vec<Vec<Option<String>>>,
+  /// Number of rows returned or affected
+  pub row_count: u32,
+}
+
+/// Settings of the session a `Client` opens, applied right after it connects
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct SessionOptions {
+  /// Abort any statement that takes longer than this many milliseconds.
+  #[napi(js_name = "statementTimeoutMs")]
+  pub statement_timeout_ms: Option<u32>,
+  /// Abort any statement that waits longer than this many milliseconds for a lock.
+  #[napi(js_name = "lockTimeoutMs")]
+  pub lock_timeout_ms: Option<u32>,
+  /// Character set the client sends and receives text in. Native connections exchange
+  /// text as UTF-8, so only "UTF8" is accepted.
+  #[napi(js_name = "clientEncoding")]
+  pub client_encoding: Option<String>,
+  /// Text format of bytea values in query results.
+  #[napi(js_name = "byteaOutput")]
+  pub bytea_output: Option<ByteaOutput>,
+  /// Time zone of the session, overriding the server default (e.g. "Asia/Tokyo").
+  pub timezone: Option<String>,
+  /// Date style of the session, overriding the server default (e.g. "ISO, DMY").
+  pub datestyle: Option<String>,
+}
+
+impl SessionOptions {
+  /// The server settings of the options, by name
+  fn settings(&self) -> Result<Vec<(&'static str, String)>> {
+    if let Some(encoding) = &self.client_encoding {
+      if !matches!(
+        encoding.to_ascii_uppercase().as_str(),
+        "UTF8" | "UTF-8" | "UNICODE"
+      ) {
+        return Err(PgEmbedError::ConfigurationError(format!(
+          "clientEncoding '{encoding}' is not supported; native connections exchange text \
+           as UTF8"
+        )));
+      }
+    }
+    Ok(
+      [
+        (
+          "statement_timeout",
+          self.statement_timeout_ms.map(|ms| ms.to_string()),
+        ),
+        (
+          "lock_timeout",
+          self.lock_timeout_ms.map(|ms| ms.to_string()),
+        ),
+        (
+          "bytea_output",
+          self
+            .bytea_output
+            .as_ref()
+            .map(|output| output.as_str().to_string()),
+        ),
+        ("TimeZone", self.timezone.clone()),
+        ("DateStyle", self.datestyle.clone()),
+      ]
+      .into_iter()
+      .filter_map(|(name, value)| Some((name, value?)))
+      .collect(),
+    )
+  }
+}
+
+/// Native connection options with the settings of `config`
+///
+/// The parameters of a connection service are filled in first, and without a password
+/// the password file of `passfile` is searched for one, as libpq does.
+pub(crate) fn connect_options(config: &ConnectionConfig) -> Result<PgConnectOptions> {
+  let config = apply_service(config)?;
+  let mut options = PgConnectOptions::new_without_pgpass();
+  if let Some(host) = &config.host {
+    options = options.host(host);
+  }
+  if let Some(port) = config.port {
+    options = options.port(port);
+  }
+  if let Some(username) = &config.username {
+    options = options.username(username);
+  }
+  if let Some(database) = &config.database {
+    options = options.database(database);
+  }
+  if let Some(password) = &config.password {
+    options = options.password(password);
+  } else if let Some(passfile) = &config.passfile {
+    let contents = std::fs::read_to_string(passfile).map_err(|e| {
+      PgEmbedError::ConfigurationError(format!("Failed to read passfile '{passfile}': {e}"))
+    })?;
+    let database = options
+      .get_database()
+      .unwrap_or(options.get_username())
+      .to_string();
+    if let Some(password) = passfile_password(
+      &contents,
+      options.get_host(),
+      options.get_port(),
+      &database,
+      options.get_username(),
+    ) {
+      options = options.password(&password);
+    }
+  }
+  if let Some(ssl_mode) = &config.ssl_mode {
+    options = options.ssl_mode(
+      ssl_mode
+        .parse()
+        .map_err(|_| PgEmbedError::ConfigurationError(format!("Invalid sslMode '{ssl_mode}'")))?,
+    );
+  }
+  if let Some(ssl_root_cert) = &config.ssl_root_cert {
+    options = options.ssl_root_cert(ssl_root_cert);
+  }
+  if let Some(application_name) = &config.application_name {
+    options = options.application_name(application_name);
+  }
+  Ok(options)
+}
+
+/// How long to wait for a connection with the settings of `config`, `None` for no limit
+///
+/// As in libpq, a `connectTimeout` of 0 waits indefinitely.
+pub(crate) fn connect_timeout(config: &ConnectionConfig) -> Result<Option<Duration>> {
+  let timeout = match config.connect_timeout {
+    Some(timeout) => Some(timeout),
+    None => apply_service(config)?.connect_timeout,
+  };
+  Ok(
+    timeout
+      .filter(|seconds| *seconds > 0)
+      .map(|seconds| Duration::from_secs(seconds.into())),
+  )
+}
+
+/// Open a native connection with the settings of `config`
+pub(crate) async fn connect(config: &ConnectionConfig) -> Result<PgConnection> {
+  connect_with_session(config, &SessionOptions::default()).await
+}
+
+/// Open a native connection with the settings of `config` and the session settings of
+/// `session`
+async fn connect_with_session(
+  config: &ConnectionConfig,
+  session: &SessionOptions,
+) -> Result<PgConnection> {
+  let settings = session.settings()?;
+  let options = connect_options(config)?;
+  let connecting = PgConnection::connect_with(&options);
+  let connection = match connect_timeout(config)? {
+    Some(timeout) => tokio::time::timeout(timeout, connecting)
+      .await
+      .map_err(|_| {
+        PgEmbedError::ConnectionError(format!(
+          "Timed out connecting after {} s",
+          timeout.as_secs()
+        ))
+      })?,
+    None => connecting.await,
+  };
+  let mut connection = connection.map_err(|e| PgEmbedError::ConnectionError(e.to_string()))?;
+  // The driver sets TimeZone and DateStyle when connecting, overriding startup options,
+  // so the settings are made on the open session instead
+  if !settings.is_empty() {
+    let calls: Vec<String> = settings
+      .iter()
+      .map(|(name, value)| {
+        format!(
+          "set_config({}, {}, false)",
+          quote_literal(name),
+          quote_literal(value)
+        )
+      })
+      .collect();
+    if let Err(e) = run_query(&mut connection, &format!("SELECT {}", calls.join(", "))).await {
+      let _ = connection.close().await;
+      return Err(e);
+    }
+  }
+  Ok(connection)
+}
+
+/// Result of a query on a `Client`, with values converted to JavaScript types
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct TypedQueryResult {
+  /// Names of the returned columns, in query order (empty when no rows were returned)
+  pub columns: Vec<String>,
+  /// Returned rows as objects mapping column names to values: booleans and 2- and 4-byte
+  /// integers and floats become booleans and numbers, NULL becomes null and every other
+  /// type, including bigint and numeric, keeps its text form
+  pub rows: Vec<HashMap<String, Option<SqlValue>>>,
+  /// Number of rows returned or affected
+  pub row_count: u32,
+}
+
+/// A column value converted to a JavaScript type, or a query parameter
+pub type SqlValue = Either3<bool, f64, String>;
+
+/// The text form of a query parameter, `None` for null
+fn parameter_text(value: &Option<SqlValue>) -> Option<String> {
+  match value.as_ref()? {
+    Either3::A(flag) => Some(flag.to_string()),
+    Either3::B(number) if number.is_nan() => Some("NaN".to_string()),
+    Either3::B(number) if number.is_infinite() => Some(
+      if *number > 0.0 {
+        "Infinity"
+      } else {
+        "-Infinity"
+      }
+      .to_string(),
+    ),
+    Either3::B(number) => Some(number.to_string()),
+    Either3::C(text) => Some(text.clone()),
+  }
+}
+
+/// OID of the `unknown` type, which the server resolves to the type a query expects
+const UNKNOWN_OID: u32 = 705;
+
+/// Largest integer a JavaScript number holds exactly
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+
+/// The type a query parameter is declared with
+///
+/// Numbers and booleans keep their type, so `SELECT $1` with 42 returns a number. Strings
+/// and null are declared as unknown, which the server resolves to the type the query
+/// expects, as it does with parameters sent without a type: `$1` in
+/// `WHERE created_at > $1` becomes a timestamp.
+fn parameter_type(value: &Option<SqlValue>) -> PgTypeInfo {
+  match value {
+    Some(Either3::A(_)) => <bool as Type<Postgres>>::type_info(),
+    Some(Either3::B(number))
+      if number.fract() == 0.0 && (f64::from(i32::MIN)..=f64::from(i32::MAX)).contains(number) =>
+    {
+      <i32 as Type<Postgres>>::type_info()
+    }
+    Some(Either3::B(number)) if number.fract() == 0.0 && number.abs() <= MAX_SAFE_INTEGER => {
+      <i64 as Type<Postgres>>::type_info()
+    }
+    Some(Either3::B(_)) => <f64 as Type<Postgres>>::type_info(),
+    None | Some(Either3::C(_)) => PgTypeInfo::with_oid(Oid(UNKNOWN_OID)),
+  }
+}
+
+/// Whether the binary form of a value of `type_info` is its text in UTF-8
+fn binary_is_text(type_info: &PgTypeInfo) -> bool {
+  match type_info.kind() {
+    PgTypeKind::Enum(_) => true,
+    PgTypeKind::Domain(base) => binary_is_text(base),
+    _ => type_info
+      .oid()
+      .is_some_and(|oid| matches!(oid.0, 19 | 25 | 114 | 142 | 1042 | 1043 | UNKNOWN_OID)),
+  }
+}
+
+/// A string or null query parameter, in the binary form of the type the server resolved
+/// it to
+struct ResolvedParameter {
+  type_info: PgTypeInfo,
+  value: Option<Vec<u8>>,
+}
+
+impl Type<Postgres> for ResolvedParameter {
+  fn type_info() -> PgTypeInfo {
+    PgTypeInfo::with_oid(Oid(UNKNOWN_OID))
+  }
+}
+
+impl Encode<'_, Postgres> for ResolvedParameter {
+  fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> std::result::Result<IsNull, BoxDynError> {
+    match &self.value {
+      Some(value) => {
+        buf.extend_from_slice(value);
+        Ok(IsNull::No)
+      }
+      None => Ok(IsNull::Yes),
+    }
+  }
+
+  fn produces(&self) -> Option<PgTypeInfo> {
+    Some(self.type_info.clone())
+  }
+}
+
+/// The binary forms of `texts`, each converted by the server to the type it is paired with
+///
+/// Every parameter is sent in binary form, so a string resolved to a type such as a
+/// timestamp or uuid is converted by that type's own input function here first.
+async fn convert_texts(
+  connection: &mut PgConnection,
+  texts: &[(&str, &PgTypeInfo)],
+) -> Result<Vec<Vec<u8>>> {
+  let oids = texts
+    .iter()
+    .map(|(_, type_info)| {
+      type_info
+        .oid()
+        .map(|oid| format!("format_type({}, NULL)", oid.0))
+        .ok_or_else(|| {
+          PgEmbedError::DatabaseError(format!("Type {type_info} of a parameter has no OID"))
+        })
+    })
+    .collect::<Result<Vec<_>>>()?;
+  let names = run_query(connection, &format!("SELECT {}", oids.join(", "))).await?;
+  let casts = names
+    .rows
+    .first()
+    .into_iter()
+    .flatten()
+    .enumerate()
+    .map(|(index, name)| {
+      format!(
+        "${}::text::{}",
+        index + 1,
+        name.as_deref().unwrap_or("text")
+      )
+    })
+    .collect::<Vec<_>>();
+  let mut query =
+    sqlx::query(AssertSqlSafe(format!("SELECT {}", casts.join(", ")))).persistent(false);
+  for (text, _) in texts {
+    query = query.bind(*text);
+  }
+  let row = query
+    .fetch_one(&mut *connection)
+    .await
+    .map_err(|e| PgEmbedError::DatabaseError(e.to_string()))?;
+  (0..texts.len())
+    .map(|index| {
+      row
+        .try_get_raw(index)
+        .ok()
+        .and_then(|value| value.as_bytes().ok().map(<[u8]>::to_vec))
+        .ok_or_else(|| {
+          PgEmbedError::DatabaseError("Parameter conversion returned no value".to_string())
+        })
+    })
+    .collect()
+}
+
+/// Run the single statement `sql` with `parameters` bound over the extended protocol and
+/// return its columns, rows (converted by `convert`) and row count
+///
+/// The statement is prepared first to learn the types the server resolved the unknown
+/// parameters to, then run with the values bound to it, so a value is never read as SQL,
+/// whatever it contains.
+async fn fetch_prepared<T, F>(
+  connection: &mut PgConnection,
+  sql: &str,
+  parameters: &[Option<SqlValue>],
+  convert: F,
+) -> Result<(Vec<String>, Vec<T>, u32)>
+where
+  F: Fn(&PgRow) -> Result<T>,
+{
+  let types: Vec<PgTypeInfo> = parameters.iter().map(parameter_type).collect();
+  let statement = (&mut *connection)
+    .prepare_with(AssertSqlSafe(sql.to_string()).into_sql_str(), &types)
+    .await
+    .map_err(|e| PgEmbedError::DatabaseError(e.to_string()))?;
+  let resolved = match statement.parameters() {
+    Some(Either::Left(resolved)) if resolved.len() == parameters.len() => resolved.to_vec(),
+    Some(Either::Left(resolved)) => {
+      return Err(PgEmbedError::DatabaseError(format!(
+        "The statement references ${}, but only {} values were given in params",
+        resolved.len(),
+        parameters.len()
+      )))
+    }
+    _ => {
+      return Err(PgEmbedError::DatabaseError(
+        "The parameters of the statement could not be determined".to_string(),
+      ))
+    }
+  };
+
+  let converted: Vec<(&str, &PgTypeInfo)> = parameters
+    .iter()
+    .zip(&resolved)
+    .filter_map(|(value, type_info)| match value {
+      Some(Either3::C(text)) if !binary_is_text(type_info) => Some((text.as_str(), type_info)),
+      _ => None,
+    })
+    .collect();
+  let mut converted = if converted.is_empty() {
+    Vec::new()
+  } else {
+    convert_texts(connection, &converted).await?
+  }
+  .into_iter();
+
+  let mut query = sqlx::query(AssertSqlSafe(sql.to_string()));
+  for (value, type_info) in parameters.iter().zip(resolved) {
+    query = match value {
+      Some(Either3::A(flag)) => query.bind(*flag),
+      Some(Either3::B(number)) if type_info == <i32 as Type<Postgres>>::type_info() => {
+        query.bind(*number as i32)
+      }
+      Some(Either3::B(number)) if type_info == <i64 as Type<Postgres>>::type_info() => {
+        query.bind(*number as i64)
+      }
+      Some(Either3::B(number)) => query.bind(*number),
+      Some(Either3::C(text)) => {
+        let value = if binary_is_text(&type_info) {
+          text.as_bytes().to_vec()
+        } else {
+          converted.next().unwrap_or_default()
+        };
+        query.bind(ResolvedParameter {
+          type_info,
+          value: Some(value),
+        })
+      }
+      None => query.bind(ResolvedParameter {
+        type_info,
+        value: None,
+      }),
+    };
+  }
+  last_result((&mut *connection).fetch_many(query), convert).await
+}
+
+/// Run `sql` with `parameters` bound and return the columns, rows (converted by `convert`)
+/// and row count of its last statement
+///
+/// Without parameters `sql` may hold several statements; with them it must be a single
+/// statement.
+async fn fetch_bound<T, F>(
+  connection: &mut PgConnection,
+  sql: &str,
+  parameters: &[Option<SqlValue>],
+  convert: F,
+) -> Result<(Vec<String>, Vec<T>, u32)>
+where
+  F: Fn(&PgRow) -> Result<T>,
+{
+  if parameters.is_empty() {
+    return fetch_last(connection, sql, convert).await;
+  }
+  let statements = split_script(sql);
+  if statements.len() != 1 || statements[0].kind != SqlStatementKind::Statement {
+    return Err(PgEmbedError::ConfigurationError(
+      "Parameters can only be bound to a single SQL statement".to_string(),
+    ));
+  }
+  let outcome = fetch_prepared(connection, &statements[0].sql, parameters, convert).await;
+  // The statement was prepared with the types of these parameters, so it must not be
+  // reused for others
+  let _ = connection.clear_cached_statements().await;
+  outcome
+}
+
+/// The text forms of `parameters` for the query log
+fn parameter_texts(parameters: &[Option<SqlValue>]) -> Vec<String> {
+  parameters
+    .iter()
+    .map(|value| parameter_text(value).unwrap_or_else(|| "NULL".to_string()))
+    .collect()
+}
+
+/// Run `sql` and return the columns, rows (converted by `convert`) and row count of its
+/// last statement
+async fn fetch_last<T, F>(
+  connection: &mut PgConnection,
+  sql: &str,
+  convert: F,
+) -> Result<(Vec<String>, Vec<T>, u32)>
+where
+  F: Fn(&PgRow) -> Result<T>,
+{
+  last_result(
+    sqlx::raw_sql(AssertSqlSafe(sql)).fetch_many(&mut *connection),
+    convert,
+  )
+  .await
+}
+
+/// The columns, rows (converted by `convert`) and row count of the last statement in
+/// `results`
+async fn last_result<T, F>(
+  mut results: BoxStream<'_, sqlx::Result<Either<PgQueryResult, PgRow>>>,
+  convert: F,
+) -> Result<(Vec<String>, Vec<T>, u32)>
+where
+  F: Fn(&PgRow) -> Result<T>,
+{
+  let mut last = (Vec::new(), Vec::new(), 0);
+  let mut columns = Vec::new();
+  let mut rows = Vec::new();
+  while let Some(item) = results
+    .try_next()
+    .await
+    .map_err(|e| PgEmbedError::DatabaseError(e.to_string()))?
+  {
+    match item {
+      Either::Left(done) => {
+        let row_count = u32::try_from(done.rows_affected()).unwrap_or(u32::MAX);
+        last = (
+          std::mem::take(&mut columns),
+          std::mem::take(&mut rows),
+          row_count,
+        );
+      }
+      Either::Right(row) => {
+        if columns.is_empty() {
+          columns = row
+            .columns()
+            .iter()
+            .map(|column| column.name().to_string())
+            .collect();
+        }
+        rows.push(convert(&row)?);
+      }
+    }
+  }
+  Ok(last)
+}
+
+/// Run `sql` and return the result of its last statement
+pub(crate) async fn run_query(connection: &mut PgConnection, sql: &str) -> Result<QueryResult> {
+  let (columns, rows, row_count) = fetch_last(connection, sql, text_values).await?;
+  Ok(QueryResult {
+    columns,
+    rows,
+    row_count,
+  })
+}
+
+/// Run `sql` with `parameters` bound and return the result of its last statement
+async fn run_bound_query(
+  connection: &mut PgConnection,
+  sql: &str,
+  parameters: &[Option<SqlValue>],
+) -> Result<QueryResult> {
+  let (columns, rows, row_count) = fetch_bound(connection, sql, parameters, text_values).await?;
+  Ok(QueryResult {
+    columns,
+    rows,
+    row_count,
+  })
+}
+
+/// Run `sql` with `parameters` bound and return the result of its last statement, with
+/// typed values
+async fn run_typed_query(
+  connection: &mut PgConnection,
+  sql: &str,
+  parameters: &[Option<SqlValue>],
+) -> Result<TypedQueryResult> {
+  let (columns, rows, row_count) = fetch_bound(connection, sql, parameters, typed_values).await?;
+  Ok(TypedQueryResult {
+    columns,
+    rows,
+    row_count,
+  })
+}
+
+/// Run a statement of a script, feeding `COPY ... FROM STDIN` statements their data
+pub(crate) async fn run_statement(
+  connection: &mut PgConnection,
+  statement: &SqlStatement,
+) -> Result<QueryResult> {
+  match statement.kind {
+    SqlStatementKind::Statement => run_query(connection, &statement.sql).await,
+    SqlStatementKind::CopyFromStdin => {
+      let mut copy = connection
+        .copy_in_raw(&statement.sql)
+        .await
+        .map_err(|e| PgEmbedError::DatabaseError(e.to_string()))?;
+      let data = statement.copy_data.as_deref().unwrap_or_default();
+      if let Err(e) = copy.send(data.as_bytes()).await {
+        let _ = copy.abort(e.to_string()).await;
+        return Err(PgEmbedError::DatabaseError(e.to_string()));
+      }
+      let rows = copy
+        .finish()
+        .await
+        .map_err(|e| PgEmbedError::DatabaseError(e.to_string()))?;
+      Ok(QueryResult {
+        row_count: u32::try_from(rows).unwrap_or(u32::MAX),
+        ..Default::default()
+      })
+    }
+    SqlStatementKind::MetaCommand => Err(PgEmbedError::DatabaseError(format!(
+      "psql meta-command '{}' can only be run by psql",
+      statement.sql
+    ))),
+  }
+}
+
+/// Run `sql` with `parameters` bound on a new connection and return the result of its
+/// last statement
+pub(crate) async fn run_query_once(
+  config: &ConnectionConfig,
+  sql: &str,
+  parameters: &[Option<SqlValue>],
+  logger: Option<&QueryLogger>,
+) -> Result<QueryResult> {
+  let mut connection = connect(config).await?;
+  let started = Instant::now();
+  let outcome = run_bound_query(&mut connection, sql, parameters).await;
+  let _ = connection.close().await;
+  if let Some(logger) = logger {
+    logger.log(sql, &parameter_texts(parameters), started, &outcome);
+  }
+  outcome
+}
+
+/// Run the statements of a script one by one, in order, on a new connection
+pub(crate) async fn run_script(
+  config: &ConnectionConfig,
+  statements: &[SqlStatement],
+  options: &ExecuteSqlBatchOptions,
+  logger: Option<&QueryLogger>,
+) -> Result<Vec<SqlStatementResult>> {
+  let mut connection = connect(config).await?;
+  let results = run_statements(&mut connection, statements, options, logger).await;
+  let _ = connection.close().await;
+  results
+}
+
+/// Run the statements of a script one by one, in order
+///
+/// Unless `continueOnError` is set, the first failing statement ends the script with an
+/// error naming its line, and an open transaction is rolled back.
+async fn run_statements(
+  connection: &mut PgConnection,
+  statements: &[SqlStatement],
+  options: &ExecuteSqlBatchOptions,
+  logger: Option<&QueryLogger>,
+) -> Result<Vec<SqlStatementResult>> {
+  let transaction = options.transaction.unwrap_or(false);
+  let continue_on_error = options.continue_on_error.unwrap_or(false);
+  let savepoints = transaction && continue_on_error;
+  if transaction {
+    run_query(connection, "BEGIN").await?;
+  }
+
+  let mut results = Vec::with_capacity(statements.len());
+  for statement in statements {
+    if savepoints {
+      run_query(connection, &format!("SAVEPOINT {QUERY_SAVEPOINT}")).await?;
+    }
+    let started = Instant::now();
+    let outcome = run_statement(connection, statement).await;
+    if let Some(logger) = logger {
+      logger.log(&statement.sql, &[], started, &outcome);
+    }
+    let (result, error) = match outcome {
+      Ok(result) => {
+        if savepoints {
+          run_query(connection, &format!("RELEASE SAVEPOINT {QUERY_SAVEPOINT}")).await?;
+        }
+        (Some(result), None)
+      }
+      Err(e) => {
+        let message = match e {
+          PgEmbedError::DatabaseError(message) => message,
+          other => other.to_string(),
+        };
+        if !continue_on_error {
+          if transaction {
+            let _ = run_query(connection, "ROLLBACK").await;
+          }
+          return Err(PgEmbedError::DatabaseError(format!(
+            "Statement at line {} failed: {message}",
+            statement.line
+          )));
+        }
+        if savepoints {
+          run_query(
+            connection,
+            &format!(
+              "ROLLBACK TO SAVEPOINT {QUERY_SAVEPOINT}; RELEASE SAVEPOINT {QUERY_SAVEPOINT}"
+            ),
+          )
+          .await?;
+        }
+        (None, Some(message))
+      }
+    };
+    results.push(SqlStatementResult {
+      line: statement.line,
+      sql: statement.sql.clone(),
+      result,
+      error,
+    });
+  }
+
+  if transaction {
+    run_query(connection, "COMMIT").await?;
+  }
+  Ok(results)
+}
+
+/// The text form of a value received in binary form, `None` if it has no conversion
+fn binary_value_text(type_info: &PgTypeInfo, bytes: &[u8]) -> Option<String> {
+  match type_info.kind() {
+    PgTypeKind::Enum(_) => Some(String::from_utf8_lossy(bytes).into_owned()),
+    PgTypeKind::Domain(base) => binary_value_text(base, bytes),
+    PgTypeKind::Array(_) => binary_array_text(bytes),
+    _ => binary_text(type_info.oid()?.0, bytes),
+  }
+}
+
+/// The text form of the value in column `index` of `row`, `None` for NULL
+///
+/// A query without parameters receives its values in text form, one with parameters in
+/// binary form, which is converted back to the text form the server would have sent.
+fn value_text(row: &PgRow, index: usize) -> Result<Option<String>> {
+  let value = row
+    .try_get_raw(index)
+    .map_err(|e| PgEmbedError::DatabaseError(e.to_string()))?;
+  if value.is_null() {
+    return Ok(None);
+  }
+  let bytes = value
+    .as_bytes()
+    .map_err(|e| PgEmbedError::DatabaseError(e.to_string()))?;
+  let text = match value.format() {
+    PgValueFormat::Text => Some(String::from_utf8_lossy(bytes).into_owned()),
+    PgValueFormat::Binary => binary_value_text(&value.type_info(), bytes),
+  };
+  text.map(Some).ok_or_else(|| {
+    PgEmbedError::DatabaseError(format!(
+      "Column \"{}\" has type {}, which a query with parameters cannot return; cast it \
+       to text",
+      row.columns()[index].name(),
+      value.type_info()
+    ))
+  })
+}
+
+/// Values of a row in their text form
+fn text_values(row: &PgRow) -> Result<Vec<Option<String>>> {
+  (0..row.len()).map(|index| value_text(row, index)).collect()
+}
+
+/// Values of a row, keyed by column name and converted to JavaScript types
+fn typed_values(row: &PgRow) -> Result<HashMap<String, Option<SqlValue>>> {
+  row
+    .columns()
+    .iter()
+    .map(|column| {
+      let value =
+        value_text(row, column.ordinal())?.map(|text| typed_value(column.type_info().name(), text));
+      Ok((column.name().to_string(), value))
+    })
+    .collect()
+}
+
+/// A value of the type named `type_name` converted from its text form
+///
+/// bigint and numeric stay strings, as a JavaScript number cannot hold all of their values.
+fn typed_value(type_name: &str, text: String) -> SqlValue {
+  match type_name {
+    "BOOL" => Either3::A(text == "t"),
+    "INT2" | "INT4" | "OID" | "FLOAT4" | "FLOAT8" => match text.parse() {
+      Ok(number) => Either3::B(number),
+      Err(_) => Either3::C(text),
+    },
+    _ => Either3::C(text),
+  }
+}
+
+/// Size of the chunks `copyInBinary()` sends its data in
+const COPY_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The qualified name of `table` and the columns a binary COPY of it copies
+async fn binary_copy_columns(
+  connection: &mut PgConnection,
+  table: &str,
+  columns: Option<&[String]>,
+) -> Result<(String, Vec<CopyColumn>)> {
+  let result = run_query(connection, &copy_columns_sql(table)).await?;
+  copy_columns(table, &result.rows, columns)
+}
+
+/// The column list of a binary COPY statement
+fn copy_column_list(columns: &[CopyColumn]) -> String {
+  columns
+    .iter()
+    .map(|column| quote_ident(&column.name))
+    .collect::<Vec<_>>()
+    .join(", ")
+}
+
+/// Copy `rows` into `table` with a binary `COPY ... FROM STDIN`
+async fn copy_in_binary(
+  connection: &mut PgConnection,
+  table: &str,
+  rows: &[Vec<Option<SqlValue>>],
+  columns: Option<&[String]>,
+) -> Result<QueryResult> {
+  let (table, columns) = binary_copy_columns(connection, table, columns).await?;
+  let mut buffer = Vec::with_capacity(COPY_CHUNK_SIZE);
+  encode_header(&mut buffer);
+  // Rows are encoded before the COPY starts, so an invalid value leaves the connection
+  // untouched
+  let mut chunks = Vec::new();
+  for row in rows {
+    encode_row(&mut buffer, &columns, row)?;
+    if buffer.len() >= COPY_CHUNK_SIZE {
+      chunks.push(std::mem::replace(
+        &mut buffer,
+        Vec::with_capacity(COPY_CHUNK_SIZE),
+      ));
+    }
+  }
+  encode_trailer(&mut buffer);
+  chunks.push(buffer);
+
+  let statement = format!(
+    "COPY {table} ({}) FROM STDIN WITH (FORMAT binary)",
+    copy_column_list(&columns)
+  );
+  let mut copy = connection
+    .copy_in_raw(&statement)
+    .await
+    .map_err(|e| PgEmbedError::DatabaseError(e.to_string()))?;
+  for chunk in chunks {
+    if let Err(e) = copy.send(chunk).await {
+      let _ = copy.abort(e.to_string()).await;
+      return Err(PgEmbedError::DatabaseError(e.to_string()));
+    }
+  }
+  let rows = copy
+    .finish()
+    .await
+    .map_err(|e| PgEmbedError::DatabaseError(e.to_string()))?;
+  Ok(QueryResult {
+    row_count: u32::try_from(rows).unwrap_or(u32::MAX),
+    ..Default::default()
+  })
+}
+
+/// Read the rows of `table` with a binary `COPY ... TO STDOUT`
+async fn copy_out_binary(
+  connection: &mut PgConnection,
+  table: &str,
+  columns: Option<&[String]>,
+) -> Result<TypedQueryResult> {
+  let (table, columns) = binary_copy_columns(connection, table, columns).await?;
+  let statement = format!(
+    "COPY {table} ({}) TO STDOUT WITH (FORMAT binary)",
+    copy_column_list(&columns)
+  );
+  let data: Vec<u8> = connection
+    .copy_out_raw(&statement)
+    .await
+    .map_err(|e| PgEmbedError::DatabaseError(e.to_string()))?
+    .try_fold(Vec::new(), |mut data, chunk| async move {
+      data.extend_from_slice(&chunk);
+      Ok(data)
+    })
+    .await
+    .map_err(|e| PgEmbedError::DatabaseError(e.to_string()))?;
+  let names: Vec<String> = columns.iter().map(|column| column.name.clone()).collect();
+  let rows: Vec<HashMap<String, Option<SqlValue>>> = decode_rows(&data, &columns)?
+    .into_iter()
+    .map(|values| names.iter().cloned().zip(values).collect())
+    .collect();
+  Ok(TypedQueryResult {
+    row_count: u32::try_from(rows.len()).unwrap_or(u32::MAX),
+    columns: names,
+    rows,
+  })
+}
+
+/// A native connection to a running instance, opened with `connect()`
+///
+/// Every query reuses the connection, so a test suite running hundreds of queries does not
+/// pay for starting psql and authenticating each time. Queries on one client run one at a
+/// time; open several clients to run queries concurrently.
+#[napi]
+pub struct Client {
+  connection: Mutex<Option<PgConnection>>,
+  logger: Option<QueryLogger>,
+}
+
+impl Client {
+  /// Open a client on a new connection with the session settings of `session`
+  pub(crate) async fn connect(
+    config: &ConnectionConfig,
+    session: &SessionOptions,
+    logger: Option<QueryLogger>,
+  ) -> Result<Self> {
+    Ok(Self {
+      connection: Mutex::new(Some(connect_with_session(config, session).await?)),
+      logger,
+    })
+  }
+}
+
+#[napi]
+impl Client {
+  /// Runs SQL on the connection of the client
+  ///
+  /// Values passed in `params` are sent apart from the SQL over the extended protocol and
+  /// bound to its references `$1`, `$2`, ..., so they are never read as SQL, whatever they
+  /// contain. Numbers and booleans keep their type; strings take the type the SQL expects.
+  /// With params, timestamptz values are given in UTC, and columns of less common types,
+  /// such as regclass or composite types, must be cast to text.
+  ///
+  /// @param sql - SQL to run; without params, several statements may be given and the
+  /// result is that of the last one
+  /// @param params - Values of the parameters `$1`, `$2`, ...; null binds NULL
+  /// @returns Promise that resolves to the returned rows, with typed values
+  /// @throws Error if the SQL fails, references a missing parameter, holds several
+  /// statements while params are given or the client is closed
+  ///
+  /// @example
+  /// ```typescript
+  /// const { rows } = await client.query('SELECT id, active FROM users WHERE id = $1', [42]);
+  /// const active = rows.filter((row) => row.active);
+  /// ```
+  #[napi]
+  pub async fn query(
+    &self,
+    sql: String,
+    params: Option<Vec<Option<SqlValue>>>,
+  ) -> napi::Result<TypedQueryResult> {
+    let params = params.unwrap_or_default();
+    let mut connection = self.connection.lock().await;
+    let connection = connection
+      .as_mut()
+      .ok_or_else(|| database_error("The client is closed"))?;
+    let started = Instant::now();
+    let outcome = run_typed_query(connection, &sql, &params).await;
+    if let Some(logger) = &self.logger {
+      logger.log(&sql, &parameter_texts(&params), started, &outcome);
+    }
+    Ok(outcome?)
+  }
+
+  /// Copies rows into a table with a binary `COPY ... FROM STDIN`
+  ///
+  /// Much faster than INSERT statements or a CSV COPY for large or wide tables, as the
+  /// values are encoded in their binary wire form here rather than parsed by the server.
+  /// The columns of the table decide how a value is encoded: boolean, smallint, integer,
+  /// bigint, oid, real, double precision, text, varchar, char, name, json, jsonb, uuid and
+  /// bytea (in hex form, `'\\x0102'`) are supported. Integers may also be given as strings,
+  /// for bigint values beyond the safe range of a JavaScript number.
+  ///
+  /// @param table - Name of the table, optionally schema-qualified
+  /// @param rows - Rows to copy, each with one value per copied column; null copies NULL
+  /// @param columns - Columns the values are for, in order (default: every column of the
+  /// table except generated ones)
+  /// @returns Promise that resolves to the number of rows copied
+  /// @throws Error if a column type is not supported, a value does not fit its column or
+  /// the COPY fails; no row is copied then
+  ///
+  /// @example
+  /// ```typescript
+  /// const rows = Array.from({ length: 100_000 }, (_, i) => [i, `user ${i}`, i % 2 === 0]);
+  /// await client.copyInBinary('users', rows, ['id', 'name', 'active']);
+  /// ```
+  #[napi]
+  pub async fn copy_in_binary(
+    &self,
+    table: String,
+    rows: Vec<Vec<Option<SqlValue>>>,
+    columns: Option<Vec<String>>,
+  ) -> napi::Result<u32> {
+    let mut connection = self.connection.lock().await;
+    let connection = connection
+      .as_mut()
+      .ok_or_else(|| database_error("The client is closed"))?;
+    let started = Instant::now();
+    let outcome = copy_in_binary(connection, &table, &rows, columns.as_deref()).await;
+    if let Some(logger) = &self.logger {
+      logger.log(
+        &format!("COPY {table} FROM STDIN WITH (FORMAT binary)"),
+        &[],
+        started,
+        &outcome,
+      );
+    }
+    Ok(outcome?.row_count)
+  }
+
+  /// Reads the rows of a table with a binary `COPY ... TO STDOUT`
+  ///
+  /// The counterpart of `copyInBinary()`, supporting the same column types, with values
+  /// typed as `query()` types them.
+  ///
+  /// @param table - Name of the table, optionally schema-qualified
+  /// @param columns - Columns to read, in order (default: every column of the table except
+  /// generated ones)
+  /// @returns Promise that resolves to the rows of the table
+  /// @throws Error if a column type is not supported or the COPY fails
+  ///
+  /// @example
+  /// ```typescript
+  /// const { rows } = await client.copyOutBinary('users', ['id', 'name']);
+  /// ```
+  #[napi]
+  pub async fn copy_out_binary(
+    &self,
+    table: String,
+    columns: Option<Vec<String>>,
+  ) -> napi::Result<TypedQueryResult> {
+    let mut connection = self.connection.lock().await;
+    let connection = connection
+      .as_mut()
+      .ok_or_else(|| database_error("The client is closed"))?;
+    let started = Instant::now();
+    let outcome = copy_out_binary(connection, &table, columns.as_deref()).await;
+    if let Some(logger) = &self.logger {
+      logger.log(
+        &format!("COPY {table} TO STDOUT WITH (FORMAT binary)"),
+        &[],
+        started,
+        &outcome,
+      );
+    }
+    Ok(outcome?)
+  }
+
+  /// Closes the connection of the client
+  ///
+  /// Does nothing if the client is already closed.
+  ///
+  /// @returns Promise that resolves once the connection is closed
+  #[napi]
+  pub async fn close(&self) -> napi::Result<()> {
+    if let Some(connection) = self.connection.lock().await.take() {
+      let _ = connection.close().await;
+    }
+    Ok(())
+  }
+}
+
+/// A transaction on a native connection, opened with `beginTransaction()` or handed to the
+/// callback of `runInRollbackTransaction()`
+///
+/// The transaction holds its own connection until it is committed or rolled back.
+#[napi]
+pub struct Transaction {
+  connection: Arc<Mutex<Option<PgConnection>>>,
+  logger: Option<QueryLogger>,
+  /// ID of a transaction that must not be committed, for `runInRollbackTransaction()`
+  rollback_only: Option<String>,
+}
+
+impl Transaction {
+  /// Begin a transaction on a new connection
+  pub(crate) async fn begin(
+    config: &ConnectionConfig,
+    logger: Option<QueryLogger>,
+  ) -> Result<Self> {
+    let mut connection = connect(config).await?;
+    run_query(&mut connection, "BEGIN").await?;
+    Ok(Self {
+      connection: Arc::new(Mutex::new(Some(connection))),
+      logger,
+      rollback_only: None,
+    })
+  }
+
+  /// Begin a transaction on a new connection that `commit()` refuses to commit
+  pub(crate) async fn begin_rollback_only(
+    config: &ConnectionConfig,
+    logger: Option<QueryLogger>,
+  ) -> Result<Self> {
+    let mut transaction = Self::begin(config, logger).await?;
+    transaction.rollback_only = Some(transaction.current_id().await?);
+    Ok(transaction)
+  }
+
+  /// A handle to the same transaction
+  pub(crate) fn handle(&self) -> Self {
+    Self {
+      connection: Arc::clone(&self.connection),
+      logger: self.logger.clone(),
+      rollback_only: self.rollback_only.clone(),
+    }
+  }
+
+  /// ID of the transaction the connection is in, assigning one if needed
+  async fn current_id(&self) -> Result<String> {
+    let mut connection = self.connection.lock().await;
+    let connection = connection.as_mut().ok_or_else(|| {
+      PgEmbedError::DatabaseError("The transaction has already ended".to_string())
+    })?;
+    run_query(connection, "SELECT txid_current()::text")
+      .await?
+      .rows
+      .first()
+      .and_then(|row| row.first().cloned().flatten())
+      .ok_or_else(|| PgEmbedError::DatabaseError("Transaction ID not returned".to_string()))
+  }
+
+  /// Roll back a transaction begun with `begin_rollback_only()`
+  ///
+  /// Fails if SQL run through the transaction ended it, e.g. with `COMMIT`, as its changes
+  /// up to then may have been committed.
+  pub(crate) async fn end_rollback_only(&self) -> Result<()> {
+    let still_open = match &self.rollback_only {
+      // After the transaction ended, each statement runs in a new one
+      Some(id) => self.current_id().await.is_ok_and(|current| current == *id),
+      None => true,
+    };
+    self.end("ROLLBACK").await?;
+    if !still_open {
+      return Err(PgEmbedError::DatabaseError(
+        "The transaction was ended by SQL run through it, so its changes may have been \
+         committed instead of rolled back"
+          .to_string(),
+      ));
+    }
+    Ok(())
+  }
+
+  /// End the transaction with `statement` (COMMIT or ROLLBACK) and close the connection
+  ///
+  /// Does nothing if the transaction has already ended.
+  pub(crate) async fn end(&self, statement: &str) -> Result<()> {
+    let Some(mut connection) = self.connection.lock().await.take() else {
+      return Ok(());
+    };
+    let result = run_query(&mut connection, statement).await;
+    let _ = connection.close().await;
+    result.map(|_| ())
+  }
+}
+
+#[napi]
+impl Transaction {
+  /// Runs SQL inside the transaction
+  ///
+  /// Each call runs under a savepoint: if the SQL fails, only its own changes are rolled
+  /// back and the transaction stays usable, so a test can assert on an expected error and
+  /// carry on.
+  ///
+  /// @param sql - SQL to run; without params, several statements may be given and the
+  /// result is that of the last one
+  /// @param params - Values of the parameters `$1`, `$2`, ...; null binds NULL
+  /// @returns Promise that resolves to the returned rows
+  /// @throws Error if the SQL fails, references a missing parameter, holds several
+  /// statements while params are given or the transaction has ended
+  ///
+  /// @example
+  /// ```typescript
+  /// const { rows } = await tx.query('SELECT count(*) FROM users WHERE name = $1', ['alice']);
+  /// ```
+  #[napi]
+  pub async fn query(
+    &self,
+    sql: String,
+    params: Option<Vec<Option<SqlValue>>>,
+  ) -> napi::Result<QueryResult> {
+    let params = params.unwrap_or_default();
+    let mut connection = self.connection.lock().await;
+    let connection = connection
+      .as_mut()
+      .ok_or_else(|| database_error("The transaction has already ended"))?;
+    run_query(connection, &format!("SAVEPOINT {QUERY_SAVEPOINT}")).await?;
+    let started = Instant::now();
+    let outcome = run_bound_query(connection, &sql, &params).await;
+    if let Some(logger) = &self.logger {
+      logger.log(&sql, &parameter_texts(&params), started, &outcome);
+    }
+    match outcome {
+      Ok(result) => {
+        run_query(connection, &format!("RELEASE SAVEPOINT {QUERY_SAVEPOINT}")).await?;
+        Ok(result)
+      }
+      Err(e) => {
+        run_query(
+          connection,
+          &format!("ROLLBACK TO SAVEPOINT {QUERY_SAVEPOINT}; RELEASE SAVEPOINT {QUERY_SAVEPOINT}"),
+        )
+        .await?;
+        Err(e.into())
+      }
+    }
+  }
+
+  /// Commits the transaction and closes its connection
+  ///
+  /// Does nothing if the transaction has already ended.
+  ///
+  /// @returns Promise that resolves once the transaction is committed
+  /// @throws Error if the commit fails, e.g. on a deferred constraint, or the transaction
+  /// was handed to `runInRollbackTransaction()`'s callback, which is always rolled back
+  #[napi]
+  pub async fn commit(&self) -> napi::Result<()> {
+    if self.rollback_only.is_some() {
+      return Err(database_error(
+        "The transaction of runInRollbackTransaction() is always rolled back and cannot be \
+         committed",
+      ));
+    }
+    Ok(self.end("COMMIT").await?)
+  }
+
+  /// Rolls the transaction back and closes its connection
+  ///
+  /// Does nothing if the transaction has already ended.
+  ///
+  /// @returns Promise that resolves once the transaction is rolled back
+  #[napi]
+  pub async fn rollback(&self) -> napi::Result<()> {
+    Ok(self.end("ROLLBACK").await?)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parameter_type() {
+    let oid = |value: Option<SqlValue>| parameter_type(&value).oid().unwrap().0;
+    assert_eq!(oid(Some(Either3::B(42.0))), 23);
+    assert_eq!(oid(Some(Either3::B(9_007_199_254_740_991.0))), 20);
+    assert_eq!(oid(Some(Either3::B(-1.5))), 701);
+    assert_eq!(oid(Some(Either3::B(f64::INFINITY))), 701);
+    assert_eq!(oid(Some(Either3::A(true))), 16);
+    assert_eq!(oid(Some(Either3::C("O'Brien".to_string()))), UNKNOWN_OID);
+    assert_eq!(oid(None), UNKNOWN_OID);
+  }
+
+  #[test]
+  fn test_binary_is_text() {
+    assert!(binary_is_text(&<String as Type<Postgres>>::type_info()));
+    assert!(!binary_is_text(&<i32 as Type<Postgres>>::type_info()));
+    assert!(!binary_is_text(&<f64 as Type<Postgres>>::type_info()));
+  }
+
+  #[test]
+  fn test_typed_value() {
+    assert!(matches!(
+      typed_value("BOOL", "t".to_string()),
+      Either3::A(true)
+    ));
+    assert!(matches!(
+      typed_value("BOOL", "f".to_string()),
+      Either3::A(false)
+    ));
+    assert!(matches!(typed_value("INT4", "42".to_string()), Either3::B(n) if n == 42.0));
+    assert!(matches!(typed_value("FLOAT8", "-1.5".to_string()), Either3::B(n) if n == -1.5));
+    assert!(matches!(typed_value("FLOAT8", "NaN".to_string()), Either3::B(n) if n.is_nan()));
+    assert!(
+      matches!(typed_value("INT8", "9007199254740993".to_string()), Either3::C(text) if text == "9007199254740993")
+    );
+    assert!(matches!(typed_value("TEXT", "42".to_string()), Either3::C(text) if text == "42"));
+  }
+}
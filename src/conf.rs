@@ -0,0 +1,439 @@
+//! Structured editing of postgresql.conf style configuration files
+//!
+//! Settings are upserted in place: the first active assignment of a key is rewritten
+//! (keeping its trailing comment), later duplicates are dropped and every other line,
+//! including comments, is preserved. Lookups follow `include`, `include_if_exists` and
+//! `include_dir` directives the same way the server does, so values set in conf.d files
+//! are found as well.
+//!
+//! Settings applied by pg-embedded itself go to a managed file in the cluster's `conf.d`
+//! directory, leaving the user's postgresql.conf untouched apart from the include line.
+
+use crate::error::{PgEmbedError, Result};
+use crate::paths::extended_path;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Maximum nesting depth of include directives, as enforced by the server
+const MAX_INCLUDE_DEPTH: usize = 10;
+
+/// Include directory, relative to the data directory, holding the managed file
+const CONF_DIR: &str = "conf.d";
+
+/// File in `CONF_DIR` that pg-embedded writes its settings to
+const MANAGED_CONF_FILE: &str = "pg-embedded.conf";
+
+/// A configuration file loaded for editing
+pub(crate) struct ConfFile {
+  path: PathBuf,
+  lines: Vec<String>,
+}
+
+/// An active line of a configuration file
+#[derive(Debug, PartialEq)]
+enum Entry<'a> {
+  /// `name = value`, with the value already unquoted
+  Setting { name: &'a str, value: String },
+  /// `include`, `include_if_exists` or `include_dir`
+  Include { directive: &'a str, target: String },
+}
+
+impl ConfFile {
+  /// Load `path`; a missing file is treated as empty and created on `save()`
+  pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+    let path = path.into();
+    let lines = match fs::read_to_string(extended_path(&path)) {
+      Ok(contents) => contents.lines().map(str::to_string).collect(),
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+      Err(e) => {
+        return Err(PgEmbedError::ConfigurationError(format!(
+          "Failed to read {}: {e}",
+          path.display()
+        )))
+      }
+    };
+    Ok(Self { path, lines })
+  }
+
+  /// Effective value of `name` in this file and the files it includes
+  pub fn get(&self, name: &str) -> Option<String> {
+    let dir = self.path.parent().unwrap_or(Path::new("."));
+    lookup(&self.lines, dir, name, 0)
+  }
+
+  /// Set `name` to `value` (quoted as needed), replacing any existing assignment in this file
+  pub fn set(&mut self, name: &str, value: &str) {
+    let assignment = format!("{name} = {}", quote_conf_value(value));
+    let mut replaced = false;
+    self.lines.retain_mut(|line| {
+      let Some(comment) = setting_comment(line, name) else {
+        return true;
+      };
+      if replaced {
+        return false;
+      }
+      replaced = true;
+      *line = match comment {
+        Some(comment) => format!("{assignment}\t{comment}"),
+        None => assignment.clone(),
+      };
+      true
+    });
+    if !replaced {
+      self.lines.push(assignment);
+    }
+  }
+
+  /// Remove every assignment of `name` from this file
+  pub fn remove(&mut self, name: &str) {
+    self
+      .lines
+      .retain(|line| setting_comment(line, name).is_none());
+  }
+
+  /// Make sure the file includes the configuration directory `dir` (e.g. `conf.d`)
+  pub fn ensure_include_dir(&mut self, dir: &str) {
+    let included = self.lines.iter().any(|line| {
+      matches!(
+        parse_line(line),
+        Some((Entry::Include { directive, target }, _))
+          if directive.eq_ignore_ascii_case("include_dir") && target == dir
+      )
+    });
+    if !included {
+      self
+        .lines
+        .push(format!("include_dir = {}", quote_conf_value(dir)));
+    }
+  }
+
+  /// Write the file back to disk, creating its directory if needed
+  pub fn save(&self) -> Result<()> {
+    let write_error = |e: std::io::Error| {
+      PgEmbedError::ConfigurationError(format!("Failed to write {}: {e}", self.path.display()))
+    };
+    if let Some(dir) = self.path.parent() {
+      fs::create_dir_all(extended_path(dir)).map_err(write_error)?;
+    }
+    let mut contents = self.lines.join("\n");
+    contents.push('\n');
+    fs::write(extended_path(&self.path), contents).map_err(write_error)
+  }
+}
+
+/// Load the file pg-embedded manages in `data_dir`, making sure postgresql.conf includes it
+///
+/// The include line is appended once, so managed settings override earlier assignments in
+/// postgresql.conf while `ALTER SYSTEM` (postgresql.auto.conf) still takes precedence.
+pub(crate) fn managed_conf(data_dir: &Path) -> Result<ConfFile> {
+  let mut main = ConfFile::load(data_dir.join("postgresql.conf"))?;
+  main.ensure_include_dir(CONF_DIR);
+  main.save()?;
+  ConfFile::load(data_dir.join(CONF_DIR).join(MANAGED_CONF_FILE))
+}
+
+/// Value of `name` the server reads from the configuration files of `data_dir`
+pub(crate) fn effective_value(data_dir: &Path, name: &str) -> Result<Option<String>> {
+  let mut value = None;
+  for file in ["postgresql.conf", "postgresql.auto.conf"] {
+    if let Some(file_value) = ConfFile::load(data_dir.join(file))?.get(name) {
+      value = Some(file_value);
+    }
+  }
+  Ok(value)
+}
+
+/// Quote a value for a configuration file assignment
+pub(crate) fn quote_conf_value(value: &str) -> String {
+  let escaped = value
+    .replace('\\', "\\\\")
+    .replace('\'', "''")
+    .replace('\n', "\\n")
+    .replace('\r', "\\r");
+  format!("'{escaped}'")
+}
+
+/// Validate a setting name before writing it to a configuration file
+pub(crate) fn validate_setting_name(name: &str) -> Result<()> {
+  let valid = !name.is_empty()
+    && name
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.');
+  if valid {
+    Ok(())
+  } else {
+    Err(PgEmbedError::ConfigurationError(format!(
+      "Invalid setting name '{name}'"
+    )))
+  }
+}
+
+fn lookup(lines: &[String], dir: &Path, name: &str, depth: usize) -> Option<String> {
+  let mut result = None;
+  for line in lines {
+    match parse_line(line) {
+      Some((Entry::Setting { name: key, value }, _)) if key.eq_ignore_ascii_case(name) => {
+        result = Some(value);
+      }
+      Some((Entry::Include { directive, target }, _)) if depth < MAX_INCLUDE_DEPTH => {
+        let target = dir.join(target);
+        let files = if directive.eq_ignore_ascii_case("include_dir") {
+          conf_dir_files(&target)
+        } else {
+          vec![target]
+        };
+        for file in files {
+          let Ok(contents) = fs::read_to_string(&file) else {
+            continue;
+          };
+          let lines: Vec<String> = contents.lines().map(str::to_string).collect();
+          let file_dir = file.parent().unwrap_or(dir);
+          if let Some(value) = lookup(&lines, file_dir, name, depth + 1) {
+            result = Some(value);
+          }
+        }
+      }
+      _ => {}
+    }
+  }
+  result
+}
+
+/// `.conf` files of an include directory in the order the server reads them
+fn conf_dir_files(dir: &Path) -> Vec<PathBuf> {
+  let Ok(entries) = fs::read_dir(dir) else {
+    return Vec::new();
+  };
+  let mut files: Vec<PathBuf> = entries
+    .flatten()
+    .map(|entry| entry.path())
+    .filter(|path| {
+      path.extension().is_some_and(|ext| ext == "conf")
+        && !path
+          .file_name()
+          .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+    })
+    .collect();
+  files.sort();
+  files
+}
+
+/// If `line` assigns `name`, its trailing comment (if any)
+fn setting_comment<'a>(line: &'a str, name: &str) -> Option<Option<&'a str>> {
+  match parse_line(line)? {
+    (Entry::Setting { name: key, .. }, comment) if key.eq_ignore_ascii_case(name) => Some(comment),
+    _ => None,
+  }
+}
+
+/// Parse an active line into its entry and trailing comment
+fn parse_line(line: &str) -> Option<(Entry<'_>, Option<&str>)> {
+  let line = line.trim_start();
+  if line.is_empty() || line.starts_with('#') {
+    return None;
+  }
+  let name_end = line
+    .find(|c: char| c.is_whitespace() || c == '=')
+    .unwrap_or(line.len());
+  let name = &line[..name_end];
+  let rest = line[name_end..].trim_start();
+  let rest = rest.strip_prefix('=').unwrap_or(rest).trim_start();
+
+  let (value, rest) = if let Some(quoted) = rest.strip_prefix('\'') {
+    unquote(quoted)?
+  } else {
+    let end = rest
+      .find(|c: char| c.is_whitespace() || c == '#')
+      .unwrap_or(rest.len());
+    (rest[..end].to_string(), &rest[end..])
+  };
+  let rest = rest.trim();
+  let comment = rest.starts_with('#').then_some(rest);
+
+  let entry = if ["include", "include_if_exists", "include_dir"]
+    .iter()
+    .any(|directive| name.eq_ignore_ascii_case(directive))
+  {
+    Entry::Include {
+      directive: name,
+      target: value,
+    }
+  } else {
+    Entry::Setting { name, value }
+  };
+  Some((entry, comment))
+}
+
+/// Read a single-quoted value (after the opening quote), returning it and the remaining text
+fn unquote(text: &str) -> Option<(String, &str)> {
+  let mut value = String::new();
+  let mut chars = text.char_indices().peekable();
+  while let Some((i, c)) = chars.next() {
+    match c {
+      '\\' => {
+        let (_, escaped) = chars.next()?;
+        value.push(match escaped {
+          'n' => '\n',
+          't' => '\t',
+          'r' => '\r',
+          other => other,
+        });
+      }
+      '\'' if chars.peek().is_some_and(|(_, next)| *next == '\'') => {
+        chars.next();
+        value.push('\'');
+      }
+      '\'' => return Some((value, &text[i + 1..])),
+      other => value.push(other),
+    }
+  }
+  None
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn conf(lines: &[&str]) -> ConfFile {
+    ConfFile {
+      path: PathBuf::from("postgresql.conf"),
+      lines: lines.iter().map(|line| line.to_string()).collect(),
+    }
+  }
+
+  #[test]
+  fn test_parse_line() {
+    assert_eq!(
+      parse_line("work_mem = '8MB'  # per sort"),
+      Some((
+        Entry::Setting {
+          name: "work_mem",
+          value: "8MB".to_string()
+        },
+        Some("# per sort")
+      ))
+    );
+    assert_eq!(
+      parse_line("fsync off").map(|(entry, _)| entry),
+      Some(Entry::Setting {
+        name: "fsync",
+        value: "off".to_string()
+      })
+    );
+    assert_eq!(
+      parse_line("archive_command='cp ''%p'' x\\\\y'").map(|(entry, _)| entry),
+      Some(Entry::Setting {
+        name: "archive_command",
+        value: "cp '%p' x\\y".to_string()
+      })
+    );
+    assert_eq!(parse_line("#work_mem = 4MB"), None);
+    assert_eq!(parse_line("   "), None);
+  }
+
+  #[test]
+  fn test_set_is_idempotent_and_keeps_comments() {
+    let mut file = conf(&[
+      "# Memory",
+      "#work_mem = 4MB",
+      "work_mem = 4MB  # per sort",
+      "fsync = on",
+      "WORK_MEM = 16MB",
+    ]);
+    file.set("work_mem", "8MB");
+    file.set("work_mem", "8MB");
+    file.set("wal_level", "replica");
+    assert_eq!(
+      file.lines,
+      [
+        "# Memory",
+        "#work_mem = 4MB",
+        "work_mem = '8MB'\t# per sort",
+        "fsync = on",
+        "wal_level = 'replica'",
+      ]
+    );
+    assert_eq!(file.get("work_mem").as_deref(), Some("8MB"));
+
+    file.remove("fsync");
+    assert_eq!(file.get("fsync"), None);
+    assert_eq!(file.lines.len(), 4);
+  }
+
+  #[test]
+  fn test_quote_conf_value() {
+    assert_eq!(quote_conf_value("cp '/a/%f' %p"), "'cp ''/a/%f'' %p'");
+    assert_eq!(quote_conf_value("C:\\wal"), "'C:\\\\wal'");
+  }
+
+  #[test]
+  fn test_validate_setting_name() {
+    assert!(validate_setting_name("work_mem").is_ok());
+    assert!(validate_setting_name("pg_stat_statements.max").is_ok());
+    assert!(validate_setting_name("").is_err());
+    assert!(validate_setting_name("work_mem = 1\nfsync").is_err());
+  }
+
+  #[test]
+  fn test_values_round_trip() {
+    let mut file = conf(&[]);
+    let command = "test ! -f '/wal/%f' && cp %p '/wal/%f'";
+    file.set("archive_command", command);
+    file.set("data_path", "C:\\pg\\data");
+    file.set("banner", "line 1\nline 2");
+    assert_eq!(file.get("banner").as_deref(), Some("line 1\nline 2"));
+    assert_eq!(file.get("archive_command").as_deref(), Some(command));
+    assert_eq!(file.get("data_path").as_deref(), Some("C:\\pg\\data"));
+  }
+
+  #[test]
+  fn test_managed_conf_overrides_main_file() {
+    let dir = std::env::temp_dir().join(format!("pg-embedded-managed-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("postgresql.conf"), "# main\nwork_mem = 4MB\n").unwrap();
+
+    for _ in 0..2 {
+      let mut managed = managed_conf(&dir).unwrap();
+      managed.set("work_mem", "8MB");
+      managed.save().unwrap();
+    }
+    assert_eq!(
+      fs::read_to_string(dir.join("postgresql.conf")).unwrap(),
+      "# main\nwork_mem = 4MB\ninclude_dir = 'conf.d'\n"
+    );
+    assert_eq!(
+      effective_value(&dir, "work_mem").unwrap().as_deref(),
+      Some("8MB")
+    );
+
+    fs::write(dir.join("postgresql.auto.conf"), "work_mem = '16MB'\n").unwrap();
+    assert_eq!(
+      effective_value(&dir, "work_mem").unwrap().as_deref(),
+      Some("16MB")
+    );
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn test_get_follows_include_dir() {
+    let dir = std::env::temp_dir().join(format!("pg-embedded-conf-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("conf.d")).unwrap();
+    fs::write(dir.join("conf.d/01-memory.conf"), "work_mem = '32MB'\n").unwrap();
+    fs::write(dir.join("conf.d/02-wal.conf"), "wal_level = logical\n").unwrap();
+
+    let mut file = ConfFile::load(dir.join("postgresql.conf")).unwrap();
+    file.set("work_mem", "8MB");
+    file.set("wal_level", "replica");
+    file.ensure_include_dir("conf.d");
+    file.ensure_include_dir("conf.d");
+    file.save().unwrap();
+
+    let file = ConfFile::load(dir.join("postgresql.conf")).unwrap();
+    assert_eq!(file.lines.len(), 3);
+    assert_eq!(file.get("work_mem").as_deref(), Some("32MB"));
+    assert_eq!(file.get("wal_level").as_deref(), Some("logical"));
+    fs::remove_dir_all(&dir).unwrap();
+  }
+}
@@ -0,0 +1,473 @@
+//! The binary format of `COPY`, encoded and decoded for `copyInBinary()` and
+//! `copyOutBinary()`
+//!
+//! The format carries every value in its binary wire form, so the columns of the table
+//! decide how a value is encoded; only the types listed in `CopyType` are supported.
+
+use crate::client::SqlValue;
+use crate::error::{PgEmbedError, Result};
+use crate::sql::quote_literal;
+use napi::bindgen_prelude::Either3;
+
+/// Signature, flags and header extension length that start every binary COPY stream
+const HEADER: &[u8] = b"PGCOPY\n\xff\r\n\0\0\0\0\0\0\0\0\0";
+
+/// Field count that ends a binary COPY stream
+const TRAILER: i16 = -1;
+
+/// The columns of `table` with their types, for a binary COPY
+///
+/// The first column is the qualified table name, the same in every row.
+pub(crate) fn copy_columns_sql(table: &str) -> String {
+  format!(
+    "SELECT c.oid::regclass::text, a.attname, a.atttypid, \
+       format_type(a.atttypid, a.atttypmod) \
+     FROM pg_class c JOIN pg_attribute a ON a.attrelid = c.oid \
+     WHERE c.oid = {}::regclass AND a.attnum > 0 AND NOT a.attisdropped \
+       AND a.attgenerated = '' \
+     ORDER BY a.attnum",
+    quote_literal(table)
+  )
+}
+
+/// Column types the binary COPY supports, by their wire form
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum CopyType {
+  Bool,
+  Int2,
+  Int4,
+  Int8,
+  Oid,
+  Float4,
+  Float8,
+  /// text, varchar, char, name and json, sent as UTF-8
+  Text,
+  Jsonb,
+  Uuid,
+  Bytea,
+}
+
+impl CopyType {
+  /// The wire form of the type with `oid`, if supported
+  fn from_oid(oid: u32) -> Option<Self> {
+    Some(match oid {
+      16 => Self::Bool,
+      17 => Self::Bytea,
+      19 | 25 | 114 | 1042 | 1043 => Self::Text,
+      20 => Self::Int8,
+      21 => Self::Int2,
+      23 => Self::Int4,
+      26 => Self::Oid,
+      700 => Self::Float4,
+      701 => Self::Float8,
+      2950 => Self::Uuid,
+      3802 => Self::Jsonb,
+      _ => return None,
+    })
+  }
+}
+
+/// A column of a binary COPY
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct CopyColumn {
+  pub name: String,
+  pub kind: CopyType,
+}
+
+/// The qualified table name and the columns of a binary COPY, from the rows of
+/// `copy_columns_sql()`
+///
+/// With `names`, only those columns are copied, in that order.
+pub(crate) fn copy_columns(
+  table: &str,
+  rows: &[Vec<Option<String>>],
+  names: Option<&[String]>,
+) -> Result<(String, Vec<CopyColumn>)> {
+  let mut qualified = table.to_string();
+  let mut all = Vec::new();
+  for row in rows {
+    let [Some(relation), Some(name), Some(oid), Some(type_name)] = row.as_slice() else {
+      continue;
+    };
+    qualified = relation.clone();
+    all.push((name.clone(), oid.parse().unwrap_or(0), type_name.clone()));
+  }
+  let selected: Vec<_> = match names {
+    Some(names) => names
+      .iter()
+      .map(|wanted| {
+        all
+          .iter()
+          .find(|(name, _, _)| name == wanted)
+          .ok_or_else(|| {
+            PgEmbedError::ConfigurationError(format!(
+              "Table {qualified} has no column \"{wanted}\""
+            ))
+          })
+      })
+      .collect::<Result<_>>()?,
+    None => all.iter().collect(),
+  };
+  let columns = selected
+    .into_iter()
+    .map(|(name, oid, type_name)| {
+      let kind = CopyType::from_oid(*oid).ok_or_else(|| {
+        PgEmbedError::ConfigurationError(format!(
+          "Column \"{name}\" has type {type_name}, which binary COPY does not support; \
+           leave the column out or use executeSql() with COPY ... CSV"
+        ))
+      })?;
+      Ok(CopyColumn {
+        name: name.clone(),
+        kind,
+      })
+    })
+    .collect::<Result<Vec<_>>>()?;
+  Ok((qualified, columns))
+}
+
+/// The bytes that start a binary COPY stream
+pub(crate) fn encode_header(buffer: &mut Vec<u8>) {
+  buffer.extend_from_slice(HEADER);
+}
+
+/// The bytes that end a binary COPY stream
+pub(crate) fn encode_trailer(buffer: &mut Vec<u8>) {
+  buffer.extend_from_slice(&TRAILER.to_be_bytes());
+}
+
+/// Append `row` to a binary COPY stream of `columns`
+pub(crate) fn encode_row(
+  buffer: &mut Vec<u8>,
+  columns: &[CopyColumn],
+  row: &[Option<SqlValue>],
+) -> Result<()> {
+  if row.len() != columns.len() {
+    return Err(PgEmbedError::ConfigurationError(format!(
+      "Row has {} values, but {} columns are copied",
+      row.len(),
+      columns.len()
+    )));
+  }
+  let mut encoded = (columns.len() as i16).to_be_bytes().to_vec();
+  for (column, value) in columns.iter().zip(row) {
+    let Some(value) = value else {
+      encoded.extend_from_slice(&(-1i32).to_be_bytes());
+      continue;
+    };
+    let field = encode_value(column.kind, value).map_err(|reason| {
+      PgEmbedError::ConfigurationError(format!(
+        "Invalid value for column \"{}\": {reason}",
+        column.name
+      ))
+    })?;
+    encoded.extend_from_slice(&(field.len() as i32).to_be_bytes());
+    encoded.extend_from_slice(&field);
+  }
+  buffer.extend_from_slice(&encoded);
+  Ok(())
+}
+
+/// An integer value, from a number without a fraction or a string
+fn integer(value: &SqlValue, min: i64, max: i64) -> std::result::Result<i64, String> {
+  let number = match value {
+    Either3::B(number) if number.fract() == 0.0 && number.abs() <= 2f64.powi(53) => *number as i64,
+    Either3::C(text) => text
+      .trim()
+      .parse()
+      .map_err(|_| format!("'{text}' is not an integer"))?,
+    _ => return Err("expected an integer".to_string()),
+  };
+  if number < min || number > max {
+    return Err(format!("{number} is out of range"));
+  }
+  Ok(number)
+}
+
+/// A floating point value, from a number or a string
+fn float(value: &SqlValue) -> std::result::Result<f64, String> {
+  match value {
+    Either3::B(number) => Ok(*number),
+    Either3::C(text) => match text.trim() {
+      "NaN" => Ok(f64::NAN),
+      "Infinity" => Ok(f64::INFINITY),
+      "-Infinity" => Ok(f64::NEG_INFINITY),
+      text => text
+        .parse()
+        .map_err(|_| format!("'{text}' is not a number")),
+    },
+    Either3::A(_) => Err("expected a number".to_string()),
+  }
+}
+
+/// The text of a value; numbers and booleans are written as JavaScript prints them
+fn text(value: &SqlValue) -> String {
+  match value {
+    Either3::A(flag) => flag.to_string(),
+    Either3::B(number) => number.to_string(),
+    Either3::C(text) => text.clone(),
+  }
+}
+
+/// The binary wire form of `value` in a column of type `kind`
+fn encode_value(kind: CopyType, value: &SqlValue) -> std::result::Result<Vec<u8>, String> {
+  Ok(match kind {
+    CopyType::Bool => match value {
+      Either3::A(flag) => vec![u8::from(*flag)],
+      _ => return Err("expected a boolean".to_string()),
+    },
+    CopyType::Int2 => (integer(value, i16::MIN.into(), i16::MAX.into())? as i16)
+      .to_be_bytes()
+      .to_vec(),
+    CopyType::Int4 => (integer(value, i32::MIN.into(), i32::MAX.into())? as i32)
+      .to_be_bytes()
+      .to_vec(),
+    CopyType::Int8 => integer(value, i64::MIN, i64::MAX)?.to_be_bytes().to_vec(),
+    CopyType::Oid => (integer(value, 0, u32::MAX.into())? as u32)
+      .to_be_bytes()
+      .to_vec(),
+    CopyType::Float4 => (float(value)? as f32).to_be_bytes().to_vec(),
+    CopyType::Float8 => float(value)?.to_be_bytes().to_vec(),
+    CopyType::Text => text(value).into_bytes(),
+    CopyType::Jsonb => {
+      let mut field = vec![1];
+      field.extend_from_slice(text(value).as_bytes());
+      field
+    }
+    CopyType::Uuid => {
+      let text = text(value);
+      let digits: String = text.chars().filter(|c| *c != '-').collect();
+      if digits.len() != 32 {
+        return Err(format!("'{text}' is not a UUID"));
+      }
+      decode_hex(&digits).ok_or_else(|| format!("'{text}' is not a UUID"))?
+    }
+    CopyType::Bytea => {
+      let text = text(value);
+      text
+        .strip_prefix("\\x")
+        .and_then(decode_hex)
+        .ok_or_else(|| format!("'{text}' is not a bytea in hex form, e.g. '\\x0102'"))?
+    }
+  })
+}
+
+/// The bytes of a string of hex digits
+fn decode_hex(digits: &str) -> Option<Vec<u8>> {
+  if !digits.len().is_multiple_of(2) {
+    return None;
+  }
+  (0..digits.len())
+    .step_by(2)
+    .map(|index| u8::from_str_radix(digits.get(index..index + 2)?, 16).ok())
+    .collect()
+}
+
+/// A string of the hex digits of `bytes`
+fn encode_hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Reads the fields of a binary COPY stream
+struct Reader<'a> {
+  data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+  fn take(&mut self, length: usize) -> Result<&'a [u8]> {
+    if self.data.len() < length {
+      return Err(PgEmbedError::DatabaseError(
+        "Binary COPY data ended unexpectedly".to_string(),
+      ));
+    }
+    let (taken, rest) = self.data.split_at(length);
+    self.data = rest;
+    Ok(taken)
+  }
+
+  fn i16(&mut self) -> Result<i16> {
+    Ok(i16::from_be_bytes(
+      self.take(2)?.try_into().unwrap_or_default(),
+    ))
+  }
+
+  fn i32(&mut self) -> Result<i32> {
+    Ok(i32::from_be_bytes(
+      self.take(4)?.try_into().unwrap_or_default(),
+    ))
+  }
+}
+
+/// The rows of a binary COPY stream of `columns`, with values typed as `query()` types them
+pub(crate) fn decode_rows(
+  data: &[u8],
+  columns: &[CopyColumn],
+) -> Result<Vec<Vec<Option<SqlValue>>>> {
+  let mut reader = Reader { data };
+  if reader.take(11)? != &HEADER[..11] {
+    return Err(PgEmbedError::DatabaseError(
+      "Binary COPY data has no valid signature".to_string(),
+    ));
+  }
+  reader.i32()?;
+  let extension = reader.i32()?;
+  reader.take(usize::try_from(extension).unwrap_or_default())?;
+  let mut rows = Vec::new();
+  loop {
+    let count = reader.i16()?;
+    if count == TRAILER {
+      return Ok(rows);
+    }
+    if usize::try_from(count).ok() != Some(columns.len()) {
+      return Err(PgEmbedError::DatabaseError(format!(
+        "Binary COPY row has {count} fields, but {} columns were copied",
+        columns.len()
+      )));
+    }
+    let row = columns
+      .iter()
+      .map(|column| {
+        let length = reader.i32()?;
+        let Ok(length) = usize::try_from(length) else {
+          return Ok(None);
+        };
+        decode_value(column.kind, reader.take(length)?).map(Some)
+      })
+      .collect::<Result<_>>()?;
+    rows.push(row);
+  }
+}
+
+/// A value of type `kind` from its binary wire form
+///
+/// bigint stays a string, as a JavaScript number cannot hold all of its values.
+fn decode_value(kind: CopyType, field: &[u8]) -> Result<SqlValue> {
+  let invalid = || PgEmbedError::DatabaseError(format!("Invalid binary {kind:?} value"));
+  let bytes = |length: usize| field.get(..length).ok_or_else(invalid);
+  Ok(match kind {
+    CopyType::Bool => Either3::A(bytes(1)?[0] != 0),
+    CopyType::Int2 => {
+      Either3::B(i16::from_be_bytes(bytes(2)?.try_into().map_err(|_| invalid())?).into())
+    }
+    CopyType::Int4 => {
+      Either3::B(i32::from_be_bytes(bytes(4)?.try_into().map_err(|_| invalid())?).into())
+    }
+    CopyType::Oid => {
+      Either3::B(u32::from_be_bytes(bytes(4)?.try_into().map_err(|_| invalid())?).into())
+    }
+    CopyType::Int8 => {
+      Either3::C(i64::from_be_bytes(bytes(8)?.try_into().map_err(|_| invalid())?).to_string())
+    }
+    CopyType::Float4 => {
+      Either3::B(f32::from_be_bytes(bytes(4)?.try_into().map_err(|_| invalid())?).into())
+    }
+    CopyType::Float8 => Either3::B(f64::from_be_bytes(
+      bytes(8)?.try_into().map_err(|_| invalid())?,
+    )),
+    CopyType::Text => Either3::C(String::from_utf8_lossy(field).to_string()),
+    CopyType::Jsonb => {
+      Either3::C(String::from_utf8_lossy(field.get(1..).ok_or_else(invalid)?).to_string())
+    }
+    CopyType::Uuid => {
+      let hex = encode_hex(bytes(16)?);
+      Either3::C(format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+      ))
+    }
+    CopyType::Bytea => Either3::C(format!("\\x{}", encode_hex(field))),
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn column(name: &str, kind: CopyType) -> CopyColumn {
+    CopyColumn {
+      name: name.to_string(),
+      kind,
+    }
+  }
+
+  #[test]
+  fn test_round_trip() {
+    let columns = [
+      column("id", CopyType::Int4),
+      column("big", CopyType::Int8),
+      column("active", CopyType::Bool),
+      column("score", CopyType::Float8),
+      column("name", CopyType::Text),
+      column("data", CopyType::Jsonb),
+      column("key", CopyType::Uuid),
+      column("blob", CopyType::Bytea),
+    ];
+    let row = vec![
+      Some(Either3::B(42.0)),
+      Some(Either3::C("9007199254740993".to_string())),
+      Some(Either3::A(true)),
+      Some(Either3::B(1.5)),
+      None,
+      Some(Either3::C("{\"a\": 1}".to_string())),
+      Some(Either3::C(
+        "a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11".to_string(),
+      )),
+      Some(Either3::C("\\x00ff".to_string())),
+    ];
+    let mut data = Vec::new();
+    encode_header(&mut data);
+    encode_row(&mut data, &columns, &row).unwrap();
+    encode_trailer(&mut data);
+
+    // Either3 does not implement PartialEq
+    assert_eq!(
+      format!("{:?}", decode_rows(&data, &columns).unwrap()),
+      format!("{:?}", [row])
+    );
+  }
+
+  #[test]
+  fn test_encode_row_errors() {
+    let columns = [column("id", CopyType::Int2)];
+    let mut data = Vec::new();
+    assert!(encode_row(&mut data, &columns, &[Some(Either3::B(1.5))]).is_err());
+    assert!(encode_row(&mut data, &columns, &[Some(Either3::B(40000.0))]).is_err());
+    assert!(encode_row(&mut data, &columns, &[Some(Either3::A(true))]).is_err());
+    assert!(encode_row(&mut data, &columns, &[]).is_err());
+    assert!(data.is_empty());
+  }
+
+  #[test]
+  fn test_copy_columns() {
+    let row = |name: &str, oid: &str, type_name: &str| {
+      ["public.users", name, oid, type_name]
+        .iter()
+        .map(|value| Some(value.to_string()))
+        .collect::<Vec<_>>()
+    };
+    let rows = [
+      row("id", "23", "integer"),
+      row("name", "25", "text"),
+      row("total", "1700", "numeric(10,2)"),
+    ];
+    let (table, columns) = copy_columns(
+      "users",
+      &rows,
+      Some(&["name".to_string(), "id".to_string()]),
+    )
+    .unwrap();
+    assert_eq!(table, "public.users");
+    assert_eq!(
+      columns,
+      [column("name", CopyType::Text), column("id", CopyType::Int4)]
+    );
+
+    let error = copy_columns("users", &rows, None).unwrap_err().to_string();
+    assert!(error.contains("numeric(10,2)"));
+    assert!(copy_columns("users", &rows, Some(&["missing".to_string()])).is_err());
+  }
+}
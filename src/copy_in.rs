@@ -0,0 +1,347 @@
+//! Bulk loading with `COPY ... FROM STDIN` on a native connection, for `copyInto()` and
+//! `openCopyWriter()`
+//!
+//! The COPY runs in a task that owns the connection; the data reaches it through a
+//! bounded channel, so a writer producing faster than the server consumes waits instead
+//! of buffering the whole input.
+
+use crate::client::{connect, run_query};
+use crate::error::{PgEmbedError, Result};
+use crate::sql::{quote_ident, quote_literal};
+use crate::tools::common::ConnectionConfig;
+use napi::bindgen_prelude::{Buffer, Either};
+use napi_derive::napi;
+use sqlx::postgres::PgConnection;
+use sqlx::Connection;
+use std::sync::Mutex;
+use tokio::sync::{mpsc, oneshot};
+
+/// Chunks a writer may queue before `write()` waits for the server
+const COPY_QUEUE_LENGTH: usize = 16;
+
+/// Options for `copyInto()` and `openCopyWriter()`
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct CopyIntoOptions {
+  /// Format of the data: "csv", "text" (tab-separated) or "binary" (the binary COPY
+  /// format, header included) (default: "csv")
+  pub format: Option<String>,
+  /// Columns the data holds, in order (default: every column of the table)
+  pub columns: Option<Vec<String>>,
+  /// Whether the first line of CSV data is a header to skip (default: false)
+  pub header: Option<bool>,
+  /// Character separating the values of a row (default: "," for CSV, a tab for text)
+  pub delimiter: Option<String>,
+  /// String that stands for NULL (default: an unquoted empty value for CSV, `\N` for text)
+  pub null: Option<String>,
+}
+
+/// The options of a `COPY` statement for data in `format`, e.g. `FORMAT csv, HEADER true`
+pub(crate) fn copy_settings(
+  format: Option<&str>,
+  header: Option<bool>,
+  delimiter: Option<&str>,
+  null: Option<&str>,
+) -> Result<String> {
+  let format = format.unwrap_or("csv");
+  if !matches!(format, "csv" | "text" | "binary") {
+    return Err(PgEmbedError::ConfigurationError(format!(
+      "Unknown COPY format '{format}'; use 'csv', 'text' or 'binary'"
+    )));
+  }
+  let mut settings = vec![format!("FORMAT {format}")];
+  if header.unwrap_or(false) {
+    if format != "csv" {
+      return Err(PgEmbedError::ConfigurationError(
+        "header is only supported for the csv format".to_string(),
+      ));
+    }
+    settings.push("HEADER true".to_string());
+  }
+  if let Some(delimiter) = delimiter {
+    if format == "binary" || delimiter.chars().count() != 1 {
+      return Err(PgEmbedError::ConfigurationError(
+        "delimiter must be a single character and is not supported for the binary format"
+          .to_string(),
+      ));
+    }
+    settings.push(format!("DELIMITER {}", quote_literal(delimiter)));
+  }
+  if let Some(null) = null {
+    if format == "binary" {
+      return Err(PgEmbedError::ConfigurationError(
+        "null is not supported for the binary format".to_string(),
+      ));
+    }
+    settings.push(format!("NULL {}", quote_literal(null)));
+  }
+  Ok(settings.join(", "))
+}
+
+/// The column list of a `COPY` statement, empty for every column
+pub(crate) fn copy_column_list(columns: Option<&[String]>) -> String {
+  match columns {
+    Some(columns) => format!(
+      " ({})",
+      columns
+        .iter()
+        .map(|column| quote_ident(column))
+        .collect::<Vec<_>>()
+        .join(", ")
+    ),
+    None => String::new(),
+  }
+}
+
+/// The name of `table` quoted as needed, as `regclass` prints it
+///
+/// regclass rejects anything but the name of an existing table or view.
+pub(crate) async fn resolve_table(connection: &mut PgConnection, table: &str) -> Result<String> {
+  run_query(
+    connection,
+    &format!("SELECT {}::regclass::text", quote_literal(table)),
+  )
+  .await?
+  .rows
+  .first()
+  .and_then(|row| row.first().cloned().flatten())
+  .ok_or_else(|| PgEmbedError::DatabaseError(format!("Table {table} not found")))
+}
+
+/// The `COPY ... FROM STDIN` statement loading data described by `options` into `table`,
+/// a table name already quoted as needed
+pub(crate) fn copy_in_statement(table: &str, options: &CopyIntoOptions) -> Result<String> {
+  let settings = copy_settings(
+    options.format.as_deref(),
+    options.header,
+    options.delimiter.as_deref(),
+    options.null.as_deref(),
+  )?;
+  Ok(format!(
+    "COPY {table}{} FROM STDIN WITH ({settings})",
+    copy_column_list(options.columns.as_deref())
+  ))
+}
+
+/// A message for the task running a COPY
+enum CopyMessage {
+  Data(Vec<u8>),
+  Abort(String),
+}
+
+/// A `COPY ... FROM STDIN` in progress, opened with `openCopyWriter()`
+///
+/// Write the data in chunks of any size with `write()`, then call `finish()` to complete
+/// the COPY, or `abort()` to cancel it; nothing is loaded until the COPY is finished.
+#[napi]
+pub struct CopyWriter {
+  sender: Mutex<Option<mpsc::Sender<CopyMessage>>>,
+  /// Result of the task running the COPY, once it ends
+  result: Mutex<Option<oneshot::Receiver<Result<u64>>>>,
+}
+
+impl CopyWriter {
+  /// Start loading data described by `options` into `table` on a new connection
+  pub(crate) async fn open(
+    config: &ConnectionConfig,
+    table: &str,
+    options: &CopyIntoOptions,
+  ) -> Result<Self> {
+    let mut connection = connect(config).await?;
+    let statement = resolve_table(&mut connection, table)
+      .await
+      .and_then(|table| copy_in_statement(&table, options));
+    let statement = match statement {
+      Ok(statement) => statement,
+      Err(e) => {
+        let _ = connection.close().await;
+        return Err(e);
+      }
+    };
+
+    let (sender, mut receiver) = mpsc::channel(COPY_QUEUE_LENGTH);
+    let (started, started_result) = oneshot::channel();
+    let (done, result) = oneshot::channel();
+    napi::bindgen_prelude::spawn(async move {
+      let database_error = |e: sqlx::Error| PgEmbedError::DatabaseError(e.to_string());
+      let result = async {
+        let mut copy = match connection.copy_in_raw(&statement).await {
+          Ok(copy) => {
+            let _ = started.send(Ok(()));
+            copy
+          }
+          Err(e) => {
+            let _ = started.send(Err(database_error(e)));
+            return Ok(0);
+          }
+        };
+        while let Some(message) = receiver.recv().await {
+          match message {
+            CopyMessage::Data(chunk) => {
+              if let Err(e) = copy.send(chunk).await {
+                let _ = copy.abort(e.to_string()).await;
+                return Err(database_error(e));
+              }
+            }
+            CopyMessage::Abort(reason) => {
+              let _ = copy.abort(reason.clone()).await;
+              return Err(PgEmbedError::DatabaseError(format!(
+                "COPY was aborted: {reason}"
+              )));
+            }
+          }
+        }
+        copy.finish().await.map_err(database_error)
+      }
+      .await;
+      let _ = connection.close().await;
+      let _ = done.send(result);
+    });
+    started_result
+      .await
+      .map_err(|_| PgEmbedError::InternalError("The COPY task ended unexpectedly".to_string()))??;
+    Ok(Self {
+      sender: Mutex::new(Some(sender)),
+      result: Mutex::new(Some(result)),
+    })
+  }
+
+  /// Wait for the task running the COPY and return its result
+  async fn join(&self) -> Result<u64> {
+    let result = self.result.lock().ok().and_then(|mut result| result.take());
+    match result {
+      Some(result) => result
+        .await
+        .map_err(|_| PgEmbedError::InternalError("The COPY task ended unexpectedly".to_string()))?,
+      None => Err(PgEmbedError::DatabaseError(
+        "The COPY has already ended".to_string(),
+      )),
+    }
+  }
+
+  /// Queue `data` for the COPY
+  pub(crate) async fn send(&self, data: Vec<u8>) -> Result<()> {
+    let sender = self.sender.lock().ok().and_then(|sender| sender.clone());
+    let Some(sender) = sender else {
+      return Err(PgEmbedError::DatabaseError(
+        "The COPY has already ended".to_string(),
+      ));
+    };
+    if sender.send(CopyMessage::Data(data)).await.is_err() {
+      // The task only stops receiving when the COPY failed
+      self.close_sender();
+      self.join().await?;
+    }
+    Ok(())
+  }
+
+  /// Complete the COPY and return the number of rows loaded
+  pub(crate) async fn complete(&self) -> Result<u64> {
+    self.close_sender();
+    self.join().await
+  }
+
+  fn close_sender(&self) {
+    if let Ok(mut sender) = self.sender.lock() {
+      sender.take();
+    }
+  }
+}
+
+#[napi]
+impl CopyWriter {
+  /// Sends a chunk of data to the COPY
+  ///
+  /// Chunks do not have to end at row boundaries. Waits while the server is behind, so a
+  /// producer awaiting every write never buffers more than a few chunks.
+  ///
+  /// @param chunk - Data in the format the writer was opened with
+  /// @returns Promise that resolves once the chunk is queued
+  /// @throws Error if the COPY has failed or ended
+  #[napi]
+  pub async fn write(&self, chunk: Either<Buffer, String>) -> napi::Result<()> {
+    let data = match chunk {
+      Either::A(buffer) => buffer.to_vec(),
+      Either::B(text) => text.into_bytes(),
+    };
+    Ok(self.send(data).await?)
+  }
+
+  /// Completes the COPY, loading every row written
+  ///
+  /// @returns Promise that resolves to the number of rows loaded
+  /// @throws Error if the data is invalid or the COPY has already ended
+  #[napi]
+  pub async fn finish(&self) -> napi::Result<u32> {
+    let rows = self.complete().await?;
+    Ok(u32::try_from(rows).unwrap_or(u32::MAX))
+  }
+
+  /// Cancels the COPY; nothing written is loaded
+  ///
+  /// Does nothing if the COPY has already ended.
+  ///
+  /// @param reason - Reason reported to the server (default: "aborted by the client")
+  /// @returns Promise that resolves once the COPY is cancelled
+  #[napi]
+  pub async fn abort(&self, reason: Option<String>) -> napi::Result<()> {
+    let sender = self.sender.lock().ok().and_then(|mut sender| sender.take());
+    let Some(sender) = sender else {
+      return Ok(());
+    };
+    let reason = reason.unwrap_or_else(|| "aborted by the client".to_string());
+    let _ = sender.send(CopyMessage::Abort(reason)).await;
+    let _ = self.join().await;
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_copy_in_statement() {
+    assert_eq!(
+      copy_in_statement("public.users", &CopyIntoOptions::default()).unwrap(),
+      "COPY public.users FROM STDIN WITH (FORMAT csv)"
+    );
+    let options = CopyIntoOptions {
+      columns: Some(vec!["id".to_string(), "Name".to_string()]),
+      header: Some(true),
+      delimiter: Some(";".to_string()),
+      null: Some("NULL".to_string()),
+      ..Default::default()
+    };
+    assert_eq!(
+      copy_in_statement("users", &options).unwrap(),
+      "COPY users (\"id\", \"Name\") FROM STDIN WITH (FORMAT csv, HEADER true, DELIMITER ';', NULL 'NULL')"
+    );
+    let binary = CopyIntoOptions {
+      format: Some("binary".to_string()),
+      ..Default::default()
+    };
+    assert_eq!(
+      copy_in_statement("users", &binary).unwrap(),
+      "COPY users FROM STDIN WITH (FORMAT binary)"
+    );
+
+    for invalid in [
+      CopyIntoOptions {
+        format: Some("json".to_string()),
+        ..Default::default()
+      },
+      CopyIntoOptions {
+        format: Some("text".to_string()),
+        header: Some(true),
+        ..Default::default()
+      },
+      CopyIntoOptions {
+        delimiter: Some("||".to_string()),
+        ..Default::default()
+      },
+    ] {
+      assert!(copy_in_statement("users", &invalid).is_err());
+    }
+  }
+}
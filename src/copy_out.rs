@@ -0,0 +1,195 @@
+//! Exporting with `COPY ... TO STDOUT` on a native connection, for `copyOut()`,
+//! `copyOutToFile()` and `openCopyReader()`
+//!
+//! The COPY runs in a task that owns the connection and hands the data over through a
+//! bounded channel, so a slow consumer holds the server back instead of buffering the
+//! whole export.
+
+use crate::client::connect;
+use crate::copy_in::{copy_column_list, copy_settings, resolve_table};
+use crate::error::{PgEmbedError, Result};
+use crate::tools::common::ConnectionConfig;
+use futures_util::StreamExt;
+use napi::bindgen_prelude::Buffer;
+use napi_derive::napi;
+use sqlx::postgres::PgConnection;
+use sqlx::Connection;
+use std::sync::Mutex;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+/// Chunks a reader may hold before the server waits for it
+const COPY_QUEUE_LENGTH: usize = 16;
+
+/// Options for `copyOut()`, `copyOutToFile()` and `openCopyReader()`
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct CopyOutOptions {
+  /// Format of the data: "csv", "text" (tab-separated) or "binary" (the binary COPY
+  /// format) (default: "csv")
+  pub format: Option<String>,
+  /// Columns to export, in order, when exporting a table (default: every column)
+  pub columns: Option<Vec<String>>,
+  /// Whether CSV data starts with a header line of the column names (default: false)
+  pub header: Option<bool>,
+  /// Character separating the values of a row (default: "," for CSV, a tab for text)
+  pub delimiter: Option<String>,
+  /// String written for NULL (default: an unquoted empty value for CSV, `\N` for text)
+  pub null: Option<String>,
+}
+
+/// Whether `source` is a query rather than the name of a table
+fn is_query(source: &str) -> bool {
+  let keyword: String = source
+    .trim_start()
+    .chars()
+    .take_while(|c| c.is_ascii_alphabetic())
+    .collect();
+  let rest = source.trim_start()[keyword.len()..].chars().next();
+  ["SELECT", "WITH", "VALUES", "TABLE"]
+    .iter()
+    .any(|query| keyword.eq_ignore_ascii_case(query))
+    && rest.is_none_or(|c| c.is_whitespace() || c == '(')
+}
+
+/// The `COPY ... TO STDOUT` statement exporting `source`, a table or a query, in the
+/// format described by `options`
+async fn copy_out_statement(
+  connection: &mut PgConnection,
+  source: &str,
+  options: &CopyOutOptions,
+) -> Result<String> {
+  let settings = copy_settings(
+    options.format.as_deref(),
+    options.header,
+    options.delimiter.as_deref(),
+    options.null.as_deref(),
+  )?;
+  let source = if is_query(source) {
+    if options.columns.is_some() {
+      return Err(PgEmbedError::ConfigurationError(
+        "columns can only be chosen when exporting a table; select them in the query instead"
+          .to_string(),
+      ));
+    }
+    format!("({})", source.trim().trim_end_matches(';'))
+  } else {
+    format!(
+      "{}{}",
+      resolve_table(connection, source).await?,
+      copy_column_list(options.columns.as_deref())
+    )
+  };
+  Ok(format!("COPY {source} TO STDOUT WITH ({settings})"))
+}
+
+/// A `COPY ... TO STDOUT` in progress, opened with `openCopyReader()`
+///
+/// Call `read()` until it returns null; the chunks do not end at row boundaries.
+#[napi]
+pub struct CopyReader {
+  receiver: tokio::sync::Mutex<mpsc::Receiver<Result<Vec<u8>>>>,
+  task: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl CopyReader {
+  /// Start exporting `source`, a table or a query, on a new connection
+  pub(crate) async fn open(
+    config: &ConnectionConfig,
+    source: &str,
+    options: &CopyOutOptions,
+  ) -> Result<Self> {
+    let mut connection = connect(config).await?;
+    let statement = match copy_out_statement(&mut connection, source, options).await {
+      Ok(statement) => statement,
+      Err(e) => {
+        let _ = connection.close().await;
+        return Err(e);
+      }
+    };
+
+    let (sender, receiver) = mpsc::channel(COPY_QUEUE_LENGTH);
+    let (started, started_result) = oneshot::channel();
+    let task = napi::bindgen_prelude::spawn(async move {
+      let database_error = |e: sqlx::Error| PgEmbedError::DatabaseError(e.to_string());
+      {
+        let mut stream = match connection.copy_out_raw(&statement).await {
+          Ok(stream) => {
+            let _ = started.send(Ok(()));
+            stream
+          }
+          Err(e) => {
+            let _ = started.send(Err(database_error(e)));
+            return;
+          }
+        };
+        while let Some(chunk) = stream.next().await {
+          let failed = chunk.is_err();
+          let chunk = chunk.map(|bytes| bytes.to_vec()).map_err(database_error);
+          // The reader was closed, or the COPY failed
+          if sender.send(chunk).await.is_err() || failed {
+            break;
+          }
+        }
+      }
+      let _ = connection.close().await;
+    });
+    started_result
+      .await
+      .map_err(|_| PgEmbedError::InternalError("The COPY task ended unexpectedly".to_string()))??;
+    Ok(Self {
+      receiver: tokio::sync::Mutex::new(receiver),
+      task: Mutex::new(Some(task)),
+    })
+  }
+
+  /// The next chunk of the export, `None` once it is complete
+  pub(crate) async fn next_chunk(&self) -> Result<Option<Vec<u8>>> {
+    self.receiver.lock().await.recv().await.transpose()
+  }
+}
+
+#[napi]
+impl CopyReader {
+  /// Reads the next chunk of the export
+  ///
+  /// @returns Promise that resolves to the next chunk, or null once the export is complete
+  /// @throws Error if the COPY fails
+  #[napi]
+  pub async fn read(&self) -> napi::Result<Option<Buffer>> {
+    Ok(self.next_chunk().await?.map(Buffer::from))
+  }
+
+  /// Stops the export and closes its connection
+  ///
+  /// Only needed when the export is not read to the end; does nothing afterwards.
+  ///
+  /// @returns Promise that resolves once the export is stopped
+  #[napi]
+  pub async fn close(&self) -> napi::Result<()> {
+    self.receiver.lock().await.close();
+    let task = self.task.lock().ok().and_then(|mut task| task.take());
+    if let Some(task) = task {
+      task.abort();
+      let _ = task.await;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_is_query() {
+    assert!(is_query("SELECT * FROM users"));
+    assert!(is_query("  with recent AS (SELECT 1) SELECT * FROM recent"));
+    assert!(is_query("VALUES (1), (2)"));
+    assert!(is_query("select(1)"));
+    assert!(!is_query("users"));
+    assert!(!is_query("public.selections"));
+    assert!(!is_query("\"SELECT\""));
+    assert!(!is_query("table_results"));
+  }
+}
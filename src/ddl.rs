@@ -0,0 +1,266 @@
+//! Capture of the DDL commands run in a database, through event triggers
+
+use crate::client::{connect, connect_options, connect_timeout, run_query};
+use crate::error::{PgEmbedError, Result};
+use crate::tools::common::ConnectionConfig;
+use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi::Status;
+use napi_derive::napi;
+use sqlx::postgres::{PgConnection, PgListener, PgPoolOptions};
+use sqlx::Connection;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+/// Channel the event triggers notify after logging commands
+const DDL_CHANNEL: &str = "pg_embedded_ddl";
+
+/// How often the listener checks whether the capture was stopped
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Log table, trigger functions and event triggers, created in the `pg_embedded` schema
+const INSTALL_SQL: &str = r#"
+CREATE SCHEMA IF NOT EXISTS pg_embedded;
+CREATE TABLE IF NOT EXISTS pg_embedded.ddl_log (
+  id bigserial PRIMARY KEY,
+  captured_at timestamptz NOT NULL DEFAULT clock_timestamp(),
+  command_tag text NOT NULL,
+  object_type text,
+  schema_name text,
+  object_identity text,
+  query text NOT NULL DEFAULT current_query()
+);
+CREATE OR REPLACE FUNCTION pg_embedded.log_ddl_command() RETURNS event_trigger
+LANGUAGE plpgsql AS $$
+BEGIN
+  INSERT INTO pg_embedded.ddl_log (command_tag, object_type, schema_name, object_identity)
+  SELECT command_tag, object_type, schema_name, object_identity
+  FROM pg_event_trigger_ddl_commands();
+  PERFORM pg_notify('pg_embedded_ddl', '');
+END $$;
+CREATE OR REPLACE FUNCTION pg_embedded.log_dropped_objects() RETURNS event_trigger
+LANGUAGE plpgsql AS $$
+BEGIN
+  INSERT INTO pg_embedded.ddl_log (command_tag, object_type, schema_name, object_identity)
+  SELECT tg_tag, object_type, schema_name, object_identity
+  FROM pg_event_trigger_dropped_objects() WHERE original;
+  PERFORM pg_notify('pg_embedded_ddl', '');
+END $$;
+DROP EVENT TRIGGER IF EXISTS pg_embedded_ddl_command;
+DROP EVENT TRIGGER IF EXISTS pg_embedded_dropped_objects;
+CREATE EVENT TRIGGER pg_embedded_ddl_command ON ddl_command_end
+  EXECUTE PROCEDURE pg_embedded.log_ddl_command();
+CREATE EVENT TRIGGER pg_embedded_dropped_objects ON sql_drop
+  EXECUTE PROCEDURE pg_embedded.log_dropped_objects();
+"#;
+
+/// Remove everything `INSTALL_SQL` created; the triggers go first so nothing is logged
+const UNINSTALL_SQL: &str = "DROP EVENT TRIGGER IF EXISTS pg_embedded_ddl_command; \
+  DROP EVENT TRIGGER IF EXISTS pg_embedded_dropped_objects; \
+  DROP FUNCTION IF EXISTS pg_embedded.log_ddl_command(); \
+  DROP FUNCTION IF EXISTS pg_embedded.log_dropped_objects(); \
+  DROP TABLE IF EXISTS pg_embedded.ddl_log";
+
+/// A DDL command captured by `captureDdl()`
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct DdlCommand {
+  /// Command tag, e.g. `CREATE TABLE` or `DROP INDEX`
+  pub command_tag: String,
+  /// Type of the affected object, e.g. `table` or `index`
+  pub object_type: Option<String>,
+  /// Schema of the affected object, if it belongs to one
+  pub schema_name: Option<String>,
+  /// Schema-qualified identity of the affected object, e.g. `public.users`
+  pub object_identity: Option<String>,
+  /// The statement that was sent, which may contain several commands
+  pub query: String,
+  /// Time the command ran, in milliseconds since the Unix epoch
+  pub captured_at: f64,
+}
+
+type DdlCallback = ThreadsafeFunction<DdlCommand, (), DdlCommand, Status, false, true>;
+
+/// Query returning the logged commands after `last_id`
+fn pending_sql(last_id: i64) -> String {
+  format!(
+    "SELECT id, command_tag, object_type, schema_name, object_identity, query, \
+     extract(epoch FROM captured_at) * 1000 FROM pg_embedded.ddl_log \
+     WHERE id > {last_id} ORDER BY id"
+  )
+}
+
+/// Parse a row of `pending_sql()` into the log ID and the command
+fn parse_ddl_command(row: &[Option<String>]) -> Option<(i64, DdlCommand)> {
+  let [id, command_tag, object_type, schema_name, object_identity, query, captured_at] = row else {
+    return None;
+  };
+  Some((
+    id.as_ref()?.parse().ok()?,
+    DdlCommand {
+      command_tag: command_tag.clone()?,
+      object_type: object_type.clone(),
+      schema_name: schema_name.clone(),
+      object_identity: object_identity.clone(),
+      query: query.clone().unwrap_or_default(),
+      captured_at: captured_at.as_ref()?.parse().ok()?,
+    },
+  ))
+}
+
+/// Connection reading the log and the callback the commands are handed to
+struct CaptureState {
+  connection: Option<PgConnection>,
+  last_id: i64,
+  callback: DdlCallback,
+}
+
+impl CaptureState {
+  /// Hand the commands logged since the last call to the callback
+  async fn deliver(&mut self) -> Result<()> {
+    let Some(connection) = self.connection.as_mut() else {
+      return Ok(());
+    };
+    let result = run_query(connection, &pending_sql(self.last_id)).await?;
+    for (id, command) in result.rows.iter().filter_map(|row| parse_ddl_command(row)) {
+      self.last_id = id;
+      self
+        .callback
+        .call(command, ThreadsafeFunctionCallMode::NonBlocking);
+    }
+    Ok(())
+  }
+}
+
+/// A running DDL capture returned by `captureDdl()`
+#[napi]
+pub struct DdlCapture {
+  state: Arc<Mutex<CaptureState>>,
+  stopped: Arc<AtomicBool>,
+  task: std::sync::Mutex<Option<JoinHandle<()>>>,
+}
+
+impl DdlCapture {
+  /// Install the event triggers in the database of `config` and start listening
+  pub(crate) async fn start(config: &ConnectionConfig, callback: DdlCallback) -> Result<Self> {
+    let mut pool = PgPoolOptions::new().max_connections(1);
+    if let Some(timeout) = connect_timeout(config)? {
+      pool = pool.acquire_timeout(timeout);
+    }
+    let pool = pool
+      .connect_with(connect_options(config)?)
+      .await
+      .map_err(|e| PgEmbedError::ConnectionError(e.to_string()))?;
+    let mut listener = PgListener::connect_with(&pool)
+      .await
+      .map_err(|e| PgEmbedError::ConnectionError(e.to_string()))?;
+    listener
+      .listen(DDL_CHANNEL)
+      .await
+      .map_err(|e| PgEmbedError::DatabaseError(e.to_string()))?;
+
+    let mut connection = connect(config).await?;
+    run_query(&mut connection, INSTALL_SQL).await?;
+    // Commands logged by an earlier capture that was not stopped are not reported
+    let last_id = run_query(
+      &mut connection,
+      "SELECT coalesce(max(id), 0) FROM pg_embedded.ddl_log",
+    )
+    .await?
+    .rows
+    .first()
+    .and_then(|row| row.first().cloned().flatten())
+    .and_then(|id| id.parse().ok())
+    .unwrap_or(0);
+
+    let state = Arc::new(Mutex::new(CaptureState {
+      connection: Some(connection),
+      last_id,
+      callback,
+    }));
+    let stopped = Arc::new(AtomicBool::new(false));
+    let task = {
+      let state = state.clone();
+      let stopped = stopped.clone();
+      napi::bindgen_prelude::spawn(async move {
+        while !stopped.load(Ordering::Relaxed) {
+          match tokio::time::timeout(STOP_POLL_INTERVAL, listener.recv()).await {
+            Ok(Ok(_)) => {
+              if state.lock().await.deliver().await.is_err() {
+                break;
+              }
+            }
+            Ok(Err(_)) => break,
+            Err(_) => {}
+          }
+        }
+        pool.close().await;
+      })
+    };
+
+    Ok(Self {
+      state,
+      stopped,
+      task: std::sync::Mutex::new(Some(task)),
+    })
+  }
+}
+
+#[napi]
+impl DdlCapture {
+  /// Stops capturing and removes the event triggers and the log table
+  ///
+  /// Commands that were logged but not yet reported are handed to the callback first.
+  ///
+  /// @returns Promise that resolves once the capture is removed
+  /// @throws Error if the event triggers cannot be removed
+  #[napi]
+  pub async fn stop(&self) -> napi::Result<()> {
+    self.stopped.store(true, Ordering::Relaxed);
+    let task = self.task.lock().ok().and_then(|mut task| task.take());
+    if let Some(task) = task {
+      let _ = task.await;
+    }
+    let mut state = self.state.lock().await;
+    state.deliver().await?;
+    let Some(mut connection) = state.connection.take() else {
+      return Ok(());
+    };
+    let result = run_query(&mut connection, UNINSTALL_SQL).await;
+    let _ = connection.close().await;
+    result?;
+    Ok(())
+  }
+}
+
+impl Drop for DdlCapture {
+  fn drop(&mut self) {
+    self.stopped.store(true, Ordering::Relaxed);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_ddl_command() {
+    let row: Vec<Option<String>> = vec![
+      Some("7".to_string()),
+      Some("CREATE TABLE".to_string()),
+      Some("table".to_string()),
+      Some("public".to_string()),
+      Some("public.users".to_string()),
+      Some("CREATE TABLE users (id int)".to_string()),
+      Some("1700000000000.5".to_string()),
+    ];
+    let (id, command) = parse_ddl_command(&row).unwrap();
+    assert_eq!(id, 7);
+    assert_eq!(command.object_identity.as_deref(), Some("public.users"));
+    assert_eq!(command.captured_at, 1700000000000.5);
+    assert!(parse_ddl_command(&row[..3]).is_none());
+    assert!(pending_sql(7).contains("WHERE id > 7"));
+  }
+}
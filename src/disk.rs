@@ -0,0 +1,313 @@
+//! Disk usage of the data directory, WAL and WAL archive, and low-space warnings
+
+use crate::logger::pg_log;
+use crate::types::InstanceState;
+use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi::Status;
+use napi_derive::napi;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Default interval between two disk usage checks
+const DEFAULT_CHECK_INTERVAL_MS: u32 = 60_000;
+
+pub(crate) type DiskUsageWarningCallback =
+  ThreadsafeFunction<DiskUsageWarning, (), DiskUsageWarning, Status, false, true>;
+
+/// Disk space used by an instance and left on its volume
+#[napi(object)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DiskUsage {
+  /// Size of the data directory, WAL included, in bytes
+  pub data_dir_bytes: i64,
+  /// Size of the WAL directory (`pg_wal`), in bytes
+  pub wal_bytes: i64,
+  /// Size of the WAL archive directory, if archiving is configured, in bytes
+  pub wal_archive_bytes: Option<i64>,
+  /// Free space on the volume of the data directory, in bytes (not available on Windows)
+  pub free_bytes: Option<i64>,
+  /// Total size of the volume of the data directory, in bytes (not available on Windows)
+  pub total_bytes: Option<i64>,
+}
+
+/// Options for `watchDiskUsage()`; a warning is raised when any threshold is crossed
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct DiskUsageWatchOptions {
+  /// Interval between two checks in milliseconds (default: 60000)
+  pub interval_ms: Option<u32>,
+  /// Warn when the WAL directory grows beyond this many bytes
+  pub max_wal_bytes: Option<i64>,
+  /// Warn when the data directory grows beyond this many bytes
+  pub max_data_dir_bytes: Option<i64>,
+  /// Warn when the free space on the volume drops below this many bytes
+  pub min_free_bytes: Option<i64>,
+}
+
+/// A disk usage threshold that was crossed, passed to the `watchDiskUsage()` callback
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct DiskUsageWarning {
+  /// The crossed threshold: "maxWalBytes", "maxDataDirBytes" or "minFreeBytes"
+  pub threshold: String,
+  /// Description of the problem, as logged
+  pub message: String,
+  /// Disk usage at the time of the check
+  pub usage: DiskUsage,
+}
+
+/// Total size of the files below `path`, without following symbolic links
+fn dir_size(path: &Path) -> u64 {
+  let Ok(entries) = fs::read_dir(path) else {
+    return 0;
+  };
+  entries
+    .flatten()
+    .map(|entry| match entry.metadata() {
+      Ok(metadata) if metadata.is_dir() => dir_size(&entry.path()),
+      Ok(metadata) => metadata.len(),
+      Err(_) => 0,
+    })
+    .sum()
+}
+
+/// Total and available bytes from the output of `df -Pk`
+#[cfg_attr(windows, allow(dead_code))]
+fn parse_df_output(output: &str) -> Option<(u64, u64)> {
+  // Filesystem 1024-blocks Used Available Capacity Mounted-on; the file system name
+  // may contain spaces, so the columns are counted from the end
+  let line = output.lines().nth(1)?;
+  let columns: Vec<&str> = line.split_whitespace().collect();
+  let count = columns.len();
+  if count < 6 {
+    return None;
+  }
+  let total: u64 = columns[count - 5].parse().ok()?;
+  let available: u64 = columns[count - 3].parse().ok()?;
+  Some((total * 1024, available * 1024))
+}
+
+/// Total and available bytes of the volume holding `path`
+#[cfg(unix)]
+pub(crate) fn volume_space(path: &Path) -> Option<(u64, u64)> {
+  let output = std::process::Command::new("df")
+    .arg("-Pk")
+    .arg(path)
+    .output()
+    .ok()?;
+  if !output.status.success() {
+    return None;
+  }
+  parse_df_output(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[cfg(windows)]
+pub(crate) fn volume_space(_path: &Path) -> Option<(u64, u64)> {
+  None
+}
+
+/// Measure the disk usage of `data_dir` and `wal_archive_dir`; this walks the
+/// directories, so it should run on a blocking thread
+pub(crate) fn measure(data_dir: &Path, wal_archive_dir: Option<&Path>) -> DiskUsage {
+  let space = volume_space(data_dir);
+  DiskUsage {
+    data_dir_bytes: dir_size(data_dir) as i64,
+    wal_bytes: dir_size(&data_dir.join("pg_wal")) as i64,
+    wal_archive_bytes: wal_archive_dir.map(|dir| dir_size(dir) as i64),
+    free_bytes: space.map(|(_, free)| free as i64),
+    total_bytes: space.map(|(total, _)| total as i64),
+  }
+}
+
+/// Thresholds of `options` that `usage` crosses
+pub(crate) fn crossed_thresholds(
+  usage: &DiskUsage,
+  options: &DiskUsageWatchOptions,
+) -> Vec<DiskUsageWarning> {
+  let mut warnings = Vec::new();
+  let mut warn = |threshold: &str, message: String| {
+    warnings.push(DiskUsageWarning {
+      threshold: threshold.to_string(),
+      message,
+      usage: usage.clone(),
+    })
+  };
+  if let Some(max) = options.max_wal_bytes.filter(|max| usage.wal_bytes > *max) {
+    warn(
+      "maxWalBytes",
+      format!(
+        "WAL directory uses {} bytes, more than {max}; check archive_command and replication slots",
+        usage.wal_bytes
+      ),
+    );
+  }
+  if let Some(max) = options
+    .max_data_dir_bytes
+    .filter(|max| usage.data_dir_bytes > *max)
+  {
+    warn(
+      "maxDataDirBytes",
+      format!(
+        "Data directory uses {} bytes, more than {max}",
+        usage.data_dir_bytes
+      ),
+    );
+  }
+  if let (Some(min), Some(free)) = (options.min_free_bytes, usage.free_bytes) {
+    if free < min {
+      warn(
+        "minFreeBytes",
+        format!("Only {free} bytes are free on the data directory's volume, less than {min}"),
+      );
+    }
+  }
+  warnings
+}
+
+/// A running disk usage watch
+pub(crate) struct DiskWatcher {
+  task: Option<JoinHandle<()>>,
+}
+
+impl DiskWatcher {
+  pub fn new() -> Self {
+    Self { task: None }
+  }
+
+  /// Check the disk usage in the background, replacing a running watch
+  ///
+  /// A warning is logged and passed to `callback` when a threshold is crossed; it is
+  /// raised again only after the usage went back within the threshold.
+  pub fn start(
+    &mut self,
+    options: DiskUsageWatchOptions,
+    data_dir: PathBuf,
+    wal_archive_dir: Option<PathBuf>,
+    state: Arc<Mutex<InstanceState>>,
+    callback: Option<DiskUsageWarningCallback>,
+  ) {
+    self.stop();
+    let interval = Duration::from_millis(u64::from(
+      options
+        .interval_ms
+        .unwrap_or(DEFAULT_CHECK_INTERVAL_MS)
+        .max(1),
+    ));
+
+    self.task = Some(napi::bindgen_prelude::spawn(async move {
+      let mut ticker = tokio::time::interval(interval);
+      let mut crossed = HashSet::new();
+      loop {
+        ticker.tick().await;
+        let running = state
+          .lock()
+          .map(|state| *state == InstanceState::Running)
+          .unwrap_or(false);
+        if !running {
+          continue;
+        }
+        let (data_dir, wal_archive_dir) = (data_dir.clone(), wal_archive_dir.clone());
+        let Ok(usage) =
+          tokio::task::spawn_blocking(move || measure(&data_dir, wal_archive_dir.as_deref())).await
+        else {
+          continue;
+        };
+        let warnings = crossed_thresholds(&usage, &options);
+        let current: HashSet<String> = warnings
+          .iter()
+          .map(|warning| warning.threshold.clone())
+          .collect();
+        for warning in warnings {
+          if crossed.contains(&warning.threshold) {
+            continue;
+          }
+          pg_log!(warn, "{}", warning.message);
+          if let Some(ref callback) = callback {
+            callback.call(warning, ThreadsafeFunctionCallMode::NonBlocking);
+          }
+        }
+        crossed = current;
+      }
+    }));
+  }
+
+  pub fn stop(&mut self) {
+    if let Some(task) = self.task.take() {
+      task.abort();
+    }
+  }
+
+  pub fn is_running(&self) -> bool {
+    self.task.as_ref().is_some_and(|task| !task.is_finished())
+  }
+}
+
+impl Drop for DiskWatcher {
+  fn drop(&mut self) {
+    self.stop();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_df_output() {
+    let output = "Filesystem     1024-blocks     Used Available Capacity Mounted on\n\
+                  /dev/nvme0n1p2   490617784 98765432 366852352      22% /\n";
+    assert_eq!(
+      parse_df_output(output),
+      Some((490617784 * 1024, 366852352 * 1024))
+    );
+    let spaced = "Filesystem 1024-blocks Used Available Capacity Mounted on\n\
+                  map auto_home 0 0 0 100% /System/Volumes/Data/home\n";
+    assert_eq!(parse_df_output(spaced), Some((0, 0)));
+    assert_eq!(parse_df_output("Filesystem\n"), None);
+  }
+
+  #[test]
+  fn test_measure_dir_sizes() {
+    let root = std::env::temp_dir().join(format!("pg-embedded-disk-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("pg_wal")).unwrap();
+    fs::write(root.join("PG_VERSION"), "17\n").unwrap();
+    fs::write(
+      root.join("pg_wal").join("000000010000000000000001"),
+      [0u8; 100],
+    )
+    .unwrap();
+
+    let usage = measure(&root, None);
+    assert_eq!(usage.data_dir_bytes, 103);
+    assert_eq!(usage.wal_bytes, 100);
+    assert_eq!(usage.wal_archive_bytes, None);
+    fs::remove_dir_all(&root).unwrap();
+  }
+
+  #[test]
+  fn test_crossed_thresholds() {
+    let usage = DiskUsage {
+      data_dir_bytes: 1000,
+      wal_bytes: 600,
+      free_bytes: Some(50),
+      ..Default::default()
+    };
+    let options = DiskUsageWatchOptions {
+      max_wal_bytes: Some(500),
+      max_data_dir_bytes: Some(2000),
+      min_free_bytes: Some(100),
+      ..Default::default()
+    };
+    let thresholds: Vec<String> = crossed_thresholds(&usage, &options)
+      .into_iter()
+      .map(|warning| warning.threshold)
+      .collect();
+    assert_eq!(thresholds, ["maxWalBytes", "minFreeBytes"]);
+    assert!(crossed_thresholds(&usage, &DiskUsageWatchOptions::default()).is_empty());
+  }
+}
@@ -1,3 +1,4 @@
+use crate::redact::redact;
 use napi::Status;
 use napi_derive::napi;
 use thiserror::Error;
@@ -23,13 +24,17 @@ pub enum PgEmbedError {
   TimeoutError(String),
   #[error("Tool execution failed: {0}")]
   ToolError(String),
+  #[error("Data directory version mismatch: {0}")]
+  VersionMismatchError(String),
+  #[error("Refusing to run as root: {0}")]
+  PrivilegeError(String),
   #[error("Internal error: {0}")]
   InternalError(String),
 }
 
 impl From<PgEmbedError> for napi::Error {
   fn from(e: PgEmbedError) -> Self {
-    napi::Error::new(Status::GenericFailure, e.to_string())
+    napi::Error::new(Status::GenericFailure, redact(&e.to_string()))
   }
 }
 
@@ -58,6 +63,10 @@ pub enum PostgresError {
   TimeoutError,
   /// Tool error
   ToolError,
+  /// The data directory belongs to another major version than the selected binaries
+  VersionMismatchError,
+  /// The process runs as root, which PostgreSQL refuses to run as
+  PrivilegeError,
 }
 
 /// PostgreSQL error information structure
@@ -91,7 +100,7 @@ pub fn convert_postgresql_error(err: postgresql_embedded::Error) -> PgEmbedError
 pub fn setup_error(message: &str) -> napi::Error {
   napi::Error::new(
     napi::Status::GenericFailure,
-    format!("Setup failed: {message}"),
+    redact(&format!("Setup failed: {message}")),
   )
 }
 
@@ -99,7 +108,7 @@ pub fn setup_error(message: &str) -> napi::Error {
 pub fn start_error(message: &str) -> napi::Error {
   napi::Error::new(
     napi::Status::GenericFailure,
-    format!("Start failed: {message}"),
+    redact(&format!("Start failed: {message}")),
   )
 }
 
@@ -107,7 +116,7 @@ pub fn start_error(message: &str) -> napi::Error {
 pub fn stop_error(message: &str) -> napi::Error {
   napi::Error::new(
     napi::Status::GenericFailure,
-    format!("Stop failed: {message}"),
+    redact(&format!("Stop failed: {message}")),
   )
 }
 
@@ -115,7 +124,7 @@ pub fn stop_error(message: &str) -> napi::Error {
 pub fn database_error(message: &str) -> napi::Error {
   napi::Error::new(
     napi::Status::GenericFailure,
-    format!("Database operation failed: {message}"),
+    redact(&format!("Database operation failed: {message}")),
   )
 }
 
@@ -123,7 +132,7 @@ pub fn database_error(message: &str) -> napi::Error {
 pub fn configuration_error(message: &str) -> napi::Error {
   napi::Error::new(
     napi::Status::GenericFailure,
-    format!("Configuration error: {message}"),
+    redact(&format!("Configuration error: {message}")),
   )
 }
 
@@ -131,7 +140,7 @@ pub fn configuration_error(message: &str) -> napi::Error {
 pub fn connection_error(message: &str) -> napi::Error {
   napi::Error::new(
     napi::Status::GenericFailure,
-    format!("Connection error: {message}"),
+    redact(&format!("Connection error: {message}")),
   )
 }
 
@@ -139,7 +148,7 @@ pub fn connection_error(message: &str) -> napi::Error {
 pub fn timeout_error(message: &str) -> napi::Error {
   napi::Error::new(
     napi::Status::GenericFailure,
-    format!("Operation timeout: {message}"),
+    redact(&format!("Operation timeout: {message}")),
   )
 }
 
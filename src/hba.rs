@@ -0,0 +1,299 @@
+//! Client authentication rules in pg_hba.conf
+
+use crate::error::{PgEmbedError, Result};
+use napi_derive::napi;
+
+/// Connection types accepted in the first pg_hba.conf column
+const CONNECTION_TYPES: [&str; 6] = [
+  "local",
+  "host",
+  "hostssl",
+  "hostnossl",
+  "hostgssenc",
+  "hostnogssenc",
+];
+
+/// A client authentication rule (one line of pg_hba.conf)
+///
+/// @example
+/// ```typescript
+/// const rule: HbaRule = {
+///   connectionType: 'host',
+///   database: 'all',
+///   user: 'mallory',
+///   address: '127.0.0.1/32',
+///   method: 'reject',
+/// };
+/// ```
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct HbaRule {
+  /// Connection type: local, host, hostssl, hostnossl, hostgssenc or hostnogssenc
+  pub connection_type: String,
+  /// Database name(s), e.g. `all`, `app` or `app,app_test`
+  pub database: String,
+  /// User name(s), e.g. `all`, `alice` or `+group`
+  pub user: String,
+  /// Client address (e.g. `127.0.0.1/32`); required for every type except `local`
+  pub address: Option<String>,
+  /// Authentication method, e.g. `scram-sha-256`, `trust`, `reject` or `cert`
+  pub method: String,
+  /// Authentication options, e.g. `clientcert=verify-full`
+  pub options: Option<String>,
+}
+
+/// Criteria selecting pg_hba.conf rules for `removeHbaRule()`; omitted fields match any value
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct HbaRuleMatcher {
+  /// Connection type to match
+  pub connection_type: Option<String>,
+  /// Database column to match
+  pub database: Option<String>,
+  /// User column to match
+  pub user: Option<String>,
+  /// Address column to match
+  pub address: Option<String>,
+  /// Authentication method to match
+  pub method: Option<String>,
+}
+
+impl HbaRule {
+  /// Check that the rule forms a well-formed pg_hba.conf line
+  pub(crate) fn validate(&self) -> Result<()> {
+    let invalid = |message: &str| Err(PgEmbedError::ConfigurationError(message.to_string()));
+    if !CONNECTION_TYPES.contains(&self.connection_type.as_str()) {
+      return invalid(&format!(
+        "Invalid connection type '{}', expected one of {}",
+        self.connection_type,
+        CONNECTION_TYPES.join(", ")
+      ));
+    }
+    let is_local = self.connection_type == "local";
+    match (&self.address, is_local) {
+      (Some(_), true) => return invalid("local rules do not take an address"),
+      (None, false) => {
+        return invalid(&format!(
+          "{} rules require an address",
+          self.connection_type
+        ))
+      }
+      _ => {}
+    }
+    for column in [&self.database, &self.user, &self.method] {
+      if column.is_empty() || column.contains(|c: char| c.is_whitespace() || c == '#') {
+        return invalid(&format!("Invalid pg_hba.conf column value '{column}'"));
+      }
+    }
+    // An address is either in CIDR notation or followed by a netmask
+    if let Some(address) = &self.address {
+      if !(1..=2).contains(&address.split_whitespace().count())
+        || address.contains(['#', '\n', '\r'])
+      {
+        return invalid(&format!("Invalid pg_hba.conf address '{address}'"));
+      }
+    }
+    if self
+      .options
+      .as_ref()
+      .is_some_and(|options| options.contains(['\n', '\r', '#']))
+    {
+      return invalid("pg_hba.conf options must be on a single line");
+    }
+    Ok(())
+  }
+
+  /// Format the rule as a pg_hba.conf line
+  fn to_line(&self) -> String {
+    let mut fields = vec![
+      self.connection_type.as_str(),
+      self.database.as_str(),
+      self.user.as_str(),
+    ];
+    fields.extend(self.address.as_deref());
+    fields.push(&self.method);
+    fields.extend(
+      self
+        .options
+        .as_deref()
+        .filter(|options| !options.is_empty()),
+    );
+    fields.join("\t")
+  }
+}
+
+impl HbaRuleMatcher {
+  fn matches(&self, rule: &HbaRule) -> bool {
+    let column = |wanted: &Option<String>, actual: Option<&str>| {
+      wanted
+        .as_deref()
+        .is_none_or(|wanted| Some(wanted) == actual)
+    };
+    column(&self.connection_type, Some(&rule.connection_type))
+      && column(&self.database, Some(&rule.database))
+      && column(&self.user, Some(&rule.user))
+      && column(&self.address, rule.address.as_deref())
+      && column(&self.method, Some(&rule.method))
+  }
+}
+
+/// Parse an active pg_hba.conf line; comments, blank lines and include directives yield None
+pub(crate) fn parse_hba_line(line: &str) -> Option<HbaRule> {
+  let content = line.split('#').next().unwrap_or_default();
+  let fields: Vec<&str> = content.split_whitespace().collect();
+  let connection_type = *fields.first()?;
+  if !CONNECTION_TYPES.contains(&connection_type) {
+    return None;
+  }
+  let mut rest = fields.get(3..)?.iter().copied();
+  let address = if connection_type == "local" {
+    None
+  } else {
+    let address = rest.next()?;
+    // An address without a CIDR suffix may be followed by a separate netmask column
+    let netmask = rest
+      .clone()
+      .next()
+      .filter(|mask| !address.contains('/') && mask.parse::<std::net::IpAddr>().is_ok());
+    match netmask {
+      Some(mask) => {
+        rest.next();
+        Some(format!("{address} {mask}"))
+      }
+      None => Some(address.to_string()),
+    }
+  };
+  let method = rest.next()?.to_string();
+  let options: Vec<&str> = rest.collect();
+  Some(HbaRule {
+    connection_type: connection_type.to_string(),
+    database: fields[1].to_string(),
+    user: fields[2].to_string(),
+    address,
+    method,
+    options: (!options.is_empty()).then(|| options.join(" ")),
+  })
+}
+
+/// Active rules of a pg_hba.conf file, in the order the server evaluates them
+pub(crate) fn list_hba_rules(contents: &str) -> Vec<HbaRule> {
+  contents.lines().filter_map(parse_hba_line).collect()
+}
+
+/// Add `rule` before all existing rules (so it takes precedence) or after them
+///
+/// Returns None when an identical rule is already present.
+pub(crate) fn add_hba_rule(contents: &str, rule: &HbaRule, append: bool) -> Option<String> {
+  if list_hba_rules(contents).contains(rule) {
+    return None;
+  }
+  let mut lines: Vec<&str> = contents.lines().collect();
+  let line = rule.to_line();
+  let index = if append {
+    lines.len()
+  } else {
+    lines
+      .iter()
+      .position(|line| parse_hba_line(line).is_some())
+      .unwrap_or(lines.len())
+  };
+  lines.insert(index, &line);
+  Some(lines.join("\n") + "\n")
+}
+
+/// Remove every rule selected by `matcher`, returning the new contents and the number removed
+pub(crate) fn remove_hba_rules(contents: &str, matcher: &HbaRuleMatcher) -> (String, u32) {
+  let mut removed = 0;
+  let lines: Vec<&str> = contents
+    .lines()
+    .filter(|line| match parse_hba_line(line) {
+      Some(rule) if matcher.matches(&rule) => {
+        removed += 1;
+        false
+      }
+      _ => true,
+    })
+    .collect();
+  (lines.join("\n") + "\n", removed)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const HBA: &str = "# TYPE DATABASE USER ADDRESS METHOD\n\nlocal all all trust\nhost all all 127.0.0.1/32 scram-sha-256\nhost all all 10.0.0.0 255.0.0.0 md5\nhostssl app all ::1/128 cert clientcert=verify-full\n";
+
+  fn reject(user: &str) -> HbaRule {
+    HbaRule {
+      connection_type: "host".to_string(),
+      database: "all".to_string(),
+      user: user.to_string(),
+      address: Some("127.0.0.1/32".to_string()),
+      method: "reject".to_string(),
+      options: None,
+    }
+  }
+
+  #[test]
+  fn test_list_hba_rules() {
+    let rules = list_hba_rules(HBA);
+    assert_eq!(rules.len(), 4);
+    assert_eq!(rules[0].address, None);
+    assert_eq!(rules[0].method, "trust");
+    assert_eq!(rules[2].address.as_deref(), Some("10.0.0.0 255.0.0.0"));
+    assert_eq!(rules[2].method, "md5");
+    assert_eq!(rules[3].options.as_deref(), Some("clientcert=verify-full"));
+  }
+
+  #[test]
+  fn test_add_hba_rule_is_idempotent() {
+    let added = add_hba_rule(HBA, &reject("mallory"), false).unwrap();
+    let rules = list_hba_rules(&added);
+    assert_eq!(rules[0], reject("mallory"));
+    assert!(added.starts_with("# TYPE DATABASE USER ADDRESS METHOD\n\nhost\tall\tmallory"));
+    assert_eq!(add_hba_rule(&added, &reject("mallory"), false), None);
+
+    let appended = add_hba_rule(HBA, &reject("eve"), true).unwrap();
+    assert_eq!(list_hba_rules(&appended).last(), Some(&reject("eve")));
+  }
+
+  #[test]
+  fn test_remove_hba_rules() {
+    let contents = add_hba_rule(HBA, &reject("mallory"), false).unwrap();
+    let matcher = HbaRuleMatcher {
+      user: Some("mallory".to_string()),
+      ..Default::default()
+    };
+    let (contents, removed) = remove_hba_rules(&contents, &matcher);
+    assert_eq!(removed, 1);
+    assert_eq!(contents, HBA);
+
+    let matcher = HbaRuleMatcher {
+      connection_type: Some("host".to_string()),
+      ..Default::default()
+    };
+    assert_eq!(remove_hba_rules(HBA, &matcher).1, 2);
+  }
+
+  #[test]
+  fn test_validate() {
+    assert!(reject("mallory").validate().is_ok());
+    let local_with_address = HbaRule {
+      connection_type: "local".to_string(),
+      ..reject("mallory")
+    };
+    assert!(local_with_address.validate().is_err());
+    let host_without_address = HbaRule {
+      address: None,
+      ..reject("mallory")
+    };
+    assert!(host_without_address.validate().is_err());
+    let bad_user = reject("mallory all");
+    assert!(bad_user.validate().is_err());
+    let netmask = HbaRule {
+      address: Some("10.0.0.0 255.0.0.0".to_string()),
+      ..reject("mallory")
+    };
+    assert!(netmask.validate().is_ok());
+  }
+}
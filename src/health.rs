@@ -0,0 +1,58 @@
+//! Probes telling whether a server is still accepting connections
+
+use std::io;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// How long a health probe waits for the server to accept a connection
+pub(crate) const HEALTH_PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Check that a server accepts connections on `host` and `port`
+///
+/// Opens and immediately closes a connection, without authenticating, so the probe is
+/// cheap and does not show up as a session. A `host` starting with `/` is the directory of
+/// the server's Unix-domain socket.
+pub(crate) fn probe_server(host: &str, port: u16, timeout: Duration) -> io::Result<()> {
+  if host.starts_with('/') {
+    return probe_socket_dir(host, port);
+  }
+  let mut last_error = io::Error::new(
+    io::ErrorKind::NotFound,
+    format!("'{host}' does not resolve to an address"),
+  );
+  for address in (host, port).to_socket_addrs()? {
+    match TcpStream::connect_timeout(&address, timeout) {
+      Ok(_) => return Ok(()),
+      Err(e) => last_error = e,
+    }
+  }
+  Err(last_error)
+}
+
+#[cfg(unix)]
+fn probe_socket_dir(dir: &str, port: u16) -> io::Result<()> {
+  std::os::unix::net::UnixStream::connect(format!("{dir}/.s.PGSQL.{port}")).map(|_| ())
+}
+
+#[cfg(not(unix))]
+fn probe_socket_dir(dir: &str, _port: u16) -> io::Result<()> {
+  Err(io::Error::new(
+    io::ErrorKind::Unsupported,
+    format!("Unix-domain socket directory '{dir}' cannot be probed on this platform"),
+  ))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::net::TcpListener;
+
+  #[test]
+  fn test_probe_server() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    assert!(probe_server("127.0.0.1", port, HEALTH_PROBE_TIMEOUT).is_ok());
+    drop(listener);
+    assert!(probe_server("127.0.0.1", port, HEALTH_PROBE_TIMEOUT).is_err());
+  }
+}
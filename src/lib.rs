@@ -1,15 +1,93 @@
+mod advisor;
+mod archive;
+mod backup_catalog;
+mod backup_verify;
+mod benchmark;
+mod binary_text;
+mod checksum;
+mod client;
+mod conf;
+mod conninfo;
+mod copy_binary;
+mod copy_in;
+mod copy_out;
+mod ddl;
+mod disk;
 mod error;
+mod hba;
+mod health;
+mod link;
 mod logger;
+mod lsn;
+mod metadata;
+mod metrics;
+mod notify;
+mod paths;
+mod planner_stats;
+mod plans;
 mod postgres;
+mod preflight;
+mod privileges;
+mod profile;
+mod query_log;
+mod redact;
+mod registry;
+mod replica;
+mod roles;
+mod router;
+mod script;
+mod server_env;
 mod settings;
+mod shard;
+mod sql;
+mod stats;
+mod tenant;
+mod testdata;
 mod tools;
+mod truncate;
 mod types;
+mod undo;
 mod version;
+mod wal;
 
+pub use advisor::*;
+pub use archive::*;
+pub use backup_catalog::*;
+pub use backup_verify::*;
+pub use benchmark::*;
+pub use checksum::*;
+pub use client::*;
+pub use conninfo::*;
+pub use copy_in::*;
+pub use copy_out::*;
+pub use ddl::*;
+pub use disk::*;
 pub use error::*;
+pub use hba::*;
+pub use link::*;
 pub use logger::*;
+pub use lsn::*;
+pub use metrics::*;
+pub use notify::*;
+pub use plans::*;
 pub use postgres::*;
+pub use preflight::*;
+pub use profile::*;
+pub use query_log::*;
+pub use redact::*;
+pub use registry::*;
+pub use replica::*;
+pub use roles::*;
+pub use router::*;
+pub use script::*;
 pub use settings::*;
+pub use shard::*;
+pub use stats::*;
+pub use tenant::*;
+pub use testdata::*;
 pub use tools::*;
+pub use truncate::*;
 pub use types::*;
+pub use undo::*;
 pub use version::*;
+pub use wal::*;
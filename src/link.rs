@@ -0,0 +1,188 @@
+//! Cross-instance and cross-database access through postgres_fdw and dblink
+
+use crate::client::QueryResult;
+use crate::sql::{quote_ident, quote_literal};
+use crate::tools::common::ConnectionConfig;
+use napi_derive::napi;
+
+/// Options for `linkInstances()`
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct LinkInstancesOptions {
+  /// Name of the foreign server created in the local database (default: "remote")
+  pub server_name: Option<String>,
+  /// Remote schemas whose tables are imported (default: ["public"])
+  pub schemas: Option<Vec<String>>,
+  /// Remote database to connect to (defaults to the remote instance's databaseName)
+  pub remote_database: Option<String>,
+}
+
+/// Local schema the tables of remote schema `schema` are imported into
+pub(crate) fn local_schema(server_name: &str, schema: &str) -> String {
+  format!("{server_name}_{schema}")
+}
+
+/// Script that installs postgres_fdw, creates the foreign server for `remote` with a user
+/// mapping for the current user, and imports `schemas`, all in one transaction
+pub(crate) fn link_script(
+  server_name: &str,
+  remote: &ConnectionConfig,
+  schemas: &[String],
+) -> String {
+  let server = quote_ident(server_name);
+  let mut server_options = Vec::new();
+  if let Some(host) = &remote.host {
+    server_options.push(format!("host {}", quote_literal(host)));
+  }
+  if let Some(port) = remote.port {
+    server_options.push(format!("port {}", quote_literal(&port.to_string())));
+  }
+  if let Some(database) = &remote.database {
+    server_options.push(format!("dbname {}", quote_literal(database)));
+  }
+  let mut user_options = Vec::new();
+  if let Some(username) = &remote.username {
+    user_options.push(format!("user {}", quote_literal(username)));
+  }
+  if let Some(password) = &remote.password {
+    user_options.push(format!("password {}", quote_literal(password)));
+  }
+
+  let mut script = vec![
+    "BEGIN;".to_string(),
+    "CREATE EXTENSION IF NOT EXISTS postgres_fdw;".to_string(),
+    format!(
+      "CREATE SERVER {server} FOREIGN DATA WRAPPER postgres_fdw{};",
+      options_clause(&server_options)
+    ),
+    format!(
+      "CREATE USER MAPPING FOR CURRENT_USER SERVER {server}{};",
+      options_clause(&user_options)
+    ),
+  ];
+  for schema in schemas {
+    let local = quote_ident(&local_schema(server_name, schema));
+    script.push(format!("CREATE SCHEMA IF NOT EXISTS {local};"));
+    script.push(format!(
+      "IMPORT FOREIGN SCHEMA {} FROM SERVER {server} INTO {local};",
+      quote_ident(schema)
+    ));
+  }
+  script.push("COMMIT;".to_string());
+  script.join("\n")
+}
+
+/// ` OPTIONS (...)` clause for `options`, empty when there are none
+fn options_clause(options: &[String]) -> String {
+  if options.is_empty() {
+    String::new()
+  } else {
+    format!(" OPTIONS ({})", options.join(", "))
+  }
+}
+
+/// Query returning the foreign tables of `server_name`, schema-qualified
+pub(crate) fn foreign_tables_sql(server_name: &str) -> String {
+  format!(
+    "SELECT format('%I.%I', n.nspname, c.relname) FROM pg_foreign_table ft \
+     JOIN pg_class c ON c.oid = ft.ftrelid JOIN pg_namespace n ON n.oid = c.relnamespace \
+     JOIN pg_foreign_server s ON s.oid = ft.ftserver WHERE s.srvname = {} ORDER BY 1",
+    quote_literal(server_name)
+  )
+}
+
+/// Query running `sql` through dblink with `conninfo`
+///
+/// dblink needs the result columns spelled out, so the remote side returns every value as
+/// a (row number, column, text value, is null) tuple for `parse_dblink_rows()`.
+pub(crate) fn dblink_sql(conninfo: &str, sql: &str) -> String {
+  let remote_sql = format!(
+    "SELECT r.n, e.key, e.value #>> '{{}}', json_typeof(e.value) = 'null' \
+     FROM (SELECT row_number() OVER () AS n, row_to_json(q) AS j FROM ({sql}) q) r, \
+     json_each(r.j) e"
+  );
+  format!(
+    "SELECT * FROM dblink({}, {}) AS t(n bigint, key text, value text, is_null boolean)",
+    quote_literal(conninfo),
+    quote_literal(&remote_sql)
+  )
+}
+
+/// Reassemble the rows of `dblink_sql()`
+pub(crate) fn parse_dblink_rows(rows: &[Vec<String>]) -> QueryResult {
+  let mut result = QueryResult::default();
+  let mut current_row = None;
+  for row in rows {
+    let [n, key, value, is_null] = row.as_slice() else {
+      continue;
+    };
+    if current_row.as_ref() != Some(n) {
+      current_row = Some(n.clone());
+      result.rows.push(Vec::new());
+    }
+    if result.rows.len() == 1 {
+      result.columns.push(key.clone());
+    }
+    if let Some(values) = result.rows.last_mut() {
+      values.push(Some(value.clone()).filter(|_| is_null != "t"));
+    }
+  }
+  result.row_count = result.rows.len() as u32;
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_link_script() {
+    let remote = ConnectionConfig {
+      host: Some("localhost".to_string()),
+      port: Some(5433),
+      username: Some("postgres".to_string()),
+      password: Some("it's".to_string()),
+      database: Some("orders".to_string()),
+      ..Default::default()
+    };
+    let script = link_script("orders", &remote, &["public".to_string()]);
+    assert!(script.contains(
+      "CREATE SERVER \"orders\" FOREIGN DATA WRAPPER postgres_fdw \
+       OPTIONS (host 'localhost', port '5433', dbname 'orders');"
+    ));
+    assert!(script.contains("OPTIONS (user 'postgres', password 'it''s');"));
+    assert!(script
+      .contains("IMPORT FOREIGN SCHEMA \"public\" FROM SERVER \"orders\" INTO \"orders_public\";"));
+    assert!(script.starts_with("BEGIN;") && script.ends_with("COMMIT;"));
+  }
+
+  #[test]
+  fn test_dblink_sql() {
+    let sql = dblink_sql("dbname='other'", "SELECT 'a' AS x");
+    assert!(sql.starts_with("SELECT * FROM dblink('dbname=''other''', 'SELECT r.n"));
+    assert!(sql.contains("FROM (SELECT ''a'' AS x) q"));
+  }
+
+  #[test]
+  fn test_parse_dblink_rows() {
+    let rows: Vec<Vec<String>> = [
+      ["1", "id", "1", "f"],
+      ["1", "name", "", "t"],
+      ["2", "id", "2", "f"],
+      ["2", "name", "bob", "f"],
+    ]
+    .iter()
+    .map(|row| row.iter().map(|value| value.to_string()).collect())
+    .collect();
+    let result = parse_dblink_rows(&rows);
+    assert_eq!(result.columns, vec!["id", "name"]);
+    assert_eq!(
+      result.rows,
+      vec![
+        vec![Some("1".to_string()), None],
+        vec![Some("2".to_string()), Some("bob".to_string())],
+      ]
+    );
+    assert_eq!(result.row_count, 2);
+  }
+}
@@ -1,8 +1,16 @@
+use crate::redact::redact;
 use napi_derive::napi;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Once;
 
 static INIT: Once = Once::new();
 
+/// Whether quiet mode suppresses all log output
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Number of live instances created with `quiet: true`
+static QUIET_INSTANCES: AtomicUsize = AtomicUsize::new(0);
+
 /// Log level enumeration
 #[napi]
 #[derive(Clone, Copy)]
@@ -26,7 +34,7 @@ struct SimpleLogger {
 
 impl log::Log for SimpleLogger {
   fn enabled(&self, metadata: &log::Metadata) -> bool {
-    metadata.level() <= self.level
+    metadata.level() <= self.level && !is_quiet()
   }
 
   fn log(&self, record: &log::Record) {
@@ -66,52 +74,98 @@ pub fn init_logger(level: Option<LogLevel>) -> napi::Result<()> {
   Ok(())
 }
 
+/// Turn quiet mode on or off
+///
+/// In quiet mode the logger prints nothing, including messages of pg-embedded itself and
+/// of its dependencies. Log output only ever goes to stderr, so stdout stays free for the
+/// output of the application either way. An instance created with the `quiet` setting
+/// also keeps the logger quiet until it is cleaned up, whatever is set here.
+///
+/// @param quiet - Whether to suppress all log output
+///
+/// @example
+/// ```typescript
+/// setQuietMode(true);
+/// const instance = new PostgresInstance();
+/// await instance.start(); // prints nothing
+/// ```
+#[napi]
+pub fn set_quiet_mode(quiet: bool) {
+  QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Whether quiet mode is on, through `setQuietMode()` or a live instance with `quiet: true`
+#[napi]
+pub fn is_quiet_mode() -> bool {
+  is_quiet()
+}
+
+pub(crate) fn is_quiet() -> bool {
+  QUIET.load(Ordering::Relaxed) || QUIET_INSTANCES.load(Ordering::Relaxed) > 0
+}
+
+/// Keeps the logger quiet while an instance created with `quiet: true` is alive
+pub(crate) struct QuietGuard;
+
+impl QuietGuard {
+  pub fn new() -> Self {
+    QUIET_INSTANCES.fetch_add(1, Ordering::Relaxed);
+    Self
+  }
+}
+
+impl Drop for QuietGuard {
+  fn drop(&mut self) {
+    QUIET_INSTANCES.fetch_sub(1, Ordering::Relaxed);
+  }
+}
+
 /// Log error message
 #[napi]
 pub fn log_error(message: String) {
-  log::error!("{message}");
+  log::error!("{}", redact(&message));
 }
 
 /// Log warning message
 #[napi]
 pub fn log_warn(message: String) {
-  log::warn!("{message}");
+  log::warn!("{}", redact(&message));
 }
 
 /// Log info message
 #[napi]
 pub fn log_info(message: String) {
-  log::info!("{message}");
+  log::info!("{}", redact(&message));
 }
 
 /// Log debug message
 #[napi]
 pub fn log_debug(message: String) {
-  log::debug!("{message}");
+  log::debug!("{}", redact(&message));
 }
 
 /// Log trace message
 #[napi]
 pub fn log_trace(message: String) {
-  log::trace!("{message}");
+  log::trace!("{}", redact(&message));
 }
 
 /// Internal logging macros
 macro_rules! pg_log {
     (error, $($arg:tt)*) => {
-        log::error!("[pg-embedded] {}", format!($($arg)*));
+        log::error!("[pg-embedded] {}", $crate::redact::redact(&format!($($arg)*)));
     };
     (warn, $($arg:tt)*) => {
-        log::warn!("[pg-embedded] {}", format!($($arg)*));
+        log::warn!("[pg-embedded] {}", $crate::redact::redact(&format!($($arg)*)));
     };
     (info, $($arg:tt)*) => {
-        log::info!("[pg-embedded] {}", format!($($arg)*));
+        log::info!("[pg-embedded] {}", $crate::redact::redact(&format!($($arg)*)));
     };
     (debug, $($arg:tt)*) => {
-        log::debug!("[pg-embedded] {}", format!($($arg)*));
+        log::debug!("[pg-embedded] {}", $crate::redact::redact(&format!($($arg)*)));
     };
     (trace, $($arg:tt)*) => {
-        log::trace!("[pg-embedded] {}", format!($($arg)*));
+        log::trace!("[pg-embedded] {}", $crate::redact::redact(&format!($($arg)*)));
     };
 }
 
@@ -0,0 +1,87 @@
+//! WAL position (LSN) helpers for replication and point-in-time recovery checks
+
+use crate::error::{PgEmbedError, Result};
+use napi_derive::napi;
+use std::cmp::Ordering;
+
+/// Parse an LSN in PostgreSQL's `XXXXXXXX/XXXXXXXX` notation into a byte position
+pub(crate) fn parse_lsn(lsn: &str) -> Result<u64> {
+  let invalid = || PgEmbedError::ConfigurationError(format!("Invalid LSN '{lsn}'"));
+  let (high, low) = lsn.trim().split_once('/').ok_or_else(invalid)?;
+  let part = |text: &str| {
+    if text.is_empty() || text.len() > 8 {
+      return Err(invalid());
+    }
+    u32::from_str_radix(text, 16).map_err(|_| invalid())
+  };
+  Ok((u64::from(part(high)?) << 32) | u64::from(part(low)?))
+}
+
+/// Compare two WAL positions
+///
+/// @param a - First LSN, e.g. `0/3000060`
+/// @param b - Second LSN
+/// @returns -1 if `a` is before `b`, 0 if they are equal and 1 if `a` is after `b`
+/// @throws Error if either value is not a valid LSN
+///
+/// @example
+/// ```typescript
+/// const { replayLsn } = await standby.getRecoveryStatus();
+/// if (compareLsn(replayLsn!, primaryLsn) >= 0) {
+///   console.log('standby caught up');
+/// }
+/// ```
+#[napi]
+pub fn compare_lsn(a: String, b: String) -> napi::Result<i32> {
+  Ok(match parse_lsn(&a)?.cmp(&parse_lsn(&b)?) {
+    Ordering::Less => -1,
+    Ordering::Equal => 0,
+    Ordering::Greater => 1,
+  })
+}
+
+/// Number of WAL bytes between two positions, like `pg_wal_lsn_diff(a, b)`
+///
+/// @param a - First LSN
+/// @param b - Second LSN
+/// @returns `a - b` in bytes (negative if `a` is before `b`)
+/// @throws Error if either value is not a valid LSN
+///
+/// @example
+/// ```typescript
+/// const lag = lsnDiffBytes(primaryLsn, replayLsn);
+/// ```
+#[napi]
+pub fn lsn_diff_bytes(a: String, b: String) -> napi::Result<i64> {
+  Ok(lsn_diff(parse_lsn(&a)?, parse_lsn(&b)?))
+}
+
+fn lsn_diff(a: u64, b: u64) -> i64 {
+  (a as i128 - b as i128).clamp(i64::MIN as i128, i64::MAX as i128) as i64
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_lsn() {
+    assert_eq!(parse_lsn("0/0").unwrap(), 0);
+    assert_eq!(parse_lsn("0/3000060").unwrap(), 0x3000060);
+    assert_eq!(parse_lsn("16/B374D848").unwrap(), 0x16_B374_D848);
+    assert_eq!(parse_lsn("16/b374d848").unwrap(), 0x16_B374_D848);
+    assert!(parse_lsn("3000060").is_err());
+    assert!(parse_lsn("0/").is_err());
+    assert!(parse_lsn("0/123456789").is_err());
+    assert!(parse_lsn("x/1").is_err());
+  }
+
+  #[test]
+  fn test_lsn_diff() {
+    let a = parse_lsn("1/0").unwrap();
+    let b = parse_lsn("0/FFFFFF00").unwrap();
+    assert_eq!(lsn_diff(a, b), 256);
+    assert_eq!(lsn_diff(b, a), -256);
+    assert_eq!(lsn_diff(a, a), 0);
+  }
+}
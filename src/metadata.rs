@@ -0,0 +1,105 @@
+//! Metadata pg-embedded records in the data directories it sets up
+//!
+//! The file uses postgresql.conf syntax and lives next to PG_VERSION, which only holds the
+//! major version. The server does not read it.
+
+use crate::conf::ConfFile;
+use crate::error::{PgEmbedError, Result};
+use crate::settings::read_data_dir_major_version;
+use postgresql_embedded::{Version, VersionReq};
+use std::path::Path;
+
+/// File in the data directory holding the metadata
+const METADATA_FILE: &str = "pg-embedded.meta";
+
+/// Full version of the binaries the cluster was set up with, e.g. `16.4.0`
+const POSTGRESQL_VERSION_KEY: &str = "postgresql_version";
+
+/// The version a requirement pins, e.g. `16.4.0` for `=16.4.0`
+///
+/// postgresql_embedded replaces requirements such as `16` with the exact version of the
+/// latest matching release when it installs the binaries.
+pub(crate) fn exact_version(version_req: &VersionReq) -> Option<Version> {
+  Version::parse(version_req.to_string().strip_prefix('=')?).ok()
+}
+
+/// Version recorded by `record_version()`, if the data directory has one
+pub(crate) fn recorded_version(data_dir: &Path) -> Option<Version> {
+  let metadata = ConfFile::load(data_dir.join(METADATA_FILE)).ok()?;
+  Version::parse(&metadata.get(POSTGRESQL_VERSION_KEY)?).ok()
+}
+
+/// Record the version of the binaries serving the cluster in `data_dir`
+pub(crate) fn record_version(data_dir: &Path, version: &Version) -> Result<()> {
+  let mut metadata = ConfFile::load(data_dir.join(METADATA_FILE))?;
+  metadata.set(POSTGRESQL_VERSION_KEY, &version.to_string());
+  metadata.save()
+}
+
+/// Reject binaries of `version` for a cluster of another major version in `data_dir`
+///
+/// A data directory that is not initialized yet is accepted. The error names both versions
+/// and, for a cluster of an older major version, how to move it to the selected one.
+pub(crate) fn check_data_dir_version(data_dir: &Path, version: &Version) -> Result<()> {
+  let Some(cluster_major) = read_data_dir_major_version(data_dir) else {
+    return Ok(());
+  };
+  if cluster_major == version.major {
+    return Ok(());
+  }
+  let set_up_with = recorded_version(data_dir)
+    .filter(|recorded| recorded.major == cluster_major)
+    .map(|recorded| format!(" (set up with {recorded})"))
+    .unwrap_or_default();
+  let suggestion = if cluster_major < version.major {
+    format!(
+      "set version to '{cluster_major}' to keep using it, or upgrade it by dumping it with \
+       PostgreSQL {cluster_major} and restoring the dump into a new dataDir"
+    )
+  } else {
+    format!("set version to '{cluster_major}' or use another dataDir")
+  };
+  Err(PgEmbedError::VersionMismatchError(format!(
+    "dataDir '{}' contains a PostgreSQL {cluster_major} cluster{set_up_with}, but PostgreSQL \
+     {version} is selected; {suggestion}",
+    data_dir.display()
+  )))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+
+  #[test]
+  fn test_exact_version() {
+    let version_req = VersionReq::parse("=16.4.0").unwrap();
+    assert_eq!(exact_version(&version_req), Some(Version::new(16, 4, 0)));
+    assert_eq!(exact_version(&VersionReq::parse("16").unwrap()), None);
+    assert_eq!(
+      exact_version(&VersionReq::parse(">=15, <17").unwrap()),
+      None
+    );
+  }
+
+  #[test]
+  fn test_check_data_dir_version() {
+    let data_dir = std::env::temp_dir().join(format!("pg-embedded-meta-{}", uuid::Uuid::now_v7()));
+    fs::create_dir_all(&data_dir).unwrap();
+    assert!(check_data_dir_version(&data_dir, &Version::new(16, 4, 0)).is_ok());
+
+    fs::write(data_dir.join("PG_VERSION"), "15\n").unwrap();
+    record_version(&data_dir, &Version::new(15, 8, 0)).unwrap();
+    assert_eq!(recorded_version(&data_dir), Some(Version::new(15, 8, 0)));
+    assert!(check_data_dir_version(&data_dir, &Version::new(15, 10, 0)).is_ok());
+    let error = check_data_dir_version(&data_dir, &Version::new(16, 4, 0))
+      .unwrap_err()
+      .to_string();
+    assert!(error.starts_with("Data directory version mismatch:"));
+    assert!(error.contains("PostgreSQL 15 cluster (set up with 15.8.0), but PostgreSQL 16.4.0"));
+    assert!(error.contains("upgrade it"));
+    let error = check_data_dir_version(&data_dir, &Version::new(14, 13, 0)).unwrap_err();
+    assert!(!error.to_string().contains("upgrade it"));
+    fs::remove_dir_all(&data_dir).unwrap();
+  }
+}
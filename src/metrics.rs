@@ -0,0 +1,340 @@
+//! Background sampling of server resource usage (memory, CPU, connections)
+
+use crate::tools::{common::ConnectionConfig, psql::parse_csv, PsqlConfig, PsqlTool};
+use crate::types::InstanceState;
+use napi_derive::napi;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+
+/// Default interval between two samples
+const DEFAULT_SAMPLE_INTERVAL_MS: u32 = 5000;
+/// Default number of samples kept before the oldest are dropped
+const DEFAULT_HISTORY_CAPACITY: u32 = 720;
+
+/// Options for `startMetricsSampler()`
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct MetricsSamplerOptions {
+  /// Interval between two samples in milliseconds (default: 5000)
+  pub interval_ms: Option<u32>,
+  /// Number of samples kept; older samples are dropped (default: 720, one hour at the default interval)
+  pub capacity: Option<u32>,
+}
+
+/// Resource usage of the server at one point in time
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct MetricsSample {
+  /// Time the sample was taken, in milliseconds since the Unix epoch
+  pub timestamp: f64,
+  /// Resident memory of the postmaster and all its child processes, in bytes
+  pub rss_bytes: Option<i64>,
+  /// CPU time used by the server processes so far (user and system), in seconds
+  pub cpu_seconds: Option<f64>,
+  /// Number of server processes (postmaster, backends and auxiliary processes)
+  pub process_count: Option<u32>,
+  /// Number of client connections
+  pub connections: Option<u32>,
+}
+
+/// Memory and CPU usage summed over a process tree
+#[derive(Debug, Default, PartialEq)]
+struct ProcessStats {
+  rss_bytes: u64,
+  cpu_seconds: f64,
+  processes: u32,
+}
+
+/// A running sampler and the samples it has collected
+pub(crate) struct MetricsSampler {
+  history: Arc<Mutex<VecDeque<MetricsSample>>>,
+  task: Option<JoinHandle<()>>,
+}
+
+impl MetricsSampler {
+  pub fn new() -> Self {
+    Self {
+      history: Arc::new(Mutex::new(VecDeque::new())),
+      task: None,
+    }
+  }
+
+  /// Start sampling in the background, replacing a running sampler and its history
+  pub fn start(
+    &mut self,
+    options: MetricsSamplerOptions,
+    data_dir: PathBuf,
+    connection: ConnectionConfig,
+    program_dir: String,
+    state: Arc<Mutex<InstanceState>>,
+  ) {
+    self.stop();
+    let interval = Duration::from_millis(u64::from(
+      options
+        .interval_ms
+        .unwrap_or(DEFAULT_SAMPLE_INTERVAL_MS)
+        .max(1),
+    ));
+    let capacity = options.capacity.unwrap_or(DEFAULT_HISTORY_CAPACITY).max(1) as usize;
+    let history = Arc::new(Mutex::new(VecDeque::with_capacity(capacity)));
+    self.history = history.clone();
+
+    self.task = Some(napi::bindgen_prelude::spawn(async move {
+      let mut ticker = tokio::time::interval(interval);
+      loop {
+        ticker.tick().await;
+        let running = state
+          .lock()
+          .map(|state| *state == InstanceState::Running)
+          .unwrap_or(false);
+        if !running {
+          continue;
+        }
+        let sample = take_sample(&data_dir, &connection, &program_dir).await;
+        if let Ok(mut history) = history.lock() {
+          if history.len() == capacity {
+            history.pop_front();
+          }
+          history.push_back(sample);
+        }
+      }
+    }));
+  }
+
+  /// Stop sampling; the collected samples are kept
+  pub fn stop(&mut self) {
+    if let Some(task) = self.task.take() {
+      task.abort();
+    }
+  }
+
+  pub fn is_running(&self) -> bool {
+    self.task.as_ref().is_some_and(|task| !task.is_finished())
+  }
+
+  pub fn history(&self) -> Vec<MetricsSample> {
+    self
+      .history
+      .lock()
+      .map(|history| history.iter().cloned().collect())
+      .unwrap_or_default()
+  }
+}
+
+impl Drop for MetricsSampler {
+  fn drop(&mut self) {
+    self.stop();
+  }
+}
+
+async fn take_sample(
+  data_dir: &Path,
+  connection: &ConnectionConfig,
+  program_dir: &str,
+) -> MetricsSample {
+  let timestamp = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|elapsed| elapsed.as_secs_f64() * 1000.0)
+    .unwrap_or_default();
+  let data_dir = data_dir.to_path_buf();
+  let stats = tokio::task::spawn_blocking(move || server_process_stats(&data_dir))
+    .await
+    .ok()
+    .flatten();
+
+  MetricsSample {
+    timestamp,
+    rss_bytes: stats.as_ref().map(|stats| stats.rss_bytes as i64),
+    cpu_seconds: stats.as_ref().map(|stats| stats.cpu_seconds),
+    process_count: stats.as_ref().map(|stats| stats.processes),
+    connections: connection_count(connection, program_dir).await,
+  }
+}
+
+/// Number of client connections other than the sampler's own
+async fn connection_count(connection: &ConnectionConfig, program_dir: &str) -> Option<u32> {
+  let tool = PsqlTool::from_connection(
+    connection.clone(),
+    program_dir.to_string(),
+    PsqlConfig {
+      csv: Some(true),
+      tuples_only: Some(true),
+      no_psqlrc: Some(true),
+      ..Default::default()
+    },
+  );
+  let result = tool
+    .execute_command(
+      "SELECT count(*) FROM pg_stat_activity \
+       WHERE backend_type = 'client backend' AND pid <> pg_backend_pid()"
+        .to_string(),
+    )
+    .await
+    .ok()?;
+  if result.exit_code != 0 {
+    return None;
+  }
+  parse_csv(&result.stdout)
+    .first()
+    .and_then(|row| row.first())
+    .and_then(|count| count.parse().ok())
+}
+
+/// PID of the postmaster, from the first line of postmaster.pid
+#[cfg_attr(windows, allow(dead_code))]
+fn postmaster_pid(data_dir: &Path) -> Option<u32> {
+  std::fs::read_to_string(data_dir.join("postmaster.pid"))
+    .ok()?
+    .lines()
+    .next()?
+    .trim()
+    .parse()
+    .ok()
+}
+
+/// Memory and CPU usage of the postmaster and its children
+#[cfg(target_os = "linux")]
+fn server_process_stats(data_dir: &Path) -> Option<ProcessStats> {
+  // Kernel clock ticks per second, 100 on all mainstream architectures
+  const CLOCK_TICKS: f64 = 100.0;
+  let root = postmaster_pid(data_dir)?;
+  let mut stats = ProcessStats::default();
+  for entry in std::fs::read_dir("/proc").ok()?.flatten() {
+    let Some(pid) = entry
+      .file_name()
+      .to_str()
+      .and_then(|pid| pid.parse::<u32>().ok())
+    else {
+      continue;
+    };
+    let Some((ppid, ticks)) = std::fs::read_to_string(entry.path().join("stat"))
+      .ok()
+      .and_then(|stat| parse_proc_stat(&stat))
+    else {
+      continue;
+    };
+    if pid != root && ppid != root {
+      continue;
+    }
+    let rss_kb = std::fs::read_to_string(entry.path().join("status"))
+      .ok()
+      .and_then(|status| parse_vm_rss_kb(&status))
+      .unwrap_or(0);
+    stats.rss_bytes += rss_kb * 1024;
+    stats.cpu_seconds += ticks as f64 / CLOCK_TICKS;
+    stats.processes += 1;
+  }
+  (stats.processes > 0).then_some(stats)
+}
+
+/// Memory and CPU usage of the postmaster and its children
+#[cfg(all(unix, not(target_os = "linux")))]
+fn server_process_stats(data_dir: &Path) -> Option<ProcessStats> {
+  let root = postmaster_pid(data_dir)?;
+  let output = std::process::Command::new("ps")
+    .args(["-A", "-o", "pid=,ppid=,rss=,time="])
+    .output()
+    .ok()?;
+  let stats = parse_ps_output(&String::from_utf8_lossy(&output.stdout), root);
+  (stats.processes > 0).then_some(stats)
+}
+
+/// Process statistics are not collected on Windows
+#[cfg(windows)]
+fn server_process_stats(_data_dir: &Path) -> Option<ProcessStats> {
+  None
+}
+
+/// Parent PID and CPU ticks (user + system) from a /proc/<pid>/stat line
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_proc_stat(stat: &str) -> Option<(u32, u64)> {
+  // The command name may contain spaces and parentheses; fields follow the last ')'
+  let fields: Vec<&str> = stat[stat.rfind(')')? + 1..].split_whitespace().collect();
+  let ppid = fields.get(1)?.parse().ok()?;
+  let utime: u64 = fields.get(11)?.parse().ok()?;
+  let stime: u64 = fields.get(12)?.parse().ok()?;
+  Some((ppid, utime + stime))
+}
+
+/// Resident memory in kB from /proc/<pid>/status
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_vm_rss_kb(status: &str) -> Option<u64> {
+  status
+    .lines()
+    .find_map(|line| line.strip_prefix("VmRSS:"))?
+    .trim()
+    .trim_end_matches("kB")
+    .trim()
+    .parse()
+    .ok()
+}
+
+/// Sum `ps -o pid=,ppid=,rss=,time=` rows of `root` and its children
+#[cfg_attr(not(all(unix, not(target_os = "linux"))), allow(dead_code))]
+fn parse_ps_output(output: &str, root: u32) -> ProcessStats {
+  let mut stats = ProcessStats::default();
+  for line in output.lines() {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let [pid, ppid, rss, time] = fields[..] else {
+      continue;
+    };
+    let (Ok(pid), Ok(ppid)) = (pid.parse::<u32>(), ppid.parse::<u32>()) else {
+      continue;
+    };
+    if pid != root && ppid != root {
+      continue;
+    }
+    stats.rss_bytes += rss.parse::<u64>().unwrap_or(0) * 1024;
+    stats.cpu_seconds += parse_cpu_time(time).unwrap_or(0.0);
+    stats.processes += 1;
+  }
+  stats
+}
+
+/// Parse a `ps` CPU time such as `1-02:03:04`, `02:03:04` or `03:04.56` into seconds
+#[cfg_attr(not(all(unix, not(target_os = "linux"))), allow(dead_code))]
+fn parse_cpu_time(time: &str) -> Option<f64> {
+  let (days, rest) = match time.split_once('-') {
+    Some((days, rest)) => (days.parse::<f64>().ok()?, rest),
+    None => (0.0, time),
+  };
+  let mut seconds = 0.0;
+  for part in rest.split(':') {
+    seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+  }
+  Some(days * 86400.0 + seconds)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_proc_stat() {
+    let stat = "4242 (postgres: checkpointer (x)) S 4200 4200 4200 0 -1 4194368 \
+                100 0 0 0 250 50 0 0 20 0 1 0 12345 170000000 2000";
+    assert_eq!(parse_proc_stat(stat), Some((4200, 300)));
+    assert_eq!(
+      parse_vm_rss_kb("Name:\tpostgres\nVmRSS:\t   20480 kB\n"),
+      Some(20480)
+    );
+  }
+
+  #[test]
+  fn test_parse_ps_output() {
+    let output =
+      "  100     1  20000 00:01.50\n  101   100   1000 0:00.50\n  200     1  99999 1-00:00:00\n";
+    assert_eq!(
+      parse_ps_output(output, 100),
+      ProcessStats {
+        rss_bytes: 21000 * 1024,
+        cpu_seconds: 2.0,
+        processes: 2,
+      }
+    );
+    assert_eq!(parse_cpu_time("1-02:03:04"), Some(93784.0));
+  }
+}
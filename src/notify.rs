@@ -0,0 +1,183 @@
+//! LISTEN/NOTIFY subscriptions on a persistent native connection, for `listen()` and
+//! `unlisten()`
+//!
+//! A task owns the listening connection and waits for notifications and for changes to
+//! the subscriptions at the same time; notifications are passed to the callbacks of
+//! their channel.
+
+use crate::client::{connect_options, connect_timeout};
+use crate::error::{PgEmbedError, Result};
+use crate::logger::pg_log;
+use crate::tools::common::ConnectionConfig;
+use futures_util::future::{select, Either};
+use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi::Status;
+use napi_derive::napi;
+use sqlx::postgres::{PgListener, PgNotification, PgPoolOptions};
+use std::collections::HashMap;
+use std::pin::pin;
+use tokio::sync::{mpsc, oneshot};
+
+pub(crate) type NotificationCallback =
+  ThreadsafeFunction<Notification, (), Notification, Status, false, true>;
+
+/// A notification received on a channel listened to with `listen()`
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Notification {
+  /// Channel the notification was sent on
+  pub channel: String,
+  /// Payload of the notification (empty if none was given)
+  pub payload: String,
+  /// Process ID of the server backend that sent the notification
+  pub process_id: u32,
+}
+
+/// A change to the subscriptions of a listener
+enum ListenerCommand {
+  Listen {
+    channel: String,
+    callback: NotificationCallback,
+    done: oneshot::Sender<Result<()>>,
+  },
+  Unlisten {
+    channel: String,
+    done: oneshot::Sender<Result<bool>>,
+  },
+}
+
+/// What the listening task woke up for
+enum ListenerEvent {
+  Command(Option<ListenerCommand>),
+  Notification(std::result::Result<PgNotification, sqlx::Error>),
+}
+
+/// A persistent connection listening for notifications
+///
+/// Clones share the connection, which is closed once every clone is dropped.
+#[derive(Clone)]
+pub(crate) struct NotificationListener {
+  commands: mpsc::UnboundedSender<ListenerCommand>,
+}
+
+impl NotificationListener {
+  /// Open a listening connection with the settings of `config`
+  pub async fn connect(config: &ConnectionConfig) -> Result<Self> {
+    let connection_error = |e: sqlx::Error| PgEmbedError::ConnectionError(e.to_string());
+    // The pool only serves to re-establish the connection after it was lost
+    let mut pool = PgPoolOptions::new().max_connections(1);
+    if let Some(timeout) = connect_timeout(config)? {
+      pool = pool.acquire_timeout(timeout);
+    }
+    let pool = pool
+      .connect_with(connect_options(config)?)
+      .await
+      .map_err(connection_error)?;
+    let mut listener = PgListener::connect_with(&pool)
+      .await
+      .map_err(connection_error)?;
+    listener.ignore_pool_close_event(true);
+
+    let (commands, mut receiver) = mpsc::unbounded_channel();
+    napi::bindgen_prelude::spawn(async move {
+      let mut callbacks: HashMap<String, Vec<NotificationCallback>> = HashMap::new();
+      loop {
+        let event = {
+          let command = pin!(receiver.recv());
+          let notification = pin!(listener.recv());
+          match select(command, notification).await {
+            Either::Left((command, _)) => ListenerEvent::Command(command),
+            Either::Right((notification, _)) => ListenerEvent::Notification(notification),
+          }
+        };
+        match event {
+          // Every handle to the listener was dropped
+          ListenerEvent::Command(None) => break,
+          ListenerEvent::Command(Some(ListenerCommand::Listen {
+            channel,
+            callback,
+            done,
+          })) => {
+            let result = if callbacks.contains_key(&channel) {
+              Ok(())
+            } else {
+              listener
+                .listen(&channel)
+                .await
+                .map_err(|e| PgEmbedError::DatabaseError(e.to_string()))
+            };
+            if result.is_ok() {
+              callbacks.entry(channel).or_default().push(callback);
+            }
+            let _ = done.send(result);
+          }
+          ListenerEvent::Command(Some(ListenerCommand::Unlisten { channel, done })) => {
+            let result = if callbacks.remove(&channel).is_some() {
+              listener
+                .unlisten(&channel)
+                .await
+                .map(|()| true)
+                .map_err(|e| PgEmbedError::DatabaseError(e.to_string()))
+            } else {
+              Ok(false)
+            };
+            let _ = done.send(result);
+          }
+          ListenerEvent::Notification(Ok(notification)) => {
+            let Some(channel_callbacks) = callbacks.get(notification.channel()) else {
+              continue;
+            };
+            let notification = Notification {
+              channel: notification.channel().to_string(),
+              payload: notification.payload().to_string(),
+              process_id: notification.process_id(),
+            };
+            for callback in channel_callbacks {
+              callback.call(
+                notification.clone(),
+                ThreadsafeFunctionCallMode::NonBlocking,
+              );
+            }
+          }
+          ListenerEvent::Notification(Err(e)) => {
+            pg_log!(warn, "Stopped listening for notifications: {}", e);
+            break;
+          }
+        }
+      }
+      pool.close().await;
+    });
+    Ok(Self { commands })
+  }
+
+  /// Whether the listening task is still running
+  pub fn is_running(&self) -> bool {
+    !self.commands.is_closed()
+  }
+
+  /// Pass the notifications on `channel` to `callback` as well
+  pub async fn listen(&self, channel: String, callback: NotificationCallback) -> Result<()> {
+    let (done, result) = oneshot::channel();
+    self.send(ListenerCommand::Listen {
+      channel,
+      callback,
+      done,
+    })?;
+    result.await.map_err(|_| listener_stopped())?
+  }
+
+  /// Stop listening on `channel`; returns whether it was listened to
+  pub async fn unlisten(&self, channel: String) -> Result<bool> {
+    let (done, result) = oneshot::channel();
+    self.send(ListenerCommand::Unlisten { channel, done })?;
+    result.await.map_err(|_| listener_stopped())?
+  }
+
+  fn send(&self, command: ListenerCommand) -> Result<()> {
+    self.commands.send(command).map_err(|_| listener_stopped())
+  }
+}
+
+fn listener_stopped() -> PgEmbedError {
+  PgEmbedError::ConnectionError("The notification listener has stopped".to_string())
+}
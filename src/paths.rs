@@ -0,0 +1,156 @@
+//! Normalization of file system paths handed to PostgreSQL, the tools and the shell
+//!
+//! Windows paths come in several spellings: with forward or backward slashes, as UNC shares
+//! (`\\server\share\dir`) and in the extended-length form (`\\?\C:\dir`,
+//! `\\?\UNC\server\share\dir`) returned by `std::fs::canonicalize()`. The server, the
+//! PostgreSQL tools and cmd.exe only understand the plain forms, so paths passed to them go
+//! through `native_path()`. Paths the library reads and writes itself go through
+//! `extended_path()`, which lifts the MAX_PATH limit of the Windows file APIs.
+
+use std::path::{Path, PathBuf};
+
+/// Prefix of extended-length paths
+const VERBATIM_PREFIX: &str = r"\\?\";
+
+/// Prefix of extended-length paths of UNC shares
+const VERBATIM_UNC_PREFIX: &str = r"\\?\UNC\";
+
+/// Longest directory path the Windows file APIs accept without the extended-length prefix
+/// (MAX_PATH minus room for an 8.3 file name)
+const MAX_DIR_PATH: usize = 248;
+
+/// `path` in the plain form PostgreSQL, the tools and the shell understand
+pub(crate) fn native_path(path: impl AsRef<Path>) -> PathBuf {
+  PathBuf::from(native_path_for(
+    &path.as_ref().to_string_lossy(),
+    cfg!(windows),
+  ))
+}
+
+/// `path` in a form the file APIs accept whatever its length
+pub(crate) fn extended_path(path: impl AsRef<Path>) -> PathBuf {
+  PathBuf::from(extended_path_for(
+    &path.as_ref().to_string_lossy(),
+    cfg!(windows),
+  ))
+}
+
+/// Plain form of `path`: on Windows without the extended-length prefix and with backslash
+/// separators; on both platforms without duplicate or trailing separators
+pub(crate) fn native_path_for(path: &str, windows: bool) -> String {
+  if !windows {
+    return collapse_separators(path, '/', "");
+  }
+  let path = if let Some(share) = path.strip_prefix(VERBATIM_UNC_PREFIX) {
+    format!(r"\\{share}")
+  } else {
+    path
+      .strip_prefix(VERBATIM_PREFIX)
+      .unwrap_or(path)
+      .to_string()
+  };
+  let path = path.replace('/', "\\");
+  match path.strip_prefix(r"\\") {
+    Some(share) => collapse_separators(share, '\\', r"\\"),
+    None => collapse_separators(&path, '\\', ""),
+  }
+}
+
+/// Extended-length form of an absolute Windows `path` too long for the plain file APIs;
+/// other paths are returned in their plain form
+pub(crate) fn extended_path_for(path: &str, windows: bool) -> String {
+  let path = native_path_for(path, windows);
+  if !windows || path.len() < MAX_DIR_PATH {
+    return path;
+  }
+  if let Some(share) = path.strip_prefix(r"\\") {
+    format!("{VERBATIM_UNC_PREFIX}{share}")
+  } else if is_drive_absolute(&path) {
+    format!("{VERBATIM_PREFIX}{path}")
+  } else {
+    path
+  }
+}
+
+/// Join the components of `path` with single `separator`s after `prefix`, keeping a root
+/// such as `/` or `C:\`
+fn collapse_separators(path: &str, separator: char, prefix: &str) -> String {
+  let components: Vec<&str> = path.split(separator).filter(|c| !c.is_empty()).collect();
+  let rooted = prefix.is_empty() && path.starts_with(separator);
+  let mut collapsed = String::from(prefix);
+  if rooted {
+    collapsed.push(separator);
+  }
+  collapsed.push_str(&components.join(&separator.to_string()));
+  // A bare drive needs its separator to stay the root of the drive
+  if components.len() == 1 && is_drive(components[0]) && path.len() > 2 {
+    collapsed.push(separator);
+  }
+  collapsed
+}
+
+fn is_drive(component: &str) -> bool {
+  let bytes = component.as_bytes();
+  bytes.len() == 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+}
+
+fn is_drive_absolute(path: &str) -> bool {
+  path.len() >= 3 && is_drive(&path[..2]) && path.as_bytes()[2] == b'\\'
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_native_path_for_windows() {
+    assert_eq!(
+      native_path_for("C:/Program Files/pg//data/", true),
+      r"C:\Program Files\pg\data"
+    );
+    assert_eq!(
+      native_path_for(r"\\?\C:\Users\dev\data", true),
+      r"C:\Users\dev\data"
+    );
+    assert_eq!(
+      native_path_for(r"\\?\UNC\server\share\pg data", true),
+      r"\\server\share\pg data"
+    );
+    assert_eq!(
+      native_path_for("//server/share/pg/", true),
+      r"\\server\share\pg"
+    );
+    assert_eq!(native_path_for("C:/", true), r"C:\");
+    assert_eq!(native_path_for(r"data\pg", true), r"data\pg");
+  }
+
+  #[test]
+  fn test_native_path_for_unix() {
+    assert_eq!(
+      native_path_for("/var/lib//pg data/", false),
+      "/var/lib/pg data"
+    );
+    assert_eq!(native_path_for("/", false), "/");
+    assert_eq!(native_path_for("data/pg", false), "data/pg");
+    assert_eq!(native_path_for("", false), "");
+  }
+
+  #[test]
+  fn test_extended_path_for() {
+    let long = format!(r"C:\{}", "nested\\".repeat(40));
+    let extended = extended_path_for(&long, true);
+    assert!(extended.starts_with(r"\\?\C:\nested"));
+    assert!(!extended.ends_with('\\'));
+
+    let share = format!(r"\\server\share\{}", "d".repeat(250));
+    assert!(extended_path_for(&share, true).starts_with(r"\\?\UNC\server\share\ddd"));
+
+    assert_eq!(extended_path_for("C:/pg/data", true), r"C:\pg\data");
+    assert_eq!(
+      extended_path_for(&"a/".repeat(200), true),
+      "a\\".repeat(200).trim_end_matches('\\')
+    );
+    let unix = format!("/{}", "d".repeat(300));
+    assert_eq!(extended_path_for(&unix, false), unix);
+  }
+}
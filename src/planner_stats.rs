@@ -0,0 +1,126 @@
+//! Planner statistics: ANALYZE with a fixed statistics target, export and import
+
+use crate::error::{PgEmbedError, Result};
+use crate::sql::quote_ident;
+
+/// First server version with pg_restore_relation_stats() and pg_restore_attribute_stats()
+pub(crate) const RESTORE_STATS_VERSION: u32 = 180000;
+
+/// Default statistics target of the server, used when none is given
+const DEFAULT_STATISTICS_TARGET: u32 = 100;
+/// Largest statistics target the server accepts
+const MAX_STATISTICS_TARGET: u32 = 10000;
+
+/// Statements restoring the size statistics of every user table, index and materialized view
+pub(crate) const EXPORT_RELATION_STATS_SQL: &str = "SELECT format(\
+     'SELECT pg_catalog.pg_restore_relation_stats(''schemaname'', %L, ''relname'', %L, \
+     ''relpages'', %s::integer, ''reltuples'', %s::real, ''relallvisible'', %s::integer);', \
+     n.nspname, c.relname, c.relpages, c.reltuples, c.relallvisible) \
+   FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace \
+   WHERE c.relkind IN ('r', 'p', 'm', 'i') \
+   AND n.nspname NOT IN ('pg_catalog', 'information_schema') AND n.nspname !~ '^pg_toast' \
+   ORDER BY n.nspname, c.relname";
+
+/// Statements restoring the column statistics of every user table; statistics that are
+/// NULL are left out, as the restore functions expect
+pub(crate) const EXPORT_ATTRIBUTE_STATS_SQL: &str = "SELECT format(\
+     'SELECT pg_catalog.pg_restore_attribute_stats(%s);', array_to_string(ARRAY[\
+     '''schemaname'', ' || quote_literal(s.schemaname), \
+     '''relname'', ' || quote_literal(s.tablename), \
+     '''attname'', ' || quote_literal(s.attname), \
+     '''inherited'', ' || s.inherited, \
+     '''null_frac'', ' || s.null_frac || '::real', \
+     '''avg_width'', ' || s.avg_width || '::integer', \
+     '''n_distinct'', ' || s.n_distinct || '::real', \
+     '''most_common_vals'', ' || quote_literal(s.most_common_vals::text), \
+     '''most_common_freqs'', ' || quote_literal(s.most_common_freqs::text) || '::real[]', \
+     '''histogram_bounds'', ' || quote_literal(s.histogram_bounds::text), \
+     '''correlation'', ' || s.correlation || '::real', \
+     '''most_common_elems'', ' || quote_literal(s.most_common_elems::text), \
+     '''most_common_elem_freqs'', ' || quote_literal(s.most_common_elem_freqs::text) || '::real[]', \
+     '''elem_count_histogram'', ' || quote_literal(s.elem_count_histogram::text) || '::real[]', \
+     '''range_length_histogram'', ' || quote_literal(s.range_length_histogram::text), \
+     '''range_empty_frac'', ' || s.range_empty_frac || '::real', \
+     '''range_bounds_histogram'', ' || quote_literal(s.range_bounds_histogram::text)\
+     ], ', ')) \
+   FROM pg_stats s \
+   WHERE s.schemaname NOT IN ('pg_catalog', 'information_schema') \
+   ORDER BY s.schemaname, s.tablename, s.attname, s.inherited";
+
+/// `ANALYZE` of `tables` (or the whole database) with `target` as the default statistics
+/// target, run in one session
+///
+/// The parts of a schema-qualified name are quoted one by one, so `app.orders` becomes
+/// `"app"."orders"`.
+pub(crate) fn analyze_sql(target: Option<u32>, tables: &[String]) -> Result<String> {
+  let target = target.unwrap_or(DEFAULT_STATISTICS_TARGET);
+  if !(1..=MAX_STATISTICS_TARGET).contains(&target) {
+    return Err(PgEmbedError::ConfigurationError(format!(
+      "Statistics target {target} is out of range, expected 1 to {MAX_STATISTICS_TARGET}"
+    )));
+  }
+  let mut sql = format!("SET default_statistics_target = {target}; ANALYZE");
+  if !tables.is_empty() {
+    sql.push(' ');
+    let tables: Vec<String> = tables
+      .iter()
+      .map(|table| {
+        table
+          .split('.')
+          .map(quote_ident)
+          .collect::<Vec<_>>()
+          .join(".")
+      })
+      .collect();
+    sql.push_str(&tables.join(", "));
+  }
+  Ok(sql)
+}
+
+/// The exported statistics script: a header followed by one statement per line
+pub(crate) fn statistics_script(
+  database: &str,
+  server_version: &str,
+  statements: &[String],
+) -> String {
+  let mut script = format!(
+    "-- Planner statistics of database {database}, exported from PostgreSQL {server_version}\n"
+  );
+  for statement in statements {
+    script.push_str(statement);
+    script.push('\n');
+  }
+  script
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_analyze_sql() {
+    assert_eq!(
+      analyze_sql(None, &[]).unwrap(),
+      "SET default_statistics_target = 100; ANALYZE"
+    );
+    assert_eq!(
+      analyze_sql(Some(500), &["users".to_string(), "app.orders".to_string()]).unwrap(),
+      "SET default_statistics_target = 500; ANALYZE \"users\", \"app\".\"orders\""
+    );
+    assert_eq!(
+      analyze_sql(None, &["users; DROP TABLE users".to_string()]).unwrap(),
+      "SET default_statistics_target = 100; ANALYZE \"users; DROP TABLE users\""
+    );
+    assert!(analyze_sql(Some(0), &[]).is_err());
+    assert!(analyze_sql(Some(10001), &[]).is_err());
+  }
+
+  #[test]
+  fn test_statistics_script() {
+    let statements = vec!["SELECT 1;".to_string(), "SELECT 2;".to_string()];
+    assert_eq!(
+      statistics_script("app", "18.0", &statements),
+      "-- Planner statistics of database app, exported from PostgreSQL 18.0\nSELECT 1;\nSELECT 2;\n"
+    );
+  }
+}
@@ -0,0 +1,313 @@
+//! Query plan snapshots and their comparison
+
+use crate::sql::quote_literal;
+use napi_derive::napi;
+use std::collections::HashMap;
+
+/// A node of a query plan, in depth-first order
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlanNode {
+  /// Nesting level below the top node, which is at 0
+  pub depth: u32,
+  /// Node type, e.g. "Seq Scan" or "Index Only Scan"
+  pub node_type: String,
+  /// Scanned relation, for scan nodes
+  pub relation_name: Option<String>,
+  /// Used index, for index scans
+  pub index_name: Option<String>,
+}
+
+/// The plan of one query in a snapshot
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct QueryPlan {
+  /// The query as passed to `snapshotPlans()`
+  pub query: String,
+  /// Output of `EXPLAIN (FORMAT JSON)`
+  pub plan: String,
+  /// Estimated total cost of the top node
+  pub total_cost: f64,
+  /// The plan's nodes, in depth-first order
+  pub nodes: Vec<PlanNode>,
+}
+
+/// Plans of a set of queries, as recorded by `snapshotPlans()`
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct PlanSnapshot {
+  /// Database the queries were planned in
+  pub database_name: String,
+  /// Time of the snapshot, in milliseconds since the Unix epoch
+  pub taken_at: f64,
+  /// One plan per query, in query order
+  pub plans: Vec<QueryPlan>,
+}
+
+/// A query whose plan differs between two snapshots
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlanDifference {
+  /// The query
+  pub query: String,
+  /// "changed", or "added" / "removed" for a query only the second / first snapshot has
+  pub kind: String,
+  /// Changed plan lines, e.g. "- Seq Scan on users" and "+ Index Scan using users_email_idx on users"
+  pub changes: Vec<String>,
+  /// Estimated total cost in the first snapshot
+  pub cost_before: Option<f64>,
+  /// Estimated total cost in the second snapshot
+  pub cost_after: Option<f64>,
+}
+
+/// `EXPLAIN (FORMAT JSON)` of `query`, without its terminating semicolon
+pub(crate) fn explain_sql(query: &str) -> String {
+  format!(
+    "EXPLAIN (FORMAT JSON) {}",
+    query.trim().trim_end_matches(';').trim_end()
+  )
+}
+
+/// Query listing the nodes of an `EXPLAIN (FORMAT JSON)` plan in depth-first order,
+/// with the total cost of the top node on every row
+pub(crate) fn plan_nodes_sql(plan: &str) -> String {
+  format!(
+    "WITH RECURSIVE nodes(path, depth, node) AS (\
+       SELECT ARRAY[]::int[], 0, ({plan}::jsonb)->0->'Plan' \
+       UNION ALL \
+       SELECT n.path || c.ord::int, n.depth + 1, c.child \
+       FROM nodes n, jsonb_array_elements(n.node->'Plans') WITH ORDINALITY AS c(child, ord)\
+     ) \
+     SELECT depth, node->>'Node Type', coalesce(node->>'Relation Name', ''), \
+       coalesce(node->>'Index Name', ''), ({plan}::jsonb)->0->'Plan'->>'Total Cost' \
+     FROM nodes ORDER BY path",
+    plan = quote_literal(plan)
+  )
+}
+
+/// Parse the rows of `plan_nodes_sql`: the nodes and the plan's total cost
+pub(crate) fn parse_plan_nodes(rows: &[Vec<String>]) -> Option<(Vec<PlanNode>, f64)> {
+  let mut total_cost = 0.0;
+  let mut nodes = Vec::with_capacity(rows.len());
+  for row in rows {
+    let [depth, node_type, relation_name, index_name, cost] = row.as_slice() else {
+      return None;
+    };
+    total_cost = cost.parse().ok()?;
+    nodes.push(PlanNode {
+      depth: depth.parse().ok()?,
+      node_type: node_type.clone(),
+      relation_name: Some(relation_name.clone()).filter(|name| !name.is_empty()),
+      index_name: Some(index_name.clone()).filter(|name| !name.is_empty()),
+    });
+  }
+  Some((nodes, total_cost))
+}
+
+/// One line describing a node, indented by its depth, e.g. "  Index Scan using users_pkey on users"
+fn node_line(node: &PlanNode) -> String {
+  let mut line = format!("{}{}", "  ".repeat(node.depth as usize), node.node_type);
+  if let Some(index) = &node.index_name {
+    line.push_str(&format!(" using {index}"));
+  }
+  if let Some(relation) = &node.relation_name {
+    line.push_str(&format!(" on {relation}"));
+  }
+  line
+}
+
+/// Line diff of two plans, with "- " for removed and "+ " for added lines
+fn diff_lines(before: &[String], after: &[String]) -> Vec<String> {
+  // Longest common subsequence table, filled from the end
+  let mut lcs = vec![vec![0usize; after.len() + 1]; before.len() + 1];
+  for i in (0..before.len()).rev() {
+    for j in (0..after.len()).rev() {
+      lcs[i][j] = if before[i] == after[j] {
+        lcs[i + 1][j + 1] + 1
+      } else {
+        lcs[i + 1][j].max(lcs[i][j + 1])
+      };
+    }
+  }
+  let (mut i, mut j) = (0, 0);
+  let mut changes = Vec::new();
+  while i < before.len() || j < after.len() {
+    if i < before.len() && j < after.len() && before[i] == after[j] {
+      i += 1;
+      j += 1;
+    } else if j == after.len() || (i < before.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+      changes.push(format!("- {}", before[i]));
+      i += 1;
+    } else {
+      changes.push(format!("+ {}", after[j]));
+      j += 1;
+    }
+  }
+  changes
+}
+
+fn plan_lines(plan: &QueryPlan) -> Vec<String> {
+  plan.nodes.iter().map(node_line).collect()
+}
+
+/// Compares two plan snapshots and lists the queries whose plans differ
+///
+/// Plans are compared by their node types, scanned relations and used indexes, so a
+/// plan only counts as changed when its shape changes; cost estimates are reported but
+/// not compared. Queries are matched by their text.
+///
+/// @param snapshotA - The earlier snapshot, e.g. from before an index or statistics change
+/// @param snapshotB - The later snapshot
+/// @returns The queries whose plans differ, in the order of the snapshots
+///
+/// @example
+/// ```typescript
+/// const before = await instance.snapshotPlans('app', ["SELECT * FROM users WHERE email = 'a@b.c'"]);
+/// await instance.executeSql('CREATE INDEX users_email_idx ON users (email); ANALYZE users;', {}, 'app');
+/// const after = await instance.snapshotPlans('app', ["SELECT * FROM users WHERE email = 'a@b.c'"]);
+/// for (const difference of comparePlans(before, after)) {
+///   console.log(difference.query, difference.changes.join('\n'));
+/// }
+/// ```
+#[napi]
+pub fn compare_plans(snapshot_a: PlanSnapshot, snapshot_b: PlanSnapshot) -> Vec<PlanDifference> {
+  let after: HashMap<&str, &QueryPlan> = snapshot_b
+    .plans
+    .iter()
+    .map(|plan| (plan.query.as_str(), plan))
+    .collect();
+  let before: HashMap<&str, &QueryPlan> = snapshot_a
+    .plans
+    .iter()
+    .map(|plan| (plan.query.as_str(), plan))
+    .collect();
+
+  let mut differences = Vec::new();
+  for plan in &snapshot_a.plans {
+    match after.get(plan.query.as_str()) {
+      Some(other) => {
+        let changes = diff_lines(&plan_lines(plan), &plan_lines(other));
+        if !changes.is_empty() {
+          differences.push(PlanDifference {
+            query: plan.query.clone(),
+            kind: "changed".to_string(),
+            changes,
+            cost_before: Some(plan.total_cost),
+            cost_after: Some(other.total_cost),
+          });
+        }
+      }
+      None => differences.push(PlanDifference {
+        query: plan.query.clone(),
+        kind: "removed".to_string(),
+        changes: diff_lines(&plan_lines(plan), &[]),
+        cost_before: Some(plan.total_cost),
+        cost_after: None,
+      }),
+    }
+  }
+  for plan in &snapshot_b.plans {
+    if !before.contains_key(plan.query.as_str()) {
+      differences.push(PlanDifference {
+        query: plan.query.clone(),
+        kind: "added".to_string(),
+        changes: diff_lines(&[], &plan_lines(plan)),
+        cost_before: None,
+        cost_after: Some(plan.total_cost),
+      });
+    }
+  }
+  differences
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn node(depth: u32, node_type: &str, relation: Option<&str>, index: Option<&str>) -> PlanNode {
+    PlanNode {
+      depth,
+      node_type: node_type.to_string(),
+      relation_name: relation.map(str::to_string),
+      index_name: index.map(str::to_string),
+    }
+  }
+
+  fn snapshot(plans: Vec<(&str, Vec<PlanNode>)>) -> PlanSnapshot {
+    PlanSnapshot {
+      database_name: "postgres".to_string(),
+      taken_at: 0.0,
+      plans: plans
+        .into_iter()
+        .map(|(query, nodes)| QueryPlan {
+          query: query.to_string(),
+          plan: String::new(),
+          total_cost: 1.0,
+          nodes,
+        })
+        .collect(),
+    }
+  }
+
+  #[test]
+  fn test_explain_sql() {
+    assert_eq!(
+      explain_sql(" SELECT 1; \n"),
+      "EXPLAIN (FORMAT JSON) SELECT 1"
+    );
+  }
+
+  #[test]
+  fn test_parse_plan_nodes() {
+    let row = |values: [&str; 5]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+    let rows = vec![
+      row(["0", "Hash Join", "", "", "35.5"]),
+      row(["1", "Seq Scan", "orders", "", "35.5"]),
+      row(["1", "Hash", "", "", "35.5"]),
+      row(["2", "Index Scan", "users", "users_pkey", "35.5"]),
+    ];
+    let (nodes, cost) = parse_plan_nodes(&rows).unwrap();
+    assert_eq!(cost, 35.5);
+    assert_eq!(nodes[1], node(1, "Seq Scan", Some("orders"), None));
+    assert_eq!(
+      node_line(&nodes[3]),
+      "    Index Scan using users_pkey on users"
+    );
+    assert_eq!(parse_plan_nodes(&[vec!["0".to_string()]]), None);
+  }
+
+  #[test]
+  fn test_compare_plans() {
+    let query = "SELECT * FROM users WHERE email = 'a'";
+    let before = snapshot(vec![
+      (query, vec![node(0, "Seq Scan", Some("users"), None)]),
+      ("SELECT 1", vec![node(0, "Result", None, None)]),
+    ]);
+    let after = snapshot(vec![
+      (
+        query,
+        vec![node(
+          0,
+          "Index Scan",
+          Some("users"),
+          Some("users_email_idx"),
+        )],
+      ),
+      ("SELECT 1", vec![node(0, "Result", None, None)]),
+      ("SELECT 2", vec![node(0, "Result", None, None)]),
+    ]);
+
+    let differences = compare_plans(before, after);
+    assert_eq!(differences.len(), 2);
+    assert_eq!(differences[0].kind, "changed");
+    assert_eq!(
+      differences[0].changes,
+      [
+        "- Seq Scan on users",
+        "+ Index Scan using users_email_idx on users"
+      ]
+    );
+    assert_eq!(differences[1].kind, "added");
+    assert_eq!(differences[1].query, "SELECT 2");
+  }
+}
@@ -1,18 +1,90 @@
 use crate::{
+  advisor::{self, IndexAdvice, SuggestIndexesOptions},
+  archive::{absolute_archive_dir, archive_command_for},
+  backup_catalog::{self, BackupEntry, BackupPrunePolicy},
+  backup_verify::{self, BackupVerification, VerifyBackupOptions},
+  benchmark::{self, PgbenchOptions, PgbenchResult},
+  checksum::{self, TableChecksum},
+  client::{self, Client, QueryResult, SessionOptions, SqlValue, Transaction},
+  conf::{effective_value, managed_conf, validate_setting_name},
+  conninfo::format_conninfo,
+  copy_in::{CopyIntoOptions, CopyWriter},
+  copy_out::{CopyOutOptions, CopyReader},
+  ddl::{DdlCapture, DdlCommand},
+  disk::{self, DiskUsage, DiskUsageWarningCallback, DiskUsageWatchOptions, DiskWatcher},
   error::{
-    convert_postgresql_error, database_error, setup_error, start_error, stop_error, timeout_error,
+    configuration_error, convert_postgresql_error, database_error, setup_error, start_error,
+    stop_error, timeout_error,
   },
-  logger::pg_log,
-  settings::PostgresSettings,
-  tools::common::ConnectionConfig,
-  types::{ConnectionInfo, InstanceState},
-  PgBasebackupConfig, PgBasebackupTool, PgDumpConfig, PgDumpTool, PgDumpallConfig, PgDumpallTool,
-  PgRestoreConfig, PgRestoreTool, PgRewindConfig, PgRewindTool, PsqlConfig, PsqlTool, ToolResult,
+  hba::{self, HbaRule, HbaRuleMatcher},
+  health,
+  link::{self, LinkInstancesOptions},
+  logger::{pg_log, QuietGuard},
+  metadata,
+  metrics::{MetricsSample, MetricsSampler, MetricsSamplerOptions},
+  notify::{NotificationCallback, NotificationListener},
+  paths::{extended_path, native_path},
+  planner_stats,
+  plans::{self, PlanSnapshot, QueryPlan},
+  preflight::{self, PreflightReport, PreflightSeverity},
+  privileges,
+  profile::{collect_sql_files, DatabaseProfile},
+  query_log::{QueryLogEvent, QueryLogOptions, QueryLogger},
+  redact::redact,
+  registry::{self, InstanceRecord},
+  replica::{self, ReplicaOptions},
+  roles::{self, CreateUserOptions, UserInfo},
+  script::{split_script, ExecuteSqlBatchOptions, SqlStatementResult},
+  server_env,
+  settings::{
+    hba_rules_with_method, hba_with_remote_access, PasswordEncryption, PostgresSettings,
+    LOGIN_EVENT_TRIGGER_VERSION, REPRODUCIBLE_SEED_SQL, REPRODUCIBLE_UNSEED_SQL,
+  },
+  sql::{quote_ident, quote_literal, quote_psql_arg},
+  stats::{self, CheckpointStats, WalStats},
+  tenant::{TenantManager, TenantManagerOptions},
+  testdata::{self, GenerateTestDataOptions},
+  tools::{
+    bin_dir::BinDir,
+    common::{check_executable, ConnectionConfig},
+    pg_restore::{count_toc_objects, object_counts_sql, VERIFIED_OBJECT_TYPES},
+    pipeline::{run_pipeline, PipeDatabaseOptions, PipelineResult, PipelineStage},
+    psql::parse_csv,
+    stream::StreamTransform,
+  },
+  truncate::{self, PlannedTable, TruncateTablesOptions},
+  types::{
+    CollationVersionMismatch, ConnectionInfo, DatabaseSqlResult, ExecuteOnAllDatabasesOptions,
+    FailurePhase, InstanceFailure, InstanceState, LockWait, PostLoadOptimizeOptions,
+    PreparedTransaction, RecoveryStatus, RestoreIntoNewDatabaseOptions,
+    RestoreIntoNewDatabaseResult, RestoredObjectCount, SchemaInfo, ServerRole, StructuredSqlResult,
+  },
+  undo::{self, UndoAction, UndoPoint, UndoneOperation},
+  wal::{self, WalFile},
+  BuildConfig, PgBasebackupCheckpoint, PgBasebackupConfig, PgBasebackupTool, PgBasebackupWalMethod,
+  PgConfigOptions, PgConfigTool, PgDumpConfig, PgDumpFormat, PgDumpTool, PgDumpallConfig,
+  PgDumpallTool, PgRestoreConfig, PgRestoreFormat, PgRestoreTool, PgRewindConfig, PgRewindTool,
+  PsqlConfig, PsqlTool, ToolResult,
 };
+use napi::bindgen_prelude::{Buffer, Either, Promise};
+use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi::Status;
 use napi_derive::napi;
+use postgresql_commands::{initdb::InitDbBuilder, CommandBuilder};
+use postgresql_embedded::VersionReq;
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
+/// Maintenance database that always exists in a fresh cluster
+const DEFAULT_DATABASE: &str = "postgres";
+
+/// How long promote() waits for the server to leave recovery by default
+const DEFAULT_PROMOTE_TIMEOUT_SECONDS: u32 = 60;
+
+/// Callback registered with `onPortResolved()`
+type PortResolvedCallback = ThreadsafeFunction<u16, (), u16, Status, false, true>;
+
 /// Connection information cache
 #[derive(Clone)]
 struct ConnectionInfoCache {
@@ -46,16 +118,70 @@ pub struct PostgresInstance {
   async_instance: Option<postgresql_embedded::PostgreSQL>,
   /// Configuration settings
   settings: postgresql_embedded::Settings,
+  /// Directory of the PostgreSQL executables, resolved once the instance is set up
+  bin_dir: Option<BinDir>,
+  /// Default database used for connections, created on start if missing
+  database_name: String,
+  /// Whether clients connect without a password (trust authentication)
+  trust_auth: bool,
+  /// Password hashing method enforced for role passwords, if configured
+  password_encryption: Option<PasswordEncryption>,
+  /// Whether pg_hba.conf admits clients from other machines
+  expose_externally: bool,
+  /// Whether start(false) runs setup for an uninitialized data directory
+  auto_setup: bool,
+  /// Directory WAL segments are archived to, if archiving is enabled
+  wal_archive_dir: Option<String>,
+  /// ICU locale a new cluster is initialized with
+  icu_locale: Option<String>,
+  /// Labels identifying the instance in the registry
+  labels: HashMap<String, String>,
+  /// application_name of the connections the instance opens itself, if any
+  application_name: Option<String>,
+  /// Profile this instance was created from, if any
+  profile: Option<DatabaseProfile>,
+  /// Whether the profile still has to be applied to a freshly initialized cluster
+  provision_pending: bool,
   /// Instance state
   state: Arc<Mutex<InstanceState>>,
+  /// Replication role the server was started in or promoted to
+  role: Arc<Mutex<ServerRole>>,
   /// Instance ID for tracking and debugging
   instance_id: String,
+  /// Human-readable instance name, if configured
+  name: Option<String>,
   /// Connection information cache
   connection_cache: Arc<Mutex<Option<ConnectionInfoCache>>>,
+  /// Lifetime of cached connection information (zero disables the cache)
+  connection_cache_ttl: Duration,
   /// Configuration hash for caching key
   config_hash: String,
   /// Startup time recording
   startup_time: Arc<Mutex<Option<Duration>>>,
+  /// Most recent setup, start or stop failure
+  last_error: Arc<Mutex<Option<InstanceFailure>>>,
+  /// Background resource sampler and its history
+  metrics_sampler: Mutex<MetricsSampler>,
+  /// Background disk usage watch
+  disk_watcher: Mutex<DiskWatcher>,
+  /// Hook receiving the queries run on native connections, if set
+  query_logger: Mutex<Option<QueryLogger>>,
+  /// Callbacks registered with `onPortResolved()`
+  port_listeners: Mutex<Vec<PortResolvedCallback>>,
+  /// Connection listening for the notifications of `listen()`, opened on first use
+  notification_listener: Mutex<Option<NotificationListener>>,
+  /// Whether undo points are taken before destructive operations
+  safe_mode: bool,
+  /// Undo points of destructive operations, most recent last
+  undo_points: Mutex<Vec<UndoPoint>>,
+  /// Whether sessions get a fixed `random()` seed (the `reproducible` preset)
+  reproducible: bool,
+  /// Environment variables added to the server process
+  server_env: HashMap<String, String>,
+  /// Replication slot on the upstream this replica streams through, dropped on cleanup
+  upstream_slot: Option<replica::UpstreamSlot>,
+  /// Keeps logging quiet until cleanup for an instance created with `quiet: true`
+  quiet: Option<QuietGuard>,
   /// Flag to track if cleanup has been called explicitly
   cleaned_up: bool,
 }
@@ -63,6 +189,7 @@ pub struct PostgresInstance {
 impl Drop for PostgresInstance {
   fn drop(&mut self) {
     // If cleanup was already called, do nothing.
+    registry::unregister(&self.instance_id);
     if self.cleaned_up {
       return;
     }
@@ -70,15 +197,16 @@ impl Drop for PostgresInstance {
     pg_log!(
       info,
       "Dropping PostgresInstance {} - cleaning up resources",
-      self.instance_id
+      self.log_name()
     );
+    self.discard_undo_points();
 
     // Try to stop async instance
     if let Some(_instance) = self.async_instance.take() {
       pg_log!(
         debug,
         "Cleaning up async PostgreSQL instance for {}",
-        self.instance_id
+        self.log_name()
       );
       // Note: We can't use async in Drop, so we just log here
       // Actual cleanup will be handled by postgresql_embedded library's Drop implementation
@@ -92,7 +220,7 @@ impl Drop for PostgresInstance {
     pg_log!(
       info,
       "PostgresInstance {} cleanup completed",
-      self.instance_id
+      self.log_name()
     );
   }
 }
@@ -116,43 +244,222 @@ impl PostgresInstance {
   #[napi(constructor)]
   pub fn new(settings: Option<PostgresSettings>) -> napi::Result<Self> {
     let postgres_settings = settings.unwrap_or_default();
+    // Quiet before anything is logged; a failed constructor releases the guard again
+    let quiet = (postgres_settings.quiet == Some(true)).then(QuietGuard::new);
     let embedded_settings = postgres_settings.to_embedded_settings()?;
+    let database_name = postgres_settings
+      .database_name
+      .clone()
+      .unwrap_or_else(|| DEFAULT_DATABASE.to_string());
     let ts = uuid::Timestamp::now(uuid::NoContext);
     let instance_id = uuid::Uuid::new_v7(ts).to_string();
 
     // Generate configuration hash for caching
-    let config_hash = Self::generate_config_hash(&embedded_settings);
+    let connection_cache_ttl = postgres_settings.connection_cache_ttl();
+    let config_hash = postgres_settings.config_hash(&embedded_settings);
 
+    let name = postgres_settings.name.clone();
     pg_log!(
       info,
-      "Creating new PostgresInstance with ID: {} (config hash: {})",
+      "Creating new PostgresInstance {}with ID: {} (config hash: {})",
+      name
+        .as_ref()
+        .map(|name| format!("'{name}' "))
+        .unwrap_or_default(),
       instance_id,
       config_hash
     );
 
+    let labels = postgres_settings.labels.clone().unwrap_or_default();
+    let state = Arc::new(Mutex::new(InstanceState::Stopped));
+    registry::register(
+      &instance_id,
+      InstanceRecord {
+        name: name.clone(),
+        labels: labels.clone(),
+        state: state.clone(),
+        host: embedded_settings.host.clone(),
+        port: embedded_settings.port,
+        database_name: database_name.clone(),
+        data_dir: embedded_settings.data_dir.to_string_lossy().to_string(),
+      },
+    );
+
     Ok(Self {
       async_instance: None,
+      bin_dir: None,
       settings: embedded_settings,
-      state: Arc::new(Mutex::new(InstanceState::Stopped)),
+      database_name,
+      trust_auth: postgres_settings.is_trust_auth(),
+      password_encryption: postgres_settings.password_encryption,
+      expose_externally: postgres_settings.is_exposed_externally(),
+      auto_setup: postgres_settings.auto_setup.unwrap_or(false),
+      wal_archive_dir: postgres_settings.wal_archive_dir.clone(),
+      icu_locale: postgres_settings.icu_locale.clone(),
+      labels,
+      application_name: match postgres_settings.application_name {
+        Some(ref application_name) if application_name.is_empty() => None,
+        Some(ref application_name) => Some(application_name.clone()),
+        None => Some(format!("pg-embedded/{instance_id}")),
+      },
+      profile: None,
+      provision_pending: false,
+      state,
+      role: Arc::new(Mutex::new(ServerRole::Primary)),
       instance_id,
+      name,
       connection_cache: Arc::new(Mutex::new(None)),
+      connection_cache_ttl,
       config_hash,
       startup_time: Arc::new(Mutex::new(None)),
+      last_error: Arc::new(Mutex::new(None)),
+      metrics_sampler: Mutex::new(MetricsSampler::new()),
+      disk_watcher: Mutex::new(DiskWatcher::new()),
+      query_logger: Mutex::new(None),
+      port_listeners: Mutex::new(Vec::new()),
+      notification_listener: Mutex::new(None),
+      safe_mode: postgres_settings.safe_mode.unwrap_or(false),
+      undo_points: Mutex::new(Vec::new()),
+      reproducible: postgres_settings.reproducible.unwrap_or(false),
+      server_env: postgres_settings.server_env.clone().unwrap_or_default(),
+      upstream_slot: None,
+      quiet,
       cleaned_up: false,
     })
   }
 
-  /// Generate configuration hash for caching
-  fn generate_config_hash(settings: &postgresql_embedded::Settings) -> String {
-    use std::collections::hash_map::DefaultHasher;
-    use std::hash::{Hash, Hasher};
+  /// Creates a new PostgreSQL instance from a database profile
+  ///
+  /// The profile's settings and server configuration are used to create the instance.
+  /// When a fresh cluster is started for the first time, the profile's extensions are
+  /// created and its migration and seed scripts are applied to the default database.
+  ///
+  /// @param profile - The profile describing the environment
+  /// @returns A new PostgresInstance
+  ///
+  /// @example
+  /// ```typescript
+  /// const instance = PostgresInstance.fromProfile({
+  ///   name: 'vector',
+  ///   settings: { databaseName: 'embeddings', port: 0 },
+  ///   extensions: ['vector'],
+  ///   migrationsDir: './migrations'
+  /// });
+  /// await instance.start();
+  /// ```
+  #[napi(factory)]
+  pub fn from_profile(profile: DatabaseProfile) -> napi::Result<Self> {
+    pg_log!(
+      info,
+      "Creating PostgresInstance from profile '{}'",
+      profile.name
+    );
+    let mut instance = Self::new(Some(profile.to_settings()))?;
+    instance.profile = Some(profile);
+    Ok(instance)
+  }
+
+  /// Creates and starts several instances from one settings template
+  ///
+  /// The instances share the installation directory of the template. A fixed port is
+  /// incremented per instance and must leave room for all of them below 65536; without
+  /// a port, or with port 0, every port is random. `dataDir`,
+  /// `walArchiveDir` and `name` get a `-<index>` suffix. The first instance installs the
+  /// binaries, then all instances are started concurrently. If any of them fails to
+  /// start, all are cleaned up and the error is returned.
+  ///
+  /// @param count - Number of instances to create (at least 1)
+  /// @param settings - Settings template shared by the instances
+  /// @returns The running instances, in index order
+  ///
+  /// @example
+  /// ```typescript
+  /// const shards = await PostgresInstance.createMany(3, { port: 0, name: 'shard' });
+  /// // shard-0, shard-1 and shard-2 are running on distinct random ports
+  /// await Promise.all(shards.map((shard) => shard.cleanup()));
+  /// ```
+  #[napi]
+  pub async fn create_many(
+    count: u32,
+    settings: Option<PostgresSettings>,
+  ) -> napi::Result<Vec<PostgresInstance>> {
+    if count == 0 {
+      return Err(configuration_error("count must be at least 1"));
+    }
+    // Without settings, the members get random ports like a template without a port
+    let template = settings.unwrap_or(PostgresSettings {
+      port: None,
+      ..Default::default()
+    });
+    if let Some(port) = template.port.filter(|port| *port != 0) {
+      if u64::from(port) + u64::from(count) - 1 > 65535 {
+        return Err(configuration_error(&format!(
+          "Port {port} leaves no room for {count} instances; the last port would exceed 65535"
+        )));
+      }
+    }
+    let mut instances = (0..count)
+      .map(|index| Self::new(Some(template.for_group_member(index))))
+      .collect::<napi::Result<Vec<_>>>()?;
+    pg_log!(info, "Starting a group of {} instances", count);
+
+    // Installing the shared binaries from several instances at once would race
+    let mut result = unsafe { instances[0].setup() }.await;
+    if result.is_ok() {
+      let starts = instances
+        .iter_mut()
+        .map(|instance| unsafe { instance.start(Some(true)) });
+      result = futures_util::future::join_all(starts)
+        .await
+        .into_iter()
+        .collect();
+    }
+    if let Err(e) = result {
+      for instance in instances.iter_mut() {
+        if let Err(cleanup_error) = unsafe { instance.cleanup() }.await {
+          pg_log!(
+            warn,
+            "Failed to clean up {} after a failed group start: {}",
+            instance.log_name(),
+            cleanup_error
+          );
+        }
+      }
+      return Err(e);
+    }
+    Ok(instances)
+  }
+
+  /// Gets the name of the profile this instance was created from
+  ///
+  /// @returns The profile name, or null if the instance was not created from a profile
+  #[napi(getter)]
+  pub fn get_profile_name(&self) -> Option<String> {
+    self.profile.as_ref().map(|profile| profile.name.clone())
+  }
+
+  /// Gets the labels assigned to this instance
+  ///
+  /// @returns The labels from the instance settings
+  #[napi(getter)]
+  pub fn get_labels(&self) -> HashMap<String, String> {
+    self.labels.clone()
+  }
+
+  /// Gets the environment variables added to the server process
+  ///
+  /// @returns The `serverEnv` from the instance settings
+  #[napi(getter)]
+  pub fn get_server_env(&self) -> HashMap<String, String> {
+    self.server_env.clone()
+  }
 
-    let mut hasher = DefaultHasher::new();
-    settings.port.hash(&mut hasher);
-    settings.username.hash(&mut hasher);
-    settings.password.hash(&mut hasher);
-    settings.host.hash(&mut hasher);
-    format!("{:x}", hasher.finish())
+  /// Gets the instance name
+  ///
+  /// @returns The name from the instance settings, or null if none was configured
+  #[napi(getter)]
+  pub fn get_name(&self) -> Option<String> {
+    self.name.clone()
   }
 
   /// Gets the unique instance ID
@@ -165,7 +472,10 @@ impl PostgresInstance {
 
   /// Gets the configuration hash for this instance
   ///
-  /// This hash is used internally for caching and can be useful for debugging.
+  /// The hash covers every setting that affects the cluster (see `computeConfigHash()`),
+  /// so it can be used as a cache key for shared fixtures or template databases. It is
+  /// computed from the requested settings: with `port: 0` it stays the same after the
+  /// server picks a random port.
   ///
   /// @returns A string hash of the instance configuration
   #[napi]
@@ -173,6 +483,41 @@ impl PostgresInstance {
     self.config_hash.clone()
   }
 
+  /// Gets the port the server listens on
+  ///
+  /// With `port: 0` this is 0 until `start()` resolves the random port; from then on it,
+  /// `connectionInfo` and every connection helper report the resolved port.
+  ///
+  /// @returns The port number
+  #[napi]
+  pub fn get_port(&self) -> u16 {
+    self.settings.port
+  }
+
+  /// Registers a callback receiving the port picked by `start()` when the instance was
+  /// created with `port: 0`
+  ///
+  /// The callback runs once per start that resolves a random port, after the getters
+  /// already report it. Instances created with a fixed port never call it.
+  ///
+  /// @param callback - Function receiving the resolved port
+  ///
+  /// @example
+  /// ```typescript
+  /// const instance = new PostgresInstance({ port: 0 });
+  /// instance.onPortResolved((port) => console.log(`PostgreSQL listens on ${port}`));
+  /// await instance.start();
+  /// ```
+  #[napi(ts_args_type = "callback: (port: number) => void")]
+  pub fn on_port_resolved(&self, callback: PortResolvedCallback) -> napi::Result<()> {
+    self
+      .port_listeners
+      .lock()
+      .map_err(|_| setup_error("Failed to acquire port listener lock"))?
+      .push(callback);
+    Ok(())
+  }
+
   /// Gets the directory where the PostgreSQL binaries are located.
   #[napi(getter)]
   pub fn get_program_dir(&self) -> napi::Result<String> {
@@ -191,6 +536,66 @@ impl PostgresInstance {
     }
   }
 
+  /// Directory of the PostgreSQL executables
+  fn bin_dir(&self) -> napi::Result<&BinDir> {
+    self
+      .bin_dir
+      .as_ref()
+      .ok_or_else(|| setup_error("PostgreSQL instance has not been initialized yet."))
+  }
+
+  /// The `programDir` for running `tool`, failing if the installation does not contain it
+  fn tool_dir(&self, tool: &str) -> napi::Result<String> {
+    Ok(self.bin_dir()?.program_dir_for(&[tool])?)
+  }
+
+  /// Path of the backup catalog of the installation
+  fn backup_catalog_path(&self) -> std::path::PathBuf {
+    backup_catalog::catalog_path(&self.settings.installation_dir)
+  }
+
+  /// The WAL position recorded with a backup that is about to start, if it is recorded
+  async fn backup_lsn(&self, recorded: bool) -> Option<String> {
+    if !recorded {
+      return None;
+    }
+    self.get_current_lsn().await.ok()
+  }
+
+  /// Record a backup at `path` in the backup catalog once its tool has succeeded
+  ///
+  /// Dry runs and failed runs are not recorded. A catalog that cannot be written, e.g. in
+  /// a read-only installation, only logs a warning, as the backup itself succeeded.
+  fn record_backup(
+    &self,
+    kind: &str,
+    path: &str,
+    database: Option<String>,
+    lsn: Option<String>,
+    result: &ToolResult,
+  ) {
+    if result.exit_code != 0 || result.raw_command.is_some() {
+      return;
+    }
+    let path = std::path::absolute(native_path(path))
+      .unwrap_or_else(|_| native_path(path))
+      .to_string_lossy()
+      .to_string();
+    let entry = BackupEntry {
+      id: uuid::Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext)).to_string(),
+      kind: kind.to_string(),
+      size_bytes: backup_catalog::backup_size(std::path::Path::new(&path)) as f64,
+      path,
+      database,
+      lsn,
+      created_at: backup_catalog::now_ms(),
+      data_dir: self.settings.data_dir.to_string_lossy().to_string(),
+    };
+    if let Err(e) = backup_catalog::record_backup(&self.backup_catalog_path(), entry) {
+      pg_log!(warn, "Backup not recorded in the catalog: {}", e);
+    }
+  }
+
   /// Gets the directory where the PostgreSQL data is stored.
   #[napi(getter)]
   pub fn get_data_dir(&self) -> napi::Result<String> {
@@ -206,30 +611,80 @@ impl PostgresInstance {
   /// # Safety
   /// Promotes a standby server to a primary server.
   ///
-  /// @returns Promise that resolves when the server is promoted.
-  /// @throws Error if promotion fails.
+  /// After signalling the promotion, this waits until the server has left recovery
+  /// (`pg_is_in_recovery()` returns false), so the server accepts writes once the
+  /// promise resolves.
+  ///
+  /// @param timeout_seconds - Maximum time to wait for the server to leave recovery (defaults to 60)
+  /// @returns Promise that resolves to the timeline ID the promoted server writes on.
+  /// @throws Error if the server is not a standby, if promotion fails or if the timeout is exceeded.
+  ///
+  /// @example
+  /// ```typescript
+  /// const timeline = await standby.promote();
+  /// console.log(`Promoted, now on timeline ${timeline}`);
+  /// ```
   #[napi]
-  pub async unsafe fn promote(&self) -> napi::Result<()> {
+  pub async unsafe fn promote(&self, timeout_seconds: Option<u32>) -> napi::Result<u32> {
+    let current_state = self.get_state()?;
+    if !matches!(current_state, InstanceState::Running) {
+      return Err(database_error("PostgreSQL instance is not running"));
+    }
+    let rows = self.query_rows("SELECT pg_is_in_recovery()", None).await?;
+    if first_value(&rows) != Some("t") {
+      return Err(database_error(
+        "PostgreSQL instance is not a standby and cannot be promoted",
+      ));
+    }
+
     if let Some(instance) = &self.async_instance {
-      let pg_ctl_path = instance
-        .settings()
-        .installation_dir
-        .join("bin")
-        .join("pg_ctl");
-      let data_dir = &instance.settings().data_dir;
-      let mut command = tokio::process::Command::new(pg_ctl_path);
-      command.arg("-D").arg(data_dir).arg("promote");
-
-      let output = command
+      let program_dir = self.tool_dir("pg_ctl")?;
+      let mut command =
+        std::process::Command::new(std::path::Path::new(&program_dir).join("pg_ctl"));
+      command
+        .arg("-D")
+        .arg(&instance.settings().data_dir)
+        .arg("promote");
+      check_executable(&command)?;
+
+      let output = tokio::process::Command::from(command)
         .output()
         .await
-        .map_err(|e| stop_error(&e.to_string()))?;
+        .map_err(|e| database_error(&format!("Failed to run pg_ctl promote: {e}")))?;
 
-      if output.status.success() {
-        Ok(())
-      } else {
-        Err(stop_error(String::from_utf8_lossy(&output.stderr).as_ref()))
+      if !output.status.success() {
+        return Err(database_error(&format!(
+          "pg_ctl promote failed: {}",
+          String::from_utf8_lossy(&output.stderr).trim()
+        )));
+      }
+
+      let timeout_seconds = timeout_seconds.unwrap_or(DEFAULT_PROMOTE_TIMEOUT_SECONDS);
+      let deadline = Instant::now() + Duration::from_secs(timeout_seconds as u64);
+      loop {
+        let rows = self.query_rows("SELECT pg_is_in_recovery()", None).await?;
+        if first_value(&rows) == Some("f") {
+          break;
+        }
+        if Instant::now() >= deadline {
+          return Err(timeout_error(&format!(
+            "Server did not leave recovery within {timeout_seconds} seconds after promotion"
+          )));
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+      }
+
+      if let Ok(mut role) = self.role.lock() {
+        *role = ServerRole::Primary;
       }
+      let timeline = self.get_current_timeline().await?;
+      pg_log!(
+        info,
+        "PostgreSQL instance {} promoted to primary on timeline {}",
+        self.log_name(),
+        timeline
+      );
+      Ok(timeline)
     } else {
       Err(setup_error(
         "PostgreSQL instance has not been initialized yet.",
@@ -237,9 +692,294 @@ impl PostgresInstance {
     }
   }
 
+  /// Gets the replication role of the running server
+  ///
+  /// The role is determined with `pg_is_in_recovery()`, so it also reflects promotions
+  /// triggered outside this instance.
+  ///
+  /// @returns Promise that resolves to Primary, or Standby while the server is in recovery
+  /// @throws Error if the instance is not running or if the query fails
+  ///
+  /// @example
+  /// ```typescript
+  /// if ((await instance.getRole()) === ServerRole.Standby) {
+  ///   await instance.promote();
+  /// }
+  /// ```
+  #[napi]
+  pub async fn get_role(&self) -> napi::Result<ServerRole> {
+    Ok(self.get_recovery_status().await?.role)
+  }
+
+  /// Gets the recovery progress of the running server
+  ///
+  /// @returns Promise that resolves to the role, the received and replayed WAL locations and whether replay is paused
+  /// @throws Error if the instance is not running or if the query fails
+  ///
+  /// @example
+  /// ```typescript
+  /// const status = await standby.getRecoveryStatus();
+  /// console.log(status.receiveLsn, status.replayLsn, status.replayPaused);
+  /// ```
+  #[napi]
+  pub async fn get_recovery_status(&self) -> napi::Result<RecoveryStatus> {
+    // pg_is_wal_replay_paused() raises an error outside recovery
+    let rows = self
+      .query_rows(
+        "SELECT pg_is_in_recovery(), pg_last_wal_receive_lsn(), pg_last_wal_replay_lsn(), \
+         CASE WHEN pg_is_in_recovery() THEN pg_is_wal_replay_paused() ELSE false END",
+        None,
+      )
+      .await?;
+    let row = rows
+      .first()
+      .filter(|row| row.len() == 4)
+      .ok_or_else(|| database_error("Unexpected result from pg_is_in_recovery"))?;
+    let role = if row[0] == "t" {
+      ServerRole::Standby
+    } else {
+      ServerRole::Primary
+    };
+    if let Ok(mut current_role) = self.role.lock() {
+      *current_role = role;
+    }
+    let lsn = |value: &String| Some(value.clone()).filter(|value| !value.is_empty());
+    Ok(RecoveryStatus {
+      role,
+      receive_lsn: lsn(&row[1]),
+      replay_lsn: lsn(&row[2]),
+      replay_paused: row[3] == "t",
+    })
+  }
+
+  /// Gets the timeline ID of the running server
+  ///
+  /// On a primary this is the timeline WAL is currently written on; on a standby it is
+  /// the timeline of the latest restartpoint.
+  ///
+  /// @returns Promise that resolves to the timeline ID
+  /// @throws Error if the instance is not running or if the query fails
+  ///
+  /// @example
+  /// ```typescript
+  /// const before = await instance.getCurrentTimeline();
+  /// await instance.promote();
+  /// ```
+  #[napi]
+  pub async fn get_current_timeline(&self) -> napi::Result<u32> {
+    // WAL file names start with the timeline ID as 8 hex digits; pg_walfile_name()
+    // is not available during recovery
+    let rows = self
+      .query_rows(
+        "SELECT CASE WHEN pg_is_in_recovery() \
+         THEN (SELECT timeline_id FROM pg_control_checkpoint()) \
+         ELSE ('x' || substr(pg_walfile_name(pg_current_wal_lsn()), 1, 8))::bit(32)::int END",
+        None,
+      )
+      .await?;
+    first_value(&rows)
+      .and_then(|timeline| timeline.parse().ok())
+      .ok_or_else(|| database_error("Unexpected result while reading the timeline"))
+  }
+
+  /// Gets the current WAL position of the server
+  ///
+  /// On a primary this is the current write position (`pg_current_wal_lsn`), on a standby
+  /// the last replayed position (`pg_last_wal_replay_lsn`). A standby has caught up with
+  /// its primary once its position is at least the primary's, see `compareLsn()`.
+  ///
+  /// @returns Promise that resolves to the LSN, e.g. `0/3000060`
+  /// @throws Error if the instance is not running or if the query fails
+  ///
+  /// @example
+  /// ```typescript
+  /// const target = await primary.getCurrentLsn();
+  /// while (compareLsn(await standby.getCurrentLsn(), target) < 0) {
+  ///   await new Promise((resolve) => setTimeout(resolve, 100));
+  /// }
+  /// ```
+  #[napi]
+  pub async fn get_current_lsn(&self) -> napi::Result<String> {
+    let rows = self
+      .query_rows(
+        "SELECT CASE WHEN pg_is_in_recovery() THEN pg_last_wal_replay_lsn() \
+         ELSE pg_current_wal_lsn() END::text",
+        None,
+      )
+      .await?;
+    first_value(&rows)
+      .filter(|lsn| !lsn.is_empty())
+      .map(str::to_string)
+      .ok_or_else(|| database_error("Unexpected result while reading the WAL position"))
+  }
+
+  /// Pauses WAL replay on a standby server
+  ///
+  /// This is a wrapper around `pg_wal_replay_pause`. WAL keeps being received but is
+  /// not applied until `resumeReplay()` is called.
+  ///
+  /// @returns Promise that resolves when the pause has been requested
+  /// @throws Error if the instance is not running or if the server is not in recovery
+  ///
+  /// @example
+  /// ```typescript
+  /// await standby.pauseReplay();
+  /// // ... inspect a consistent snapshot ...
+  /// await standby.resumeReplay();
+  /// ```
+  #[napi]
+  pub async fn pause_replay(&self) -> napi::Result<()> {
+    self
+      .query_rows("SELECT pg_wal_replay_pause()", None)
+      .await?;
+    Ok(())
+  }
+
+  /// Resumes WAL replay on a standby server after `pauseReplay()`
+  ///
+  /// This is a wrapper around `pg_wal_replay_resume`.
+  ///
+  /// @returns Promise that resolves when replay has been resumed
+  /// @throws Error if the instance is not running or if the server is not in recovery
+  #[napi]
+  pub async fn resume_replay(&self) -> napi::Result<()> {
+    self
+      .query_rows("SELECT pg_wal_replay_resume()", None)
+      .await?;
+    Ok(())
+  }
+
+  /// Creates and starts a streaming replica of this instance
+  ///
+  /// The replica's data directory is copied with pg_basebackup and configured to stream
+  /// WAL from this instance. It uses the same installation, credentials and server
+  /// configuration. This instance may itself be a standby, so replicas of replicas form
+  /// cascading replication trees; with `slotName`, a physical replication slot is created
+  /// on this instance and the replica streams through it until its `cleanup()` drops the
+  /// slot. With `recoveryMinApplyDelay`, the replica applies transactions only after the
+  /// delay, so a delayed standby keeps data that was dropped on the primary until the
+  /// delay has passed. Call `pauseReplay()` to keep it longer; `promote()` replays all
+  /// received WAL without delay and loses the data.
+  ///
+  /// @param options - Data directory, port, name, replication slot and replay delay of the replica
+  /// @returns Promise that resolves to the running replica
+  /// @throws Error if the instance is not running, if an option is invalid or if the
+  /// backup or the replica's start fails
+  ///
+  /// @example
+  /// ```typescript
+  /// const delayed = await primary.createReplica({ recoveryMinApplyDelay: '1h' });
+  /// await primary.executeSql('DROP TABLE orders', {});
+  /// await delayed.pauseReplay();
+  /// // orders still exists on the delayed replica and can be dumped from it
+  ///
+  /// // Cascading: primary -> tier1 -> tier2
+  /// const tier1 = await primary.createReplica({ slotName: 'tier1' });
+  /// const tier2 = await tier1.createReplica({ slotName: 'tier2' });
+  /// ```
+  #[napi]
+  pub async fn create_replica(
+    &self,
+    options: Option<ReplicaOptions>,
+  ) -> napi::Result<PostgresInstance> {
+    let options = options.unwrap_or_default();
+    let current_state = self.get_state()?;
+    if !matches!(current_state, InstanceState::Running) {
+      return Err(database_error("PostgreSQL instance is not running"));
+    }
+    if let Some(ref delay) = options.recovery_min_apply_delay {
+      replica::validate_apply_delay(delay)?;
+    }
+    if let Some(ref slot_name) = options.slot_name {
+      replica::validate_slot_name(slot_name)?;
+    }
+
+    let mut replica = Self::new(Some(PostgresSettings {
+      name: options.name.clone(),
+      version: Some(self.settings.version.to_string()),
+      host: Some(self.settings.host.clone()),
+      port: Some(options.port.unwrap_or(0)),
+      username: Some(self.settings.username.clone()),
+      password: Some(self.client_password()),
+      data_dir: options.data_dir.clone(),
+      installation_dir: Some(self.settings.installation_dir.to_string_lossy().to_string()),
+      // Hot standby requires settings such as max_connections to match the primary's
+      server_config: Some(self.settings.configuration.clone()),
+      server_env: Some(self.server_env.clone()),
+      ..Default::default()
+    }))?;
+    let replica_dir = replica.settings.data_dir.clone();
+    pg_log!(
+      info,
+      "Creating replica of {} in {}",
+      self.log_name(),
+      replica_dir.display()
+    );
+
+    if let Some(ref slot_name) = options.slot_name {
+      // Reserve WAL right away so nothing is recycled before the replica connects
+      self
+        .query_rows(
+          &format!(
+            "SELECT pg_create_physical_replication_slot({}, true)",
+            quote_literal(slot_name)
+          ),
+          None,
+        )
+        .await?;
+      replica.upstream_slot = Some(replica::UpstreamSlot {
+        connection: self.connection_config(),
+        name: slot_name.clone(),
+      });
+    }
+
+    let program_dir = self.tool_dir("pg_basebackup")?;
+    let tool = PgBasebackupTool::from_connection(
+      self.connection_config(),
+      program_dir,
+      PgBasebackupConfig {
+        pgdata: replica_dir.to_string_lossy().to_string(),
+        checkpoint: Some(PgBasebackupCheckpoint::Fast),
+        wal_method: Some(PgBasebackupWalMethod::Stream),
+        ..Default::default()
+      },
+    );
+    let created = async {
+      let result = tool.execute().await?;
+      if result.exit_code != 0 {
+        return Err(setup_error(&format!(
+          "Failed to copy the data directory for the replica: {}",
+          result.stderr.trim()
+        )));
+      }
+
+      // The replica identifies itself in pg_stat_replication with its application_name
+      let primary_conninfo = format_conninfo(&ConnectionConfig {
+        database: None,
+        application_name: replica.application_name.clone(),
+        ..self.connection_config()
+      });
+      replica::configure_standby(
+        &replica_dir,
+        &primary_conninfo,
+        options.slot_name.as_deref(),
+        options.recovery_min_apply_delay.as_deref(),
+      )?;
+      unsafe { replica.start(Some(false)) }.await
+    }
+    .await;
+    if let Err(e) = created {
+      if let Some(slot) = &replica.upstream_slot {
+        slot.drop_slot().await;
+      }
+      return Err(e);
+    }
+    Ok(replica)
+  }
+
   /// Gets the current state of the PostgreSQL instance
   ///
-  /// @returns The current instance state (Stopped, Starting, Running, or Stopping)
+  /// @returns The current instance state (Stopped, Initialized, Starting, Running, Stopping, or Failed)
   #[napi(getter)]
   pub fn get_state(&self) -> napi::Result<InstanceState> {
     let state = self
@@ -251,13 +991,15 @@ impl PostgresInstance {
       InstanceState::Starting => InstanceState::Starting,
       InstanceState::Running => InstanceState::Running,
       InstanceState::Stopping => InstanceState::Stopping,
+      InstanceState::Failed => InstanceState::Failed,
+      InstanceState::Initialized => InstanceState::Initialized,
     })
   }
 
   /// Gets the connection information for the PostgreSQL instance
   ///
   /// This method returns cached connection information when available for better performance.
-  /// The cache is automatically invalidated after 5 minutes.
+  /// The cache is invalidated after `connectionCacheTtlSeconds` (5 minutes by default).
   ///
   /// @returns Connection information including host, port, username, and connection string
   /// @throws Error if the instance is not running
@@ -270,15 +1012,21 @@ impl PostgresInstance {
 
     match *state {
       InstanceState::Running => {
-        // Check cache
-        if let Ok(mut cache) = self.connection_cache.lock() {
+        // Check cache (a zero TTL disables caching)
+        let cache_lock = if self.connection_cache_ttl.is_zero() {
+          None
+        } else {
+          self.connection_cache.lock().ok()
+        };
+        if let Some(mut cache) = cache_lock {
           if let Some(cached) = cache.as_ref() {
-            // Cache valid for 5 minutes
-            if cached.created_at.elapsed() < Duration::from_secs(300) {
+            if cached.created_at.elapsed() < self.connection_cache_ttl
+              && cached.info.port == self.settings.port
+            {
               pg_log!(
                 debug,
                 "Using cached connection info for instance {}",
-                self.instance_id
+                self.log_name()
               );
               return Ok(cached.info.clone());
             }
@@ -288,8 +1036,8 @@ impl PostgresInstance {
           let host = self.settings.host.clone();
           let port = self.settings.port;
           let username = self.settings.username.clone();
-          let password = self.settings.password.clone();
-          let database_name = "postgres".to_string();
+          let password = self.client_password();
+          let database_name = self.database_name.clone();
 
           let connection_info = ConnectionInfo::new(host, port, username, password, database_name);
 
@@ -302,16 +1050,16 @@ impl PostgresInstance {
           pg_log!(
             debug,
             "Created and cached new connection info for instance {}",
-            self.instance_id
+            self.log_name()
           );
           Ok(connection_info)
         } else {
-          // Cache lock failed, create connection info directly
+          // Caching disabled or cache lock failed, create connection info directly
           let host = self.settings.host.clone();
           let port = self.settings.port;
           let username = self.settings.username.clone();
-          let password = self.settings.password.clone();
-          let database_name = "postgres".to_string();
+          let password = self.client_password();
+          let database_name = self.database_name.clone();
 
           Ok(ConnectionInfo::new(
             host,
@@ -326,78 +1074,558 @@ impl PostgresInstance {
     }
   }
 
-  /// Set instance state
-  fn set_state(&self, new_state: InstanceState) -> napi::Result<()> {
-    let mut state = self
-      .state
-      .lock()
-      .map_err(|_| setup_error("Failed to acquire state lock"))?;
-
-    // Log state transition
-    pg_log!(debug, "State transition: {:?} -> {:?}", *state, new_state);
-    *state = new_state;
-    Ok(())
-  }
-
-  /// Checks if the PostgreSQL instance is healthy and running
+  /// Checks whether the data directory has been initialized
+  ///
+  /// Inspects the data directory for the files initdb creates, so it also reports
+  /// clusters initialized by an earlier process or a previous instance.
   ///
-  /// @returns true if the instance is running and healthy, false otherwise
+  /// @returns true if setup (initdb) does not need to run again, false otherwise
   #[napi]
-  pub fn is_healthy(&self) -> napi::Result<bool> {
-    let state = self.get_state()?;
-
-    match state {
-      InstanceState::Running => {
-        // Check if instance is actually running
-        let has_async = self.async_instance.is_some();
-
-        Ok(has_async)
-      }
-      _ => Ok(false),
-    }
+  pub fn is_initialized(&self) -> bool {
+    self.is_data_dir_initialized()
   }
 
-  /// # Safety
-  /// Sets up the PostgreSQL instance asynchronously
+  /// Reads a server setting from the cluster's configuration files
   ///
-  /// This method initializes the PostgreSQL instance but does not start it.
-  /// It's automatically called by start() if needed.
+  /// postgresql.conf, the files it includes and postgresql.auto.conf are read in the
+  /// order the server reads them, so the last assignment wins. Values passed on the
+  /// command line through `serverConfig` are not included.
   ///
-  /// @returns Promise that resolves when setup is complete
-  /// @throws Error if setup fails
+  /// @param name - Setting name, e.g. `work_mem`
+  /// @returns The configured value, or null if the files do not set it
+  /// @throws Error if the data directory has not been initialized
+  ///
+  /// @example
+  /// ```typescript
+  /// const walLevel = instance.getConfigFileValue('wal_level');
+  /// ```
   #[napi]
-  pub async unsafe fn setup(&mut self) -> napi::Result<()> {
-    pg_log!(
-      info,
-      "Starting PostgreSQL setup on port {}",
-      self.settings.port
-    );
-    self.set_state(InstanceState::Starting)?;
-
-    let mut instance = postgresql_embedded::PostgreSQL::new(self.settings.clone());
-    match instance.setup().await {
-      Ok(_) => {
-        pg_log!(info, "PostgreSQL setup completed successfully");
-        self.async_instance = Some(instance);
-        self.set_state(InstanceState::Stopped)?; // Setup完成后设置为Stopped状态，等待start
-        Ok(())
-      }
-      Err(e) => {
-        pg_log!(error, "PostgreSQL setup failed: {}", e);
-        self.set_state(InstanceState::Stopped)?;
-        Err(convert_postgresql_error(e).into())
-      }
-    }
+  pub fn get_config_file_value(&self, name: String) -> napi::Result<Option<String>> {
+    self.ensure_data_dir_initialized()?;
+    Ok(effective_value(&self.settings.data_dir, &name)?)
   }
 
-  /// # Safety
-  /// Starts the PostgreSQL instance asynchronously
+  /// Writes a server setting to the cluster's configuration files
   ///
-  /// This method starts the PostgreSQL server and makes it ready to accept connections.
-  /// It includes automatic setup if the instance hasn't been set up yet.
+  /// The value is stored in `conf.d/pg-embedded.conf`, which postgresql.conf includes, and
+  /// replaces any value previously written there, so calling this repeatedly does not
+  /// create duplicates. Reload or restart the server for the change to take effect.
   ///
-  /// @returns Promise that resolves when the instance is started and ready
-  /// @throws Error if the instance is already running or if startup fails
+  /// @param name - Setting name, e.g. `work_mem`
+  /// @param value - New value, or null to remove the value written earlier
+  /// @throws Error if the data directory has not been initialized or the file cannot be written
+  ///
+  /// @example
+  /// ```typescript
+  /// instance.setConfigFileValue('wal_level', 'logical');
+  /// await instance.stop();
+  /// await instance.start();
+  /// ```
+  #[napi]
+  pub fn set_config_file_value(&self, name: String, value: Option<String>) -> napi::Result<()> {
+    validate_setting_name(&name)?;
+    self.ensure_data_dir_initialized()?;
+    let mut conf = managed_conf(&self.settings.data_dir)?;
+    match value {
+      Some(value) => conf.set(&name, &value),
+      None => conf.remove(&name),
+    }
+    conf.save()?;
+    Ok(())
+  }
+
+  /// Writes server settings to the configuration files and reloads a running server
+  ///
+  /// Every setting is stored like with `setConfigFileValue()`. If the instance is running,
+  /// its configuration is reloaded; settings that only change on a restart are returned.
+  /// Settings passed on the command line through `serverConfig` take precedence over the
+  /// configuration files and cannot be changed this way.
+  ///
+  /// @param config - Settings to write, e.g. `{ work_mem: '16MB' }`
+  /// @returns Promise that resolves to the names of the settings that still need a restart
+  /// @throws Error if a setting name is invalid, the data directory has not been initialized
+  /// or the reload fails
+  ///
+  /// @example
+  /// ```typescript
+  /// const pending = await instance.applyServerConfig({ work_mem: '16MB', max_connections: '50' });
+  /// if (pending.length > 0) {
+  ///   await instance.stop();
+  ///   await instance.start();
+  /// }
+  /// ```
+  #[napi]
+  pub async fn apply_server_config(
+    &self,
+    config: HashMap<String, String>,
+  ) -> napi::Result<Vec<String>> {
+    for name in config.keys() {
+      validate_setting_name(name)?;
+    }
+    self.ensure_data_dir_initialized()?;
+    let mut conf = managed_conf(&self.settings.data_dir)?;
+    for (name, value) in &config {
+      conf.set(name, value);
+    }
+    conf.save()?;
+
+    if !matches!(self.get_state()?, InstanceState::Running) {
+      return Ok(Vec::new());
+    }
+    self.reload_config().await?;
+    let rows = self
+      .query_rows(
+        "SELECT name FROM pg_settings WHERE pending_restart ORDER BY name",
+        None,
+      )
+      .await?;
+    Ok(
+      rows
+        .into_iter()
+        .filter_map(|row| row.into_iter().next())
+        .collect(),
+    )
+  }
+
+  /// Lists the client authentication rules in pg_hba.conf
+  ///
+  /// @returns The active rules, in the order the server evaluates them
+  /// @throws Error if the data directory has not been initialized
+  ///
+  /// @example
+  /// ```typescript
+  /// for (const rule of instance.listHbaRules()) {
+  ///   console.log(rule.connectionType, rule.user, rule.method);
+  /// }
+  /// ```
+  #[napi]
+  pub fn list_hba_rules(&self) -> napi::Result<Vec<HbaRule>> {
+    Ok(hba::list_hba_rules(&self.read_hba_file()?))
+  }
+
+  /// Adds a client authentication rule to pg_hba.conf
+  ///
+  /// The server evaluates rules top to bottom and uses the first match, so the rule is
+  /// inserted before the existing rules unless `append` is set. Adding a rule that is
+  /// already present does nothing. A running server reloads its configuration before the
+  /// promise resolves; if it rejects the new file, the change is rolled back.
+  ///
+  /// @param rule - The rule to add
+  /// @param append - Add the rule after the existing rules instead of before them (default: false)
+  /// @returns Promise that resolves once the rule is in effect
+  /// @throws Error if the rule is invalid, the file cannot be written or the server rejects it
+  ///
+  /// @example
+  /// ```typescript
+  /// await instance.addHbaRule({
+  ///   connectionType: 'host',
+  ///   database: 'all',
+  ///   user: 'mallory',
+  ///   address: '127.0.0.1/32',
+  ///   method: 'reject',
+  /// });
+  /// ```
+  #[napi]
+  pub async fn add_hba_rule(&self, rule: HbaRule, append: Option<bool>) -> napi::Result<()> {
+    rule.validate()?;
+    let contents = self.read_hba_file()?;
+    if let Some(updated) = hba::add_hba_rule(&contents, &rule, append.unwrap_or(false)) {
+      self.replace_hba_file(&contents, &updated).await?;
+    }
+    Ok(())
+  }
+
+  /// Removes client authentication rules from pg_hba.conf
+  ///
+  /// A running server reloads its configuration before the promise resolves.
+  ///
+  /// @param matcher - Columns the rules to remove must match; omitted columns match any value
+  /// @returns Promise that resolves to the number of removed rules
+  /// @throws Error if the file cannot be written or the server rejects the result
+  ///
+  /// @example
+  /// ```typescript
+  /// await instance.removeHbaRule({ user: 'mallory' });
+  /// ```
+  #[napi]
+  pub async fn remove_hba_rule(&self, matcher: HbaRuleMatcher) -> napi::Result<u32> {
+    let contents = self.read_hba_file()?;
+    let (updated, removed) = hba::remove_hba_rules(&contents, &matcher);
+    if removed > 0 {
+      self.replace_hba_file(&contents, &updated).await?;
+    }
+    Ok(removed)
+  }
+
+  fn read_hba_file(&self) -> napi::Result<String> {
+    self.ensure_data_dir_initialized()?;
+    let hba_file = self.settings.data_dir.join("pg_hba.conf");
+    std::fs::read_to_string(&hba_file)
+      .map_err(|e| database_error(&format!("Failed to read {}: {e}", hba_file.display())))
+  }
+
+  /// Write new pg_hba.conf contents and reload a running server, restoring `previous`
+  /// if the server reports errors in the new file
+  async fn replace_hba_file(&self, previous: &str, contents: &str) -> napi::Result<()> {
+    let hba_file = self.settings.data_dir.join("pg_hba.conf");
+    let write = |contents: &str| {
+      std::fs::write(&hba_file, contents)
+        .map_err(|e| database_error(&format!("Failed to write {}: {e}", hba_file.display())))
+    };
+    write(contents)?;
+    if !matches!(self.get_state()?, InstanceState::Running) {
+      return Ok(());
+    }
+
+    let rows = self
+      .query_rows(
+        "SELECT line_number || ': ' || error FROM pg_hba_file_rules WHERE error IS NOT NULL",
+        None,
+      )
+      .await?;
+    if !rows.is_empty() {
+      write(previous)?;
+      let errors: Vec<String> = rows.into_iter().flatten().collect();
+      return Err(database_error(&format!(
+        "pg_hba.conf rejected by the server: {}",
+        errors.join("; ")
+      )));
+    }
+    self.reload_config().await
+  }
+
+  /// Reload the server configuration and wait until new connections use it
+  async fn reload_config(&self) -> napi::Result<()> {
+    // New backends inherit the postmaster's configuration load time, so a later
+    // timestamp shows the postmaster has processed the reload signal
+    let rows = self
+      .query_rows("SELECT pg_conf_load_time()::text, pg_reload_conf()", None)
+      .await?;
+    let loaded_before = first_value(&rows).unwrap_or_default().to_string();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+      let rows = self
+        .query_rows("SELECT pg_conf_load_time()::text", None)
+        .await?;
+      if first_value(&rows).is_some_and(|loaded| loaded != loaded_before) {
+        return Ok(());
+      }
+      tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    Err(timeout_error(
+      "Server did not reload its configuration within 5 seconds",
+    ))
+  }
+
+  fn ensure_data_dir_initialized(&self) -> napi::Result<()> {
+    if self.is_data_dir_initialized() {
+      Ok(())
+    } else {
+      Err(setup_error(&format!(
+        "data directory {} is not initialized - call setup() first",
+        self.settings.data_dir.display()
+      )))
+    }
+  }
+
+  /// Whether initdb has already been run for the data directory
+  fn is_data_dir_initialized(&self) -> bool {
+    let data_dir = &self.settings.data_dir;
+    data_dir.join("PG_VERSION").is_file() && data_dir.join("postgresql.conf").is_file()
+  }
+
+  /// Name used to identify the instance in log messages
+  fn log_name(&self) -> &str {
+    self.name.as_deref().unwrap_or(&self.instance_id)
+  }
+
+  /// Gets the most recent failure of this instance
+  ///
+  /// The failure is kept until the next successful start, so it can be inspected after
+  /// the instance entered the Failed state (or after a failed stop).
+  ///
+  /// @returns The failed phase, error message and server log excerpt, or null if nothing failed
+  ///
+  /// @example
+  /// ```typescript
+  /// try {
+  ///   await instance.start();
+  /// } catch {
+  ///   const failure = instance.getLastError();
+  ///   console.error(failure?.phase, failure?.message, failure?.stderr);
+  /// }
+  /// ```
+  #[napi]
+  pub fn get_last_error(&self) -> Option<InstanceFailure> {
+    self
+      .last_error
+      .lock()
+      .ok()
+      .and_then(|last_error| last_error.clone())
+  }
+
+  /// Remember a failure without changing the instance state
+  fn remember_failure(&self, phase: FailurePhase, message: &str) {
+    let stderr = match phase {
+      FailurePhase::Start | FailurePhase::Runtime => {
+        read_log_tail(&self.settings.data_dir.join("start.log"))
+      }
+      _ => None,
+    };
+    if let Ok(mut last_error) = self.last_error.lock() {
+      *last_error = Some(InstanceFailure {
+        phase,
+        message: redact(message),
+        stderr,
+      });
+    }
+  }
+
+  /// Remember a failure and move the instance to the Failed state
+  fn record_failure(&self, phase: FailurePhase, message: &str) -> napi::Result<()> {
+    self.remember_failure(phase, message);
+    self.set_state(InstanceState::Failed)
+  }
+
+  /// Set instance state
+  fn set_state(&self, new_state: InstanceState) -> napi::Result<()> {
+    let mut state = self
+      .state
+      .lock()
+      .map_err(|_| setup_error("Failed to acquire state lock"))?;
+
+    // Log state transition
+    pg_log!(debug, "State transition: {:?} -> {:?}", *state, new_state);
+    *state = new_state;
+    Ok(())
+  }
+
+  /// Checks if the PostgreSQL instance is healthy and running
+  ///
+  /// Only the instance state is checked, so this returns at once; use `probeHealth()` to
+  /// find out whether the server still accepts connections.
+  ///
+  /// @returns true if the instance is running, false otherwise
+  #[napi]
+  pub fn is_healthy(&self) -> napi::Result<bool> {
+    let state = self.get_state()?;
+    Ok(matches!(state, InstanceState::Running) && self.async_instance.is_some())
+  }
+
+  /// Probes whether the server of a running instance accepts connections
+  ///
+  /// Opens a connection to the server's port (without authenticating), waiting at most
+  /// 500 ms, so a server that died or hangs is reported as unhealthy even though the
+  /// instance still counts as running. The probe runs off the JavaScript thread.
+  ///
+  /// @param reconcile - When the probe of a running instance fails, move the instance to
+  /// the Failed state and record the failure in `lastError` (default: false)
+  /// @returns Promise that resolves to true if the instance is running and accepts
+  /// connections, false otherwise
+  ///
+  /// @example
+  /// ```typescript
+  /// if (!(await instance.probeHealth(true))) {
+  ///   const failure = instance.getLastError();
+  ///   console.error(failure?.message, failure?.stderr);
+  /// }
+  /// ```
+  #[napi]
+  pub async fn probe_health(&self, reconcile: Option<bool>) -> napi::Result<bool> {
+    if !self.is_healthy()? {
+      return Ok(false);
+    }
+
+    let host = self.settings.host.clone();
+    let port = self.settings.port;
+    // The probe blocks for up to its timeout, which must not stall the event loop
+    let probe_host = host.clone();
+    let probe = tokio::task::spawn_blocking(move || {
+      health::probe_server(&probe_host, port, health::HEALTH_PROBE_TIMEOUT)
+    })
+    .await
+    .map_err(|e| setup_error(&format!("Failed to run the health probe: {e}")))?;
+    match probe {
+      Ok(()) => Ok(true),
+      Err(e) => {
+        pg_log!(warn, "Health probe of {}:{} failed: {}", host, port, e);
+        if reconcile.unwrap_or(false) {
+          self.record_failure(
+            FailurePhase::Runtime,
+            &format!("The server no longer accepts connections on {host}:{port}: {e}"),
+          )?;
+        }
+        Ok(false)
+      }
+    }
+  }
+
+  /// # Safety
+  /// Sets up the PostgreSQL instance asynchronously
+  ///
+  /// This method initializes the PostgreSQL instance but does not start it.
+  /// It's automatically called by start() if needed.
+  ///
+  /// @returns Promise that resolves when setup is complete
+  /// @throws Error if setup fails or the process runs as root, which PostgreSQL refuses
+  #[napi]
+  pub async unsafe fn setup(&mut self) -> napi::Result<()> {
+    pg_log!(
+      info,
+      "Starting PostgreSQL setup on port {}",
+      self.settings.port
+    );
+    self.set_state(InstanceState::Starting)?;
+    if let Err(e) = privileges::ensure_not_root() {
+      let error: napi::Error = e.into();
+      self.record_failure(FailurePhase::Setup, &error.reason)?;
+      return Err(error);
+    }
+
+    // Profile provisioning only runs against a cluster created by this setup
+    let fresh_cluster = !self.settings.data_dir.join("PG_VERSION").exists();
+
+    // postgresql_embedded runs initdb without locale options; with an ICU locale it only
+    // installs the binaries (a placeholder postgresql.conf marks the cluster as initialized)
+    // and the cluster is initialized here afterwards
+    let icu_locale = self.icu_locale.clone().filter(|_| fresh_cluster);
+    if icu_locale.is_some() {
+      if let Err(e) = self.write_initdb_placeholder() {
+        self.record_failure(FailurePhase::Setup, &e.reason)?;
+        return Err(e);
+      }
+    }
+
+    let mut instance = postgresql_embedded::PostgreSQL::new(self.settings.clone());
+    let mut result = instance.setup().await.map_err(|e| {
+      pg_log!(error, "PostgreSQL setup failed: {}", e);
+      let error: napi::Error = convert_postgresql_error(e).into();
+      error
+    });
+    if let Some(ref icu_locale) = icu_locale {
+      let _ = std::fs::remove_file(self.settings.data_dir.join("postgresql.conf"));
+      if result.is_ok() {
+        result = Self::initdb_with_icu(instance.settings(), icu_locale).await;
+      }
+    }
+    match result {
+      Ok(_) => {
+        if let Err(e) = self.record_resolved_version(instance.settings()) {
+          self.record_failure(FailurePhase::Setup, &e.reason)?;
+          return Err(e);
+        }
+        if let Err(e) = self.configure_hba_auth(fresh_cluster) {
+          self.record_failure(FailurePhase::Setup, &e.reason)?;
+          return Err(e);
+        }
+        pg_log!(info, "PostgreSQL setup completed successfully");
+        self.bin_dir = Some(BinDir::from_installation_dir(
+          &instance.settings().installation_dir,
+        ));
+        self.async_instance = Some(instance);
+        self.provision_pending = fresh_cluster && self.profile.is_some();
+        self.set_state(InstanceState::Initialized)?; // Setup完成后设置为Initialized状态，等待start
+        Ok(())
+      }
+      Err(e) => {
+        self.record_failure(FailurePhase::Setup, &e.reason)?;
+        Err(e)
+      }
+    }
+  }
+
+  /// Pin the settings to the version the requirement resolved to during setup and record
+  /// it in the data directory, refusing a cluster of another major version
+  fn record_resolved_version(
+    &mut self,
+    settings: &postgresql_embedded::Settings,
+  ) -> napi::Result<()> {
+    let Some(version) = metadata::exact_version(&settings.version) else {
+      return Ok(());
+    };
+    metadata::check_data_dir_version(&self.settings.data_dir, &version)?;
+    metadata::record_version(&self.settings.data_dir, &version)?;
+    pg_log!(info, "Using PostgreSQL {}", version);
+    self.settings.version = settings.version.clone();
+    Ok(())
+  }
+
+  /// Pin the settings to the version recorded in an existing data directory when it
+  /// satisfies the requirement, so a cluster keeps running on the binaries it was set up with
+  fn pin_recorded_version(&mut self) -> napi::Result<()> {
+    if metadata::exact_version(&self.settings.version).is_none() {
+      if let Some(recorded) = metadata::recorded_version(&self.settings.data_dir) {
+        if self.settings.version.matches(&recorded) {
+          self.settings.version = VersionReq::parse(&format!("={recorded}"))
+            .map_err(|e| setup_error(&format!("Invalid recorded version {recorded}: {e}")))?;
+        }
+      }
+    }
+    if let Some(version) = metadata::exact_version(&self.settings.version) {
+      metadata::check_data_dir_version(&self.settings.data_dir, &version)?;
+    }
+    Ok(())
+  }
+
+  fn write_initdb_placeholder(&self) -> napi::Result<()> {
+    let data_dir = &self.settings.data_dir;
+    std::fs::create_dir_all(extended_path(data_dir))
+      .and_then(|_| std::fs::write(extended_path(data_dir.join("postgresql.conf")), ""))
+      .map_err(|e| {
+        setup_error(&format!(
+          "Failed to prepare data directory {}: {e}",
+          data_dir.display()
+        ))
+      })
+  }
+
+  /// Initialize the cluster with the ICU locale provider, using the same options as
+  /// postgresql_embedded otherwise
+  async fn initdb_with_icu(
+    settings: &postgresql_embedded::Settings,
+    icu_locale: &str,
+  ) -> napi::Result<()> {
+    pg_log!(
+      info,
+      "Initializing cluster with ICU locale '{}'",
+      icu_locale
+    );
+    if !settings.password_file.exists() {
+      std::fs::write(&settings.password_file, &settings.password)
+        .map_err(|e| setup_error(&format!("Failed to write password file: {e}")))?;
+    }
+    let command = InitDbBuilder::from(settings)
+      .pgdata(&settings.data_dir)
+      .username(postgresql_embedded::BOOTSTRAP_SUPERUSER)
+      .auth("password")
+      .pwfile(&settings.password_file)
+      .encoding("UTF8")
+      .locale_provider("icu")
+      .icu_locale(icu_locale)
+      .build();
+    let output = tokio::process::Command::from(command)
+      .output()
+      .await
+      .map_err(|e| setup_error(&format!("Failed to run initdb: {e}")))?;
+    if output.status.success() {
+      Ok(())
+    } else {
+      let stderr = String::from_utf8_lossy(&output.stderr);
+      pg_log!(error, "initdb failed: {}", stderr.trim());
+      Err(setup_error(&format!("initdb failed: {}", stderr.trim())))
+    }
+  }
+
+  /// # Safety
+  /// Starts the PostgreSQL instance asynchronously
+  ///
+  /// This method starts the PostgreSQL server and makes it ready to accept connections.
+  /// It includes automatic setup if the instance hasn't been set up yet.
+  ///
+  /// @param initialize - Whether to run setup first if needed (default: true). With `false`
+  ///   the data directory must already be initialized, unless the `autoSetup` setting is enabled.
+  /// @returns Promise that resolves when the instance is started and ready
+  /// @throws Error if the instance is already running, the data directory is not initialized, the process runs as root, or if startup fails
   ///
   /// @example
   /// ```typescript
@@ -434,20 +1662,63 @@ impl PostgresInstance {
       self.settings.port
     );
     self.set_state(InstanceState::Starting)?;
+    if let Err(e) = privileges::ensure_not_root() {
+      let error: napi::Error = e.into();
+      self.record_failure(FailurePhase::Start, &error.reason)?;
+      return Err(error);
+    }
 
     // Lazy initialization: create instance only when needed
-    if self.async_instance.is_none() && should_initialize {
+    if self.async_instance.is_none() && !should_initialize && !self.is_data_dir_initialized() {
+      if !self.auto_setup {
+        pg_log!(
+          error,
+          "Data directory {} is not initialized",
+          self.settings.data_dir.display()
+        );
+        let error = setup_error(&format!(
+          "data directory {} is not initialized - call setup() or enable autoSetup",
+          self.settings.data_dir.display()
+        ));
+        self.record_failure(FailurePhase::Setup, &error.reason)?;
+        return Err(error);
+      }
+      pg_log!(
+        info,
+        "Data directory {} is not initialized, running setup",
+        self.settings.data_dir.display()
+      );
+    }
+    if self.async_instance.is_none() && (should_initialize || !self.is_data_dir_initialized()) {
       self.setup().await?;
+      self.set_state(InstanceState::Starting)?;
     }
 
     if self.async_instance.is_none() {
+      if let Err(e) = self.pin_recorded_version() {
+        self.record_failure(FailurePhase::Start, &e.reason)?;
+        return Err(e);
+      }
       // If not initializing, we need to create the instance object without setting it up
       let instance = postgresql_embedded::PostgreSQL::new(self.settings.clone());
+      self.bin_dir = Some(BinDir::from_installation_dir(
+        &instance.settings().installation_dir,
+      ));
       self.async_instance = Some(instance);
     }
 
-    if let Some(ref mut instance) = self.async_instance {
-      match instance.start().await {
+    if let Err(e) = self.configure_wal_archiving() {
+      self.record_failure(FailurePhase::Start, &e.reason)?;
+      return Err(e);
+    }
+
+    let startup_duration = if let Some(ref mut instance) = self.async_instance {
+      let started = if self.server_env.is_empty() {
+        instance.start().await
+      } else {
+        start_with_server_env(instance, &self.server_env).await
+      };
+      match started {
         Ok(_) => {
           let startup_duration = start_time.elapsed();
 
@@ -457,27 +1728,69 @@ impl PostgresInstance {
           }
 
           let db_settings = instance.settings();
+          let port_resolved = self.settings.port != db_settings.port;
           self.settings.port = db_settings.port;
-          pg_log!(
-            info,
-            "PostgreSQL instance started successfully on port {} in {:?}",
-            self.settings.port,
-            startup_duration
-          );
-          self.set_state(InstanceState::Running)?;
-          Ok(())
+          registry::update_port(&self.instance_id, self.settings.port);
+          if port_resolved {
+            // Connection info cached before the port was known is stale
+            if let Ok(mut cache) = self.connection_cache.lock() {
+              *cache = None;
+            }
+            if let Ok(listeners) = self.port_listeners.lock() {
+              for listener in listeners.iter() {
+                listener.call(self.settings.port, ThreadsafeFunctionCallMode::NonBlocking);
+              }
+            }
+          }
+          // The server enters standby mode when standby.signal is present
+          let role = if self.settings.data_dir.join("standby.signal").exists() {
+            ServerRole::Standby
+          } else {
+            ServerRole::Primary
+          };
+          if let Ok(mut current_role) = self.role.lock() {
+            *current_role = role;
+          }
+          startup_duration
         }
         Err(e) => {
           pg_log!(error, "Failed to start PostgreSQL instance: {}", e);
-          self.set_state(InstanceState::Stopped)?;
-          Err(convert_postgresql_error(e).into())
+          self.record_failure(FailurePhase::Start, &e.to_string())?;
+          return Err(convert_postgresql_error(e).into());
         }
       }
     } else {
       pg_log!(error, "PostgreSQL instance not initialized");
-      self.set_state(InstanceState::Stopped)?;
-      Err(start_error("PostgreSQL instance not initialized"))
+      self.record_failure(FailurePhase::Start, "PostgreSQL instance not initialized")?;
+      return Err(start_error("PostgreSQL instance not initialized"));
+    };
+
+    if let Err(e) = self.prepare_databases().await {
+      pg_log!(error, "Failed to prepare databases after start: {}", e);
+      if let Some(ref mut instance) = self.async_instance {
+        if let Err(stop_err) = instance.stop().await {
+          pg_log!(
+            warn,
+            "Failed to stop instance after setup error: {}",
+            stop_err
+          );
+        }
+      }
+      self.record_failure(FailurePhase::Start, &e.reason)?;
+      return Err(e);
+    }
+
+    if let Ok(mut last_error) = self.last_error.lock() {
+      *last_error = None;
     }
+    pg_log!(
+      info,
+      "PostgreSQL instance started successfully on port {} in {:?}",
+      self.settings.port,
+      startup_duration
+    );
+    self.set_state(InstanceState::Running)?;
+    Ok(())
   }
 
   /// # Safety
@@ -502,7 +1815,7 @@ impl PostgresInstance {
   async unsafe fn internal_stop(&mut self, is_cleanup: bool) -> napi::Result<()> {
     let current_state = self.get_state()?;
     match current_state {
-      InstanceState::Stopped => {
+      InstanceState::Stopped | InstanceState::Failed | InstanceState::Initialized => {
         if !is_cleanup {
           pg_log!(
             warn,
@@ -530,6 +1843,9 @@ impl PostgresInstance {
       _ => {}
     }
 
+    if let Err(e) = self.remove_session_seed().await {
+      pg_log!(warn, "Failed to remove the session seed: {}", e);
+    }
     pg_log!(info, "Stopping PostgreSQL instance");
     self.set_state(InstanceState::Stopping)?;
 
@@ -543,6 +1859,7 @@ impl PostgresInstance {
         Err(e) => {
           pg_log!(error, "Failed to stop PostgreSQL instance: {}", e);
           if !is_cleanup {
+            self.remember_failure(FailurePhase::Stop, &e.to_string());
             self.set_state(InstanceState::Running)?;
             Err(convert_postgresql_error(e).into())
           } else {
@@ -599,6 +1916,35 @@ impl PostgresInstance {
     }
   }
 
+  /// Creates a database unless it already exists
+  ///
+  /// Lets setup code that runs against a persistent instance be re-run without
+  /// catching errors.
+  ///
+  /// @param name - The name of the database to create
+  /// @returns Promise that resolves to true if the database was created, false if it existed
+  /// @throws Error if the instance is not running or if database creation fails
+  ///
+  /// @example
+  /// ```typescript
+  /// if (await instance.createDatabaseIfNotExists('myapp')) {
+  ///   await instance.executeSqlFile('./schema.sql', {}, 'myapp');
+  /// }
+  /// ```
+  #[napi]
+  pub async fn create_database_if_not_exists(&self, name: String) -> napi::Result<bool> {
+    if self.database_exists(name.clone()).await? {
+      return Ok(false);
+    }
+    let create = format!("CREATE DATABASE {}", quote_ident(&name));
+    match self.query_rows(&create, None).await {
+      Ok(_) => Ok(true),
+      // Another client created it in the meantime
+      Err(_) if self.database_exists(name.clone()).await? => Ok(false),
+      Err(e) => Err(e),
+    }
+  }
+
   /// # Safety
   /// Creates a database dump using pg_dump
   ///
@@ -606,7 +1952,7 @@ impl PostgresInstance {
   /// The instance must be running before calling this method.
   ///
   /// @param options - Configuration options for pg_dump
-  /// @param database_name - Optional name of the database to dump (defaults to 'postgres')
+  /// @param database_name - Optional name of the database to dump (defaults to the configured databaseName)
   /// @returns Promise that resolves with the execution result when the dump is complete
   /// @throws Error if the instance is not running or if the dump fails
   ///
@@ -630,14 +1976,20 @@ impl PostgresInstance {
       return Err(database_error("PostgreSQL instance is not running"));
     }
 
-    let program_dir = self.get_program_dir()?;
+    let program_dir = self.tool_dir("pg_dump")?;
     let mut connection_config = self.connection_config();
     if let Some(database_name) = database_name {
       connection_config.database = Some(database_name);
     }
-    let tool =
-      PgDumpTool::from_connection(connection_config, format!("{program_dir}/bin"), options);
-    tool.execute().await.map_err(|error| error.into())
+    let database = connection_config.database.clone();
+    let file = options.file.clone();
+    let lsn = self.backup_lsn(file.is_some()).await;
+    let tool = PgDumpTool::from_connection(connection_config, program_dir, options);
+    let result = tool.execute().await?;
+    if let Some(file) = file {
+      self.record_backup("dump", &file, database, lsn, &result);
+    }
+    Ok(result)
   }
 
   /// # Safety
@@ -648,7 +2000,7 @@ impl PostgresInstance {
   /// set up streaming replication. The instance must be running before calling this method.
   ///
   /// @param options - Configuration options for pg_basebackup
-  /// @param database_name - Optional name of the database to connect to (defaults to 'postgres')
+  /// @param database_name - Optional name of the database to connect to (defaults to the configured databaseName)
   /// @returns Promise that resolves with the execution result when the backup is complete
   /// @throws Error if the instance is not running or if the backup fails
   ///
@@ -672,14 +2024,17 @@ impl PostgresInstance {
       return Err(database_error("PostgreSQL instance is not running"));
     }
 
-    let program_dir = self.get_program_dir()?;
+    let program_dir = self.tool_dir("pg_basebackup")?;
     let mut connection_config = self.connection_config();
     if let Some(database_name) = database_name {
       connection_config.database = Some(database_name);
     }
-    let tool =
-      PgBasebackupTool::from_connection(connection_config, format!("{program_dir}/bin"), options);
-    tool.execute().await.map_err(|error| error.into())
+    let pgdata = options.pgdata.clone();
+    let lsn = self.backup_lsn(true).await;
+    let tool = PgBasebackupTool::from_connection(connection_config, program_dir, options);
+    let result = tool.execute().await?;
+    self.record_backup("basebackup", &pgdata, None, lsn, &result);
+    Ok(result)
   }
 
   /// # Safety
@@ -689,7 +2044,7 @@ impl PostgresInstance {
   /// file created by pg_dump. The instance must be running before calling this method.
   ///
   /// @param options - Configuration options for pg_restore
-  /// @param database_name - Optional name of the database to restore to (defaults to 'postgres')
+  /// @param database_name - Optional name of the database to restore to (defaults to the configured databaseName)
   /// @returns Promise that resolves with the execution result when the restore is complete
   /// @throws Error if the instance is not running or if the restore fails
   ///
@@ -713,25 +2068,253 @@ impl PostgresInstance {
       return Err(database_error("PostgreSQL instance is not running"));
     }
 
-    let program_dir = self.get_program_dir()?;
+    let program_dir = self.tool_dir("pg_restore")?;
     let mut connection_config = self.connection_config();
     if let Some(database_name) = database_name {
       connection_config.database = Some(database_name);
     }
-    let tool =
-      PgRestoreTool::from_connection(connection_config, format!("{program_dir}/bin"), options);
+    if let Some(ref target) = connection_config.database {
+      self
+        .save_database_undo_point("createRestore", target)
+        .await?;
+    }
+    let tool = PgRestoreTool::from_connection(connection_config, program_dir, options);
     tool.execute().await.map_err(|error| error.into())
   }
 
-  /// # Safety
-  /// Rewinds a PostgreSQL cluster using pg_rewind
+  /// Restores an archive into a database that is created for it
   ///
-  /// This method executes pg_rewind to synchronize a PostgreSQL cluster with another
-  /// copy of the same cluster, after the clusters' timelines have diverged.
+  /// The database is created (from template0 by default) and the archive is restored with
+  /// `noOwner` and `exitOnError`, so objects belong to the connecting user and the restore
+  /// stops at the first error. Afterwards, the numbers of tables, views, materialized views
+  /// and sequences are compared with the archive's table of contents. If the restore
+  /// fails, the new database is dropped again.
+  ///
+  /// @param archivePath - Archive created by pg_dump in the custom, directory or tar format
+  /// @param databaseName - Name of the database to create; it must not exist yet
+  /// @param options - Template, archive format, parallel jobs and verification
+  /// @returns Promise that resolves to the pg_restore result and the compared object counts
+  /// @throws Error if the instance is not running, the database cannot be created, the
+  /// restore fails or the restored object counts do not match the archive
+  ///
+  /// @example
+  /// ```typescript
+  /// const { objectCounts } = await instance.restoreIntoNewDatabase('./backup.dump', 'restored');
+  /// for (const { objectType, actual } of objectCounts) {
+  ///   console.log(`${objectType}: ${actual}`);
+  /// }
+  /// ```
+  #[napi]
+  pub async fn restore_into_new_database(
+    &self,
+    archive_path: String,
+    database_name: String,
+    options: Option<RestoreIntoNewDatabaseOptions>,
+  ) -> napi::Result<RestoreIntoNewDatabaseResult> {
+    let options = options.unwrap_or_default();
+    if database_name.is_empty() {
+      return Err(database_error("Database name cannot be empty"));
+    }
+
+    let mut create = format!("CREATE DATABASE {}", quote_ident(&database_name));
+    if options.from_template0.unwrap_or(true) {
+      create.push_str(" TEMPLATE template0");
+    }
+    self.query_rows(&create, None).await?;
+
+    let program_dir = self.tool_dir("pg_restore")?;
+    let mut connection_config = self.connection_config();
+    connection_config.database = Some(database_name.clone());
+    let tool = PgRestoreTool::from_connection(
+      connection_config,
+      program_dir,
+      PgRestoreConfig {
+        file: archive_path,
+        format: options.format,
+        jobs: options.jobs,
+        no_owner: Some(true),
+        exit_on_error: Some(true),
+        ..Default::default()
+      },
+    );
+    let restored = async {
+      let result = tool.execute().await?;
+      if result.exit_code != 0 {
+        return Err(database_error(&format!(
+          "Failed to restore into database '{database_name}': {}",
+          result.stderr.trim()
+        )));
+      }
+      Ok(result)
+    }
+    .await;
+    let result = match restored {
+      Ok(result) => result,
+      Err(error) => {
+        let drop = format!("DROP DATABASE IF EXISTS {}", quote_ident(&database_name));
+        if let Err(e) = self.query_rows(&drop, None).await {
+          pg_log!(warn, "Failed to drop database {}: {}", database_name, e);
+        }
+        return Err(error);
+      }
+    };
+
+    let mut object_counts = Vec::new();
+    if options.verify.unwrap_or(true) {
+      let listing = tool.list_archive(&StreamTransform::default()).await?;
+      let rows = self
+        .query_rows(&object_counts_sql(), Some(database_name.clone()))
+        .await?;
+      let actual = rows.first().cloned().unwrap_or_default();
+      for (index, (object_type, _)) in VERIFIED_OBJECT_TYPES.iter().enumerate() {
+        object_counts.push(RestoredObjectCount {
+          object_type: object_type.to_string(),
+          expected: count_toc_objects(&listing, object_type),
+          actual: actual
+            .get(index)
+            .and_then(|count| count.parse().ok())
+            .unwrap_or(0),
+        });
+      }
+      let mismatches: Vec<String> = object_counts
+        .iter()
+        .filter(|count| count.expected != count.actual)
+        .map(|count| {
+          format!(
+            "{} (expected {}, found {})",
+            count.object_type, count.expected, count.actual
+          )
+        })
+        .collect();
+      if !mismatches.is_empty() {
+        return Err(database_error(&format!(
+          "Restored database '{database_name}' does not match the archive: {}",
+          mismatches.join(", ")
+        )));
+      }
+    }
+
+    Ok(RestoreIntoNewDatabaseResult {
+      database_name,
+      result,
+      object_counts,
+    })
+  }
+
+  /// Checks that a backup can be restored, in a throwaway instance
+  ///
+  /// A temporary instance of the same version is started, the archive is restored into a
+  /// new database as with `restoreIntoNewDatabase()`, and the checks are run against it.
+  /// The instance and its data directory are removed afterwards, whatever the outcome.
+  /// This instance does not have to be running.
+  ///
+  /// @param archivePath - Archive created by pg_dump in the custom, directory or tar format
+  /// @param options - Checks to run, database name, archive format and parallel jobs
+  /// @returns Promise that resolves to the report; a failed restore or check is reported,
+  /// not thrown
+  /// @throws Error if the throwaway instance cannot be started
+  ///
+  /// @example
+  /// ```typescript
+  /// const report = await instance.verifyBackup('./backup.dump', {
+  ///   checks: ['SELECT count(*) > 0 FROM users', "SELECT to_regclass('public.orders') IS NOT NULL"],
+  /// });
+  /// if (!report.passed) {
+  ///   console.error(report.error ?? report.checks.filter((check) => !check.passed));
+  /// }
+  /// ```
+  #[napi]
+  pub async fn verify_backup(
+    &self,
+    archive_path: String,
+    options: Option<VerifyBackupOptions>,
+  ) -> napi::Result<BackupVerification> {
+    let options = options.unwrap_or_default();
+    let started = Instant::now();
+    let database_name = options
+      .database_name
+      .clone()
+      .unwrap_or_else(|| "verify_backup".to_string());
+
+    let mut scratch = Self::new(Some(PostgresSettings {
+      version: Some(self.settings.version.to_string()),
+      port: Some(0),
+      username: Some(self.settings.username.clone()),
+      password: Some(self.client_password()),
+      installation_dir: Some(self.settings.installation_dir.to_string_lossy().to_string()),
+      // Restored objects may depend on preloaded libraries and other server settings
+      server_config: Some(self.settings.configuration.clone()),
+      server_env: Some(self.server_env.clone()),
+      ..Default::default()
+    }))?;
+    pg_log!(
+      info,
+      "Verifying backup {} in {}",
+      archive_path,
+      scratch.log_name()
+    );
+
+    let verification = async {
+      unsafe { scratch.start(Some(true)) }.await?;
+      let restored = scratch
+        .restore_into_new_database(
+          archive_path.clone(),
+          database_name.clone(),
+          Some(RestoreIntoNewDatabaseOptions {
+            format: options.format,
+            jobs: options.jobs,
+            ..Default::default()
+          }),
+        )
+        .await;
+      let restored = match restored {
+        Ok(restored) => restored,
+        Err(e) => {
+          return Ok(BackupVerification {
+            passed: false,
+            restored: false,
+            error: Some(e.reason.clone()),
+            object_counts: Vec::new(),
+            checks: Vec::new(),
+            duration_ms: 0.0,
+          })
+        }
+      };
+
+      let mut connection = scratch.connection_config();
+      connection.database = Some(database_name.clone());
+      let mut checks = Vec::new();
+      for sql in options.checks.iter().flatten() {
+        checks.push(backup_verify::run_check(&connection, sql).await);
+      }
+      Ok::<_, napi::Error>(BackupVerification {
+        passed: checks.iter().all(|check| check.passed),
+        restored: true,
+        error: None,
+        object_counts: restored.object_counts,
+        checks,
+        duration_ms: 0.0,
+      })
+    }
+    .await;
+
+    if let Err(e) = unsafe { scratch.cleanup() }.await {
+      pg_log!(warn, "Failed to clean up {}: {}", scratch.log_name(), e);
+    }
+    let mut verification = verification?;
+    verification.duration_ms = started.elapsed().as_secs_f64() * 1000.0;
+    Ok(verification)
+  }
+
+  /// # Safety
+  /// Rewinds a PostgreSQL cluster using pg_rewind
+  ///
+  /// This method executes pg_rewind to synchronize a PostgreSQL cluster with another
+  /// copy of the same cluster, after the clusters' timelines have diverged.
   /// The instance must be running before calling this method.
   ///
   /// @param options - Configuration options for pg_rewind
-  /// @param database_name - Optional name of the database to connect to (defaults to 'postgres')
+  /// @param database_name - Optional name of the database to connect to (defaults to the configured databaseName)
   /// @returns Promise that resolves with the execution result when the rewind is complete
   /// @throws Error if the instance is not running or if the rewind fails
   ///
@@ -754,13 +2337,13 @@ impl PostgresInstance {
       return Err(database_error("PostgreSQL instance is not running"));
     }
 
-    let program_dir = self.get_program_dir()?;
+    let program_dir = self.tool_dir("pg_rewind")?;
+    self.save_data_dir_undo_point("createRewind", &options.target_pgdata)?;
     let mut connection_config = self.connection_config();
     if let Some(database_name) = database_name {
       connection_config.database = Some(database_name);
     }
-    let tool =
-      PgRewindTool::from_connection(connection_config, format!("{program_dir}/bin"), options);
+    let tool = PgRewindTool::from_connection(connection_config, program_dir, options);
     tool.execute().await.map_err(|error| error.into())
   }
 
@@ -794,13 +2377,65 @@ impl PostgresInstance {
       return Err(database_error("PostgreSQL instance is not running"));
     }
 
-    let program_dir = self.get_program_dir()?;
-    let tool = PgDumpallTool::from_connection(
-      self.connection_config(),
-      format!("{program_dir}/bin"),
-      options,
-    );
-    tool.execute().await.map_err(|error| error.into())
+    let program_dir = self.tool_dir("pg_dumpall")?;
+    let file = options.file.clone();
+    let lsn = self.backup_lsn(file.is_some()).await;
+    let tool = PgDumpallTool::from_connection(self.connection_config(), program_dir, options);
+    let result = tool.execute().await?;
+    if let Some(file) = file {
+      self.record_backup("dumpall", &file, None, lsn, &result);
+    }
+    Ok(result)
+  }
+
+  /// Lists the backups made from this instance
+  ///
+  /// `createDump()` and `createDumpall()` with a `file`, and `createBaseBackup()`, record
+  /// every backup they make in a catalog in the installation directory, with its path,
+  /// size, database and the WAL position it started at. Restore tooling can look up what
+  /// exists there instead of scanning directories. Backups made by other means are not
+  /// listed.
+  ///
+  /// @returns The recorded backups, newest first
+  /// @throws Error if the catalog cannot be read
+  ///
+  /// @example
+  /// ```typescript
+  /// const [latest] = instance.listBackups().filter((backup) => backup.kind === 'dump');
+  /// await instance.restoreIntoNewDatabase(latest.path, 'restored');
+  /// ```
+  #[napi]
+  pub fn list_backups(&self) -> napi::Result<Vec<BackupEntry>> {
+    let data_dir = self.settings.data_dir.to_string_lossy();
+    Ok(backup_catalog::list_backups(
+      &self.backup_catalog_path(),
+      &data_dir,
+    )?)
+  }
+
+  /// Removes old backups of this instance from the catalog and the disk
+  ///
+  /// Backups are removed when they exceed `keepLast` for their kind or are older than
+  /// `maxAgeMs`; backups whose files no longer exist are always removed from the catalog.
+  /// Without a policy only those are removed.
+  ///
+  /// @param policy - Which backups to keep
+  /// @returns The removed backups
+  /// @throws Error if the catalog cannot be updated or a backup cannot be deleted
+  ///
+  /// @example
+  /// ```typescript
+  /// // Keep the three newest dumps and base backups, and nothing older than a week
+  /// instance.pruneBackups({ keepLast: 3, maxAgeMs: 7 * 24 * 60 * 60 * 1000 });
+  /// ```
+  #[napi]
+  pub fn prune_backups(&self, policy: Option<BackupPrunePolicy>) -> napi::Result<Vec<BackupEntry>> {
+    let data_dir = self.settings.data_dir.to_string_lossy();
+    Ok(backup_catalog::prune_backups(
+      &self.backup_catalog_path(),
+      &data_dir,
+      &policy.unwrap_or_default(),
+    )?)
   }
 
   /// # Safety
@@ -811,7 +2446,7 @@ impl PostgresInstance {
   ///
   /// @param sql - The SQL command(s) to execute
   /// @param options - Configuration options for psql
-  /// @param database_name - Optional database name to connect to (defaults to 'postgres')
+  /// @param database_name - Optional database name to connect to (defaults to the configured databaseName)
   /// @returns Promise that resolves with the execution result
   /// @throws Error if the instance is not running or if the execution fails
   ///
@@ -832,18 +2467,164 @@ impl PostgresInstance {
       return Err(database_error("PostgreSQL instance is not running"));
     }
 
-    let program_dir = self.get_program_dir()?;
+    let program_dir = self.tool_dir("psql")?;
     let mut connection_config = self.connection_config();
     if let Some(database_name) = database_name {
       connection_config.database = Some(database_name);
     }
-    let tool = PsqlTool::from_connection(connection_config, format!("{program_dir}/bin"), options);
+    let tool = PsqlTool::from_connection(connection_config, program_dir, options);
     tool
       .execute_command(sql)
       .await
       .map_err(|error| error.into())
   }
 
+  /// Executes the statements of a SQL script one by one on a native connection
+  ///
+  /// The script is split with `splitSqlScript()`, so semicolons inside string literals,
+  /// dollar-quoted function bodies and comments do not break statements apart, and
+  /// `COPY ... FROM STDIN` statements are fed the data lines that follow them. All
+  /// statements run in one session. psql meta-commands such as `\connect` are not
+  /// supported; run scripts containing them with `executeSql()`.
+  ///
+  /// @param script - The SQL script
+  /// @param options - Whether to use a transaction and whether to continue after errors
+  /// @param database_name - Optional database name to connect to (defaults to the configured databaseName)
+  /// @returns Promise that resolves with the outcome of every statement, in script order
+  /// @throws Error if the instance is not running, the connection fails or, unless
+  /// `continueOnError` is set, a statement fails; the message names the statement's line
+  ///
+  /// @example
+  /// ```typescript
+  /// const results = await instance.executeSqlBatch(fs.readFileSync('seed.sql', 'utf8'), {
+  ///   transaction: true,
+  /// });
+  /// console.log(`${results.length} statements applied`);
+  /// ```
+  #[napi]
+  pub async fn execute_sql_batch(
+    &self,
+    script: String,
+    options: Option<ExecuteSqlBatchOptions>,
+    database_name: Option<String>,
+  ) -> napi::Result<Vec<SqlStatementResult>> {
+    if !matches!(self.get_state()?, InstanceState::Running) {
+      return Err(database_error("PostgreSQL instance is not running"));
+    }
+    let options = options.unwrap_or_default();
+    let statements = split_script(&script);
+    let mut connection_config = self.connection_config();
+    if let Some(database_name) = database_name {
+      connection_config.database = Some(database_name);
+    }
+    let logger = self.query_logger(&connection_config);
+    Ok(client::run_script(&connection_config, &statements, &options, logger.as_ref()).await?)
+  }
+
+  /// Executes SQL on a native connection and returns the rows as objects
+  ///
+  /// Unlike `executeSql()`, no psql output has to be parsed: each row maps column names
+  /// to values in their text form, with NULL as null. With several statements the result
+  /// is that of the last one. psql meta-commands are not supported.
+  ///
+  /// @param sql - The SQL command(s) to execute
+  /// @param database_name - Optional database name to connect to (defaults to the configured databaseName)
+  /// @param params - Values of the parameters `$1`, `$2`, ... of the SQL; null binds NULL
+  /// @returns Promise that resolves to the columns, rows and row count
+  /// @throws Error if the instance is not running, the connection fails or the SQL fails
+  ///
+  /// @example
+  /// ```typescript
+  /// const { rows, rowCount } = await instance.executeSqlStructured(
+  ///   'SELECT id, email FROM users WHERE created_at > $1',
+  ///   undefined,
+  ///   ['2024-01-01'],
+  /// );
+  /// console.log(rowCount, rows[0].email);
+  /// ```
+  #[napi]
+  pub async fn execute_sql_structured(
+    &self,
+    sql: String,
+    database_name: Option<String>,
+    params: Option<Vec<Option<SqlValue>>>,
+  ) -> napi::Result<StructuredSqlResult> {
+    if !matches!(self.get_state()?, InstanceState::Running) {
+      return Err(database_error("PostgreSQL instance is not running"));
+    }
+    let mut connection_config = self.connection_config();
+    if let Some(database_name) = database_name {
+      connection_config.database = Some(database_name);
+    }
+    let logger = self.query_logger(&connection_config);
+    let result = client::run_query_once(
+      &connection_config,
+      &sql,
+      &params.unwrap_or_default(),
+      logger.as_ref(),
+    )
+    .await?;
+    Ok(result.into())
+  }
+
+  /// Executes SQL on every database of the cluster
+  ///
+  /// The SQL runs once per database that accepts connections, in name order, which is
+  /// convenient for cluster-wide maintenance such as `CREATE EXTENSION` or `GRANT`.
+  /// Template databases are skipped. A failure on one database does not stop the others;
+  /// check the `exitCode` of each result.
+  ///
+  /// @param sql - The SQL command(s) to execute
+  /// @param options - Databases to skip and psql options
+  /// @returns Promise that resolves with one result per database
+  /// @throws Error if the instance is not running or if the databases cannot be listed
+  ///
+  /// @example
+  /// ```typescript
+  /// const results = await instance.executeSqlOnAllDatabases('CREATE EXTENSION IF NOT EXISTS pg_trgm;', {
+  ///   exclude: ['postgres'],
+  /// });
+  /// for (const { databaseName, result } of results) {
+  ///   console.log(databaseName, result.exitCode);
+  /// }
+  /// ```
+  #[napi]
+  pub async fn execute_sql_on_all_databases(
+    &self,
+    sql: String,
+    options: Option<ExecuteOnAllDatabasesOptions>,
+  ) -> napi::Result<Vec<DatabaseSqlResult>> {
+    let options = options.unwrap_or_default();
+    let exclude = options.exclude.unwrap_or_default();
+    let rows = self
+      .query_rows(
+        "SELECT datname FROM pg_database WHERE datallowconn AND NOT datistemplate ORDER BY datname",
+        None,
+      )
+      .await?;
+
+    let program_dir = self.tool_dir("psql")?;
+    let mut results = Vec::new();
+    for database_name in rows.into_iter().filter_map(|row| row.into_iter().next()) {
+      if exclude.contains(&database_name) {
+        continue;
+      }
+      let mut connection_config = self.connection_config();
+      connection_config.database = Some(database_name.clone());
+      let tool = PsqlTool::from_connection(
+        connection_config,
+        program_dir.clone(),
+        options.psql.clone().unwrap_or_default(),
+      );
+      let result = tool.execute_command(sql.clone()).await?;
+      results.push(DatabaseSqlResult {
+        database_name,
+        result,
+      });
+    }
+    Ok(results)
+  }
+
   /// # Safety
   /// Executes SQL commands from a file using psql
   ///
@@ -852,7 +2633,7 @@ impl PostgresInstance {
   ///
   /// @param file_path - Path to the SQL file to execute
   /// @param options - Configuration options for psql
-  /// @param database_name - Optional database name to connect to (defaults to 'postgres')
+  /// @param database_name - Optional database name to connect to (defaults to the configured databaseName)
   /// @returns Promise that resolves with the execution result
   /// @throws Error if the instance is not running, if the file doesn't exist, or if the execution fails
   ///
@@ -873,12 +2654,12 @@ impl PostgresInstance {
       return Err(database_error("PostgreSQL instance is not running"));
     }
 
-    let program_dir = self.get_program_dir()?;
+    let program_dir = self.tool_dir("psql")?;
     let mut connection_config = self.connection_config();
     if let Some(database_name) = database_name {
       connection_config.database = Some(database_name);
     }
-    let tool = PsqlTool::from_connection(connection_config, format!("{program_dir}/bin"), options);
+    let tool = PsqlTool::from_connection(connection_config, program_dir, options);
     tool
       .execute_file(file_path)
       .await
@@ -906,6 +2687,7 @@ impl PostgresInstance {
     if name.is_empty() {
       return Err(database_error("Database name cannot be empty"));
     }
+    self.save_database_undo_point("dropDatabase", &name).await?;
 
     if let Some(ref mut instance) = self.async_instance {
       match instance.drop_database(&name).await {
@@ -917,6 +2699,85 @@ impl PostgresInstance {
     }
   }
 
+  /// Drops a database if it exists
+  ///
+  /// @param name - The name of the database to drop
+  /// @returns Promise that resolves to true if the database was dropped, false if it did not exist
+  /// @throws Error if the instance is not running or if database deletion fails, e.g.
+  /// because clients are still connected to it
+  ///
+  /// @example
+  /// ```typescript
+  /// await instance.dropDatabaseIfExists('myapp_test');
+  /// ```
+  #[napi]
+  pub async fn drop_database_if_exists(&self, name: String) -> napi::Result<bool> {
+    let existed = self.database_exists(name.clone()).await?;
+    if existed {
+      self
+        .save_database_undo_point("dropDatabaseIfExists", &name)
+        .await?;
+    }
+    self
+      .query_rows(
+        &format!("DROP DATABASE IF EXISTS {}", quote_ident(&name)),
+        None,
+      )
+      .await?;
+    Ok(existed)
+  }
+
+  /// Reverts the most recent destructive operation recorded in safe mode
+  ///
+  /// With the `safeMode` setting, `dropDatabase()`, `dropDatabaseIfExists()` and
+  /// `createRestore()` dump the affected database first, and `createRewind()` copies the
+  /// target data directory. Undoing drops the database if it exists and recreates it from
+  /// the dump, or puts the data directory copy back in place; a rewound cluster must be
+  /// stopped. Undo points are kept until they are used or the instance is cleaned up.
+  ///
+  /// @returns Promise that resolves to the reverted operation
+  /// @throws Error if there is nothing to undo or restoring fails; the undo point is kept
+  /// so it can be retried
+  ///
+  /// @example
+  /// ```typescript
+  /// const instance = new PostgresInstance({ persistent: true, dataDir: './pgdata', safeMode: true });
+  /// await instance.start();
+  /// await instance.dropDatabase('app');
+  /// await instance.undoLastDestructiveOp(); // 'app' is back
+  /// ```
+  #[napi]
+  pub async fn undo_last_destructive_op(&self) -> napi::Result<UndoneOperation> {
+    let undo_point = self
+      .undo_points
+      .lock()
+      .map_err(|_| setup_error("Failed to acquire undo point lock"))?
+      .pop()
+      .ok_or_else(|| database_error("There is no destructive operation to undo"))?;
+
+    let restored = match &undo_point.action {
+      UndoAction::RestoreDatabase {
+        database,
+        dump_file,
+      } => self.restore_database_dump(database, dump_file).await,
+      UndoAction::RestoreDataDir { data_dir, snapshot } => {
+        undo::restore_dir(snapshot, data_dir).map_err(napi::Error::from)
+      }
+    };
+    if let Err(e) = restored {
+      self.push_undo_point(undo_point)?;
+      return Err(e);
+    }
+    undo_point.discard();
+    pg_log!(
+      info,
+      "Undid {} on {}",
+      undo_point.operation,
+      self.log_name()
+    );
+    Ok(undo_point.undone())
+  }
+
   /// Checks if a database exists asynchronously
   ///
   /// @param name - The name of the database to check
@@ -951,116 +2812,2217 @@ impl PostgresInstance {
     }
   }
 
-  /// # Safety
-  /// Starts the PostgreSQL instance asynchronously with a timeout
+  /// Creates a schema in a database
   ///
-  /// @param timeout_seconds - Maximum time to wait for startup in seconds
-  /// @returns Promise that resolves when the instance is started and ready
-  /// @throws Error if the instance is already running, if startup fails, or if timeout is exceeded
+  /// @param database_name - Optional database to create the schema in (defaults to the configured databaseName)
+  /// @param name - Name of the schema
+  /// @param owner - Optional role to own the schema (defaults to the connecting user)
+  /// @param if_not_exists - Do nothing if the schema already exists (default: false)
+  /// @returns Promise that resolves when the schema is created
+  /// @throws Error if the instance is not running, the name is empty or the schema exists
+  /// and `ifNotExists` is not set
   ///
   /// @example
   /// ```typescript
-  /// await instance.startWithTimeout(30); // 30 second timeout
+  /// await instance.createSchema(undefined, 'tenant_acme', 'acme_app');
+  /// await instance.createSchema(undefined, 'tenant_acme', 'acme_app', true); // no error
   /// ```
   #[napi]
-  pub async unsafe fn start_with_timeout(&mut self, timeout_seconds: u32) -> napi::Result<()> {
-    let timeout_duration = Duration::from_secs(timeout_seconds as u64);
-
-    pg_log!(
-      info,
-      "Starting PostgreSQL instance with timeout of {} seconds",
-      timeout_seconds
-    );
-
-    // Use tokio::time::timeout to wrap the start operation
-    match tokio::time::timeout(timeout_duration, self.start(Some(true))).await {
-      Ok(result) => result,
-      Err(_) => {
-        pg_log!(
-          error,
-          "PostgreSQL start operation timed out after {} seconds",
-          timeout_seconds
-        );
-        self.set_state(InstanceState::Stopped)?;
-        Err(timeout_error(&format!(
-          "Start operation timed out after {timeout_seconds} seconds"
-        )))
-      }
+  pub async fn create_schema(
+    &self,
+    database_name: Option<String>,
+    name: String,
+    owner: Option<String>,
+    if_not_exists: Option<bool>,
+  ) -> napi::Result<()> {
+    if name.is_empty() {
+      return Err(database_error("Schema name cannot be empty"));
     }
+    let authorization = owner
+      .map(|owner| format!(" AUTHORIZATION {}", quote_ident(&owner)))
+      .unwrap_or_default();
+    let if_not_exists = if if_not_exists.unwrap_or(false) {
+      "IF NOT EXISTS "
+    } else {
+      ""
+    };
+    self
+      .query_rows(
+        &format!(
+          "CREATE SCHEMA {if_not_exists}{}{authorization}",
+          quote_ident(&name)
+        ),
+        database_name,
+      )
+      .await?;
+    Ok(())
   }
 
-  /// # Safety
-  /// Stops the PostgreSQL instance asynchronously with a timeout
+  /// Drops a schema from a database
   ///
-  /// @param timeout_seconds - Maximum time to wait for shutdown in seconds
-  /// @returns Promise that resolves when the instance is stopped
-  /// @throws Error if the instance is already stopped, if stopping fails, or if timeout is exceeded
+  /// @param database_name - Optional database to drop the schema from (defaults to the configured databaseName)
+  /// @param name - Name of the schema
+  /// @param cascade - Also drop the objects in the schema (default: false, which fails if it is not empty)
+  /// @param if_exists - Do nothing if the schema does not exist (default: false)
+  /// @returns Promise that resolves when the schema is dropped
+  /// @throws Error if the instance is not running, the schema does not exist and `ifExists`
+  /// is not set, or it is not empty and `cascade` is not set
   ///
   /// @example
   /// ```typescript
-  /// await instance.stopWithTimeout(10); // 10 second timeout
+  /// await instance.dropSchema(undefined, 'tenant_acme', true);
   /// ```
   #[napi]
-  pub async unsafe fn stop_with_timeout(&mut self, timeout_seconds: u32) -> napi::Result<()> {
-    let timeout_duration = Duration::from_secs(timeout_seconds as u64);
-
-    pg_log!(
-      info,
-      "Stopping PostgreSQL instance with timeout of {} seconds",
-      timeout_seconds
-    );
-
-    // Use tokio::time::timeout to wrap the stop operation
-    match tokio::time::timeout(timeout_duration, self.stop()).await {
-      Ok(result) => result,
-      Err(_) => {
-        pg_log!(
-          error,
-          "PostgreSQL stop operation timed out after {} seconds",
-          timeout_seconds
-        );
-        // In timeout case, we're not sure of actual state, keep current state
-        Err(timeout_error(&format!(
-          "Stop operation timed out after {timeout_seconds} seconds"
-        )))
-      }
+  pub async fn drop_schema(
+    &self,
+    database_name: Option<String>,
+    name: String,
+    cascade: Option<bool>,
+    if_exists: Option<bool>,
+  ) -> napi::Result<()> {
+    if name.is_empty() {
+      return Err(database_error("Schema name cannot be empty"));
     }
+    let behavior = if cascade.unwrap_or(false) {
+      "CASCADE"
+    } else {
+      "RESTRICT"
+    };
+    let if_exists = if if_exists.unwrap_or(false) {
+      "IF EXISTS "
+    } else {
+      ""
+    };
+    self
+      .query_rows(
+        &format!("DROP SCHEMA {if_exists}{} {behavior}", quote_ident(&name)),
+        database_name,
+      )
+      .await?;
+    Ok(())
   }
 
-  /// Gets the startup time of the PostgreSQL instance in seconds
-  ///
-  /// This method returns the time it took for the last successful start operation.
+  /// Installs an extension in a database
   ///
-  /// @returns The startup time in seconds, or null if the instance hasn't been started yet
+  /// @param database_name - Optional database to install the extension in (defaults to the configured databaseName)
+  /// @param name - Name of the extension, e.g. `pg_trgm`
+  /// @param schema - Optional schema for the extension's objects (defaults to the first schema of the search_path)
+  /// @param if_not_exists - Do nothing if the extension is already installed (default: false)
+  /// @returns Promise that resolves when the extension is installed
+  /// @throws Error if the instance is not running, the extension is not available or it is
+  /// already installed and `ifNotExists` is not set
   ///
   /// @example
   /// ```typescript
-  /// await instance.start();
-  /// const startupTime = instance.getStartupTime();
-  /// console.log(`Started in ${startupTime} seconds`);
+  /// await instance.createExtension(undefined, 'pgcrypto', undefined, true);
   /// ```
   #[napi]
-  pub fn get_startup_time(&self) -> Option<f64> {
-    if let Ok(startup_time) = self.startup_time.lock() {
-      startup_time.map(|duration| duration.as_secs_f64())
-    } else {
-      None
+  pub async fn create_extension(
+    &self,
+    database_name: Option<String>,
+    name: String,
+    schema: Option<String>,
+    if_not_exists: Option<bool>,
+  ) -> napi::Result<()> {
+    if name.is_empty() {
+      return Err(database_error("Extension name cannot be empty"));
     }
+    let if_not_exists = if if_not_exists.unwrap_or(false) {
+      "IF NOT EXISTS "
+    } else {
+      ""
+    };
+    let schema = schema
+      .map(|schema| format!(" SCHEMA {}", quote_ident(&schema)))
+      .unwrap_or_default();
+    self
+      .query_rows(
+        &format!(
+          "CREATE EXTENSION {if_not_exists}{}{schema}",
+          quote_ident(&name)
+        ),
+        database_name,
+      )
+      .await?;
+    Ok(())
   }
 
-  /// Clears the connection information cache
+  /// Removes an extension from a database
   ///
-  /// This forces the next call to connectionInfo to regenerate the connection information.
+  /// @param database_name - Optional database to remove the extension from (defaults to the configured databaseName)
+  /// @param name - Name of the extension
+  /// @param cascade - Also drop the objects depending on the extension (default: false)
+  /// @param if_exists - Do nothing if the extension is not installed (default: false)
+  /// @returns Promise that resolves when the extension is removed
+  /// @throws Error if the instance is not running, the extension is not installed and
+  /// `ifExists` is not set, or objects depend on it and `cascade` is not set
   ///
-  /// @returns void
+  /// @example
+  /// ```typescript
+  /// await instance.dropExtension(undefined, 'pgcrypto', false, true);
+  /// ```
   #[napi]
-  pub fn clear_connection_cache(&self) -> napi::Result<()> {
-    if let Ok(mut cache) = self.connection_cache.lock() {
-      *cache = None;
-      pg_log!(
-        debug,
+  pub async fn drop_extension(
+    &self,
+    database_name: Option<String>,
+    name: String,
+    cascade: Option<bool>,
+    if_exists: Option<bool>,
+  ) -> napi::Result<()> {
+    if name.is_empty() {
+      return Err(database_error("Extension name cannot be empty"));
+    }
+    let behavior = if cascade.unwrap_or(false) {
+      "CASCADE"
+    } else {
+      "RESTRICT"
+    };
+    let if_exists = if if_exists.unwrap_or(false) {
+      "IF EXISTS "
+    } else {
+      ""
+    };
+    self
+      .query_rows(
+        &format!(
+          "DROP EXTENSION {if_exists}{} {behavior}",
+          quote_ident(&name)
+        ),
+        database_name,
+      )
+      .await?;
+    Ok(())
+  }
+
+  /// Lists the user schemas of a database
+  ///
+  /// System schemas (`pg_catalog`, `information_schema`, `pg_toast` and temporary schemas)
+  /// are left out.
+  ///
+  /// @param database_name - Optional database to list (defaults to the configured databaseName)
+  /// @returns Promise that resolves to the schemas, sorted by name in byte order
+  /// @throws Error if the instance is not running or if the query fails
+  ///
+  /// @example
+  /// ```typescript
+  /// const tenants = (await instance.listSchemas()).filter((schema) => schema.name.startsWith('tenant_'));
+  /// ```
+  #[napi]
+  pub async fn list_schemas(&self, database_name: Option<String>) -> napi::Result<Vec<SchemaInfo>> {
+    let rows = self
+      .query_rows(
+        "SELECT nspname, pg_get_userbyid(nspowner) FROM pg_namespace \
+         WHERE nspname NOT LIKE 'pg\\_%' AND nspname <> 'information_schema' \
+         ORDER BY nspname COLLATE \"C\"",
+        database_name,
+      )
+      .await?;
+    Ok(
+      rows
+        .into_iter()
+        .filter(|row| row.len() == 2)
+        .map(|row| SchemaInfo {
+          name: row[0].clone(),
+          owner: row[1].clone(),
+        })
+        .collect(),
+    )
+  }
+
+  /// Creates a role that can log in, or a group role with `login: false`
+  ///
+  /// Every attribute is set explicitly, so the role only gets the privileges asked for.
+  /// The password is sent over a native connection rather than on a command line and is
+  /// hashed by the server with its `password_encryption` method.
+  ///
+  /// @param options - Name, password and attributes of the role
+  /// @returns Promise that resolves when the role is created
+  /// @throws Error if the instance is not running, the name is empty or the role exists
+  ///
+  /// @example
+  /// ```typescript
+  /// await instance.createUser({ name: 'app', password: 's3cret', createdb: true });
+  /// const users = await instance.listUsers();
+  /// ```
+  #[napi]
+  pub async fn create_user(&self, options: CreateUserOptions) -> napi::Result<()> {
+    let sql = roles::create_user_sql(&options)?;
+    self.run_role_sql(&sql).await?;
+    pg_log!(info, "Created role {} on {}", options.name, self.log_name());
+    Ok(())
+  }
+
+  /// Sets or removes the password of a role
+  ///
+  /// @param name - Name of the role
+  /// @param password - The new password, or null to remove the password
+  /// @returns Promise that resolves when the password is changed
+  /// @throws Error if the instance is not running or the role does not exist
+  ///
+  /// @example
+  /// ```typescript
+  /// await instance.alterUserPassword('app', 'rotated');
+  /// ```
+  #[napi]
+  pub async fn alter_user_password(
+    &self,
+    name: String,
+    password: Option<String>,
+  ) -> napi::Result<()> {
+    let sql = roles::alter_password_sql(&name, password.as_deref())?;
+    self.run_role_sql(&sql).await
+  }
+
+  /// Drops a role
+  ///
+  /// A role that owns objects or holds privileges cannot be dropped; reassign or drop
+  /// them first, e.g. with `REASSIGN OWNED BY app TO postgres; DROP OWNED BY app;` in every
+  /// database.
+  ///
+  /// @param name - Name of the role
+  /// @returns Promise that resolves when the role is dropped
+  /// @throws Error if the instance is not running, the role does not exist or is still in use
+  ///
+  /// @example
+  /// ```typescript
+  /// await instance.dropUser('app');
+  /// ```
+  #[napi]
+  pub async fn drop_user(&self, name: String) -> napi::Result<()> {
+    let sql = roles::drop_user_sql(&name)?;
+    self.run_role_sql(&sql).await?;
+    pg_log!(info, "Dropped role {} on {}", name, self.log_name());
+    Ok(())
+  }
+
+  /// Lists the roles of the cluster
+  ///
+  /// The predefined `pg_*` roles are left out, while the superuser of the instance is
+  /// included.
+  ///
+  /// @returns Promise that resolves to the roles, sorted by name
+  /// @throws Error if the instance is not running or if the query fails
+  ///
+  /// @example
+  /// ```typescript
+  /// const logins = (await instance.listUsers()).filter((user) => user.login);
+  /// ```
+  #[napi]
+  pub async fn list_users(&self) -> napi::Result<Vec<UserInfo>> {
+    let rows = self.query_rows(roles::LIST_USERS_SQL, None).await?;
+    Ok(roles::parse_users(&rows))
+  }
+
+  /// Creates a manager that provisions tenants of this instance from a template
+  ///
+  /// With `TenantStrategy.Schema` every tenant gets a copy of a template schema in a shared
+  /// database; with `TenantStrategy.Database` every tenant gets a copy of a template
+  /// database. The manager keeps track of the tenants it provisioned and hands out a
+  /// connection string for each.
+  ///
+  /// @param options - Strategy, template and naming of the tenants
+  /// @returns A tenant manager for this instance
+  /// @throws Error if the instance is not running or the options are invalid
+  ///
+  /// @example
+  /// ```typescript
+  /// await instance.executeSql('CREATE SCHEMA template; CREATE TABLE template.users (id int);', {});
+  /// const tenants = instance.createTenantManager({ strategy: TenantStrategy.Schema, template: 'template' });
+  /// const acme = await tenants.provision('acme');
+  /// // acme.schema === 'tenant_acme'
+  /// ```
+  #[napi]
+  pub fn create_tenant_manager(
+    &self,
+    options: TenantManagerOptions,
+  ) -> napi::Result<TenantManager> {
+    if !matches!(self.get_state()?, InstanceState::Running) {
+      return Err(database_error("PostgreSQL instance is not running"));
+    }
+    let program_dir = self.bin_dir()?.program_dir_for(&["pg_dump", "psql"])?;
+    Ok(TenantManager::new(
+      self.connection_config(),
+      program_dir,
+      options,
+    )?)
+  }
+
+  /// Cancels the query currently running in the given backend
+  ///
+  /// This is a wrapper around `pg_cancel_backend`. The backend stays connected;
+  /// only its current statement is aborted with a cancellation error.
+  ///
+  /// @param pid - Process ID of the backend whose query should be cancelled
+  /// @returns Promise that resolves to true if the cancel signal was sent, false otherwise
+  /// @throws Error if the instance is not running or if the cancellation fails
+  ///
+  /// @example
+  /// ```typescript
+  /// const cancelled = await instance.cancelQuery(12345);
+  /// ```
+  #[napi]
+  pub async fn cancel_query(&self, pid: i32) -> napi::Result<bool> {
+    let rows = self
+      .query_rows(&format!("SELECT pg_cancel_backend({pid})"), None)
+      .await?;
+    Ok(first_value(&rows) == Some("t"))
+  }
+
+  /// Cancels all running queries, optionally limited to one database
+  ///
+  /// Every active client backend except the one issuing the cancellation receives
+  /// `pg_cancel_backend`. Connections stay open.
+  ///
+  /// @param database_name - Optional database whose queries should be cancelled (defaults to all databases)
+  /// @returns Promise that resolves to the number of backends that were signalled
+  /// @throws Error if the instance is not running or if the cancellation fails
+  ///
+  /// @example
+  /// ```typescript
+  /// const count = await instance.cancelAllQueries('myapp');
+  /// console.log(`Cancelled ${count} queries`);
+  /// ```
+  #[napi]
+  pub async fn cancel_all_queries(&self, database_name: Option<String>) -> napi::Result<u32> {
+    let database_filter = match database_name {
+      Some(name) => format!("AND datname = {}", quote_literal(&name)),
+      None => String::new(),
+    };
+    let sql = format!(
+      "SELECT count(*) FROM (SELECT pg_cancel_backend(pid) AS cancelled FROM pg_stat_activity \
+       WHERE pid <> pg_backend_pid() AND backend_type = 'client backend' \
+       AND state = 'active' {database_filter}) c WHERE cancelled"
+    );
+    let rows = self.query_rows(&sql, None).await?;
+    first_value(&rows)
+      .and_then(|count| count.parse().ok())
+      .ok_or_else(|| database_error("Unexpected result from pg_cancel_backend"))
+  }
+
+  /// Copies a database into another instance by piping pg_dump into psql
+  ///
+  /// The plain SQL dump is streamed from this instance's pg_dump through an optional
+  /// filter into the target instance's psql, which stops at the first error. No
+  /// temporary file is written, so databases of any size can be copied. The target
+  /// database must exist.
+  ///
+  /// @param target - The running instance to load the dump into
+  /// @param options - Source and target databases, extra pg_dump arguments and a filter
+  /// @returns Promise that resolves to the outcome of the pg_dump, filter and psql stages;
+  /// check `success` and `failedStage`
+  /// @throws Error if either instance is not running, a tool cannot be found or a tool
+  /// runs longer than `timeout`
+  ///
+  /// @example
+  /// ```typescript
+  /// const result = await source.pipeDatabaseTo(target, {
+  ///   filter: { replace: [{ from: 'OWNER TO legacy', to: 'OWNER TO postgres' }] },
+  /// });
+  /// if (!result.success) {
+  ///   const failed = result.stages.find((stage) => stage.name === result.failedStage);
+  ///   console.error(failed?.stderr);
+  /// }
+  /// ```
+  #[napi]
+  pub async fn pipe_database_to(
+    &self,
+    target: &PostgresInstance,
+    options: Option<PipeDatabaseOptions>,
+  ) -> napi::Result<PipelineResult> {
+    if !matches!(self.get_state()?, InstanceState::Running) {
+      return Err(database_error("PostgreSQL instance is not running"));
+    }
+    if !matches!(target.get_state()?, InstanceState::Running) {
+      return Err(database_error(
+        "The target PostgreSQL instance is not running",
+      ));
+    }
+    let options = options.unwrap_or_default();
+    let mut source_connection = self.connection_config();
+    if let Some(database) = options.database {
+      source_connection.database = Some(database);
+    }
+    let mut target_connection = target.connection_config();
+    if let Some(database) = options.target_database {
+      target_connection.database = Some(database);
+    }
+
+    let mut stages = vec![PipelineStage {
+      tool: Some("pg_dump".to_string()),
+      program_dir: Some(self.tool_dir("pg_dump")?),
+      args: options.dump_args,
+      connection: Some(source_connection),
+      timeout: options.timeout,
+      ..Default::default()
+    }];
+    if let Some(filter) = options.filter {
+      stages.push(PipelineStage {
+        filter: Some(filter),
+        ..Default::default()
+      });
+    }
+    stages.push(PipelineStage {
+      tool: Some("psql".to_string()),
+      program_dir: Some(target.tool_dir("psql")?),
+      args: Some(
+        ["-X", "-q", "-v", "ON_ERROR_STOP=1"]
+          .iter()
+          .map(|arg| arg.to_string())
+          .collect(),
+      ),
+      connection: Some(target_connection),
+      timeout: options.timeout,
+      ..Default::default()
+    });
+    Ok(run_pipeline(stages).await?)
+  }
+
+  /// Makes the tables of another instance queryable from this one through postgres_fdw
+  ///
+  /// Installs the postgres_fdw extension in the local database, creates a foreign server
+  /// pointing at the remote instance with a user mapping for the local user, and imports
+  /// the tables of each remote schema as foreign tables into the local schema
+  /// `<serverName>_<schema>`, e.g. `remote_public`. Everything is created in one
+  /// transaction, so a failed link leaves nothing behind.
+  ///
+  /// @param local_database - Optional local database to link from (defaults to the configured databaseName)
+  /// @param remote - The running instance to link to
+  /// @param options - Server name, remote schemas and remote database
+  /// @returns Promise that resolves to the imported foreign tables, schema-qualified
+  /// @throws Error if either instance is not running or if the setup fails, e.g. because
+  /// the server name is taken
+  ///
+  /// @example
+  /// ```typescript
+  /// await billing.linkInstances(undefined, orders, { serverName: 'orders' });
+  /// await billing.executeSql('SELECT count(*) FROM orders_public.orders', {});
+  /// ```
+  #[napi]
+  pub async fn link_instances(
+    &self,
+    local_database: Option<String>,
+    remote: &PostgresInstance,
+    options: Option<LinkInstancesOptions>,
+  ) -> napi::Result<Vec<String>> {
+    if !matches!(remote.get_state()?, InstanceState::Running) {
+      return Err(database_error(
+        "The remote PostgreSQL instance is not running",
+      ));
+    }
+    let options = options.unwrap_or_default();
+    let server_name = options.server_name.unwrap_or_else(|| "remote".to_string());
+    let schemas = options
+      .schemas
+      .unwrap_or_else(|| vec!["public".to_string()]);
+    let mut connection = remote.connection_config();
+    if let Some(remote_database) = options.remote_database {
+      connection.database = Some(remote_database);
+    }
+
+    self
+      .script_rows(
+        &link::link_script(&server_name, &connection, &schemas),
+        local_database.clone(),
+      )
+      .await?;
+    let rows = self
+      .query_rows(&link::foreign_tables_sql(&server_name), local_database)
+      .await?;
+    Ok(
+      rows
+        .into_iter()
+        .filter_map(|row| row.into_iter().next())
+        .collect(),
+    )
+  }
+
+  /// Runs a query on another database of this instance through dblink
+  ///
+  /// Installs the dblink extension in the source database if needed and runs `sql` in
+  /// `target_database` over a dblink connection, the way applications relying on
+  /// cross-database access do. The rows are returned with their column names, so no
+  /// column definition list is needed.
+  ///
+  /// @param target_database - Database to run the query in
+  /// @param sql - A single query returning rows
+  /// @param source_database - Optional database dblink is installed in and called from (defaults to the configured databaseName)
+  /// @returns Promise that resolves to the rows of the query, values in their text form
+  /// @throws Error if the instance is not running or if the query fails
+  ///
+  /// @example
+  /// ```typescript
+  /// const { columns, rows } = await instance.dblinkQuery('reporting', 'SELECT id, name FROM customers');
+  /// ```
+  #[napi]
+  pub async fn dblink_query(
+    &self,
+    target_database: String,
+    sql: String,
+    source_database: Option<String>,
+  ) -> napi::Result<QueryResult> {
+    self
+      .query_rows(
+        "CREATE EXTENSION IF NOT EXISTS dblink",
+        source_database.clone(),
+      )
+      .await?;
+    let mut connection = self.connection_config();
+    connection.database = Some(target_database);
+    let sql = link::dblink_sql(
+      &format_conninfo(&connection),
+      sql.trim().trim_end_matches(';'),
+    );
+    let rows = self.query_rows(&sql, source_database).await?;
+    Ok(link::parse_dblink_rows(&rows))
+  }
+
+  /// Lists backends waiting for locks together with the backends blocking them
+  ///
+  /// Each waiting backend is reported once per blocker, as found by
+  /// `pg_blocking_pids()`, with the queries of both sides and the relations involved, so
+  /// concurrency tests can assert on lock contention and debug hanging queries.
+  ///
+  /// @param database_name - Optional database to inspect (defaults to the configured databaseName)
+  /// @returns Promise that resolves to the lock waits, longest waiting first
+  /// @throws Error if the instance is not running or if the query fails
+  ///
+  /// @example
+  /// ```typescript
+  /// for (const wait of await instance.getLockWaits()) {
+  ///   console.log(`${wait.blockedPid} waits for ${wait.blockingPid} on ${wait.relations.join(', ')}`);
+  /// }
+  /// ```
+  #[napi]
+  pub async fn get_lock_waits(&self, database_name: Option<String>) -> napi::Result<Vec<LockWait>> {
+    // pg_locks.waitstart exists from PostgreSQL 14; before that the wait is timed from
+    // the start of the waiting query
+    let wait_start = if self.server_version_num().await? >= 140000 {
+      "coalesce(waiting.waitstart, blocked.state_change)"
+    } else {
+      "blocked.state_change"
+    };
+    // Relation names only resolve in the database the query runs in. A backend waiting
+    // for a row lock holds a granted tuple lock naming the row's table.
+    let sql = format!(
+      "SELECT blocked.pid, blocked.query, blocking.pid, blocking.query, blocking.state, \
+         waiting.locktype, waiting.mode, \
+         (SELECT string_agg(DISTINCT l.relation::regclass::text, E'\\n') FROM pg_locks l \
+          WHERE l.pid = blocked.pid AND l.relation IS NOT NULL \
+          AND (NOT l.granted OR l.locktype = 'tuple')), \
+         extract(epoch FROM now() - {wait_start}) * 1000 \
+         FROM pg_stat_activity blocked \
+         CROSS JOIN LATERAL unnest(pg_blocking_pids(blocked.pid)) AS b(pid) \
+         JOIN pg_stat_activity blocking ON blocking.pid = b.pid \
+         LEFT JOIN LATERAL (SELECT * FROM pg_locks l WHERE l.pid = blocked.pid AND NOT l.granted \
+          LIMIT 1) waiting ON true \
+         WHERE blocked.datname = current_database() \
+         ORDER BY 9 DESC, 1, 3"
+    );
+    let rows = self.query_rows(&sql, database_name).await?;
+    Ok(
+      rows
+        .iter()
+        .filter(|row| row.len() == 9)
+        .map(|row| LockWait {
+          blocked_pid: row[0].parse().unwrap_or_default(),
+          blocked_query: row[1].clone(),
+          blocking_pid: row[2].parse().unwrap_or_default(),
+          blocking_query: row[3].clone(),
+          blocking_state: Some(row[4].clone()).filter(|state| !state.is_empty()),
+          lock_type: Some(row[5].clone()).filter(|lock_type| !lock_type.is_empty()),
+          mode: Some(row[6].clone()).filter(|mode| !mode.is_empty()),
+          relations: row[7].lines().map(str::to_string).collect(),
+          wait_duration_ms: row[8].parse().unwrap_or_default(),
+        })
+        .collect(),
+    )
+  }
+
+  /// Lists the transactions prepared for two-phase commit in all databases
+  ///
+  /// Preparing transactions requires `maxPreparedTransactions` to be set in the settings.
+  ///
+  /// @returns Promise that resolves to the prepared transactions, oldest first
+  /// @throws Error if the instance is not running or if the query fails
+  ///
+  /// @example
+  /// ```typescript
+  /// await instance.executeSql("BEGIN; INSERT INTO payments VALUES (1); PREPARE TRANSACTION 'pay-1';", {});
+  /// const [prepared] = await instance.listPreparedTransactions();
+  /// console.log(prepared.gid, prepared.database);
+  /// ```
+  #[napi]
+  pub async fn list_prepared_transactions(&self) -> napi::Result<Vec<PreparedTransaction>> {
+    let rows = self
+      .query_rows(
+        "SELECT gid, transaction, extract(epoch FROM prepared) * 1000, owner, database \
+         FROM pg_prepared_xacts ORDER BY prepared, gid",
+        None,
+      )
+      .await?;
+    Ok(
+      rows
+        .into_iter()
+        .filter(|row| row.len() == 5)
+        .map(|row| PreparedTransaction {
+          gid: row[0].clone(),
+          transaction: row[1].clone(),
+          prepared: row[2].parse().unwrap_or_default(),
+          owner: row[3].clone(),
+          database: row[4].clone(),
+        })
+        .collect(),
+    )
+  }
+
+  /// Commits a prepared transaction
+  ///
+  /// The transaction is committed in the database it was prepared in.
+  ///
+  /// @param gid - Global identifier of the prepared transaction
+  /// @returns Promise that resolves when the transaction is committed
+  /// @throws Error if the instance is not running or no transaction with that identifier is prepared
+  ///
+  /// @example
+  /// ```typescript
+  /// await instance.commitPrepared('pay-1');
+  /// ```
+  #[napi]
+  pub async fn commit_prepared(&self, gid: String) -> napi::Result<()> {
+    self.finish_prepared(&gid, "COMMIT PREPARED").await
+  }
+
+  /// Rolls back a prepared transaction
+  ///
+  /// The transaction is rolled back in the database it was prepared in.
+  ///
+  /// @param gid - Global identifier of the prepared transaction
+  /// @returns Promise that resolves when the transaction is rolled back
+  /// @throws Error if the instance is not running or no transaction with that identifier is prepared
+  ///
+  /// @example
+  /// ```typescript
+  /// await instance.rollbackPrepared('pay-1');
+  /// ```
+  #[napi]
+  pub async fn rollback_prepared(&self, gid: String) -> napi::Result<()> {
+    self.finish_prepared(&gid, "ROLLBACK PREPARED").await
+  }
+
+  /// Updates planner statistics after a bulk data load
+  ///
+  /// Runs `ANALYZE` (or `VACUUM (ANALYZE)` with `vacuum: true`) on every table of the
+  /// database, so query plans match those of a long-running production database instead
+  /// of the plans chosen for freshly loaded tables without statistics.
+  ///
+  /// @param database_name - Optional database to optimize (defaults to the configured databaseName)
+  /// @param options - Whether to VACUUM as well
+  /// @returns Promise that resolves when the statistics are up to date
+  /// @throws Error if the instance is not running or if the command fails
+  ///
+  /// @example
+  /// ```typescript
+  /// await instance.executeFile('./fixtures/orders.sql', {});
+  /// await instance.postLoadOptimize(undefined, { vacuum: true });
+  /// ```
+  #[napi]
+  pub async fn post_load_optimize(
+    &self,
+    database_name: Option<String>,
+    options: Option<PostLoadOptimizeOptions>,
+  ) -> napi::Result<()> {
+    let vacuum = options.and_then(|options| options.vacuum).unwrap_or(false);
+    let sql = if vacuum {
+      "VACUUM (ANALYZE)"
+    } else {
+      "ANALYZE"
+    };
+    self.query_rows(sql, database_name).await?;
+    Ok(())
+  }
+
+  /// Computes row counts and content checksums of tables
+  ///
+  /// Each checksum is an md5 hash of the table's rows sorted by their text form, so two
+  /// tables with the same rows have the same checksum regardless of physical row order.
+  /// Use it to verify dump/restore round trips or replication without writing SQL.
+  ///
+  /// @param database_name - Optional database to read (defaults to the configured databaseName)
+  /// @param tables - Tables to checksum, optionally schema-qualified; all user tables when omitted
+  /// @returns Promise that resolves to one checksum per table
+  /// @throws Error if the instance is not running or if a query fails
+  ///
+  /// @example
+  /// ```typescript
+  /// const before = await source.tableChecksums();
+  /// const after = await restored.tableChecksums();
+  /// assert.deepEqual(after, before);
+  /// ```
+  #[napi]
+  pub async fn table_checksums(
+    &self,
+    database_name: Option<String>,
+    tables: Option<Vec<String>>,
+  ) -> napi::Result<Vec<TableChecksum>> {
+    if !matches!(self.get_state()?, InstanceState::Running) {
+      return Err(database_error("PostgreSQL instance is not running"));
+    }
+    let mut connection = self.connection_config();
+    if let Some(database_name) = database_name {
+      connection.database = Some(database_name);
+    }
+    let program_dir = self.tool_dir("psql")?;
+    Ok(checksum::table_checksums(connection, program_dir, tables).await?)
+  }
+
+  /// Runs a callback inside a transaction that is always rolled back
+  ///
+  /// The callback receives a transaction on a new connection and can query through it as
+  /// much as it likes; once it settles the transaction is rolled back, whether it resolved
+  /// or threw, so nothing it wrote is left behind. This is the classic transactional test
+  /// pattern and needs no cleanup SQL. Code that opens its own connections does not see
+  /// the uncommitted changes. The transaction's `commit()` rejects.
+  ///
+  /// @param callback - Async function receiving the transaction
+  /// @param database_name - Optional database to connect to (defaults to the configured databaseName)
+  /// @returns Promise that resolves once the transaction has been rolled back
+  /// @throws Error if the instance is not running, the connection fails, the callback throws
+  /// or SQL run by the callback ended the transaction, e.g. with `COMMIT`
+  ///
+  /// @example
+  /// ```typescript
+  /// await instance.runInRollbackTransaction(async (tx) => {
+  ///   await tx.query("INSERT INTO users (name) VALUES ('alice')");
+  ///   const { rows } = await tx.query('SELECT count(*) FROM users');
+  ///   assert.equal(rows[0][0], '1');
+  /// });
+  /// ```
+  #[napi(
+    ts_args_type = "callback: (transaction: Transaction) => Promise<void>, databaseName?: string | undefined | null"
+  )]
+  pub async fn run_in_rollback_transaction(
+    &self,
+    callback: ThreadsafeFunction<Transaction, Promise<()>, Transaction, Status, false>,
+    database_name: Option<String>,
+  ) -> napi::Result<()> {
+    if !matches!(self.get_state()?, InstanceState::Running) {
+      return Err(database_error("PostgreSQL instance is not running"));
+    }
+    let mut connection = self.connection_config();
+    if let Some(database_name) = database_name {
+      connection.database = Some(database_name);
+    }
+    let transaction =
+      Transaction::begin_rollback_only(&connection, self.query_logger(&connection)).await?;
+    let outcome = match callback.call_async(transaction.handle()).await {
+      Ok(promise) => promise.await,
+      Err(e) => Err(e),
+    };
+    let rolled_back = transaction.end_rollback_only().await;
+    outcome?;
+    Ok(rolled_back?)
+  }
+
+  /// Begins a transaction on a new connection
+  ///
+  /// Queries through the transaction see each other's changes; other connections see
+  /// them only once `commit()` is called. Call `rollback()` to discard them, e.g. at the
+  /// end of a test for isolation. Use `runInRollbackTransaction()` to have the rollback
+  /// happen however the test ends.
+  ///
+  /// @param database_name - Optional database to connect to (defaults to the configured databaseName)
+  /// @returns Promise that resolves to the open transaction
+  /// @throws Error if the instance is not running or the connection fails
+  ///
+  /// @example
+  /// ```typescript
+  /// const tx = await instance.beginTransaction();
+  /// try {
+  ///   await tx.query('INSERT INTO users (name) VALUES ($1)', ['alice']);
+  ///   const { rows } = await tx.query('SELECT count(*) FROM users');
+  ///   assert.equal(rows[0][0], '1');
+  /// } finally {
+  ///   await tx.rollback();
+  /// }
+  /// ```
+  #[napi]
+  pub async fn begin_transaction(
+    &self,
+    database_name: Option<String>,
+  ) -> napi::Result<Transaction> {
+    if !matches!(self.get_state()?, InstanceState::Running) {
+      return Err(database_error("PostgreSQL instance is not running"));
+    }
+    let mut connection = self.connection_config();
+    if let Some(database_name) = database_name {
+      connection.database = Some(database_name);
+    }
+    Ok(Transaction::begin(&connection, self.query_logger(&connection)).await?)
+  }
+
+  /// Loads data into a table with `COPY ... FROM STDIN`
+  ///
+  /// Orders of magnitude faster than inserting large fixtures row by row through psql:
+  /// the data is streamed to the server on a native connection as it is, in CSV, text or
+  /// binary COPY format. Node streams and other async iterables of chunks are accepted as
+  /// well and are read chunk by chunk, so a large file is never held in memory.
+  ///
+  /// @param table - Name of the table, optionally schema-qualified
+  /// @param data - The data, or a stream of chunks of it
+  /// @param options - Format, columns, header, delimiter and NULL string of the data
+  /// @param database_name - Optional database to load into (defaults to the configured databaseName)
+  /// @returns Promise that resolves to the number of rows loaded
+  /// @throws Error if the instance is not running, the table does not exist or the data
+  /// is invalid; no row is loaded then
+  ///
+  /// @example
+  /// ```typescript
+  /// await instance.copyInto('users', 'id,name\n1,alice\n2,bob\n', { header: true });
+  /// await instance.copyInto('events', fs.createReadStream('events.csv'));
+  /// ```
+  #[napi]
+  pub async fn copy_into(
+    &self,
+    table: String,
+    data: Either<Buffer, String>,
+    options: Option<CopyIntoOptions>,
+    database_name: Option<String>,
+  ) -> napi::Result<u32> {
+    let writer = self.open_copy_writer(table, options, database_name).await?;
+    writer.write(data).await?;
+    writer.finish().await
+  }
+
+  /// Starts loading data into a table with `COPY ... FROM STDIN`, chunk by chunk
+  ///
+  /// The writer behind `copyInto()` for data produced piece by piece, e.g. rows generated
+  /// in a loop. Nothing is loaded until `finish()` is called on the writer.
+  ///
+  /// @param table - Name of the table, optionally schema-qualified
+  /// @param options - Format, columns, header, delimiter and NULL string of the data
+  /// @param database_name - Optional database to load into (defaults to the configured databaseName)
+  /// @returns Promise that resolves to the writer once the COPY has started
+  /// @throws Error if the instance is not running, the table does not exist or the options
+  /// are invalid
+  ///
+  /// @example
+  /// ```typescript
+  /// const writer = await instance.openCopyWriter('events', { format: 'text' });
+  /// for (let i = 0; i < 1_000_000; i++) {
+  ///   await writer.write(`${i}\tevent ${i}\n`);
+  /// }
+  /// const rows = await writer.finish();
+  /// ```
+  #[napi]
+  pub async fn open_copy_writer(
+    &self,
+    table: String,
+    options: Option<CopyIntoOptions>,
+    database_name: Option<String>,
+  ) -> napi::Result<CopyWriter> {
+    if !matches!(self.get_state()?, InstanceState::Running) {
+      return Err(database_error("PostgreSQL instance is not running"));
+    }
+    let mut connection = self.connection_config();
+    if let Some(database_name) = database_name {
+      connection.database = Some(database_name);
+    }
+    Ok(CopyWriter::open(&connection, &table, &options.unwrap_or_default()).await?)
+  }
+
+  /// Exports a table or the result of a query with `COPY ... TO STDOUT`
+  ///
+  /// Exports just the rows a test produced, in CSV, text or binary COPY format, without
+  /// dumping the whole database.
+  ///
+  /// @param source - Name of a table, optionally schema-qualified, or a query starting
+  /// with SELECT, WITH, VALUES or TABLE
+  /// @param options - Format, columns, header, delimiter and NULL string of the data
+  /// @param database_name - Optional database to export from (defaults to the configured databaseName)
+  /// @returns Promise that resolves to the exported data
+  /// @throws Error if the instance is not running, the table does not exist or the query
+  /// fails
+  ///
+  /// @example
+  /// ```typescript
+  /// const csv = await instance.copyOut('results', { header: true });
+  /// const failed = await instance.copyOut("SELECT id, name FROM results WHERE status = 'failed'");
+  /// ```
+  #[napi]
+  pub async fn copy_out(
+    &self,
+    source: String,
+    options: Option<CopyOutOptions>,
+    database_name: Option<String>,
+  ) -> napi::Result<Buffer> {
+    let reader = self
+      .open_copy_reader(source, options, database_name)
+      .await?;
+    let mut data = Vec::new();
+    while let Some(chunk) = reader.next_chunk().await? {
+      data.extend_from_slice(&chunk);
+    }
+    Ok(data.into())
+  }
+
+  /// Exports a table or the result of a query with `COPY ... TO STDOUT` into a file
+  ///
+  /// The data is written chunk by chunk as it arrives, so a large export is never held in
+  /// memory. The file is replaced if it exists, and removed again if the export fails.
+  ///
+  /// @param source - Name of a table, optionally schema-qualified, or a query starting
+  /// with SELECT, WITH, VALUES or TABLE
+  /// @param file - Path of the file to write
+  /// @param options - Format, columns, header, delimiter and NULL string of the data
+  /// @param database_name - Optional database to export from (defaults to the configured databaseName)
+  /// @returns Promise that resolves to the number of bytes written
+  /// @throws Error if the instance is not running, the table does not exist, the query
+  /// fails or the file cannot be written
+  ///
+  /// @example
+  /// ```typescript
+  /// await instance.copyOutToFile('results', './artifacts/results.csv', { header: true });
+  /// ```
+  #[napi]
+  pub async fn copy_out_to_file(
+    &self,
+    source: String,
+    file: String,
+    options: Option<CopyOutOptions>,
+    database_name: Option<String>,
+  ) -> napi::Result<f64> {
+    use std::io::Write;
+
+    let reader = self
+      .open_copy_reader(source, options, database_name)
+      .await?;
+    let path = extended_path(std::path::Path::new(&file));
+    let write_error = |e: std::io::Error| database_error(&format!("Failed to write {file}: {e}"));
+    let result = async {
+      let mut output = std::fs::File::create(&path).map_err(write_error)?;
+      let mut written = 0u64;
+      while let Some(chunk) = reader.next_chunk().await? {
+        output.write_all(&chunk).map_err(write_error)?;
+        written += chunk.len() as u64;
+      }
+      output.flush().map_err(write_error)?;
+      Ok::<_, napi::Error>(written)
+    }
+    .await;
+    if result.is_err() {
+      reader.close().await?;
+      let _ = std::fs::remove_file(&path);
+    }
+    result.map(|written| written as f64)
+  }
+
+  /// Starts exporting a table or the result of a query with `COPY ... TO STDOUT`, chunk
+  /// by chunk
+  ///
+  /// The reader behind `copyOutToFile()` and `copyOutToStream()` for consumers of their
+  /// own. Close the reader with `close()` when not reading it to the end.
+  ///
+  /// @param source - Name of a table, optionally schema-qualified, or a query starting
+  /// with SELECT, WITH, VALUES or TABLE
+  /// @param options - Format, columns, header, delimiter and NULL string of the data
+  /// @param database_name - Optional database to export from (defaults to the configured databaseName)
+  /// @returns Promise that resolves to the reader once the COPY has started
+  /// @throws Error if the instance is not running, the table does not exist, the query is
+  /// invalid or the options are invalid
+  ///
+  /// @example
+  /// ```typescript
+  /// const reader = await instance.openCopyReader('events', { format: 'text' });
+  /// for (let chunk = await reader.read(); chunk; chunk = await reader.read()) {
+  ///   process.stdout.write(chunk);
+  /// }
+  /// ```
+  #[napi]
+  pub async fn open_copy_reader(
+    &self,
+    source: String,
+    options: Option<CopyOutOptions>,
+    database_name: Option<String>,
+  ) -> napi::Result<CopyReader> {
+    if !matches!(self.get_state()?, InstanceState::Running) {
+      return Err(database_error("PostgreSQL instance is not running"));
+    }
+    let mut connection = self.connection_config();
+    if let Some(database_name) = database_name {
+      connection.database = Some(database_name);
+    }
+    Ok(CopyReader::open(&connection, &source, &options.unwrap_or_default()).await?)
+  }
+
+  /// Opens a native client on a new connection
+  ///
+  /// The client keeps its connection open across queries, which is much faster than
+  /// `executeSql()` starting psql for every query, and returns rows with typed values.
+  /// Close the client with `close()` when done; it is also closed when it is garbage
+  /// collected.
+  ///
+  /// @param database_name - Optional database to connect to (defaults to the configured databaseName)
+  /// @param options - Settings of the session, such as a statement timeout or time zone
+  /// @returns Promise that resolves to the connected client
+  /// @throws Error if the instance is not running, an option is invalid or the connection
+  /// fails
+  ///
+  /// @example
+  /// ```typescript
+  /// const client = await instance.connect(undefined, { statementTimeoutMs: 5000, timezone: 'UTC' });
+  /// try {
+  ///   const { rows } = await client.query('SELECT count(*)::int AS count FROM users');
+  ///   console.log(rows[0].count);
+  /// } finally {
+  ///   await client.close();
+  /// }
+  /// ```
+  #[napi]
+  pub async fn connect(
+    &self,
+    database_name: Option<String>,
+    options: Option<SessionOptions>,
+  ) -> napi::Result<Client> {
+    if !matches!(self.get_state()?, InstanceState::Running) {
+      return Err(database_error("PostgreSQL instance is not running"));
+    }
+    let mut connection = self.connection_config();
+    if let Some(database_name) = database_name {
+      connection.database = Some(database_name);
+    }
+    Ok(
+      Client::connect(
+        &connection,
+        &options.unwrap_or_default(),
+        self.query_logger(&connection),
+      )
+      .await?,
+    )
+  }
+
+  /// Listens for notifications sent with NOTIFY or `pg_notify()` on a channel
+  ///
+  /// All channels share one connection to the configured database, opened on the first
+  /// call and kept open until `cleanup()`; a notification reaches the callbacks of its
+  /// channel once its transaction commits. Several callbacks can listen on the same
+  /// channel. Listening stops when the server stops; listen again after a restart.
+  ///
+  /// @param channel - Name of the channel, case-sensitive
+  /// @param callback - Function receiving each notification on the channel
+  /// @returns Promise that resolves once the server listens on the channel
+  /// @throws Error if the instance is not running or the connection fails
+  ///
+  /// @example
+  /// ```typescript
+  /// await instance.listen('orders', (notification) => {
+  ///   console.log(`order ${notification.payload} created`);
+  /// });
+  /// await instance.executeSql("SELECT pg_notify('orders', '42')", {});
+  /// ```
+  #[napi(ts_args_type = "channel: string, callback: (notification: Notification) => void")]
+  pub async fn listen(&self, channel: String, callback: NotificationCallback) -> napi::Result<()> {
+    if !matches!(self.get_state()?, InstanceState::Running) {
+      return Err(database_error("PostgreSQL instance is not running"));
+    }
+    let running = self
+      .notification_listener
+      .lock()
+      .ok()
+      .and_then(|listener| listener.clone())
+      .filter(|listener| listener.is_running());
+    let listener = match running {
+      Some(listener) => listener,
+      None => {
+        let listener = NotificationListener::connect(&self.connection_config()).await?;
+        let mut current = self
+          .notification_listener
+          .lock()
+          .map_err(|_| database_error("Failed to acquire notification listener lock"))?;
+        // Another call may have opened a listener in the meantime
+        match current.as_ref().filter(|current| current.is_running()) {
+          Some(current) => current.clone(),
+          None => current.insert(listener).clone(),
+        }
+      }
+    };
+    Ok(listener.listen(channel, callback).await?)
+  }
+
+  /// Stops listening on a channel, removing every callback passed to `listen()` for it
+  ///
+  /// @param channel - Name of the channel
+  /// @returns Promise that resolves to whether the channel was listened to
+  /// @throws Error if the server cannot be told to stop listening
+  #[napi]
+  pub async fn unlisten(&self, channel: String) -> napi::Result<bool> {
+    let listener = self
+      .notification_listener
+      .lock()
+      .ok()
+      .and_then(|listener| listener.clone());
+    match listener {
+      Some(listener) if listener.is_running() => Ok(listener.unlisten(channel).await?),
+      _ => Ok(false),
+    }
+  }
+
+  /// Reports the DDL commands run in a database as they are committed
+  ///
+  /// Installs event triggers that log every DDL command, with the statement it came from,
+  /// to a table in the `pg_embedded` schema and notify a listener, which hands the
+  /// commands to the callback in order. Lets migration tools snapshot exactly the DDL
+  /// their code emitted. Commands on global objects such as databases and roles are not
+  /// reported, as event triggers do not fire for them. Call `stop()` on the returned
+  /// capture to remove the triggers again.
+  ///
+  /// @param database_name - Optional database to watch (defaults to the configured databaseName)
+  /// @param callback - Function receiving each captured command
+  /// @returns Promise that resolves to the running capture once the triggers are installed
+  /// @throws Error if the instance is not running or the triggers cannot be installed
+  ///
+  /// @example
+  /// ```typescript
+  /// const commands: DdlCommand[] = [];
+  /// const capture = await instance.captureDdl(undefined, (command) => commands.push(command));
+  /// await runMigrations(instance.connectionInfo.connectionString);
+  /// await capture.stop();
+  /// console.log(commands.map((command) => command.commandTag));
+  /// ```
+  #[napi(
+    ts_args_type = "databaseName: string | undefined | null, callback: (command: DdlCommand) => void"
+  )]
+  pub async fn capture_ddl(
+    &self,
+    database_name: Option<String>,
+    callback: ThreadsafeFunction<DdlCommand, (), DdlCommand, Status, false, true>,
+  ) -> napi::Result<DdlCapture> {
+    if !matches!(self.get_state()?, InstanceState::Running) {
+      return Err(database_error("PostgreSQL instance is not running"));
+    }
+    let mut connection = self.connection_config();
+    if let Some(database_name) = database_name {
+      connection.database = Some(database_name);
+    }
+    Ok(DdlCapture::start(&connection, callback).await?)
+  }
+
+  /// Empties tables, including the tables that reference them through foreign keys
+  ///
+  /// All tables are truncated by a single `TRUNCATE` statement, so foreign keys between
+  /// them never fail on the order they are emptied in. Tables outside the list that
+  /// reference one of the tables must be emptied as well; they are only included with
+  /// `cascade: true`, otherwise the call fails naming them. Meant for cleaning up between
+  /// tests without recreating the database.
+  ///
+  /// @param database_name - Optional database to clean (defaults to the configured databaseName)
+  /// @param tables - Tables to truncate, optionally schema-qualified; all user tables when omitted
+  /// @param options - Whether to include referencing tables and to reset sequences
+  /// @returns Promise that resolves to the quoted names of the truncated tables
+  /// @throws Error if the instance is not running, if a table does not exist or if other
+  /// tables reference the tables and `cascade` is not set
+  ///
+  /// @example
+  /// ```typescript
+  /// test.afterEach(async () => {
+  ///   await instance.truncateTables(undefined, undefined, { restartIdentity: true });
+  /// });
+  /// ```
+  #[napi]
+  pub async fn truncate_tables(
+    &self,
+    database_name: Option<String>,
+    tables: Option<Vec<String>>,
+    options: Option<TruncateTablesOptions>,
+  ) -> napi::Result<Vec<String>> {
+    let options = options.unwrap_or_default();
+    let rows = self
+      .query_rows(
+        &truncate::truncate_plan_sql(tables.as_deref()),
+        database_name.clone(),
+      )
+      .await?;
+    let mut missing = Vec::new();
+    let mut requested = Vec::new();
+    let mut referencing = Vec::new();
+    for table in rows
+      .iter()
+      .filter_map(|row| truncate::parse_planned_table(row))
+    {
+      match table {
+        PlannedTable::Missing(name) => missing.push(name),
+        PlannedTable::Requested(name) => requested.push(name),
+        PlannedTable::Referencing(name) => referencing.push(name),
+      }
+    }
+    if !missing.is_empty() {
+      return Err(database_error(&format!(
+        "Tables do not exist: {}",
+        missing.join(", ")
+      )));
+    }
+    if !referencing.is_empty() && !options.cascade.unwrap_or(false) {
+      return Err(database_error(&format!(
+        "Tables {} reference the tables to truncate; pass cascade: true to truncate them too",
+        referencing.join(", ")
+      )));
+    }
+
+    requested.extend(referencing);
+    if requested.is_empty() {
+      return Ok(requested);
+    }
+    let sql = truncate::truncate_sql(&requested, options.restart_identity.unwrap_or(false));
+    self.query_rows(&sql, database_name).await?;
+    Ok(requested)
+  }
+
+  /// Fills a table with generated rows
+  ///
+  /// Values are derived from the column types: random integers and decimals, UUIDs,
+  /// timestamps within the last year, booleans and lorem ipsum text. Integer and text
+  /// columns with a unique index get unique values, and columns with defaults, identity
+  /// and generated columns are left to the server. All rows are inserted with a single
+  /// `INSERT ... SELECT ... FROM generate_series()` statement.
+  ///
+  /// @param table - Table to fill, optionally schema-qualified
+  /// @param rows - Number of rows to insert
+  /// @param options - Target database and random seed
+  /// @returns Promise that resolves to the number of inserted rows
+  /// @throws Error if the instance is not running, the table does not exist or a required
+  /// column has a type no values can be generated for
+  ///
+  /// @example
+  /// ```typescript
+  /// await instance.executeSql('CREATE TABLE events (id serial PRIMARY KEY, name text, at timestamptz);', {});
+  /// await instance.generateTestData('events', 100000, { seed: 0.5 });
+  /// ```
+  #[napi]
+  pub async fn generate_test_data(
+    &self,
+    table: String,
+    rows: u32,
+    options: Option<GenerateTestDataOptions>,
+  ) -> napi::Result<u32> {
+    let options = options.unwrap_or_default();
+    let database_name = options.database_name;
+    let column_rows = self
+      .query_rows(&testdata::columns_sql(&table), database_name.clone())
+      .await?;
+    let mut relation = None;
+    let mut columns = Vec::new();
+    for (table, column) in column_rows
+      .iter()
+      .filter_map(|row| testdata::parse_column(row))
+    {
+      relation = Some(table);
+      columns.push(column);
+    }
+    let relation =
+      relation.ok_or_else(|| database_error(&format!("Table '{table}' has no columns")))?;
+
+    let mut sql = testdata::insert_sql(&relation, &columns, rows)?;
+    if let Some(seed) = options.seed {
+      if !(-1.0..=1.0).contains(&seed) {
+        return Err(configuration_error("seed must be between -1 and 1"));
+      }
+      sql = format!("SELECT setseed({seed});\n{sql}");
+    }
+    self.script_rows(&sql, database_name).await?;
+    Ok(rows)
+  }
+
+  /// Stores binary data as a new large object
+  ///
+  /// The data is imported with psql's `\lo_import`, so it is transferred in binary form.
+  ///
+  /// @param data - Contents of the large object
+  /// @param database_name - Optional database to store the object in (defaults to the configured databaseName)
+  /// @returns Promise that resolves to the OID of the new large object
+  /// @throws Error if the instance is not running or if the import fails
+  ///
+  /// @example
+  /// ```typescript
+  /// const oid = await instance.putLargeObject(Buffer.from('hello'));
+  /// const data = await instance.getLargeObject(oid);
+  /// ```
+  #[napi]
+  pub async fn put_large_object(
+    &self,
+    data: Buffer,
+    database_name: Option<String>,
+  ) -> napi::Result<u32> {
+    let data_file = scratch_file("bin");
+    std::fs::write(&data_file, &data)
+      .map_err(|e| database_error(&format!("Failed to write large object data: {e}")))?;
+    let script = format!(
+      "\\lo_import {}\nSELECT :LASTOID;\n",
+      quote_psql_arg(&data_file.to_string_lossy())
+    );
+    let rows = self.script_rows(&script, database_name).await;
+    let _ = std::fs::remove_file(&data_file);
+    first_value(&rows?)
+      .and_then(|oid| oid.parse().ok())
+      .ok_or_else(|| database_error("Unexpected result from \\lo_import"))
+  }
+
+  /// Reads the contents of a large object
+  ///
+  /// @param oid - OID of the large object
+  /// @param database_name - Optional database containing the object (defaults to the configured databaseName)
+  /// @returns Promise that resolves to the contents of the large object
+  /// @throws Error if the instance is not running or if the object does not exist
+  ///
+  /// @example
+  /// ```typescript
+  /// const data = await instance.getLargeObject(oid);
+  /// console.log(data.toString());
+  /// ```
+  #[napi]
+  pub async fn get_large_object(
+    &self,
+    oid: u32,
+    database_name: Option<String>,
+  ) -> napi::Result<Buffer> {
+    let data_file = scratch_file("bin");
+    let script = format!(
+      "\\lo_export {oid} {}\n",
+      quote_psql_arg(&data_file.to_string_lossy())
+    );
+    let exported = self.script_rows(&script, database_name).await;
+    let data = exported.and_then(|_| {
+      std::fs::read(&data_file)
+        .map_err(|e| database_error(&format!("Failed to read large object data: {e}")))
+    });
+    let _ = std::fs::remove_file(&data_file);
+    Ok(data?.into())
+  }
+
+  /// Lists collations whose recorded version no longer matches the collation library
+  ///
+  /// Indexes on text columns depend on the sort order of their collation. When a cluster
+  /// (or a restored dump) was created with a different ICU or C library version, the
+  /// recorded and actual versions differ and such indexes may be corrupt until rebuilt.
+  /// The database's default collation is checked as well.
+  ///
+  /// @param database_name - Optional database to check (defaults to the configured databaseName)
+  /// @returns Promise that resolves to the mismatching collations (empty if all versions match)
+  /// @throws Error if the instance is not running or if the query fails
+  ///
+  /// @example
+  /// ```typescript
+  /// const mismatches = await instance.checkCollationVersionMismatch('app');
+  /// if (mismatches.length > 0) {
+  ///   await instance.executeSql('REINDEX DATABASE app;');
+  /// }
+  /// ```
+  #[napi]
+  pub async fn check_collation_version_mismatch(
+    &self,
+    database_name: Option<String>,
+  ) -> napi::Result<Vec<CollationVersionMismatch>> {
+    let sql = "SELECT datname, true, datcollversion, pg_database_collation_actual_version(oid) \
+               FROM pg_database WHERE datname = current_database() \
+               AND datcollversion IS DISTINCT FROM pg_database_collation_actual_version(oid) \
+               UNION ALL \
+               SELECT collname, false, collversion, pg_collation_actual_version(oid) \
+               FROM pg_collation WHERE collversion IS NOT NULL \
+               AND collversion IS DISTINCT FROM pg_collation_actual_version(oid)";
+    let rows = self.query_rows(sql, database_name).await?;
+    let version = |value: &String| Some(value.clone()).filter(|value| !value.is_empty());
+    Ok(
+      rows
+        .iter()
+        .filter(|row| row.len() == 4)
+        .map(|row| CollationVersionMismatch {
+          collation: row[0].clone(),
+          database_default: row[1] == "t",
+          recorded_version: version(&row[2]),
+          actual_version: version(&row[3]),
+        })
+        .collect(),
+    )
+  }
+
+  /// Gets WAL generation statistics from pg_stat_wal
+  ///
+  /// The counters accumulate since the last statistics reset. Fields the server version
+  /// no longer reports (WAL write and sync activity moved to pg_stat_io in PostgreSQL 18)
+  /// are null, so the object has the same shape on every version.
+  ///
+  /// @returns Promise that resolves to the WAL statistics
+  /// @throws Error if the instance is not running or the server is older than PostgreSQL 14
+  ///
+  /// @example
+  /// ```typescript
+  /// const before = await instance.getWalStats();
+  /// await instance.executeSql('UPDATE accounts SET balance = balance + 1', {});
+  /// const after = await instance.getWalStats();
+  /// console.log(`${after.bytes - before.bytes} bytes of WAL`);
+  /// ```
+  #[napi]
+  pub async fn get_wal_stats(&self) -> napi::Result<WalStats> {
+    let sql = stats::wal_stats_sql(self.server_version_num().await?)?;
+    let rows = self.query_rows(&sql, None).await?;
+    rows
+      .first()
+      .and_then(|row| stats::parse_wal_stats(row))
+      .ok_or_else(|| database_error("Unexpected pg_stat_wal output"))
+  }
+
+  /// Gets checkpoint statistics
+  ///
+  /// The statistics are read from pg_stat_checkpointer, or from pg_stat_bgwriter before
+  /// PostgreSQL 17; restartpoint counters are only available on PostgreSQL 17 and later
+  /// and are null otherwise.
+  ///
+  /// @returns Promise that resolves to the checkpoint statistics
+  /// @throws Error if the instance is not running or if the query fails
+  ///
+  /// @example
+  /// ```typescript
+  /// await instance.executeSql('CHECKPOINT', {});
+  /// const { requested, buffersWritten } = await instance.getCheckpointStats();
+  /// ```
+  #[napi]
+  pub async fn get_checkpoint_stats(&self) -> napi::Result<CheckpointStats> {
+    let sql = stats::checkpoint_stats_sql(self.server_version_num().await?);
+    let rows = self.query_rows(&sql, None).await?;
+    rows
+      .first()
+      .and_then(|row| stats::parse_checkpoint_stats(row))
+      .ok_or_else(|| database_error("Unexpected checkpoint statistics output"))
+  }
+
+  /// Sets `wal_keep_size`, the amount of WAL kept for standbys beyond what checkpoints need
+  ///
+  /// The setting is written like with `applyServerConfig()` and takes effect on a running
+  /// server right away. Keeping WAL lets a standby or `pg_rewind` catch up after the
+  /// primary moved on; `'0'` lets checkpoints remove or recycle old segments as soon as
+  /// possible.
+  ///
+  /// @param size - Amount of WAL, e.g. '512MB' or '1GB' (a plain number means megabytes)
+  /// @returns Promise that resolves when the setting is applied
+  /// @throws Error if the size is invalid, the data directory has not been initialized
+  /// or the reload fails
+  ///
+  /// @example
+  /// ```typescript
+  /// await instance.setWalKeepSize('1GB');
+  /// ```
+  #[napi]
+  pub async fn set_wal_keep_size(&self, size: String) -> napi::Result<()> {
+    wal::validate_wal_keep_size(&size)?;
+    self
+      .apply_server_config(HashMap::from([("wal_keep_size".to_string(), size)]))
+      .await?;
+    Ok(())
+  }
+
+  /// Lists the files in the WAL directory (`pg_wal`), oldest segment first
+  ///
+  /// @returns Promise that resolves to the WAL segments and history files
+  /// @throws Error if the instance is not running or if the query fails
+  ///
+  /// @example
+  /// ```typescript
+  /// const segments = (await instance.listWalFiles()).filter((file) => file.isSegment);
+  /// console.log(`${segments.length} segments, oldest ${segments[0].name}`);
+  /// ```
+  #[napi]
+  pub async fn list_wal_files(&self) -> napi::Result<Vec<WalFile>> {
+    let rows = self.query_rows(wal::LIST_WAL_FILES_SQL, None).await?;
+    rows
+      .iter()
+      .map(|row| {
+        wal::parse_wal_file(row).ok_or_else(|| database_error("Unexpected pg_ls_waldir output"))
+      })
+      .collect()
+  }
+
+  /// Switches to a new WAL segment and runs a checkpoint
+  ///
+  /// The switch completes the current segment, so it can be archived, and the checkpoint
+  /// lets the server remove or recycle the segments it no longer needs. This brings WAL
+  /// into a known state before archiving, recycling or `pg_rewind` tests.
+  ///
+  /// @returns Promise that resolves to the LSN the completed segment ends at
+  /// @throws Error if the instance is not running, is a standby or if the commands fail
+  ///
+  /// @example
+  /// ```typescript
+  /// const switchedAt = await instance.forceCheckpointAndSwitchWal();
+  /// ```
+  #[napi]
+  pub async fn force_checkpoint_and_switch_wal(&self) -> napi::Result<String> {
+    let rows = self.query_rows("SELECT pg_switch_wal()", None).await?;
+    let lsn = first_value(&rows)
+      .map(str::to_string)
+      .ok_or_else(|| database_error("Unexpected pg_switch_wal output"))?;
+    self.query_rows("CHECKPOINT", None).await?;
+    Ok(lsn)
+  }
+
+  /// Runs pgbench against the instance and returns its summary
+  ///
+  /// With `initialize` the pgbench tables are created first (`pgbench -i`). Built-in
+  /// scripts need those tables; a custom script runs without vacuuming them.
+  /// `runBenchmarkSuite()` runs several named workloads and compares them with a baseline.
+  ///
+  /// @param options - Script, clients, run length and initialization (defaults: tpcb-like, 1 client, 10 transactions)
+  /// @returns Promise that resolves to the throughput and latency of the run
+  /// @throws Error if the instance is not running, the options conflict or pgbench fails
+  ///
+  /// @example
+  /// ```typescript
+  /// const { tps, latencyAvgMs } = await instance.runPgbench({
+  ///   initialize: true,
+  ///   builtin: 'select-only',
+  ///   clients: 4,
+  ///   durationSeconds: 5,
+  /// });
+  /// ```
+  #[napi]
+  pub async fn run_pgbench(&self, options: Option<PgbenchOptions>) -> napi::Result<PgbenchResult> {
+    let options = options.unwrap_or_default();
+    benchmark::validate_pgbench_options(&options)?;
+    let current_state = self.get_state()?;
+    if !matches!(current_state, InstanceState::Running) {
+      return Err(database_error("PostgreSQL instance is not running"));
+    }
+    let program_dir = self.tool_dir("pgbench")?;
+    let mut connection = self.connection_config();
+    if let Some(database) = &options.database_name {
+      connection.database = Some(database.clone());
+    }
+
+    if options.initialize.unwrap_or(false) {
+      let command = benchmark::init_command(&program_dir, &connection, &options);
+      check_executable(&command)?;
+      let output = tokio::process::Command::from(command)
+        .output()
+        .await
+        .map_err(|e| database_error(&format!("Failed to run pgbench: {e}")))?;
+      if !output.status.success() {
+        return Err(database_error(&format!(
+          "pgbench initialization failed: {}",
+          String::from_utf8_lossy(&output.stderr).trim()
+        )));
+      }
+    }
+
+    let script_file = match &options.script {
+      Some(script) => {
+        let file = scratch_file("sql");
+        std::fs::write(&file, script)
+          .map_err(|e| database_error(&format!("Failed to write pgbench script: {e}")))?;
+        Some(file)
+      }
+      None => None,
+    };
+    let command =
+      benchmark::bench_command(&program_dir, &connection, &options, script_file.as_deref());
+    check_executable(&command)?;
+    let output = tokio::process::Command::from(command).output().await;
+    if let Some(file) = &script_file {
+      let _ = std::fs::remove_file(file);
+    }
+    let output = output.map_err(|e| database_error(&format!("Failed to run pgbench: {e}")))?;
+    if !output.status.success() {
+      return Err(database_error(&format!(
+        "pgbench failed: {}",
+        String::from_utf8_lossy(&output.stderr).trim()
+      )));
+    }
+    benchmark::parse_pgbench_output(&String::from_utf8_lossy(&output.stdout))
+      .ok_or_else(|| database_error("Unexpected pgbench output"))
+  }
+
+  /// Records the plans of a set of queries, for comparison with `comparePlans()`
+  ///
+  /// Each query is planned with `EXPLAIN (FORMAT JSON)`, without running it. Taking a
+  /// snapshot before and after an index or statistics change shows which queries switch
+  /// plans, e.g. from a sequential to an index scan.
+  ///
+  /// @param databaseName - Database to plan the queries in
+  /// @param queries - Queries to plan; a query may not have parameters
+  /// @returns Promise that resolves to the plans, in query order
+  /// @throws Error if the instance is not running or a query cannot be planned
+  ///
+  /// @example
+  /// ```typescript
+  /// const baseline = await instance.snapshotPlans('app', ['SELECT * FROM orders WHERE customer_id = 42']);
+  /// ```
+  #[napi]
+  pub async fn snapshot_plans(
+    &self,
+    database_name: String,
+    queries: Vec<String>,
+  ) -> napi::Result<PlanSnapshot> {
+    let taken_at = std::time::SystemTime::now()
+      .duration_since(std::time::UNIX_EPOCH)
+      .map(|elapsed| elapsed.as_secs_f64() * 1000.0)
+      .unwrap_or_default();
+    let mut query_plans = Vec::with_capacity(queries.len());
+    for query in queries {
+      let rows = self
+        .query_rows(&plans::explain_sql(&query), Some(database_name.clone()))
+        .await
+        .map_err(|e| database_error(&format!("Failed to plan query '{query}': {e}")))?;
+      let plan = first_value(&rows)
+        .map(str::to_string)
+        .ok_or_else(|| database_error("Unexpected EXPLAIN output"))?;
+      let rows = self
+        .query_rows(&plans::plan_nodes_sql(&plan), Some(database_name.clone()))
+        .await?;
+      let (nodes, total_cost) = plans::parse_plan_nodes(&rows)
+        .ok_or_else(|| database_error("Unexpected plan node output"))?;
+      query_plans.push(QueryPlan {
+        query,
+        plan,
+        total_cost,
+        nodes,
+      });
+    }
+    Ok(PlanSnapshot {
+      database_name,
+      taken_at,
+      plans: query_plans,
+    })
+  }
+
+  /// Reports tables that are read mostly by sequential scans and the columns frequent
+  /// statements filter them on
+  ///
+  /// Tables come from pg_stat_user_tables. When the pg_stat_statements extension is
+  /// installed in the database (and loaded through `shared_preload_libraries`), the most
+  /// frequently called statements are searched for WHERE clause columns; a reported table
+  /// then lists those of its columns that lead no index, with a `CREATE INDEX` statement
+  /// for the most used one. Run it after the test suite has exercised the application.
+  ///
+  /// @param databaseName - Database to analyze
+  /// @param options - Thresholds for reported tables and the number of statements examined
+  /// @returns Promise that resolves to the report
+  /// @throws Error if the instance is not running or if the statistics cannot be read
+  ///
+  /// @example
+  /// ```typescript
+  /// const advice = await instance.suggestIndexes('app', { minRows: 100 });
+  /// for (const suggestion of advice.suggestions) {
+  ///   console.log(`${suggestion.tableName}: ${suggestion.reason}`, suggestion.createIndexSql);
+  /// }
+  /// ```
+  #[napi]
+  pub async fn suggest_indexes(
+    &self,
+    database_name: String,
+    options: Option<SuggestIndexesOptions>,
+  ) -> napi::Result<IndexAdvice> {
+    let options = options.unwrap_or_default();
+    let database = Some(database_name.clone());
+    let rows = self
+      .query_rows(&advisor::seq_scan_tables_sql(&options), database.clone())
+      .await?;
+    let mut suggestions = rows
+      .iter()
+      .map(|row| {
+        advisor::parse_seq_scan_table(row)
+          .ok_or_else(|| database_error("Unexpected pg_stat_user_tables output"))
+      })
+      .collect::<napi::Result<Vec<_>>>()?;
+
+    let installed = self
+      .query_rows(advisor::STATEMENTS_INSTALLED_SQL, database.clone())
+      .await?;
+    // Reading pg_stat_statements fails when the library is not preloaded
+    let statements = match first_value(&installed) {
+      Some("1") => self
+        .query_rows(
+          &advisor::frequent_statements_sql(&options),
+          database.clone(),
+        )
+        .await
+        .ok(),
+      _ => None,
+    };
+    let statements_available = statements.is_some();
+    let frequent_predicates: Vec<_> = statements
+      .unwrap_or_default()
+      .iter()
+      .filter_map(|row| advisor::parse_frequent_statement(row))
+      .filter(|predicate| !predicate.columns.is_empty())
+      .collect();
+
+    if !suggestions.is_empty() && !frequent_predicates.is_empty() {
+      let unindexed = self
+        .query_rows(advisor::UNINDEXED_COLUMNS_SQL, database)
+        .await?;
+      advisor::attach_columns(&mut suggestions, &frequent_predicates, &unindexed);
+    }
+    Ok(IndexAdvice {
+      database_name,
+      statements_available,
+      suggestions,
+      frequent_predicates,
+    })
+  }
+
+  /// Runs ANALYZE with a fixed default statistics target
+  ///
+  /// The target is set for the ANALYZE session only, so the statistics do not depend on
+  /// the server's `default_statistics_target` or the machine. ANALYZE reads tables with
+  /// fewer than 300 × target rows completely, which gives the same statistics in every
+  /// run; larger tables are sampled at random, so export their statistics once with
+  /// `exportStatistics()` and import them instead.
+  ///
+  /// @param databaseName - Database to analyze
+  /// @param target - Statistics target from 1 to 10000 (default: 100)
+  /// @param tables - Tables to analyze, optionally schema-qualified (`schema.table`); the
+  /// names are quoted, so they are case-sensitive (default: every table of the database)
+  /// @returns Promise that resolves when the statistics are collected
+  /// @throws Error if the instance is not running, the target is out of range or ANALYZE fails
+  ///
+  /// @example
+  /// ```typescript
+  /// await instance.analyzeWithStatisticsTarget('app', 100, ['users', 'orders']);
+  /// ```
+  #[napi]
+  pub async fn analyze_with_statistics_target(
+    &self,
+    database_name: String,
+    target: Option<u32>,
+    tables: Option<Vec<String>>,
+  ) -> napi::Result<()> {
+    let sql = planner_stats::analyze_sql(target, &tables.unwrap_or_default())?;
+    self.query_rows(&sql, Some(database_name)).await?;
+    Ok(())
+  }
+
+  /// Exports the planner statistics of a database as a SQL script
+  ///
+  /// The script restores the size statistics of tables, indexes and materialized views
+  /// and the column statistics ANALYZE collected, with `pg_restore_relation_stats()` and
+  /// `pg_restore_attribute_stats()`. Importing it into a database with the same schema
+  /// makes the planner choose the same plans on every machine. The data of extended
+  /// statistics objects (`CREATE STATISTICS`) cannot be restored and is not exported.
+  ///
+  /// @param databaseName - Database to export the statistics of
+  /// @returns Promise that resolves to the script, one statement per line
+  /// @throws Error if the instance is not running, the server is older than PostgreSQL 18
+  /// or the statistics cannot be read
+  ///
+  /// @example
+  /// ```typescript
+  /// await instance.analyzeWithStatisticsTarget('app', 100);
+  /// fs.writeFileSync('test/fixtures/app-stats.sql', await instance.exportStatistics('app'));
+  /// ```
+  #[napi]
+  pub async fn export_statistics(&self, database_name: String) -> napi::Result<String> {
+    self.require_restore_stats().await?;
+    let database = Some(database_name.clone());
+    let version = self.query_rows("SHOW server_version", None).await?;
+    let mut statements: Vec<String> = Vec::new();
+    for sql in [
+      planner_stats::EXPORT_RELATION_STATS_SQL,
+      planner_stats::EXPORT_ATTRIBUTE_STATS_SQL,
+    ] {
+      let rows = self.query_rows(sql, database.clone()).await?;
+      statements.extend(rows.into_iter().filter_map(|row| row.into_iter().next()));
+    }
+    Ok(planner_stats::statistics_script(
+      &database_name,
+      first_value(&version).unwrap_or_default(),
+      &statements,
+    ))
+  }
+
+  /// Imports planner statistics exported with `exportStatistics()`
+  ///
+  /// The statistics replace what ANALYZE collected, until the next ANALYZE or autovacuum
+  /// run; disable autovacuum (`serverConfig: { autovacuum: 'off' }`) to keep them.
+  /// Statistics of tables or columns missing from the database are skipped with a
+  /// warning.
+  ///
+  /// @param databaseName - Database with the same schema as the exported one
+  /// @param script - Script returned by `exportStatistics()`
+  /// @returns Promise that resolves to the number of relations and columns whose statistics were restored
+  /// @throws Error if the instance is not running, the server is older than PostgreSQL 18
+  /// or a statement of the script fails
+  ///
+  /// @example
+  /// ```typescript
+  /// const restored = await instance.importStatistics('app', fs.readFileSync('test/fixtures/app-stats.sql', 'utf8'));
+  /// ```
+  #[napi]
+  pub async fn import_statistics(
+    &self,
+    database_name: String,
+    script: String,
+  ) -> napi::Result<u32> {
+    self.require_restore_stats().await?;
+    let mut connection_config = self.connection_config();
+    connection_config.database = Some(database_name);
+    let results = client::run_script(
+      &connection_config,
+      &split_script(&script),
+      &ExecuteSqlBatchOptions::default(),
+      None,
+    )
+    .await?;
+    let restored = results
+      .iter()
+      .filter_map(|statement| statement.result.as_ref())
+      .filter(|result| {
+        result
+          .rows
+          .first()
+          .and_then(|row| row.first())
+          .is_some_and(|value| value.as_deref() == Some("t"))
+      })
+      .count();
+    Ok(restored as u32)
+  }
+
+  /// # Safety
+  /// Starts the PostgreSQL instance asynchronously with a timeout
+  ///
+  /// @param timeout_seconds - Maximum time to wait for startup in seconds
+  /// @returns Promise that resolves when the instance is started and ready
+  /// @throws Error if the instance is already running, if startup fails, or if timeout is exceeded
+  ///
+  /// @example
+  /// ```typescript
+  /// await instance.startWithTimeout(30); // 30 second timeout
+  /// ```
+  #[napi]
+  pub async unsafe fn start_with_timeout(&mut self, timeout_seconds: u32) -> napi::Result<()> {
+    let timeout_duration = Duration::from_secs(timeout_seconds as u64);
+
+    pg_log!(
+      info,
+      "Starting PostgreSQL instance with timeout of {} seconds",
+      timeout_seconds
+    );
+
+    // Use tokio::time::timeout to wrap the start operation
+    match tokio::time::timeout(timeout_duration, self.start(Some(true))).await {
+      Ok(result) => result,
+      Err(_) => {
+        pg_log!(
+          error,
+          "PostgreSQL start operation timed out after {} seconds",
+          timeout_seconds
+        );
+        let error = timeout_error(&format!(
+          "Start operation timed out after {timeout_seconds} seconds"
+        ));
+        self.record_failure(FailurePhase::Start, &error.reason)?;
+        Err(error)
+      }
+    }
+  }
+
+  /// # Safety
+  /// Stops the PostgreSQL instance asynchronously with a timeout
+  ///
+  /// @param timeout_seconds - Maximum time to wait for shutdown in seconds
+  /// @returns Promise that resolves when the instance is stopped
+  /// @throws Error if the instance is already stopped, if stopping fails, or if timeout is exceeded
+  ///
+  /// @example
+  /// ```typescript
+  /// await instance.stopWithTimeout(10); // 10 second timeout
+  /// ```
+  #[napi]
+  pub async unsafe fn stop_with_timeout(&mut self, timeout_seconds: u32) -> napi::Result<()> {
+    let timeout_duration = Duration::from_secs(timeout_seconds as u64);
+
+    pg_log!(
+      info,
+      "Stopping PostgreSQL instance with timeout of {} seconds",
+      timeout_seconds
+    );
+
+    // Use tokio::time::timeout to wrap the stop operation
+    match tokio::time::timeout(timeout_duration, self.stop()).await {
+      Ok(result) => result,
+      Err(_) => {
+        pg_log!(
+          error,
+          "PostgreSQL stop operation timed out after {} seconds",
+          timeout_seconds
+        );
+        // In timeout case, we're not sure of actual state, keep current state
+        Err(timeout_error(&format!(
+          "Stop operation timed out after {timeout_seconds} seconds"
+        )))
+      }
+    }
+  }
+
+  /// Starts sampling the server's resource usage in the background
+  ///
+  /// At every interval the resident memory and CPU time of the postmaster and its child
+  /// processes and the number of client connections are recorded in a ring buffer, so
+  /// long-running soak tests can detect memory growth with `getMetricsHistory()`. No
+  /// samples are taken while the server is not running. Memory and CPU are not collected
+  /// on Windows. Starting the sampler again restarts it with an empty history.
+  ///
+  /// @param options - Sampling interval and number of samples kept
+  /// @throws Error if the program directory cannot be determined
+  ///
+  /// @example
+  /// ```typescript
+  /// instance.startMetricsSampler({ intervalMs: 10000 });
+  /// // ... run the soak test ...
+  /// const history = instance.getMetricsHistory();
+  /// const growth = history.at(-1)!.rssBytes! - history[0].rssBytes!;
+  /// ```
+  #[napi]
+  pub fn start_metrics_sampler(&self, options: Option<MetricsSamplerOptions>) -> napi::Result<()> {
+    let program_dir = self.tool_dir("psql")?;
+    let mut sampler = self
+      .metrics_sampler
+      .lock()
+      .map_err(|_| setup_error("Failed to acquire metrics sampler lock"))?;
+    sampler.start(
+      options.unwrap_or_default(),
+      self.settings.data_dir.clone(),
+      self.connection_config(),
+      program_dir,
+      self.state.clone(),
+    );
+    Ok(())
+  }
+
+  /// Sets a hook that receives the queries run on native connections
+  ///
+  /// Every statement run by `executeSqlBatch()` and every `query()` of a transaction
+  /// from `runInRollbackTransaction()` is reported with its duration and row count, or
+  /// its error, after it completes. Queries run through psql, such as `executeSql()`,
+  /// are not reported. Parameter values are masked unless `includeParameters` is set,
+  /// and credentials in the SQL are redacted. Setting a hook replaces the previous one.
+  ///
+  /// @param callback - Function receiving each reported query
+  /// @param options - Threshold for slow queries and whether to include parameter values
+  ///
+  /// @example
+  /// ```typescript
+  /// instance.setQueryLogHook((event) => {
+  ///   const status = event.error ? `failed: ${event.error}` : `${event.rowCount} rows`;
+  ///   console.log(`[${event.durationMs.toFixed(1)} ms] ${event.sql} (${status})`);
+  /// }, { slowQueryMs: 50 });
+  /// ```
+  #[napi(
+    ts_args_type = "callback: (event: QueryLogEvent) => void, options?: QueryLogOptions | undefined | null"
+  )]
+  pub fn set_query_log_hook(
+    &self,
+    callback: ThreadsafeFunction<QueryLogEvent, (), QueryLogEvent, Status, false, true>,
+    options: Option<QueryLogOptions>,
+  ) -> napi::Result<()> {
+    let mut logger = self
+      .query_logger
+      .lock()
+      .map_err(|_| setup_error("Failed to acquire query logger lock"))?;
+    *logger = Some(QueryLogger::new(callback, options.unwrap_or_default()));
+    Ok(())
+  }
+
+  /// Removes the hook set with `setQueryLogHook()`
+  #[napi]
+  pub fn clear_query_log_hook(&self) {
+    if let Ok(mut logger) = self.query_logger.lock() {
+      *logger = None;
+    }
+  }
+
+  /// Stops the background resource sampler; the collected samples are kept
+  #[napi]
+  pub fn stop_metrics_sampler(&self) {
+    if let Ok(mut sampler) = self.metrics_sampler.lock() {
+      sampler.stop();
+    }
+  }
+
+  /// Whether the background resource sampler is running
+  #[napi]
+  pub fn is_metrics_sampler_running(&self) -> bool {
+    self
+      .metrics_sampler
+      .lock()
+      .map(|sampler| sampler.is_running())
+      .unwrap_or(false)
+  }
+
+  /// Gets the samples collected by `startMetricsSampler()`, oldest first
+  ///
+  /// @returns The collected samples; empty if the sampler was never started
+  #[napi]
+  pub fn get_metrics_history(&self) -> Vec<MetricsSample> {
+    self
+      .metrics_sampler
+      .lock()
+      .map(|sampler| sampler.history())
+      .unwrap_or_default()
+  }
+
+  /// Gets the disk space used by the data directory, its WAL and the WAL archive
+  ///
+  /// The directories are walked on a blocking thread, so this can take a moment for a
+  /// large cluster. The free and total space of the volume are read with `df` and are
+  /// not available on Windows.
+  ///
+  /// @returns Promise that resolves to the disk usage in bytes
+  /// @throws Error if the data directory does not exist
+  ///
+  /// @example
+  /// ```typescript
+  /// const usage = await instance.getDiskUsage();
+  /// console.log(`WAL: ${usage.walBytes} bytes, free: ${usage.freeBytes} bytes`);
+  /// ```
+  #[napi]
+  pub async fn get_disk_usage(&self) -> napi::Result<DiskUsage> {
+    let data_dir = self.settings.data_dir.clone();
+    if !data_dir.is_dir() {
+      return Err(setup_error(&format!(
+        "Data directory {} does not exist",
+        data_dir.display()
+      )));
+    }
+    let wal_archive_dir = self.wal_archive_dir.clone().map(std::path::PathBuf::from);
+    tokio::task::spawn_blocking(move || disk::measure(&data_dir, wal_archive_dir.as_deref()))
+      .await
+      .map_err(|e| setup_error(&format!("Failed to measure disk usage: {e}")))
+  }
+
+  /// Checks the limits and locale of the environment for problems that make setup or start fail
+  ///
+  /// Constrained containers, as found in CI, cause failures whose messages do not name the
+  /// cause. This checks the open file limit, the free space of `/dev/shm` (Linux), the
+  /// System V semaphore limits (macOS) and whether the locale set in the environment or
+  /// `serverEnv` is installed. Findings other than `Ok` are logged as warnings. The instance
+  /// does not have to be set up or running.
+  ///
+  /// @returns Promise that resolves to the findings, each with a remedy for a problem
+  ///
+  /// @example
+  /// ```typescript
+  /// const report = await instance.preflight();
+  /// for (const finding of report.findings.filter((f) => f.severity !== PreflightSeverity.Ok)) {
+  ///   console.warn(`${finding.message}: ${finding.remedy}`);
+  /// }
+  /// ```
+  #[napi]
+  pub async fn preflight(&self) -> napi::Result<PreflightReport> {
+    let configuration = self.settings.configuration.clone();
+    let server_env = self.server_env.clone();
+    let report = tokio::task::spawn_blocking(move || preflight::run(&configuration, &server_env))
+      .await
+      .map_err(|e| setup_error(&format!("Failed to run the preflight checks: {e}")))?;
+    for finding in &report.findings {
+      if finding.severity != PreflightSeverity::Ok {
+        pg_log!(
+          warn,
+          "Preflight check {}: {}",
+          finding.check,
+          finding.message
+        );
+      }
+    }
+    Ok(report)
+  }
+
+  /// Starts checking the disk usage in the background and warns when a threshold is crossed
+  ///
+  /// WAL that piles up because archiving or a replication slot is stuck can silently fill
+  /// a disk. At every interval the usage is measured as with `getDiskUsage()`; a crossed
+  /// threshold is logged as a warning and passed to the callback. A warning is raised again
+  /// only after the usage went back within its threshold. No checks run while the server
+  /// is not running. Starting the watch again replaces the previous one.
+  ///
+  /// @param options - Check interval and thresholds
+  /// @param callback - Optional function receiving each warning
+  ///
+  /// @example
+  /// ```typescript
+  /// instance.watchDiskUsage({ maxWalBytes: 1024 ** 3, minFreeBytes: 5 * 1024 ** 3 }, (warning) => {
+  ///   console.warn(warning.message);
+  /// });
+  /// ```
+  #[napi(
+    ts_args_type = "options: DiskUsageWatchOptions, callback?: ((warning: DiskUsageWarning) => void) | undefined | null"
+  )]
+  pub fn watch_disk_usage(
+    &self,
+    options: DiskUsageWatchOptions,
+    callback: Option<DiskUsageWarningCallback>,
+  ) -> napi::Result<()> {
+    let mut watcher = self
+      .disk_watcher
+      .lock()
+      .map_err(|_| setup_error("Failed to acquire disk watcher lock"))?;
+    watcher.start(
+      options,
+      self.settings.data_dir.clone(),
+      self.wal_archive_dir.clone().map(std::path::PathBuf::from),
+      self.state.clone(),
+      callback,
+    );
+    Ok(())
+  }
+
+  /// Stops the background disk usage watch started with `watchDiskUsage()`
+  #[napi]
+  pub fn stop_disk_usage_watch(&self) {
+    if let Ok(mut watcher) = self.disk_watcher.lock() {
+      watcher.stop();
+    }
+  }
+
+  /// Whether the background disk usage watch is running
+  #[napi]
+  pub fn is_disk_usage_watch_running(&self) -> bool {
+    self
+      .disk_watcher
+      .lock()
+      .map(|watcher| watcher.is_running())
+      .unwrap_or(false)
+  }
+
+  /// Gets the startup time of the PostgreSQL instance in seconds
+  ///
+  /// This method returns the time it took for the last successful start operation.
+  ///
+  /// @returns The startup time in seconds, or null if the instance hasn't been started yet
+  ///
+  /// @example
+  /// ```typescript
+  /// await instance.start();
+  /// const startupTime = instance.getStartupTime();
+  /// console.log(`Started in ${startupTime} seconds`);
+  /// ```
+  #[napi]
+  pub fn get_startup_time(&self) -> Option<f64> {
+    if let Ok(startup_time) = self.startup_time.lock() {
+      startup_time.map(|duration| duration.as_secs_f64())
+    } else {
+      None
+    }
+  }
+
+  /// Clears the connection information cache
+  ///
+  /// This forces the next call to connectionInfo to regenerate the connection information.
+  ///
+  /// @returns void
+  #[napi]
+  pub fn clear_connection_cache(&self) -> napi::Result<()> {
+    if let Ok(mut cache) = self.connection_cache.lock() {
+      *cache = None;
+      pg_log!(
+        debug,
         "Connection cache cleared for instance {}",
-        self.instance_id
+        self.log_name()
       );
     }
     Ok(())
@@ -1068,14 +5030,15 @@ impl PostgresInstance {
 
   /// Checks if the connection information cache is valid
   ///
-  /// The cache is considered valid if it exists and is less than 5 minutes old.
+  /// The cache is considered valid if it exists and is younger than `connectionCacheTtlSeconds`.
   ///
   /// @returns true if the cache is valid, false otherwise
   #[napi]
   pub fn is_connection_cache_valid(&self) -> bool {
     if let Ok(cache) = self.connection_cache.lock() {
       if let Some(cached) = cache.as_ref() {
-        return cached.created_at.elapsed() < Duration::from_secs(300);
+        return cached.created_at.elapsed() < self.connection_cache_ttl
+          && cached.info.port == self.settings.port;
       }
     }
     false
@@ -1083,16 +5046,175 @@ impl PostgresInstance {
 
   /// Gets the PostgreSQL version used by this instance
   ///
-  /// @returns PostgreSQL version string (e.g., "15.4")
+  /// A version requirement such as `'16'` resolves to the latest matching release during
+  /// setup; the resolved version is recorded in the data directory and reported here. Before
+  /// setup the version recorded by an earlier setup, or the embedded version, is returned.
+  ///
+  /// @returns PostgreSQL version string (e.g., "16.4.0")
   ///
   /// @example
   /// ```typescript
-  /// const version = instance.getPostgreSQLVersion();
-  /// console.log(`Using PostgreSQL ${version}`);
+  /// const instance = new PostgresInstance({ version: '16' });
+  /// await instance.start();
+  /// console.log(`Using PostgreSQL ${instance.getPostgreSQLVersion()}`);
   /// ```
   #[napi]
   pub fn get_postgre_sql_version(&self) -> String {
-    crate::version::get_postgre_sql_version()
+    metadata::exact_version(&self.settings.version)
+      .or_else(|| metadata::recorded_version(&self.settings.data_dir))
+      .map(|version| version.to_string())
+      .unwrap_or_else(crate::version::get_postgre_sql_version)
+  }
+
+  /// Gets the build configuration of the PostgreSQL installation
+  ///
+  /// Runs pg_config, which reports where the installation keeps its executables, libraries
+  /// and extension files, its version and how it was built. The instance does not need to
+  /// be running, but it must have been set up.
+  ///
+  /// @returns Promise that resolves to the build configuration
+  /// @throws Error if the instance has not been set up or pg_config fails
+  ///
+  /// @example
+  /// ```typescript
+  /// const { version, pkglibdir, configure } = await instance.getBuildConfig();
+  /// console.log(version, pkglibdir, configure.includes('--with-icu'));
+  /// ```
+  #[napi]
+  pub async fn get_build_config(&self) -> napi::Result<BuildConfig> {
+    let tool = PgConfigTool::new(PgConfigOptions {
+      program_dir: self.tool_dir("pg_config")?,
+      tool: None,
+    });
+    Ok(tool.get_build_config().await?)
+  }
+
+  /// Password clients use to connect; empty in trust mode
+  fn client_password(&self) -> String {
+    if self.trust_auth {
+      String::new()
+    } else {
+      self.settings.password.clone()
+    }
+  }
+
+  /// Enable WAL archiving into the configured archive directory
+  ///
+  /// The commands are written to the configuration file rather than passed on the command
+  /// line, where pg_ctl would hand their quotes to a shell.
+  fn configure_wal_archiving(&self) -> napi::Result<()> {
+    let Some(ref archive_dir) = self.wal_archive_dir else {
+      return Ok(());
+    };
+    let archive_dir = std::path::Path::new(archive_dir);
+    std::fs::create_dir_all(extended_path(archive_dir)).map_err(|e| {
+      start_error(&format!(
+        "Failed to create WAL archive directory {}: {e}",
+        archive_dir.display()
+      ))
+    })?;
+    let archive_dir = absolute_archive_dir(archive_dir)
+      .map_err(|e| start_error(&format!("Invalid WAL archive directory: {e}")))?;
+
+    let mut conf = managed_conf(&self.settings.data_dir)?;
+    conf.set("archive_mode", "on");
+    conf.set(
+      "archive_command",
+      &archive_command_for(&archive_dir, cfg!(windows)),
+    );
+    conf.save()?;
+    pg_log!(debug, "Archiving WAL segments to {}", archive_dir);
+    Ok(())
+  }
+
+  /// Apply the authentication method and remote access settings to the cluster's pg_hba.conf
+  ///
+  /// The method is only applied to a cluster initdb just created, whose pg_hba.conf holds
+  /// nothing but initdb's rules, so rules added later, e.g. with `addHbaRule()`, are never
+  /// rewritten. The remote access rules are only appended when missing.
+  fn configure_hba_auth(&self, fresh_cluster: bool) -> napi::Result<()> {
+    let method = if self.trust_auth {
+      Some("trust")
+    } else {
+      self
+        .password_encryption
+        .map(|encryption| encryption.as_str())
+    };
+    if method.is_none() && !self.expose_externally {
+      return Ok(());
+    }
+
+    let hba_file = self.settings.data_dir.join("pg_hba.conf");
+    let mut contents = std::fs::read_to_string(&hba_file)
+      .map_err(|e| setup_error(&format!("Failed to read {}: {e}", hba_file.display())))?;
+    if let (Some(method), true) = (method, fresh_cluster) {
+      contents = hba_rules_with_method(&contents, method);
+    }
+    if self.expose_externally {
+      // initdb configures the "password" method unless another one was selected
+      contents = hba_with_remote_access(&contents, method.unwrap_or("password"));
+    }
+    std::fs::write(&hba_file, contents)
+      .map_err(|e| setup_error(&format!("Failed to write {}: {e}", hba_file.display())))?;
+    pg_log!(
+      debug,
+      "Configured client authentication in {}",
+      hba_file.display()
+    );
+    Ok(())
+  }
+
+  /// Re-hash the superuser password when initdb stored it with a different method
+  async fn apply_password_encryption(&self) -> napi::Result<()> {
+    let Some(password_encryption) = self.password_encryption else {
+      return Ok(());
+    };
+    if self.trust_auth {
+      return Ok(());
+    }
+
+    let rows = self
+      .fetch_rows(
+        "SELECT rolpassword FROM pg_authid WHERE rolname = current_user",
+        None,
+      )
+      .await?;
+    let stored = first_value(&rows).unwrap_or_default();
+    if stored.starts_with(password_encryption.hash_prefix()) {
+      return Ok(());
+    }
+
+    pg_log!(
+      info,
+      "Re-hashing password of role '{}' with {}",
+      self.settings.username,
+      password_encryption.as_str()
+    );
+    let sql = format!(
+      "ALTER ROLE CURRENT_USER PASSWORD {}",
+      quote_literal(&self.settings.password)
+    );
+    let result = self
+      .script_tool(PsqlConfig::default(), None)?
+      .execute_command(sql)
+      .await?;
+    if result.exit_code != 0 {
+      return Err(setup_error(&format!(
+        "Failed to apply password encryption: {}",
+        result.stderr.trim()
+      )));
+    }
+    Ok(())
+  }
+
+  /// The query log hook, if set, reporting queries on the database of `connection`
+  fn query_logger(&self, connection: &ConnectionConfig) -> Option<QueryLogger> {
+    self
+      .query_logger
+      .lock()
+      .ok()?
+      .as_ref()
+      .map(|logger| logger.for_database(connection.database.clone()))
   }
 
   pub fn connection_config(&self) -> ConnectionConfig {
@@ -1100,9 +5222,445 @@ impl PostgresInstance {
       host: Some(self.settings.host.clone()),
       port: Some(self.settings.port),
       username: Some(self.settings.username.clone()),
-      password: Some(self.settings.password.clone()),
-      database: Some("postgres".to_string()),
+      password: Some(self.client_password()).filter(|password| !password.is_empty()),
+      database: Some(self.database_name.clone()),
+      application_name: self.application_name.clone(),
+      ..Default::default()
+    }
+  }
+
+  /// In safe mode, dump `database` so `undoLastDestructiveOp()` can recreate it
+  ///
+  /// Nothing is saved for a database that does not exist.
+  async fn save_database_undo_point(&self, operation: &str, database: &str) -> napi::Result<()> {
+    if !self.safe_mode || !self.database_exists(database.to_string()).await? {
+      return Ok(());
+    }
+    let dump_file = scratch_file("dump");
+    let mut connection_config = self.connection_config();
+    connection_config.database = Some(database.to_string());
+    let result = PgDumpTool::from_connection(
+      connection_config,
+      self.tool_dir("pg_dump")?,
+      PgDumpConfig {
+        file: Some(dump_file.to_string_lossy().to_string()),
+        format: Some(PgDumpFormat::Custom),
+        create: Some(true),
+        ..Default::default()
+      },
+    )
+    .execute()
+    .await?;
+    if result.exit_code != 0 {
+      let _ = std::fs::remove_file(&dump_file);
+      return Err(database_error(&format!(
+        "Safe mode could not dump database '{database}' before {operation}: {}",
+        result.stderr.trim()
+      )));
+    }
+    pg_log!(
+      info,
+      "Saved undo point for {} of database {}",
+      operation,
+      database
+    );
+    self.push_undo_point(UndoPoint {
+      operation: operation.to_string(),
+      action: UndoAction::RestoreDatabase {
+        database: database.to_string(),
+        dump_file,
+      },
+    })
+  }
+
+  /// In safe mode, copy `data_dir` so `undoLastDestructiveOp()` can put it back
+  fn save_data_dir_undo_point(&self, operation: &str, data_dir: &str) -> napi::Result<()> {
+    let data_dir = std::path::PathBuf::from(data_dir);
+    if !self.safe_mode || !data_dir.is_dir() {
+      return Ok(());
+    }
+    let snapshot = scratch_file("snapshot");
+    if let Err(e) = undo::copy_dir(&data_dir, &snapshot) {
+      let _ = std::fs::remove_dir_all(&snapshot);
+      return Err(setup_error(&format!(
+        "Safe mode could not snapshot {} before {operation}: {e}",
+        data_dir.display()
+      )));
+    }
+    pg_log!(
+      info,
+      "Saved undo point for {} of {}",
+      operation,
+      data_dir.display()
+    );
+    self.push_undo_point(UndoPoint {
+      operation: operation.to_string(),
+      action: UndoAction::RestoreDataDir { data_dir, snapshot },
+    })
+  }
+
+  /// Replace `database` with the database saved in `dump_file` by pg_dump `--create`
+  async fn restore_database_dump(
+    &self,
+    database: &str,
+    dump_file: &std::path::Path,
+  ) -> napi::Result<()> {
+    self
+      .query_rows(
+        &format!("DROP DATABASE IF EXISTS {}", quote_ident(database)),
+        Some(DEFAULT_DATABASE.to_string()),
+      )
+      .await?;
+    let mut connection_config = self.connection_config();
+    connection_config.database = Some(DEFAULT_DATABASE.to_string());
+    let result = PgRestoreTool::from_connection(
+      connection_config,
+      self.tool_dir("pg_restore")?,
+      PgRestoreConfig {
+        file: dump_file.to_string_lossy().to_string(),
+        format: Some(PgRestoreFormat::Custom),
+        create: Some(true),
+        exit_on_error: Some(true),
+        ..Default::default()
+      },
+    )
+    .execute()
+    .await?;
+    if result.exit_code != 0 {
+      return Err(database_error(&format!(
+        "Failed to recreate database '{database}': {}",
+        result.stderr.trim()
+      )));
+    }
+    Ok(())
+  }
+
+  fn push_undo_point(&self, undo_point: UndoPoint) -> napi::Result<()> {
+    self
+      .undo_points
+      .lock()
+      .map_err(|_| setup_error("Failed to acquire undo point lock"))?
+      .push(undo_point);
+    Ok(())
+  }
+
+  /// Remove the dumps and snapshots of all undo points
+  fn discard_undo_points(&self) {
+    if let Ok(mut undo_points) = self.undo_points.lock() {
+      for undo_point in undo_points.drain(..) {
+        undo_point.discard();
+      }
+    }
+  }
+
+  /// Run a query through psql and return the result rows
+  ///
+  /// Output is requested as headerless CSV so values can be parsed reliably;
+  /// a non-zero exit code is reported as a database error carrying psql's stderr.
+  pub(crate) async fn query_rows(
+    &self,
+    sql: &str,
+    database_name: Option<String>,
+  ) -> napi::Result<Vec<Vec<String>>> {
+    let current_state = self.get_state()?;
+    if !matches!(current_state, InstanceState::Running) {
+      return Err(database_error("PostgreSQL instance is not running"));
+    }
+    self.fetch_rows(sql, database_name).await
+  }
+
+  /// Run a role statement on a native connection, which keeps passwords off command lines
+  async fn run_role_sql(&self, sql: &str) -> napi::Result<()> {
+    if !matches!(self.get_state()?, InstanceState::Running) {
+      return Err(database_error("PostgreSQL instance is not running"));
+    }
+    client::run_script(
+      &self.connection_config(),
+      &split_script(sql),
+      &ExecuteSqlBatchOptions::default(),
+      None,
+    )
+    .await?;
+    Ok(())
+  }
+
+  /// Fail unless the server can restore planner statistics
+  async fn require_restore_stats(&self) -> napi::Result<()> {
+    if self.server_version_num().await? < planner_stats::RESTORE_STATS_VERSION {
+      return Err(configuration_error(
+        "Exporting and importing planner statistics requires PostgreSQL 18 or later",
+      ));
+    }
+    Ok(())
+  }
+
+  /// Version of the running server as a number, e.g. 160004 for 16.4
+  async fn server_version_num(&self) -> napi::Result<u32> {
+    let rows = self
+      .query_rows("SELECT current_setting('server_version_num')", None)
+      .await?;
+    first_value(&rows)
+      .and_then(|version| version.parse().ok())
+      .ok_or_else(|| database_error("Unexpected server_version_num output"))
+  }
+
+  /// Run `statement` (COMMIT PREPARED or ROLLBACK PREPARED) on a prepared transaction,
+  /// which only works from the database the transaction was prepared in
+  async fn finish_prepared(&self, gid: &str, statement: &str) -> napi::Result<()> {
+    let rows = self
+      .query_rows(
+        &format!(
+          "SELECT database FROM pg_prepared_xacts WHERE gid = {}",
+          quote_literal(gid)
+        ),
+        None,
+      )
+      .await?;
+    let database = first_value(&rows)
+      .ok_or_else(|| database_error(&format!("No prepared transaction with gid '{gid}'")))?;
+    self
+      .query_rows(
+        &format!("{statement} {}", quote_literal(gid)),
+        Some(database.to_string()),
+      )
+      .await?;
+    Ok(())
+  }
+
+  /// Run a query through psql without checking the instance state (used while starting)
+  async fn fetch_rows(
+    &self,
+    sql: &str,
+    database_name: Option<String>,
+  ) -> napi::Result<Vec<Vec<String>>> {
+    let config = PsqlConfig {
+      csv: Some(true),
+      tuples_only: Some(true),
+      ..Default::default()
+    };
+    let result = self
+      .script_tool(config, database_name)?
+      .execute_command(sql.to_string())
+      .await?;
+    if result.exit_code != 0 {
+      return Err(database_error(result.stderr.trim()));
+    }
+    Ok(parse_csv(&result.stdout))
+  }
+
+  /// Run a psql script, which may contain backslash commands, and return its result rows
+  async fn script_rows(
+    &self,
+    script: &str,
+    database_name: Option<String>,
+  ) -> napi::Result<Vec<Vec<String>>> {
+    let current_state = self.get_state()?;
+    if !matches!(current_state, InstanceState::Running) {
+      return Err(database_error("PostgreSQL instance is not running"));
+    }
+    let script_file = scratch_file("sql");
+    std::fs::write(&script_file, script)
+      .map_err(|e| database_error(&format!("Failed to write psql script: {e}")))?;
+    let config = PsqlConfig {
+      csv: Some(true),
+      tuples_only: Some(true),
+      ..Default::default()
+    };
+    let result = match self.script_tool(config, database_name) {
+      Ok(tool) => tool
+        .execute_file(script_file.to_string_lossy().to_string())
+        .await
+        .map_err(napi::Error::from),
+      Err(e) => Err(e),
+    };
+    let _ = std::fs::remove_file(&script_file);
+    let result = result?;
+    if result.exit_code != 0 {
+      return Err(database_error(result.stderr.trim()));
+    }
+    Ok(parse_csv(&result.stdout))
+  }
+
+  /// Build a psql tool for internal scripts that stops at the first error
+  fn script_tool(
+    &self,
+    config: PsqlConfig,
+    database_name: Option<String>,
+  ) -> napi::Result<PsqlTool> {
+    let program_dir = self.tool_dir("psql")?;
+    let mut connection_config = self.connection_config();
+    if let Some(database_name) = database_name {
+      connection_config.database = Some(database_name);
+    }
+    let config = PsqlConfig {
+      no_psqlrc: Some(true),
+      variable: Some(("ON_ERROR_STOP".to_string(), "1".to_string())),
+      ..config
+    };
+    Ok(PsqlTool::from_connection(
+      connection_config,
+      program_dir,
+      config,
+    ))
+  }
+
+  /// Create the default database and apply a pending profile after the server started
+  async fn prepare_databases(&mut self) -> napi::Result<()> {
+    if let Some(ref instance) = self.async_instance {
+      Self::ensure_database(instance, &self.database_name).await?;
     }
+    self.apply_password_encryption().await?;
+    self.install_session_seed().await?;
+    if self.provision_pending {
+      self.apply_profile().await?;
+      self.provision_pending = false;
+    }
+    Ok(())
+  }
+
+  /// With the reproducible preset, seed `random()` in every new session of template1,
+  /// postgres and the instance's database
+  async fn install_session_seed(&self) -> napi::Result<()> {
+    if !self.reproducible || self.role.lock().map(|role| *role).ok() == Some(ServerRole::Standby) {
+      // A standby has the trigger of its primary
+      return Ok(());
+    }
+    let rows = self
+      .fetch_rows("SELECT current_setting('server_version_num')", None)
+      .await?;
+    let version: u32 = first_value(&rows)
+      .and_then(|version| version.parse().ok())
+      .unwrap_or_default();
+    if version < LOGIN_EVENT_TRIGGER_VERSION {
+      pg_log!(
+        warn,
+        "Seeding random() per session needs PostgreSQL 17 or later; sessions are not seeded"
+      );
+      return Ok(());
+    }
+
+    let mut databases = vec!["template1", DEFAULT_DATABASE];
+    if !databases.contains(&self.database_name.as_str()) {
+      databases.push(&self.database_name);
+    }
+    for database in databases {
+      let result = self
+        .script_tool(PsqlConfig::default(), Some(database.to_string()))?
+        .execute_command(REPRODUCIBLE_SEED_SQL.to_string())
+        .await?;
+      if result.exit_code != 0 {
+        return Err(setup_error(&format!(
+          "Failed to seed sessions of database '{database}': {}",
+          result.stderr.trim()
+        )));
+      }
+    }
+    Ok(())
+  }
+
+  /// Remove the session seed of the reproducible preset from every database, so it stays
+  /// out of the data directory of a persistent instance once the instance stops
+  ///
+  /// A temporary data directory is deleted anyway, and a standby has the seed of its
+  /// primary.
+  async fn remove_session_seed(&self) -> napi::Result<()> {
+    if !self.reproducible
+      || self.settings.temporary
+      || !matches!(self.get_state()?, InstanceState::Running)
+      || self.role.lock().map(|role| *role).ok() == Some(ServerRole::Standby)
+    {
+      return Ok(());
+    }
+    let databases = self
+      .query_rows("SELECT datname FROM pg_database WHERE datallowconn", None)
+      .await?;
+    for database in databases
+      .into_iter()
+      .filter_map(|row| row.into_iter().next())
+    {
+      let result = self
+        .script_tool(PsqlConfig::default(), Some(database.clone()))?
+        .execute_command(REPRODUCIBLE_UNSEED_SQL.to_string())
+        .await?;
+      if result.exit_code != 0 {
+        return Err(setup_error(&format!(
+          "Failed to remove the session seed from database '{database}': {}",
+          result.stderr.trim()
+        )));
+      }
+    }
+    Ok(())
+  }
+
+  /// Create the profile's extensions and run its migration and seed scripts
+  async fn apply_profile(&self) -> napi::Result<()> {
+    let Some(ref profile) = self.profile else {
+      return Ok(());
+    };
+    pg_log!(info, "Applying profile '{}'", profile.name);
+
+    for extension in profile.extensions.iter().flatten() {
+      let sql = format!("CREATE EXTENSION IF NOT EXISTS {}", quote_ident(extension));
+      let result = self
+        .script_tool(PsqlConfig::default(), None)?
+        .execute_command(sql)
+        .await?;
+      if result.exit_code != 0 {
+        return Err(setup_error(&format!(
+          "Failed to create extension '{}': {}",
+          extension,
+          result.stderr.trim()
+        )));
+      }
+    }
+
+    for dir in [&profile.migrations_dir, &profile.seed_dir]
+      .into_iter()
+      .flatten()
+    {
+      for file in collect_sql_files(dir)? {
+        pg_log!(debug, "Running profile script {}", file.display());
+        let result = self
+          .script_tool(PsqlConfig::default(), None)?
+          .execute_file(file.to_string_lossy().to_string())
+          .await?;
+        if result.exit_code != 0 {
+          return Err(setup_error(&format!(
+            "Profile script '{}' failed: {}",
+            file.display(),
+            result.stderr.trim()
+          )));
+        }
+      }
+    }
+
+    if let Some(ref options) = profile.post_load_optimize {
+      pg_log!(debug, "Optimizing after loading profile '{}'", profile.name);
+      self.post_load_optimize(None, Some(options.clone())).await?;
+    }
+    Ok(())
+  }
+
+  /// Create the configured default database if it does not exist yet
+  async fn ensure_database(
+    instance: &postgresql_embedded::PostgreSQL,
+    database_name: &str,
+  ) -> napi::Result<()> {
+    if database_name == DEFAULT_DATABASE {
+      return Ok(());
+    }
+    let exists = instance
+      .database_exists(database_name)
+      .await
+      .map_err(convert_postgresql_error)?;
+    if !exists {
+      pg_log!(info, "Creating default database '{}'", database_name);
+      instance
+        .create_database(database_name)
+        .await
+        .map_err(convert_postgresql_error)?;
+    }
+    Ok(())
   }
 
   /// # Safety
@@ -1129,11 +5687,31 @@ impl PostgresInstance {
 
     pg_log!(info, "Manually cleaning up PostgreSQL instance resources");
 
+    if let Ok(mut sampler) = self.metrics_sampler.lock() {
+      sampler.stop();
+    }
+    self.stop_disk_usage_watch();
+    if let Ok(mut listener) = self.notification_listener.lock() {
+      listener.take();
+    }
+
     // First try to stop gracefully using internal_stop
     if let Err(e) = self.internal_stop(true).await {
       pg_log!(warn, "Graceful stop failed during cleanup: {}", e);
     }
 
+    self.discard_undo_points();
+    // The replica has stopped, so the upstream no longer needs to keep WAL for it
+    if let Some(slot) = self.upstream_slot.take() {
+      slot.drop_slot().await;
+    }
+    if self.settings.temporary {
+      let data_dir = self.settings.data_dir.to_string_lossy();
+      if let Err(e) = backup_catalog::forget_backups(&self.backup_catalog_path(), &data_dir) {
+        pg_log!(warn, "Failed to remove backups from the catalog: {}", e);
+      }
+    }
+
     // Then take ownership of the instance to ensure it's dropped
     if let Some(instance) = self.async_instance.take() {
       pg_log!(debug, "Taking ownership of PostgreSQL instance for cleanup");
@@ -1154,8 +5732,67 @@ impl PostgresInstance {
     // Ensure final state is stopped
     self.set_state(InstanceState::Stopped)?;
     self.cleaned_up = true;
+    registry::unregister(&self.instance_id);
 
     pg_log!(info, "Manual cleanup completed");
+    self.quiet.take();
+    Ok(())
+  }
+}
+
+/// Start the server with `server_env` added to the environment pg_ctl passes on to it
+///
+/// postgresql_embedded starts the server with the environment of this process and offers
+/// no way to extend it, so this resolves port 0 and runs the equivalent pg_ctl command.
+async fn start_with_server_env(
+  instance: &mut postgresql_embedded::PostgreSQL,
+  server_env: &HashMap<String, String>,
+) -> postgresql_embedded::Result<()> {
+  if instance.settings().port == 0 {
+    let listener = std::net::TcpListener::bind(("0.0.0.0", 0))?;
+    let mut settings = instance.settings().clone();
+    settings.port = listener.local_addr()?.port();
+    *instance = postgresql_embedded::PostgreSQL::new(settings);
+  }
+  let settings = instance.settings();
+  #[cfg(unix)]
+  if let Some(socket_dir) = &settings.socket_dir {
+    std::fs::create_dir_all(socket_dir)?;
+  }
+  pg_log!(
+    debug,
+    "Starting server with environment variables {}",
+    server_env.keys().cloned().collect::<Vec<_>>().join(", ")
+  );
+  let output = tokio::process::Command::from(server_env::start_command(settings, server_env))
+    .output()
+    .await?;
+  if output.status.success() {
     Ok(())
+  } else {
+    Err(postgresql_embedded::Error::DatabaseStartError(format!(
+      "pg_ctl start failed: {}",
+      String::from_utf8_lossy(&output.stderr).trim()
+    )))
   }
 }
+
+/// Last lines of a server log file, if it exists and is not empty
+fn read_log_tail(path: &std::path::Path) -> Option<String> {
+  const MAX_LINES: usize = 20;
+  let contents = std::fs::read_to_string(path).ok()?;
+  let lines: Vec<&str> = contents.lines().collect();
+  let tail = lines[lines.len().saturating_sub(MAX_LINES)..].join("\n");
+  (!tail.trim().is_empty()).then_some(tail)
+}
+
+/// Unique path in the system temporary directory for short-lived files
+pub(crate) fn scratch_file(extension: &str) -> std::path::PathBuf {
+  let id = uuid::Uuid::new_v7(uuid::Timestamp::now(uuid::NoContext));
+  std::env::temp_dir().join(format!("pg-embedded-{id}.{extension}"))
+}
+
+/// First column of the first row of a query result
+fn first_value(rows: &[Vec<String>]) -> Option<&str> {
+  rows.first()?.first().map(String::as_str)
+}
@@ -0,0 +1,537 @@
+//! Checks of the limits and locale of the environment a server runs in, for `preflight()`
+//!
+//! Constrained CI containers make initdb and the server fail with messages that do not
+//! point at the cause: too few file descriptors, a small `/dev/shm` or an environment
+//! locale that is not installed. Each check reports what it found and how to fix it.
+
+use napi_derive::napi;
+use std::collections::HashMap;
+#[cfg(target_os = "linux")]
+use std::path::Path;
+
+/// Fewer open files than this and the server refuses to start
+#[cfg(any(test, unix))]
+const MIN_OPEN_FILES: u64 = 64;
+/// Fewer open files than this and busy servers run out of file descriptors
+#[cfg(any(test, unix))]
+const RECOMMENDED_OPEN_FILES: u64 = 1024;
+/// Less free space in `/dev/shm` than this and parallel queries fail to allocate memory
+#[cfg(any(test, target_os = "linux"))]
+const RECOMMENDED_SHM_BYTES: u64 = 128 * 1024 * 1024;
+/// Locale categories initdb and the server take from the environment
+#[cfg(any(test, unix))]
+const LOCALE_CATEGORIES: [&str; 6] = [
+  "LC_COLLATE",
+  "LC_CTYPE",
+  "LC_MESSAGES",
+  "LC_MONETARY",
+  "LC_NUMERIC",
+  "LC_TIME",
+];
+
+/// Outcome of a preflight check
+#[napi]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum PreflightSeverity {
+  /// Nothing to do
+  Ok,
+  /// The server starts, but may fail under load or behave unexpectedly
+  Warning,
+  /// Setup or start will fail
+  Error,
+}
+
+/// Result of one check of `preflight()`
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PreflightFinding {
+  /// The check: "openFiles", "sharedMemory", "semaphores" or "locale"
+  pub check: String,
+  /// Whether the check passed, or how the environment affects the server
+  pub severity: PreflightSeverity,
+  /// What was found
+  pub message: String,
+  /// How to fix a warning or error
+  pub remedy: Option<String>,
+}
+
+/// Report of `preflight()`
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct PreflightReport {
+  /// Whether no check found an error
+  pub passed: bool,
+  /// Findings of the checks that apply to this platform, in order
+  pub findings: Vec<PreflightFinding>,
+}
+
+#[cfg(any(test, unix))]
+impl PreflightFinding {
+  fn new(check: &str, severity: PreflightSeverity, message: String) -> Self {
+    Self {
+      check: check.to_string(),
+      severity,
+      message,
+      remedy: None,
+    }
+  }
+
+  fn with_remedy(mut self, remedy: &str) -> Self {
+    self.remedy = Some(remedy.to_string());
+    self
+  }
+}
+
+/// Open file limit from the output of `ulimit -n`
+#[cfg(any(test, unix))]
+fn parse_open_files_limit(output: &str) -> Option<u64> {
+  match output.trim() {
+    "unlimited" => Some(u64::MAX),
+    limit => limit.parse().ok(),
+  }
+}
+
+/// Soft limit on the open files of this process, which the server inherits
+#[cfg(unix)]
+fn open_files_limit() -> Option<u64> {
+  let output = std::process::Command::new("sh")
+    .args(["-c", "ulimit -n"])
+    .output()
+    .ok()?;
+  if !output.status.success() {
+    return None;
+  }
+  parse_open_files_limit(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[cfg(any(test, unix))]
+fn check_open_files(limit: Option<u64>) -> PreflightFinding {
+  const CHECK: &str = "openFiles";
+  const REMEDY: &str = "Raise the limit with `ulimit -n 4096` before starting Node, or with \
+                        `--ulimit nofile=4096:4096` for a Docker container";
+  match limit {
+    None => PreflightFinding::new(
+      CHECK,
+      PreflightSeverity::Warning,
+      "Could not determine the open file limit".to_string(),
+    ),
+    Some(limit) if limit < MIN_OPEN_FILES => PreflightFinding::new(
+      CHECK,
+      PreflightSeverity::Error,
+      format!(
+        "The open file limit is {limit}; the server needs at least {MIN_OPEN_FILES} and \
+         refuses to start with \"insufficient file descriptors available\""
+      ),
+    )
+    .with_remedy(REMEDY),
+    Some(limit) if limit < RECOMMENDED_OPEN_FILES => PreflightFinding::new(
+      CHECK,
+      PreflightSeverity::Warning,
+      format!(
+        "The open file limit is {limit}; with fewer than {RECOMMENDED_OPEN_FILES} busy \
+         servers fail with \"out of file descriptors\""
+      ),
+    )
+    .with_remedy(REMEDY),
+    Some(u64::MAX) => PreflightFinding::new(
+      CHECK,
+      PreflightSeverity::Ok,
+      "The open file limit is unlimited".to_string(),
+    ),
+    Some(limit) => PreflightFinding::new(
+      CHECK,
+      PreflightSeverity::Ok,
+      format!("The open file limit is {limit}"),
+    ),
+  }
+}
+
+/// Free space of `/dev/shm`, where the server allocates dynamic shared memory on Linux
+#[cfg(any(test, target_os = "linux"))]
+fn check_shared_memory(free_bytes: Option<u64>) -> PreflightFinding {
+  const CHECK: &str = "sharedMemory";
+  let mib = |bytes: u64| bytes / (1024 * 1024);
+  match free_bytes {
+    None => PreflightFinding::new(
+      CHECK,
+      PreflightSeverity::Warning,
+      "/dev/shm is not mounted; the server cannot allocate dynamic shared memory".to_string(),
+    )
+    .with_remedy("Mount a tmpfs at /dev/shm, or set `dynamic_shared_memory_type: 'mmap'`"),
+    Some(free) if free < RECOMMENDED_SHM_BYTES => PreflightFinding::new(
+      CHECK,
+      PreflightSeverity::Warning,
+      format!(
+        "/dev/shm has {} MiB free; parallel queries fail with \"could not resize shared \
+         memory segment\" below about {} MiB",
+        mib(free),
+        mib(RECOMMENDED_SHM_BYTES)
+      ),
+    )
+    .with_remedy(
+      "Give the container more shared memory, e.g. `docker run --shm-size=256m`, or set \
+       `max_parallel_workers_per_gather: '0'`",
+    ),
+    Some(free) => PreflightFinding::new(
+      CHECK,
+      PreflightSeverity::Ok,
+      format!("/dev/shm has {} MiB free", mib(free)),
+    ),
+  }
+}
+
+/// Value of the integer server setting `name`, or its default
+#[cfg(any(test, target_os = "macos"))]
+fn setting(configuration: &HashMap<String, String>, name: &str, default: u64) -> u64 {
+  configuration
+    .get(name)
+    .and_then(|value| value.trim().parse().ok())
+    .unwrap_or(default)
+}
+
+/// Semaphore sets and semaphores the server allocates with `configuration`
+///
+/// Every backend and auxiliary process gets a semaphore, in sets of 16 with one extra
+/// semaphore each, as described in "Managing Kernel Resources" of the PostgreSQL manual.
+#[cfg(any(test, target_os = "macos"))]
+fn required_semaphores(configuration: &HashMap<String, String>) -> (u64, u64) {
+  let processes = setting(configuration, "max_connections", 100)
+    + setting(configuration, "autovacuum_max_workers", 3)
+    + setting(configuration, "max_wal_senders", 10)
+    + setting(configuration, "max_worker_processes", 8)
+    + 6;
+  let sets = processes.div_ceil(16);
+  (sets, sets * 17)
+}
+
+/// `SEMMNI` and `SEMMNS` from the output of `sysctl -n kern.sysv.semmni kern.sysv.semmns`
+#[cfg(any(test, target_os = "macos"))]
+fn parse_semaphore_limits(output: &str) -> Option<(u64, u64)> {
+  let mut values = output.split_whitespace().map(|value| value.parse().ok());
+  Some((values.next()??, values.next()??))
+}
+
+/// System V semaphore limits, which the server uses on macOS
+#[cfg(target_os = "macos")]
+fn semaphore_limits() -> Option<(u64, u64)> {
+  let output = std::process::Command::new("sysctl")
+    .args(["-n", "kern.sysv.semmni", "kern.sysv.semmns"])
+    .output()
+    .ok()?;
+  if !output.status.success() {
+    return None;
+  }
+  parse_semaphore_limits(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[cfg(any(test, target_os = "macos"))]
+fn check_semaphores(limits: Option<(u64, u64)>, required: (u64, u64)) -> PreflightFinding {
+  const CHECK: &str = "semaphores";
+  let (required_sets, required_semaphores) = required;
+  match limits {
+    None => PreflightFinding::new(
+      CHECK,
+      PreflightSeverity::Warning,
+      "Could not determine the System V semaphore limits".to_string(),
+    ),
+    Some((sets, semaphores)) if sets < required_sets || semaphores < required_semaphores => {
+      PreflightFinding::new(
+        CHECK,
+        PreflightSeverity::Warning,
+        format!(
+          "The system allows {sets} semaphore sets and {semaphores} semaphores; the server \
+           needs {required_sets} and {required_semaphores}, and fails with \"could not \
+           create semaphores\" when other servers already use some"
+        ),
+      )
+      .with_remedy(
+        "Raise the limits with `sudo sysctl -w kern.sysv.semmni=256 kern.sysv.semmns=512`, \
+         or lower max_connections",
+      )
+    }
+    Some((sets, semaphores)) => PreflightFinding::new(
+      CHECK,
+      PreflightSeverity::Ok,
+      format!(
+        "The system allows {sets} semaphore sets and {semaphores} semaphores; the server \
+         needs {required_sets} and {required_semaphores}"
+      ),
+    ),
+  }
+}
+
+/// Locale names compare equal however their codeset is spelled, e.g. "en_US.UTF-8" and
+/// "en_US.utf8"
+#[cfg(any(test, unix))]
+fn normalize_locale(locale: &str) -> String {
+  match locale.split_once('.') {
+    Some((name, codeset)) => format!("{name}.{}", codeset.to_lowercase().replace('-', "")),
+    None => locale.to_string(),
+  }
+}
+
+/// Whether `locale` is always available
+#[cfg(any(test, unix))]
+fn is_builtin_locale(locale: &str) -> bool {
+  let name = locale.split_once('.').map_or(locale, |(name, _)| name);
+  matches!(name, "C" | "POSIX")
+}
+
+/// The locales initdb and the server use, each with the variable it is taken from
+///
+/// Every category is resolved as the C library does: LC_ALL overrides all of them, then
+/// the category's own variable applies, then LANG. `variable` looks up a variable, empty
+/// values count as unset. A locale set by one variable for several categories is listed
+/// once.
+#[cfg(any(test, unix))]
+fn effective_locales<F>(variable: F) -> Vec<(&'static str, String)>
+where
+  F: Fn(&str) -> Option<String>,
+{
+  let lookup = |name: &str| variable(name).filter(|value| !value.is_empty());
+  let mut locales: Vec<(&'static str, String)> = Vec::new();
+  for category in LOCALE_CATEGORIES {
+    let Some(resolved) = ["LC_ALL", category, "LANG"]
+      .into_iter()
+      .find_map(|name| lookup(name).map(|value| (name, value)))
+    else {
+      continue;
+    };
+    if !locales.contains(&resolved) {
+      locales.push(resolved);
+    }
+  }
+  locales
+}
+
+/// The locales of initdb and the server, with `server_env` taking precedence over the
+/// environment of this process
+#[cfg(unix)]
+fn locale_settings(server_env: &HashMap<String, String>) -> Vec<(&'static str, String)> {
+  effective_locales(|name| {
+    server_env
+      .get(name)
+      .cloned()
+      .or_else(|| std::env::var(name).ok())
+  })
+}
+
+/// Locales installed on the system, from `locale -a`
+#[cfg(unix)]
+fn installed_locales() -> Option<Vec<String>> {
+  let output = std::process::Command::new("locale")
+    .arg("-a")
+    .output()
+    .ok()?;
+  if !output.status.success() {
+    return None;
+  }
+  Some(
+    String::from_utf8_lossy(&output.stdout)
+      .lines()
+      .map(|line| line.trim().to_string())
+      .filter(|line| !line.is_empty())
+      .collect(),
+  )
+}
+
+#[cfg(any(test, unix))]
+fn check_locale(
+  settings: &[(&'static str, String)],
+  installed: Option<&[String]>,
+) -> PreflightFinding {
+  const CHECK: &str = "locale";
+  let wanted: Vec<&(&str, String)> = settings
+    .iter()
+    .filter(|(_, locale)| !is_builtin_locale(locale))
+    .collect();
+  if wanted.is_empty() {
+    return PreflightFinding::new(
+      CHECK,
+      PreflightSeverity::Ok,
+      "The environment uses the built-in C locale".to_string(),
+    );
+  }
+  let Some(installed) = installed else {
+    return PreflightFinding::new(
+      CHECK,
+      PreflightSeverity::Warning,
+      "Could not list the installed locales with `locale -a`".to_string(),
+    );
+  };
+  let installed: Vec<String> = installed.iter().map(|l| normalize_locale(l)).collect();
+  let missing: Vec<String> = wanted
+    .iter()
+    .filter(|(_, locale)| !installed.contains(&normalize_locale(locale)))
+    .map(|(name, locale)| format!("{name}={locale}"))
+    .collect();
+  if missing.is_empty() {
+    let locales: Vec<&str> = wanted.iter().map(|(_, locale)| locale.as_str()).collect();
+    return PreflightFinding::new(
+      CHECK,
+      PreflightSeverity::Ok,
+      format!("The locale {} is installed", locales.join(", ")),
+    );
+  }
+  PreflightFinding::new(
+    CHECK,
+    PreflightSeverity::Error,
+    format!(
+      "The environment sets {}, which is not installed; initdb fails with \"invalid locale \
+       settings\"",
+      missing.join(", ")
+    ),
+  )
+  .with_remedy(
+    "Install the locale (e.g. `locale-gen` or the `locales` package on Debian), or set \
+     `LANG: 'C.UTF-8'` in serverEnv and the environment of Node",
+  )
+}
+
+/// Run the checks that apply to this platform; this starts processes, so it should run on
+/// a blocking thread
+pub(crate) fn run(
+  configuration: &HashMap<String, String>,
+  server_env: &HashMap<String, String>,
+) -> PreflightReport {
+  #[cfg_attr(not(unix), allow(unused_mut))]
+  let mut findings = Vec::new();
+  #[cfg(unix)]
+  findings.push(check_open_files(open_files_limit()));
+  #[cfg(target_os = "linux")]
+  {
+    let shm = Path::new("/dev/shm");
+    let free_bytes = if shm.is_dir() {
+      crate::disk::volume_space(shm).map(|(_, free)| free)
+    } else {
+      None
+    };
+    findings.push(check_shared_memory(free_bytes));
+  }
+  #[cfg(target_os = "macos")]
+  findings.push(check_semaphores(
+    semaphore_limits(),
+    required_semaphores(configuration),
+  ));
+  #[cfg(unix)]
+  findings.push(check_locale(
+    &locale_settings(server_env),
+    installed_locales().as_deref(),
+  ));
+  #[cfg(not(target_os = "macos"))]
+  let _ = configuration;
+  #[cfg(not(unix))]
+  let _ = server_env;
+  PreflightReport {
+    passed: !findings
+      .iter()
+      .any(|finding| finding.severity == PreflightSeverity::Error),
+    findings,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_check_open_files() {
+    assert_eq!(parse_open_files_limit("1024\n"), Some(1024));
+    assert_eq!(parse_open_files_limit("unlimited\n"), Some(u64::MAX));
+    assert_eq!(parse_open_files_limit(""), None);
+    assert_eq!(
+      check_open_files(Some(32)).severity,
+      PreflightSeverity::Error
+    );
+    assert_eq!(
+      check_open_files(Some(256)).severity,
+      PreflightSeverity::Warning
+    );
+    assert!(check_open_files(Some(256)).remedy.is_some());
+    assert_eq!(
+      check_open_files(Some(u64::MAX)).severity,
+      PreflightSeverity::Ok
+    );
+  }
+
+  #[test]
+  fn test_check_shared_memory() {
+    let finding = check_shared_memory(Some(64 * 1024 * 1024));
+    assert_eq!(finding.severity, PreflightSeverity::Warning);
+    assert!(finding.message.contains("64 MiB"));
+    assert_eq!(
+      check_shared_memory(Some(1024 * 1024 * 1024)).severity,
+      PreflightSeverity::Ok
+    );
+  }
+
+  #[test]
+  fn test_check_semaphores() {
+    assert_eq!(required_semaphores(&HashMap::new()), (8, 136));
+    let configuration = HashMap::from([("max_connections".to_string(), "500".to_string())]);
+    assert_eq!(required_semaphores(&configuration), (33, 561));
+    assert_eq!(parse_semaphore_limits("87\n87\n"), Some((87, 87)));
+    assert_eq!(parse_semaphore_limits("87\n"), None);
+    assert_eq!(
+      check_semaphores(Some((87, 87)), (8, 136)).severity,
+      PreflightSeverity::Warning
+    );
+    assert_eq!(
+      check_semaphores(Some((256, 512)), (8, 136)).severity,
+      PreflightSeverity::Ok
+    );
+  }
+
+  #[test]
+  fn test_effective_locales() {
+    let env = HashMap::from([("LC_ALL", "C.UTF-8"), ("LANG", "de_DE.UTF-8")]);
+    assert_eq!(
+      effective_locales(|name| env.get(name).map(|value| value.to_string())),
+      [("LC_ALL", "C.UTF-8".to_string())]
+    );
+
+    let env = HashMap::from([
+      ("LC_ALL", ""),
+      ("LC_CTYPE", "de_DE.UTF-8"),
+      ("LANG", "en_US.UTF-8"),
+    ]);
+    assert_eq!(
+      effective_locales(|name| env.get(name).map(|value| value.to_string())),
+      [
+        ("LANG", "en_US.UTF-8".to_string()),
+        ("LC_CTYPE", "de_DE.UTF-8".to_string())
+      ]
+    );
+
+    assert!(effective_locales(|_| None).is_empty());
+  }
+
+  #[test]
+  fn test_check_locale() {
+    let installed = vec![
+      "C".to_string(),
+      "C.utf8".to_string(),
+      "en_US.utf8".to_string(),
+    ];
+    let settings = vec![("LANG", "en_US.UTF-8".to_string())];
+    assert_eq!(
+      check_locale(&settings, Some(installed.as_slice())).severity,
+      PreflightSeverity::Ok
+    );
+
+    let settings = vec![
+      ("LC_ALL", "de_DE.UTF-8".to_string()),
+      ("LANG", "C.UTF-8".to_string()),
+    ];
+    let finding = check_locale(&settings, Some(installed.as_slice()));
+    assert_eq!(finding.severity, PreflightSeverity::Error);
+    assert!(finding.message.contains("LC_ALL=de_DE.UTF-8"));
+    assert!(!finding.message.contains("LANG"));
+
+    let settings = vec![("LANG", "POSIX".to_string())];
+    assert_eq!(
+      check_locale(&settings, None).severity,
+      PreflightSeverity::Ok
+    );
+  }
+}
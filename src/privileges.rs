@@ -0,0 +1,67 @@
+//! Detection of a root account, which PostgreSQL refuses to run as
+//!
+//! initdb and the server exit with a terse "cannot be run as root" when started by root,
+//! which surfaces as an obscure setup or start failure. The check runs first so the
+//! error explains what to do instead.
+
+use crate::error::{PgEmbedError, Result};
+use crate::logger::pg_log;
+use std::sync::LazyLock;
+
+/// Whether this process runs as root, determined once
+static RUNNING_AS_ROOT: LazyLock<bool> = LazyLock::new(running_as_root);
+
+/// Whether the effective user of this process is root
+#[cfg(unix)]
+fn running_as_root() -> bool {
+  // `id -u` prints the effective user ID, without a dependency on libc for one call.
+  // If `id` cannot run, this deliberately reports "not root": initdb and the server then
+  // still refuse to run as root themselves, only with a less helpful message.
+  std::process::Command::new("id")
+    .arg("-u")
+    .output()
+    .map(|output| output.status.success() && String::from_utf8_lossy(&output.stdout).trim() == "0")
+    .unwrap_or(false)
+}
+
+/// initdb and pg_ctl drop Administrator rights with a restricted token themselves
+#[cfg(not(unix))]
+fn running_as_root() -> bool {
+  false
+}
+
+/// The error for a process running as root, if `root` is set
+fn check_privileges(root: bool) -> Result<()> {
+  if !root {
+    return Ok(());
+  }
+  Err(PgEmbedError::PrivilegeError(
+    "PostgreSQL cannot run as root; run this process as an unprivileged user that owns the \
+     data and installation directories, e.g. with `USER node` in a Dockerfile or \
+     `su <user> -c 'node app.js'`"
+      .to_string(),
+  ))
+}
+
+/// Fail with an explanation when this process runs as root
+pub(crate) fn ensure_not_root() -> Result<()> {
+  let result = check_privileges(*RUNNING_AS_ROOT);
+  if let Err(ref e) = result {
+    pg_log!(warn, "{}", e);
+  }
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_check_privileges() {
+    assert!(check_privileges(false).is_ok());
+    let error = check_privileges(true).unwrap_err().to_string();
+    assert!(error.starts_with("Refusing to run as root: "));
+    assert!(error.contains("cannot run as root"));
+    assert!(error.contains("unprivileged user"));
+  }
+}
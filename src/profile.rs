@@ -0,0 +1,126 @@
+use crate::error::{PgEmbedError, Result};
+use crate::settings::PostgresSettings;
+use crate::types::PostLoadOptimizeOptions;
+use napi_derive::napi;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Reusable environment recipe for a PostgreSQL instance
+///
+/// A profile combines instance settings with server configuration, extensions and
+/// SQL scripts that are applied when a fresh cluster is started for the first time.
+/// Use it with `PostgresInstance.fromProfile()`.
+///
+/// @example
+/// ```typescript
+/// const analytics: DatabaseProfile = {
+///   name: 'analytics',
+///   settings: { databaseName: 'analytics', port: 0 },
+///   serverConfig: { work_mem: '64MB' },
+///   extensions: ['pg_trgm'],
+///   migrationsDir: './migrations',
+///   seedDir: './seeds'
+/// };
+/// const instance = PostgresInstance.fromProfile(analytics);
+/// await instance.start();
+/// ```
+#[napi(object)]
+#[derive(Clone)]
+pub struct DatabaseProfile {
+  /// Profile name used to identify the recipe (e.g. "analytics", "app", "vector")
+  pub name: String,
+  /// Base instance settings
+  pub settings: Option<PostgresSettings>,
+  /// Server configuration parameters, overriding `settings.serverConfig` entries with the same name
+  pub server_config: Option<HashMap<String, String>>,
+  /// Extensions created in the default database
+  pub extensions: Option<Vec<String>>,
+  /// Directory of `.sql` migration scripts, applied in file name order
+  pub migrations_dir: Option<String>,
+  /// Directory of `.sql` seed scripts, applied in file name order after migrations
+  pub seed_dir: Option<String>,
+  /// Run `postLoadOptimize()` with these options after the seed scripts
+  pub post_load_optimize: Option<PostLoadOptimizeOptions>,
+}
+
+impl DatabaseProfile {
+  /// Build the instance settings described by this profile
+  pub(crate) fn to_settings(&self) -> PostgresSettings {
+    let mut settings = self.settings.clone().unwrap_or_default();
+    if let Some(ref server_config) = self.server_config {
+      settings
+        .server_config
+        .get_or_insert_with(HashMap::new)
+        .extend(server_config.clone());
+    }
+    settings
+  }
+}
+
+/// List the `.sql` files of a script directory sorted by file name
+pub(crate) fn collect_sql_files(dir: &str) -> Result<Vec<PathBuf>> {
+  let path = Path::new(dir);
+  let entries = fs::read_dir(path).map_err(|e| {
+    PgEmbedError::ConfigurationError(format!("Cannot read script directory '{dir}': {e}"))
+  })?;
+
+  let mut files = Vec::new();
+  for entry in entries {
+    let file = entry?.path();
+    if file.is_file() && file.extension().is_some_and(|ext| ext == "sql") {
+      files.push(file);
+    }
+  }
+  files.sort();
+  Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_collect_sql_files_sorted() {
+    let dir = std::env::temp_dir().join(format!("pg-embedded-profile-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    for name in ["002_data.sql", "001_schema.sql", "README.md"] {
+      fs::write(dir.join(name), "").unwrap();
+    }
+
+    let files = collect_sql_files(&dir.to_string_lossy()).unwrap();
+    let names: Vec<_> = files
+      .iter()
+      .map(|f| f.file_name().unwrap().to_string_lossy().to_string())
+      .collect();
+    assert_eq!(names, vec!["001_schema.sql", "002_data.sql"]);
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn test_profile_server_config_overrides_settings() {
+    let profile = DatabaseProfile {
+      name: "app".to_string(),
+      settings: Some(PostgresSettings {
+        server_config: Some(HashMap::from([
+          ("work_mem".to_string(), "4MB".to_string()),
+          ("max_connections".to_string(), "50".to_string()),
+        ])),
+        ..Default::default()
+      }),
+      server_config: Some(HashMap::from([(
+        "work_mem".to_string(),
+        "64MB".to_string(),
+      )])),
+      extensions: None,
+      migrations_dir: None,
+      seed_dir: None,
+      post_load_optimize: None,
+    };
+
+    let server_config = profile.to_settings().server_config.unwrap();
+    assert_eq!(server_config["work_mem"], "64MB");
+    assert_eq!(server_config["max_connections"], "50");
+  }
+}
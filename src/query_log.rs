@@ -0,0 +1,200 @@
+//! Reporting of the queries run on native connections to a hook
+//!
+//! Test frameworks use the hook to print slow or failing queries. Query parameters are
+//! masked unless the hook asks for them, as they often carry personal data or secrets.
+
+use crate::client::{QueryResult, TypedQueryResult};
+use crate::error::{PgEmbedError, Result};
+use crate::redact::{redact, MASK};
+use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi::Status;
+use napi_derive::napi;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Options for `setQueryLogHook()`
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct QueryLogOptions {
+  /// Only report successful queries that took at least this many milliseconds; failing
+  /// queries are always reported (default: report every query)
+  pub slow_query_ms: Option<f64>,
+  /// Report the values of query parameters instead of masking them (default: false)
+  pub include_parameters: Option<bool>,
+}
+
+/// A query reported to the hook set with `setQueryLogHook()`
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryLogEvent {
+  /// The SQL that was run, with credentials redacted
+  pub sql: String,
+  /// Parameter values, masked as `********` unless `includeParameters` is set
+  pub parameters: Vec<String>,
+  /// Database the query ran in
+  pub database: Option<String>,
+  /// Time the query took, in milliseconds
+  pub duration_ms: f64,
+  /// Number of rows returned or affected, if the query succeeded
+  pub row_count: Option<u32>,
+  /// Error message, if the query failed
+  pub error: Option<String>,
+}
+
+type QueryLogCallback = ThreadsafeFunction<QueryLogEvent, (), QueryLogEvent, Status, false, true>;
+
+/// The hook of an instance, handed to the native connections it opens
+#[derive(Clone)]
+pub(crate) struct QueryLogger {
+  callback: Arc<QueryLogCallback>,
+  options: QueryLogOptions,
+  database: Option<String>,
+}
+
+impl QueryLogger {
+  pub fn new(callback: QueryLogCallback, options: QueryLogOptions) -> Self {
+    Self {
+      callback: Arc::new(callback),
+      options,
+      database: None,
+    }
+  }
+
+  /// The same hook, reporting `database` as the database of the queries
+  pub fn for_database(&self, database: Option<String>) -> Self {
+    Self {
+      database,
+      ..self.clone()
+    }
+  }
+
+  /// Report a query that started at `started` and ended with `outcome`
+  pub fn log(
+    &self,
+    sql: &str,
+    parameters: &[String],
+    started: Instant,
+    outcome: &Result<impl RowCount>,
+  ) {
+    let outcome = match outcome {
+      Ok(result) => Ok(result.row_count()),
+      Err(PgEmbedError::DatabaseError(message)) => Err(message.clone()),
+      Err(error) => Err(error.to_string()),
+    };
+    if let Some(event) = query_log_event(
+      sql,
+      parameters,
+      self.database.clone(),
+      started.elapsed(),
+      outcome,
+      &self.options,
+    ) {
+      self
+        .callback
+        .call(event, ThreadsafeFunctionCallMode::NonBlocking);
+    }
+  }
+}
+
+/// A query result whose number of rows is reported to the hook
+pub(crate) trait RowCount {
+  fn row_count(&self) -> u32;
+}
+
+impl RowCount for QueryResult {
+  fn row_count(&self) -> u32 {
+    self.row_count
+  }
+}
+
+impl RowCount for TypedQueryResult {
+  fn row_count(&self) -> u32 {
+    self.row_count
+  }
+}
+
+/// The event reporting a query, or `None` if the options filter it out
+pub(crate) fn query_log_event(
+  sql: &str,
+  parameters: &[String],
+  database: Option<String>,
+  elapsed: Duration,
+  outcome: std::result::Result<u32, String>,
+  options: &QueryLogOptions,
+) -> Option<QueryLogEvent> {
+  let duration_ms = elapsed.as_secs_f64() * 1000.0;
+  let fast = options
+    .slow_query_ms
+    .is_some_and(|threshold| duration_ms < threshold);
+  if outcome.is_ok() && fast {
+    return None;
+  }
+  let parameters = if options.include_parameters.unwrap_or(false) {
+    parameters.to_vec()
+  } else {
+    parameters.iter().map(|_| MASK.to_string()).collect()
+  };
+  let (row_count, error) = match outcome {
+    Ok(row_count) => (Some(row_count), None),
+    Err(error) => (None, Some(redact(&error))),
+  };
+  Some(QueryLogEvent {
+    sql: redact(sql),
+    parameters,
+    database,
+    duration_ms,
+    row_count,
+    error,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_query_log_event() {
+    let parameters = ["alice".to_string(), "secret".to_string()];
+    let event = query_log_event(
+      "SELECT * FROM users WHERE name = $1 AND token = $2",
+      &parameters,
+      Some("app".to_string()),
+      Duration::from_millis(12),
+      Ok(1),
+      &QueryLogOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(event.parameters, [MASK, MASK]);
+    assert_eq!(event.row_count, Some(1));
+    assert!(event.duration_ms >= 12.0);
+
+    let options = QueryLogOptions {
+      slow_query_ms: Some(100.0),
+      include_parameters: Some(true),
+    };
+    assert!(query_log_event(
+      "SELECT 1",
+      &[],
+      None,
+      Duration::from_millis(5),
+      Ok(1),
+      &options
+    )
+    .is_none());
+    let event = query_log_event(
+      "SELECT $1",
+      &parameters[..1],
+      None,
+      Duration::from_millis(5),
+      Err("relation \"x\" does not exist".to_string()),
+      &options,
+    )
+    .unwrap();
+    assert_eq!(event.parameters, ["alice"]);
+    assert_eq!(event.row_count, None);
+    assert_eq!(
+      event.error.as_deref(),
+      Some("relation \"x\" does not exist")
+    );
+  }
+}
@@ -0,0 +1,152 @@
+//! Process-wide registry of live PostgresInstance objects, used to look instances up by name or label
+
+use crate::types::InstanceState;
+use napi_derive::napi;
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+
+static REGISTRY: LazyLock<Mutex<HashMap<String, InstanceRecord>>> =
+  LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Registry entry describing a live instance
+pub(crate) struct InstanceRecord {
+  pub name: Option<String>,
+  pub labels: HashMap<String, String>,
+  pub state: Arc<Mutex<InstanceState>>,
+  pub host: String,
+  pub port: u16,
+  pub database_name: String,
+  pub data_dir: String,
+}
+
+/// Summary of a live PostgreSQL instance returned by `findInstances()`
+#[napi(object)]
+pub struct InstanceSummary {
+  /// Unique instance ID
+  pub instance_id: String,
+  /// Instance name from the settings, if any
+  pub name: Option<String>,
+  /// Labels assigned in the instance settings
+  pub labels: HashMap<String, String>,
+  /// Current instance state
+  pub state: InstanceState,
+  /// Host address for connections
+  pub host: String,
+  /// Port number (the resolved port once a random-port instance has started)
+  pub port: u16,
+  /// Default database name
+  pub database_name: String,
+  /// Data directory path
+  pub data_dir: String,
+}
+
+/// Filter for `findInstances()`
+#[napi(object)]
+pub struct InstanceQuery {
+  /// Instance name to match
+  pub name: Option<String>,
+  /// Labels an instance must carry, all with the given values
+  pub label: Option<HashMap<String, String>>,
+}
+
+/// Find live PostgreSQL instances of this process by name or label
+///
+/// Instances are registered when they are created and removed again by `cleanup()`.
+///
+/// @param query - Name and labels the instances must match; all instances are returned when omitted
+/// @returns Summaries of the matching instances
+///
+/// @example
+/// ```typescript
+/// const instances = findInstances({ label: { service: 'orders' } });
+/// for (const instance of instances) {
+///   console.log(instance.instanceId, instance.port);
+/// }
+/// ```
+#[napi]
+pub fn find_instances(query: Option<InstanceQuery>) -> Vec<InstanceSummary> {
+  let query = query.unwrap_or(InstanceQuery {
+    name: None,
+    label: None,
+  });
+  let wanted = query.label.unwrap_or_default();
+  let Ok(registry) = REGISTRY.lock() else {
+    return Vec::new();
+  };
+
+  let mut instances: Vec<InstanceSummary> = registry
+    .iter()
+    .filter(|(_, record)| query.name.is_none() || record.name == query.name)
+    .filter(|(_, record)| matches_labels(&record.labels, &wanted))
+    .map(|(instance_id, record)| InstanceSummary {
+      instance_id: instance_id.clone(),
+      name: record.name.clone(),
+      labels: record.labels.clone(),
+      state: record
+        .state
+        .lock()
+        .map(|state| *state)
+        .unwrap_or(InstanceState::Stopped),
+      host: record.host.clone(),
+      port: record.port,
+      database_name: record.database_name.clone(),
+      data_dir: record.data_dir.clone(),
+    })
+    .collect();
+  // Instance IDs are UUIDv7, so this orders instances by creation time
+  instances.sort_by(|a, b| a.instance_id.cmp(&b.instance_id));
+  instances
+}
+
+fn matches_labels(labels: &HashMap<String, String>, wanted: &HashMap<String, String>) -> bool {
+  wanted
+    .iter()
+    .all(|(key, value)| labels.get(key) == Some(value))
+}
+
+pub(crate) fn register(instance_id: &str, record: InstanceRecord) {
+  if let Ok(mut registry) = REGISTRY.lock() {
+    registry.insert(instance_id.to_string(), record);
+  }
+}
+
+pub(crate) fn unregister(instance_id: &str) {
+  if let Ok(mut registry) = REGISTRY.lock() {
+    registry.remove(instance_id);
+  }
+}
+
+/// Record the port an instance actually listens on
+pub(crate) fn update_port(instance_id: &str, port: u16) {
+  if let Ok(mut registry) = REGISTRY.lock() {
+    if let Some(record) = registry.get_mut(instance_id) {
+      record.port = port;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_matches_labels() {
+    let labels = HashMap::from([
+      ("service".to_string(), "orders".to_string()),
+      ("env".to_string(), "test".to_string()),
+    ]);
+    assert!(matches_labels(&labels, &HashMap::new()));
+    assert!(matches_labels(
+      &labels,
+      &HashMap::from([("service".to_string(), "orders".to_string())])
+    ));
+    assert!(!matches_labels(
+      &labels,
+      &HashMap::from([("service".to_string(), "billing".to_string())])
+    ));
+    assert!(!matches_labels(
+      &labels,
+      &HashMap::from([("team".to_string(), "core".to_string())])
+    ));
+  }
+}
@@ -0,0 +1,165 @@
+//! Streaming replicas created from running instances
+
+use crate::client::run_query_once;
+use crate::conf::{managed_conf, ConfFile};
+use crate::error::{PgEmbedError, Result};
+use crate::logger::pg_log;
+use crate::sql::quote_literal;
+use crate::tools::common::ConnectionConfig;
+use napi_derive::napi;
+use std::path::Path;
+use std::time::Duration;
+
+/// Longest replication slot name the server accepts
+const MAX_SLOT_NAME_LENGTH: usize = 63;
+
+/// How often dropping a slot is tried while the upstream still sees the replica connected
+const DROP_SLOT_ATTEMPTS: u32 = 50;
+
+/// Pause between two attempts to drop a slot
+const DROP_SLOT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Units `recovery_min_apply_delay` accepts; a value without unit is in milliseconds
+const DELAY_UNITS: [&str; 6] = ["us", "ms", "s", "min", "h", "d"];
+
+/// Options for `createReplica()`
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct ReplicaOptions {
+  /// Data directory of the replica (defaults to a temporary directory)
+  pub data_dir: Option<String>,
+  /// Port of the replica (defaults to 0, a free port chosen on start)
+  pub port: Option<u32>,
+  /// Human-readable name of the replica
+  pub name: Option<String>,
+  /// Delay before the replica replays committed transactions, e.g. `'5min'` or `'30s'`,
+  /// written as `recovery_min_apply_delay`
+  pub recovery_min_apply_delay: Option<String>,
+  /// Physical replication slot to create on the upstream and stream through, so the
+  /// upstream keeps WAL the replica has not received yet
+  pub slot_name: Option<String>,
+}
+
+/// Check that `delay` is a time value such as `300000`, `30s` or `5 min`
+pub(crate) fn validate_apply_delay(delay: &str) -> Result<()> {
+  let delay = delay.trim();
+  let digits = delay.len() - delay.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+  let unit = delay[digits..].trim_start();
+  if digits == 0 || !(unit.is_empty() || DELAY_UNITS.contains(&unit)) {
+    return Err(PgEmbedError::ConfigurationError(format!(
+      "Invalid recoveryMinApplyDelay '{delay}': expected a number optionally followed by one of {}",
+      DELAY_UNITS.join(", ")
+    )));
+  }
+  Ok(())
+}
+
+/// Check that `name` is a valid replication slot name (lower case letters, digits and `_`)
+pub(crate) fn validate_slot_name(name: &str) -> Result<()> {
+  let valid = !name.is_empty()
+    && name.len() <= MAX_SLOT_NAME_LENGTH
+    && name
+      .chars()
+      .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+  if !valid {
+    return Err(PgEmbedError::ConfigurationError(format!(
+      "Invalid slotName '{name}': use up to {MAX_SLOT_NAME_LENGTH} lower case letters, digits and underscores"
+    )));
+  }
+  Ok(())
+}
+
+/// Turn a base backup in `data_dir` into a standby streaming from `primary_conninfo`
+///
+/// The upstream may itself be a standby. `primary_conninfo` and `primary_slot_name` are
+/// removed from postgresql.auto.conf, where a backup of a standby carries the connection
+/// to its own upstream, so the managed values take effect.
+pub(crate) fn configure_standby(
+  data_dir: &Path,
+  primary_conninfo: &str,
+  slot_name: Option<&str>,
+  recovery_min_apply_delay: Option<&str>,
+) -> Result<()> {
+  let mut auto_conf = ConfFile::load(data_dir.join("postgresql.auto.conf"))?;
+  auto_conf.remove("primary_conninfo");
+  auto_conf.remove("primary_slot_name");
+  auto_conf.save()?;
+
+  let mut conf = managed_conf(data_dir)?;
+  conf.set("primary_conninfo", primary_conninfo);
+  match slot_name {
+    Some(slot_name) => conf.set("primary_slot_name", slot_name),
+    None => conf.remove("primary_slot_name"),
+  }
+  match recovery_min_apply_delay {
+    Some(delay) => conf.set("recovery_min_apply_delay", delay.trim()),
+    None => conf.remove("recovery_min_apply_delay"),
+  }
+  conf.save()?;
+
+  std::fs::write(data_dir.join("standby.signal"), "").map_err(|e| {
+    PgEmbedError::SetupError(format!(
+      "Failed to write {}: {e}",
+      data_dir.join("standby.signal").display()
+    ))
+  })
+}
+
+/// A physical replication slot on the upstream of a replica
+#[derive(Clone, Debug)]
+pub(crate) struct UpstreamSlot {
+  /// Connection to the upstream the slot was created on
+  pub connection: ConnectionConfig,
+  /// Name of the slot
+  pub name: String,
+}
+
+impl UpstreamSlot {
+  /// Drop the slot, so the upstream stops keeping WAL for the replica
+  ///
+  /// The upstream's walsender may still hold the slot for a moment after the replica
+  /// stopped, so an active slot is retried for a few seconds. Failures are only logged:
+  /// the upstream may already be gone, and with it the slot.
+  pub async fn drop_slot(&self) {
+    let sql = format!(
+      "SELECT pg_drop_replication_slot({})",
+      quote_literal(&self.name)
+    );
+    for attempt in 1..=DROP_SLOT_ATTEMPTS {
+      match run_query_once(&self.connection, &sql, &[], None).await {
+        Ok(_) => return,
+        Err(e) if attempt < DROP_SLOT_ATTEMPTS && e.to_string().contains("is active") => {
+          tokio::time::sleep(DROP_SLOT_RETRY_DELAY).await;
+        }
+        Err(e) => {
+          pg_log!(warn, "Failed to drop replication slot {}: {}", self.name, e);
+          return;
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_validate_apply_delay() {
+    for delay in ["300000", "30s", "5min", "5 min", "1h", "0"] {
+      assert!(validate_apply_delay(delay).is_ok(), "{delay}");
+    }
+    for delay in ["", "min", "5 minutes", "-1s", "5min; DROP"] {
+      assert!(validate_apply_delay(delay).is_err(), "{delay}");
+    }
+  }
+
+  #[test]
+  fn test_validate_slot_name() {
+    assert!(validate_slot_name("tier_2").is_ok());
+    assert!(validate_slot_name("").is_err());
+    assert!(validate_slot_name("Tier2").is_err());
+    assert!(validate_slot_name("tier-2").is_err());
+    assert!(validate_slot_name(&"a".repeat(64)).is_err());
+  }
+}
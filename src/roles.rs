@@ -0,0 +1,191 @@
+//! Login roles managed with `createUser()`, `alterUserPassword()`, `dropUser()` and `listUsers()`
+
+use crate::error::{PgEmbedError, Result};
+use crate::sql::{quote_ident, quote_literal};
+use napi_derive::napi;
+
+/// Options for `createUser()`
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct CreateUserOptions {
+  /// Name of the role
+  pub name: String,
+  /// Password of the role; without one the role can only log in where no password is
+  /// required, e.g. with trust authentication
+  pub password: Option<String>,
+  /// Make the role a superuser (default: false)
+  pub superuser: Option<bool>,
+  /// Allow the role to create databases (default: false)
+  pub createdb: Option<bool>,
+  /// Allow the role to create, alter and drop other roles (default: false)
+  pub createrole: Option<bool>,
+  /// Allow the role to log in (default: true)
+  pub login: Option<bool>,
+}
+
+/// A role, as reported by `listUsers()`
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct UserInfo {
+  /// Name of the role
+  pub name: String,
+  /// Whether the role is a superuser
+  pub superuser: bool,
+  /// Whether the role may create databases
+  pub createdb: bool,
+  /// Whether the role may create other roles
+  pub createrole: bool,
+  /// Whether the role may log in
+  pub login: bool,
+  /// Roles the role is a member of, sorted by name
+  pub member_of: Vec<String>,
+}
+
+/// Roles of the cluster with their attributes and memberships; the predefined `pg_*`
+/// roles are left out
+pub(crate) const LIST_USERS_SQL: &str = "SELECT r.rolname, r.rolsuper, r.rolcreatedb, \
+     r.rolcreaterole, r.rolcanlogin, coalesce(string_agg(m.rolname, E'\\n' ORDER BY m.rolname), '') \
+   FROM pg_roles r \
+   LEFT JOIN pg_auth_members am ON am.member = r.oid \
+   LEFT JOIN pg_roles m ON m.oid = am.roleid \
+   WHERE r.rolname !~ '^pg_' \
+   GROUP BY r.rolname, r.rolsuper, r.rolcreatedb, r.rolcreaterole, r.rolcanlogin \
+   ORDER BY r.rolname";
+
+fn validate_user_name(name: &str) -> Result<()> {
+  if name.is_empty() {
+    return Err(PgEmbedError::ConfigurationError(
+      "User name cannot be empty".to_string(),
+    ));
+  }
+  Ok(())
+}
+
+/// The `PASSWORD` clause of a role; `PASSWORD NULL` removes the password
+fn password_clause(password: Option<&str>) -> String {
+  match password {
+    Some(password) => format!("PASSWORD {}", quote_literal(password)),
+    None => "PASSWORD NULL".to_string(),
+  }
+}
+
+/// `CREATE ROLE` with every attribute spelled out, so no server default applies
+pub(crate) fn create_user_sql(options: &CreateUserOptions) -> Result<String> {
+  validate_user_name(&options.name)?;
+  let attribute = |enabled: Option<bool>, default: bool, name: &str| {
+    if enabled.unwrap_or(default) {
+      name.to_string()
+    } else {
+      format!("NO{name}")
+    }
+  };
+  let mut sql = format!(
+    "CREATE ROLE {} WITH {} {} {} {}",
+    quote_ident(&options.name),
+    attribute(options.superuser, false, "SUPERUSER"),
+    attribute(options.createdb, false, "CREATEDB"),
+    attribute(options.createrole, false, "CREATEROLE"),
+    attribute(options.login, true, "LOGIN"),
+  );
+  if let Some(password) = &options.password {
+    sql.push(' ');
+    sql.push_str(&password_clause(Some(password)));
+  }
+  Ok(sql)
+}
+
+/// `ALTER ROLE` setting or, without a password, removing the password of a role
+pub(crate) fn alter_password_sql(name: &str, password: Option<&str>) -> Result<String> {
+  validate_user_name(name)?;
+  Ok(format!(
+    "ALTER ROLE {} WITH {}",
+    quote_ident(name),
+    password_clause(password)
+  ))
+}
+
+/// `DROP ROLE` of a role
+pub(crate) fn drop_user_sql(name: &str) -> Result<String> {
+  validate_user_name(name)?;
+  Ok(format!("DROP ROLE {}", quote_ident(name)))
+}
+
+/// Parse the rows of `LIST_USERS_SQL`
+pub(crate) fn parse_users(rows: &[Vec<String>]) -> Vec<UserInfo> {
+  rows
+    .iter()
+    .filter_map(|row| {
+      let [name, superuser, createdb, createrole, login, member_of] = row.as_slice() else {
+        return None;
+      };
+      Some(UserInfo {
+        name: name.clone(),
+        superuser: superuser == "t",
+        createdb: createdb == "t",
+        createrole: createrole == "t",
+        login: login == "t",
+        member_of: member_of
+          .lines()
+          .filter(|role| !role.is_empty())
+          .map(str::to_string)
+          .collect(),
+      })
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_create_user_sql() {
+    let options = CreateUserOptions {
+      name: "app".to_string(),
+      password: Some("it's secret".to_string()),
+      createdb: Some(true),
+      ..Default::default()
+    };
+    assert_eq!(
+      create_user_sql(&options).unwrap(),
+      "CREATE ROLE \"app\" WITH NOSUPERUSER CREATEDB NOCREATEROLE LOGIN PASSWORD 'it''s secret'"
+    );
+
+    let group = CreateUserOptions {
+      name: "readers".to_string(),
+      login: Some(false),
+      ..Default::default()
+    };
+    assert_eq!(
+      create_user_sql(&group).unwrap(),
+      "CREATE ROLE \"readers\" WITH NOSUPERUSER NOCREATEDB NOCREATEROLE NOLOGIN"
+    );
+    assert!(create_user_sql(&CreateUserOptions::default()).is_err());
+  }
+
+  #[test]
+  fn test_alter_password_sql() {
+    assert_eq!(
+      alter_password_sql("app", Some("new")).unwrap(),
+      "ALTER ROLE \"app\" WITH PASSWORD 'new'"
+    );
+    assert_eq!(
+      alter_password_sql("app", None).unwrap(),
+      "ALTER ROLE \"app\" WITH PASSWORD NULL"
+    );
+    assert!(drop_user_sql("").is_err());
+  }
+
+  #[test]
+  fn test_parse_users() {
+    let row = |values: [&str; 6]| values.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+    let users = parse_users(&[
+      row(["app", "f", "t", "f", "t", "pg_read_all_data\nreaders"]),
+      row(["readers", "f", "f", "f", "f", ""]),
+    ]);
+    assert_eq!(users[0].member_of, ["pg_read_all_data", "readers"]);
+    assert!(users[0].createdb && users[0].login && !users[0].superuser);
+    assert!(users[1].member_of.is_empty());
+    assert!(!users[1].login);
+  }
+}
@@ -0,0 +1,470 @@
+//! Splitting of SQL scripts into statements
+//!
+//! Semicolons only end a statement outside of string literals, quoted identifiers,
+//! dollar-quoted bodies, comments and parentheses. `BEGIN ATOMIC ... END` bodies of SQL
+//! functions and procedures are kept in one piece, and the data lines following
+//! `COPY ... FROM STDIN` up to the `\.` terminator belong to the COPY statement, the way
+//! psql reads them.
+
+use crate::client::QueryResult;
+use napi_derive::napi;
+
+#[napi]
+#[derive(Clone, Debug, PartialEq)]
+/// What a statement of a SQL script is.
+pub enum SqlStatementKind {
+  /// A statement sent to the server as is.
+  Statement,
+  /// A `COPY ... FROM STDIN` statement followed by its data.
+  CopyFromStdin,
+  /// A psql meta-command such as `\connect`, which only psql understands.
+  MetaCommand,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+/// A statement of a SQL script.
+pub struct SqlStatement {
+  /// The statement text without the terminating semicolon, or the meta-command line.
+  pub sql: String,
+  /// What the statement is.
+  pub kind: SqlStatementKind,
+  /// Line of the script the statement starts on, counting from 1.
+  pub line: u32,
+  /// Data lines of a `COPY ... FROM STDIN` statement, each ending with a newline,
+  /// without the `\.` terminator.
+  #[napi(js_name = "copyData")]
+  pub copy_data: Option<String>,
+}
+
+/// Options for `executeSqlBatch()`
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct ExecuteSqlBatchOptions {
+  /// Run all statements in one transaction, which is rolled back if a statement fails
+  /// (default: false)
+  pub transaction: Option<bool>,
+  /// Record a failing statement in the results and carry on with the next one instead of
+  /// throwing; within a transaction only the failing statement is rolled back (default: false)
+  pub continue_on_error: Option<bool>,
+}
+
+/// Outcome of one statement run by `executeSqlBatch()`
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct SqlStatementResult {
+  /// Line of the script the statement starts on, counting from 1
+  pub line: u32,
+  /// The statement text
+  pub sql: String,
+  /// Rows returned or affected, if the statement succeeded
+  pub result: Option<QueryResult>,
+  /// Error message, if the statement failed
+  pub error: Option<String>,
+}
+
+/// Keywords a statement starts with, enough to tell a routine definition
+const LEADING_WORDS: usize = 4;
+
+/// The statement being read
+#[derive(Default)]
+struct Pending {
+  start: usize,
+  line: u32,
+  paren_depth: i32,
+  begin_depth: i32,
+  words: Vec<String>,
+  previous_word: String,
+  from_stdin: bool,
+}
+
+impl Pending {
+  fn new(start: usize, line: u32) -> Self {
+    Self {
+      start,
+      line,
+      ..Default::default()
+    }
+  }
+
+  /// Track a keyword or identifier of the statement
+  fn word(&mut self, word: &str) {
+    let word = word.to_ascii_uppercase();
+    if self.words.len() < LEADING_WORDS {
+      self.words.push(word.clone());
+    }
+    if self.is_routine() {
+      match word.as_str() {
+        "BEGIN" | "CASE" => self.begin_depth += 1,
+        "END" => self.begin_depth = (self.begin_depth - 1).max(0),
+        _ => {}
+      }
+    }
+    if self.previous_word == "FROM" && word == "STDIN" {
+      self.from_stdin = true;
+    }
+    self.previous_word = word;
+  }
+
+  /// Whether the statement defines a function or procedure, whose body may contain
+  /// semicolons inside `BEGIN ATOMIC ... END`
+  fn is_routine(&self) -> bool {
+    let words: Vec<&str> = self.words.iter().map(String::as_str).collect();
+    matches!(
+      words.as_slice(),
+      ["CREATE", "FUNCTION" | "PROCEDURE", ..]
+        | ["CREATE", "OR", "REPLACE", "FUNCTION" | "PROCEDURE", ..]
+    )
+  }
+
+  fn is_copy_from_stdin(&self) -> bool {
+    self.from_stdin && self.words.first().is_some_and(|word| word == "COPY")
+  }
+
+  /// Whether a semicolon at this point ends the statement
+  fn at_top_level(&self) -> bool {
+    self.paren_depth <= 0 && self.begin_depth <= 0
+  }
+}
+
+fn is_ident_start(byte: u8) -> bool {
+  byte.is_ascii_alphabetic() || byte == b'_' || byte >= 0x80
+}
+
+fn is_ident_char(byte: u8) -> bool {
+  is_ident_start(byte) || byte.is_ascii_digit() || byte == b'$'
+}
+
+/// Split `text` into its statements
+///
+/// Empty statements and text consisting only of comments are skipped. An unterminated
+/// last statement is returned as is.
+pub(crate) fn split_script(text: &str) -> Vec<SqlStatement> {
+  let bytes = text.as_bytes();
+  let mut statements = Vec::new();
+  let mut pending: Option<Pending> = None;
+  let mut line = 1u32;
+  let mut index = 0;
+
+  while index < bytes.len() {
+    let byte = bytes[index];
+    let next = bytes.get(index + 1).copied();
+
+    if byte == b'\n' {
+      line += 1;
+      index += 1;
+      continue;
+    }
+    if byte == b'-' && next == Some(b'-') {
+      index = end_of_line(bytes, index);
+      continue;
+    }
+    if byte == b'/' && next == Some(b'*') {
+      let end = skip_block_comment(bytes, index);
+      line += count_lines(&bytes[index..end]);
+      index = end;
+      continue;
+    }
+
+    let Some(statement) = pending.as_mut() else {
+      if byte.is_ascii_whitespace() || byte == b';' {
+        index += 1;
+      } else if byte == b'\\' {
+        let end = end_of_line(bytes, index);
+        statements.push(SqlStatement {
+          sql: text[index..end].trim_end().to_string(),
+          kind: SqlStatementKind::MetaCommand,
+          line,
+          copy_data: None,
+        });
+        index = end;
+      } else {
+        pending = Some(Pending::new(index, line));
+      }
+      continue;
+    };
+
+    match byte {
+      b'\'' => {
+        let escapes = index > 0
+          && matches!(bytes[index - 1], b'E' | b'e')
+          && (index < 2 || !is_ident_char(bytes[index - 2]));
+        let end = skip_quoted(bytes, index, b'\'', escapes);
+        line += count_lines(&bytes[index..end]);
+        index = end;
+      }
+      b'"' => {
+        let end = skip_quoted(bytes, index, b'"', false);
+        line += count_lines(&bytes[index..end]);
+        index = end;
+      }
+      b'$' => match dollar_tag(bytes, index) {
+        Some(tag) => {
+          let body = index + tag.len();
+          let end = find(&bytes[body..], tag)
+            .map(|position| body + position + tag.len())
+            .unwrap_or(bytes.len());
+          line += count_lines(&bytes[index..end]);
+          index = end;
+        }
+        None => index += 1,
+      },
+      b'(' => {
+        statement.paren_depth += 1;
+        index += 1;
+      }
+      b')' => {
+        statement.paren_depth -= 1;
+        index += 1;
+      }
+      b';' if statement.at_top_level() => {
+        let sql = text[statement.start..index].trim_end().to_string();
+        let (kind, copy_data) = if statement.is_copy_from_stdin() {
+          let (data, end, lines) = copy_data(text, end_of_line(bytes, index));
+          index = end;
+          line += lines;
+          (SqlStatementKind::CopyFromStdin, Some(data))
+        } else {
+          index += 1;
+          (SqlStatementKind::Statement, None)
+        };
+        statements.push(SqlStatement {
+          sql,
+          kind,
+          line: statement.line,
+          copy_data,
+        });
+        pending = None;
+      }
+      _ if is_ident_start(byte) => {
+        let end = bytes[index..]
+          .iter()
+          .position(|&byte| !is_ident_char(byte))
+          .map_or(bytes.len(), |length| index + length);
+        statement.word(&text[index..end]);
+        index = end;
+      }
+      _ => index += 1,
+    }
+  }
+
+  if let Some(statement) = pending {
+    let sql = text[statement.start..].trim_end().to_string();
+    if !sql.is_empty() {
+      statements.push(SqlStatement {
+        sql,
+        kind: SqlStatementKind::Statement,
+        line: statement.line,
+        copy_data: None,
+      });
+    }
+  }
+  statements
+}
+
+/// Index of the newline ending the line at `index`, or the end of the text
+fn end_of_line(bytes: &[u8], index: usize) -> usize {
+  find(&bytes[index..], b"\n").map_or(bytes.len(), |position| index + position)
+}
+
+fn count_lines(bytes: &[u8]) -> u32 {
+  bytes.iter().filter(|&&byte| byte == b'\n').count() as u32
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+  haystack
+    .windows(needle.len())
+    .position(|window| window == needle)
+}
+
+/// End of the (possibly nested) block comment starting at `index`
+fn skip_block_comment(bytes: &[u8], index: usize) -> usize {
+  let mut depth = 0;
+  let mut index = index;
+  while index < bytes.len() {
+    match (bytes[index], bytes.get(index + 1)) {
+      (b'/', Some(b'*')) => {
+        depth += 1;
+        index += 2;
+      }
+      (b'*', Some(b'/')) => {
+        depth -= 1;
+        index += 2;
+        if depth == 0 {
+          return index;
+        }
+      }
+      _ => index += 1,
+    }
+  }
+  bytes.len()
+}
+
+/// End of the literal or identifier quoted with `quote` starting at `index`; a doubled
+/// quote stands for itself, and with `escapes` so does a backslash-escaped one
+fn skip_quoted(bytes: &[u8], index: usize, quote: u8, escapes: bool) -> usize {
+  let mut index = index + 1;
+  while index < bytes.len() {
+    match bytes[index] {
+      b'\\' if escapes => index += 2,
+      byte if byte == quote => {
+        if bytes.get(index + 1) == Some(&quote) {
+          index += 2;
+        } else {
+          return index + 1;
+        }
+      }
+      _ => index += 1,
+    }
+  }
+  bytes.len()
+}
+
+/// The dollar-quote tag (`$$` or `$name$`) starting at `index`, if it starts one
+///
+/// A `$` inside an identifier or followed by a digit (a parameter such as `$1`) does not.
+fn dollar_tag(bytes: &[u8], index: usize) -> Option<&[u8]> {
+  if index > 0 && is_ident_char(bytes[index - 1]) {
+    return None;
+  }
+  let name_start = index + 1;
+  if bytes
+    .get(name_start)
+    .is_some_and(|&byte| !is_ident_start(byte) && byte != b'$')
+  {
+    return None;
+  }
+  let name_length = bytes[name_start..]
+    .iter()
+    .position(|&byte| !(is_ident_char(byte) && byte != b'$'))?;
+  let end = name_start + name_length;
+  (bytes.get(end) == Some(&b'$')).then(|| &bytes[index..=end])
+}
+
+/// Data lines of a COPY statement following the newline at `index`, the index of the end
+/// of the `\.` terminator line, and the number of newlines consumed
+///
+/// Data without a terminator ends with the script, as in psql.
+fn copy_data(text: &str, index: usize) -> (String, usize, u32) {
+  let mut data = String::new();
+  let mut index = index;
+  let mut lines = 0;
+  while index < text.len() {
+    index += 1;
+    lines += 1;
+    if index >= text.len() {
+      break;
+    }
+    let end = end_of_line(text.as_bytes(), index);
+    let data_line = text[index..end].trim_end_matches('\r');
+    if data_line == "\\." {
+      return (data, end, lines);
+    }
+    data.push_str(data_line);
+    data.push('\n');
+    index = end;
+  }
+  (data, text.len(), lines)
+}
+
+#[napi]
+/// Splits a SQL script into its statements.
+///
+/// Unlike splitting at every semicolon, this keeps string literals, quoted identifiers,
+/// comments, dollar-quoted function bodies and `BEGIN ATOMIC ... END` bodies intact,
+/// attaches the data lines of `COPY ... FROM STDIN` to the COPY statement and reports
+/// psql meta-commands such as `\connect` separately.
+///
+/// @param text - The SQL script
+/// @returns The statements in script order, with the line each starts on
+///
+/// @example
+/// ```typescript
+/// import { splitSqlScript, SqlStatementKind } from 'pg-embedded';
+///
+/// const statements = splitSqlScript(fs.readFileSync('schema.sql', 'utf8'));
+/// for (const statement of statements) {
+///   if (statement.kind === SqlStatementKind.MetaCommand) {
+///     console.warn(`skipping line ${statement.line}: ${statement.sql}`);
+///   }
+/// }
+/// ```
+pub fn split_sql_script(text: String) -> Vec<SqlStatement> {
+  split_script(&text)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn sql(statements: &[SqlStatement]) -> Vec<&str> {
+    statements
+      .iter()
+      .map(|statement| statement.sql.as_str())
+      .collect()
+  }
+
+  #[test]
+  fn test_split_simple_statements() {
+    let statements = split_script("SELECT 1;\n\n  SELECT 'a;b' AS \"x;y\" ;;\n-- done\nSELECT 2");
+    assert_eq!(
+      sql(&statements),
+      ["SELECT 1", "SELECT 'a;b' AS \"x;y\"", "SELECT 2"]
+    );
+    assert_eq!(
+      statements
+        .iter()
+        .map(|statement| statement.line)
+        .collect::<Vec<_>>(),
+      [1, 3, 5]
+    );
+    assert!(split_script("-- only a comment\n/* and /* nested */ one; */\n").is_empty());
+  }
+
+  #[test]
+  fn test_split_quoted_bodies() {
+    let script = "CREATE FUNCTION f() RETURNS int AS $body$\nBEGIN\n  RETURN 1;\nEND;\n$body$ \
+                  LANGUAGE plpgsql;\nSELECT E'it\\'s;', 'o''k;', $$a;b$$, $1;\nSELECT 3;";
+    let statements = split_script(script);
+    assert_eq!(statements.len(), 3);
+    assert!(statements[0].sql.ends_with("LANGUAGE plpgsql"));
+    assert_eq!(statements[1].sql, "SELECT E'it\\'s;', 'o''k;', $$a;b$$, $1");
+    assert_eq!(statements[1].line, 6);
+    assert_eq!(statements[2].line, 7);
+  }
+
+  #[test]
+  fn test_split_begin_atomic() {
+    let script = "CREATE OR REPLACE FUNCTION g(x int) RETURNS int LANGUAGE sql\nBEGIN ATOMIC\n  \
+                  SELECT CASE WHEN x > 0 THEN 1 ELSE 0 END;\n  SELECT x;\nEND;\nCREATE RULE r AS ON \
+                  INSERT TO t DO ALSO (NOTIFY t; NOTIFY u);\nSELECT 1;";
+    let statements = split_script(script);
+    assert_eq!(statements.len(), 3);
+    assert!(statements[0].sql.ends_with("SELECT x;\nEND"));
+    assert!(statements[1].sql.ends_with("NOTIFY u)"));
+  }
+
+  #[test]
+  fn test_split_copy_data() {
+    let script = "COPY public.t (id, note) FROM stdin;\n1\thello; world\n2\t\\N\n\\.\n\\connect \
+                  other\nSELECT 1;\n";
+    let statements = split_script(script);
+    assert_eq!(statements.len(), 3);
+    assert_eq!(statements[0].kind, SqlStatementKind::CopyFromStdin);
+    assert_eq!(statements[0].sql, "COPY public.t (id, note) FROM stdin");
+    assert_eq!(
+      statements[0].copy_data.as_deref(),
+      Some("1\thello; world\n2\t\\N\n")
+    );
+    assert_eq!(statements[1].kind, SqlStatementKind::MetaCommand);
+    assert_eq!(statements[1].sql, "\\connect other");
+    assert_eq!(statements[1].line, 5);
+    assert_eq!(statements[2].line, 6);
+
+    let statements = split_script("COPY t FROM STDIN;\r\n1\r\n2");
+    assert_eq!(statements[0].copy_data.as_deref(), Some("1\n2\n"));
+    let statements = split_script("COPY t TO STDOUT; SELECT 1;");
+    assert_eq!(statements[0].kind, SqlStatementKind::Statement);
+    assert_eq!(statements.len(), 2);
+  }
+}
@@ -0,0 +1,93 @@
+//! Environment of the server process (the `serverEnv` setting)
+
+use crate::error::{PgEmbedError, Result};
+use postgresql_commands::pg_ctl::{Mode, PgCtlBuilder};
+use postgresql_commands::traits::CommandBuilder;
+use std::collections::HashMap;
+use std::process::Command;
+
+/// Check that every variable can be set on a process
+pub(crate) fn validate_server_env(server_env: &HashMap<String, String>) -> Result<()> {
+  for (name, value) in server_env {
+    if name.is_empty() || name.contains('=') || name.contains('\0') {
+      return Err(PgEmbedError::ConfigurationError(format!(
+        "Invalid server environment variable name '{name}'"
+      )));
+    }
+    if value.contains('\0') {
+      return Err(PgEmbedError::ConfigurationError(format!(
+        "Server environment variable {name} contains a NUL character"
+      )));
+    }
+  }
+  Ok(())
+}
+
+/// `pg_ctl start` with `server_env` added to its environment, which the postmaster inherits
+///
+/// The arguments are those postgresql_embedded starts the server with, so the server is
+/// configured the same way whether or not the setting is used.
+pub(crate) fn start_command(
+  settings: &postgresql_embedded::Settings,
+  server_env: &HashMap<String, String>,
+) -> Command {
+  let mut options = vec![format!("-F -p {}", settings.port)];
+  #[cfg(unix)]
+  if let Some(socket_dir) = &settings.socket_dir {
+    options.push(format!("-k {}", socket_dir.to_string_lossy()));
+  }
+  for (key, value) in &settings.configuration {
+    options.push(format!("-c {key}={value}"));
+  }
+
+  let mut builder = PgCtlBuilder::from(settings).env("PGDATABASE", "");
+  let mut names: Vec<&String> = server_env.keys().collect();
+  names.sort();
+  for name in names {
+    builder = builder.env(name.as_str(), server_env[name].as_str());
+  }
+  builder
+    .mode(Mode::Start)
+    .pgdata(&settings.data_dir)
+    .log(settings.data_dir.join("start.log"))
+    .options(options.as_slice())
+    .wait()
+    .build()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::ffi::OsStr;
+
+  #[test]
+  fn test_validate_server_env() {
+    let env = |name: &str, value: &str| HashMap::from([(name.to_string(), value.to_string())]);
+    assert!(validate_server_env(&env("LD_LIBRARY_PATH", "/opt/ext/lib")).is_ok());
+    assert!(validate_server_env(&env("", "x")).is_err());
+    assert!(validate_server_env(&env("A=B", "x")).is_err());
+    assert!(validate_server_env(&env("PGLOCALEDIR", "a\0b")).is_err());
+  }
+
+  #[test]
+  fn test_start_command() {
+    let settings = postgresql_embedded::Settings {
+      port: 5433,
+      data_dir: "/srv/pg".into(),
+      configuration: HashMap::from([("timezone".to_string(), "UTC".to_string())]),
+      ..Default::default()
+    };
+    let server_env = HashMap::from([("PGLOCALEDIR".to_string(), "/opt/locale".to_string())]);
+    let command = start_command(&settings, &server_env);
+
+    let envs: Vec<_> = command.get_envs().collect();
+    assert!(envs.contains(&(OsStr::new("PGLOCALEDIR"), Some(OsStr::new("/opt/locale")))));
+    let args: Vec<String> = command
+      .get_args()
+      .map(|arg| arg.to_string_lossy().to_string())
+      .collect();
+    assert!(args.contains(&"start".to_string()));
+    assert!(args.contains(&"-F -p 5433".to_string()));
+    assert!(args.contains(&"-c timezone=UTC".to_string()));
+  }
+}
@@ -1,7 +1,76 @@
-use crate::error::configuration_error;
+use crate::error::{configuration_error, PgEmbedError, Result};
+use crate::paths::native_path;
+use crate::server_env::validate_server_env;
 use napi_derive::napi;
-use postgresql_embedded::Settings;
-use std::path::PathBuf;
+use postgresql_embedded::{Settings, VersionReq};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const DEFAULT_CONNECTION_CACHE_TTL_SECONDS: u32 = 300;
+
+/// Prefix of generated temporary data directory names
+const DEFAULT_TEMP_DIR_PREFIX: &str = "pg-embedded";
+
+/// Server parameters of the `reproducible` preset; explicit settings take precedence
+const REPRODUCIBLE_CONFIG: &[(&str, &str)] = &[
+  ("timezone", "UTC"),
+  ("datestyle", "ISO,MDY"),
+  ("intervalstyle", "postgres"),
+  ("lc_messages", "C"),
+  ("lc_monetary", "C"),
+  ("lc_numeric", "C"),
+  ("lc_time", "C"),
+  ("default_statistics_target", "100"),
+  ("extra_float_digits", "1"),
+];
+
+/// First server version with login event triggers, which seed `random()` per session
+pub(crate) const LOGIN_EVENT_TRIGGER_VERSION: u32 = 170000;
+
+/// Seed `random()` with a fixed value at the start of every session of the database it
+/// runs in; safe to run again. The function lives in its own schema, away from user objects.
+pub(crate) const REPRODUCIBLE_SEED_SQL: &str = "CREATE SCHEMA IF NOT EXISTS _pg_embedded; \
+   CREATE OR REPLACE FUNCTION _pg_embedded.seed_session() RETURNS event_trigger \
+   LANGUAGE plpgsql AS $$ BEGIN PERFORM setseed(0.5); END $$; \
+   DROP EVENT TRIGGER IF EXISTS pg_embedded_seed_session; \
+   CREATE EVENT TRIGGER pg_embedded_seed_session ON login \
+   EXECUTE FUNCTION _pg_embedded.seed_session();";
+
+/// Remove what `REPRODUCIBLE_SEED_SQL` installed from the database it runs in
+pub(crate) const REPRODUCIBLE_UNSEED_SQL: &str =
+  "DROP EVENT TRIGGER IF EXISTS pg_embedded_seed_session; \
+   DROP SCHEMA IF EXISTS _pg_embedded CASCADE;";
+
+/// Password hashing method used for role passwords
+#[napi]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PasswordEncryption {
+  /// Legacy MD5 hashes, for clients that do not support SCRAM
+  Md5,
+  /// SCRAM-SHA-256 hashes (PostgreSQL default)
+  ScramSha256,
+}
+
+impl PasswordEncryption {
+  /// Value of the `password_encryption` server parameter, which is also the pg_hba.conf method
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      PasswordEncryption::Md5 => "md5",
+      PasswordEncryption::ScramSha256 => "scram-sha-256",
+    }
+  }
+
+  /// Prefix of role password hashes stored with this method in pg_authid
+  pub(crate) fn hash_prefix(&self) -> &'static str {
+    match self {
+      PasswordEncryption::Md5 => "md5",
+      PasswordEncryption::ScramSha256 => "SCRAM-SHA-256$",
+    }
+  }
+}
 
 /// PostgreSQL configuration settings
 ///
@@ -18,50 +87,244 @@ use std::path::PathBuf;
 /// };
 /// ```
 #[napi(object)]
+#[derive(Clone)]
 pub struct PostgresSettings {
+  /// Human-readable instance name used in log messages, `findInstances()` results and the
+  /// temporary data directory name (letters, digits, "-", "_" and "." only)
+  pub name: Option<String>,
   /// PostgreSQL version (e.g., "15.0", ">=14.0")
   pub version: Option<String>,
   /// Host address for database connection (default: "localhost")
   pub host: Option<String>,
+  /// Comma-separated addresses the server listens on (default: "localhost", "*" for all interfaces)
+  pub listen_addresses: Option<String>,
+  /// Make the server reachable from other machines, e.g. from the host of a devcontainer.
+  /// Listens on all interfaces and allows password logins from any address in pg_hba.conf.
+  /// Requires a password; trust authentication is rejected (default: false)
+  pub expose_externally: Option<bool>,
   /// Port number (0-65535, default: 5432, 0 for random)
   pub port: Option<u32>,
   /// Username for database connection (default: "postgres")
   pub username: Option<String>,
-  /// Password for database connection (default: "postgres")
+  /// Password for database connection (default: "postgres").
+  /// Set to null or an empty string to run without passwords using trust authentication.
+  #[napi(ts_type = "string | null")]
   pub password: Option<String>,
-  /// Default database name (default: "postgres")
+  /// Password hashing for role passwords, including the superuser and roles created later.
+  /// Also selects the matching pg_hba.conf authentication method (default: server default)
+  pub password_encryption: Option<PasswordEncryption>,
+  /// Default database name, created on first start if missing (default: "postgres")
   pub database_name: Option<String>,
   /// Custom data directory path
   pub data_dir: Option<String>,
+  /// Directory generated temporary data directories are created in when `dataDir` is not
+  /// set, e.g. a project's `.cache/pg-embedded` (default: the OS temp directory)
+  pub temp_dir_parent: Option<String>,
+  /// Prefix of generated temporary data directory names, which are
+  /// `<prefix>-<name>-<random suffix>` (default: "pg-embedded"; letters, digits, "-", "_"
+  /// and "." only)
+  pub temp_dir_prefix: Option<String>,
   /// Custom installation directory path
   pub installation_dir: Option<String>,
+  /// ICU locale of a new cluster (e.g. "en-US"). Initializes the cluster with the ICU
+  /// locale provider (`initdb --locale-provider=icu --icu-locale=...`); ignored for a data
+  /// directory that is already initialized
+  pub icu_locale: Option<String>,
   /// Timeout in seconds for database operations (default: 30)
   pub timeout: Option<u32>,
   /// Setup timeout in seconds for PostgreSQL initialization (default: 300 on Windows, 30 on other platforms)
   pub setup_timeout: Option<u32>,
   /// Whether to persist data between runs (default: false)
   pub persistent: Option<bool>,
+  /// Run setup automatically when `start(false)` finds an uninitialized data directory
+  /// instead of failing (default: false)
+  pub auto_setup: Option<bool>,
+  /// Take an undo point before destructive operations (`dropDatabase()`,
+  /// `dropDatabaseIfExists()`, `createRestore()` and `createRewind()`) so that
+  /// `undoLastDestructiveOp()` can revert them, e.g. for persistent local instances
+  /// (default: false)
+  pub safe_mode: Option<bool>,
+  /// Server configuration parameters passed to the server on start (e.g. { shared_buffers: '256MB' })
+  pub server_config: Option<HashMap<String, String>>,
+  /// Environment variables added to the server process (e.g. { LD_LIBRARY_PATH: '/opt/ext/lib' }
+  /// for the libraries of custom extensions, or PGLOCALEDIR). The server otherwise inherits
+  /// the environment of this process
+  pub server_env: Option<HashMap<String, String>>,
+  /// Default time zone of the server, e.g. "America/New_York" (the `timezone` parameter)
+  pub timezone: Option<String>,
+  /// Default date output and input interpretation style, e.g. "ISO, DMY" (the `datestyle` parameter)
+  pub datestyle: Option<String>,
+  /// Preset for output that is identical on every machine, e.g. for golden-file tests:
+  /// fixes the time zone (UTC), date and interval styles, the C locale for messages,
+  /// numbers, money and times and `default_statistics_target`, and seeds `random()` with
+  /// `setseed(0.5)` at the start of every session. The seed comes from a login event
+  /// trigger (PostgreSQL 17 and later) with a function in the schema `_pg_embedded`,
+  /// installed in template1, postgres and the instance's database on start and removed
+  /// from all databases when a persistent instance stops. `serverConfig`, `timezone` and
+  /// `datestyle` override the preset (default: false)
+  pub reproducible: Option<bool>,
+  /// Number of transactions that can be in the prepared state at once, enabling two-phase
+  /// commit (the `max_prepared_transactions` parameter, default: 0, which disables it)
+  pub max_prepared_transactions: Option<u32>,
+  /// Directory completed WAL segments are archived to. Enables `archive_mode` with a
+  /// platform-appropriate `archive_command` (see `archiveCommand()`); created if missing
+  pub wal_archive_dir: Option<String>,
+  /// How long connection information is cached, in seconds (default: 300, 0 disables caching)
+  pub connection_cache_ttl_seconds: Option<u32>,
+  /// Labels identifying the instance (e.g. { service: 'orders' }), used by `findInstances()`
+  pub labels: Option<HashMap<String, String>>,
+  /// `application_name` of the connections pg-embedded opens itself, such as `executeSql()`
+  /// and the tools, so they can be told apart in pg_stat_activity
+  /// (default: "pg-embedded/<instanceId>", an empty string disables the tag)
+  pub application_name: Option<String>,
+  /// Suppress all log output, e.g. for command-line tools whose output must stay
+  /// machine-parseable. Logging is process-wide, so the whole process is quiet until the
+  /// instance is cleaned up (see `setQuietMode()`) (default: false)
+  pub quiet: Option<bool>,
 }
 
 impl Default for PostgresSettings {
   fn default() -> Self {
     Self {
+      name: None,
       version: None,
       host: Some("localhost".to_string()),
+      listen_addresses: None,
+      expose_externally: None,
       port: Some(5432),
       username: Some("postgres".to_string()),
       password: Some("postgres".to_string()),
+      password_encryption: None,
       database_name: Some("postgres".to_string()),
       data_dir: None,
+      temp_dir_parent: None,
+      temp_dir_prefix: None,
       installation_dir: None,
+      icu_locale: None,
       timeout: Some(30),
       setup_timeout: None,
       persistent: Some(false),
+      auto_setup: None,
+      safe_mode: None,
+      server_config: None,
+      server_env: None,
+      timezone: None,
+      datestyle: None,
+      reproducible: None,
+      max_prepared_transactions: None,
+      wal_archive_dir: None,
+      connection_cache_ttl_seconds: None,
+      labels: None,
+      application_name: None,
+      quiet: None,
     }
   }
 }
 
 impl PostgresSettings {
+  /// Whether the instance runs without passwords (trust authentication)
+  pub(crate) fn is_trust_auth(&self) -> bool {
+    self.password.as_deref() == Some("")
+  }
+
+  /// Whether the server accepts connections from other machines
+  pub(crate) fn is_exposed_externally(&self) -> bool {
+    self.expose_externally.unwrap_or(false)
+  }
+
+  /// Lifetime of cached connection information; zero disables the cache
+  pub(crate) fn connection_cache_ttl(&self) -> Duration {
+    Duration::from_secs(
+      self
+        .connection_cache_ttl_seconds
+        .unwrap_or(DEFAULT_CONNECTION_CACHE_TTL_SECONDS)
+        .into(),
+    )
+  }
+
+  /// Settings of member `index` of a group created from this template by `createMany()`
+  ///
+  /// A fixed port is offset by the index, while port 0 or no port stays random. The data
+  /// directory, WAL archive directory and name get a `-<index>` suffix so the members never
+  /// share a cluster.
+  pub(crate) fn for_group_member(&self, index: u32) -> Self {
+    Self {
+      port: match self.port {
+        None | Some(0) => Some(0),
+        Some(port) => Some(port + index),
+      },
+      data_dir: self
+        .data_dir
+        .as_ref()
+        .map(|data_dir| format!("{}-{index}", data_dir.trim_end_matches(['/', '\\']))),
+      wal_archive_dir: self
+        .wal_archive_dir
+        .as_ref()
+        .map(|dir| format!("{}-{index}", dir.trim_end_matches(['/', '\\']))),
+      name: self.name.as_ref().map(|name| format!("{name}-{index}")),
+      ..self.clone()
+    }
+  }
+
+  /// Stable hash of every setting that affects the cluster
+  ///
+  /// `resolved` must be the result of `to_embedded_settings()`. Generated values (the
+  /// random password and temporary data directory) are left out so that identical
+  /// configurations produce identical hashes.
+  pub(crate) fn config_hash(&self, resolved: &Settings) -> String {
+    let data_dir = if self.data_dir.is_some() {
+      resolved.data_dir.to_string_lossy().to_string()
+    } else {
+      String::new()
+    };
+    let mut entries = vec![
+      ("version".to_string(), resolved.version.to_string()),
+      ("host".to_string(), resolved.host.clone()),
+      ("port".to_string(), resolved.port.to_string()),
+      ("username".to_string(), resolved.username.clone()),
+      (
+        "password".to_string(),
+        self.password.clone().unwrap_or_default(),
+      ),
+      (
+        "database_name".to_string(),
+        self.database_name.clone().unwrap_or_default(),
+      ),
+      ("data_dir".to_string(), data_dir),
+      (
+        "installation_dir".to_string(),
+        resolved.installation_dir.to_string_lossy().to_string(),
+      ),
+      ("temporary".to_string(), resolved.temporary.to_string()),
+      (
+        "icu_locale".to_string(),
+        self.icu_locale.clone().unwrap_or_default(),
+      ),
+      (
+        "wal_archive_dir".to_string(),
+        self.wal_archive_dir.clone().unwrap_or_default(),
+      ),
+      (
+        "connection_cache_ttl_seconds".to_string(),
+        self.connection_cache_ttl().as_secs().to_string(),
+      ),
+    ];
+    let mut configuration: Vec<_> = resolved.configuration.iter().collect();
+    configuration.sort();
+    for (key, value) in configuration {
+      entries.push((format!("configuration.{key}"), value.clone()));
+    }
+
+    let mut hasher = Sha256::new();
+    for (key, value) in entries {
+      hasher.update(format!("{key}={value}\n"));
+    }
+    hasher.finalize()[..16]
+      .iter()
+      .map(|byte| format!("{byte:02x}"))
+      .collect()
+  }
+
   /// Validate configuration parameters
   pub fn validate(&self) -> napi::Result<()> {
     // Validate port number
@@ -85,6 +348,13 @@ impl PostgresSettings {
       }
     }
 
+    // Validate listen addresses
+    if let Some(ref listen_addresses) = self.listen_addresses {
+      if listen_addresses.trim().is_empty() {
+        return Err(configuration_error("Listen addresses cannot be empty"));
+      }
+    }
+
     // Validate database name
     if let Some(ref database_name) = self.database_name {
       if database_name.is_empty() {
@@ -92,9 +362,212 @@ impl PostgresSettings {
       }
     }
 
+    // Validate server configuration parameter names
+    if let Some(ref server_config) = self.server_config {
+      if server_config.keys().any(|key| key.trim().is_empty()) {
+        return Err(configuration_error(
+          "Server configuration parameter names cannot be empty",
+        ));
+      }
+    }
+
+    // Validate server environment variables
+    if let Some(ref server_env) = self.server_env {
+      validate_server_env(server_env)?;
+    }
+
+    // Validate instance name
+    if let Some(ref name) = self.name {
+      if !is_dir_name_part(name) {
+        return Err(configuration_error(
+          "Instance name must be non-empty and contain only letters, digits, '-', '_' and '.'",
+        ));
+      }
+    }
+
+    // Validate temporary data directory prefix
+    if let Some(ref prefix) = self.temp_dir_prefix {
+      if !is_dir_name_part(prefix) {
+        return Err(configuration_error(
+          "tempDirPrefix must be non-empty and contain only letters, digits, '-', '_' and '.'",
+        ));
+      }
+    }
+
+    // Validate time zone; the value is passed to the server on the pg_ctl command line
+    if let Some(ref timezone) = self.timezone {
+      if timezone.is_empty()
+        || !timezone
+          .chars()
+          .all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '_' | '+' | '-' | ':' | '.'))
+      {
+        return Err(configuration_error(&format!(
+          "Invalid time zone '{timezone}'"
+        )));
+      }
+    }
+
+    // Validate date style
+    if let Some(ref datestyle) = self.datestyle {
+      if datestyle_components(datestyle).is_none() {
+        return Err(configuration_error(&format!(
+          "Invalid date style '{datestyle}', expected e.g. 'ISO, MDY'"
+        )));
+      }
+    }
+
+    // Validate ICU locale
+    if let Some(ref icu_locale) = self.icu_locale {
+      if icu_locale.trim().is_empty() {
+        return Err(configuration_error("ICU locale cannot be empty"));
+      }
+    }
+
+    // Trust authentication on all addresses would let anyone on the network in as superuser
+    if self.is_trust_auth() && self.is_exposed_externally() {
+      return Err(configuration_error(
+        "exposeExternally requires a password; trust authentication would let anyone on the \
+         network connect as superuser",
+      ));
+    }
+
+    // Validate label names
+    if let Some(ref labels) = self.labels {
+      if labels.keys().any(|key| key.trim().is_empty()) {
+        return Err(configuration_error("Label names cannot be empty"));
+      }
+    }
+
+    self.validate_directories()?;
+
     Ok(())
   }
 
+  /// Validate the data and installation directory paths
+  ///
+  /// Both directories must either exist as writable directories or be creatable,
+  /// they must not be nested inside each other, and the data directory must not
+  /// belong to a running cluster of a different major version.
+  fn validate_directories(&self) -> Result<()> {
+    let data_dir = match self.data_dir.as_deref() {
+      Some(dir) => Some(resolve_dir("dataDir", dir)?),
+      None => None,
+    };
+    let installation_dir = match self.installation_dir.as_deref() {
+      Some(dir) => Some(resolve_dir("installationDir", dir)?),
+      None => None,
+    };
+
+    if let Some(ref data_dir) = data_dir {
+      ensure_writable_dir("dataDir", data_dir)?;
+    } else if let Some(ref temp_dir_parent) = self.temp_dir_parent {
+      ensure_writable_dir(
+        "tempDirParent",
+        &resolve_dir("tempDirParent", temp_dir_parent)?,
+      )?;
+    }
+    if let Some(ref installation_dir) = installation_dir {
+      // An existing installation may be read-only; only a missing one has to be creatable
+      if !installation_dir.exists() {
+        ensure_writable_dir("installationDir", installation_dir)?;
+      } else if !installation_dir.is_dir() {
+        return Err(PgEmbedError::ConfigurationError(format!(
+          "installationDir '{}' exists but is not a directory",
+          installation_dir.display()
+        )));
+      }
+    }
+
+    if let (Some(data_dir), Some(installation_dir)) = (&data_dir, &installation_dir) {
+      if data_dir == installation_dir {
+        return Err(PgEmbedError::ConfigurationError(format!(
+          "dataDir and installationDir must be different directories (both are '{}')",
+          data_dir.display()
+        )));
+      }
+      if data_dir.starts_with(installation_dir) {
+        return Err(PgEmbedError::ConfigurationError(format!(
+          "dataDir '{}' must not be located inside installationDir '{}'",
+          data_dir.display(),
+          installation_dir.display()
+        )));
+      }
+      if installation_dir.starts_with(data_dir) {
+        return Err(PgEmbedError::ConfigurationError(format!(
+          "installationDir '{}' must not be located inside dataDir '{}'",
+          installation_dir.display(),
+          data_dir.display()
+        )));
+      }
+    }
+
+    if let Some(ref data_dir) = data_dir {
+      self.ensure_no_foreign_running_cluster(data_dir)?;
+    }
+
+    Ok(())
+  }
+
+  /// Reject a data directory that holds a running cluster of another major version
+  fn ensure_no_foreign_running_cluster(&self, data_dir: &Path) -> Result<()> {
+    if !data_dir.join("postmaster.pid").exists() {
+      return Ok(());
+    }
+    let Some(cluster_major) = read_data_dir_major_version(data_dir) else {
+      return Ok(());
+    };
+    let Some(expected_major) = self.expected_major_version() else {
+      return Ok(());
+    };
+
+    if cluster_major != expected_major {
+      return Err(PgEmbedError::ConfigurationError(format!(
+        "dataDir '{}' contains a running PostgreSQL {} cluster, but PostgreSQL {} is configured",
+        data_dir.display(),
+        cluster_major,
+        expected_major
+      )));
+    }
+    Ok(())
+  }
+
+  /// Major version this configuration will run, if it can be determined up front
+  fn expected_major_version(&self) -> Option<u64> {
+    match self.version.as_deref() {
+      Some(version) => {
+        let version_req = VersionReq::parse(version).ok()?;
+        // Only a requirement pinned to a single major (e.g. "17", "=17.5.0", "^17") is conclusive
+        let mut majors = version_req.comparators.iter().map(|c| c.major);
+        let major = majors.next()?;
+        majors.all(|m| m == major).then_some(major)
+      }
+      None => crate::version::get_postgre_sql_version()
+        .split('.')
+        .next()
+        .and_then(|major| major.parse().ok()),
+    }
+  }
+
+  /// Generated data directory for an instance without `dataDir`, or None to keep the
+  /// anonymous temporary directory of postgresql_embedded
+  fn temp_data_dir(&self) -> Result<Option<PathBuf>> {
+    if self.name.is_none() && self.temp_dir_parent.is_none() && self.temp_dir_prefix.is_none() {
+      return Ok(None);
+    }
+    let parent = match self.temp_dir_parent {
+      Some(ref parent) => resolve_dir("tempDirParent", parent)?,
+      None => std::env::temp_dir(),
+    };
+    Ok(Some(named_temp_dir(
+      &parent,
+      self
+        .temp_dir_prefix
+        .as_deref()
+        .unwrap_or(DEFAULT_TEMP_DIR_PREFIX),
+      self.name.as_deref(),
+    )))
+  }
+
   /// Convert to postgresql_embedded::Settings
   pub fn to_embedded_settings(&self) -> napi::Result<Settings> {
     self.validate()?;
@@ -138,21 +611,26 @@ impl PostgresSettings {
       settings.username = username.clone();
     }
 
-    // Set password
-    if let Some(ref password) = self.password {
-      settings.password = password.clone();
+    // Set password; in trust mode initdb still receives the generated placeholder password
+    if let Some(ref password) = self.password.as_ref().filter(|p| !p.is_empty()) {
+      settings.password = password.to_string();
     }
 
-    // Note: postgresql_embedded doesn't support setting database name directly, uses default "postgres"
+    // Note: database_name is not part of postgresql_embedded settings; PostgresInstance
+    // creates it on start and uses it as the default connection database
 
     // Set data directory
     if let Some(ref data_dir) = self.data_dir {
-      settings.data_dir = PathBuf::from(data_dir);
+      settings.data_dir = native_path(data_dir);
+    } else if let Some(temp_data_dir) = self.temp_data_dir()? {
+      // Replace the anonymous temporary directory with a generated one
+      let _ = fs::remove_dir(&settings.data_dir);
+      settings.data_dir = temp_data_dir;
     }
 
     // Set installation directory
     if let Some(ref installation_dir) = self.installation_dir {
-      settings.installation_dir = PathBuf::from(installation_dir);
+      settings.installation_dir = native_path(installation_dir);
     }
 
     // Note: postgresql_embedded doesn't support setting timeout directly
@@ -162,6 +640,509 @@ impl PostgresSettings {
       settings.temporary = !persistent;
     }
 
+    // Set the reproducible preset first, so explicit parameters override it
+    if self.reproducible.unwrap_or(false) {
+      for (name, value) in REPRODUCIBLE_CONFIG {
+        settings
+          .configuration
+          .insert(name.to_string(), value.to_string());
+      }
+    }
+
+    // Set server configuration parameters
+    if let Some(ref server_config) = self.server_config {
+      settings.configuration.extend(server_config.clone());
+    }
+
+    // Set time zone and date style
+    if let Some(ref timezone) = self.timezone {
+      settings
+        .configuration
+        .insert("timezone".to_string(), timezone.clone());
+    }
+    if let Some(datestyle) = self.datestyle.as_deref().and_then(datestyle_components) {
+      // Without spaces the value survives the shell pg_ctl starts the server through
+      settings
+        .configuration
+        .insert("datestyle".to_string(), datestyle.join(","));
+    }
+    if let Some(max_prepared_transactions) = self.max_prepared_transactions {
+      settings.configuration.insert(
+        "max_prepared_transactions".to_string(),
+        max_prepared_transactions.to_string(),
+      );
+    }
+
+    // Set listen addresses
+    let listen_addresses = if self.is_exposed_externally() {
+      Some("*")
+    } else {
+      self.listen_addresses.as_deref()
+    };
+    if let Some(listen_addresses) = listen_addresses {
+      // pg_ctl hands server options to a shell, where a bare "*" would be glob-expanded
+      let listen_addresses = listen_addresses.replace('*', "0.0.0.0,::");
+      settings
+        .configuration
+        .insert("listen_addresses".to_string(), listen_addresses);
+    }
+
+    // Set password encryption
+    if let Some(password_encryption) = self.password_encryption {
+      settings.configuration.insert(
+        "password_encryption".to_string(),
+        password_encryption.as_str().to_string(),
+      );
+    }
+
     Ok(settings)
   }
 }
+
+/// Compute the configuration hash an instance with these settings would report
+///
+/// The hash is the first 128 bits (hex encoded) of a SHA-256 digest over `key=value` lines
+/// for the version requirement, host, port, username, password, database name, data and
+/// installation directories, temporary flag, ICU locale, WAL archive directory, connection cache TTL and every server
+/// configuration parameter (sorted by name). Generated values such as a random temporary
+/// data directory are excluded, so it is suitable as an external cache key.
+///
+/// @param settings - The instance settings
+/// @returns The configuration hash, identical to `PostgresInstance.getConfigHash()`
+///
+/// @example
+/// ```typescript
+/// const key = computeConfigHash({ version: '16', serverConfig: { fsync: 'off' } });
+/// ```
+#[napi]
+pub fn compute_config_hash(settings: Option<PostgresSettings>) -> napi::Result<String> {
+  let settings = settings.unwrap_or_default();
+  let resolved = settings.to_embedded_settings()?;
+  // No instance is created, so drop the empty directories generated for one
+  if settings.data_dir.is_none() {
+    let _ = fs::remove_dir(&resolved.data_dir);
+  }
+  if let Some(password_dir) = resolved.password_file.parent() {
+    let _ = fs::remove_dir(password_dir);
+  }
+  Ok(settings.config_hash(&resolved))
+}
+
+/// Components of a date style such as "ISO, MDY", or None if it is malformed
+fn datestyle_components(datestyle: &str) -> Option<Vec<&str>> {
+  let components: Vec<&str> = datestyle.split(',').map(str::trim).collect();
+  components
+    .iter()
+    .all(|component| !component.is_empty() && component.chars().all(|c| c.is_ascii_alphabetic()))
+    .then_some(components)
+}
+
+/// Whether `part` may be used in a generated directory name
+fn is_dir_name_part(part: &str) -> bool {
+  !part.is_empty()
+    && part
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Path of a new temporary data directory, `<parent>/<prefix>-[<name>-]<random suffix>`
+fn named_temp_dir(parent: &Path, prefix: &str, name: Option<&str>) -> PathBuf {
+  let ts = uuid::Timestamp::now(uuid::NoContext);
+  let id = uuid::Uuid::new_v7(ts).simple().to_string();
+  // The trailing characters of a UUIDv7 are random, the leading ones are a timestamp
+  let suffix = &id[id.len() - 12..];
+  match name {
+    Some(name) => parent.join(format!("{prefix}-{name}-{suffix}")),
+    None => parent.join(format!("{prefix}-{suffix}")),
+  }
+}
+
+/// Turn a configured directory into an absolute path, rejecting empty values
+fn resolve_dir(field: &str, dir: &str) -> Result<PathBuf> {
+  if dir.trim().is_empty() {
+    return Err(PgEmbedError::ConfigurationError(format!(
+      "{field} cannot be empty"
+    )));
+  }
+  std::path::absolute(dir).map_err(|e| {
+    PgEmbedError::ConfigurationError(format!("{field} '{dir}' is not a valid path: {e}"))
+  })
+}
+
+/// Ensure the directory exists and is writable, or that it can be created
+fn ensure_writable_dir(field: &str, dir: &Path) -> Result<()> {
+  if dir.exists() {
+    if !dir.is_dir() {
+      return Err(PgEmbedError::ConfigurationError(format!(
+        "{field} '{}' exists but is not a directory",
+        dir.display()
+      )));
+    }
+    if !is_writable(dir) {
+      return Err(PgEmbedError::ConfigurationError(format!(
+        "{field} '{}' is not writable",
+        dir.display()
+      )));
+    }
+    return Ok(());
+  }
+
+  // The directory will be created later, so its closest existing ancestor must accept it
+  let ancestor = dir.ancestors().skip(1).find(|p| p.exists());
+  match ancestor {
+    Some(parent) if parent.is_dir() && is_writable(parent) => Ok(()),
+    Some(parent) if !parent.is_dir() => Err(PgEmbedError::ConfigurationError(format!(
+      "{field} '{}' cannot be created because '{}' is not a directory",
+      dir.display(),
+      parent.display()
+    ))),
+    Some(parent) => Err(PgEmbedError::ConfigurationError(format!(
+      "{field} '{}' cannot be created because '{}' is not writable",
+      dir.display(),
+      parent.display()
+    ))),
+    None => Err(PgEmbedError::ConfigurationError(format!(
+      "{field} '{}' cannot be created",
+      dir.display()
+    ))),
+  }
+}
+
+/// Check writability by creating and removing a probe file
+fn is_writable(dir: &Path) -> bool {
+  let probe = dir.join(format!(".pg-embedded-write-test-{}", std::process::id()));
+  match fs::File::create(&probe) {
+    Ok(_) => {
+      let _ = fs::remove_file(&probe);
+      true
+    }
+    Err(_) => false,
+  }
+}
+
+/// Read the major version recorded in a data directory's PG_VERSION file
+pub(crate) fn read_data_dir_major_version(data_dir: &Path) -> Option<u64> {
+  let content = fs::read_to_string(data_dir.join("PG_VERSION")).ok()?;
+  content.trim().split('.').next()?.parse().ok()
+}
+
+/// Rewrite the authentication method of every pg_hba.conf rule to `method`
+///
+/// Only the method column changes; the rest of each line, including auth options after
+/// the method, is kept as it is. Meant for the rules initdb generated in a fresh cluster.
+pub(crate) fn hba_rules_with_method(contents: &str, method: &str) -> String {
+  let mut rules = String::with_capacity(contents.len());
+  for line in contents.lines() {
+    let fields = field_spans(line);
+    // local rules have no address column: TYPE DATABASE USER METHOD
+    let method_index = match fields.first().map(|span| &line[span.clone()]) {
+      Some("local") => 3,
+      Some(kind) if !kind.starts_with('#') => 4,
+      _ => usize::MAX,
+    };
+    match fields.get(method_index) {
+      Some(span) => {
+        rules.push_str(&line[..span.start]);
+        rules.push_str(method);
+        rules.push_str(&line[span.end..]);
+      }
+      None => rules.push_str(line),
+    }
+    rules.push('\n');
+  }
+  rules
+}
+
+/// Byte ranges of the whitespace-separated fields of `line`
+fn field_spans(line: &str) -> Vec<std::ops::Range<usize>> {
+  let mut spans = Vec::new();
+  let mut start = None;
+  for (index, c) in line.char_indices() {
+    match (c.is_whitespace(), start) {
+      (true, Some(field_start)) => {
+        spans.push(field_start..index);
+        start = None;
+      }
+      (false, None) => start = Some(index),
+      _ => {}
+    }
+  }
+  if let Some(field_start) = start {
+    spans.push(field_start..line.len());
+  }
+  spans
+}
+
+/// Append pg_hba.conf rules that let clients on any IPv4 or IPv6 address log in with `method`
+pub(crate) fn hba_with_remote_access(contents: &str, method: &str) -> String {
+  let mut rules = contents.to_string();
+  if !rules.is_empty() && !rules.ends_with('\n') {
+    rules.push('\n');
+  }
+  for address in ["0.0.0.0/0", "::/0"] {
+    let present = contents.lines().any(|line| {
+      let fields: Vec<&str> = line.split_whitespace().collect();
+      fields.len() > 4 && fields[0] == "host" && fields[1] == "all" && fields[3] == address
+    });
+    if !present {
+      rules.push_str(&format!("host\tall\tall\t{address}\t{method}\n"));
+    }
+  }
+  rules
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_hba_rules_with_method() {
+    let hba = "# comment\nlocal   all   all   password\nhost all all 127.0.0.1/32 scram-sha-256\nhost replication all ::1/128 password\n";
+    assert_eq!(
+      hba_rules_with_method(hba, "trust"),
+      "# comment\nlocal   all   all   trust\nhost all all 127.0.0.1/32 trust\nhost replication all ::1/128 trust\n"
+    );
+    assert_eq!(
+      hba_rules_with_method("host all all 127.0.0.1/32 password", "md5"),
+      "host all all 127.0.0.1/32 md5\n"
+    );
+    assert_eq!(
+      hba_rules_with_method(
+        "hostssl\tall\tall\t0.0.0.0/0\tcert\tclientcert=verify-full",
+        "md5"
+      ),
+      "hostssl\tall\tall\t0.0.0.0/0\tmd5\tclientcert=verify-full\n"
+    );
+  }
+
+  #[test]
+  fn test_hba_with_remote_access() {
+    let hba = "local all all password\n";
+    let exposed = hba_with_remote_access(hba, "password");
+    assert_eq!(
+      exposed,
+      "local all all password\nhost\tall\tall\t0.0.0.0/0\tpassword\nhost\tall\tall\t::/0\tpassword\n"
+    );
+    assert_eq!(hba_with_remote_access(&exposed, "password"), exposed);
+  }
+
+  #[test]
+  fn test_datestyle_components() {
+    assert_eq!(datestyle_components("ISO, MDY"), Some(vec!["ISO", "MDY"]));
+    assert_eq!(datestyle_components("German"), Some(vec!["German"]));
+    assert_eq!(datestyle_components("ISO,"), None);
+    assert_eq!(datestyle_components("ISO; DROP"), None);
+  }
+
+  #[test]
+  fn test_named_temp_dir_is_unique() {
+    let temp_dir = std::env::temp_dir();
+    let first = named_temp_dir(&temp_dir, DEFAULT_TEMP_DIR_PREFIX, Some("orders-db"));
+    let second = named_temp_dir(&temp_dir, DEFAULT_TEMP_DIR_PREFIX, Some("orders-db"));
+    assert!(first
+      .file_name()
+      .unwrap()
+      .to_string_lossy()
+      .starts_with("pg-embedded-orders-db-"));
+    assert_ne!(first, second);
+
+    let cache = Path::new("/work/app/.cache");
+    let anonymous = named_temp_dir(cache, "app-test", None);
+    assert_eq!(anonymous.parent(), Some(cache));
+    let file_name = anonymous.file_name().unwrap().to_string_lossy().to_string();
+    assert!(file_name.starts_with("app-test-"));
+    assert_eq!(file_name.len(), "app-test-".len() + 12);
+  }
+
+  #[test]
+  fn test_temp_data_dir() {
+    assert_eq!(PostgresSettings::default().temp_data_dir().unwrap(), None);
+
+    let cache = std::env::temp_dir().join("app-cache");
+    let settings = PostgresSettings {
+      temp_dir_parent: Some(cache.to_string_lossy().to_string()),
+      temp_dir_prefix: Some("app".to_string()),
+      ..Default::default()
+    };
+    let data_dir = settings.temp_data_dir().unwrap().unwrap();
+    assert_eq!(data_dir.parent(), Some(cache.as_path()));
+    assert!(data_dir
+      .file_name()
+      .unwrap()
+      .to_string_lossy()
+      .starts_with("app-"));
+
+    assert!(is_dir_name_part("app-test_1.0"));
+    assert!(!is_dir_name_part("app/test"));
+    assert!(!is_dir_name_part(""));
+  }
+
+  #[test]
+  fn test_config_hash_covers_cluster_settings() {
+    let settings = PostgresSettings::default();
+    let resolved = |data_dir: &str| Settings {
+      data_dir: PathBuf::from(data_dir),
+      ..Settings::default()
+    };
+    // Generated temporary data directories do not change the hash
+    let hash = settings.config_hash(&resolved("/tmp/a"));
+    assert_eq!(hash, settings.config_hash(&resolved("/tmp/b")));
+    assert_eq!(hash.len(), 32);
+
+    let with_data_dir = PostgresSettings {
+      data_dir: Some("/srv/pg".to_string()),
+      ..settings.clone()
+    };
+    assert_ne!(hash, with_data_dir.config_hash(&resolved("/srv/pg")));
+
+    let mut with_config = resolved("/tmp/a");
+    with_config
+      .configuration
+      .insert("fsync".to_string(), "off".to_string());
+    assert_ne!(hash, settings.config_hash(&with_config));
+  }
+
+  #[test]
+  fn test_for_group_member() {
+    let template = PostgresSettings {
+      name: Some("shard".to_string()),
+      port: Some(6000),
+      data_dir: Some("/srv/pg/".to_string()),
+      installation_dir: Some("/opt/pg".to_string()),
+      ..Default::default()
+    };
+    let member = template.for_group_member(2);
+    assert_eq!(member.name.as_deref(), Some("shard-2"));
+    assert_eq!(member.port, Some(6002));
+    assert_eq!(member.data_dir.as_deref(), Some("/srv/pg-2"));
+    assert_eq!(member.installation_dir.as_deref(), Some("/opt/pg"));
+
+    let random = PostgresSettings {
+      port: Some(0),
+      ..Default::default()
+    };
+    let member = random.for_group_member(1);
+    assert_eq!(member.port, Some(0));
+    assert_eq!(member.name, None);
+    assert_eq!(member.data_dir, None);
+    let unset = PostgresSettings {
+      port: None,
+      ..Default::default()
+    };
+    assert_eq!(unset.for_group_member(1).port, Some(0));
+  }
+
+  #[test]
+  fn test_reproducible_preset() {
+    let settings = PostgresSettings {
+      reproducible: Some(true),
+      timezone: Some("Europe/Amsterdam".to_string()),
+      server_config: Some(HashMap::from([(
+        "lc_messages".to_string(),
+        "en_US.UTF-8".to_string(),
+      )])),
+      data_dir: Some("/srv/pg".to_string()),
+      ..Default::default()
+    };
+    let configuration = settings.to_embedded_settings().unwrap().configuration;
+    assert_eq!(configuration["datestyle"], "ISO,MDY");
+    assert_eq!(configuration["lc_numeric"], "C");
+    assert_eq!(configuration["timezone"], "Europe/Amsterdam");
+    assert_eq!(configuration["lc_messages"], "en_US.UTF-8");
+
+    let plain = PostgresSettings {
+      data_dir: Some("/srv/pg".to_string()),
+      ..Default::default()
+    };
+    let configuration = plain.to_embedded_settings().unwrap().configuration;
+    assert!(!configuration.contains_key("lc_numeric"));
+  }
+
+  fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+      "pg-embedded-settings-{name}-{}",
+      std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+  }
+
+  fn settings_with_dirs(data_dir: &Path, installation_dir: Option<&Path>) -> PostgresSettings {
+    PostgresSettings {
+      data_dir: Some(data_dir.to_string_lossy().to_string()),
+      installation_dir: installation_dir.map(|p| p.to_string_lossy().to_string()),
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn test_validate_rejects_exposed_trust_auth() {
+    let settings = PostgresSettings {
+      password: Some(String::new()),
+      expose_externally: Some(true),
+      ..Default::default()
+    };
+    let error = settings.validate().unwrap_err();
+    assert!(error
+      .reason
+      .contains("exposeExternally requires a password"));
+    let with_password = PostgresSettings {
+      expose_externally: Some(true),
+      ..Default::default()
+    };
+    assert!(with_password.validate().is_ok());
+  }
+
+  #[test]
+  fn test_validate_accepts_creatable_dirs() {
+    let root = scratch_dir("creatable");
+    let settings = settings_with_dirs(&root.join("data"), Some(&root.join("install")));
+    assert!(settings.validate_directories().is_ok());
+    fs::remove_dir_all(&root).unwrap();
+  }
+
+  #[test]
+  fn test_validate_rejects_file_as_data_dir() {
+    let root = scratch_dir("file");
+    let file = root.join("not-a-dir");
+    fs::write(&file, "x").unwrap();
+    let error = settings_with_dirs(&file, None)
+      .validate_directories()
+      .unwrap_err();
+    assert!(error.to_string().contains("not a directory"));
+    fs::remove_dir_all(&root).unwrap();
+  }
+
+  #[test]
+  fn test_validate_rejects_nested_dirs() {
+    let root = scratch_dir("nested");
+    let install = root.join("install");
+    let error = settings_with_dirs(&install.join("data"), Some(&install))
+      .validate_directories()
+      .unwrap_err();
+    assert!(error.to_string().contains("inside installationDir"));
+
+    let error = settings_with_dirs(&root, Some(&root))
+      .validate_directories()
+      .unwrap_err();
+    assert!(error.to_string().contains("must be different"));
+    fs::remove_dir_all(&root).unwrap();
+  }
+
+  #[test]
+  fn test_validate_rejects_running_cluster_of_other_version() {
+    let root = scratch_dir("running");
+    fs::write(root.join("PG_VERSION"), "15\n").unwrap();
+    fs::write(root.join("postmaster.pid"), "1\n").unwrap();
+
+    let mut settings = settings_with_dirs(&root, None);
+    settings.version = Some("=16.4.0".to_string());
+    let error = settings.validate_directories().unwrap_err();
+    assert!(error.to_string().contains("running PostgreSQL 15 cluster"));
+
+    settings.version = Some("15".to_string());
+    assert!(settings.validate_directories().is_ok());
+    fs::remove_dir_all(&root).unwrap();
+  }
+}
@@ -0,0 +1,249 @@
+//! Hash-based routing over separate servers for testing application-level sharding
+
+use crate::client::{self, QueryResult};
+use crate::conninfo::parse_conninfo;
+use crate::error::{PgEmbedError, Result};
+use crate::script::{split_script, ExecuteSqlBatchOptions};
+use crate::sql::{quote_ident, quote_literal};
+use crate::tools::common::ConnectionConfig;
+use napi_derive::napi;
+use sha2::{Digest, Sha256};
+use sqlx::Connection;
+use std::collections::{BTreeSet, HashMap};
+
+/// Options for `new ShardSet()`
+#[napi(object)]
+pub struct ShardSetOptions {
+  /// Connection strings of the shards (URIs or `key=value` strings), in shard order
+  pub shards: Vec<String>,
+}
+
+/// Shard a key belongs to among `count` shards
+///
+/// The first eight bytes of the key's SHA-256 digest modulo the shard count, so the
+/// mapping is the same on every platform and in every run.
+fn shard_index(key: &str, count: usize) -> usize {
+  let digest = Sha256::digest(key.as_bytes());
+  let mut prefix = [0u8; 8];
+  prefix.copy_from_slice(&digest[..8]);
+  (u64::from_be_bytes(prefix) % count as u64) as usize
+}
+
+/// Multi-row INSERT of `rows` into `table`, with NULL for columns a row does not set
+fn insert_sql(
+  table: &str,
+  columns: &[String],
+  rows: &[&HashMap<String, Option<String>>],
+) -> String {
+  let values = rows
+    .iter()
+    .map(|row| {
+      let values = columns
+        .iter()
+        .map(|column| match row.get(column) {
+          Some(Some(value)) => quote_literal(value),
+          _ => "NULL".to_string(),
+        })
+        .collect::<Vec<_>>();
+      format!("({})", values.join(", "))
+    })
+    .collect::<Vec<_>>();
+  format!(
+    "INSERT INTO {table} ({}) VALUES {}",
+    columns
+      .iter()
+      .map(|column| quote_ident(column))
+      .collect::<Vec<_>>()
+      .join(", "),
+    values.join(", ")
+  )
+}
+
+/// A set of shards on separate servers, with rows routed by a hash of their shard key
+///
+/// Pairs with `PostgresInstance.createMany()` to test application-level sharding against
+/// real, independent servers.
+///
+/// @example
+/// ```typescript
+/// const instances = await PostgresInstance.createMany(3, { port: 0 });
+/// const shards = new ShardSet({
+///   shards: instances.map((instance) => instance.connectionInfo.connectionString),
+/// });
+/// await shards.applySchema('CREATE TABLE users (id text PRIMARY KEY, name text)');
+/// await shards.insertRows('users', 'id', [{ id: 'u1', name: 'Ada' }, { id: 'u2', name: 'Bob' }]);
+/// const user = await shards.executeForKey('u1', "SELECT name FROM users WHERE id = 'u1'");
+/// ```
+#[napi]
+pub struct ShardSet {
+  uris: Vec<String>,
+  configs: Vec<ConnectionConfig>,
+}
+
+#[napi]
+impl ShardSet {
+  /// Creates a shard set over the given servers
+  ///
+  /// @param options - Connection strings of the shards
+  /// @throws Error if no shard is given or a connection string is invalid
+  #[napi(constructor)]
+  pub fn new(options: ShardSetOptions) -> napi::Result<Self> {
+    if options.shards.is_empty() {
+      return Err(
+        PgEmbedError::ConfigurationError("A shard set needs at least one shard".to_string()).into(),
+      );
+    }
+    let configs = options
+      .shards
+      .iter()
+      .map(|uri| parse_conninfo(uri))
+      .collect::<Result<Vec<_>>>()?;
+    Ok(Self {
+      uris: options.shards,
+      configs,
+    })
+  }
+
+  /// Number of shards in the set
+  #[napi(getter)]
+  pub fn get_shard_count(&self) -> u32 {
+    self.configs.len() as u32
+  }
+
+  /// Index of the shard owning a key
+  ///
+  /// Keys are hashed with SHA-256, so the same key maps to the same shard in every run.
+  ///
+  /// @param key - Shard key, e.g. a customer ID
+  /// @returns The zero-based shard index
+  #[napi]
+  pub fn shard_for(&self, key: String) -> u32 {
+    shard_index(&key, self.configs.len()) as u32
+  }
+
+  /// Connection string of the shard owning a key
+  ///
+  /// @param key - Shard key
+  /// @returns The connection string of the shard as passed to the constructor
+  #[napi]
+  pub fn connection_string_for(&self, key: String) -> String {
+    self.uris[shard_index(&key, self.uris.len())].clone()
+  }
+
+  /// Runs a schema script on every shard, in shard order
+  ///
+  /// The script is split like `executeSqlBatch()` and stops at the first failing
+  /// statement.
+  ///
+  /// @param sql - Script creating the shared schema
+  /// @returns Promise that resolves when every shard has the schema
+  /// @throws Error naming the shard if the script fails on one
+  #[napi]
+  pub async fn apply_schema(&self, sql: String) -> napi::Result<()> {
+    let statements = split_script(&sql);
+    let options = ExecuteSqlBatchOptions::default();
+    for (index, config) in self.configs.iter().enumerate() {
+      client::run_script(config, &statements, &options, None)
+        .await
+        .map_err(|e| {
+          PgEmbedError::DatabaseError(format!("Failed to apply the schema to shard {index}: {e}"))
+        })?;
+    }
+    Ok(())
+  }
+
+  /// Runs SQL on the shard owning a key
+  ///
+  /// @param key - Shard key selecting the shard
+  /// @param sql - SQL to run
+  /// @returns Promise that resolves to the result of the last statement
+  /// @throws Error if connecting or the query fails
+  #[napi]
+  pub async fn execute_for_key(&self, key: String, sql: String) -> napi::Result<QueryResult> {
+    let config = &self.configs[shard_index(&key, self.configs.len())];
+    let mut connection = client::connect(config).await?;
+    let result = client::run_query(&mut connection, &sql).await;
+    let _ = connection.close().await;
+    Ok(result?)
+  }
+
+  /// Inserts rows into a table, sending each row to the shard owning its key
+  ///
+  /// Every shard receives one multi-row INSERT. Columns are the union of the rows' keys;
+  /// a row that does not set a column inserts NULL.
+  ///
+  /// @param table - Table to insert into, used as given so it may be schema-qualified
+  /// @param keyColumn - Column whose value is the shard key
+  /// @param rows - Rows as objects mapping column names to text values
+  /// @returns Promise that resolves to the number of rows inserted into each shard
+  /// @throws Error if a row has no value for the key column or an INSERT fails
+  #[napi(
+    ts_args_type = "table: string, keyColumn: string, rows: Array<Record<string, string | null>>"
+  )]
+  pub async fn insert_rows(
+    &self,
+    table: String,
+    key_column: String,
+    rows: Vec<HashMap<String, Option<String>>>,
+  ) -> napi::Result<Vec<u32>> {
+    let mut routed = vec![Vec::new(); self.configs.len()];
+    for row in &rows {
+      let key = row.get(&key_column).cloned().flatten().ok_or_else(|| {
+        PgEmbedError::ConfigurationError(format!("Row has no value for key column '{key_column}'"))
+      })?;
+      routed[shard_index(&key, self.configs.len())].push(row);
+    }
+    let columns = rows
+      .iter()
+      .flat_map(|row| row.keys().cloned())
+      .collect::<BTreeSet<_>>()
+      .into_iter()
+      .collect::<Vec<_>>();
+
+    let mut counts = Vec::with_capacity(routed.len());
+    for (index, shard_rows) in routed.iter().enumerate() {
+      if shard_rows.is_empty() {
+        counts.push(0);
+        continue;
+      }
+      let mut connection = client::connect(&self.configs[index]).await?;
+      let result =
+        client::run_query(&mut connection, &insert_sql(&table, &columns, shard_rows)).await;
+      let _ = connection.close().await;
+      let result = result.map_err(|e| {
+        PgEmbedError::DatabaseError(format!("Failed to insert into shard {index}: {e}"))
+      })?;
+      counts.push(result.row_count);
+    }
+    Ok(counts)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_shard_index_is_stable_and_spread() {
+    assert_eq!(shard_index("customer-42", 4), shard_index("customer-42", 4));
+    assert_eq!(shard_index("anything", 1), 0);
+    let used = (0..100)
+      .map(|i| shard_index(&format!("key-{i}"), 4))
+      .collect::<BTreeSet<_>>();
+    assert_eq!(used.len(), 4);
+  }
+
+  #[test]
+  fn test_insert_sql() {
+    let first = HashMap::from([
+      ("id".to_string(), Some("u1".to_string())),
+      ("name".to_string(), Some("O'Brien".to_string())),
+    ]);
+    let second = HashMap::from([("id".to_string(), Some("u2".to_string()))]);
+    let columns = vec!["id".to_string(), "name".to_string()];
+    assert_eq!(
+      insert_sql("users", &columns, &[&first, &second]),
+      "INSERT INTO users (\"id\", \"name\") VALUES ('u1', 'O''Brien'), ('u2', NULL)"
+    );
+  }
+}
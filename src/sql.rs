@@ -0,0 +1,49 @@
+//! Helpers for building SQL text that is sent to the server
+
+/// Quote an identifier (database, role, extension, ...) for safe use in SQL
+pub(crate) fn quote_ident(ident: &str) -> String {
+  format!("\"{}\"", ident.replace('"', "\"\""))
+}
+
+/// Quote a string literal for safe use in SQL
+pub(crate) fn quote_literal(value: &str) -> String {
+  if value.contains('\\') {
+    // E'' syntax keeps backslashes literal regardless of standard_conforming_strings
+    format!("E'{}'", value.replace('\\', "\\\\").replace('\'', "''"))
+  } else {
+    format!("'{}'", value.replace('\'', "''"))
+  }
+}
+
+/// Quote an argument of a psql backslash command (e.g. a file name for `\lo_import`)
+pub(crate) fn quote_psql_arg(value: &str) -> String {
+  // psql processes backslash escapes inside single-quoted arguments
+  format!("'{}'", value.replace('\\', "\\\\").replace('\'', "''"))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_quote_ident() {
+    assert_eq!(quote_ident("users"), "\"users\"");
+    assert_eq!(quote_ident("my \"db\""), "\"my \"\"db\"\"\"");
+  }
+
+  #[test]
+  fn test_quote_literal() {
+    assert_eq!(quote_literal("app"), "'app'");
+    assert_eq!(quote_literal("o'brien"), "'o''brien'");
+    assert_eq!(quote_literal("c:\\data"), "E'c:\\\\data'");
+  }
+
+  #[test]
+  fn test_quote_psql_arg() {
+    assert_eq!(quote_psql_arg("/tmp/a.bin"), "'/tmp/a.bin'");
+    assert_eq!(
+      quote_psql_arg("C:\\Temp\\it's.bin"),
+      "'C:\\\\Temp\\\\it''s.bin'"
+    );
+  }
+}
@@ -0,0 +1,176 @@
+//! WAL and checkpoint activity statistics with the same shape on every server version
+
+use crate::error::{PgEmbedError, Result};
+use napi_derive::napi;
+
+/// First server version with pg_stat_wal
+const PG_STAT_WAL_VERSION: u32 = 140000;
+/// First server version with pg_stat_checkpointer
+const PG_STAT_CHECKPOINTER_VERSION: u32 = 170000;
+/// First server version whose pg_stat_wal no longer reports write and sync activity
+const WAL_IO_MOVED_VERSION: u32 = 180000;
+
+/// WAL generation statistics from pg_stat_wal
+#[napi(object)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WalStats {
+  /// Number of WAL records generated
+  pub records: i64,
+  /// Number of full page images generated
+  pub full_page_images: i64,
+  /// Amount of WAL generated in bytes
+  pub bytes: i64,
+  /// Number of times WAL was written because the WAL buffers were full
+  pub buffers_full: i64,
+  /// Number of WAL buffer writes to disk (null on PostgreSQL 18 and later, see pg_stat_io)
+  pub writes: Option<i64>,
+  /// Number of WAL file syncs to disk (null on PostgreSQL 18 and later)
+  pub syncs: Option<i64>,
+  /// Time spent writing WAL in milliseconds, 0 unless track_wal_io_timing is on
+  /// (null on PostgreSQL 18 and later)
+  pub write_time_ms: Option<f64>,
+  /// Time spent syncing WAL in milliseconds, 0 unless track_wal_io_timing is on
+  /// (null on PostgreSQL 18 and later)
+  pub sync_time_ms: Option<f64>,
+  /// Time the statistics were last reset, in milliseconds since the Unix epoch
+  pub stats_reset: Option<f64>,
+}
+
+/// Checkpoint statistics from pg_stat_checkpointer, or pg_stat_bgwriter before PostgreSQL 17
+#[napi(object)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CheckpointStats {
+  /// Number of scheduled checkpoints triggered by checkpoint_timeout
+  pub timed: i64,
+  /// Number of requested checkpoints (CHECKPOINT, max_wal_size, ...)
+  pub requested: i64,
+  /// Time spent writing checkpoint files in milliseconds
+  pub write_time_ms: f64,
+  /// Time spent syncing checkpoint files in milliseconds
+  pub sync_time_ms: f64,
+  /// Number of buffers written during checkpoints
+  pub buffers_written: i64,
+  /// Number of scheduled restartpoints on a standby (null before PostgreSQL 17)
+  pub restartpoints_timed: Option<i64>,
+  /// Number of requested restartpoints on a standby (null before PostgreSQL 17)
+  pub restartpoints_requested: Option<i64>,
+  /// Number of restartpoints performed on a standby (null before PostgreSQL 17)
+  pub restartpoints_done: Option<i64>,
+  /// Time the statistics were last reset, in milliseconds since the Unix epoch
+  pub stats_reset: Option<f64>,
+}
+
+/// Query returning the columns of `WalStats` in field order for the server version
+pub(crate) fn wal_stats_sql(server_version: u32) -> Result<String> {
+  if server_version < PG_STAT_WAL_VERSION {
+    return Err(PgEmbedError::DatabaseError(
+      "WAL statistics require PostgreSQL 14 or later".to_string(),
+    ));
+  }
+  let io = if server_version < WAL_IO_MOVED_VERSION {
+    "wal_write, wal_sync, wal_write_time, wal_sync_time"
+  } else {
+    "NULL, NULL, NULL, NULL"
+  };
+  Ok(format!(
+    "SELECT wal_records, wal_fpi, wal_bytes, wal_buffers_full, {io}, \
+     extract(epoch FROM stats_reset) * 1000 FROM pg_stat_wal"
+  ))
+}
+
+/// Query returning the columns of `CheckpointStats` in field order for the server version
+pub(crate) fn checkpoint_stats_sql(server_version: u32) -> String {
+  if server_version < PG_STAT_CHECKPOINTER_VERSION {
+    "SELECT checkpoints_timed, checkpoints_req, checkpoint_write_time, checkpoint_sync_time, \
+     buffers_checkpoint, NULL, NULL, NULL, extract(epoch FROM stats_reset) * 1000 \
+     FROM pg_stat_bgwriter"
+      .to_string()
+  } else {
+    "SELECT num_timed, num_requested, write_time, sync_time, buffers_written, \
+     restartpoints_timed, restartpoints_req, restartpoints_done, \
+     extract(epoch FROM stats_reset) * 1000 FROM pg_stat_checkpointer"
+      .to_string()
+  }
+}
+
+/// Parse the row of `wal_stats_sql()`
+pub(crate) fn parse_wal_stats(row: &[String]) -> Option<WalStats> {
+  let [records, fpi, bytes, buffers_full, writes, syncs, write_time, sync_time, reset] = row else {
+    return None;
+  };
+  Some(WalStats {
+    records: records.parse().ok()?,
+    full_page_images: fpi.parse().ok()?,
+    bytes: bytes.parse().ok()?,
+    buffers_full: buffers_full.parse().ok()?,
+    writes: writes.parse().ok(),
+    syncs: syncs.parse().ok(),
+    write_time_ms: write_time.parse().ok(),
+    sync_time_ms: sync_time.parse().ok(),
+    stats_reset: reset.parse().ok(),
+  })
+}
+
+/// Parse the row of `checkpoint_stats_sql()`
+pub(crate) fn parse_checkpoint_stats(row: &[String]) -> Option<CheckpointStats> {
+  let [timed, requested, write_time, sync_time, buffers, rp_timed, rp_requested, rp_done, reset] =
+    row
+  else {
+    return None;
+  };
+  Some(CheckpointStats {
+    timed: timed.parse().ok()?,
+    requested: requested.parse().ok()?,
+    write_time_ms: write_time.parse().ok()?,
+    sync_time_ms: sync_time.parse().ok()?,
+    buffers_written: buffers.parse().ok()?,
+    restartpoints_timed: rp_timed.parse().ok(),
+    restartpoints_requested: rp_requested.parse().ok(),
+    restartpoints_done: rp_done.parse().ok(),
+    stats_reset: reset.parse().ok(),
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn row(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+  }
+
+  #[test]
+  fn test_stats_sql_depends_on_version() {
+    assert!(wal_stats_sql(130000).is_err());
+    assert!(wal_stats_sql(160004).unwrap().contains("wal_write_time"));
+    assert!(!wal_stats_sql(180000).unwrap().contains("wal_write"));
+    assert!(checkpoint_stats_sql(160004).contains("pg_stat_bgwriter"));
+    assert!(checkpoint_stats_sql(170000).contains("pg_stat_checkpointer"));
+  }
+
+  #[test]
+  fn test_parse_stats_rows() {
+    let wal = parse_wal_stats(&row(&[
+      "120",
+      "4",
+      "8192",
+      "0",
+      "",
+      "",
+      "",
+      "",
+      "1700000000000",
+    ]))
+    .unwrap();
+    assert_eq!(wal.bytes, 8192);
+    assert_eq!(wal.writes, None);
+    assert_eq!(wal.stats_reset, Some(1700000000000.0));
+
+    let checkpoint =
+      parse_checkpoint_stats(&row(&["3", "1", "12.5", "0.5", "42", "", "", "", ""])).unwrap();
+    assert_eq!(checkpoint.requested, 1);
+    assert_eq!(checkpoint.write_time_ms, 12.5);
+    assert_eq!(checkpoint.restartpoints_done, None);
+    assert!(parse_checkpoint_stats(&row(&["3"])).is_none());
+  }
+}
@@ -0,0 +1,186 @@
+//! Synthetic data generation for seeding performance and pagination tests
+
+use crate::error::{PgEmbedError, Result};
+use crate::sql::{quote_ident, quote_literal};
+use napi_derive::napi;
+
+/// Words random text values are built from
+const LOREM: &str = "'{lorem,ipsum,dolor,sit,amet,consectetur,adipiscing,elit,sed,do,\
+                     eiusmod,tempor,incididunt,ut,labore,et,dolore,magna,aliqua,enim}'::text[]";
+const LOREM_WORDS: usize = 20;
+const TEXT_WORDS: usize = 4;
+
+/// Options for `generateTestData()`
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct GenerateTestDataOptions {
+  /// Database containing the table (defaults to the configured databaseName)
+  pub database_name: Option<String>,
+  /// Seed for `random()` between -1 and 1, making the generated values repeatable.
+  /// UUIDs are always random.
+  pub seed: Option<f64>,
+}
+
+/// Column of the target table as read from pg_attribute
+pub(crate) struct ColumnInfo {
+  pub name: String,
+  /// Type name, e.g. `int4` (for domains, the name of the base type)
+  pub type_name: String,
+  /// Type as written in SQL, including modifiers, e.g. `character varying(20)`
+  pub formatted_type: String,
+  pub not_null: bool,
+  /// Column is filled by the server (default, identity or generated column)
+  pub has_default: bool,
+  /// Column is part of a unique index
+  pub unique: bool,
+}
+
+/// Query listing the columns of `table` as rows matching `ColumnInfo`, prefixed with the
+/// quoted table name
+pub(crate) fn columns_sql(table: &str) -> String {
+  format!(
+    "SELECT a.attrelid::regclass::text, a.attname, \
+     CASE WHEN t.typtype = 'd' THEN format_type(t.typbasetype, NULL) ELSE t.typname END, \
+     format_type(a.atttypid, a.atttypmod), a.attnotnull, \
+     a.atthasdef OR a.attidentity <> '' OR a.attgenerated <> '', \
+     EXISTS (SELECT 1 FROM pg_index i WHERE i.indrelid = a.attrelid AND i.indisunique \
+     AND a.attnum = ANY (i.indkey)) \
+     FROM pg_attribute a JOIN pg_type t ON t.oid = a.atttypid \
+     WHERE a.attrelid = {}::regclass AND a.attnum > 0 AND NOT a.attisdropped \
+     ORDER BY a.attnum",
+    quote_literal(table)
+  )
+}
+
+/// Parse a row of `columns_sql()` into the quoted table name and the column
+pub(crate) fn parse_column(row: &[String]) -> Option<(String, ColumnInfo)> {
+  let [table, name, type_name, formatted_type, not_null, has_default, unique] = row else {
+    return None;
+  };
+  Some((
+    table.clone(),
+    ColumnInfo {
+      name: name.clone(),
+      type_name: type_name.clone(),
+      formatted_type: formatted_type.clone(),
+      not_null: not_null == "t",
+      has_default: has_default == "t",
+      unique: unique == "t",
+    },
+  ))
+}
+
+/// Build the statement inserting `rows` generated rows into `table`
+///
+/// Columns filled by the server are skipped, as are nullable columns of types no values
+/// can be generated for.
+pub(crate) fn insert_sql(table: &str, columns: &[ColumnInfo], rows: u32) -> Result<String> {
+  let mut names = Vec::new();
+  let mut values = Vec::new();
+  for column in columns.iter().filter(|column| !column.has_default) {
+    match column_expression(table, column) {
+      Some(expression) => {
+        names.push(quote_ident(&column.name));
+        values.push(format!("({expression})::{}", column.formatted_type));
+      }
+      None if column.not_null => {
+        return Err(PgEmbedError::ConfigurationError(format!(
+          "Cannot generate values for column '{}' of type {}",
+          column.name, column.formatted_type
+        )));
+      }
+      None => {}
+    }
+  }
+
+  if names.is_empty() {
+    return Ok(format!(
+      "INSERT INTO {table} SELECT FROM generate_series(1, {rows}) AS g"
+    ));
+  }
+  Ok(format!(
+    "INSERT INTO {table} ({}) SELECT {} FROM generate_series(1, {rows}) AS g",
+    names.join(", "),
+    values.join(", ")
+  ))
+}
+
+/// SQL expression generating a value for `column`, evaluated once per row of `g`
+fn column_expression(table: &str, column: &ColumnInfo) -> Option<String> {
+  let name = quote_ident(&column.name);
+  let expression = match column.type_name.as_str() {
+    // Continue after the largest existing value so repeated calls stay unique
+    "int2" | "int4" | "int8" | "smallint" | "integer" | "bigint" if column.unique => {
+      format!("(SELECT coalesce(max({name}), 0) FROM {table}) + g")
+    }
+    "int2" | "smallint" => "floor(random() * 32767)".to_string(),
+    "int4" | "int8" | "integer" | "bigint" => "floor(random() * 1000000)".to_string(),
+    "numeric" | "float4" | "float8" | "real" | "double precision" => {
+      "round((random() * 100)::numeric, 2)".to_string()
+    }
+    "bool" | "boolean" => "random() < 0.5".to_string(),
+    "uuid" => "gen_random_uuid()".to_string(),
+    "date" => "current_date - floor(random() * 365)::int".to_string(),
+    "timestamp" | "timestamptz" | "timestamp without time zone" | "timestamp with time zone" => {
+      "now() - random() * interval '365 days'".to_string()
+    }
+    "time" | "timetz" | "time without time zone" | "time with time zone" => {
+      "time '00:00' + random() * interval '24 hours'".to_string()
+    }
+    "text" | "varchar" | "bpchar" | "name" | "character varying" | "character" if column.unique => {
+      "gen_random_uuid()::text".to_string()
+    }
+    "text" | "varchar" | "bpchar" | "name" | "character varying" | "character" => {
+      let word = format!("({LOREM})[1 + floor(random() * {LOREM_WORDS})::int]");
+      vec![word; TEXT_WORDS].join(" || ' ' || ")
+    }
+    "json" | "jsonb" => "json_build_object('id', g, 'value', round(random() * 1000))".to_string(),
+    "bytea" => "decode(md5(random()::text), 'hex')".to_string(),
+    _ => return None,
+  };
+  Some(expression)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn column(name: &str, type_name: &str, unique: bool) -> ColumnInfo {
+    ColumnInfo {
+      name: name.to_string(),
+      type_name: type_name.to_string(),
+      formatted_type: type_name.to_string(),
+      not_null: true,
+      has_default: false,
+      unique,
+    }
+  }
+
+  #[test]
+  fn test_insert_sql() {
+    let mut serial = column("id", "int4", true);
+    serial.has_default = true;
+    let columns = [
+      serial,
+      column("code", "int4", true),
+      column("flag", "bool", false),
+    ];
+    assert_eq!(
+      insert_sql("items", &columns, 10).unwrap(),
+      "INSERT INTO items (\"code\", \"flag\") SELECT \
+       ((SELECT coalesce(max(\"code\"), 0) FROM items) + g)::int4, (random() < 0.5)::bool \
+       FROM generate_series(1, 10) AS g"
+    );
+  }
+
+  #[test]
+  fn test_insert_sql_unsupported_types() {
+    let mut nullable = column("shape", "polygon", false);
+    nullable.not_null = false;
+    assert_eq!(
+      insert_sql("items", &[nullable], 5).unwrap(),
+      "INSERT INTO items SELECT FROM generate_series(1, 5) AS g"
+    );
+    assert!(insert_sql("items", &[column("shape", "polygon", false)], 5).is_err());
+  }
+}
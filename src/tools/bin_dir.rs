@@ -0,0 +1,90 @@
+//! The directory holding the executables of a PostgreSQL installation
+
+use crate::error::Result;
+use crate::paths::native_path;
+use crate::tools::common::check_program;
+use std::path::{Path, PathBuf};
+
+/// Directory of the PostgreSQL executables, `<installation dir>/bin`
+///
+/// An instance resolves it once from its installation directory and hands it to every
+/// tool it runs, so executable paths are joined component by component instead of being
+/// formatted into strings.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct BinDir(PathBuf);
+
+impl BinDir {
+  pub fn from_installation_dir(installation_dir: &Path) -> Self {
+    Self(native_path(installation_dir.join("bin")))
+  }
+
+  /// The directory as the `programDir` of the tool options
+  pub fn program_dir(&self) -> String {
+    self.0.to_string_lossy().to_string()
+  }
+
+  /// Path of the executable `tool`, with the platform's executable suffix
+  pub fn executable_path(&self, tool: &str) -> PathBuf {
+    self
+      .0
+      .join(format!("{tool}{}", std::env::consts::EXE_SUFFIX))
+  }
+
+  /// Path of the executable `tool`, failing if the installation does not contain it or
+  /// it cannot be executed
+  pub fn executable(&self, tool: &str) -> Result<PathBuf> {
+    let path = self.executable_path(tool);
+    check_program(&path)?;
+    Ok(path)
+  }
+
+  /// The `programDir` for running `tools`, failing if any of them is missing
+  pub fn program_dir_for(&self, tools: &[&str]) -> Result<String> {
+    for tool in tools {
+      self.executable(tool)?;
+    }
+    Ok(self.program_dir())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+
+  #[test]
+  fn test_bin_dir() {
+    let installation_dir =
+      std::env::temp_dir().join(format!("pg-embedded-bin-{}", uuid::Uuid::now_v7()));
+    let bin_dir = BinDir::from_installation_dir(&installation_dir);
+    assert_eq!(
+      bin_dir.executable_path("psql"),
+      installation_dir
+        .join("bin")
+        .join(format!("psql{}", std::env::consts::EXE_SUFFIX))
+    );
+
+    fs::create_dir_all(installation_dir.join("bin")).unwrap();
+    fs::write(bin_dir.executable_path("psql"), "").unwrap();
+    #[cfg(unix)]
+    {
+      use std::os::unix::fs::PermissionsExt;
+      fs::set_permissions(
+        bin_dir.executable_path("psql"),
+        fs::Permissions::from_mode(0o755),
+      )
+      .unwrap();
+    }
+    assert_eq!(
+      bin_dir.program_dir_for(&["psql"]).unwrap(),
+      installation_dir.join("bin").to_string_lossy()
+    );
+    let error = bin_dir
+      .program_dir_for(&["psql", "pg_dump"])
+      .unwrap_err()
+      .to_string();
+    assert!(error.contains("pg_dump not found"));
+    assert!(error.contains("installationDir"));
+    fs::remove_dir_all(&installation_dir).unwrap();
+  }
+}
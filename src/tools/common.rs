@@ -1,11 +1,24 @@
+use crate::error::PgEmbedError;
+use crate::redact::redact;
 use crate::types::ConnectionInfo;
 use napi_derive::napi;
+use postgresql_commands::traits::CommandBuilder;
 use serde::{Deserialize, Serialize};
-use std::{fmt::Display, process::Output};
+use std::{
+  fmt::Display,
+  path::Path,
+  process::{Command, Output},
+  time::Duration,
+};
+
+/// Bytes of each output stream a timeout error includes, from the end of the stream
+const PARTIAL_OUTPUT_LIMIT: usize = 4096;
 
 #[napi(object)]
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 /// Configuration for connecting to a PostgreSQL server.
+///
+/// Native connections, such as those of `connect()`, honour the same fields as the tools.
 pub struct ConnectionConfig {
   /// The host of the PostgreSQL server.
   pub host: Option<String>,
@@ -17,6 +30,69 @@ pub struct ConnectionConfig {
   pub password: Option<String>,
   /// The database to connect to.
   pub database: Option<String>,
+  /// SSL mode for the connection (disable, allow, prefer, require, verify-ca, verify-full).
+  /// Passed to the tools as the PGSSLMODE environment variable.
+  #[napi(js_name = "sslMode")]
+  pub ssl_mode: Option<String>,
+  /// Path to the root certificate used to verify the server certificate.
+  /// Passed to the tools as the PGSSLROOTCERT environment variable.
+  #[napi(js_name = "sslRootCert")]
+  pub ssl_root_cert: Option<String>,
+  /// Path to a password file (.pgpass format) to look up the password in.
+  /// Passed to the tools as the PGPASSFILE environment variable.
+  pub passfile: Option<String>,
+  /// Name of a connection service defined in a service file (e.g. `~/.pg_service.conf`).
+  /// Passed to the tools as the PGSERVICE environment variable; explicitly set fields take precedence.
+  pub service: Option<String>,
+  /// Maximum time to wait while connecting, in seconds.
+  /// Passed to the tools as the PGCONNECT_TIMEOUT environment variable.
+  #[napi(js_name = "connectTimeout")]
+  pub connect_timeout: Option<u32>,
+  /// Application name reported to the server (visible in pg_stat_activity).
+  /// Passed to the tools as the PGAPPNAME environment variable.
+  #[napi(js_name = "applicationName")]
+  pub application_name: Option<String>,
+}
+
+impl ConnectionConfig {
+  /// The password to pass to the tools; an empty password means none (trust authentication).
+  pub fn password(&self) -> Option<&str> {
+    self
+      .password
+      .as_deref()
+      .filter(|password| !password.is_empty())
+  }
+
+  /// libpq environment variables for the connection options that have no common command-line flag.
+  pub fn libpq_env(&self) -> Vec<(&'static str, String)> {
+    let mut env = Vec::new();
+    if let Some(ssl_mode) = &self.ssl_mode {
+      env.push(("PGSSLMODE", ssl_mode.clone()));
+    }
+    if let Some(ssl_root_cert) = &self.ssl_root_cert {
+      env.push(("PGSSLROOTCERT", ssl_root_cert.clone()));
+    }
+    if let Some(passfile) = &self.passfile {
+      env.push(("PGPASSFILE", passfile.clone()));
+    }
+    if let Some(service) = &self.service {
+      env.push(("PGSERVICE", service.clone()));
+    }
+    if let Some(connect_timeout) = self.connect_timeout {
+      env.push(("PGCONNECT_TIMEOUT", connect_timeout.to_string()));
+    }
+    if let Some(application_name) = &self.application_name {
+      env.push(("PGAPPNAME", application_name.clone()));
+    }
+    env
+  }
+
+  /// Apply the libpq environment variables of this connection to a tool command.
+  pub fn apply_env(&self, command: &mut Command) {
+    for (key, value) in self.libpq_env() {
+      command.env(key, value);
+    }
+  }
 }
 
 impl From<ConnectionInfo> for ConnectionConfig {
@@ -25,8 +101,9 @@ impl From<ConnectionInfo> for ConnectionConfig {
       host: Some(info.host),
       port: Some(info.port),
       username: Some(info.username),
-      password: Some(info.password),
+      password: Some(info.password).filter(|password| !password.is_empty()),
       database: Some(info.database_name),
+      ..Default::default()
     }
   }
 }
@@ -43,12 +120,30 @@ impl Display for ConnectionConfig {
     if let Some(username) = &self.username {
       conn_str.push_str(&format!("user={username} ",));
     }
-    if let Some(password) = &self.password {
+    if let Some(password) = self.password() {
       conn_str.push_str(&format!("password={password} "));
     }
     if let Some(database) = &self.database {
       conn_str.push_str(&format!("dbname={database} "));
     }
+    if let Some(ssl_mode) = &self.ssl_mode {
+      conn_str.push_str(&format!("sslmode={ssl_mode} "));
+    }
+    if let Some(ssl_root_cert) = &self.ssl_root_cert {
+      conn_str.push_str(&format!("sslrootcert={ssl_root_cert} "));
+    }
+    if let Some(passfile) = &self.passfile {
+      conn_str.push_str(&format!("passfile={passfile} "));
+    }
+    if let Some(service) = &self.service {
+      conn_str.push_str(&format!("service={service} "));
+    }
+    if let Some(connect_timeout) = self.connect_timeout {
+      conn_str.push_str(&format!("connect_timeout={connect_timeout} "));
+    }
+    if let Some(application_name) = &self.application_name {
+      conn_str.push_str(&format!("application_name={application_name} "));
+    }
     write!(f, "{}", conn_str.trim())
   }
 }
@@ -60,10 +155,86 @@ impl Display for ConnectionConfig {
 /// These are common options that apply to all PostgreSQL tools,
 /// separate from connection-specific settings.
 pub struct ToolOptions {
-  /// Timeout for the tool execution in seconds.
+  /// Timeout for the tool execution in seconds. A tool still running after this time is
+  /// killed, and the execution fails with the output written so far.
   pub timeout: Option<u32>,
   /// If true, suppresses tool output.
   pub silent: Option<bool>,
+  /// If true, composes the command without running it. The result carries the command
+  /// line in `command` (redacted) and `rawCommand` (as it would run), for debugging the
+  /// option mapping or running the command elsewhere.
+  pub dry_run: Option<bool>,
+}
+
+impl ToolOptions {
+  /// Whether the tool output should be suppressed.
+  pub fn is_silent(&self) -> bool {
+    self.silent.unwrap_or(false)
+  }
+
+  /// Whether commands are only composed, not run.
+  pub fn is_dry_run(&self) -> bool {
+    self.dry_run.unwrap_or(false)
+  }
+
+  /// How long the tool may run, if limited; a timeout of 0 means no limit.
+  pub fn time_limit(&self) -> Option<Duration> {
+    time_limit(self.timeout)
+  }
+}
+
+/// The time limit for a timeout in seconds; a timeout of 0 means no limit.
+pub(crate) fn time_limit(timeout: Option<u32>) -> Option<Duration> {
+  timeout
+    .filter(|seconds| *seconds > 0)
+    .map(|seconds| Duration::from_secs(u64::from(seconds)))
+}
+
+/// The error for `tool` killed after running for `time_limit`, with the end of the
+/// output it wrote until then.
+pub(crate) fn timed_out_error(
+  tool: &str,
+  time_limit: Duration,
+  stdout: &[u8],
+  stderr: &[u8],
+) -> PgEmbedError {
+  let partial = |output: &[u8]| {
+    let start = output.len().saturating_sub(PARTIAL_OUTPUT_LIMIT);
+    let text = String::from_utf8_lossy(&output[start..]).trim().to_string();
+    if start > 0 {
+      format!("...{text}")
+    } else {
+      text
+    }
+  };
+  let seconds = time_limit.as_secs();
+  PgEmbedError::TimeoutError(format!(
+    "{tool} did not finish within {seconds} second{} and was killed; stdout: '{}', stderr: '{}'",
+    if seconds == 1 { "" } else { "s" },
+    partial(stdout),
+    partial(stderr)
+  ))
+}
+
+/// Build a `PGOPTIONS` value that applies per-session statement and lock timeouts.
+///
+/// Returns `None` when neither timeout is set so the caller can leave the environment untouched.
+pub fn session_timeout_options(
+  statement_timeout_ms: Option<u32>,
+  lock_timeout_ms: Option<u32>,
+) -> Option<String> {
+  let mut options = Vec::new();
+  if let Some(ms) = statement_timeout_ms {
+    options.push(format!("-c statement_timeout={ms}"));
+  }
+  if let Some(ms) = lock_timeout_ms {
+    options.push(format!("-c lock_timeout={ms}"));
+  }
+  if options.is_empty() {
+    None
+  } else {
+    Some(options.join(" "))
+  }
 }
 
 #[napi(object)]
@@ -76,11 +247,21 @@ pub struct ToolResult {
   pub stdout: String,
   /// The standard error of the tool.
   pub stderr: String,
+  /// The executed command line (program followed by its arguments), with credentials redacted.
   pub command: Vec<String>,
+  /// Names of the environment variables set for the tool, such as PGPASSWORD or PGSSLMODE.
+  pub environment: Vec<String>,
+  /// The command line without redaction; only set for a dry run.
+  pub raw_command: Option<Vec<String>>,
 }
 
 impl ToolResult {
-  pub fn from_output(output: Output, _silent: bool) -> crate::error::Result<Self> {
+  pub fn from_output(
+    command: Vec<String>,
+    environment: Vec<String>,
+    output: Output,
+    _silent: bool,
+  ) -> crate::error::Result<Self> {
     let exit_code = output.status.code().unwrap_or(1);
     let stdout = String::from_utf8_lossy(&output.stdout).to_string();
     let stderr = String::from_utf8_lossy(&output.stderr).to_string();
@@ -89,45 +270,215 @@ impl ToolResult {
       exit_code,
       stdout,
       stderr,
-      command: vec![],
+      command,
+      environment,
+      raw_command: None,
     })
   }
-}
-
-pub fn convert_options(
-  instance_config: &ConnectionConfig,
-  tool_config: Option<ConnectionConfig>,
-) -> Vec<String> {
-  let mut args = Vec::new();
-  let config = tool_config.unwrap_or(instance_config.clone());
 
-  if let Some(host) = &config.host {
-    args.push("--host".to_string());
-    args.push(host.clone());
+  /// The result of a dry run: the composed command, which did not run.
+  pub fn dry_run(command: &Command) -> Self {
+    Self {
+      exit_code: 0,
+      stdout: String::new(),
+      stderr: String::new(),
+      command: command_line(command),
+      environment: command_env(command),
+      raw_command: Some(
+        std::iter::once(command.get_program())
+          .chain(command.get_args())
+          .map(|arg| arg.to_string_lossy().to_string())
+          .collect(),
+      ),
+    }
   }
-  if let Some(port) = config.port {
-    args.push("--port".to_string());
-    args.push(port.to_string());
+}
+
+/// Check that the program of a tool command exists and can be executed, before it is spawned
+///
+/// Spawning a missing program only fails with the operating system's "No such file or
+/// directory", which does not say which file was expected. Programs given without a
+/// directory are looked up in PATH by the operating system and are not checked.
+pub(crate) fn check_executable(command: &Command) -> crate::error::Result<()> {
+  let program = Path::new(command.get_program());
+  if program
+    .parent()
+    .is_none_or(|dir| dir.as_os_str().is_empty())
+  {
+    return Ok(());
   }
-  if let Some(username) = &config.username {
-    args.push("--username".to_string());
-    args.push(username.clone());
+  check_program(program)
+}
+
+/// Check that `program`, the path of a tool's executable, exists and can be executed
+pub(crate) fn check_program(program: &Path) -> crate::error::Result<()> {
+  let tool = program
+    .file_stem()
+    .map(|stem| stem.to_string_lossy().to_string())
+    .unwrap_or_default();
+  let program = if program.exists() || !cfg!(windows) {
+    program.to_path_buf()
+  } else {
+    program.with_extension("exe")
+  };
+  if !program.is_file() {
+    return Err(PgEmbedError::ToolError(format!(
+      "{tool} not found at '{}'. Check the programDir option of the tool, or the \
+       installationDir and version settings of the instance it belongs to",
+      program.display()
+    )));
   }
-  if let Some(database) = &config.database {
-    args.push("--dbname".to_string());
-    args.push(database.clone());
+  if !is_executable(&program) {
+    return Err(PgEmbedError::ToolError(format!(
+      "{tool} at '{}' is not executable. Check the file permissions of the PostgreSQL \
+       installation",
+      program.display()
+    )));
   }
+  Ok(())
+}
 
-  args
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+  use std::os::unix::fs::PermissionsExt;
+  path
+    .metadata()
+    .is_ok_and(|metadata| metadata.permissions().mode() & 0o111 != 0)
 }
 
-pub fn format_tool_args<T>(command: &T) -> Vec<String>
-where
-  T: postgresql_commands::traits::CommandBuilder,
-{
-  command
-    .get_args()
-    .iter()
-    .map(|s| s.to_string_lossy().to_string())
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> bool {
+  true
+}
+
+/// The command line of a tool command (program followed by its arguments), with credentials redacted.
+pub fn command_line(command: &Command) -> Vec<String> {
+  std::iter::once(command.get_program())
+    .chain(command.get_args())
+    .map(|arg| redact(&arg.to_string_lossy()))
     .collect()
 }
+
+/// Compose the command of a tool from its configured builder.
+///
+/// Every tool builds its command here, so the command line is mapped from the builder
+/// and the connection's libpq settings are added to the environment in the same way.
+pub fn compose<B: CommandBuilder>(builder: B, connection: &ConnectionConfig) -> Command {
+  let mut command = builder.build();
+  connection.apply_env(&mut command);
+  command
+}
+
+/// Names of the environment variables a tool command sets; their values may be credentials.
+pub fn command_env(command: &Command) -> Vec<String> {
+  let mut names: Vec<String> = command
+    .get_envs()
+    .filter(|(_, value)| value.is_some())
+    .map(|(name, _)| name.to_string_lossy().to_string())
+    .collect();
+  names.sort();
+  names
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_check_executable() {
+    let dir = std::env::temp_dir().join(format!("pg-embedded-exe-{}", uuid::Uuid::now_v7()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let error = check_executable(&Command::new(dir.join("pg_restore")))
+      .unwrap_err()
+      .to_string();
+    assert!(error.contains("pg_restore not found at"));
+    assert!(error.contains("installationDir and version"));
+
+    // Programs without a directory are resolved through PATH
+    assert!(check_executable(&Command::new("pg_restore")).is_ok());
+
+    #[cfg(unix)]
+    {
+      let program = dir.join("psql");
+      std::fs::write(&program, "").unwrap();
+      let error = check_executable(&Command::new(&program)).unwrap_err();
+      assert!(error.to_string().contains("is not executable"));
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn test_compose() {
+    let connection = ConnectionConfig {
+      ssl_mode: Some("require".to_string()),
+      application_name: Some("migrations".to_string()),
+      ..Default::default()
+    };
+    let builder = postgresql_commands::psql::PsqlBuilder::new()
+      .program_dir("/pg/bin")
+      .host("localhost")
+      .pg_password("secret");
+    let command = compose(builder, &connection);
+    assert_eq!(
+      command_line(&command),
+      ["/pg/bin/psql", "--host", "localhost"]
+    );
+    assert_eq!(
+      command_env(&command),
+      ["PGAPPNAME", "PGPASSWORD", "PGSSLMODE"]
+    );
+  }
+
+  #[test]
+  fn test_dry_run_result() {
+    let mut command = Command::new("/pg/bin/psql");
+    command.args(["--dbname", "dbname=app password=secret", "--no-psqlrc"]);
+    let result = ToolResult::dry_run(&command);
+    assert_eq!(result.exit_code, 0);
+    assert_eq!(
+      result.command,
+      [
+        "/pg/bin/psql",
+        "--dbname",
+        "dbname=app password=********",
+        "--no-psqlrc"
+      ]
+    );
+    assert_eq!(
+      result.raw_command.unwrap(),
+      [
+        "/pg/bin/psql",
+        "--dbname",
+        "dbname=app password=secret",
+        "--no-psqlrc"
+      ]
+    );
+  }
+
+  #[test]
+  fn test_time_limit() {
+    let options = |timeout| ToolOptions {
+      timeout,
+      ..Default::default()
+    };
+    assert_eq!(options(None).time_limit(), None);
+    assert_eq!(options(Some(0)).time_limit(), None);
+    assert_eq!(
+      options(Some(30)).time_limit(),
+      Some(Duration::from_secs(30))
+    );
+
+    let error =
+      timed_out_error("pg_dump", Duration::from_secs(30), b"-- partial\n", b"").to_string();
+    assert!(error.contains("pg_dump did not finish within 30 seconds"));
+    assert!(error.contains("stdout: '-- partial'"));
+    let long = vec![b'x'; PARTIAL_OUTPUT_LIMIT + 10];
+    let error = timed_out_error("psql", Duration::from_secs(1), b"", &long).to_string();
+    assert!(error.contains("psql did not finish within 1 second and"));
+    assert!(error.contains(&format!(
+      "stderr: '...{}'",
+      "x".repeat(PARTIAL_OUTPUT_LIMIT)
+    )));
+  }
+}
@@ -0,0 +1,130 @@
+//! Checks of tool options against the version of the installed tool binaries
+
+use crate::error::{PgEmbedError, Result};
+use crate::paths::native_path;
+use crate::tools::common::check_executable;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use tokio::process::Command as TokioCommand;
+
+/// A command-line option that only exists from a given PostgreSQL major version on
+pub(crate) struct OptionRequirement {
+  /// The option as passed on the command line, e.g. `--large-objects`
+  pub option: &'static str,
+  /// First major version of the tool that accepts the option
+  pub min_major: u32,
+}
+
+impl OptionRequirement {
+  pub const fn new(option: &'static str, min_major: u32) -> Self {
+    Self { option, min_major }
+  }
+}
+
+/// Major versions of tool binaries, keyed by path, so `--version` runs once per binary
+fn version_cache() -> &'static Mutex<HashMap<String, u32>> {
+  static CACHE: OnceLock<Mutex<HashMap<String, u32>>> = OnceLock::new();
+  CACHE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Reject options the tool in `program_dir` does not support, before the tool is spawned
+///
+/// The tool's version is only looked up when `requirements` is not empty.
+pub(crate) async fn check_option_support(
+  program_dir: &str,
+  tool: &str,
+  requirements: &[OptionRequirement],
+) -> Result<()> {
+  if requirements.is_empty() {
+    return Ok(());
+  }
+  let major = tool_major_version(program_dir, tool).await?;
+  unsupported_option_error(tool, major, requirements).map_or(Ok(()), Err)
+}
+
+/// Major version of `tool` in `program_dir`, from `<tool> --version`
+pub(crate) async fn tool_major_version(program_dir: &str, tool: &str) -> Result<u32> {
+  let path = native_path(program_dir)
+    .join(tool)
+    .to_string_lossy()
+    .to_string();
+  if let Some(major) = version_cache()
+    .lock()
+    .ok()
+    .and_then(|cache| cache.get(&path).copied())
+  {
+    return Ok(major);
+  }
+
+  let command = std::process::Command::new(&path);
+  check_executable(&command)?;
+  let output = TokioCommand::from(command)
+    .arg("--version")
+    .output()
+    .await?;
+  let stdout = String::from_utf8_lossy(&output.stdout);
+  let major = parse_major_version(&stdout).ok_or_else(|| {
+    PgEmbedError::ToolError(format!(
+      "Cannot determine the version of {tool} from '{}'",
+      stdout.trim()
+    ))
+  })?;
+  if let Ok(mut cache) = version_cache().lock() {
+    cache.insert(path, major);
+  }
+  Ok(major)
+}
+
+/// Major version from `--version` output such as `pg_dump (PostgreSQL) 16.4` or
+/// `pg_dump (PostgreSQL) 17beta1`
+fn parse_major_version(output: &str) -> Option<u32> {
+  let version = output.split_whitespace().last()?;
+  let digits: String = version.chars().take_while(char::is_ascii_digit).collect();
+  digits.parse().ok()
+}
+
+/// Error for the first option `major` does not support, if any
+fn unsupported_option_error(
+  tool: &str,
+  major: u32,
+  requirements: &[OptionRequirement],
+) -> Option<PgEmbedError> {
+  let requirement = requirements
+    .iter()
+    .find(|requirement| major < requirement.min_major)?;
+  Some(PgEmbedError::ConfigurationError(format!(
+    "{tool} option {} requires PostgreSQL {} or later, but the installed {tool} is version {major}",
+    requirement.option, requirement.min_major
+  )))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_major_version() {
+    assert_eq!(parse_major_version("pg_dump (PostgreSQL) 16.4\n"), Some(16));
+    assert_eq!(
+      parse_major_version("pg_dump (PostgreSQL) 17beta1"),
+      Some(17)
+    );
+    assert_eq!(
+      parse_major_version("pg_basebackup (PostgreSQL) 9.6.24"),
+      Some(9)
+    );
+    assert_eq!(parse_major_version(""), None);
+  }
+
+  #[test]
+  fn test_unsupported_option_error() {
+    let requirements = [OptionRequirement::new("--large-objects", 16)];
+    assert!(unsupported_option_error("pg_dump", 16, &requirements).is_none());
+    let error = unsupported_option_error("pg_dump", 15, &requirements).unwrap();
+    assert_eq!(
+      error.to_string(),
+      "Configuration error: pg_dump option --large-objects requires PostgreSQL 16 or later, \
+       but the installed pg_dump is version 15"
+    );
+  }
+}
@@ -0,0 +1,311 @@
+//! Process-wide hooks around tool executions, for auditing, metrics or wrapping commands
+
+use crate::error::{PgEmbedError, Result};
+use crate::redact::redact;
+use crate::tools::common::{
+  check_executable, command_env, command_line, timed_out_error, ToolOptions, ToolResult,
+};
+use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi::Status;
+use napi_derive::napi;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::process::Command as TokioCommand;
+
+/// A tool command about to run, passed to the hook set with `setBeforeExecuteHook()`
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ToolCommand {
+  /// Name of the tool, e.g. "pg_dump"
+  pub tool: String,
+  /// The command line (program followed by its arguments), with credentials redacted
+  pub command: Vec<String>,
+}
+
+/// A finished tool command, passed to the hook set with `setAfterExecuteHook()`
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ToolExecution {
+  /// Name of the tool, e.g. "pg_dump"
+  pub tool: String,
+  /// The command line that ran, including a wrapper from the before hook, with
+  /// credentials redacted
+  pub command: Vec<String>,
+  /// Exit code of the tool
+  pub exit_code: i32,
+  /// Standard error of the tool, with credentials redacted
+  pub stderr: String,
+  /// Time the tool took, in milliseconds
+  pub duration_ms: f64,
+}
+
+type BeforeExecuteCallback =
+  ThreadsafeFunction<ToolCommand, Option<Vec<String>>, ToolCommand, Status, false, true>;
+type AfterExecuteCallback =
+  ThreadsafeFunction<ToolExecution, (), ToolExecution, Status, false, true>;
+
+static BEFORE_EXECUTE: LazyLock<Mutex<Option<Arc<BeforeExecuteCallback>>>> =
+  LazyLock::new(|| Mutex::new(None));
+static AFTER_EXECUTE: LazyLock<Mutex<Option<Arc<AfterExecuteCallback>>>> =
+  LazyLock::new(|| Mutex::new(None));
+
+/// Sets a hook that runs before every tool command
+///
+/// The hook receives the tool name and its command line, with credentials redacted, before
+/// the process is spawned. It may return a wrapper, a program and its arguments, that the
+/// command is run with, e.g. `['nice', '-n', '10']`; the command itself cannot be replaced,
+/// as its redacted form is all the hook sees. The hook applies to every tool of the process,
+/// including the psql runs behind `executeSql()` and the stages of `runToolPipeline()`.
+/// Passing null removes the hook.
+///
+/// @param callback - Function receiving each command, optionally returning a wrapper
+///
+/// @example
+/// ```typescript
+/// setBeforeExecuteHook(({ tool, command }) => {
+///   audit.write(`${tool}: ${command.join(' ')}`);
+///   return tool === 'pg_dump' ? ['nice', '-n', '10'] : undefined;
+/// });
+/// ```
+#[napi(
+  ts_args_type = "callback: ((command: ToolCommand) => string[] | undefined | null | void) | undefined | null"
+)]
+pub fn set_before_execute_hook(callback: Option<BeforeExecuteCallback>) {
+  if let Ok(mut hook) = BEFORE_EXECUTE.lock() {
+    *hook = callback.map(Arc::new);
+  }
+}
+
+/// Sets a hook that runs after every tool command
+///
+/// The hook receives the tool name, the command line that ran and its exit code, stderr
+/// and duration, with credentials redacted. Pipelines report their stages in their result
+/// instead. Passing null removes the hook.
+///
+/// @param callback - Function receiving each finished command
+///
+/// @example
+/// ```typescript
+/// setAfterExecuteHook(({ tool, exitCode, durationMs }) => {
+///   metrics.histogram('pg_tool_duration_ms', durationMs, { tool, exitCode });
+/// });
+/// ```
+#[napi(ts_args_type = "callback: ((execution: ToolExecution) => void) | undefined | null")]
+pub fn set_after_execute_hook(callback: Option<AfterExecuteCallback>) {
+  if let Ok(mut hook) = AFTER_EXECUTE.lock() {
+    *hook = callback.map(Arc::new);
+  }
+}
+
+/// Name of the tool a command runs, the file name of its program
+pub(crate) fn tool_name(command: &Command) -> String {
+  Path::new(command.get_program())
+    .file_stem()
+    .map(|stem| stem.to_string_lossy().to_string())
+    .unwrap_or_default()
+}
+
+/// `command` run through `wrapper`, with the same environment and working directory
+fn wrap_command(command: &Command, wrapper: &[String]) -> Command {
+  let mut wrapped = Command::new(&wrapper[0]);
+  wrapped
+    .args(&wrapper[1..])
+    .arg(command.get_program())
+    .args(command.get_args());
+  for (key, value) in command.get_envs() {
+    match value {
+      Some(value) => wrapped.env(key, value),
+      None => wrapped.env_remove(key),
+    };
+  }
+  if let Some(dir) = command.get_current_dir() {
+    wrapped.current_dir(dir);
+  }
+  #[cfg(windows)]
+  {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    wrapped.creation_flags(CREATE_NO_WINDOW);
+  }
+  wrapped
+}
+
+/// What the after hook is told about a command that was passed to the before hook
+pub(crate) struct ExecutionReport {
+  /// The command line that runs, with credentials redacted
+  pub command_line: Vec<String>,
+  /// Names of the environment variables set for the command
+  pub environment: Vec<String>,
+  tool: String,
+  started: Instant,
+}
+
+impl ExecutionReport {
+  /// Report the result of the command to the after hook
+  pub fn finish(&self, result: &ToolResult) {
+    let Some(hook) = AFTER_EXECUTE.lock().ok().and_then(|hook| hook.clone()) else {
+      return;
+    };
+    hook.call(
+      ToolExecution {
+        tool: self.tool.clone(),
+        command: self.command_line.clone(),
+        exit_code: result.exit_code,
+        stderr: redact(&result.stderr),
+        duration_ms: self.started.elapsed().as_secs_f64() * 1000.0,
+      },
+      ThreadsafeFunctionCallMode::NonBlocking,
+    );
+  }
+}
+
+/// Pass `command` to the before hook, wrapping it when the hook returns a wrapper
+pub(crate) async fn before_execute(command: Command) -> Result<(Command, ExecutionReport)> {
+  let tool = tool_name(&command);
+  let hook = BEFORE_EXECUTE.lock().ok().and_then(|hook| hook.clone());
+  let command = match hook {
+    Some(hook) => {
+      let wrapper = hook
+        .call_async(ToolCommand {
+          tool: tool.clone(),
+          command: command_line(&command),
+        })
+        .await
+        .map_err(|e| PgEmbedError::ToolError(format!("beforeExecute hook failed: {e}")))?;
+      match wrapper {
+        Some(wrapper) if !wrapper.is_empty() => wrap_command(&command, &wrapper),
+        _ => command,
+      }
+    }
+    None => command,
+  };
+  let report = ExecutionReport {
+    command_line: command_line(&command),
+    environment: command_env(&command),
+    tool,
+    started: Instant::now(),
+  };
+  Ok((command, report))
+}
+
+/// Run a tool command with its output captured, passing it through the hooks
+pub(crate) async fn run_tool_command(
+  command: Command,
+  options: &ToolOptions,
+) -> Result<ToolResult> {
+  if options.is_dry_run() {
+    return Ok(ToolResult::dry_run(&command));
+  }
+  check_executable(&command)?;
+  let (command, report) = before_execute(command).await?;
+  let output = capture_output(command, options.time_limit()).await?;
+  let result = ToolResult::from_output(
+    report.command_line.clone(),
+    report.environment.clone(),
+    output,
+    options.is_silent(),
+  )?;
+  report.finish(&result);
+  Ok(result)
+}
+
+/// Append everything `pipe` yields to `buffer`, which keeps what was read when the
+/// future is dropped
+async fn read_into(mut pipe: impl AsyncRead + Unpin, buffer: &mut Vec<u8>) -> std::io::Result<()> {
+  let mut chunk = [0u8; 8192];
+  loop {
+    let read = pipe.read(&mut chunk).await?;
+    if read == 0 {
+      return Ok(());
+    }
+    buffer.extend_from_slice(&chunk[..read]);
+  }
+}
+
+/// Run `command` with its output captured, killing it once `time_limit` has passed
+pub(crate) async fn capture_output(
+  command: Command,
+  time_limit: Option<Duration>,
+) -> Result<Output> {
+  let tool = tool_name(&command);
+  let mut child = TokioCommand::from(command)
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .kill_on_drop(true)
+    .spawn()?;
+  let stdout_pipe = child.stdout.take().expect("stdout is piped");
+  let stderr_pipe = child.stderr.take().expect("stderr is piped");
+  let mut stdout = Vec::new();
+  let mut stderr = Vec::new();
+  let finished = {
+    let run = async {
+      let (read_stdout, read_stderr) = futures_util::future::join(
+        read_into(stdout_pipe, &mut stdout),
+        read_into(stderr_pipe, &mut stderr),
+      )
+      .await;
+      read_stdout?;
+      read_stderr?;
+      child.wait().await
+    };
+    match time_limit {
+      Some(time_limit) => tokio::time::timeout(time_limit, run).await.ok(),
+      None => Some(run.await),
+    }
+  };
+  match finished {
+    Some(status) => Ok(Output {
+      status: status?,
+      stdout,
+      stderr,
+    }),
+    None => {
+      let _ = child.kill().await;
+      Err(timed_out_error(
+        &tool,
+        time_limit.unwrap_or_default(),
+        &stdout,
+        &stderr,
+      ))
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::ffi::OsStr;
+
+  #[test]
+  fn test_tool_name() {
+    assert_eq!(tool_name(&Command::new("/pg/bin/pg_dump")), "pg_dump");
+    assert_eq!(tool_name(&Command::new("psql")), "psql");
+  }
+
+  #[test]
+  fn test_wrap_command() {
+    let mut command = Command::new("/pg/bin/pg_dump");
+    command
+      .args(["--schema-only", "app"])
+      .env("PGPASSWORD", "secret")
+      .current_dir("/tmp");
+    let wrapped = wrap_command(
+      &command,
+      &["nice".to_string(), "-n".to_string(), "10".to_string()],
+    );
+
+    assert_eq!(wrapped.get_program(), "nice");
+    let args: Vec<&OsStr> = wrapped.get_args().collect();
+    assert_eq!(
+      args,
+      ["-n", "10", "/pg/bin/pg_dump", "--schema-only", "app"]
+    );
+    assert!(wrapped
+      .get_envs()
+      .any(|(key, value)| key == "PGPASSWORD" && value == Some(OsStr::new("secret"))));
+    assert_eq!(wrapped.get_current_dir(), Some(Path::new("/tmp")));
+  }
+}
@@ -1,19 +1,33 @@
 // Tooling module for pg-embedded
 
+pub(crate) mod bin_dir;
 pub mod common;
+pub(crate) mod compat;
+pub mod hooks;
 pub mod pg_basebackup;
+pub mod pg_config;
 pub mod pg_dump;
 pub mod pg_dumpall;
 pub mod pg_isready;
 pub mod pg_restore;
 pub mod pg_rewind;
+pub mod pipeline;
 pub mod psql;
+pub mod stream;
+pub mod tool;
+pub mod verbose;
 
 pub use self::common::*;
+pub use self::hooks::*;
 pub use self::pg_basebackup::*;
+pub use self::pg_config::*;
 pub use self::pg_dump::*;
 pub use self::pg_dumpall::*;
 pub use self::pg_isready::*;
 pub use self::pg_restore::*;
 pub use self::pg_rewind::*;
+pub use self::pipeline::*;
 pub use self::psql::*;
+pub use self::stream::*;
+pub use self::tool::*;
+pub use self::verbose::*;
@@ -1,11 +1,12 @@
 use crate::error::Result;
-use crate::tools::common::{ConnectionConfig, ToolOptions, ToolResult};
+use crate::paths::native_path;
+use crate::tools::common::{compose, ConnectionConfig, ToolOptions, ToolResult};
+use crate::tools::compat::{check_option_support, OptionRequirement};
+use crate::tools::tool::PgTool;
 use napi_derive::napi;
 use postgresql_commands::pg_basebackup::PgBaseBackupBuilder;
-use postgresql_commands::traits::CommandBuilder;
 use serde::Deserialize;
-use std::process::{Command, Stdio};
-use tokio::process::Command as TokioCommand;
+use std::process::Command;
 
 #[napi]
 #[derive(Clone, Debug, Deserialize)]
@@ -236,16 +237,43 @@ impl PgBasebackupTool {
   ///
   /// @returns A promise that resolves with the result of the command execution.
   pub async fn execute(&self) -> Result<ToolResult> {
-    let command = to_command(&self.options)?;
-    run_command(command, &self.options).await
+    check_option_support(
+      &self.options.program_dir,
+      "pg_basebackup",
+      &version_requirements(&self.options.config),
+    )
+    .await?;
+    PgTool::execute(self).await
   }
 }
 
+impl PgTool for PgBasebackupTool {
+  fn tool_options(&self) -> ToolOptions {
+    self.options.config.tool.clone().unwrap_or_default()
+  }
+
+  fn compose(&self) -> Result<Command> {
+    to_command(&self.options)
+  }
+}
+
+/// Options of the configuration that older pg_basebackup versions do not accept
+fn version_requirements(config: &PgBasebackupConfig) -> Vec<OptionRequirement> {
+  let mut requirements = Vec::new();
+  if config.wal_method.is_some() {
+    requirements.push(OptionRequirement::new("--wal-method", 10));
+  }
+  if config.create_slot == Some(true) {
+    requirements.push(OptionRequirement::new("--create-slot", 11));
+  }
+  requirements
+}
+
 fn to_command(options: &PgBasebackupOptions) -> Result<Command> {
   let mut builder = PgBaseBackupBuilder::new();
   let config = &options.config;
 
-  builder = builder.program_dir(&options.program_dir);
+  builder = builder.program_dir(native_path(&options.program_dir));
 
   let connection = &options.connection;
   if let Some(host) = &connection.host {
@@ -257,7 +285,7 @@ fn to_command(options: &PgBasebackupOptions) -> Result<Command> {
   if let Some(user) = &connection.username {
     builder = builder.username(user);
   }
-  if let Some(password) = &connection.password {
+  if let Some(password) = connection.password() {
     builder = builder.pg_password(password);
   }
 
@@ -286,23 +314,5 @@ fn to_command(options: &PgBasebackupOptions) -> Result<Command> {
     builder = builder.wal_method(wal_method.to_pg_basebackup_wal_method());
   }
 
-  let command = builder.build();
-  Ok(command)
-}
-
-async fn run_command(command: Command, options: &PgBasebackupOptions) -> Result<ToolResult> {
-  let output = TokioCommand::from(command)
-    .stdout(Stdio::piped())
-    .stderr(Stdio::piped())
-    .output()
-    .await?;
-  ToolResult::from_output(
-    output,
-    options
-      .config
-      .tool
-      .as_ref()
-      .and_then(|t| t.silent)
-      .unwrap_or(false),
-  )
+  Ok(compose(builder, connection))
 }
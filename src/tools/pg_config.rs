@@ -0,0 +1,222 @@
+use crate::error::{PgEmbedError, Result};
+use crate::paths::native_path;
+use crate::tools::common::{ToolOptions, ToolResult};
+use crate::tools::tool::PgTool;
+use napi_derive::napi;
+use postgresql_commands::pg_config::PgConfigBuilder;
+use postgresql_commands::traits::CommandBuilder;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::process::Command;
+
+/// Complete options for configuring the `pg_config` tool.
+///
+/// pg_config reads the configuration the installation was built with; it does not
+/// connect to a server, so only the program directory is required.
+///
+/// @example
+/// ```typescript
+/// const options = {
+///   programDir: '/home/postgresql/17.5.0/bin',
+/// };
+/// ```
+#[napi(object)]
+#[derive(Clone, Debug, Deserialize)]
+pub struct PgConfigOptions {
+  /// The directory where the `pg_config` executable is located (required).
+  #[napi(js_name = "programDir")]
+  pub program_dir: String,
+  /// Generic tool options like silent mode and dry run.
+  pub tool: Option<ToolOptions>,
+}
+
+/// Build configuration of a PostgreSQL installation, as reported by pg_config.
+///
+/// Values pg_config reports as "not recorded" are empty.
+#[napi(object)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BuildConfig {
+  /// PostgreSQL version, e.g. "PostgreSQL 17.5"
+  pub version: String,
+  /// Location of the executables
+  pub bindir: String,
+  /// Location of the documentation
+  pub docdir: String,
+  /// Location of the client C header files
+  pub includedir: String,
+  /// Location of the C header files for server extensions
+  pub includedir_server: String,
+  /// Location of the client libraries
+  pub libdir: String,
+  /// Location of dynamically loadable modules (extension libraries)
+  pub pkglibdir: String,
+  /// Location of architecture-independent support files (extension control files live in
+  /// its `extension` directory)
+  pub sharedir: String,
+  /// Location of system-wide configuration files
+  pub sysconfdir: String,
+  /// Location of the extension makefiles
+  pub pgxs: String,
+  /// Options given to `configure` when PostgreSQL was built
+  pub configure: Vec<String>,
+  /// C compiler used for the build
+  pub cc: String,
+  /// C compiler flags used for the build
+  pub cflags: String,
+  /// Linker flags used for the build
+  pub ldflags: String,
+  /// Libraries linked into the executables
+  pub libs: String,
+  /// Every value pg_config reported, keyed by its name as printed (e.g. "CFLAGS_SL")
+  pub values: HashMap<String, String>,
+}
+
+/// Parse the `NAME = value` lines printed by pg_config without arguments.
+pub(crate) fn parse_pg_config(output: &str) -> BuildConfig {
+  let values: HashMap<String, String> = output
+    .lines()
+    .filter_map(|line| line.split_once('='))
+    .map(|(name, value)| {
+      let value = value.trim();
+      let value = if value == "not recorded" { "" } else { value };
+      (name.trim().to_string(), value.to_string())
+    })
+    .collect();
+  let value = |name: &str| values.get(name).cloned().unwrap_or_default();
+  BuildConfig {
+    version: value("VERSION"),
+    bindir: value("BINDIR"),
+    docdir: value("DOCDIR"),
+    includedir: value("INCLUDEDIR"),
+    includedir_server: value("INCLUDEDIR-SERVER"),
+    libdir: value("LIBDIR"),
+    pkglibdir: value("PKGLIBDIR"),
+    sharedir: value("SHAREDIR"),
+    sysconfdir: value("SYSCONFDIR"),
+    pgxs: value("PGXS"),
+    configure: configure_flags(&value("CONFIGURE")),
+    cc: value("CC"),
+    cflags: value("CFLAGS"),
+    ldflags: value("LDFLAGS"),
+    libs: value("LIBS"),
+    values,
+  }
+}
+
+/// Split the `CONFIGURE` value, in which every option is single-quoted.
+fn configure_flags(configure: &str) -> Vec<String> {
+  if configure.contains('\'') {
+    configure
+      .split('\'')
+      .skip(1)
+      .step_by(2)
+      .map(str::to_string)
+      .collect()
+  } else {
+    configure.split_whitespace().map(str::to_string).collect()
+  }
+}
+
+/// A tool for reading the build configuration of a PostgreSQL installation.
+///
+/// Reports where the installation keeps its executables, libraries and extension files,
+/// its version and how it was built, for diagnostics and for installing extensions.
+///
+/// @example
+/// ```typescript
+/// import { PgConfigTool } from 'pg-embedded';
+///
+/// const configTool = new PgConfigTool({ programDir: '/home/postgresql/17.5.0/bin' });
+/// const config = await configTool.getBuildConfig();
+/// console.log(config.version, config.pkglibdir);
+/// ```
+#[napi]
+pub struct PgConfigTool {
+  options: PgConfigOptions,
+}
+
+#[napi]
+impl PgConfigTool {
+  /// Creates a new `PgConfigTool` instance.
+  ///
+  /// @param options - Configuration options for pg_config (programDir is required)
+  /// @returns A new PgConfigTool instance
+  #[napi(constructor)]
+  pub fn new(options: PgConfigOptions) -> Self {
+    Self { options }
+  }
+
+  /// Executes `pg_config` and returns its raw output.
+  ///
+  /// @returns Promise<ToolResult> whose stdout holds the `NAME = value` lines
+  /// @throws Error if the command fails to execute
+  #[napi]
+  pub async fn execute(&self) -> Result<ToolResult> {
+    PgTool::execute(self).await
+  }
+
+  /// Executes `pg_config` and returns the build configuration as a typed object.
+  ///
+  /// @returns Promise<BuildConfig> with the directories, version and build flags
+  /// @throws Error if pg_config cannot be run or fails
+  ///
+  /// @example
+  /// ```typescript
+  /// const { sharedir, pkglibdir } = await configTool.getBuildConfig();
+  /// fs.copyFileSync('my_ext.control', path.join(sharedir, 'extension', 'my_ext.control'));
+  /// ```
+  #[napi]
+  pub async fn get_build_config(&self) -> Result<BuildConfig> {
+    let result = PgTool::execute(self).await?;
+    if result.exit_code != 0 {
+      return Err(PgEmbedError::ToolError(format!(
+        "pg_config failed: {}",
+        result.stderr.trim()
+      )));
+    }
+    Ok(parse_pg_config(&result.stdout))
+  }
+}
+
+impl PgTool for PgConfigTool {
+  fn tool_options(&self) -> ToolOptions {
+    self.options.tool.clone().unwrap_or_default()
+  }
+
+  fn compose(&self) -> Result<Command> {
+    Ok(
+      PgConfigBuilder::new()
+        .program_dir(native_path(&self.options.program_dir))
+        .build(),
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_pg_config() {
+    let output = "BINDIR = /opt/pg/bin\n\
+       SHAREDIR = /opt/pg/share\n\
+       PKGLIBDIR = /opt/pg/lib/postgresql\n\
+       INCLUDEDIR-SERVER = /opt/pg/include/server\n\
+       CONFIGURE =  '--prefix=/opt/pg' '--with-openssl' 'CFLAGS=-O2 -g'\n\
+       CC = gcc\n\
+       LDFLAGS_EX = \n\
+       SYSCONFDIR = not recorded\n\
+       VERSION = PostgreSQL 17.5\n";
+    let config = parse_pg_config(output);
+    assert_eq!(config.version, "PostgreSQL 17.5");
+    assert_eq!(config.bindir, "/opt/pg/bin");
+    assert_eq!(config.includedir_server, "/opt/pg/include/server");
+    assert_eq!(
+      config.configure,
+      ["--prefix=/opt/pg", "--with-openssl", "CFLAGS=-O2 -g"]
+    );
+    assert_eq!(config.sysconfdir, "");
+    assert_eq!(config.values["LDFLAGS_EX"], "");
+    assert_eq!(config.values["CC"], "gcc");
+  }
+}
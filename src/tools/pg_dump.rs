@@ -1,11 +1,19 @@
-use crate::error::Result;
-use crate::tools::common::{ConnectionConfig, ToolOptions, ToolResult};
+use crate::error::{PgEmbedError, Result};
+use crate::paths::native_path;
+use crate::tools::common::{compose, ConnectionConfig, ToolOptions, ToolResult};
+use crate::tools::compat::tool_major_version;
+use crate::tools::stream::{
+  run_piped, run_piped_to_file, run_to_file, StreamCompression, StreamTransform,
+};
+use crate::tools::tool::PgTool;
+use crate::tools::verbose::{parse_verbose_line, VerboseEvent};
+use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi::Status;
 use napi_derive::napi;
 use postgresql_commands::pg_dump::PgDumpBuilder;
-use postgresql_commands::traits::CommandBuilder;
 use serde::Deserialize;
-use std::process::{Command, Stdio};
-use tokio::process::Command as TokioCommand;
+use std::process::Command;
+use std::sync::Arc;
 
 #[napi]
 #[derive(Clone, Debug, Deserialize)]
@@ -95,6 +103,13 @@ pub struct PgDumpConfig {
   /// Equivalent to pg_dump --no-privileges flag.
   #[napi(js_name = "noPrivileges")]
   pub no_privileges: Option<bool>,
+  /// Include large objects in the dump, also when `table` or `schema` selects only some objects.
+  /// Equivalent to pg_dump --large-objects (--blobs) flag.
+  pub blobs: Option<bool>,
+  /// Exclude large objects from the dump.
+  /// Equivalent to pg_dump --no-large-objects (--no-blobs) flag.
+  #[napi(js_name = "noBlobs")]
+  pub no_blobs: Option<bool>,
   /// Enable verbose output showing detailed progress information.
   /// Equivalent to pg_dump --verbose flag.
   pub verbose: Option<bool>,
@@ -127,6 +142,59 @@ pub struct PgDumpConfig {
   /// Equivalent to the pg_dump --if-exists flag.
   #[napi(js_name = "ifExists")]
   pub if_exists: Option<bool>,
+  /// Compress the dump stream inside pg-embedded before writing it to `file`.
+  /// Applied only when `file` is set; works with every format except Directory.
+  #[napi(js_name = "outputCompression")]
+  pub output_compression: Option<StreamCompression>,
+  /// Encrypt the dump stream with this passphrase (age format, readable with `age -d`).
+  /// Applied only when `file` is set; works with every format except Directory.
+  #[napi(js_name = "encryptionPassphrase")]
+  pub encryption_passphrase: Option<String>,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default, PartialEq)]
+/// Progress of a running dump, reported by `PgDumpTool.executeWithProgress()`.
+pub struct DumpProgress {
+  /// Number of tables whose data has been dumped so far.
+  pub completed: u32,
+  /// Total number of tables whose data the dump contains.
+  pub total: u32,
+  /// The table whose data is currently being dumped.
+  #[napi(js_name = "currentTable")]
+  pub current_table: Option<String>,
+}
+
+impl DumpProgress {
+  /// Update the progress from one line of `pg_dump --verbose` output.
+  ///
+  /// Returns true if the line marks the start of a table's data.
+  fn apply_verbose_line(&mut self, line: &str) -> bool {
+    let Some(table) = line.strip_prefix("pg_dump: dumping contents of table ") else {
+      return false;
+    };
+    self.completed = (self.completed + 1).min(self.total);
+    self.current_table = Some(table.trim_matches('"').to_string());
+    true
+  }
+}
+
+/// Count the tables with data in a plain schema-only dump.
+///
+/// Every `TABLE` entry counts except partitioned tables, whose rows belong to their
+/// partitions.
+fn count_data_tables(schema: &str) -> u32 {
+  schema
+    .split("\n-- Name: ")
+    .skip(1)
+    .filter(|entry| {
+      entry
+        .lines()
+        .next()
+        .is_some_and(|header| header.contains("; Type: TABLE;"))
+        && !entry.contains("\nPARTITION BY ")
+    })
+    .count() as u32
 }
 
 #[napi(object)]
@@ -277,12 +345,15 @@ impl PgDumpTool {
 
   /// Builds a pg_dump command with all configured options.
   /// This internal method translates the TypeScript options into command-line arguments.
-  fn to_command(&self, force_stdout: bool) -> Result<Command> {
+  ///
+  /// With `legacy_large_objects`, the large object options are passed as `--blobs` and
+  /// `--no-blobs`, the names pg_dump used before PostgreSQL 16.
+  fn to_command(&self, force_stdout: bool, legacy_large_objects: bool) -> Result<Command> {
     let mut builder = PgDumpBuilder::new();
     let config = &self.options.config;
 
     // Set required program directory
-    builder = builder.program_dir(&self.options.program_dir);
+    builder = builder.program_dir(native_path(&self.options.program_dir));
 
     // Set required connection parameters
     let connection = &self.options.connection;
@@ -295,7 +366,7 @@ impl PgDumpTool {
     if let Some(user) = &connection.username {
       builder = builder.username(user);
     }
-    if let Some(password) = &connection.password {
+    if let Some(password) = connection.password() {
       builder = builder.pg_password(password);
     }
     if let Some(dbname) = &connection.database {
@@ -338,6 +409,18 @@ impl PgDumpTool {
         builder = builder.no_privileges();
       }
     }
+    if !legacy_large_objects {
+      if let Some(blobs) = config.blobs {
+        if blobs {
+          builder = builder.large_objects();
+        }
+      }
+      if let Some(no_blobs) = config.no_blobs {
+        if no_blobs {
+          builder = builder.no_large_objects();
+        }
+      }
+    }
     if let Some(verbose) = config.verbose {
       if verbose {
         builder = builder.verbose();
@@ -375,28 +458,30 @@ impl PgDumpTool {
       }
     }
 
-    let command = builder.build();
+    let mut command = compose(builder, connection);
+    if legacy_large_objects {
+      if config.blobs == Some(true) {
+        command.arg("--blobs");
+      }
+      if config.no_blobs == Some(true) {
+        command.arg("--no-blobs");
+      }
+    }
     Ok(command)
   }
 
-  /// Executes the pg_dump command asynchronously and captures output.
-  /// This internal method handles the actual command execution and result processing.
-  async fn run_command(&self, command: Command) -> Result<ToolResult> {
-    let output = TokioCommand::from(command)
-      .stdout(Stdio::piped())
-      .stderr(Stdio::piped())
-      .output()
-      .await?;
-    ToolResult::from_output(
-      output,
-      self
-        .options
-        .config
-        .tool
-        .as_ref()
-        .and_then(|t| t.silent)
-        .unwrap_or(false),
-    )
+  /// Builds the pg_dump command for the installed pg_dump.
+  ///
+  /// Its version is only looked up when a large object option is set, as those were
+  /// renamed in PostgreSQL 16.
+  async fn versioned_command(&self, force_stdout: bool) -> Result<Command> {
+    let config = &self.options.config;
+    let legacy_large_objects = if config.blobs == Some(true) || config.no_blobs == Some(true) {
+      tool_major_version(&self.options.program_dir, "pg_dump").await? < 16
+    } else {
+      false
+    };
+    self.to_command(force_stdout, legacy_large_objects)
   }
 
   #[napi(js_name = "executeToString")]
@@ -427,8 +512,8 @@ impl PgDumpTool {
   /// }
   /// ```
   pub async fn execute_to_string(&self) -> Result<ToolResult> {
-    let command = self.to_command(true)?;
-    self.run_command(command).await
+    let command = self.versioned_command(true).await?;
+    self.run(command).await
   }
 
   #[napi]
@@ -453,7 +538,239 @@ impl PgDumpTool {
   /// }
   /// ```
   pub async fn execute(&self) -> Result<ToolResult> {
-    let command = self.to_command(false)?;
-    self.run_command(command).await
+    let transform = self.output_transform()?;
+    if let Some(file) = transform.file {
+      let command = self.versioned_command(true).await?;
+      return run_to_file(command, file, transform.stream, &self.tool_options()).await;
+    }
+
+    let command = self.versioned_command(false).await?;
+    self.run(command).await
+  }
+
+  #[napi]
+  /// Executes the pg_dump command and reports its verbose output as structured events.
+  ///
+  /// Verbose mode is enabled automatically. Every line pg_dump prints is parsed into a
+  /// `VerboseEvent` naming the action and the object (type, schema and name) it is
+  /// working on. The result is the same as with `execute()`.
+  ///
+  /// @param on_event - Callback invoked with a `VerboseEvent` for every line of output.
+  /// @returns Promise<ToolResult> containing exit code, stdout, and stderr
+  /// @throws Error if the command fails to execute or if there are configuration issues
+  ///
+  /// @example
+  /// ```typescript
+  /// await dumpTool.executeWithEvents((event) => {
+  ///   if (event.action === VerboseAction.Data) {
+  ///     console.log(`dumping table ${event.schema}.${event.name}`);
+  ///   }
+  /// });
+  /// ```
+  pub async fn execute_with_events(
+    &self,
+    on_event: ThreadsafeFunction<VerboseEvent, (), VerboseEvent, Status, false>,
+  ) -> Result<ToolResult> {
+    let transform = self.output_transform()?;
+    let mut command = self.versioned_command(transform.file.is_some()).await?;
+    if self.options.config.verbose != Some(true) {
+      command.arg("--verbose");
+    }
+    let on_line = move |line: &str| {
+      if let Some(event) = parse_verbose_line("pg_dump", line) {
+        on_event.call(event, ThreadsafeFunctionCallMode::NonBlocking);
+      }
+    };
+    match transform.file {
+      Some(file) => {
+        run_piped_to_file(
+          command,
+          file,
+          transform.stream,
+          on_line,
+          &self.tool_options(),
+        )
+        .await
+      }
+      None => run_piped(command, None, on_line, &self.tool_options()).await,
+    }
+  }
+
+  #[napi]
+  /// Executes the pg_dump command and reports progress while it runs.
+  ///
+  /// The total is the number of tables a schema-only dump with the same table and schema
+  /// selection contains, and pg_dump's verbose output is parsed to track the table whose
+  /// data is being dumped. Verbose mode is enabled automatically. Works with every format,
+  /// including parallel Directory dumps.
+  ///
+  /// @param on_progress - Callback invoked with a `DumpProgress` for every dumped table.
+  /// @returns Promise<ToolResult> containing exit code, stdout, and stderr
+  /// @throws Error if the tables cannot be counted or the command fails to execute
+  ///
+  /// @example
+  /// ```typescript
+  /// const result = await dumpTool.executeWithProgress((progress) => {
+  ///   console.log(`${progress.completed}/${progress.total} ${progress.currentTable ?? ''}`);
+  /// });
+  /// ```
+  pub async fn execute_with_progress(
+    &self,
+    on_progress: ThreadsafeFunction<DumpProgress, (), DumpProgress, Status, false>,
+  ) -> Result<ToolResult> {
+    let transform = self.output_transform()?;
+    // A dry run composes the command only, without connecting
+    let total = if self.tool_options().is_dry_run() {
+      0
+    } else {
+      self.count_dumped_tables().await?
+    };
+
+    let mut command = self.versioned_command(transform.file.is_some()).await?;
+    if self.options.config.verbose != Some(true) {
+      command.arg("--verbose");
+    }
+    let mut progress = DumpProgress {
+      total,
+      ..Default::default()
+    };
+    let on_progress = Arc::new(on_progress);
+    let callback = Arc::clone(&on_progress);
+    let on_line = move |line: &str| {
+      if progress.apply_verbose_line(line) {
+        callback.call(progress.clone(), ThreadsafeFunctionCallMode::NonBlocking);
+      }
+    };
+    let result = match transform.file {
+      Some(file) => {
+        run_piped_to_file(
+          command,
+          file,
+          transform.stream,
+          on_line,
+          &self.tool_options(),
+        )
+        .await?
+      }
+      None => run_piped(command, None, on_line, &self.tool_options()).await?,
+    };
+
+    if result.exit_code == 0 {
+      on_progress.call(
+        DumpProgress {
+          completed: total,
+          total,
+          current_table: None,
+        },
+        ThreadsafeFunctionCallMode::NonBlocking,
+      );
+    }
+    Ok(result)
+  }
+
+  /// Counts the tables whose data the dump contains, from a schema-only dump with the
+  /// same table and schema selection.
+  async fn count_dumped_tables(&self) -> Result<u32> {
+    let config = &self.options.config;
+    if config.schema_only == Some(true) {
+      return Ok(0);
+    }
+    let listing = PgDumpTool::from_connection(
+      self.options.connection.clone(),
+      self.options.program_dir.clone(),
+      PgDumpConfig {
+        tool: Some(ToolOptions {
+          timeout: self.tool_options().timeout,
+          silent: Some(true),
+          ..Default::default()
+        }),
+        schema_only: Some(true),
+        table: config.table.clone(),
+        exclude_table: config.exclude_table.clone(),
+        schema: config.schema.clone(),
+        exclude_schema: config.exclude_schema.clone(),
+        ..Default::default()
+      },
+    );
+    let result = PgTool::execute(&listing).await?;
+    if result.exit_code != 0 {
+      return Err(PgEmbedError::ToolError(format!(
+        "Failed to count the tables to dump: {}",
+        result.stderr.trim()
+      )));
+    }
+    Ok(count_data_tables(&result.stdout))
+  }
+
+  /// The stream transformation of the configuration and the file it writes to, if active.
+  fn output_transform(&self) -> Result<OutputTransform> {
+    let config = &self.options.config;
+    let stream = StreamTransform::new(
+      config.output_compression.clone(),
+      config.encryption_passphrase.clone(),
+    );
+    if !stream.is_active() {
+      return Ok(OutputTransform { stream, file: None });
+    }
+    let Some(file) = config.file.clone() else {
+      return Err(PgEmbedError::ConfigurationError(
+        "outputCompression and encryptionPassphrase require the file option".to_string(),
+      ));
+    };
+    if matches!(config.format, Some(PgDumpFormat::Directory)) {
+      return Err(PgEmbedError::ConfigurationError(
+        "outputCompression and encryptionPassphrase cannot be used with the Directory format"
+          .to_string(),
+      ));
+    }
+    Ok(OutputTransform {
+      stream,
+      file: Some(file),
+    })
+  }
+}
+
+impl PgTool for PgDumpTool {
+  fn tool_options(&self) -> ToolOptions {
+    self.options.config.tool.clone().unwrap_or_default()
+  }
+
+  fn compose(&self) -> Result<Command> {
+    self.to_command(false, false)
+  }
+}
+
+/// How the dump stream is written: by pg_dump itself, or through a transformation
+/// into `file` when one is active.
+struct OutputTransform {
+  stream: StreamTransform,
+  file: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_count_data_tables() {
+    let schema = "--\n-- PostgreSQL database dump\n--\n\n\
+      --\n-- Name: users; Type: TABLE; Schema: public; Owner: postgres\n--\n\n\
+      CREATE TABLE public.users (\n    id integer\n);\n\n\
+      --\n-- Name: events; Type: TABLE; Schema: public; Owner: postgres\n--\n\n\
+      CREATE TABLE public.events (\n    id integer\n)\nPARTITION BY RANGE (id);\n\n\
+      --\n-- Name: users_id_seq; Type: SEQUENCE; Schema: public; Owner: postgres\n--\n";
+    assert_eq!(count_data_tables(schema), 1);
+  }
+
+  #[test]
+  fn test_apply_verbose_line() {
+    let mut progress = DumpProgress {
+      total: 2,
+      ..Default::default()
+    };
+    assert!(!progress.apply_verbose_line("pg_dump: reading schemas"));
+    assert!(progress.apply_verbose_line("pg_dump: dumping contents of table \"public.users\""));
+    assert_eq!(progress.completed, 1);
+    assert_eq!(progress.current_table.as_deref(), Some("public.users"));
   }
 }
@@ -1,11 +1,12 @@
-use crate::error::Result;
-use crate::tools::common::{ConnectionConfig, ToolOptions, ToolResult};
+use crate::error::{PgEmbedError, Result};
+use crate::paths::native_path;
+use crate::tools::common::{compose, ConnectionConfig, ToolOptions, ToolResult};
+use crate::tools::stream::{run_to_file, StreamCompression, StreamTransform};
+use crate::tools::tool::PgTool;
 use napi_derive::napi;
 use postgresql_commands::pg_dumpall::PgDumpAllBuilder;
-use postgresql_commands::traits::CommandBuilder;
 use serde::Deserialize;
-use std::process::{Command, Stdio};
-use tokio::process::Command as TokioCommand;
+use std::process::Command;
 
 #[napi(object)]
 #[derive(Clone, Debug, Default, Deserialize)]
@@ -46,6 +47,14 @@ pub struct PgDumpallConfig {
   /// Corresponds to the `--no-privileges` command-line argument.
   #[napi(js_name = "noPrivileges")]
   pub no_privileges: Option<bool>,
+  /// Compress the dump stream inside pg-embedded before writing it to `file`.
+  /// Applied only when `file` is set.
+  #[napi(js_name = "outputCompression")]
+  pub output_compression: Option<StreamCompression>,
+  /// Encrypt the dump stream with this passphrase (age format, readable with `age -d`).
+  /// Applied only when `file` is set.
+  #[napi(js_name = "encryptionPassphrase")]
+  pub encryption_passphrase: Option<String>,
 }
 
 #[napi(object)]
@@ -169,7 +178,7 @@ impl PgDumpallTool {
   /// The dump content will be available in the `stdout` property of the result.
   pub async fn execute_to_string(&self) -> Result<ToolResult> {
     let command = to_command(&self.options, true)?;
-    run_command(command, &self.options).await
+    self.run(command).await
   }
 
   #[napi]
@@ -180,8 +189,47 @@ impl PgDumpallTool {
   ///
   /// @returns A promise that resolves with the result of the command execution.
   pub async fn execute(&self) -> Result<ToolResult> {
-    let command = to_command(&self.options, false)?;
-    run_command(command, &self.options).await
+    self.validate()?;
+    let transform = self.output_transform();
+    if let Some(file) = self
+      .options
+      .config
+      .file
+      .clone()
+      .filter(|_| transform.is_active())
+    {
+      let command = to_command(&self.options, true)?;
+      return run_to_file(command, file, transform, &self.tool_options()).await;
+    }
+
+    PgTool::execute(self).await
+  }
+
+  /// The stream transformation applied to the dump file
+  fn output_transform(&self) -> StreamTransform {
+    StreamTransform::new(
+      self.options.config.output_compression.clone(),
+      self.options.config.encryption_passphrase.clone(),
+    )
+  }
+}
+
+impl PgTool for PgDumpallTool {
+  fn tool_options(&self) -> ToolOptions {
+    self.options.config.tool.clone().unwrap_or_default()
+  }
+
+  fn validate(&self) -> Result<()> {
+    if self.output_transform().is_active() && self.options.config.file.is_none() {
+      return Err(PgEmbedError::ConfigurationError(
+        "outputCompression and encryptionPassphrase require the file option".to_string(),
+      ));
+    }
+    Ok(())
+  }
+
+  fn compose(&self) -> Result<Command> {
+    to_command(&self.options, false)
   }
 }
 
@@ -189,7 +237,7 @@ fn to_command(options: &PgDumpallOptions, force_stdout: bool) -> Result<Command>
   let mut builder = PgDumpAllBuilder::new();
   let config = &options.config;
 
-  builder = builder.program_dir(&options.program_dir);
+  builder = builder.program_dir(native_path(&options.program_dir));
 
   let connection = &options.connection;
   if let Some(host) = &connection.host {
@@ -201,7 +249,7 @@ fn to_command(options: &PgDumpallOptions, force_stdout: bool) -> Result<Command>
   if let Some(user) = &connection.username {
     builder = builder.username(user);
   }
-  if let Some(password) = &connection.password {
+  if let Some(password) = connection.password() {
     builder = builder.pg_password(password);
   }
 
@@ -246,23 +294,5 @@ fn to_command(options: &PgDumpallOptions, force_stdout: bool) -> Result<Command>
     }
   }
 
-  let command = builder.build();
-  Ok(command)
-}
-
-async fn run_command(command: Command, options: &PgDumpallOptions) -> Result<ToolResult> {
-  let output = TokioCommand::from(command)
-    .stdout(Stdio::piped())
-    .stderr(Stdio::piped())
-    .output()
-    .await?;
-  ToolResult::from_output(
-    output,
-    options
-      .config
-      .tool
-      .as_ref()
-      .and_then(|t| t.silent)
-      .unwrap_or(false),
-  )
+  Ok(compose(builder, connection))
 }
@@ -1,11 +1,12 @@
 use crate::error::Result;
-use crate::tools::common::{ConnectionConfig, ToolOptions, ToolResult};
+use crate::paths::native_path;
+use crate::tools::common::{check_executable, compose, ConnectionConfig, ToolOptions, ToolResult};
+use crate::tools::hooks::capture_output;
+use crate::tools::tool::PgTool;
 use napi_derive::napi;
 use postgresql_commands::pg_isready::PgIsReadyBuilder;
-use postgresql_commands::traits::CommandBuilder;
 use serde::Deserialize;
-use std::process::{Command, Stdio};
-use tokio::process::Command as TokioCommand;
+use std::process::Command;
 
 #[napi(object)]
 #[derive(Clone, Debug, Default, Deserialize)]
@@ -165,29 +166,30 @@ impl PgIsReadyTool {
   /// Performs a quick check to see if the server is running.
   #[napi]
   pub async fn check(&self) -> Result<bool> {
-    let command = self.to_command()?;
-    let output = TokioCommand::from(command).output().await?;
+    let command = self.compose()?;
+    check_executable(&command)?;
+    let output = capture_output(command, self.tool_options().time_limit()).await?;
     Ok(output.status.success())
   }
 
   /// Executes `pg_isready` and returns the detailed result.
   #[napi]
   pub async fn execute(&self) -> Result<ToolResult> {
-    let command = self.to_command()?;
-    let output = TokioCommand::from(command)
-      .stdout(Stdio::piped())
-      .stderr(Stdio::piped())
-      .output()
-      .await?;
-    ToolResult::from_output(output, self.options.config.silent.unwrap_or(false))
+    PgTool::execute(self).await
+  }
+}
+
+impl PgTool for PgIsReadyTool {
+  fn tool_options(&self) -> ToolOptions {
+    self.options.config.tool.clone().unwrap_or_default()
   }
 
-  fn to_command(&self) -> Result<Command> {
+  fn compose(&self) -> Result<Command> {
     let mut builder = PgIsReadyBuilder::new();
     let config = &self.options.config;
 
     // Set required program directory
-    builder = builder.program_dir(&self.options.program_dir);
+    builder = builder.program_dir(native_path(&self.options.program_dir));
 
     // Set required connection parameters
     let connection = &self.options.connection;
@@ -211,6 +213,6 @@ impl PgIsReadyTool {
     } else if let Some(dbname) = &connection.database {
       builder = builder.dbname(dbname);
     }
-    Ok(builder.build())
+    Ok(compose(builder, connection))
   }
 }
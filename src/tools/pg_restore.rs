@@ -1,12 +1,20 @@
-use crate::error::Result;
-use crate::tools::common::{ConnectionConfig, ToolOptions, ToolResult};
+use crate::error::{PgEmbedError, Result};
+use crate::paths::native_path;
+use crate::tools::common::{
+  check_executable, command_env, command_line, compose, ConnectionConfig, ToolOptions, ToolResult,
+};
+use crate::tools::hooks::capture_output;
+use crate::tools::stream::{run_from_file, run_piped, StreamCompression, StreamTransform};
+use crate::tools::tool::PgTool;
+use crate::tools::verbose::{parse_verbose_line, VerboseEvent};
 
+use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi::Status;
 use napi_derive::napi;
 use postgresql_commands::pg_restore::PgRestoreBuilder;
-use postgresql_commands::traits::CommandBuilder;
 use serde::Deserialize;
 use std::process::Command;
-use tokio::process::Command as TokioCommand;
+use std::sync::Arc;
 
 #[napi]
 #[derive(Clone, Debug, Deserialize)]
@@ -83,6 +91,14 @@ pub struct PgRestoreConfig {
   /// Equivalent to the pg_restore --if-exists flag.
   #[napi(js_name = "ifExists")]
   pub if_exists: Option<bool>,
+  /// Compression that pg-embedded applied to the archive (see `outputCompression` of pg_dump).
+  /// The archive is decompressed while it is streamed into pg_restore.
+  #[napi(js_name = "inputCompression")]
+  pub input_compression: Option<StreamCompression>,
+  /// Passphrase the archive was encrypted with (see `encryptionPassphrase` of pg_dump).
+  /// The archive is decrypted while it is streamed into pg_restore.
+  #[napi(js_name = "encryptionPassphrase")]
+  pub encryption_passphrase: Option<String>,
 }
 
 /// Complete options for the `pg_restore` tool.
@@ -120,6 +136,109 @@ pub struct PgRestoreOptions {
   pub config: PgRestoreConfig,
 }
 
+#[napi(object)]
+#[derive(Clone, Debug, Default, PartialEq)]
+/// Progress of a running restore, reported by `PgRestoreTool.executeWithProgress()`.
+pub struct RestoreProgress {
+  /// Number of archive entries restored so far.
+  pub completed: u32,
+  /// Total number of entries in the archive's table of contents.
+  pub total: u32,
+  /// The entry pg_restore is currently working on (e.g. `creating TABLE "public.users"`).
+  #[napi(js_name = "currentObject")]
+  pub current_object: Option<String>,
+  /// The table whose data is currently being restored.
+  #[napi(js_name = "currentTable")]
+  pub current_table: Option<String>,
+}
+
+impl RestoreProgress {
+  /// Update the progress from one line of `pg_restore --verbose` output.
+  ///
+  /// Returns true if the line marks the start of a new archive entry.
+  fn apply_verbose_line(&mut self, line: &str) -> bool {
+    let Some(message) = line.strip_prefix("pg_restore: ") else {
+      return false;
+    };
+    if let Some(table) = message.strip_prefix("processing data for table ") {
+      self.current_table = Some(table.trim_matches('"').to_string());
+    } else if !(message.starts_with("creating ") || message.starts_with("executing ")) {
+      return false;
+    }
+    // Some entries print several lines, so never report more than the archive holds
+    self.completed = (self.completed + 1).min(self.total);
+    self.current_object = Some(message.to_string());
+    true
+  }
+}
+
+/// Count the entries of a `pg_restore --list` table of contents.
+fn count_toc_entries(listing: &str) -> u32 {
+  listing
+    .lines()
+    .filter(|line| {
+      let line = line.trim();
+      !line.is_empty() && !line.starts_with(';')
+    })
+    .count() as u32
+}
+
+/// Archive entry types whose restored objects are counted by
+/// `restoreIntoNewDatabase()`, with the pg_class relkinds they create.
+pub(crate) const VERIFIED_OBJECT_TYPES: [(&str, &str); 4] = [
+  ("TABLE", "'r', 'p'"),
+  ("VIEW", "'v'"),
+  ("MATERIALIZED VIEW", "'m'"),
+  ("SEQUENCE", "'S'"),
+];
+
+/// Count the entries of `object_type` in a `pg_restore --list` table of contents.
+///
+/// Entries that only carry data or settings of such an object (`TABLE DATA`,
+/// `SEQUENCE SET`, `SEQUENCE OWNED BY`, ...) are not counted.
+pub(crate) fn count_toc_objects(listing: &str, object_type: &str) -> u32 {
+  listing
+    .lines()
+    .filter(|line| !line.trim_start().starts_with(';'))
+    .filter_map(|line| {
+      // `<id>; <catalog oid> <object oid> <type> <schema> <name> <owner>`
+      let (_, entry) = line.split_once("; ")?;
+      let mut fields = entry.splitn(3, ' ');
+      fields.next()?;
+      fields.next()?;
+      fields.next()
+    })
+    .filter(|entry| {
+      entry
+        .strip_prefix(object_type)
+        .and_then(|rest| rest.strip_prefix(' '))
+        .is_some_and(|rest| {
+          !matches!(
+            rest.split(' ').next(),
+            Some("DATA" | "SET" | "OWNED" | "ATTACH")
+          )
+        })
+    })
+    .count() as u32
+}
+
+/// Query counting the objects of `VERIFIED_OBJECT_TYPES` in the current database, in
+/// the same order; objects belonging to extensions are left out like in archives.
+pub(crate) fn object_counts_sql() -> String {
+  let counts: Vec<String> = VERIFIED_OBJECT_TYPES
+    .iter()
+    .map(|(_, relkinds)| format!("count(*) FILTER (WHERE c.relkind IN ({relkinds}))"))
+    .collect();
+  format!(
+    "SELECT {} FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace \
+     WHERE n.nspname NOT IN ('pg_catalog', 'information_schema') \
+     AND n.nspname NOT LIKE 'pg_toast%' \
+     AND NOT EXISTS (SELECT 1 FROM pg_depend d WHERE d.classid = 'pg_class'::regclass \
+     AND d.objid = c.oid AND d.deptype = 'e')",
+    counts.join(", ")
+  )
+}
+
 /// A tool for restoring a PostgreSQL database from an archive created by `pg_dump`.
 #[napi]
 pub struct PgRestoreTool {
@@ -175,13 +294,13 @@ impl PgRestoreTool {
     Self { options }
   }
 
-  fn to_command(&self) -> Result<Command> {
+  fn to_command(&self, from_stdin: bool) -> Result<Command> {
     let mut builder = PgRestoreBuilder::new();
     let options = &self.options;
     let config = &options.config;
 
     // Set program directory
-    builder = builder.program_dir(&options.program_dir);
+    builder = builder.program_dir(native_path(&options.program_dir));
 
     // Don't use builder.file() as it conflicts with --dbname
     // Instead, we'll add the file as a positional argument later
@@ -256,7 +375,7 @@ impl PgRestoreTool {
       }
     }
 
-    let mut command = builder.build();
+    let mut command = compose(builder, &options.connection);
 
     if let Some(host) = &options.connection.host {
       command.arg("--host").arg(host);
@@ -267,7 +386,7 @@ impl PgRestoreTool {
     if let Some(username) = &options.connection.username {
       command.arg("--username").arg(username);
     }
-    if let Some(password) = &options.connection.password {
+    if let Some(password) = options.connection.password() {
       command.env("PGPASSWORD", password);
     }
     if let Some(database) = &options.connection.database {
@@ -275,23 +394,57 @@ impl PgRestoreTool {
     }
 
     // Add the file as a positional argument (not as --file option)
-    command.arg(&config.file);
+    if !from_stdin {
+      command.arg(&config.file);
+    }
 
     Ok(command)
   }
 
-  async fn run_command(&self, command: Command) -> Result<ToolResult> {
-    let output = TokioCommand::from(command).output().await?;
-    ToolResult::from_output(
-      output,
-      self
-        .options
-        .config
-        .tool
-        .as_ref()
-        .and_then(|t| t.silent)
-        .unwrap_or(false),
-    )
+  /// Reads the archive's table of contents and returns the number of entries.
+  async fn count_archive_entries(&self, transform: &StreamTransform) -> Result<u32> {
+    Ok(count_toc_entries(&self.list_archive(transform).await?))
+  }
+
+  /// Reads the archive's table of contents (`pg_restore --list`).
+  pub(crate) async fn list_archive(&self, transform: &StreamTransform) -> Result<String> {
+    let config = &self.options.config;
+    let mut builder = PgRestoreBuilder::new()
+      .program_dir(native_path(&self.options.program_dir))
+      .list();
+    if let Some(format) = &config.format {
+      builder = builder.format(format.to_pg_restore_format());
+    }
+    let mut command = compose(builder, &self.options.connection);
+    check_executable(&command)?;
+    let time_limit = self.tool_options().time_limit();
+
+    let result = if transform.is_active() {
+      let input = Some((config.file.clone(), transform.clone()));
+      let options = ToolOptions {
+        silent: Some(true),
+        timeout: config.tool.as_ref().and_then(|tool| tool.timeout),
+        ..Default::default()
+      };
+      run_piped(command, input, |_| {}, &options).await?
+    } else {
+      command.arg(&config.file);
+      let command_line = command_line(&command);
+      let environment = command_env(&command);
+      ToolResult::from_output(
+        command_line,
+        environment,
+        capture_output(command, time_limit).await?,
+        true,
+      )?
+    };
+    if result.exit_code != 0 {
+      return Err(PgEmbedError::ToolError(format!(
+        "Failed to read archive contents: {}",
+        result.stderr.trim()
+      )));
+    }
+    Ok(result.stdout)
   }
 
   /// Executes the pg_restore command with the configured options.
@@ -331,7 +484,217 @@ impl PgRestoreTool {
   /// ```
   #[napi]
   pub async fn execute(&self) -> Result<ToolResult> {
-    let command = self.to_command()?;
-    self.run_command(command).await
+    let config = &self.options.config;
+    let transform = StreamTransform::new(
+      config.input_compression.clone(),
+      config.encryption_passphrase.clone(),
+    );
+    if transform.is_active() {
+      if matches!(config.format, Some(PgRestoreFormat::Directory)) {
+        return Err(PgEmbedError::ConfigurationError(
+          "inputCompression and encryptionPassphrase cannot be used with the Directory format"
+            .to_string(),
+        ));
+      }
+      let command = self.to_command(true)?;
+      return run_from_file(
+        command,
+        config.file.clone(),
+        transform,
+        &self.tool_options(),
+      )
+      .await;
+    }
+
+    PgTool::execute(self).await
+  }
+
+  /// Executes the pg_restore command and reports progress while it runs.
+  ///
+  /// The total is taken from the archive's table of contents (`pg_restore --list`),
+  /// and pg_restore's verbose output is parsed to track completed entries and the
+  /// table currently being loaded. Verbose mode is enabled automatically.
+  ///
+  /// @param on_progress - Callback invoked with a `RestoreProgress` for every restored entry.
+  /// @returns {Promise<ToolResult>} A promise that resolves with the result of the command.
+  /// @throws {Error} If the archive cannot be read or the command fails to execute.
+  ///
+  /// @example
+  /// ```typescript
+  /// const result = await restoreTool.executeWithProgress((progress) => {
+  ///   console.log(`${progress.completed}/${progress.total} ${progress.currentTable ?? ''}`);
+  /// });
+  /// ```
+  #[napi]
+  pub async fn execute_with_progress(
+    &self,
+    on_progress: ThreadsafeFunction<RestoreProgress, (), RestoreProgress, Status, false>,
+  ) -> Result<ToolResult> {
+    let config = &self.options.config;
+    if matches!(config.format, Some(PgRestoreFormat::Directory))
+      && (config.input_compression.is_some() || config.encryption_passphrase.is_some())
+    {
+      return Err(PgEmbedError::ConfigurationError(
+        "inputCompression and encryptionPassphrase cannot be used with the Directory format"
+          .to_string(),
+      ));
+    }
+    let transform = StreamTransform::new(
+      config.input_compression.clone(),
+      config.encryption_passphrase.clone(),
+    );
+    // A dry run composes the command only, without reading the archive
+    let total = if self.tool_options().is_dry_run() {
+      0
+    } else {
+      self.count_archive_entries(&transform).await?
+    };
+
+    let mut command = self.to_command(transform.is_active())?;
+    if config.verbose != Some(true) {
+      command.arg("--verbose");
+    }
+    let input = transform
+      .is_active()
+      .then(|| (config.file.clone(), transform.clone()));
+
+    let mut progress = RestoreProgress {
+      total,
+      ..Default::default()
+    };
+    let on_progress = Arc::new(on_progress);
+    let callback = Arc::clone(&on_progress);
+    let result = run_piped(
+      command,
+      input,
+      move |line| {
+        if progress.apply_verbose_line(line) {
+          callback.call(progress.clone(), ThreadsafeFunctionCallMode::NonBlocking);
+        }
+      },
+      &self.tool_options(),
+    )
+    .await?;
+
+    if result.exit_code == 0 {
+      on_progress.call(
+        RestoreProgress {
+          completed: total,
+          total,
+          current_object: None,
+          current_table: None,
+        },
+        ThreadsafeFunctionCallMode::NonBlocking,
+      );
+    }
+    Ok(result)
+  }
+
+  /// Executes the pg_restore command and reports its verbose output as structured events.
+  ///
+  /// Verbose mode is enabled automatically. Every line pg_restore prints is parsed into a
+  /// `VerboseEvent` naming the action and the object (type, schema and name) it is
+  /// working on, e.g. `Data` for table `public.orders` while its rows are loaded.
+  ///
+  /// @param on_event - Callback invoked with a `VerboseEvent` for every line of output.
+  /// @returns {Promise<ToolResult>} A promise that resolves with the result of the command.
+  /// @throws {Error} If the command fails to execute or if there are configuration issues.
+  ///
+  /// @example
+  /// ```typescript
+  /// await restoreTool.executeWithEvents((event) => {
+  ///   if (event.action === VerboseAction.Data) {
+  ///     console.log(`restoring table ${event.schema}.${event.name}`);
+  ///   }
+  /// });
+  /// ```
+  #[napi]
+  pub async fn execute_with_events(
+    &self,
+    on_event: ThreadsafeFunction<VerboseEvent, (), VerboseEvent, Status, false>,
+  ) -> Result<ToolResult> {
+    let config = &self.options.config;
+    let transform = StreamTransform::new(
+      config.input_compression.clone(),
+      config.encryption_passphrase.clone(),
+    );
+    if transform.is_active() && matches!(config.format, Some(PgRestoreFormat::Directory)) {
+      return Err(PgEmbedError::ConfigurationError(
+        "inputCompression and encryptionPassphrase cannot be used with the Directory format"
+          .to_string(),
+      ));
+    }
+
+    let mut command = self.to_command(transform.is_active())?;
+    if config.verbose != Some(true) {
+      command.arg("--verbose");
+    }
+    let input = transform
+      .is_active()
+      .then(|| (config.file.clone(), transform.clone()));
+    run_piped(
+      command,
+      input,
+      move |line| {
+        if let Some(event) = parse_verbose_line("pg_restore", line) {
+          on_event.call(event, ThreadsafeFunctionCallMode::NonBlocking);
+        }
+      },
+      &self.tool_options(),
+    )
+    .await
+  }
+}
+
+impl PgTool for PgRestoreTool {
+  fn tool_options(&self) -> ToolOptions {
+    self.options.config.tool.clone().unwrap_or_default()
+  }
+
+  fn compose(&self) -> Result<Command> {
+    self.to_command(false)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_count_toc_entries() {
+    let listing = ";\n; Archive created at 2025-01-01\n;\n215; 1259 16385 TABLE public users postgres\n3320; 0 16385 TABLE DATA public users postgres\n";
+    assert_eq!(count_toc_entries(listing), 2);
+  }
+
+  #[test]
+  fn test_count_toc_objects() {
+    let listing = ";\n; Archive created at 2025-01-01\n;\n\
+                   215; 1259 16385 TABLE public users postgres\n\
+                   216; 1259 16390 SEQUENCE public users_id_seq postgres\n\
+                   217; 0 0 SEQUENCE OWNED BY public users_id_seq postgres\n\
+                   218; 1259 16395 MATERIALIZED VIEW public user_stats postgres\n\
+                   3320; 0 16385 TABLE DATA public users postgres\n\
+                   3321; 0 0 SEQUENCE SET public users_id_seq postgres\n";
+    assert_eq!(count_toc_objects(listing, "TABLE"), 1);
+    assert_eq!(count_toc_objects(listing, "SEQUENCE"), 1);
+    assert_eq!(count_toc_objects(listing, "VIEW"), 0);
+    assert_eq!(count_toc_objects(listing, "MATERIALIZED VIEW"), 1);
+  }
+
+  #[test]
+  fn test_apply_verbose_line() {
+    let mut progress = RestoreProgress {
+      total: 3,
+      ..Default::default()
+    };
+    assert!(!progress.apply_verbose_line("pg_restore: connecting to database for restore"));
+    assert!(progress.apply_verbose_line("pg_restore: creating TABLE \"public.users\""));
+    assert!(progress.apply_verbose_line("pg_restore: processing data for table \"public.users\""));
+    assert_eq!(progress.completed, 2);
+    assert_eq!(progress.current_table.as_deref(), Some("public.users"));
+    assert_eq!(
+      progress.current_object.as_deref(),
+      Some("processing data for table \"public.users\"")
+    );
   }
 }
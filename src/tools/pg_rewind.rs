@@ -1,12 +1,15 @@
+use crate::archive::{absolute_archive_dir, archive_command_for, restore_command_for};
+use crate::conf::managed_conf;
 use crate::error::Result;
-use crate::tools::common::{ConnectionConfig, ToolOptions, ToolResult};
+use crate::logger::pg_log;
+use crate::paths::{extended_path, native_path};
+use crate::tools::common::{compose, ConnectionConfig, ToolOptions, ToolResult};
+use crate::tools::compat::{check_option_support, OptionRequirement};
+use crate::tools::tool::PgTool;
 use napi_derive::napi;
 use postgresql_commands::pg_rewind::PgRewindBuilder;
-use postgresql_commands::traits::CommandBuilder;
 use serde::Deserialize;
 use std::process::Command;
-use std::process::Stdio;
-use tokio::process::Command as TokioCommand;
 
 #[napi(object)]
 #[derive(Clone, Debug, Default, Deserialize)]
@@ -293,13 +296,22 @@ impl PgRewindTool {
   /// }
   /// ```
   pub async fn execute(&self) -> Result<ToolResult> {
-    // Auto-configure WAL settings if requested
-    if self.options.config.auto_configure_wal.unwrap_or(false) {
+    if self.options.config.restore_target_wal == Some(true) {
+      check_option_support(
+        &self.options.program_dir,
+        "pg_rewind",
+        &[OptionRequirement::new("--restore-target-wal", 13)],
+      )
+      .await?;
+    }
+
+    // Auto-configure WAL settings if requested; a dry run leaves the target untouched
+    let dry_run = self.tool_options().is_dry_run();
+    if self.options.config.auto_configure_wal.unwrap_or(false) && !dry_run {
       self.auto_configure_wal_settings().await?;
     }
 
-    let command = to_command(&self.options)?;
-    run_command(command, &self.options).await
+    PgTool::execute(self).await
   }
 
   /// Automatically configures all WAL-related PostgreSQL settings required for pg_rewind.
@@ -331,7 +343,7 @@ impl PgRewindTool {
     use std::fs;
     use std::path::Path;
 
-    println!("[DEBUG] Starting auto_configure_wal_settings");
+    pg_log!(debug, "Starting auto_configure_wal_settings");
 
     // Create WAL archive directory if not specified
     let archive_dir = if let Some(dir) = &self.options.config.wal_archive_dir {
@@ -343,64 +355,79 @@ impl PgRewindTool {
       parent.join("wal_archive").to_string_lossy().to_string()
     };
 
-    println!("[DEBUG] Archive directory: {archive_dir}");
+    pg_log!(debug, "Archive directory: {archive_dir}");
 
     // Create archive directory
-    fs::create_dir_all(&archive_dir).map_err(|e| {
+    fs::create_dir_all(extended_path(&archive_dir)).map_err(|e| {
       crate::error::PgEmbedError::InternalError(format!(
         "Failed to create WAL archive directory: {e}",
       ))
     })?;
+    let archive_dir = absolute_archive_dir(Path::new(&archive_dir))?;
 
     // Configure target PostgreSQL instance
     let config_path = Path::new(&self.options.config.target_pgdata).join("postgresql.conf");
 
-    println!("[DEBUG] Config path: {config_path:?}");
+    pg_log!(debug, "Config path: {config_path:?}");
 
     if config_path.exists() {
-      println!("[DEBUG] Config file exists, reading...");
-      let mut config_content = fs::read_to_string(&config_path).map_err(|e| {
-        crate::error::PgEmbedError::InternalError(format!("Failed to read postgresql.conf: {e}"))
-      })?;
-
-      // Add required configurations for pg_rewind
-      let additional_config = format!(
-        "\n# Auto-configured for pg_rewind\n\
-         wal_log_hints = on\n\
-         archive_mode = on\n\
-         archive_command = 'cp \"%p\" \"{archive_dir}//%f\"'\n\
-         restore_command = 'cp \"{archive_dir}//%f\" \"%p\"'\n\
-         wal_level = replica\n\
-         max_wal_senders = 3\n",
-      );
-
-      println!("[DEBUG] Adding configuration:\n{additional_config}");
-
-      config_content.push_str(&additional_config);
-
-      fs::write(&config_path, config_content).map_err(|e| {
-        crate::error::PgEmbedError::InternalError(format!("Failed to write postgresql.conf: {e}"))
-      })?;
-
-      println!("[DEBUG] Configuration written successfully");
+      pg_log!(debug, "Config file exists, reading...");
+      let mut conf = managed_conf(Path::new(&self.options.config.target_pgdata))?;
+
+      // Set required configurations for pg_rewind; existing assignments are replaced,
+      // so repeated runs do not pile up duplicates
+      let settings = [
+        ("wal_log_hints", "on".to_string()),
+        ("archive_mode", "on".to_string()),
+        (
+          "archive_command",
+          archive_command_for(&archive_dir, cfg!(windows)),
+        ),
+        (
+          "restore_command",
+          restore_command_for(&archive_dir, cfg!(windows)),
+        ),
+        ("wal_level", "replica".to_string()),
+        ("max_wal_senders", "3".to_string()),
+      ];
+      for (name, value) in &settings {
+        pg_log!(debug, "Setting {name} = {value}");
+        conf.set(name, value);
+      }
+      conf.save()?;
+
+      pg_log!(debug, "Configuration written successfully");
 
       // Try to reload configuration if possible
       // For pg_rewind, we need the target server to have loaded these settings at some point
       // Since the target is typically stopped, we'll add a note about this requirement
-      println!("[DEBUG] Note: Target server must be restarted to load WAL configuration before using pg_rewind");
+      pg_log!(
+        debug,
+        "Note: Target server must be restarted to load WAL configuration before using pg_rewind"
+      );
     } else {
-      println!("[DEBUG] Config file does not exist!");
+      pg_log!(debug, "Config file does not exist");
     }
 
     Ok(())
   }
 }
 
+impl PgTool for PgRewindTool {
+  fn tool_options(&self) -> ToolOptions {
+    self.options.config.tool.clone().unwrap_or_default()
+  }
+
+  fn compose(&self) -> Result<Command> {
+    to_command(&self.options)
+  }
+}
+
 fn to_command(options: &PgRewindOptions) -> Result<Command> {
   let mut builder = PgRewindBuilder::new();
   let config = &options.config;
 
-  builder = builder.program_dir(&options.program_dir);
+  builder = builder.program_dir(native_path(&options.program_dir));
 
   builder = builder.target_pgdata(&config.target_pgdata);
 
@@ -422,7 +449,7 @@ fn to_command(options: &PgRewindOptions) -> Result<Command> {
     if let Some(username) = &source_instance.username {
       conn_str.push_str(&format!("user={username} "));
     }
-    if let Some(password) = &source_instance.password {
+    if let Some(password) = source_instance.password() {
       conn_str.push_str(&format!("password={password} "));
     }
     if let Some(database) = &source_instance.database {
@@ -443,7 +470,7 @@ fn to_command(options: &PgRewindOptions) -> Result<Command> {
     if let Some(username) = &options.connection.username {
       conn_str.push_str(&format!("user={username} "));
     }
-    if let Some(password) = &options.connection.password {
+    if let Some(password) = options.connection.password() {
       conn_str.push_str(&format!("password={password} "));
     }
     if !conn_str.is_empty() {
@@ -475,23 +502,12 @@ fn to_command(options: &PgRewindOptions) -> Result<Command> {
     }
   }
 
-  let command = builder.build();
-  Ok(command)
-}
-
-async fn run_command(command: Command, options: &PgRewindOptions) -> Result<ToolResult> {
-  let output = TokioCommand::from(command)
-    .stdout(Stdio::piped())
-    .stderr(Stdio::piped())
-    .output()
-    .await?;
-  ToolResult::from_output(
-    output,
-    options
-      .config
-      .tool
+  // libpq settings of the source server apply to the connection pg_rewind opens
+  Ok(compose(
+    builder,
+    config
+      .source_instance
       .as_ref()
-      .and_then(|t| t.silent)
-      .unwrap_or(false),
-  )
+      .unwrap_or(&options.connection),
+  ))
 }
@@ -0,0 +1,622 @@
+//! Pipelines of tool processes connected with pipes
+//!
+//! Each stage reads the output of the stage before it, so a dump can be filtered and
+//! restored without a temporary file. Pipes have a bounded buffer: a slow stage blocks the
+//! stages writing into it instead of letting their output pile up in memory.
+
+use crate::error::{PgEmbedError, Result};
+use crate::paths::native_path;
+use crate::tools::common::{
+  check_executable, command_line, time_limit, timed_out_error, ConnectionConfig,
+};
+use crate::tools::hooks::before_execute;
+use crate::tools::stream::Watchdog;
+use napi_derive::napi;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::process::{ChildStdout, Command, ExitStatus, Stdio};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Signal number of SIGPIPE, which ends a tool whose reader exited early
+#[cfg(unix)]
+const SIGPIPE: i32 = 13;
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+/// A literal text replacement applied by a pipeline filter.
+pub struct TextReplacement {
+  /// Text to search for.
+  pub from: String,
+  /// Text to replace every occurrence with.
+  pub to: String,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+/// A line-based transformation of the stream between two pipeline stages, similar to `sed`.
+///
+/// Lines are processed one at a time, so filters work on streams of any size.
+pub struct PipelineFilter {
+  /// Replacements applied to every line, in order.
+  pub replace: Option<Vec<TextReplacement>>,
+  /// Lines containing any of these strings are dropped, before replacements are applied.
+  #[napi(js_name = "dropLinesContaining")]
+  pub drop_lines_containing: Option<Vec<String>>,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+/// A stage of a tool pipeline: either a tool process or a filter.
+pub struct PipelineStage {
+  /// Name of the stage in the result (defaults to the tool name, or `filter`).
+  pub name: Option<String>,
+  /// The tool to run, e.g. `pg_dump` or `psql`.
+  pub tool: Option<String>,
+  /// Directory containing the tool executable (defaults to a lookup in PATH).
+  #[napi(js_name = "programDir")]
+  pub program_dir: Option<String>,
+  /// Command-line arguments of the tool.
+  pub args: Option<Vec<String>>,
+  /// Connection of the tool, passed as libpq environment variables (PGHOST, PGPORT, ...).
+  pub connection: Option<ConnectionConfig>,
+  /// Transformation applied instead of running a tool.
+  pub filter: Option<PipelineFilter>,
+  /// Timeout of the tool in seconds. A tool still running after this time is killed, and
+  /// the pipeline fails with the stderr it wrote so far.
+  pub timeout: Option<u32>,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug)]
+/// The outcome of one pipeline stage.
+pub struct PipelineStageResult {
+  /// Name of the stage.
+  pub name: String,
+  /// The exit code of the tool; for filters 0 on success and 1 on failure.
+  pub exit_code: i32,
+  /// The standard error of the tool, or the error of a filter.
+  pub stderr: String,
+  /// The executed command line with credentials redacted; empty for filters.
+  pub command: Vec<String>,
+  /// Whether the stage failed only because a later stage stopped reading its output.
+  pub interrupted: bool,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug)]
+/// The outcome of a tool pipeline.
+pub struct PipelineResult {
+  /// Whether every stage succeeded.
+  pub success: bool,
+  /// Name of the stage that caused the pipeline to fail, if any.
+  pub failed_stage: Option<String>,
+  /// The standard output of the last stage.
+  pub stdout: String,
+  /// The outcome of every stage, in pipeline order.
+  pub stages: Vec<PipelineStageResult>,
+}
+
+/// Options for `pipeDatabaseTo()`
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct PipeDatabaseOptions {
+  /// Database to dump (defaults to the source instance's databaseName)
+  pub database: Option<String>,
+  /// Existing database to load the dump into (defaults to the target instance's databaseName)
+  pub target_database: Option<String>,
+  /// Additional pg_dump arguments, e.g. `['--schema-only']`
+  pub dump_args: Option<Vec<String>>,
+  /// Transformation applied to the SQL between pg_dump and psql
+  pub filter: Option<PipelineFilter>,
+  /// Timeout of pg_dump and psql in seconds, see `PipelineStage.timeout`
+  pub timeout: Option<u32>,
+}
+
+/// A pipeline stage ready to run
+enum Stage {
+  Tool {
+    name: String,
+    command: Command,
+    time_limit: Option<Duration>,
+  },
+  Filter {
+    name: String,
+    filter: PipelineFilter,
+  },
+}
+
+/// A stage while the pipeline runs
+enum Running {
+  Tool {
+    name: String,
+    command: Vec<String>,
+    watchdog: Watchdog,
+    time_limit: Option<Duration>,
+    stderr: JoinHandle<Vec<u8>>,
+  },
+  Filter {
+    name: String,
+    worker: JoinHandle<io::Result<()>>,
+  },
+}
+
+impl PipelineFilter {
+  /// Apply the filter to one line; `None` drops the line
+  fn apply(&self, line: &[u8]) -> Option<Vec<u8>> {
+    let dropped = self
+      .drop_lines_containing
+      .iter()
+      .flatten()
+      .any(|needle| find(line, needle.as_bytes()).is_some());
+    if dropped {
+      return None;
+    }
+    let mut line = line.to_vec();
+    for replacement in self.replace.iter().flatten() {
+      line = replace_all(
+        &line,
+        replacement.from.as_bytes(),
+        replacement.to.as_bytes(),
+      );
+    }
+    Some(line)
+  }
+
+  /// Copy `reader` to `writer` line by line, applying the filter
+  fn run<R: Read, W: Write>(&self, reader: R, writer: W) -> io::Result<()> {
+    let mut reader = BufReader::new(reader);
+    let mut writer = BufWriter::new(writer);
+    let mut line = Vec::new();
+    while reader.read_until(b'\n', &mut line)? > 0 {
+      if let Some(filtered) = self.apply(&line) {
+        writer.write_all(&filtered)?;
+      }
+      line.clear();
+    }
+    writer.flush()
+  }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+  if needle.is_empty() {
+    return None;
+  }
+  haystack
+    .windows(needle.len())
+    .position(|window| window == needle)
+}
+
+fn replace_all(haystack: &[u8], from: &[u8], to: &[u8]) -> Vec<u8> {
+  let mut replaced = Vec::with_capacity(haystack.len());
+  let mut rest = haystack;
+  while let Some(position) = find(rest, from) {
+    replaced.extend_from_slice(&rest[..position]);
+    replaced.extend_from_slice(to);
+    rest = &rest[position + from.len()..];
+  }
+  replaced.extend_from_slice(rest);
+  replaced
+}
+
+impl PipelineStage {
+  /// Validate the stage and build the command or filter it runs
+  fn prepare(self, index: usize) -> Result<Stage> {
+    match (self.tool, self.filter) {
+      (Some(tool), None) => {
+        let program = match &self.program_dir {
+          Some(program_dir) => native_path(program_dir).join(&tool),
+          None => tool.clone().into(),
+        };
+        let mut command = Command::new(program);
+        command.args(self.args.unwrap_or_default());
+        if let Some(connection) = &self.connection {
+          apply_connection(&mut command, connection);
+        }
+        Ok(Stage::Tool {
+          name: self.name.unwrap_or(tool),
+          command,
+          time_limit: time_limit(self.timeout),
+        })
+      }
+      (None, Some(filter)) => Ok(Stage::Filter {
+        name: self.name.unwrap_or_else(|| "filter".to_string()),
+        filter,
+      }),
+      _ => Err(PgEmbedError::ConfigurationError(format!(
+        "Pipeline stage {} must set exactly one of tool and filter",
+        index + 1
+      ))),
+    }
+  }
+}
+
+/// Pass a connection to a tool through the libpq environment variables
+fn apply_connection(command: &mut Command, connection: &ConnectionConfig) {
+  if let Some(host) = &connection.host {
+    command.env("PGHOST", host);
+  }
+  if let Some(port) = connection.port {
+    command.env("PGPORT", port.to_string());
+  }
+  if let Some(username) = &connection.username {
+    command.env("PGUSER", username);
+  }
+  if let Some(password) = connection.password() {
+    command.env("PGPASSWORD", password);
+  }
+  if let Some(database) = &connection.database {
+    command.env("PGDATABASE", database);
+  }
+  connection.apply_env(command);
+}
+
+/// Run the stages of a pipeline, connecting the output of each stage to the input of the next
+///
+/// All executables are checked before any process is spawned. A failing stage does not
+/// raise an error; its exit code and stderr are reported in the result. A stage killed
+/// for exceeding its timeout fails the whole pipeline with a timeout error.
+pub(crate) async fn run_pipeline(stages: Vec<PipelineStage>) -> Result<PipelineResult> {
+  let stages = stages
+    .into_iter()
+    .enumerate()
+    .map(|(index, stage)| stage.prepare(index))
+    .collect::<Result<Vec<_>>>()?;
+  match stages.first() {
+    None => {
+      return Err(PgEmbedError::ConfigurationError(
+        "A pipeline needs at least one stage".to_string(),
+      ))
+    }
+    Some(Stage::Filter { .. }) => {
+      return Err(PgEmbedError::ConfigurationError(
+        "The first stage of a pipeline must run a tool".to_string(),
+      ))
+    }
+    Some(Stage::Tool { .. }) => {}
+  }
+  for stage in &stages {
+    if let Stage::Tool { command, .. } = stage {
+      check_executable(command)?;
+    }
+  }
+  let mut hooked = Vec::with_capacity(stages.len());
+  for stage in stages {
+    hooked.push(match stage {
+      Stage::Tool {
+        name,
+        command,
+        time_limit,
+      } => Stage::Tool {
+        name,
+        command: before_execute(command).await?.0,
+        time_limit,
+      },
+      filter => filter,
+    });
+  }
+
+  tokio::task::spawn_blocking(move || run_stages(hooked))
+    .await
+    .map_err(|e| PgEmbedError::InternalError(e.to_string()))?
+}
+
+#[napi]
+/// Runs tool processes connected with pipes, optionally filtering the stream between them.
+///
+/// The output of every stage is the input of the next one, like a shell pipeline, so a
+/// dump can be migrated into another server without a temporary file. Filter stages
+/// rewrite the stream line by line in between. A stage that writes faster than the next
+/// one reads is slowed down by the pipe, so memory use stays constant.
+///
+/// @param stages - The stages in pipeline order; the first one must run a tool
+/// @returns Promise<PipelineResult> with the exit code and stderr of every stage and the stdout of the last one
+/// @throws Error if a stage is invalid, its executable cannot be found or started, or it
+/// runs longer than its timeout
+///
+/// @example
+/// ```typescript
+/// const result = await runToolPipeline([
+///   {
+///     tool: 'pg_dump',
+///     programDir: '/home/postgresql/16.4.0/bin',
+///     args: ['--no-owner'],
+///     connection: { host: 'localhost', port: 5432, username: 'postgres', database: 'legacy' },
+///   },
+///   { filter: { replace: [{ from: 'legacy_schema', to: 'public' }] } },
+///   {
+///     tool: 'psql',
+///     programDir: '/home/postgresql/17.5.0/bin',
+///     args: ['-X', '-v', 'ON_ERROR_STOP=1'],
+///     connection: { host: 'localhost', port: 5433, username: 'postgres', database: 'app' },
+///   },
+/// ]);
+/// if (!result.success) {
+///   console.error(`${result.failedStage} failed`, result.stages);
+/// }
+/// ```
+pub async fn run_tool_pipeline(stages: Vec<PipelineStage>) -> Result<PipelineResult> {
+  run_pipeline(stages).await
+}
+
+fn run_stages(stages: Vec<Stage>) -> Result<PipelineResult> {
+  let mut running = Vec::with_capacity(stages.len());
+  let mut upstream: Option<Upstream> = None;
+  for stage in stages {
+    let spawned = match stage {
+      Stage::Tool {
+        name,
+        mut command,
+        time_limit,
+      } => {
+        if let Some(input) = upstream.take() {
+          command.stdin(input.into_stdio());
+        }
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+        command
+          .spawn()
+          .map_err(|e| PgEmbedError::ToolError(format!("Failed to start stage '{name}': {e}")))
+          .map(|mut child| {
+            upstream = child.stdout.take().map(Upstream::Child);
+            let stderr = child.stderr.take().expect("stderr is piped");
+            Running::Tool {
+              command: command_line(&command),
+              stderr: std::thread::spawn(move || read_all(stderr)),
+              name,
+              watchdog: Watchdog::start(child, time_limit),
+              time_limit,
+            }
+          })
+      }
+      Stage::Filter { name, filter } => io::pipe()
+        .map_err(|e| PgEmbedError::ToolError(format!("Failed to start stage '{name}': {e}")))
+        .map(|(reader, writer)| {
+          let input = upstream.replace(Upstream::Pipe(reader));
+          let worker = std::thread::spawn(move || match input {
+            Some(input) => filter.run(input.into_reader(), writer),
+            None => Ok(()),
+          });
+          Running::Filter { name, worker }
+        }),
+    };
+    match spawned {
+      Ok(stage) => running.push(stage),
+      Err(error) => {
+        for stage in &running {
+          if let Running::Tool { watchdog, .. } = stage {
+            watchdog.kill();
+          }
+        }
+        drop(upstream);
+        for stage in running {
+          stage.finish();
+        }
+        return Err(error);
+      }
+    }
+  }
+
+  let stdout = upstream.map(|output| read_all(output.into_reader()));
+  let mut stages = Vec::with_capacity(running.len());
+  let mut timed_out = None;
+  for stage in running {
+    let (stage, limit) = stage.finish();
+    if let (None, Some(limit)) = (&timed_out, limit) {
+      timed_out = Some((stage.name.clone(), stage.stderr.clone(), limit));
+    }
+    stages.push(stage);
+  }
+  if let Some((name, stderr, limit)) = timed_out {
+    return Err(timed_out_error(&name, limit, &[], stderr.as_bytes()));
+  }
+  let failed_stage = stages
+    .iter()
+    .find(|stage| stage.exit_code != 0 && !stage.interrupted)
+    .or_else(|| stages.iter().find(|stage| stage.exit_code != 0))
+    .map(|stage| stage.name.clone());
+  Ok(PipelineResult {
+    success: failed_stage.is_none(),
+    failed_stage,
+    stdout: String::from_utf8_lossy(&stdout.unwrap_or_default()).to_string(),
+    stages,
+  })
+}
+
+/// The output of the last stage started, waiting to be connected to the next one
+enum Upstream {
+  Child(ChildStdout),
+  Pipe(io::PipeReader),
+}
+
+impl Upstream {
+  /// Hand the output to a tool process as its stdin
+  fn into_stdio(self) -> Stdio {
+    match self {
+      Upstream::Child(stdout) => stdout.into(),
+      Upstream::Pipe(reader) => reader.into(),
+    }
+  }
+
+  /// Read the output in this process
+  fn into_reader(self) -> Box<dyn Read + Send> {
+    match self {
+      Upstream::Child(stdout) => Box::new(stdout),
+      Upstream::Pipe(reader) => Box::new(reader),
+    }
+  }
+}
+
+impl Running {
+  /// Wait for the stage to end and collect its outcome, with the time limit of a tool
+  /// killed for running too long
+  fn finish(self) -> (PipelineStageResult, Option<Duration>) {
+    match self {
+      Running::Tool {
+        name,
+        command,
+        watchdog,
+        time_limit,
+        stderr,
+      } => {
+        let status = watchdog.wait();
+        let stderr = String::from_utf8_lossy(&stderr.join().unwrap_or_default()).to_string();
+        let (exit_code, interrupted, timed_out) = match status {
+          Ok((status, timed_out)) => (
+            status.code().unwrap_or(1),
+            !status.success() && (is_broken_pipe(&status) || stderr.contains("Broken pipe")),
+            timed_out,
+          ),
+          Err(_) => (1, false, false),
+        };
+        let result = PipelineStageResult {
+          name,
+          exit_code,
+          stderr,
+          command,
+          interrupted,
+        };
+        (result, time_limit.filter(|_| timed_out))
+      }
+      Running::Filter { name, worker } => {
+        let outcome = worker
+          .join()
+          .unwrap_or_else(|_| Err(io::Error::other("the filter panicked")));
+        let (exit_code, stderr, interrupted) = match outcome {
+          Ok(()) => (0, String::new(), false),
+          Err(error) => (
+            1,
+            error.to_string(),
+            error.kind() == io::ErrorKind::BrokenPipe,
+          ),
+        };
+        let result = PipelineStageResult {
+          name,
+          exit_code,
+          stderr,
+          command: Vec::new(),
+          interrupted,
+        };
+        (result, None)
+      }
+    }
+  }
+}
+
+#[cfg(unix)]
+fn is_broken_pipe(status: &ExitStatus) -> bool {
+  use std::os::unix::process::ExitStatusExt;
+  status.signal() == Some(SIGPIPE)
+}
+
+#[cfg(not(unix))]
+fn is_broken_pipe(_status: &ExitStatus) -> bool {
+  false
+}
+
+fn read_all<R: Read>(mut reader: R) -> Vec<u8> {
+  let mut buffer = Vec::new();
+  let _ = reader.read_to_end(&mut buffer);
+  buffer
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn tool(tool: &str, args: &[&str]) -> PipelineStage {
+    PipelineStage {
+      tool: Some(tool.to_string()),
+      args: Some(args.iter().map(|arg| arg.to_string()).collect()),
+      ..Default::default()
+    }
+  }
+
+  fn run(stages: Vec<PipelineStage>) -> Result<PipelineResult> {
+    tokio::runtime::Builder::new_current_thread()
+      .build()
+      .unwrap()
+      .block_on(run_pipeline(stages))
+  }
+
+  #[test]
+  fn test_filter() {
+    let filter = PipelineFilter {
+      replace: Some(vec![TextReplacement {
+        from: "OWNER TO admin".to_string(),
+        to: "OWNER TO app".to_string(),
+      }]),
+      drop_lines_containing: Some(vec!["COMMENT ON".to_string()]),
+    };
+    let input = "ALTER TABLE t OWNER TO admin;\nCOMMENT ON TABLE t IS 'x';\nSELECT 1;";
+    let mut output = Vec::new();
+    filter.run(input.as_bytes(), &mut output).unwrap();
+    assert_eq!(
+      String::from_utf8(output).unwrap(),
+      "ALTER TABLE t OWNER TO app;\nSELECT 1;"
+    );
+    assert_eq!(replace_all(b"aXbXc", b"X", b"--"), b"a--b--c");
+    assert_eq!(replace_all(b"abc", b"", b"-"), b"abc");
+  }
+
+  #[test]
+  fn test_invalid_stages() {
+    let error = run(Vec::new()).unwrap_err();
+    assert!(error.to_string().contains("at least one stage"));
+    let filter = PipelineStage {
+      filter: Some(PipelineFilter::default()),
+      ..Default::default()
+    };
+    let error = run(vec![filter]).unwrap_err();
+    assert!(error.to_string().contains("must run a tool"));
+    let error = run(vec![PipelineStage::default()]).unwrap_err();
+    assert!(error.to_string().contains("stage 1 must set exactly one"));
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_run_pipeline() {
+    let result = run(vec![
+      tool("printf", &["one\\ntwo\\nthree\\n"]),
+      PipelineStage {
+        filter: Some(PipelineFilter {
+          replace: Some(vec![TextReplacement {
+            from: "t".to_string(),
+            to: "T".to_string(),
+          }]),
+          drop_lines_containing: Some(vec!["one".to_string()]),
+        }),
+        ..Default::default()
+      },
+      tool("cat", &[]),
+    ])
+    .unwrap();
+    assert!(result.success);
+    assert_eq!(result.stdout, "Two\nThree\n");
+    assert_eq!(result.stages.len(), 3);
+    assert_eq!(result.stages[1].name, "filter");
+
+    let result = run(vec![
+      tool("printf", &["data"]),
+      tool("sh", &["-c", "exit 3"]),
+    ])
+    .unwrap();
+    assert!(!result.success);
+    assert_eq!(result.failed_stage.as_deref(), Some("sh"));
+    assert_eq!(result.stages[1].exit_code, 3);
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_stage_timeout() {
+    let sleep = PipelineStage {
+      timeout: Some(1),
+      ..tool("sleep", &["30"])
+    };
+    let started = std::time::Instant::now();
+    let error = run(vec![sleep, tool("cat", &[])]).unwrap_err();
+    assert!(matches!(error, PgEmbedError::TimeoutError(_)));
+    assert!(error
+      .to_string()
+      .contains("sleep did not finish within 1 second"));
+    assert!(started.elapsed() < Duration::from_secs(10));
+  }
+}
@@ -1,12 +1,35 @@
 use crate::error::{PgEmbedError, Result};
-use crate::tools::common::{ConnectionConfig, ToolOptions, ToolResult};
+use crate::paths::native_path;
+use crate::tools::common::{
+  compose, session_timeout_options, ConnectionConfig, ToolOptions, ToolResult,
+};
+use crate::tools::tool::PgTool;
 use napi_derive::napi;
 use postgresql_commands::psql::PsqlBuilder;
 use postgresql_commands::traits::CommandBuilder;
 use serde::Deserialize;
 
-use std::process::{Command, Stdio};
-use tokio::process::Command as TokioCommand;
+use std::process::Command;
+
+#[napi]
+#[derive(Clone, Debug, Deserialize)]
+/// Text format of bytea values (the `bytea_output` setting).
+pub enum ByteaOutput {
+  /// Hexadecimal, e.g. `\xdeadbeef` (the server default)
+  Hex,
+  /// Traditional escape format, with octal escapes for non-printable bytes
+  Escape,
+}
+
+impl ByteaOutput {
+  /// Value of the `bytea_output` setting
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      ByteaOutput::Hex => "hex",
+      ByteaOutput::Escape => "escape",
+    }
+  }
+}
 
 #[napi(object)]
 #[derive(Clone, Debug, Default, Deserialize)]
@@ -46,6 +69,28 @@ pub struct PsqlConfig {
   /// Show help, then exit. Possible values: options, commands, variables.
   /// Equivalent to psql --help flag.
   pub help: Option<String>,
+  /// Abort any statement that takes longer than this many milliseconds.
+  /// Applied as `statement_timeout` through the PGOPTIONS environment variable.
+  #[napi(js_name = "statementTimeoutMs")]
+  pub statement_timeout_ms: Option<u32>,
+  /// Abort any statement that waits longer than this many milliseconds for a lock.
+  /// Applied as `lock_timeout` through the PGOPTIONS environment variable.
+  #[napi(js_name = "lockTimeoutMs")]
+  pub lock_timeout_ms: Option<u32>,
+  /// Time zone of the session, overriding the server default (e.g. "Asia/Tokyo").
+  /// Applied through the PGTZ environment variable.
+  pub timezone: Option<String>,
+  /// Date style of the session, overriding the server default (e.g. "ISO, DMY").
+  /// Applied through the PGDATESTYLE environment variable.
+  pub datestyle: Option<String>,
+  /// Character set the client sends and receives text in (e.g. "UTF8", "LATIN1").
+  /// Applied through the PGCLIENTENCODING environment variable.
+  #[napi(js_name = "clientEncoding")]
+  pub client_encoding: Option<String>,
+  /// Text format of bytea values in query results.
+  /// Applied as `bytea_output` through the PGOPTIONS environment variable.
+  #[napi(js_name = "byteaOutput")]
+  pub bytea_output: Option<ByteaOutput>,
 
   // Echo options
   /// Echo all input from script.
@@ -168,6 +213,53 @@ pub struct PsqlOptions {
   pub config: PsqlConfig,
 }
 
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+/// A relation reported by a psql `\d` meta-command (`\dt`, `\di`, `\dv`).
+pub struct PsqlRelationInfo {
+  /// Schema containing the relation.
+  pub schema: String,
+  /// Name of the relation.
+  pub name: String,
+  /// Relation type as printed by psql (e.g. "table", "index", "view").
+  #[napi(js_name = "type")]
+  pub relation_type: String,
+  /// Owner of the relation.
+  pub owner: String,
+  /// Table the relation belongs to (indexes only).
+  pub table: Option<String>,
+}
+
+impl PsqlRelationInfo {
+  /// Convert `--csv` output of a `\d` meta-command (including its header row) into relations.
+  fn from_csv(output: &str) -> Vec<Self> {
+    let mut rows = parse_csv(output).into_iter();
+    let Some(header) = rows.next() else {
+      return Vec::new();
+    };
+    let column = |name: &str| header.iter().position(|h| h == name);
+    let (schema, name, kind, owner, table) = (
+      column("Schema"),
+      column("Name"),
+      column("Type"),
+      column("Owner"),
+      column("Table"),
+    );
+    let field = |row: &Vec<String>, index: Option<usize>| {
+      index.and_then(|i| row.get(i)).cloned().unwrap_or_default()
+    };
+    rows
+      .map(|row| Self {
+        schema: field(&row, schema),
+        name: field(&row, name),
+        relation_type: field(&row, kind),
+        owner: field(&row, owner),
+        table: table.and_then(|i| row.get(i).cloned()),
+      })
+      .collect()
+  }
+}
+
 #[napi]
 /// A tool for executing SQL commands and scripts using the `psql` interactive terminal.
 ///
@@ -276,7 +368,7 @@ impl PsqlTool {
     let mut builder = PsqlBuilder::new();
 
     // Set required program directory
-    builder = builder.program_dir(&self.options.program_dir);
+    builder = builder.program_dir(native_path(&self.options.program_dir));
 
     // Set required connection parameters
     let connection = &self.options.connection;
@@ -289,7 +381,7 @@ impl PsqlTool {
     if let Some(user) = &connection.username {
       builder = builder.username(user);
     }
-    if let Some(password) = &connection.password {
+    if let Some(password) = connection.password() {
       builder = builder.pg_password(password);
     }
     if let Some(dbname) = &connection.database {
@@ -331,6 +423,25 @@ impl PsqlTool {
     if let Some(help) = &config.help {
       builder = builder.help(help);
     }
+    let mut pgoptions: Vec<String> =
+      session_timeout_options(config.statement_timeout_ms, config.lock_timeout_ms)
+        .into_iter()
+        .collect();
+    if let Some(bytea_output) = &config.bytea_output {
+      pgoptions.push(format!("-c bytea_output={}", bytea_output.as_str()));
+    }
+    if !pgoptions.is_empty() {
+      builder = builder.env("PGOPTIONS", pgoptions.join(" ").as_str());
+    }
+    if let Some(client_encoding) = &config.client_encoding {
+      builder = builder.env("PGCLIENTENCODING", client_encoding);
+    }
+    if let Some(timezone) = &config.timezone {
+      builder = builder.env("PGTZ", timezone);
+    }
+    if let Some(datestyle) = &config.datestyle {
+      builder = builder.env("PGDATESTYLE", datestyle);
+    }
 
     // Echo options
     if let Some(echo_all) = config.echo_all {
@@ -435,32 +546,13 @@ impl PsqlTool {
       builder = builder.command(command);
     } else if let Some(file) = file_path {
       builder = builder.file(file);
-    } else {
+    } else if config.command.is_none() && config.file.is_none() {
       return Err(PgEmbedError::ConfigurationError(
         "Either a command or a file must be provided for execution.".to_string(),
       ));
     }
 
-    Ok(builder.build())
-  }
-
-  /// Asynchronously runs a prepared command.
-  async fn run_command(&self, command: Command) -> Result<ToolResult> {
-    let output = TokioCommand::from(command)
-      .stdout(Stdio::piped())
-      .stderr(Stdio::piped())
-      .output()
-      .await?;
-    ToolResult::from_output(
-      output,
-      self
-        .options
-        .config
-        .tool
-        .as_ref()
-        .and_then(|t| t.silent)
-        .unwrap_or(false),
-    )
+    Ok(compose(builder, connection))
   }
 
   #[napi]
@@ -480,7 +572,7 @@ impl PsqlTool {
   /// ```
   pub async fn execute_command(&self, command_str: String) -> Result<ToolResult> {
     let command = self.to_command(Some(&command_str), None)?;
-    self.run_command(command).await
+    self.run(command).await
   }
 
   #[napi]
@@ -499,6 +591,221 @@ impl PsqlTool {
   /// ```
   pub async fn execute_file(&self, file_path: String) -> Result<ToolResult> {
     let command = self.to_command(None, Some(&file_path))?;
-    self.run_command(command).await
+    self.run(command).await
+  }
+
+  #[napi(js_name = "listTablesPsql")]
+  /// Lists the tables visible in the search path using the `\dt` meta-command.
+  ///
+  /// Works against any server reachable by psql, which makes it useful for external servers.
+  ///
+  /// @param database - Optional database to inspect (defaults to the connection's database).
+  /// @returns A promise that resolves to the tables reported by psql.
+  /// @throws An error if psql fails.
+  ///
+  /// @example
+  /// ```typescript
+  /// const tables = await psql.listTablesPsql('mydb');
+  /// console.log(tables.map((t) => `${t.schema}.${t.name}`));
+  /// ```
+  pub async fn list_tables_psql(&self, database: Option<String>) -> Result<Vec<PsqlRelationInfo>> {
+    self.run_meta_command("\\dt", database).await
+  }
+
+  #[napi]
+  /// Lists the indexes of a table using the `\di` meta-command.
+  ///
+  /// @param database - Optional database to inspect (defaults to the connection's database).
+  /// @param table - Table name, optionally schema-qualified (e.g. "public.users").
+  /// @returns A promise that resolves to the indexes defined on the table.
+  /// @throws An error if psql fails.
+  ///
+  /// @example
+  /// ```typescript
+  /// const indexes = await psql.listIndexes('mydb', 'public.users');
+  /// ```
+  pub async fn list_indexes(
+    &self,
+    database: Option<String>,
+    table: String,
+  ) -> Result<Vec<PsqlRelationInfo>> {
+    let (schema, table) = match table.split_once('.') {
+      Some((schema, table)) => (Some(schema.to_string()), table.to_string()),
+      None => (None, table),
+    };
+    let indexes = self.run_meta_command("\\di *.*", database).await?;
+    Ok(
+      indexes
+        .into_iter()
+        .filter(|index| index.table.as_deref() == Some(table.as_str()))
+        .filter(|index| match &schema {
+          Some(schema) => &index.schema == schema,
+          None => index.schema != "pg_catalog" && index.schema != "information_schema",
+        })
+        .collect(),
+    )
+  }
+
+  #[napi]
+  /// Lists the views visible in the search path using the `\dv` meta-command.
+  ///
+  /// @param database - Optional database to inspect (defaults to the connection's database).
+  /// @returns A promise that resolves to the views reported by psql.
+  /// @throws An error if psql fails.
+  ///
+  /// @example
+  /// ```typescript
+  /// const views = await psql.listViews('mydb');
+  /// ```
+  pub async fn list_views(&self, database: Option<String>) -> Result<Vec<PsqlRelationInfo>> {
+    self.run_meta_command("\\dv", database).await
+  }
+
+  /// Runs a `\d`-style meta-command with CSV output and parses the listed relations.
+  async fn run_meta_command(
+    &self,
+    meta_command: &str,
+    database: Option<String>,
+  ) -> Result<Vec<PsqlRelationInfo>> {
+    let mut options = self.options.clone();
+    if let Some(database) = database {
+      options.connection.database = Some(database);
+    }
+    options.config = PsqlConfig {
+      tool: options.config.tool.take(),
+      csv: Some(true),
+      quiet: Some(true),
+      no_psqlrc: Some(true),
+      ..Default::default()
+    };
+    let tool = PsqlTool { options };
+    let result = tool.execute_command(meta_command.to_string()).await?;
+    if result.exit_code != 0 {
+      return Err(PgEmbedError::ToolError(format!(
+        "psql {meta_command} failed: {}",
+        result.stderr.trim()
+      )));
+    }
+    Ok(PsqlRelationInfo::from_csv(&result.stdout))
+  }
+}
+
+impl PgTool for PsqlTool {
+  fn tool_options(&self) -> ToolOptions {
+    self.options.config.tool.clone().unwrap_or_default()
+  }
+
+  fn compose(&self) -> Result<Command> {
+    self.to_command(None, None)
+  }
+}
+
+/// Parse the output of `psql --csv --tuples-only` into rows of fields.
+///
+/// Quoted fields may contain separators, doubled quotes and line breaks.
+pub(crate) fn parse_csv(output: &str) -> Vec<Vec<String>> {
+  let mut rows = Vec::new();
+  let mut row = Vec::new();
+  let mut field = String::new();
+  let mut in_quotes = false;
+  let mut chars = output.chars().peekable();
+
+  while let Some(c) = chars.next() {
+    if in_quotes {
+      match c {
+        '"' if chars.peek() == Some(&'"') => {
+          field.push('"');
+          chars.next();
+        }
+        '"' => in_quotes = false,
+        _ => field.push(c),
+      }
+      continue;
+    }
+    match c {
+      '"' => in_quotes = true,
+      ',' => row.push(std::mem::take(&mut field)),
+      '\r' => {}
+      '\n' => {
+        row.push(std::mem::take(&mut field));
+        rows.push(std::mem::take(&mut row));
+      }
+      _ => field.push(c),
+    }
+  }
+  if !field.is_empty() || !row.is_empty() {
+    row.push(field);
+    rows.push(row);
+  }
+  rows
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_compose() {
+    let tool = PsqlTool::from_connection(
+      ConnectionConfig::default(),
+      "/usr/lib/postgresql/17/bin".to_string(),
+      PsqlConfig {
+        command: Some("SELECT 1".to_string()),
+        ..Default::default()
+      },
+    );
+    let args: Vec<String> = tool
+      .compose()
+      .unwrap()
+      .get_args()
+      .map(|arg| arg.to_string_lossy().to_string())
+      .collect();
+    assert!(args
+      .windows(2)
+      .any(|pair| pair == ["--command", "SELECT 1"]));
+
+    let tool = PsqlTool::from_connection(
+      ConnectionConfig::default(),
+      "/usr/lib/postgresql/17/bin".to_string(),
+      PsqlConfig::default(),
+    );
+    assert!(tool.compose().is_err());
+  }
+
+  #[test]
+  fn test_parse_csv_simple_rows() {
+    let rows = parse_csv("1,alice\n2,bob\n");
+    assert_eq!(rows, vec![vec!["1", "alice"], vec!["2", "bob"]]);
+  }
+
+  #[test]
+  fn test_parse_csv_quoted_fields() {
+    let rows = parse_csv("\"a,b\",\"say \"\"hi\"\"\",\"line1\nline2\"\n,x\n");
+    assert_eq!(
+      rows,
+      vec![vec!["a,b", "say \"hi\"", "line1\nline2"], vec!["", "x"]]
+    );
+  }
+
+  #[test]
+  fn test_relations_from_csv() {
+    let output = "Schema,Name,Type,Owner,Table\npublic,users_pkey,index,postgres,users\n";
+    let relations = PsqlRelationInfo::from_csv(output);
+    assert_eq!(
+      relations,
+      vec![PsqlRelationInfo {
+        schema: "public".to_string(),
+        name: "users_pkey".to_string(),
+        relation_type: "index".to_string(),
+        owner: "postgres".to_string(),
+        table: Some("users".to_string()),
+      }]
+    );
+    assert!(PsqlRelationInfo::from_csv("").is_empty());
+  }
+
+  #[test]
+  fn test_parse_csv_empty_output() {
+    assert!(parse_csv("").is_empty());
   }
 }
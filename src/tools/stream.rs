@@ -0,0 +1,408 @@
+use crate::error::{PgEmbedError, Result};
+use crate::tools::common::{check_executable, timed_out_error, ToolOptions, ToolResult};
+use crate::tools::hooks::{before_execute, tool_name};
+use age::secrecy::SecretString;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use napi_derive::napi;
+use serde::Deserialize;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+#[napi]
+#[derive(Clone, Debug, Deserialize)]
+/// Compression applied by pg-embedded to a dump stream.
+///
+/// Unlike the server-side `compression` level of pg_dump, this compresses the complete
+/// output stream, so it also works for plain SQL dumps and pg_dumpall.
+pub enum StreamCompression {
+  /// gzip compression, readable with `gunzip`
+  Gzip,
+  /// Zstandard compression, readable with `zstd -d`
+  Zstd,
+}
+
+/// Transformations applied to a dump stream between the tool and the file on disk.
+///
+/// Data is compressed first and encrypted second; reading reverses the order.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct StreamTransform {
+  pub compression: Option<StreamCompression>,
+  pub passphrase: Option<String>,
+}
+
+impl StreamTransform {
+  pub fn new(compression: Option<StreamCompression>, passphrase: Option<String>) -> Self {
+    Self {
+      compression,
+      passphrase,
+    }
+  }
+
+  /// Whether the stream needs any processing at all
+  pub fn is_active(&self) -> bool {
+    self.compression.is_some() || self.passphrase.is_some()
+  }
+
+  /// Compress and encrypt everything read from `reader` into `output`
+  pub fn encode<R: Read, W: Write>(&self, mut reader: R, output: W) -> io::Result<u64> {
+    let mut output = BufWriter::new(output);
+    let written = match &self.passphrase {
+      Some(passphrase) => {
+        let encryptor =
+          age::Encryptor::with_user_passphrase(SecretString::from(passphrase.clone()));
+        let mut writer = encryptor.wrap_output(&mut output)?;
+        let written = compress(&mut reader, &mut writer, self.compression.as_ref())?;
+        writer.finish()?;
+        written
+      }
+      None => compress(&mut reader, &mut output, self.compression.as_ref())?,
+    };
+    output.flush()?;
+    Ok(written)
+  }
+
+  /// Open a reader that decrypts and decompresses `input`
+  pub fn decode<R: Read + Send + 'static>(&self, input: R) -> io::Result<Box<dyn Read + Send>> {
+    let input = BufReader::new(input);
+    let decrypted: Box<dyn Read + Send> = match &self.passphrase {
+      Some(passphrase) => {
+        let decryptor = age::Decryptor::new_buffered(input).map_err(io::Error::other)?;
+        let identity = age::scrypt::Identity::new(SecretString::from(passphrase.clone()));
+        Box::new(
+          decryptor
+            .decrypt(std::iter::once(&identity as &dyn age::Identity))
+            .map_err(io::Error::other)?,
+        )
+      }
+      None => Box::new(input),
+    };
+    Ok(match self.compression {
+      Some(StreamCompression::Gzip) => Box::new(GzDecoder::new(decrypted)),
+      Some(StreamCompression::Zstd) => Box::new(zstd::Decoder::new(decrypted)?),
+      None => decrypted,
+    })
+  }
+}
+
+fn compress<R: Read, W: Write>(
+  reader: &mut R,
+  writer: &mut W,
+  compression: Option<&StreamCompression>,
+) -> io::Result<u64> {
+  match compression {
+    Some(StreamCompression::Gzip) => {
+      let mut encoder = GzEncoder::new(writer, flate2::Compression::default());
+      let written = io::copy(reader, &mut encoder)?;
+      encoder.finish()?;
+      Ok(written)
+    }
+    Some(StreamCompression::Zstd) => {
+      let mut encoder = zstd::Encoder::new(writer, 0)?;
+      let written = io::copy(reader, &mut encoder)?;
+      encoder.finish()?;
+      Ok(written)
+    }
+    None => io::copy(reader, writer),
+  }
+}
+
+/// Run a tool that writes its output to stdout and stream that output into `file`.
+pub(crate) async fn run_to_file(
+  command: Command,
+  file: String,
+  transform: StreamTransform,
+  options: &ToolOptions,
+) -> Result<ToolResult> {
+  run_piped_to_file(command, file, transform, |_| {}, options).await
+}
+
+/// Run a tool that writes its output to stdout, stream that output into `file` and report
+/// every stderr line to `on_stderr_line` as soon as the tool writes it.
+pub(crate) async fn run_piped_to_file<F>(
+  command: Command,
+  file: String,
+  transform: StreamTransform,
+  mut on_stderr_line: F,
+  options: &ToolOptions,
+) -> Result<ToolResult>
+where
+  F: FnMut(&str) + Send + 'static,
+{
+  if options.is_dry_run() {
+    return Ok(ToolResult::dry_run(&command));
+  }
+  check_executable(&command)?;
+  let tool = tool_name(&command);
+  let time_limit = options.time_limit();
+  let (mut command, report) = before_execute(command).await?;
+  let output = tokio::task::spawn_blocking(move || -> Result<Output> {
+    command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    let mut child = command.spawn()?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let watchdog = Watchdog::start(child, time_limit);
+    let stderr_reader = std::thread::spawn(move || {
+      let mut stderr_bytes = Vec::new();
+      let mut lines = BufReader::new(stderr);
+      let mut line = Vec::new();
+      while lines.read_until(b'\n', &mut line).unwrap_or(0) > 0 {
+        on_stderr_line(String::from_utf8_lossy(&line).trim_end());
+        stderr_bytes.append(&mut line);
+      }
+      stderr_bytes
+    });
+
+    let encoded = File::create(&file).and_then(|out| transform.encode(stdout, out));
+    let (status, timed_out) = watchdog.wait()?;
+    let stderr = stderr_reader.join().unwrap_or_default();
+    if let (true, Some(limit)) = (timed_out, time_limit) {
+      return Err(timed_out_error(&tool, limit, &[], &stderr));
+    }
+    encoded?;
+
+    Ok(Output {
+      status,
+      stdout: Vec::new(),
+      stderr,
+    })
+  })
+  .await
+  .map_err(|e| PgEmbedError::InternalError(e.to_string()))??;
+
+  let result = ToolResult::from_output(
+    report.command_line.clone(),
+    report.environment.clone(),
+    output,
+    options.is_silent(),
+  )?;
+  report.finish(&result);
+  Ok(result)
+}
+
+/// Run a tool that reads its input from stdin, feeding it the decoded contents of `file`.
+pub(crate) async fn run_from_file(
+  command: Command,
+  file: String,
+  transform: StreamTransform,
+  options: &ToolOptions,
+) -> Result<ToolResult> {
+  run_piped(command, Some((file, transform)), |_| {}, options).await
+}
+
+/// Run a tool, optionally feeding it the decoded contents of a file on stdin, and report
+/// every stderr line to `on_stderr_line` as soon as the tool writes it.
+pub(crate) async fn run_piped<F>(
+  command: Command,
+  input: Option<(String, StreamTransform)>,
+  mut on_stderr_line: F,
+  options: &ToolOptions,
+) -> Result<ToolResult>
+where
+  F: FnMut(&str) + Send + 'static,
+{
+  if options.is_dry_run() {
+    return Ok(ToolResult::dry_run(&command));
+  }
+  check_executable(&command)?;
+  let tool = tool_name(&command);
+  let time_limit = options.time_limit();
+  let (mut command, report) = before_execute(command).await?;
+  let output = tokio::task::spawn_blocking(move || -> Result<Output> {
+    let reader = match input {
+      Some((file, transform)) => Some(transform.decode(File::open(&file)?)?),
+      None => None,
+    };
+    if reader.is_some() {
+      command.stdin(Stdio::piped());
+    }
+    command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    let mut child = command.spawn()?;
+
+    let writer = match (reader, child.stdin.take()) {
+      (Some(mut reader), Some(mut stdin)) => Some(std::thread::spawn(move || {
+        // A broken pipe means the tool exited early; its stderr explains why
+        let _ = io::copy(&mut reader, &mut stdin);
+      })),
+      _ => None,
+    };
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stdout_reader = std::thread::spawn(move || read_all(stdout));
+    let mut lines = BufReader::new(child.stderr.take().expect("stderr is piped"));
+    let watchdog = Watchdog::start(child, time_limit);
+
+    let mut stderr = Vec::new();
+    let mut line = Vec::new();
+    // The pipe closes once the tool exits, also when the watchdog kills it
+    while lines.read_until(b'\n', &mut line)? > 0 {
+      on_stderr_line(String::from_utf8_lossy(&line).trim_end());
+      stderr.append(&mut line);
+    }
+
+    let (status, timed_out) = watchdog.wait()?;
+    if let Some(writer) = writer {
+      let _ = writer.join();
+    }
+    let stdout = stdout_reader.join().unwrap_or_default();
+    if let (true, Some(limit)) = (timed_out, time_limit) {
+      return Err(timed_out_error(&tool, limit, &stdout, &stderr));
+    }
+    Ok(Output {
+      status,
+      stdout,
+      stderr,
+    })
+  })
+  .await
+  .map_err(|e| PgEmbedError::InternalError(e.to_string()))??;
+
+  let result = ToolResult::from_output(
+    report.command_line.clone(),
+    report.environment.clone(),
+    output,
+    options.is_silent(),
+  )?;
+  report.finish(&result);
+  Ok(result)
+}
+
+/// Kills a child process that is still running when its time limit expires
+pub(crate) struct Watchdog {
+  child: Arc<Mutex<Child>>,
+  stop: mpsc::Sender<()>,
+  thread: Option<JoinHandle<bool>>,
+}
+
+impl Watchdog {
+  /// Watch `child`, which is killed after `time_limit`; without a limit it is never killed
+  pub fn start(child: Child, time_limit: Option<Duration>) -> Self {
+    let child = Arc::new(Mutex::new(child));
+    let (stop, stopped) = mpsc::channel::<()>();
+    let thread = time_limit.map(|limit| {
+      let child = Arc::clone(&child);
+      std::thread::spawn(move || {
+        if stopped.recv_timeout(limit) != Err(mpsc::RecvTimeoutError::Timeout) {
+          return false;
+        }
+        // kill() also succeeds for a child that exited but was not reaped yet, so only a
+        // child that is still running counts as timed out
+        child
+          .lock()
+          .map(|mut child| matches!(child.try_wait(), Ok(None)) && child.kill().is_ok())
+          .unwrap_or(false)
+      })
+    });
+    Self {
+      child,
+      stop,
+      thread,
+    }
+  }
+
+  /// Kill the child right away, e.g. when the processes it talks to failed to start
+  pub fn kill(&self) {
+    if let Ok(mut child) = self.child.lock() {
+      let _ = child.kill();
+    }
+  }
+
+  /// Wait for the child after its output was read; returns its status and whether it was
+  /// killed for running too long
+  pub fn wait(mut self) -> io::Result<(std::process::ExitStatus, bool)> {
+    let _ = self.stop.send(());
+    let timed_out = self
+      .thread
+      .take()
+      .map(|thread| thread.join().unwrap_or(false))
+      .unwrap_or(false);
+    let status = self
+      .child
+      .lock()
+      .map_err(|_| io::Error::other("child process lock poisoned"))?
+      .wait()?;
+    Ok((status, timed_out))
+  }
+}
+
+fn read_all<R: Read>(mut reader: R) -> Vec<u8> {
+  let mut buffer = Vec::new();
+  let _ = reader.read_to_end(&mut buffer);
+  buffer
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn round_trip(transform: StreamTransform) {
+    let data = b"CREATE TABLE t (id int);\nINSERT INTO t VALUES (1);\n".repeat(100);
+    let mut encoded = Vec::new();
+    transform.encode(&data[..], &mut encoded).unwrap();
+    if transform.is_active() {
+      assert_ne!(encoded, data);
+    }
+
+    let mut decoded = Vec::new();
+    transform
+      .decode(io::Cursor::new(encoded))
+      .unwrap()
+      .read_to_end(&mut decoded)
+      .unwrap();
+    assert_eq!(decoded, data);
+  }
+
+  #[test]
+  fn test_round_trip_compression() {
+    round_trip(StreamTransform::new(Some(StreamCompression::Gzip), None));
+    round_trip(StreamTransform::new(Some(StreamCompression::Zstd), None));
+  }
+
+  #[test]
+  fn test_round_trip_encryption() {
+    round_trip(StreamTransform::new(None, Some("secret".to_string())));
+    round_trip(StreamTransform::new(
+      Some(StreamCompression::Zstd),
+      Some("secret".to_string()),
+    ));
+  }
+
+  #[test]
+  #[cfg(unix)]
+  fn test_watchdog_ignores_exited_child() {
+    let child = std::process::Command::new("true").spawn().unwrap();
+    let watchdog = Watchdog::start(child, Some(Duration::from_millis(200)));
+    std::thread::sleep(Duration::from_millis(500));
+    let (status, timed_out) = watchdog.wait().unwrap();
+    assert!(status.success());
+    assert!(!timed_out);
+  }
+
+  #[test]
+  #[cfg(unix)]
+  fn test_watchdog_kills_running_child() {
+    let child = std::process::Command::new("sleep")
+      .arg("10")
+      .spawn()
+      .unwrap();
+    let watchdog = Watchdog::start(child, Some(Duration::from_millis(100)));
+    std::thread::sleep(Duration::from_millis(300));
+    let (status, timed_out) = watchdog.wait().unwrap();
+    assert!(!status.success());
+    assert!(timed_out);
+  }
+
+  #[test]
+  fn test_wrong_passphrase_fails() {
+    let mut encoded = Vec::new();
+    StreamTransform::new(None, Some("right".to_string()))
+      .encode(&b"data"[..], &mut encoded)
+      .unwrap();
+    let result =
+      StreamTransform::new(None, Some("wrong".to_string())).decode(io::Cursor::new(encoded));
+    assert!(result.is_err());
+  }
+}
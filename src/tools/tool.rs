@@ -0,0 +1,219 @@
+//! The interface shared by the tool wrappers, and `executeTool()` for programs without one
+
+use crate::error::{PgEmbedError, Result};
+use crate::paths::native_path;
+use crate::tools::common::{ConnectionConfig, ToolOptions, ToolResult};
+use crate::tools::hooks::run_tool_command;
+use crate::tools::stream::run_piped;
+use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi::Status;
+use napi_derive::napi;
+use std::future::Future;
+use std::process::Command;
+
+/// A wrapper around one PostgreSQL program
+///
+/// A wrapper composes the command line from its configuration; validation, the hooks,
+/// dry runs and output capture are the same for every tool.
+pub(crate) trait PgTool {
+  /// Generic tool options such as silent mode and dry run
+  fn tool_options(&self) -> ToolOptions;
+
+  /// Check the configuration before a command is composed
+  fn validate(&self) -> Result<()> {
+    Ok(())
+  }
+
+  /// Compose the command the tool runs by default
+  fn compose(&self) -> Result<Command>;
+
+  /// Run a command of the tool with its output captured
+  fn run(&self, command: Command) -> impl Future<Output = Result<ToolResult>> + Send {
+    let options = self.tool_options();
+    async move { run_tool_command(command, &options).await }
+  }
+
+  /// Validate and compose the default command, then run it with its output captured
+  fn execute(&self) -> impl Future<Output = Result<ToolResult>> + Send {
+    let command = self.validate().and_then(|()| self.compose());
+    let options = self.tool_options();
+    async move { run_tool_command(command?, &options).await }
+  }
+
+  /// Validate and compose the default command, then run it, reporting every stderr line
+  /// as soon as the tool writes it
+  fn stream<F>(&self, on_stderr_line: F) -> impl Future<Output = Result<ToolResult>> + Send
+  where
+    F: FnMut(&str) + Send + 'static,
+  {
+    let command = self.validate().and_then(|()| self.compose());
+    let options = self.tool_options();
+    async move { run_piped(command?, None, on_stderr_line, &options).await }
+  }
+}
+
+/// Options for `executeTool()`
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct ExecuteToolOptions {
+  /// The directory where the program is located
+  pub program_dir: String,
+  /// Arguments passed to the program
+  pub args: Option<Vec<String>>,
+  /// Connection settings, passed to the program as libpq environment variables
+  /// (PGHOST, PGPORT, PGUSER, PGPASSWORD, PGDATABASE and those of the other settings)
+  pub connection: Option<ConnectionConfig>,
+  /// Generic tool options such as dry run
+  pub tool: Option<ToolOptions>,
+}
+
+/// A program of the installation that has no dedicated wrapper
+pub(crate) struct GenericTool {
+  name: String,
+  options: ExecuteToolOptions,
+}
+
+impl GenericTool {
+  pub fn new(name: String, options: ExecuteToolOptions) -> Self {
+    Self { name, options }
+  }
+}
+
+impl PgTool for GenericTool {
+  fn tool_options(&self) -> ToolOptions {
+    self.options.tool.clone().unwrap_or_default()
+  }
+
+  fn validate(&self) -> Result<()> {
+    let name = &self.name;
+    if name.is_empty()
+      || name == "."
+      || name == ".."
+      || name.contains(['/', '\\'])
+      || name.contains('\0')
+    {
+      return Err(PgEmbedError::ConfigurationError(format!(
+        "Invalid tool name '{name}'; pass the file name of a program in programDir"
+      )));
+    }
+    Ok(())
+  }
+
+  fn compose(&self) -> Result<Command> {
+    let program = native_path(&self.options.program_dir).join(format!(
+      "{}{}",
+      self.name,
+      std::env::consts::EXE_SUFFIX
+    ));
+    let mut command = Command::new(program);
+    command.args(self.options.args.iter().flatten());
+    if let Some(connection) = &self.options.connection {
+      let env = [
+        ("PGHOST", connection.host.clone()),
+        ("PGPORT", connection.port.map(|port| port.to_string())),
+        ("PGUSER", connection.username.clone()),
+        ("PGPASSWORD", connection.password().map(str::to_string)),
+        ("PGDATABASE", connection.database.clone()),
+      ];
+      for (key, value) in env {
+        if let Some(value) = value {
+          command.env(key, value);
+        }
+      }
+      connection.apply_env(&mut command);
+    }
+    #[cfg(windows)]
+    {
+      use std::os::windows::process::CommandExt;
+      const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+      command.creation_flags(CREATE_NO_WINDOW);
+    }
+    Ok(command)
+  }
+}
+
+/// Runs a program of a PostgreSQL installation that has no dedicated wrapper
+///
+/// An escape hatch for programs such as `oid2name`, `pg_test_fsync` or `pg_controldata`:
+/// the arguments are passed as given, the connection settings through the libpq
+/// environment variables. The command goes through the hooks and honours `dryRun` like
+/// every other tool.
+///
+/// @param name - File name of the program, without a directory or `.exe` suffix
+/// @param options - Program directory, arguments, connection and tool options
+/// @param on_stderr_line - Optional callback receiving every line the program writes to
+/// stderr as soon as it is written, e.g. the `--progress` output of `pg_checksums`
+/// @returns Promise that resolves to the result of the program
+/// @throws Error if the name is not a plain file name or the program cannot be run
+///
+/// @example
+/// ```typescript
+/// const result = await executeTool('pg_test_fsync', {
+///   programDir: instance.programDir + '/bin',
+///   args: ['--secs-per-test', '1'],
+/// });
+/// console.log(result.stdout);
+/// ```
+#[napi(
+  ts_args_type = "name: string, options: ExecuteToolOptions, onStderrLine?: ((line: string) => void) | undefined | null"
+)]
+pub async fn execute_tool(
+  name: String,
+  options: ExecuteToolOptions,
+  on_stderr_line: Option<ThreadsafeFunction<String, (), String, Status, false>>,
+) -> Result<ToolResult> {
+  let tool = GenericTool::new(name, options);
+  match on_stderr_line {
+    Some(callback) => {
+      tool
+        .stream(move |line: &str| {
+          callback.call(line.to_string(), ThreadsafeFunctionCallMode::NonBlocking);
+        })
+        .await
+    }
+    None => tool.execute().await,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::ffi::OsStr;
+  use std::path::Path;
+
+  #[test]
+  fn test_generic_tool() {
+    let tool = GenericTool::new(
+      "oid2name".to_string(),
+      ExecuteToolOptions {
+        program_dir: "/pg/bin".to_string(),
+        args: Some(vec!["--quiet".to_string()]),
+        connection: Some(ConnectionConfig {
+          port: Some(5433),
+          password: Some("secret".to_string()),
+          ..Default::default()
+        }),
+        tool: None,
+      },
+    );
+    assert!(tool.validate().is_ok());
+    let command = tool.compose().unwrap();
+    assert_eq!(
+      Path::new(command.get_program()).file_stem(),
+      Some(OsStr::new("oid2name"))
+    );
+    assert_eq!(command.get_args().collect::<Vec<_>>(), ["--quiet"]);
+    let envs: Vec<_> = command.get_envs().collect();
+    assert!(envs.contains(&(OsStr::new("PGPORT"), Some(OsStr::new("5433")))));
+    assert!(envs.contains(&(OsStr::new("PGPASSWORD"), Some(OsStr::new("secret")))));
+    assert!(!envs.iter().any(|(key, _)| *key == "PGHOST"));
+
+    for name in ["", "..", "../bin/psql", "bin\\psql"] {
+      assert!(
+        GenericTool::new(name.to_string(), ExecuteToolOptions::default())
+          .validate()
+          .is_err()
+      );
+    }
+  }
+}
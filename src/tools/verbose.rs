@@ -0,0 +1,152 @@
+use napi_derive::napi;
+
+/// Object types of archive entries that consist of several words.
+///
+/// `executing` lines print the type and the entry name unquoted, so these are needed to
+/// tell where the type ends.
+const MULTI_WORD_TYPES: [&str; 9] = [
+  "MATERIALIZED VIEW DATA",
+  "PUBLICATION TABLES IN SCHEMA",
+  "BLOB METADATA",
+  "CHECK CONSTRAINT",
+  "DEFAULT ACL",
+  "FK CONSTRAINT",
+  "LARGE OBJECT",
+  "SEQUENCE SET",
+  "TABLE DATA",
+];
+
+#[napi]
+#[derive(Clone, Debug, PartialEq)]
+/// What a line of pg_dump or pg_restore verbose output reports.
+pub enum VerboseAction {
+  /// An object is being created (`creating TABLE "public.users"`).
+  Create,
+  /// A statement of an archive entry is being run (`executing SEQUENCE SET users_id_seq`).
+  Execute,
+  /// Table data is being dumped or restored.
+  Data,
+  /// Catalog information is being read (`reading schemas`).
+  Read,
+  /// Any other message, such as connection details, warnings and errors.
+  Other,
+}
+
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+/// A structured line of pg_dump or pg_restore verbose output.
+pub struct VerboseEvent {
+  /// The tool that printed the line, `pg_dump` or `pg_restore`.
+  pub tool: String,
+  /// What the line reports.
+  pub action: VerboseAction,
+  /// Type of the object, e.g. `TABLE`, `INDEX` or `TABLE DATA` for table contents.
+  #[napi(js_name = "objectType")]
+  pub object_type: Option<String>,
+  /// Schema of the object, if it belongs to one.
+  pub schema: Option<String>,
+  /// Name of the object.
+  pub name: Option<String>,
+  /// The message as printed by the tool, without the tool name prefix.
+  pub message: String,
+}
+
+/// Parse one line of `tool --verbose` output.
+///
+/// Returns `None` for lines that were not printed by `tool` itself, such as server
+/// notices and continuation lines.
+pub(crate) fn parse_verbose_line(tool: &str, line: &str) -> Option<VerboseEvent> {
+  let message = line.strip_prefix(tool)?.strip_prefix(": ")?.trim_end();
+  let mut event = VerboseEvent {
+    tool: tool.to_string(),
+    action: VerboseAction::Other,
+    object_type: None,
+    schema: None,
+    name: None,
+    message: message.to_string(),
+  };
+
+  if let Some(table) = message
+    .strip_prefix("processing data for table ")
+    .or_else(|| message.strip_prefix("dumping contents of table "))
+  {
+    event.action = VerboseAction::Data;
+    event.object_type = Some("TABLE DATA".to_string());
+    (event.schema, event.name) = split_qualified(table.trim_matches('"'));
+  } else if let Some(entry) = message.strip_prefix("creating ") {
+    event.action = VerboseAction::Create;
+    match entry.split_once(" \"") {
+      Some((object_type, target)) => {
+        event.object_type = Some(object_type.to_string());
+        (event.schema, event.name) = split_qualified(target.trim_end_matches('"'));
+      }
+      None => event.object_type = Some(entry.to_string()),
+    }
+  } else if let Some(entry) = message.strip_prefix("executing ") {
+    event.action = VerboseAction::Execute;
+    let object_type = MULTI_WORD_TYPES
+      .iter()
+      .copied()
+      .find(|object_type| {
+        entry
+          .strip_prefix(object_type)
+          .is_some_and(|rest| rest.is_empty() || rest.starts_with(' '))
+      })
+      .unwrap_or_else(|| entry.split(' ').next().unwrap_or(entry));
+    event.object_type = Some(object_type.to_string());
+    let name = entry[object_type.len()..].trim();
+    if !name.is_empty() {
+      event.name = Some(name.to_string());
+    }
+  } else if message.starts_with("reading ") {
+    event.action = VerboseAction::Read;
+  }
+  Some(event)
+}
+
+/// Split `schema.name` into its parts; names without a schema are returned as is
+fn split_qualified(target: &str) -> (Option<String>, Option<String>) {
+  match target.split_once('.') {
+    Some((schema, name)) => (Some(schema.to_string()), Some(name.to_string())),
+    None => (None, Some(target.to_string())),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_verbose_line() {
+    let event =
+      parse_verbose_line("pg_restore", "pg_restore: creating TABLE \"public.orders\"").unwrap();
+    assert_eq!(event.action, VerboseAction::Create);
+    assert_eq!(event.object_type.as_deref(), Some("TABLE"));
+    assert_eq!(event.schema.as_deref(), Some("public"));
+    assert_eq!(event.name.as_deref(), Some("orders"));
+
+    let event = parse_verbose_line(
+      "pg_dump",
+      "pg_dump: dumping contents of table \"public.orders\"",
+    )
+    .unwrap();
+    assert_eq!(event.action, VerboseAction::Data);
+    assert_eq!(event.object_type.as_deref(), Some("TABLE DATA"));
+    assert_eq!(event.name.as_deref(), Some("orders"));
+
+    let event = parse_verbose_line(
+      "pg_restore",
+      "pg_restore: executing SEQUENCE SET orders_id_seq",
+    )
+    .unwrap();
+    assert_eq!(event.action, VerboseAction::Execute);
+    assert_eq!(event.object_type.as_deref(), Some("SEQUENCE SET"));
+    assert_eq!(event.name.as_deref(), Some("orders_id_seq"));
+
+    let event = parse_verbose_line("pg_dump", "pg_dump: reading schemas").unwrap();
+    assert_eq!(event.action, VerboseAction::Read);
+    assert_eq!(event.object_type, None);
+
+    assert!(parse_verbose_line("pg_restore", "DETAIL:  Key (id)=(1) already exists.").is_none());
+  }
+}
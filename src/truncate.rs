@@ -0,0 +1,122 @@
+//! Truncation of tables together with the tables that reference them
+
+use crate::sql::quote_literal;
+use napi_derive::napi;
+
+/// Options for `truncateTables()`
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct TruncateTablesOptions {
+  /// Also truncate tables with foreign keys to the given tables, recursively (default: false)
+  pub cascade: Option<bool>,
+  /// Reset the sequences owned by columns of the truncated tables (default: false)
+  pub restart_identity: Option<bool>,
+}
+
+/// How a row of `truncate_plan_sql()` relates to the requested tables
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum PlannedTable {
+  /// A requested table that does not exist
+  Missing(String),
+  /// A requested table, by its quoted relation name
+  Requested(String),
+  /// A table referencing a requested table through foreign keys, by its quoted relation name
+  Referencing(String),
+}
+
+/// Query returning the requested tables and every table that references them
+///
+/// Without `tables` all user tables outside extensions are requested.
+pub(crate) fn truncate_plan_sql(tables: Option<&[String]>) -> String {
+  let requested = match tables {
+    Some(tables) => {
+      let names: Vec<String> = tables.iter().map(|table| quote_literal(table)).collect();
+      format!(
+        "SELECT name, to_regclass(name)::oid FROM unnest(ARRAY[{}]::text[]) AS t(name)",
+        names.join(", ")
+      )
+    }
+    None => "SELECT c.oid::regclass::text, c.oid FROM pg_class c \
+             JOIN pg_namespace n ON n.oid = c.relnamespace \
+             WHERE c.relkind IN ('r', 'p') AND n.nspname NOT IN ('pg_catalog', 'information_schema') \
+             AND n.nspname NOT LIKE 'pg_toast%' \
+             AND NOT EXISTS (SELECT 1 FROM pg_depend d WHERE d.classid = 'pg_class'::regclass \
+             AND d.objid = c.oid AND d.deptype = 'e')"
+      .to_string(),
+  };
+  format!(
+    "WITH RECURSIVE requested(name, rel) AS ({requested}), \
+     closure(rel) AS (SELECT rel FROM requested WHERE rel IS NOT NULL \
+     UNION SELECT c.conrelid FROM pg_constraint c JOIN closure ON c.confrelid = closure.rel \
+     WHERE c.contype = 'f') \
+     SELECT 'missing', name FROM requested WHERE rel IS NULL \
+     UNION ALL SELECT CASE WHEN rel IN (SELECT rel FROM requested WHERE rel IS NOT NULL) \
+     THEN 'requested' ELSE 'referencing' END, rel::regclass::text FROM closure \
+     ORDER BY 1, 2"
+  )
+}
+
+/// Parse a row of `truncate_plan_sql()`
+pub(crate) fn parse_planned_table(row: &[String]) -> Option<PlannedTable> {
+  let [kind, name] = row else {
+    return None;
+  };
+  match kind.as_str() {
+    "missing" => Some(PlannedTable::Missing(name.clone())),
+    "requested" => Some(PlannedTable::Requested(name.clone())),
+    "referencing" => Some(PlannedTable::Referencing(name.clone())),
+    _ => None,
+  }
+}
+
+/// A single TRUNCATE of `relations`, so foreign keys among them never see a partial state
+pub(crate) fn truncate_sql(relations: &[String], restart_identity: bool) -> String {
+  let identity = if restart_identity {
+    "RESTART IDENTITY"
+  } else {
+    "CONTINUE IDENTITY"
+  };
+  format!("TRUNCATE TABLE {} {identity}", relations.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn row(values: &[&str]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+  }
+
+  #[test]
+  fn test_truncate_plan_sql() {
+    let sql = truncate_plan_sql(Some(&["users".to_string(), "o'rders".to_string()]));
+    assert!(sql.contains("unnest(ARRAY['users', 'o''rders']::text[])"));
+    assert!(truncate_plan_sql(None).contains("d.deptype = 'e'"));
+  }
+
+  #[test]
+  fn test_parse_planned_table() {
+    assert_eq!(
+      parse_planned_table(&row(&["referencing", "\"Order\""])),
+      Some(PlannedTable::Referencing("\"Order\"".to_string()))
+    );
+    assert_eq!(
+      parse_planned_table(&row(&["missing", "nope"])),
+      Some(PlannedTable::Missing("nope".to_string()))
+    );
+    assert!(parse_planned_table(&row(&["requested"])).is_none());
+  }
+
+  #[test]
+  fn test_truncate_sql() {
+    let relations = row(&["users", "orders"]);
+    assert_eq!(
+      truncate_sql(&relations, true),
+      "TRUNCATE TABLE users, orders RESTART IDENTITY"
+    );
+    assert_eq!(
+      truncate_sql(&relations, false),
+      "TRUNCATE TABLE users, orders CONTINUE IDENTITY"
+    );
+  }
+}
@@ -1,4 +1,7 @@
+use crate::client::QueryResult;
+use crate::tools::{PgRestoreFormat, PsqlConfig, ToolResult};
 use napi_derive::napi;
+use std::collections::HashMap;
 
 /// PostgreSQL instance state enumeration
 #[napi]
@@ -12,6 +15,222 @@ pub enum InstanceState {
   Running,
   /// Stopping
   Stopping,
+  /// Setup or start failed; see `getLastError()` for the cause
+  Failed,
+  /// Setup completed (the data directory is initialized) but the server has not been started yet
+  Initialized,
+}
+
+/// Replication role of a running server
+#[napi]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ServerRole {
+  /// Accepts writes
+  Primary,
+  /// Replays WAL from a primary or archive (in recovery)
+  Standby,
+}
+
+/// Recovery progress of a server, as reported by `getRecoveryStatus()`
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct RecoveryStatus {
+  /// Current replication role
+  pub role: ServerRole,
+  /// Last WAL location received and flushed by streaming replication, if any
+  pub receive_lsn: Option<String>,
+  /// Last WAL location replayed during recovery, if any
+  pub replay_lsn: Option<String>,
+  /// Whether WAL replay is paused
+  pub replay_paused: bool,
+}
+
+/// A backend waiting for a lock held by another backend, as reported by `getLockWaits()`
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct LockWait {
+  /// Process ID of the waiting backend
+  pub blocked_pid: i32,
+  /// Query of the waiting backend
+  pub blocked_query: String,
+  /// Process ID of a backend holding or queued ahead for the lock
+  pub blocking_pid: i32,
+  /// Current or last query of the blocking backend
+  pub blocking_query: String,
+  /// State of the blocking backend, e.g. `idle in transaction`
+  pub blocking_state: Option<String>,
+  /// Type of the awaited lock, e.g. `relation` or `transactionid` for row locks
+  pub lock_type: Option<String>,
+  /// Mode of the awaited lock, e.g. `AccessExclusiveLock`
+  pub mode: Option<String>,
+  /// Relations the waiting backend is trying to lock, including tables of awaited rows
+  pub relations: Vec<String>,
+  /// How long the backend has been waiting, in milliseconds (timed from the start of its
+  /// query before PostgreSQL 14)
+  pub wait_duration_ms: f64,
+}
+
+/// A schema of a database, as reported by `listSchemas()`
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct SchemaInfo {
+  /// Schema name
+  pub name: String,
+  /// Role owning the schema
+  pub owner: String,
+}
+
+/// A transaction prepared for two-phase commit, as reported by `listPreparedTransactions()`
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct PreparedTransaction {
+  /// Global identifier given to `PREPARE TRANSACTION`
+  pub gid: String,
+  /// Numeric ID of the transaction
+  pub transaction: String,
+  /// Time the transaction was prepared, in milliseconds since the Unix epoch
+  pub prepared: f64,
+  /// Role that executed the transaction
+  pub owner: String,
+  /// Database the transaction was executed in
+  pub database: String,
+}
+
+/// A collation whose recorded version differs from the version provided by the
+/// collation library, as reported by `checkCollationVersionMismatch()`
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct CollationVersionMismatch {
+  /// Collation name, or the database name for the database's default collation
+  pub collation: String,
+  /// Whether the entry describes the database's default collation
+  pub database_default: bool,
+  /// Version recorded when the collation (or database) was created
+  pub recorded_version: Option<String>,
+  /// Version provided by the operating system or ICU library now
+  pub actual_version: Option<String>,
+}
+
+/// Options for `postLoadOptimize()` and the automatic optimization after data loads
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct PostLoadOptimizeOptions {
+  /// Also VACUUM the tables, updating the visibility map for index-only scans (default: false)
+  pub vacuum: Option<bool>,
+}
+
+/// Options for `executeSqlOnAllDatabases()`
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct ExecuteOnAllDatabasesOptions {
+  /// Databases to skip
+  pub exclude: Option<Vec<String>>,
+  /// psql options applied to every execution
+  pub psql: Option<PsqlConfig>,
+}
+
+/// Result of running SQL on one database with `executeSqlOnAllDatabases()`
+#[napi(object)]
+#[derive(Debug)]
+pub struct DatabaseSqlResult {
+  /// Database the SQL ran on
+  pub database_name: String,
+  /// psql result for this database
+  pub result: ToolResult,
+}
+
+/// Result of `executeSqlStructured()`, with every row keyed by column name
+#[napi(object)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StructuredSqlResult {
+  /// Names of the returned columns, in query order (empty when no rows were returned)
+  pub columns: Vec<String>,
+  /// Returned rows as objects mapping column names to values in their text form, with
+  /// NULL as null; of columns sharing a name, the last one is kept
+  pub rows: Vec<HashMap<String, Option<String>>>,
+  /// Number of rows returned or affected
+  pub row_count: u32,
+}
+
+impl From<QueryResult> for StructuredSqlResult {
+  fn from(result: QueryResult) -> Self {
+    let rows = result
+      .rows
+      .into_iter()
+      .map(|row| result.columns.iter().cloned().zip(row).collect())
+      .collect();
+    Self {
+      columns: result.columns,
+      rows,
+      row_count: result.row_count,
+    }
+  }
+}
+
+/// Options for `restoreIntoNewDatabase()`
+#[napi(object)]
+#[derive(Clone, Debug, Default)]
+pub struct RestoreIntoNewDatabaseOptions {
+  /// Create the database from template0, so objects added to template1 cannot clash
+  /// with the restored ones (default: true)
+  pub from_template0: Option<bool>,
+  /// Archive format (detected by pg_restore when omitted)
+  pub format: Option<PgRestoreFormat>,
+  /// Number of parallel restore jobs
+  pub jobs: Option<u32>,
+  /// Compare the number of restored tables, views, materialized views and sequences
+  /// with the archive's table of contents (default: true)
+  pub verify: Option<bool>,
+}
+
+/// Number of objects of one type in the archive and in the restored database
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct RestoredObjectCount {
+  /// Archive entry type, e.g. `TABLE` or `SEQUENCE`
+  pub object_type: String,
+  /// Number of entries in the archive
+  pub expected: u32,
+  /// Number of objects in the restored database
+  pub actual: u32,
+}
+
+/// Result of `restoreIntoNewDatabase()`
+#[napi(object)]
+#[derive(Debug)]
+pub struct RestoreIntoNewDatabaseResult {
+  /// The created database
+  pub database_name: String,
+  /// pg_restore result
+  pub result: ToolResult,
+  /// Object counts compared after the restore, empty if verification was disabled
+  pub object_counts: Vec<RestoredObjectCount>,
+}
+
+/// Lifecycle phase in which an instance failure occurred
+#[napi]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FailurePhase {
+  /// Installation or cluster initialization (setup)
+  Setup,
+  /// Server start, including default database creation and profile provisioning
+  Start,
+  /// Server shutdown
+  Stop,
+  /// The running server stopped accepting connections, as detected by `probeHealth(true)`
+  Runtime,
+}
+
+/// Details of the most recent instance failure
+#[napi(object)]
+#[derive(Clone, Debug)]
+pub struct InstanceFailure {
+  /// Phase that failed
+  pub phase: FailurePhase,
+  /// Error message
+  pub message: String,
+  /// Last lines of the server log, when the server wrote one
+  pub stderr: Option<String>,
 }
 
 /// Connection information structure
@@ -37,19 +256,24 @@ impl ConnectionInfo {
   /// Generate a safe connection string without password (for logging)
   #[napi]
   pub fn safe_connection_string(&self) -> String {
+    let credentials = if self.password.is_empty() { "" } else { ":***" };
     format!(
-      "postgresql://{}:***@{}:{}/{}",
-      self.username, self.host, self.port, self.database_name
+      "postgresql://{}{}@{}:{}/{}",
+      self.username, credentials, self.host, self.port, self.database_name
     )
   }
 
   /// Generate JDBC format connection string
   #[napi]
   pub fn jdbc_url(&self) -> String {
-    format!(
-      "jdbc:postgresql://{}:{}/{}?user={}&password={}",
-      self.host, self.port, self.database_name, self.username, self.password
-    )
+    let mut url = format!(
+      "jdbc:postgresql://{}:{}/{}?user={}",
+      self.host, self.port, self.database_name, self.username
+    );
+    if !self.password.is_empty() {
+      url.push_str(&format!("&password={}", self.password));
+    }
+    url
   }
 }
 
@@ -62,8 +286,12 @@ impl ConnectionInfo {
     password: String,
     database_name: String,
   ) -> Self {
-    let connection_string =
-      format!("postgresql://{username}:{password}@{host}:{port}/{database_name}");
+    // Trust mode instances have no password; omit it rather than emitting an empty one
+    let connection_string = if password.is_empty() {
+      format!("postgresql://{username}@{host}:{port}/{database_name}")
+    } else {
+      format!("postgresql://{username}:{password}@{host}:{port}/{database_name}")
+    };
 
     Self {
       host,
@@ -81,8 +309,32 @@ impl ConnectionInfo {
     config.insert("host".to_string(), self.host.clone());
     config.insert("port".to_string(), self.port.to_string());
     config.insert("user".to_string(), self.username.clone());
-    config.insert("password".to_string(), self.password.clone());
+    if !self.password.is_empty() {
+      config.insert("password".to_string(), self.password.clone());
+    }
     config.insert("database".to_string(), self.database_name.clone());
     config
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_structured_sql_result() {
+    let result = StructuredSqlResult::from(QueryResult {
+      columns: vec!["id".to_string(), "email".to_string()],
+      rows: vec![
+        vec![Some("1".to_string()), Some("a@example.com".to_string())],
+        vec![Some("2".to_string()), None],
+      ],
+      row_count: 2,
+    });
+    assert_eq!(result.columns, ["id", "email"]);
+    assert_eq!(result.row_count, 2);
+    assert_eq!(result.rows[0]["email"].as_deref(), Some("a@example.com"));
+    assert_eq!(result.rows[1]["id"].as_deref(), Some("2"));
+    assert_eq!(result.rows[1]["email"], None);
+  }
+}
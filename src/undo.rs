@@ -0,0 +1,127 @@
+//! Undo points taken before destructive operations when safe mode is enabled
+
+use crate::error::Result;
+use napi_derive::napi;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// An operation reverted by `undoLastDestructiveOp()`
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct UndoneOperation {
+  /// The reverted operation, e.g. "dropDatabase"
+  pub operation: String,
+  /// Database that was recreated from its dump, for database operations
+  pub database_name: Option<String>,
+  /// Data directory that was put back from its snapshot, for `createRewind()`
+  pub data_dir: Option<String>,
+}
+
+/// How an undo point restores the state before the operation
+pub(crate) enum UndoAction {
+  /// Recreate a database from a custom-format dump taken with `--create`
+  RestoreDatabase {
+    database: String,
+    dump_file: PathBuf,
+  },
+  /// Replace a data directory with a copy taken before the operation
+  RestoreDataDir {
+    data_dir: PathBuf,
+    snapshot: PathBuf,
+  },
+}
+
+/// State saved before a destructive operation
+pub(crate) struct UndoPoint {
+  pub operation: String,
+  pub action: UndoAction,
+}
+
+impl UndoPoint {
+  /// Description of the operation this undo point reverts
+  pub(crate) fn undone(&self) -> UndoneOperation {
+    let (database_name, data_dir) = match &self.action {
+      UndoAction::RestoreDatabase { database, .. } => (Some(database.clone()), None),
+      UndoAction::RestoreDataDir { data_dir, .. } => {
+        (None, Some(data_dir.to_string_lossy().to_string()))
+      }
+    };
+    UndoneOperation {
+      operation: self.operation.clone(),
+      database_name,
+      data_dir,
+    }
+  }
+
+  /// Remove the saved dump or snapshot
+  pub(crate) fn discard(&self) {
+    let _ = match &self.action {
+      UndoAction::RestoreDatabase { dump_file, .. } => fs::remove_file(dump_file),
+      UndoAction::RestoreDataDir { snapshot, .. } => fs::remove_dir_all(snapshot),
+    };
+  }
+}
+
+/// Copy the directory tree `from` to `to`, keeping directory permissions
+///
+/// The server refuses to start on a data directory with permissions looser than 0750,
+/// so the permissions of every directory are copied along with the files.
+pub(crate) fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+  fs::create_dir_all(to)?;
+  fs::set_permissions(to, fs::metadata(from)?.permissions())?;
+  for entry in fs::read_dir(from)? {
+    let entry = entry?;
+    let target = to.join(entry.file_name());
+    if entry.file_type()?.is_dir() {
+      copy_dir(&entry.path(), &target)?;
+    } else {
+      fs::copy(entry.path(), &target)?;
+    }
+  }
+  Ok(())
+}
+
+/// Replace `data_dir` with `snapshot`, consuming the snapshot
+pub(crate) fn restore_dir(snapshot: &Path, data_dir: &Path) -> Result<()> {
+  if data_dir.exists() {
+    fs::remove_dir_all(data_dir)?;
+  }
+  if fs::rename(snapshot, data_dir).is_err() {
+    // Renaming fails across file systems
+    copy_dir(snapshot, data_dir)?;
+    fs::remove_dir_all(snapshot)?;
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_snapshot_and_restore_dir() {
+    let root = std::env::temp_dir().join(format!("pg-embedded-undo-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let data_dir = root.join("data");
+    fs::create_dir_all(data_dir.join("base")).unwrap();
+    fs::write(data_dir.join("PG_VERSION"), "17\n").unwrap();
+    fs::write(data_dir.join("base").join("1"), "table").unwrap();
+
+    let snapshot = root.join("snapshot");
+    copy_dir(&data_dir, &snapshot).unwrap();
+    fs::write(data_dir.join("PG_VERSION"), "changed\n").unwrap();
+    fs::remove_file(data_dir.join("base").join("1")).unwrap();
+
+    restore_dir(&snapshot, &data_dir).unwrap();
+    assert_eq!(
+      fs::read_to_string(data_dir.join("PG_VERSION")).unwrap(),
+      "17\n"
+    );
+    assert_eq!(
+      fs::read_to_string(data_dir.join("base").join("1")).unwrap(),
+      "table"
+    );
+    assert!(!snapshot.exists());
+    fs::remove_dir_all(&root).unwrap();
+  }
+}
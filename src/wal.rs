@@ -0,0 +1,94 @@
+//! WAL retention helpers: segment listing and `wal_keep_size` values
+
+use crate::error::{PgEmbedError, Result};
+use napi_derive::napi;
+
+/// Query listing the files of the WAL directory, oldest segment first
+pub(crate) const LIST_WAL_FILES_SQL: &str =
+  "SELECT name, size, extract(epoch FROM modification) * 1000 FROM pg_ls_waldir() ORDER BY name";
+
+/// A file in the server's WAL directory (`pg_wal`)
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct WalFile {
+  /// File name, e.g. "000000010000000000000003"
+  pub name: String,
+  /// File size in bytes
+  pub size_bytes: i64,
+  /// Last modification time, in milliseconds since the Unix epoch
+  pub modified: f64,
+  /// Whether the file is a WAL segment, as opposed to a timeline history or backup label file
+  pub is_segment: bool,
+}
+
+/// Whether `name` is a WAL segment file name (24 hexadecimal digits)
+fn is_segment_name(name: &str) -> bool {
+  name.len() == 24 && name.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Parse a row of `LIST_WAL_FILES_SQL`
+pub(crate) fn parse_wal_file(row: &[String]) -> Option<WalFile> {
+  let [name, size, modified] = row else {
+    return None;
+  };
+  Some(WalFile {
+    is_segment: is_segment_name(name),
+    name: name.clone(),
+    size_bytes: size.parse().ok()?,
+    modified: modified.parse().ok()?,
+  })
+}
+
+/// Check a `wal_keep_size` value: a number of megabytes, optionally with a memory unit
+pub(crate) fn validate_wal_keep_size(size: &str) -> Result<()> {
+  let digits = size.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+  let unit = &size[digits.len()..];
+  if digits.is_empty()
+    || !digits.chars().all(|c| c.is_ascii_digit())
+    || !matches!(unit, "" | "kB" | "MB" | "GB" | "TB")
+  {
+    return Err(PgEmbedError::ConfigurationError(format!(
+      "Invalid WAL keep size '{size}', expected e.g. '512MB' or '1GB'"
+    )));
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_wal_file() {
+    let row = |name: &str| {
+      vec![
+        name.to_string(),
+        "16777216".to_string(),
+        "1700000000000".to_string(),
+      ]
+    };
+    assert_eq!(
+      parse_wal_file(&row("000000010000000000000003")),
+      Some(WalFile {
+        name: "000000010000000000000003".to_string(),
+        size_bytes: 16777216,
+        modified: 1700000000000.0,
+        is_segment: true,
+      })
+    );
+    assert!(!parse_wal_file(&row("00000002.history")).unwrap().is_segment);
+    assert_eq!(parse_wal_file(&["x".to_string()]), None);
+  }
+
+  #[test]
+  fn test_validate_wal_keep_size() {
+    assert!(validate_wal_keep_size("0").is_ok());
+    assert!(validate_wal_keep_size("512MB").is_ok());
+    assert!(validate_wal_keep_size("1GB").is_ok());
+    assert!(validate_wal_keep_size("").is_err());
+    assert!(validate_wal_keep_size("MB").is_err());
+    assert!(validate_wal_keep_size("1 GB").is_err());
+    assert!(validate_wal_keep_size("1gb").is_err());
+    assert!(validate_wal_keep_size("1GB; DROP").is_err());
+  }
+}